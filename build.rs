@@ -0,0 +1,37 @@
+use std::{
+    fs,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Compiles build information into the binary, read back by the `version` module.
+fn main() {
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+    println!("cargo:rerun-if-changed=migrations");
+    let commit = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map_or_else(|| "unknown".to_owned(), |commit| commit.trim().to_owned());
+    let built = std::env::var("SOURCE_DATE_EPOCH").unwrap_or_else(|_| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            .to_string()
+    });
+    let migration = fs::read_dir("migrations")
+        .unwrap()
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name().into_string().ok()?;
+            Some(name.split_once('_')?.0.to_owned())
+        })
+        .max()
+        .unwrap_or_default();
+    println!("cargo:rustc-env=ZAI_COMMIT={commit}");
+    println!("cargo:rustc-env=ZAI_BUILT={built}");
+    println!("cargo:rustc-env=ZAI_MIGRATION={migration}");
+}
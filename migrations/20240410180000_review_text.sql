@@ -0,0 +1 @@
+ALTER TABLE reviews ADD COLUMN body TEXT, ADD COLUMN spoiler BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,7 @@
+CREATE TABLE review_replies(
+    id SERIAL PRIMARY KEY,
+    review_id INTEGER NOT NULL REFERENCES reviews ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users ON DELETE CASCADE,
+    body TEXT NOT NULL,
+    date TIMESTAMP NOT NULL DEFAULT now()
+);
@@ -0,0 +1,6 @@
+CREATE TABLE item_similarities(
+    item_id INTEGER NOT NULL REFERENCES items ON DELETE CASCADE,
+    similar_item_id INTEGER NOT NULL REFERENCES items ON DELETE CASCADE,
+    similarity REAL NOT NULL,
+    PRIMARY KEY(item_id, similar_item_id)
+);
@@ -0,0 +1,2 @@
+ALTER TABLE reviews ADD COLUMN private BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE users ADD COLUMN private_ratings BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1 @@
+ALTER TABLE items ADD COLUMN created TIMESTAMP NOT NULL DEFAULT now();
@@ -0,0 +1,7 @@
+CREATE TABLE audit_log(
+    id SERIAL PRIMARY KEY,
+    actor_id INTEGER REFERENCES users ON DELETE SET NULL,
+    action VARCHAR NOT NULL,
+    target VARCHAR NOT NULL,
+    date TIMESTAMP NOT NULL DEFAULT now()
+);
@@ -0,0 +1,10 @@
+CREATE TABLE tags(
+    id SERIAL PRIMARY KEY,
+    name VARCHAR NOT NULL UNIQUE
+);
+
+CREATE TABLE item_tags(
+    item_id INTEGER NOT NULL REFERENCES items ON DELETE CASCADE,
+    tag_id INTEGER NOT NULL REFERENCES tags ON DELETE CASCADE,
+    PRIMARY KEY(item_id, tag_id)
+);
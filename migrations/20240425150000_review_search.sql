@@ -0,0 +1 @@
+CREATE INDEX reviews_body_search ON reviews USING GIN (to_tsvector('english', body)) WHERE body IS NOT NULL;
@@ -0,0 +1,16 @@
+CREATE TABLE item_subscriptions(
+    user_id INTEGER NOT NULL REFERENCES users ON DELETE CASCADE,
+    item_id INTEGER NOT NULL REFERENCES items ON DELETE CASCADE,
+    PRIMARY KEY(user_id, item_id)
+);
+
+CREATE TABLE notifications(
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users ON DELETE CASCADE,
+    item_id INTEGER NOT NULL REFERENCES items ON DELETE CASCADE,
+    author_id INTEGER NOT NULL REFERENCES users ON DELETE CASCADE,
+    date TIMESTAMP NOT NULL DEFAULT now(),
+    read BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX notifications_user ON notifications(user_id, date DESC);
@@ -0,0 +1,8 @@
+CREATE TABLE item_images(
+    id SERIAL PRIMARY KEY,
+    item_id INTEGER NOT NULL REFERENCES items ON DELETE CASCADE,
+    is_cover BOOLEAN NOT NULL DEFAULT FALSE,
+    date TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX item_images_cover ON item_images(item_id) WHERE is_cover;
@@ -0,0 +1,6 @@
+CREATE TABLE settings(
+    key VARCHAR PRIMARY KEY,
+    value TEXT NOT NULL
+);
+
+INSERT INTO settings(key, value) VALUES('admin_contact', ''), ('policies', '');
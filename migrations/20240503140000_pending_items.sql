@@ -0,0 +1,12 @@
+CREATE TABLE pending_items(
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users ON DELETE CASCADE,
+    locator VARCHAR NOT NULL,
+    title VARCHAR NOT NULL,
+    description VARCHAR NOT NULL,
+    status VARCHAR NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'approved', 'rejected')),
+    reason TEXT,
+    date TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE INDEX pending_items_user ON pending_items(user_id, date DESC);
@@ -0,0 +1,4 @@
+CREATE TABLE item_aliases(
+    locator VARCHAR PRIMARY KEY,
+    item_id INTEGER NOT NULL REFERENCES items ON DELETE CASCADE
+);
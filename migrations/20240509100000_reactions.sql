@@ -0,0 +1,17 @@
+CREATE TABLE review_reactions(
+    review_id INTEGER NOT NULL REFERENCES reviews ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users ON DELETE CASCADE,
+    reaction VARCHAR NOT NULL,
+    PRIMARY KEY(review_id, user_id, reaction)
+);
+
+CREATE TABLE reply_reactions(
+    reply_id INTEGER NOT NULL REFERENCES review_replies ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users ON DELETE CASCADE,
+    reaction VARCHAR NOT NULL,
+    PRIMARY KEY(reply_id, user_id, reaction)
+);
+
+CREATE FUNCTION count_reactions(reactions VARCHAR[], names TEXT[]) RETURNS BIGINT[] AS $$
+    SELECT ARRAY(SELECT COUNT(r.reaction) FROM UNNEST(names) WITH ORDINALITY AS n(name, position) LEFT JOIN UNNEST(reactions) AS r(reaction) ON r.reaction = n.name GROUP BY n.position ORDER BY n.position);
+$$ LANGUAGE SQL IMMUTABLE;
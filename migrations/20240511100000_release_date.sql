@@ -0,0 +1,5 @@
+ALTER TABLE items ADD COLUMN release_date DATE;
+
+DROP VIEW items_score;
+
+CREATE VIEW items_score AS SELECT i.*, c.slug AS category_slug, c.name AS category_name, COALESCE(AVG(r.rating)::REAL, 0) AS score, (SELECT COUNT(*) FROM reviews WHERE item_id=i.id) AS review_count, (DENSE_RANK() OVER (ORDER BY COALESCE(AVG(r.rating)::REAL, 0) DESC)) AS rank, (DENSE_RANK() OVER (ORDER BY (SELECT COUNT(*) FROM reviews WHERE item_id=i.id) DESC)) AS popularity, (DENSE_RANK() OVER (PARTITION BY i.category_id ORDER BY COALESCE(AVG(r.rating)::REAL, 0) DESC)) AS category_rank FROM items i LEFT JOIN categories c ON i.category_id=c.id LEFT JOIN reviews r ON i.id=r.item_id GROUP BY i.id, c.id ORDER BY score DESC;
@@ -0,0 +1,14 @@
+CREATE TABLE collections(
+    id SERIAL PRIMARY KEY,
+    slug VARCHAR NOT NULL UNIQUE,
+    name VARCHAR NOT NULL
+);
+
+CREATE TABLE collection_items(
+    collection_id INTEGER NOT NULL REFERENCES collections ON DELETE CASCADE,
+    item_id INTEGER NOT NULL REFERENCES items ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    PRIMARY KEY(collection_id, item_id)
+);
+
+CREATE INDEX collection_items_item ON collection_items(item_id);
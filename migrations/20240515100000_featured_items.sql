@@ -0,0 +1,4 @@
+CREATE TABLE featured_items(
+    item_id INTEGER PRIMARY KEY REFERENCES items ON DELETE CASCADE,
+    position INTEGER NOT NULL
+);
@@ -0,0 +1,11 @@
+CREATE TABLE comments(
+    id SERIAL PRIMARY KEY,
+    item_id INTEGER NOT NULL REFERENCES items ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users ON DELETE CASCADE,
+    parent_id INTEGER REFERENCES comments ON DELETE CASCADE,
+    body TEXT NOT NULL,
+    date TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE INDEX comments_item ON comments(item_id) WHERE parent_id IS NULL;
+CREATE INDEX comments_parent ON comments(parent_id);
@@ -0,0 +1,8 @@
+CREATE TABLE chart_snapshots(
+    month DATE NOT NULL,
+    item_id INTEGER NOT NULL REFERENCES items ON DELETE CASCADE,
+    rank BIGINT NOT NULL,
+    score REAL NOT NULL,
+    review_count BIGINT NOT NULL,
+    PRIMARY KEY(month, item_id)
+);
@@ -0,0 +1,16 @@
+CREATE TABLE user_lists(
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users ON DELETE CASCADE,
+    slug VARCHAR NOT NULL,
+    name VARCHAR NOT NULL,
+    UNIQUE(user_id, slug)
+);
+
+CREATE TABLE user_list_items(
+    list_id INTEGER NOT NULL REFERENCES user_lists ON DELETE CASCADE,
+    item_id INTEGER NOT NULL REFERENCES items ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    PRIMARY KEY(list_id, item_id)
+);
+
+CREATE INDEX user_list_items_item ON user_list_items(item_id);
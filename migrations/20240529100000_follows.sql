@@ -0,0 +1,11 @@
+CREATE TABLE follows(
+    follower_id INTEGER NOT NULL REFERENCES users ON DELETE CASCADE,
+    followed_id INTEGER NOT NULL REFERENCES users ON DELETE CASCADE,
+    date TIMESTAMP NOT NULL DEFAULT now(),
+    PRIMARY KEY(follower_id, followed_id),
+    CHECK(follower_id <> followed_id)
+);
+
+CREATE INDEX follows_followed ON follows(followed_id);
+
+ALTER TABLE user_list_items ADD COLUMN date TIMESTAMP NOT NULL DEFAULT now();
@@ -0,0 +1,21 @@
+ALTER TABLE users ADD COLUMN email VARCHAR;
+ALTER TABLE users ADD COLUMN email_verified BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE users ADD COLUMN email_token VARCHAR UNIQUE;
+
+CREATE UNIQUE INDEX users_email ON users(LOWER(email));
+
+ALTER TABLE notifications ADD COLUMN emailed BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE mail_jobs(
+    id SERIAL PRIMARY KEY,
+    recipient VARCHAR NOT NULL,
+    subject VARCHAR NOT NULL,
+    html TEXT NOT NULL,
+    text TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    run_at TIMESTAMP NOT NULL DEFAULT now(),
+    sent_at TIMESTAMP
+);
+
+CREATE INDEX mail_jobs_pending ON mail_jobs(run_at) WHERE sent_at IS NULL;
@@ -0,0 +1,3 @@
+ALTER TABLE users ADD COLUMN bio VARCHAR;
+ALTER TABLE users ADD COLUMN location VARCHAR;
+ALTER TABLE users ADD COLUMN website VARCHAR;
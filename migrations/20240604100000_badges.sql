@@ -0,0 +1,6 @@
+CREATE TABLE user_badges(
+    user_id INTEGER NOT NULL REFERENCES users ON DELETE CASCADE,
+    badge VARCHAR NOT NULL,
+    date TIMESTAMP NOT NULL DEFAULT now(),
+    PRIMARY KEY(user_id, badge)
+);
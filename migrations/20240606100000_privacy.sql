@@ -0,0 +1,3 @@
+ALTER TABLE users ADD COLUMN unlisted BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE users ADD COLUMN hidden_ratings BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE users ADD COLUMN login_required BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,3 @@
+ALTER TABLE users ALTER COLUMN avatar_hue DROP EXPRESSION;
+ALTER TABLE users ALTER COLUMN avatar_hue SET DEFAULT floor(random() * 360);
+ALTER TABLE users ADD COLUMN avatar_glyph VARCHAR NOT NULL DEFAULT 'user';
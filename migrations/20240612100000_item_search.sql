@@ -0,0 +1,31 @@
+ALTER TABLE items ADD COLUMN search TSVECTOR NOT NULL DEFAULT ''::TSVECTOR;
+
+-- Weighted document of an item: its title first, then the locators it was merged from, then its
+-- description.
+CREATE FUNCTION item_document(item INTEGER, title VARCHAR, description TEXT) RETURNS TSVECTOR AS $$
+    SELECT setweight(to_tsvector('english', title), 'A')
+        || setweight(to_tsvector('english', COALESCE((SELECT string_agg(replace(locator, '_', ' '), ' ') FROM item_aliases WHERE item_id = item), '')), 'B')
+        || setweight(to_tsvector('english', description), 'C');
+$$ LANGUAGE SQL STABLE;
+
+CREATE FUNCTION update_item_search() RETURNS TRIGGER AS $$
+    BEGIN
+        NEW.search := item_document(NEW.id, NEW.title, NEW.description);
+        RETURN NEW;
+    END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER item_search BEFORE INSERT OR UPDATE OF title, description ON items FOR EACH ROW EXECUTE FUNCTION update_item_search();
+
+CREATE FUNCTION update_alias_search() RETURNS TRIGGER AS $$
+    BEGIN
+        UPDATE items SET search = item_document(id, title, description) WHERE id = NEW.item_id OR id = OLD.item_id;
+        RETURN NULL;
+    END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER alias_search AFTER INSERT OR UPDATE OR DELETE ON item_aliases FOR EACH ROW EXECUTE FUNCTION update_alias_search();
+
+UPDATE items SET search = item_document(id, title, description);
+
+CREATE INDEX items_search ON items USING GIN (search);
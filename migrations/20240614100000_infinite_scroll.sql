@@ -0,0 +1 @@
+ALTER TABLE users ADD COLUMN infinite_scroll BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1 @@
+CREATE INDEX items_initial ON items (upper(left(title, 1)));
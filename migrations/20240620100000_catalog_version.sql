@@ -0,0 +1,15 @@
+-- Counter bumped by every statement changing what the catalog export contains, so that clients
+-- polling it can be told cheaply whether anything changed since their last download.
+CREATE SEQUENCE catalog_version;
+SELECT setval('catalog_version', 1);
+
+CREATE FUNCTION bump_catalog_version() RETURNS TRIGGER AS $$
+    BEGIN
+        PERFORM nextval('catalog_version');
+        RETURN NULL;
+    END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER items_catalog_version AFTER INSERT OR DELETE OR UPDATE OF locator, title, description, category_id ON items FOR EACH STATEMENT EXECUTE FUNCTION bump_catalog_version();
+CREATE TRIGGER reviews_catalog_version AFTER INSERT OR DELETE OR UPDATE OF item_id, rating ON reviews FOR EACH STATEMENT EXECUTE FUNCTION bump_catalog_version();
+CREATE TRIGGER categories_catalog_version AFTER UPDATE OF name ON categories FOR EACH STATEMENT EXECUTE FUNCTION bump_catalog_version();
@@ -0,0 +1,24 @@
+-- Key pair of the site actor, created on first start with ActivityPub enabled.
+CREATE TABLE activitypub_keys(
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    private_key TEXT NOT NULL
+);
+
+CREATE TABLE activitypub_followers(
+    id SERIAL PRIMARY KEY,
+    actor VARCHAR NOT NULL UNIQUE,
+    inbox VARCHAR NOT NULL,
+    created TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE TABLE activitypub_deliveries(
+    id SERIAL PRIMARY KEY,
+    inbox VARCHAR NOT NULL,
+    activity TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    run_at TIMESTAMP NOT NULL DEFAULT now(),
+    delivered_at TIMESTAMP
+);
+
+CREATE INDEX activitypub_deliveries_pending ON activitypub_deliveries(run_at) WHERE delivered_at IS NULL;
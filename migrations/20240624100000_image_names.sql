@@ -0,0 +1,16 @@
+-- Name of each stored image, a hash of its contents for new uploads. Images stored before keep
+-- the name they were stored under.
+ALTER TABLE users ADD COLUMN avatar VARCHAR;
+UPDATE users SET avatar = username WHERE has_avatar;
+ALTER TABLE users DROP COLUMN has_avatar;
+
+ALTER TABLE item_images ADD COLUMN file VARCHAR;
+UPDATE item_images SET file = id::TEXT;
+ALTER TABLE item_images ALTER COLUMN file SET NOT NULL;
+
+ALTER TABLE items ADD COLUMN cover VARCHAR;
+UPDATE items SET cover = locator;
+
+DROP VIEW items_score;
+
+CREATE VIEW items_score AS SELECT i.*, c.slug AS category_slug, c.name AS category_name, COALESCE(AVG(r.rating)::REAL, 0) AS score, (SELECT COUNT(*) FROM reviews WHERE item_id=i.id) AS review_count, (DENSE_RANK() OVER (ORDER BY COALESCE(AVG(r.rating)::REAL, 0) DESC)) AS rank, (DENSE_RANK() OVER (ORDER BY (SELECT COUNT(*) FROM reviews WHERE item_id=i.id) DESC)) AS popularity, (DENSE_RANK() OVER (PARTITION BY i.category_id ORDER BY COALESCE(AVG(r.rating)::REAL, 0) DESC)) AS category_rank, (SELECT COUNT(*) FROM favorites WHERE item_id=i.id) AS favorite_count FROM items i LEFT JOIN categories c ON i.category_id=c.id LEFT JOIN reviews r ON i.id=r.item_id GROUP BY i.id, c.id ORDER BY score DESC;
@@ -0,0 +1,11 @@
+CREATE TABLE jobs(
+    name VARCHAR PRIMARY KEY,
+    description VARCHAR NOT NULL,
+    interval_seconds INTEGER NOT NULL,
+    next_run_at TIMESTAMP NOT NULL DEFAULT now(),
+    started_at TIMESTAMP,
+    finished_at TIMESTAMP,
+    last_error TEXT,
+    runs INTEGER NOT NULL DEFAULT 0,
+    failures INTEGER NOT NULL DEFAULT 0
+);
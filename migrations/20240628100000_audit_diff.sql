@@ -0,0 +1,50 @@
+-- Name of the actor at the time, kept when their account is removed. Entries without one were
+-- made from the command line.
+ALTER TABLE audit_log ADD COLUMN actor_name VARCHAR;
+UPDATE audit_log a SET actor_name = u.username FROM users u WHERE u.id = a.actor_id;
+
+-- Fields the action changed, as [old, new] pairs keyed by field.
+ALTER TABLE audit_log ADD COLUMN diff JSONB NOT NULL DEFAULT '{}';
+
+CREATE INDEX audit_log_date ON audit_log(date DESC, id DESC);
+
+-- Fields of an item shown in the diffs of the audit log.
+CREATE FUNCTION item_snapshot(item INTEGER) RETURNS JSONB AS $$
+    SELECT jsonb_build_object(
+        'locator', i.locator,
+        'title', i.title,
+        'description', i.description,
+        'category', (SELECT name FROM categories WHERE id = i.category_id),
+        'release_date', i.release_date,
+        'unreleased', i.unreleased,
+        'cover', i.cover,
+        'gallery', ARRAY(SELECT file FROM item_images WHERE item_id = i.id ORDER BY id),
+        'tags', ARRAY(SELECT t.name FROM item_tags it JOIN tags t ON t.id = it.tag_id WHERE it.item_id = i.id ORDER BY t.name)
+    ) FROM items i WHERE i.id = item;
+$$ LANGUAGE SQL STABLE;
+
+-- Fields of a user shown in the diffs of the audit log, leaving out their password.
+CREATE FUNCTION user_snapshot(person INTEGER) RETURNS JSONB AS $$
+    SELECT jsonb_build_object(
+        'username', username,
+        'is_admin', is_admin,
+        'email', email,
+        'bio', bio,
+        'location', location,
+        'website', website,
+        'avatar', avatar,
+        'avatar_glyph', avatar_glyph,
+        'private_ratings', private_ratings,
+        'unlisted', unlisted,
+        'hidden_ratings', hidden_ratings,
+        'login_required', login_required
+    ) FROM users WHERE id = person;
+$$ LANGUAGE SQL STABLE;
+
+-- Fields that differ between two snapshots, as [old, new] pairs. A missing snapshot stands for
+-- a target that did not exist before the action or no longer does after it.
+CREATE FUNCTION snapshot_diff(old JSONB, new JSONB) RETURNS JSONB AS $$
+    SELECT COALESCE(jsonb_object_agg(key, jsonb_build_array(o.value, n.value)), '{}')
+    FROM jsonb_each(COALESCE(old, '{}')) o FULL JOIN jsonb_each(COALESCE(new, '{}')) n USING (key)
+    WHERE o.value IS DISTINCT FROM n.value;
+$$ LANGUAGE SQL IMMUTABLE;
@@ -0,0 +1,3 @@
+ALTER TABLE users ADD COLUMN email_token_created TIMESTAMP;
+
+UPDATE users SET email_token_created = now() WHERE email_token IS NOT NULL;
@@ -0,0 +1,6 @@
+ALTER TABLE users ADD COLUMN registered TIMESTAMP NOT NULL DEFAULT now();
+ALTER TABLE users ADD COLUMN first_verified TIMESTAMP;
+
+UPDATE users SET first_verified = now() WHERE email_verified;
+
+ALTER TABLE jobs ADD COLUMN last_result TEXT;
@@ -0,0 +1,7 @@
+ALTER TABLE users ADD COLUMN weekly_email BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE weekly_scores(
+    item_id INTEGER PRIMARY KEY REFERENCES items ON DELETE CASCADE,
+    score REAL NOT NULL,
+    review_count BIGINT NOT NULL
+);
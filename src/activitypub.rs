@@ -0,0 +1,760 @@
+//! Minimal ActivityPub presence of the site, letting Fediverse users follow the catalog as
+//! `@catalog@<host>` and see new items in their timelines.
+//!
+//! It is only served when the `ACTIVITYPUB` environment variable is `true`, and its links start
+//! with `PUBLIC_URL` like those in mail. The actor answers follow requests on its own and
+//! publishes a `Create` activity for every new item to the inboxes of its followers, delivered in
+//! the background and signed with the actor key as HTTP Signatures require.
+//!
+//! Activities posted to the inbox are only acted on when their HTTP Signature verifies with a key
+//! of their actor, covering the request target, host, date and body digest, and sent shortly
+//! before they are received. Every request to another server, from fetching actor documents and
+//! keys to delivering activities, goes through a [`pinned_client`](metadata::pinned_client), so
+//! that activities cannot make the server reach its own private network.
+
+use crate::{database::DatabaseError, jobs::Scheduler, mailer, metadata, routes};
+use axum::http::HeaderMap;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use maud::html;
+use regex::Regex;
+use reqwest::{header, Method, Url};
+use rsa::{
+    pkcs1v15::{Signature, SigningKey, VerifyingKey},
+    pkcs8::{DecodePrivateKey, DecodePublicKey, EncodePrivateKey, EncodePublicKey, LineEnding},
+    rand_core::OsRng,
+    signature::{SignatureEncoding, Signer, Verifier},
+    RsaPrivateKey, RsaPublicKey,
+};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use sqlx::{
+    query, query_as, query_scalar,
+    types::chrono::{NaiveDateTime, Utc},
+    PgPool,
+};
+use std::{
+    env,
+    sync::OnceLock,
+    time::{Duration, SystemTime},
+};
+use tokio::{sync::OnceCell, task::spawn_blocking};
+use tracing::warn;
+
+/// Name of the site actor in WebFinger accounts.
+pub const ACTOR_NAME: &str = "catalog";
+
+pub const CONTENT_TYPE: &str = "application/activity+json";
+pub const WEBFINGER_CONTENT_TYPE: &str = "application/jrd+json";
+
+const PUBLIC: &str = "https://www.w3.org/ns/activitystreams#Public";
+const KEY_BITS: usize = 2048;
+const DELIVERY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Activities delivered per delivery run.
+const BATCH_SIZE: i64 = 20;
+
+/// How long a claimed batch is kept from other delivery runs, longer than delivering it can take.
+const CLAIM_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+/// Failed deliveries of an activity before it is given up on.
+const MAX_ATTEMPTS: i32 = 8;
+
+/// Newest items listed in the outbox.
+const OUTBOX_SIZE: i64 = 20;
+
+/// Furthest the `Date` of a signed activity may be from the time it is received, so that captured
+/// requests cannot be replayed later.
+const DATE_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+/// Headers the signature of an activity posted to the inbox has to cover.
+const SIGNED_HEADERS: [&str; 4] = ["(request-target)", "host", "date", "digest"];
+
+struct NewItem {
+    locator: String,
+    title: String,
+    created: NaiveDateTime,
+}
+
+struct DeliveryJob {
+    id: i32,
+    inbox: String,
+    activity: String,
+}
+
+/// Whether the site takes part in the Fediverse.
+pub fn enabled() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| env::var("ACTIVITYPUB").is_ok_and(|value| value == "true"))
+}
+
+fn actor_id() -> String {
+    mailer::link(&routes::url::path(routes::ACTIVITYPUB_ACTOR))
+}
+
+fn followers_id() -> String {
+    mailer::link(&routes::url::path(routes::ACTIVITYPUB_FOLLOWERS))
+}
+
+fn inbox_id() -> String {
+    mailer::link(&routes::url::path(routes::ACTIVITYPUB_INBOX))
+}
+
+/// Account of the actor, such as `catalog@example.com`.
+fn account() -> String {
+    let base = Url::parse(&mailer::link("/")).ok();
+    let host = match base
+        .as_ref()
+        .and_then(|url| Some((url.host_str()?, url.port())))
+    {
+        Some((host, Some(port))) => format!("{host}:{port}"),
+        Some((host, None)) => host.to_owned(),
+        None => "localhost".to_owned(),
+    };
+    format!("{ACTOR_NAME}@{host}")
+}
+
+/// Key of the actor, created the first time it is needed.
+async fn private_key(pool: &PgPool) -> Result<&'static RsaPrivateKey, DatabaseError> {
+    static KEY: OnceCell<RsaPrivateKey> = OnceCell::const_new();
+    KEY.get_or_try_init(|| async {
+        let internal = |e: rsa::pkcs8::Error| DatabaseError::InternalError(Box::new(e));
+        if let Some(pem) = query_scalar!("SELECT private_key FROM activitypub_keys")
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
+        {
+            return RsaPrivateKey::from_pkcs8_pem(&pem).map_err(internal);
+        }
+        let key = spawn_blocking(|| RsaPrivateKey::new(&mut OsRng, KEY_BITS))
+            .await
+            .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
+            .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+        let pem = key.to_pkcs8_pem(LineEnding::LF).map_err(internal)?;
+        // Another instance may have stored its key first, which is the one kept.
+        let pem = query_scalar!("INSERT INTO activitypub_keys(private_key) VALUES ($1) ON CONFLICT (id) DO UPDATE SET id = activitypub_keys.id RETURNING private_key", pem.as_str())
+            .fetch_one(pool)
+            .await
+            .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+        RsaPrivateKey::from_pkcs8_pem(&pem).map_err(internal)
+    })
+    .await
+}
+
+/// WebFinger description of the actor, when the resource asked for is it.
+pub fn webfinger(resource: &str) -> Option<Value> {
+    let account = format!("acct:{}", account());
+    (resource == account || resource == actor_id()).then(|| {
+        json!({
+            "subject": account,
+            "aliases": [actor_id()],
+            "links": [{"rel": "self", "type": CONTENT_TYPE, "href": actor_id()}],
+        })
+    })
+}
+
+pub async fn actor(pool: &PgPool) -> Result<Value, DatabaseError> {
+    let public_key = private_key(pool)
+        .await?
+        .to_public_key()
+        .to_public_key_pem(LineEnding::LF)
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    Ok(json!({
+        "@context": ["https://www.w3.org/ns/activitystreams", "https://w3id.org/security/v1"],
+        "id": actor_id(),
+        "type": "Service",
+        "preferredUsername": ACTOR_NAME,
+        "name": "ZAI",
+        "summary": "New items of the catalog.",
+        "url": mailer::link(&routes::url::path(routes::ITEMS)),
+        "inbox": inbox_id(),
+        "outbox": mailer::link(&routes::url::path(routes::ACTIVITYPUB_OUTBOX)),
+        "followers": followers_id(),
+        "publicKey": {
+            "id": format!("{}#main-key", actor_id()),
+            "owner": actor_id(),
+            "publicKeyPem": public_key,
+        },
+    }))
+}
+
+/// `Create` activity announcing a new item.
+fn create(item: &NewItem) -> Value {
+    let url = mailer::link(&routes::url::item(&item.locator));
+    let published = item.created.and_utc().to_rfc3339();
+    let content = html! {
+        p { "New in the catalog: " a href=(url) {(item.title)} }
+    };
+    json!({
+        "@context": "https://www.w3.org/ns/activitystreams",
+        "id": format!("{url}#create"),
+        "type": "Create",
+        "actor": actor_id(),
+        "published": published,
+        "to": [PUBLIC],
+        "cc": [followers_id()],
+        "object": {
+            "id": url,
+            "type": "Note",
+            "attributedTo": actor_id(),
+            "content": content.into_string(),
+            "url": url,
+            "published": published,
+            "to": [PUBLIC],
+            "cc": [followers_id()],
+        },
+    })
+}
+
+/// Outbox of the actor with the newest items.
+pub async fn outbox(pool: &PgPool) -> Result<Value, DatabaseError> {
+    let total = query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM items"#)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    let items = query_as!(
+        NewItem,
+        "SELECT locator, title, created FROM items ORDER BY created DESC, id DESC LIMIT $1",
+        OUTBOX_SIZE
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    Ok(json!({
+        "@context": "https://www.w3.org/ns/activitystreams",
+        "id": mailer::link(&routes::url::path(routes::ACTIVITYPUB_OUTBOX)),
+        "type": "OrderedCollection",
+        "totalItems": total,
+        "orderedItems": items.iter().map(create).collect::<Vec<_>>(),
+    }))
+}
+
+/// Followers of the actor, only counted.
+pub async fn followers(pool: &PgPool) -> Result<Value, DatabaseError> {
+    let total = query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM activitypub_followers"#)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    Ok(json!({
+        "@context": "https://www.w3.org/ns/activitystreams",
+        "id": followers_id(),
+        "type": "OrderedCollection",
+        "totalItems": total,
+    }))
+}
+
+/// Queues the announcement of a new item for every follower inbox.
+pub async fn publish_item(pool: &PgPool, locator: &str) -> Result<(), DatabaseError> {
+    if !enabled() {
+        return Ok(());
+    }
+    let Some(item) = query_as!(
+        NewItem,
+        "SELECT locator, title, created FROM items WHERE locator = $1",
+        locator
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
+    else {
+        return Ok(());
+    };
+    query!("INSERT INTO activitypub_deliveries(inbox, activity) SELECT DISTINCT inbox, $1 FROM activitypub_followers", create(&item).to_string())
+        .execute(pool)
+        .await
+        .map(|_| ())
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// `host` of a request to the URL, with the port when it is not the default one.
+fn host(url: &Url) -> String {
+    match url.port() {
+        Some(port) => format!("{}:{port}", url.host_str().unwrap_or_default()),
+        None => url.host_str().unwrap_or_default().to_owned(),
+    }
+}
+
+/// `(request-target)` of a request to the URL, its lowercase method and path.
+fn request_target(method: &Method, url: &Url) -> String {
+    let method = method.as_str().to_lowercase();
+    match url.query() {
+        Some(query) => format!("{method} {}?{query}", url.path()),
+        None => format!("{method} {}", url.path()),
+    }
+}
+
+/// Text signed for a request, covering the headers named in [`signature_header`].
+fn signing_string(method: &Method, url: &Url, date: &str, digest: Option<&str>) -> String {
+    let mut lines = vec![
+        format!("(request-target): {}", request_target(method, url)),
+        format!("host: {}", host(url)),
+        format!("date: {date}"),
+    ];
+    if let Some(digest) = digest {
+        lines.push(format!("digest: {digest}"));
+    }
+    lines.join("\n")
+}
+
+/// `Signature` header of a request, signed with the actor key.
+fn signature_header(key: &RsaPrivateKey, signed: &str, with_digest: bool) -> String {
+    let signature = SigningKey::<Sha256>::new(key.clone()).sign(signed.as_bytes());
+    format!(
+        "keyId=\"{}#main-key\",algorithm=\"rsa-sha256\",headers=\"(request-target) host date{}\",signature=\"{}\"",
+        actor_id(),
+        if with_digest { " digest" } else { "" },
+        BASE64.encode(signature.to_bytes())
+    )
+}
+
+/// Sends a signed request to another server, with the activity as its body when there is one.
+async fn signed_request(
+    key: &RsaPrivateKey,
+    method: Method,
+    url: &str,
+    body: Option<&str>,
+) -> Result<reqwest::Response, String> {
+    let url = Url::parse(url).map_err(|e| e.to_string())?;
+    if !matches!(url.scheme(), "https" | "http") {
+        return Err(format!("Not an http link: {url}"));
+    }
+    let date = httpdate::fmt_http_date(SystemTime::now());
+    let digest = body.map(|body| format!("SHA-256={}", BASE64.encode(Sha256::digest(body))));
+    let signature = signature_header(
+        key,
+        &signing_string(&method, &url, &date, digest.as_deref()),
+        digest.is_some(),
+    );
+    let client = metadata::pinned_client(&url).await.map_err(|e| match e {
+        DatabaseError::IllegalCoverUrl => format!("Not a public http address: {url}"),
+        DatabaseError::CoverUnavailable => format!("Couldn't resolve {url}"),
+        e => e.to_string(),
+    })?;
+    let mut request = client
+        .request(method, url)
+        .header(header::ACCEPT, CONTENT_TYPE)
+        .header(header::DATE, date)
+        .header("Signature", signature);
+    if let (Some(body), Some(digest)) = (body, digest) {
+        request = request
+            .header(header::CONTENT_TYPE, CONTENT_TYPE)
+            .header("Digest", digest)
+            .body(body.to_owned());
+    }
+    let response = request.send().await.map_err(|e| e.to_string())?;
+    if response.status().is_success() {
+        Ok(response)
+    } else {
+        Err(format!("Responded with {}", response.status()))
+    }
+}
+
+/// Inbox of a remote actor, read from its actor document.
+async fn remote_inbox(key: &RsaPrivateKey, actor: &str) -> Result<String, String> {
+    let document: Value = signed_request(key, Method::GET, actor, None)
+        .await?
+        .json()
+        .await
+        .map_err(|e| e.to_string())?;
+    document["endpoints"]["sharedInbox"]
+        .as_str()
+        .or(document["inbox"].as_str())
+        .map(str::to_owned)
+        .ok_or_else(|| format!("No inbox in the actor document of {actor}"))
+}
+
+/// Parameters of a `Signature` header.
+#[derive(Debug, PartialEq)]
+struct SignatureParameters {
+    key_id: String,
+    /// Lowercase names of the signed headers, in the order they were signed in.
+    headers: Vec<String>,
+    signature: Vec<u8>,
+}
+
+fn parse_signature(value: &str) -> Option<SignatureParameters> {
+    static PARAMETERS: OnceLock<Regex> = OnceLock::new();
+    let parameters = PARAMETERS.get_or_init(|| Regex::new(r#"(\w+)="([^"]*)""#).unwrap());
+    let parameter = |name: &str| {
+        parameters
+            .captures_iter(value)
+            .find(|captures| &captures[1] == name)
+            .map(|captures| captures[2].to_owned())
+    };
+    // Only RSA keys are used in practice, which hs2019 leaves to the key to tell.
+    if parameter("algorithm")
+        .is_some_and(|algorithm| algorithm != "rsa-sha256" && algorithm != "hs2019")
+    {
+        return None;
+    }
+    Some(SignatureParameters {
+        key_id: parameter("keyId")?,
+        headers: parameter("headers")
+            .unwrap_or_else(|| "date".to_owned())
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .collect(),
+        signature: BASE64.decode(parameter("signature")?).ok()?,
+    })
+}
+
+/// Whether a `Digest` header holds the SHA-256 digest of the body.
+fn digest_matches(value: &str, body: &[u8]) -> bool {
+    let expected = BASE64.encode(Sha256::digest(body));
+    value
+        .split(',')
+        .filter_map(|digest| digest.trim().split_once('='))
+        .any(|(algorithm, digest)| algorithm.eq_ignore_ascii_case("SHA-256") && digest == expected)
+}
+
+/// Text the signature of an activity posted to the inbox was made over. The request target and host
+/// are those of the public address of the inbox, which the sender signed, rather than the ones a
+/// reverse proxy passed on.
+fn received_signing_string(
+    signature: &SignatureParameters,
+    headers: &HeaderMap,
+) -> Result<String, String> {
+    let inbox = Url::parse(&inbox_id()).map_err(|e| e.to_string())?;
+    signature
+        .headers
+        .iter()
+        .map(|name| match name.as_str() {
+            "(request-target)" => Ok(format!(
+                "(request-target): {}",
+                request_target(&Method::POST, &inbox)
+            )),
+            "host" => Ok(format!("host: {}", host(&inbox))),
+            name => headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| format!("{name}: {value}"))
+                .ok_or_else(|| format!("Signed header {name} is missing")),
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(|lines| lines.join("\n"))
+}
+
+/// Checks the signature against the public key it claims to be made with.
+fn check_signature(
+    public_key: RsaPublicKey,
+    signature: &SignatureParameters,
+    headers: &HeaderMap,
+) -> Result<(), String> {
+    let signed = received_signing_string(signature, headers)?;
+    let bytes = Signature::try_from(signature.signature.as_slice()).map_err(|e| e.to_string())?;
+    VerifyingKey::<Sha256>::new(public_key)
+        .verify(signed.as_bytes(), &bytes)
+        .map_err(|_| "Signature does not match".to_owned())
+}
+
+/// Public key of a remote actor and the actor owning it, read from the document at the address of
+/// the key. That is usually the actor document, holding the key under `publicKey`, or else the key
+/// itself.
+async fn remote_key(key: &RsaPrivateKey, key_id: &str) -> Result<(RsaPublicKey, String), String> {
+    let mut url = Url::parse(key_id).map_err(|e| e.to_string())?;
+    url.set_fragment(None);
+    let document: Value = signed_request(key, Method::GET, url.as_str(), None)
+        .await?
+        .json()
+        .await
+        .map_err(|e| e.to_string())?;
+    let public_key = match &document["publicKey"] {
+        Value::Array(keys) => keys.iter().find(|public_key| public_key["id"] == key_id),
+        Value::Null => Some(&document),
+        public_key => Some(public_key),
+    }
+    .filter(|public_key| public_key["id"] == key_id)
+    .ok_or_else(|| format!("No key {key_id} in its document"))?;
+    let pem = public_key["publicKeyPem"]
+        .as_str()
+        .ok_or_else(|| format!("Key {key_id} has no PEM"))?;
+    let owner = public_key["owner"]
+        .as_str()
+        .ok_or_else(|| format!("Key {key_id} has no owner"))?;
+    let public_key = RsaPublicKey::from_public_key_pem(pem).map_err(|e| e.to_string())?;
+    Ok((public_key, owner.to_owned()))
+}
+
+/// Whether two links point to the same server, so that one can speak for the other.
+fn same_origin(first: &str, second: &str) -> bool {
+    match (Url::parse(first), Url::parse(second)) {
+        (Ok(first), Ok(second)) => first.origin() == second.origin(),
+        _ => false,
+    }
+}
+
+/// Tells why an activity posted to the inbox cannot be trusted to come from its actor, if it
+/// cannot.
+async fn authenticate(
+    key: &RsaPrivateKey,
+    headers: &HeaderMap,
+    body: &[u8],
+    actor: &str,
+) -> Result<(), String> {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    let signature = header("signature")
+        .and_then(parse_signature)
+        .ok_or("Missing or malformed signature")?;
+    if let Some(name) = SIGNED_HEADERS
+        .iter()
+        .find(|name| !signature.headers.iter().any(|signed| signed == *name))
+    {
+        return Err(format!("Signature does not cover {name}"));
+    }
+    let date = header("date")
+        .and_then(|date| httpdate::parse_http_date(date).ok())
+        .ok_or("Missing or malformed date")?;
+    let now = SystemTime::now();
+    if now.duration_since(date).unwrap_or_else(|e| e.duration()) > DATE_WINDOW {
+        return Err("Date is too far from now".to_owned());
+    }
+    if !header("digest").is_some_and(|digest| digest_matches(digest, body)) {
+        return Err("Digest does not match the body".to_owned());
+    }
+    // A key from another server could name any actor as its owner.
+    if !same_origin(&signature.key_id, actor) {
+        return Err(format!(
+            "Key {} is not on the server of the actor",
+            signature.key_id
+        ));
+    }
+    let (public_key, owner) = remote_key(key, &signature.key_id).await?;
+    if owner != actor {
+        return Err(format!("Key {} belongs to {owner}", signature.key_id));
+    }
+    check_signature(public_key, &signature, headers)
+}
+
+/// Whether an activity posted to the inbox was signed by its actor, as [`receive`] requires.
+pub async fn verify(
+    pool: &PgPool,
+    headers: &HeaderMap,
+    body: &[u8],
+    activity: &Value,
+) -> Result<bool, DatabaseError> {
+    let Some(actor) = activity["actor"].as_str() else {
+        return Ok(false);
+    };
+    let key = private_key(pool).await?;
+    match authenticate(key, headers, body, actor).await {
+        Ok(()) => Ok(true),
+        Err(e) => {
+            warn!(actor, error = %e, "refused an unverified activity");
+            Ok(false)
+        }
+    }
+}
+
+/// Handles an activity posted to the inbox once it is [verified](verify), following back `Follow`
+/// requests and forgetting followers who undo them. Other activities are ignored.
+pub async fn receive(pool: &PgPool, activity: &Value) -> Result<(), DatabaseError> {
+    let Some(actor) = activity["actor"].as_str() else {
+        return Ok(());
+    };
+    match activity["type"].as_str() {
+        Some("Follow") if activity["object"].as_str() == Some(&actor_id()) => {
+            let key = private_key(pool).await?;
+            let inbox = match remote_inbox(key, actor).await {
+                Ok(inbox) => inbox,
+                Err(e) => {
+                    warn!(actor, error = %e, "following back failed");
+                    return Ok(());
+                }
+            };
+            let accept = json!({
+                "@context": "https://www.w3.org/ns/activitystreams",
+                "id": format!("{}#accepts/{}", actor_id(), Utc::now().timestamp_micros()),
+                "type": "Accept",
+                "actor": actor_id(),
+                "object": activity,
+            });
+            let mut transaction = pool
+                .begin()
+                .await
+                .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+            query!("INSERT INTO activitypub_followers(actor, inbox) VALUES ($1, $2) ON CONFLICT (actor) DO UPDATE SET inbox = EXCLUDED.inbox", actor, inbox)
+                .execute(&mut *transaction)
+                .await
+                .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+            query!(
+                "INSERT INTO activitypub_deliveries(inbox, activity) VALUES ($1, $2)",
+                inbox,
+                accept.to_string()
+            )
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+            transaction
+                .commit()
+                .await
+                .map_err(|e| DatabaseError::InternalError(Box::new(e)))
+        }
+        Some("Undo")
+            if activity["object"]["type"] == "Follow"
+                && activity["object"]["actor"].as_str() == Some(actor) =>
+        {
+            query!("DELETE FROM activitypub_followers WHERE actor = $1", actor)
+                .execute(pool)
+                .await
+                .map(|_| ())
+                .map_err(|e| DatabaseError::InternalError(Box::new(e)))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Sends a batch of due activities, retrying failed ones later with a growing delay.
+///
+/// The batch is claimed by moving its activities past the time a delivery may take, so that no
+/// rows stay locked and no connection stays taken while remote inboxes are being waited on.
+pub async fn deliver(pool: &PgPool) -> Result<(), DatabaseError> {
+    let key = private_key(pool).await?;
+    let jobs = query_as!(DeliveryJob, "UPDATE activitypub_deliveries SET run_at = now() + $3 * INTERVAL '1 second' WHERE id IN (SELECT id FROM activitypub_deliveries WHERE delivered_at IS NULL AND attempts < $1 AND run_at <= now() ORDER BY run_at LIMIT $2 FOR UPDATE SKIP LOCKED) RETURNING id, inbox, activity", MAX_ATTEMPTS, BATCH_SIZE, CLAIM_TIMEOUT.as_secs_f64())
+        .fetch_all(pool)
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    let mut results = Vec::with_capacity(jobs.len());
+    for job in jobs {
+        let result = signed_request(key, Method::POST, &job.inbox, Some(&job.activity)).await;
+        results.push((job.id, result.err()));
+    }
+    let mut transaction = pool
+        .begin()
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    for (id, error) in results {
+        match error {
+            None => query!("UPDATE activitypub_deliveries SET delivered_at = now() WHERE id = $1", id)
+                .execute(&mut *transaction)
+                .await,
+            Some(error) => query!("UPDATE activitypub_deliveries SET attempts = attempts + 1, last_error = $2, run_at = now() + (attempts + 1) * INTERVAL '10 minutes' WHERE id = $1", id, error)
+                .execute(&mut *transaction)
+                .await,
+        }
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    }
+    transaction
+        .commit()
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Periodically delivers queued activities in the background, when ActivityPub is enabled.
+pub fn schedule(scheduler: &mut Scheduler) {
+    if !enabled() {
+        return;
+    }
+    scheduler.add(
+        "activitypub",
+        "Send queued activities to followers",
+        DELIVERY_INTERVAL,
+        |pool| async move { deliver(&pool).await },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signs_request_target_host_date_and_digest() {
+        let url = Url::parse("https://example.com:8443/users/a/inbox?x=1").unwrap();
+        let date = "Sun, 16 Jun 2024 10:00:00 GMT";
+        assert_eq!(
+            signing_string(&Method::POST, &url, date, Some("SHA-256=abc")),
+            "(request-target): post /users/a/inbox?x=1\nhost: example.com:8443\ndate: Sun, 16 Jun 2024 10:00:00 GMT\ndigest: SHA-256=abc"
+        );
+        let url = Url::parse("https://example.com/users/a").unwrap();
+        assert_eq!(
+            signing_string(&Method::GET, &url, date, None),
+            "(request-target): get /users/a\nhost: example.com\ndate: Sun, 16 Jun 2024 10:00:00 GMT"
+        );
+    }
+
+    #[test]
+    fn verifies_signatures_made_over_the_inbox_request() {
+        let key = RsaPrivateKey::new(&mut OsRng, 1024).unwrap();
+        let inbox = Url::parse(&inbox_id()).unwrap();
+        let date = httpdate::fmt_http_date(SystemTime::now());
+        let digest = format!("SHA-256={}", BASE64.encode(Sha256::digest(b"{}")));
+        let signed = signing_string(&Method::POST, &inbox, &date, Some(&digest));
+        let mut headers = HeaderMap::new();
+        headers.insert("date", date.parse().unwrap());
+        headers.insert("digest", digest.parse().unwrap());
+        let signature = parse_signature(&signature_header(&key, &signed, true)).unwrap();
+        assert_eq!(signature.key_id, format!("{}#main-key", actor_id()));
+        assert_eq!(signature.headers, SIGNED_HEADERS);
+        assert!(check_signature(key.to_public_key(), &signature, &headers).is_ok());
+        let other = RsaPrivateKey::new(&mut OsRng, 1024).unwrap();
+        assert!(check_signature(other.to_public_key(), &signature, &headers).is_err());
+        headers.insert("date", "Sun, 16 Jun 2024 10:00:00 GMT".parse().unwrap());
+        assert!(check_signature(key.to_public_key(), &signature, &headers).is_err());
+    }
+
+    #[test]
+    fn parses_signature_headers_and_digests() {
+        let signature = parse_signature(
+            r#"keyId="https://example.com/actor#main-key",algorithm="hs2019",headers="(request-target) Host date",signature="AQID""#,
+        )
+        .unwrap();
+        assert_eq!(
+            signature,
+            SignatureParameters {
+                key_id: "https://example.com/actor#main-key".to_owned(),
+                headers: vec![
+                    "(request-target)".to_owned(),
+                    "host".to_owned(),
+                    "date".to_owned()
+                ],
+                signature: vec![1, 2, 3],
+            }
+        );
+        assert!(parse_signature(r#"keyId="a",algorithm="hmac-sha256",signature="AQID""#).is_none());
+        assert!(parse_signature(r#"algorithm="rsa-sha256",signature="AQID""#).is_none());
+        let digest = format!("SHA-256={}", BASE64.encode(Sha256::digest(b"{}")));
+        assert!(digest_matches(&digest, b"{}"));
+        assert!(digest_matches(&format!("SHA-512=abc, {digest}"), b"{}"));
+        assert!(!digest_matches(&digest, b"{\"type\":\"Undo\"}"));
+    }
+
+    #[test]
+    fn trusts_keys_only_from_the_server_of_the_actor() {
+        assert!(same_origin(
+            "https://example.com/users/a#main-key",
+            "https://example.com/users/a"
+        ));
+        assert!(!same_origin(
+            "https://evil.example/users/a#main-key",
+            "https://example.com/users/a"
+        ));
+        assert!(!same_origin(
+            "http://example.com/users/a#main-key",
+            "https://example.com/users/a"
+        ));
+    }
+
+    #[tokio::test]
+    async fn refuses_requests_to_private_addresses() {
+        let key = RsaPrivateKey::new(&mut OsRng, 1024).unwrap();
+        for url in [
+            "http://127.0.0.1/inbox",
+            "http://169.254.169.254/latest",
+            "http://[::1]/actor",
+        ] {
+            let error = signed_request(&key, Method::GET, url, None)
+                .await
+                .unwrap_err();
+            assert!(
+                error.starts_with("Not a public http address"),
+                "{url}: {error}"
+            );
+        }
+    }
+
+    #[test]
+    fn finds_only_the_site_actor() {
+        let found = webfinger(&format!("acct:{}", account())).unwrap();
+        assert_eq!(found["links"][0]["href"], actor_id());
+        assert!(webfinger(&actor_id()).is_some());
+        assert!(webfinger("acct:someone@example.com").is_none());
+    }
+}
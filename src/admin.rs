@@ -0,0 +1,241 @@
+use crate::database::{DatabaseError, ItemRow};
+use csv::Writer;
+use std::cmp::Ordering;
+
+/// Columns of the admin item table.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ItemColumn {
+    Title,
+    Score,
+    Reviews,
+    Created,
+    Status,
+}
+
+impl ItemColumn {
+    pub const ALL: [ItemColumn; 5] = [
+        ItemColumn::Title,
+        ItemColumn::Score,
+        ItemColumn::Reviews,
+        ItemColumn::Created,
+        ItemColumn::Status,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ItemColumn::Title => "title",
+            ItemColumn::Score => "score",
+            ItemColumn::Reviews => "reviews",
+            ItemColumn::Created => "created",
+            ItemColumn::Status => "status",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ItemColumn::Title => "Title",
+            ItemColumn::Score => "Score",
+            ItemColumn::Reviews => "Reviews",
+            ItemColumn::Created => "Created",
+            ItemColumn::Status => "Status",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|column| column.name() == name)
+    }
+
+    pub fn value(self, row: &ItemRow) -> String {
+        match self {
+            ItemColumn::Title => row.title.clone(),
+            ItemColumn::Score => format!("{:.2}", row.score),
+            ItemColumn::Reviews => row.review_count.to_string(),
+            ItemColumn::Created => row.created.format("%Y-%m-%d %H:%M").to_string(),
+            ItemColumn::Status => row.status.clone(),
+        }
+    }
+
+    fn compare(self, a: &ItemRow, b: &ItemRow) -> Ordering {
+        match self {
+            ItemColumn::Title => a.title.to_lowercase().cmp(&b.title.to_lowercase()),
+            ItemColumn::Score => a.score.total_cmp(&b.score),
+            ItemColumn::Reviews => a.review_count.cmp(&b.review_count),
+            ItemColumn::Created => a.created.cmp(&b.created),
+            ItemColumn::Status => a.status.cmp(&b.status),
+        }
+    }
+}
+
+/// Sorting and visible columns of the admin item table, kept in the query string so that the
+/// page and its CSV export agree.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ItemTable {
+    pub sort: ItemColumn,
+    pub descending: bool,
+    pub columns: Vec<ItemColumn>,
+}
+
+impl ItemTable {
+    /// Reads `sort`, `desc` and any number of `column` parameters, showing every column when
+    /// none is selected.
+    pub fn from_params(params: &[(String, String)]) -> Self {
+        let param = |key: &'static str| {
+            params
+                .iter()
+                .filter(move |(name, _)| name == key)
+                .map(|(_, value)| value.as_str())
+        };
+        let columns: Vec<ItemColumn> = ItemColumn::ALL
+            .into_iter()
+            .filter(|column| param("column").any(|name| name == column.name()))
+            .collect();
+        ItemTable {
+            sort: param("sort")
+                .find_map(ItemColumn::parse)
+                .unwrap_or(ItemColumn::Title),
+            descending: param("desc").any(|value| value == "true"),
+            columns: if columns.is_empty() {
+                ItemColumn::ALL.to_vec()
+            } else {
+                columns
+            },
+        }
+    }
+
+    pub fn query_string(&self) -> String {
+        let mut query = format!("sort={}", self.sort.name());
+        if self.descending {
+            query.push_str("&desc=true");
+        }
+        for column in &self.columns {
+            query.push_str("&column=");
+            query.push_str(column.name());
+        }
+        query
+    }
+
+    /// The same table sorted by `column`, reversing the order if it is already sorted by it.
+    pub fn sorted_by(&self, column: ItemColumn) -> Self {
+        ItemTable {
+            sort: column,
+            descending: self.sort == column && !self.descending,
+            columns: self.columns.clone(),
+        }
+    }
+
+    pub fn sort(&self, rows: &mut [ItemRow]) {
+        rows.sort_by(|a, b| {
+            let ordering = self.sort.compare(a, b);
+            if self.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+    }
+
+    /// Writes the visible columns of `rows` as CSV, with the item locator first.
+    pub fn to_csv(&self, rows: &[ItemRow]) -> Result<Vec<u8>, DatabaseError> {
+        let mut writer = Writer::from_writer(Vec::new());
+        let internal = |e| DatabaseError::InternalError(Box::new(e));
+        writer
+            .write_record(
+                std::iter::once("locator").chain(self.columns.iter().map(|column| column.name())),
+            )
+            .map_err(internal)?;
+        for row in rows {
+            writer
+                .write_record(
+                    std::iter::once(row.locator.clone())
+                        .chain(self.columns.iter().map(|column| column.value(row))),
+                )
+                .map_err(internal)?;
+        }
+        writer
+            .into_inner()
+            .map_err(|e| DatabaseError::InternalError(Box::new(e.into_error())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::types::chrono::NaiveDateTime;
+
+    fn params(query: &[(&str, &str)]) -> Vec<(String, String)> {
+        query
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn row(title: &str, score: f32, review_count: i64) -> ItemRow {
+        ItemRow {
+            locator: title.to_lowercase(),
+            title: title.to_owned(),
+            score,
+            review_count,
+            created: NaiveDateTime::default(),
+            status: if review_count == 0 {
+                "unrated"
+            } else {
+                "rated"
+            }
+            .to_owned(),
+        }
+    }
+
+    #[test]
+    fn shows_all_columns_by_default() {
+        let table = ItemTable::from_params(&[]);
+        assert_eq!(table.sort, ItemColumn::Title);
+        assert!(!table.descending);
+        assert_eq!(table.columns, ItemColumn::ALL);
+    }
+
+    #[test]
+    fn round_trips_through_query_string() {
+        let table = ItemTable::from_params(&params(&[
+            ("sort", "reviews"),
+            ("desc", "true"),
+            ("column", "status"),
+            ("column", "title"),
+            ("column", "bogus"),
+        ]));
+        assert_eq!(table.columns, [ItemColumn::Title, ItemColumn::Status]);
+        let query: Vec<(String, String)> = table
+            .query_string()
+            .split('&')
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap();
+                (key.to_owned(), value.to_owned())
+            })
+            .collect();
+        assert_eq!(ItemTable::from_params(&query), table);
+        assert_eq!(
+            table.sorted_by(ItemColumn::Reviews).descending,
+            !table.descending
+        );
+    }
+
+    #[test]
+    fn sorts_and_exports_visible_columns() {
+        let mut rows = vec![
+            row("FLCL", 7.5, 2),
+            row("bna", 0.0, 0),
+            row("Beastars", 9.0, 1),
+        ];
+        let table = ItemTable::from_params(&params(&[
+            ("sort", "score"),
+            ("desc", "true"),
+            ("column", "title"),
+            ("column", "reviews"),
+        ]));
+        table.sort(&mut rows);
+        let csv = String::from_utf8(table.to_csv(&rows).unwrap()).unwrap();
+        assert_eq!(
+            csv,
+            "locator,title,reviews\nbeastars,Beastars,1\nflcl,FLCL,2\nbna,bna,0\n"
+        );
+    }
+}
@@ -0,0 +1,274 @@
+//! Static assets. Stylesheets and scripts are compressed ahead of time next to the originals, so
+//! that [`ServeDir`](tower_http::services::ServeDir) can send the brotli or gzip variant a browser
+//! accepts without compressing on every request, and responses carry an `ETag` so that browsers
+//! revalidate them with a bodiless 304 once they expire.
+//!
+//! Assets are also fingerprinted with a hash of their content, which pages link them with, so that
+//! browsers keep them for good and still fetch them again as soon as they change. Third-party
+//! scripts and fonts are vendored under `vendor/` by `make vendor` and never fetched from their
+//! CDNs, so the server refuses to start while they are missing.
+
+use crate::{etag, images};
+use axum::{
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use brotli::CompressorWriter;
+use flate2::{write::GzEncoder, Compression};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+use tokio::task;
+
+/// Cache policy of static assets, which change under the same URL on deploys.
+pub const CACHE_CONTROL: &str = "public, max-age=3600";
+/// Length of the fingerprints assets are linked with.
+const FINGERPRINT_LENGTH: usize = 16;
+/// Extensions of the assets worth compressing, images are compressed already.
+const COMPRESSIBLE: [&str; 2] = ["css", "js"];
+/// Highest brotli quality and window, affordable as every asset is only compressed once.
+const BROTLI_QUALITY: u32 = 11;
+const BROTLI_WINDOW: u32 = 22;
+
+/// Writes the missing or outdated `.br` and `.gz` variants of the assets in the directory.
+pub async fn precompress(dir: PathBuf) -> io::Result<()> {
+    task::spawn_blocking(move || precompress_dir(&dir)).await?
+}
+
+fn precompress_dir(dir: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            precompress_dir(&path)?;
+        } else if path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| COMPRESSIBLE.contains(&extension))
+        {
+            write_variant(&path, "br", |source, file| {
+                let mut writer = CompressorWriter::new(file, 4096, BROTLI_QUALITY, BROTLI_WINDOW);
+                writer.write_all(source)?;
+                writer.flush()
+            })?;
+            write_variant(&path, "gz", |source, file| {
+                let mut encoder = GzEncoder::new(file, Compression::best());
+                encoder.write_all(source)?;
+                encoder.finish().map(drop)
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes a variant unless it is newer than the asset, through a temporary file so that a
+/// half-written variant is never served.
+fn write_variant(
+    path: &Path,
+    extension: &str,
+    compress: impl FnOnce(&[u8], &mut File) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut variant = path.as_os_str().to_owned();
+    variant.push(".");
+    variant.push(extension);
+    let variant = PathBuf::from(variant);
+    let modified = fs::metadata(path)?.modified()?;
+    if fs::metadata(&variant)
+        .and_then(|metadata| metadata.modified())
+        .is_ok_and(|compressed| compressed >= modified)
+    {
+        return Ok(());
+    }
+    let source = fs::read(path)?;
+    let temporary = variant.with_extension(format!("{extension}.tmp"));
+    compress(&source, &mut File::create(&temporary)?)?;
+    fs::rename(temporary, variant)
+}
+
+/// Fingerprints of the assets by their path within the static directory, computed at startup.
+static FINGERPRINTS: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+/// Fingerprints the assets in the directory, except for the ones under `skip`, like the uploaded
+/// images served from their own content-addressed names.
+pub async fn fingerprint(dir: PathBuf, skip: PathBuf) -> io::Result<()> {
+    let skip = fs::canonicalize(skip).ok();
+    let fingerprints =
+        task::spawn_blocking(move || fingerprints(&dir, &dir, skip.as_deref())).await??;
+    FINGERPRINTS.get_or_init(|| fingerprints);
+    Ok(())
+}
+
+fn fingerprints(
+    root: &Path,
+    dir: &Path,
+    skip: Option<&Path>,
+) -> io::Result<HashMap<String, String>> {
+    let mut fingerprints = HashMap::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if skip.is_none() || fs::canonicalize(&path).ok().as_deref() != skip {
+                fingerprints.extend(self::fingerprints(root, &path, skip)?);
+            }
+            continue;
+        }
+        // Compressed variants are served in place of their asset, under its fingerprint.
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "br" || extension == "gz")
+        {
+            continue;
+        }
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+        let mut fingerprint = format!("{:x}", Sha256::digest(fs::read(&path)?));
+        fingerprint.truncate(FINGERPRINT_LENGTH);
+        let relative = relative
+            .iter()
+            .map(|component| component.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        fingerprints.insert(relative, fingerprint);
+    }
+    Ok(fingerprints)
+}
+
+/// Fingerprint of the asset at the path within the static directory, if it exists.
+pub fn fingerprint_of(path: &str) -> Option<&'static str> {
+    FINGERPRINTS.get()?.get(path).map(String::as_str)
+}
+
+/// Vendored htmx, within the static directory.
+pub const HTMX: &str = "vendor/htmx.min.js";
+/// Vendored font of the pages, within the static directory.
+pub const FONT: &str = "vendor/quicksand-latin-500-normal.woff2";
+
+/// Vendored assets missing from the static directory.
+pub fn missing_vendored(dir: &Path) -> Vec<&'static str> {
+    [HTMX, FONT]
+        .into_iter()
+        .filter(|path| !dir.join(path).is_file())
+        .collect()
+}
+
+/// Entity tag of an asset response, naming the version of the file and the encoding it was sent
+/// in, as each encoding is a different representation.
+fn entity_tag(headers: &HeaderMap) -> Option<String> {
+    let modified = headers.get(header::LAST_MODIFIED)?;
+    let mut hasher = Sha256::new();
+    hasher.update(modified.as_bytes());
+    for name in [header::CONTENT_LENGTH, header::CONTENT_ENCODING] {
+        hasher.update(b"\0");
+        if let Some(value) = headers.get(name) {
+            hasher.update(value.as_bytes());
+        }
+    }
+    let mut tag = format!("{:x}", hasher.finalize());
+    tag.truncate(16);
+    Some(etag::weak(&tag))
+}
+
+/// Tags successful asset responses with an `ETag` and answers requests still holding it with a
+/// 304, keeping the caching headers. Responses vary with `Accept-Encoding`, which shared caches
+/// have to be told as they may be sent a precompressed variant. Assets requested under their
+/// current fingerprint are cached for good.
+pub async fn revalidate(request: Request, next: Next) -> Response {
+    let request_headers = request.headers().clone();
+    let fingerprinted =
+        fingerprint_of(request.uri().path().trim_start_matches('/')).is_some_and(|fingerprint| {
+            form_urlencoded::parse(request.uri().query().unwrap_or_default().as_bytes())
+                .any(|(key, value)| key == "v" && value == fingerprint)
+        });
+    let mut response = next.run(request).await;
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+    if fingerprinted {
+        response.headers_mut().insert(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static(images::CACHE_CONTROL),
+        );
+    }
+    let Some(tag) = entity_tag(response.headers()) else {
+        return response;
+    };
+    let Ok(value) = HeaderValue::from_str(&tag) else {
+        return response;
+    };
+    response.headers_mut().insert(header::ETAG, value);
+    response.headers_mut().insert(
+        header::VARY,
+        HeaderValue::from_static(header::ACCEPT_ENCODING.as_str()),
+    );
+    if !etag::matches(&request_headers, &tag) {
+        return response;
+    }
+    let mut headers = response.headers().clone();
+    headers.remove(header::CONTENT_LENGTH);
+    headers.remove(header::CONTENT_TYPE);
+    (StatusCode::NOT_MODIFIED, headers).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[tokio::test]
+    async fn precompresses_stylesheets_and_scripts() {
+        let dir = std::env::temp_dir().join(format!("zai-assets-{}", std::process::id()));
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        let css = "body { color: black; }\n".repeat(100);
+        fs::write(dir.join("nested/style.css"), &css).unwrap();
+        fs::write(dir.join("icon.png"), b"png").unwrap();
+        precompress(dir.clone()).await.unwrap();
+        let mut gunzipped = String::new();
+        flate2::read::GzDecoder::new(File::open(dir.join("nested/style.css.gz")).unwrap())
+            .read_to_string(&mut gunzipped)
+            .unwrap();
+        assert_eq!(gunzipped, css);
+        let mut unbrotlied = String::new();
+        brotli::Decompressor::new(File::open(dir.join("nested/style.css.br")).unwrap(), 4096)
+            .read_to_string(&mut unbrotlied)
+            .unwrap();
+        assert_eq!(unbrotlied, css);
+        assert!(!dir.join("icon.png.gz").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn fingerprints_assets_by_content() {
+        let dir = std::env::temp_dir().join(format!("zai-fingerprints-{}", std::process::id()));
+        fs::create_dir_all(dir.join("vendor")).unwrap();
+        fs::create_dir_all(dir.join("images")).unwrap();
+        fs::write(dir.join("vendor/htmx.min.js"), "htmx").unwrap();
+        fs::write(dir.join("vendor/htmx.min.js.gz"), "gzipped").unwrap();
+        fs::write(dir.join("copy.js"), "htmx").unwrap();
+        fs::write(dir.join("images/cover.webp"), "cover").unwrap();
+        let skip = fs::canonicalize(dir.join("images")).unwrap();
+        let fingerprints = fingerprints(&dir, &dir, Some(&skip)).unwrap();
+        let mut paths: Vec<_> = fingerprints.keys().map(String::as_str).collect();
+        paths.sort_unstable();
+        assert_eq!(paths, ["copy.js", "vendor/htmx.min.js"]);
+        assert_eq!(fingerprints["copy.js"], fingerprints["vendor/htmx.min.js"]);
+        assert_eq!(fingerprints["copy.js"].len(), FINGERPRINT_LENGTH);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn tells_which_vendored_assets_are_missing() {
+        let dir = std::env::temp_dir().join(format!("zai-vendored-{}", std::process::id()));
+        fs::create_dir_all(dir.join("vendor")).unwrap();
+        assert_eq!(missing_vendored(&dir), [HTMX, FONT]);
+        fs::write(dir.join(HTMX), "htmx").unwrap();
+        assert_eq!(missing_vendored(&dir), [FONT]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,168 @@
+//! Actions recorded in the audit log and how its entries read on the admin page. Each entry keeps
+//! the fields its action changed as `[old, new]` pairs, taken from snapshots of the target.
+
+use crate::routes::url;
+use serde_json::Value;
+
+/// Page a target of an action links to, while it is still there to link to.
+#[derive(Clone, Copy)]
+enum Link {
+    Item,
+    /// Merges are recorded as `merged/survivor`.
+    Survivor,
+    /// Posts are recorded as `item/author`.
+    PostItem,
+    User,
+    None,
+}
+
+pub struct Action {
+    pub name: &'static str,
+    pub label: &'static str,
+    link: Link,
+}
+
+const fn action(name: &'static str, label: &'static str, link: Link) -> Action {
+    Action { name, label, link }
+}
+
+pub const ACTIONS: [Action; 21] = [
+    action("add_item", "Added item", Link::Item),
+    action("edit_item", "Edited item", Link::Item),
+    action("remove_item", "Removed item", Link::None),
+    action("merge_item", "Merged item", Link::Survivor),
+    action("lock_item", "Locked item", Link::Item),
+    action("unlock_item", "Unlocked item", Link::Item),
+    action("feature_item", "Featured item", Link::Item),
+    action("unfeature_item", "Unfeatured item", Link::Item),
+    action("set_item_cover", "Changed cover", Link::Item),
+    action("remove_item_image", "Removed image", Link::Item),
+    action("approve_suggestion", "Approved suggestion", Link::Item),
+    action("reject_suggestion", "Rejected suggestion", Link::None),
+    action("remove_review", "Removed review", Link::PostItem),
+    action("remove_comment", "Removed comment", Link::PostItem),
+    action("remove_reply", "Removed reply", Link::PostItem),
+    action("add_category", "Added category", Link::None),
+    action("remove_category", "Removed category", Link::None),
+    action("edit_user", "Edited user", Link::User),
+    action("remove_user", "Removed user", Link::None),
+    action("make_admin", "Made admin", Link::User),
+    action("force_password_reset", "Forced password reset", Link::User),
+];
+
+fn find(name: &str) -> Option<&'static Action> {
+    ACTIONS.iter().find(|action| action.name == name)
+}
+
+/// Label of an action, or its name for actions this version does not know of.
+pub fn label(name: &str) -> &str {
+    find(name).map_or(name, |action| action.label)
+}
+
+/// Page of the target of an action, when it has one.
+pub fn link(name: &str, target: &str) -> Option<String> {
+    match find(name)?.link {
+        Link::Item => Some(url::item(target)),
+        Link::Survivor => target.split_once('/').map(|(_, into)| url::item(into)),
+        Link::PostItem => target.split_once('/').map(|(item, _)| url::item(item)),
+        Link::User => Some(url::user(target)),
+        Link::None => None,
+    }
+}
+
+/// Field changed by an action, with its values as text, missing where the field had none.
+pub struct Change {
+    pub field: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+fn show(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::Bool(true) => Some("yes".to_owned()),
+        Value::Bool(false) => Some("no".to_owned()),
+        Value::String(text) => Some(text.clone()),
+        Value::Array(values) if values.is_empty() => None,
+        Value::Array(values) => Some(
+            values
+                .iter()
+                .filter_map(show)
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+        value => Some(value.to_string()),
+    }
+}
+
+/// Changes recorded in the diff of an entry, by field name.
+pub fn changes(diff: &Value) -> Vec<Change> {
+    let Value::Object(fields) = diff else {
+        return Vec::new();
+    };
+    fields
+        .iter()
+        .filter_map(|(field, change)| match change.as_array()?.as_slice() {
+            [old, new] => Some(Change {
+                field: field.replace('_', " "),
+                old: show(old),
+                new: show(new),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn reads_changes_from_diffs() {
+        let diff = json!({
+            "title": ["Ergo Proxy", "Ergo Proxy (2006)"],
+            "tags": [["anime"], ["anime", "sci-fi"]],
+            "release_date": [null, "2006-02-25"],
+            "unreleased": [true, false],
+        });
+        let changes: Vec<_> = changes(&diff)
+            .into_iter()
+            .map(|change| (change.field, change.old, change.new))
+            .collect();
+        let text = |value: &str| Some(value.to_owned());
+        assert_eq!(
+            changes,
+            [
+                ("release date".to_owned(), None, text("2006-02-25")),
+                ("tags".to_owned(), text("anime"), text("anime, sci-fi")),
+                (
+                    "title".to_owned(),
+                    text("Ergo Proxy"),
+                    text("Ergo Proxy (2006)")
+                ),
+                ("unreleased".to_owned(), text("yes"), text("no")),
+            ]
+        );
+        assert!(super::changes(&json!({})).is_empty());
+    }
+
+    #[test]
+    fn links_targets_that_remain() {
+        assert_eq!(
+            link("edit_item", "ergo_proxy"),
+            Some(url::item("ergo_proxy"))
+        );
+        assert_eq!(
+            link("merge_item", "ergo/ergo_proxy"),
+            Some(url::item("ergo_proxy"))
+        );
+        assert_eq!(
+            link("remove_comment", "ergo_proxy/admin"),
+            Some(url::item("ergo_proxy"))
+        );
+        assert_eq!(link("make_admin", "admin"), Some(url::user("admin")));
+        assert_eq!(link("remove_item", "ergo_proxy"), None);
+        assert_eq!(label("unknown_action"), "unknown_action");
+    }
+}
@@ -0,0 +1,68 @@
+//! Badges awarded to users for milestones. They are checked periodically in the background
+//! rather than on every review, and are kept once awarded.
+
+use crate::{database::DatabaseError, jobs::Scheduler};
+use sqlx::{query, PgPool};
+use std::time::Duration;
+
+const AWARD_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Reviews needed for the `hundred_reviews` badge.
+const HUNDRED_REVIEWS: i64 = 100;
+/// Reviews with text needed for the `critic` badge.
+const CRITIC_REVIEWS: i64 = 10;
+/// Earliest registered users getting the `early_adopter` badge.
+const EARLY_ADOPTERS: i64 = 100;
+
+pub struct Badge {
+    /// Name as stored.
+    pub name: &'static str,
+    pub title: &'static str,
+    pub emoji: &'static str,
+}
+
+pub const BADGES: [Badge; 4] = [
+    Badge {
+        name: "early_adopter",
+        title: "Early adopter: one of the first 100 users",
+        emoji: "🌱",
+    },
+    Badge {
+        name: "first_review",
+        title: "First review: rated an item",
+        emoji: "✍️",
+    },
+    Badge {
+        name: "critic",
+        title: "Critic: wrote 10 reviews",
+        emoji: "🎓",
+    },
+    Badge {
+        name: "hundred_reviews",
+        title: "Centurion: rated 100 items",
+        emoji: "💯",
+    },
+];
+
+pub fn get(name: &str) -> Option<&'static Badge> {
+    BADGES.iter().find(|badge| badge.name == name)
+}
+
+/// Awards badges users have earned since the last check, returning how many were awarded.
+pub async fn award(pool: &PgPool) -> Result<u64, DatabaseError> {
+    query!("INSERT INTO user_badges(user_id, badge) SELECT user_id, 'first_review' FROM reviews GROUP BY user_id UNION ALL SELECT user_id, 'hundred_reviews' FROM reviews GROUP BY user_id HAVING COUNT(*) >= $1 UNION ALL SELECT user_id, 'critic' FROM reviews WHERE body IS NOT NULL GROUP BY user_id HAVING COUNT(*) >= $2 UNION ALL (SELECT id, 'early_adopter' FROM users ORDER BY id LIMIT $3) ON CONFLICT DO NOTHING", HUNDRED_REVIEWS, CRITIC_REVIEWS, EARLY_ADOPTERS)
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected())
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Periodically awards badges in the background.
+pub fn schedule(scheduler: &mut Scheduler) {
+    scheduler.add(
+        "badges",
+        "Award badges for milestones",
+        AWARD_INTERVAL,
+        |pool| async move { award(&pool).await },
+    );
+}
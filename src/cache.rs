@@ -0,0 +1,229 @@
+//! Short-lived cache of the hottest reads: the first pages of the item and user listings, which
+//! most anonymous visitors land on, item rows, and the users of sessions, loaded on every request
+//! of a logged in user. Entries expire after half a minute and every successful write request of a
+//! logged in user empties the cache, as do registrations, so pages only ever lag behind background
+//! jobs. Visitors' writes, such as logging in, searching or posting to the inbox, leave it be, so
+//! that they cannot keep it cold.
+
+use crate::{
+    database::{self, Category, DatabaseError, Item, Page, TagCount, User},
+    sessions::CurrentUser,
+};
+use axum::{extract::Request, http::Method, middleware::Next, response::Response, Extension};
+use moka::sync::Cache;
+use sqlx::PgPool;
+use std::{
+    future::Future,
+    hash::Hash,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// How long an entry is served before it is read from the database again.
+const TTL: Duration = Duration::from_secs(30);
+/// Entries kept in each cache, as listing keys include their query.
+const CAPACITY: u64 = 1000;
+
+/// An item listing page along with the categories, tags and featured items shown beside it.
+pub type ItemListing = (Option<Page<Item>>, Vec<Category>, Vec<TagCount>, Vec<Item>);
+
+pub struct QueryCache {
+    item_listings: Cache<String, ItemListing>,
+    user_listings: Cache<String, Option<Page<User>>>,
+    items: Cache<String, Item>,
+    users: Cache<i32, User>,
+    /// Bumped on every invalidation, so that reads started before a write do not cache what they
+    /// read.
+    generation: AtomicU64,
+}
+
+fn cache<K: Hash + Eq + Send + Sync + 'static, V: Clone + Send + Sync + 'static>() -> Cache<K, V> {
+    Cache::builder()
+        .max_capacity(CAPACITY)
+        .time_to_live(TTL)
+        .build()
+}
+
+impl Default for QueryCache {
+    fn default() -> Self {
+        QueryCache {
+            item_listings: cache(),
+            user_listings: cache(),
+            items: cache(),
+            users: cache(),
+            generation: AtomicU64::new(0),
+        }
+    }
+}
+
+impl QueryCache {
+    async fn get_or_fetch<K, V>(
+        &self,
+        cache: &Cache<K, V>,
+        key: K,
+        fetch: impl Future<Output = Result<V, DatabaseError>>,
+    ) -> Result<V, DatabaseError>
+    where
+        K: Hash + Eq + Send + Sync + 'static,
+        V: Clone + Send + Sync + 'static,
+    {
+        if let Some(value) = cache.get(&key) {
+            return Ok(value);
+        }
+        let generation = self.generation.load(Ordering::Acquire);
+        let value = fetch.await?;
+        if self.generation.load(Ordering::Acquire) == generation {
+            cache.insert(key, value.clone());
+        }
+        Ok(value)
+    }
+
+    /// First page of an item listing, keyed by the address of the page.
+    pub async fn item_listing(
+        &self,
+        key: String,
+        fetch: impl Future<Output = Result<ItemListing, DatabaseError>>,
+    ) -> Result<ItemListing, DatabaseError> {
+        self.get_or_fetch(&self.item_listings, key, fetch).await
+    }
+
+    /// First page of a user listing, keyed by the address of the page.
+    pub async fn user_listing(
+        &self,
+        key: String,
+        fetch: impl Future<Output = Result<Option<Page<User>>, DatabaseError>>,
+    ) -> Result<Option<Page<User>>, DatabaseError> {
+        self.get_or_fetch(&self.user_listings, key, fetch).await
+    }
+
+    /// An item by its locator. Missing items are not cached, as they may be an alias.
+    pub async fn item(&self, pool: &PgPool, locator: &str) -> Result<Option<Item>, DatabaseError> {
+        if let Some(item) = self.items.get(locator) {
+            return Ok(Some(item));
+        }
+        let generation = self.generation.load(Ordering::Acquire);
+        let item = database::get_item(pool, locator).await?;
+        if let Some(item) = &item {
+            if self.generation.load(Ordering::Acquire) == generation {
+                self.items.insert(locator.to_owned(), item.clone());
+            }
+        }
+        Ok(item)
+    }
+
+    /// A user by their id. Deleted users are not cached, so that their sessions end right away.
+    pub async fn user(&self, pool: &PgPool, id: i32) -> Result<Option<User>, DatabaseError> {
+        if let Some(user) = self.users.get(&id) {
+            return Ok(Some(user));
+        }
+        let generation = self.generation.load(Ordering::Acquire);
+        let user = database::get_user_by_id(pool, id).await?;
+        if let Some(user) = &user {
+            if self.generation.load(Ordering::Acquire) == generation {
+                self.users.insert(id, user.clone());
+            }
+        }
+        Ok(user)
+    }
+
+    pub fn invalidate(&self) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        self.item_listings.invalidate_all();
+        self.user_listings.invalidate_all();
+        self.items.invalidate_all();
+        self.users.invalidate_all();
+    }
+}
+
+/// Empties the cache after every successful request of a logged in user that may have written
+/// something. Visitors can only write through handlers that invalidate the cache themselves.
+pub async fn invalidate_on_write(
+    Extension(cache): Extension<Arc<QueryCache>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let is_write = !matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    ) && request
+        .extensions()
+        .get::<CurrentUser>()
+        .is_some_and(|user| user.0.is_some());
+    let response = next.run(request).await;
+    if is_write && !response.status().is_client_error() && !response.status().is_server_error() {
+        cache.invalidate();
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn serves_cached_listings_until_invalidated() {
+        let cache = QueryCache::default();
+        let fetch = |number_of_pages| async move {
+            Ok(Some(Page {
+                target: String::new(),
+                items: Vec::new(),
+                current_page: 0,
+                number_of_pages,
+                per_page: 20,
+                params: Vec::new(),
+                keyset: None,
+            }))
+        };
+        let pages = |page: Option<Page<User>>| page.unwrap().number_of_pages;
+        let first = cache.user_listing("/users".to_owned(), fetch(1)).await;
+        assert_eq!(pages(first.unwrap()), 1);
+        let cached = cache.user_listing("/users".to_owned(), fetch(2)).await;
+        assert_eq!(pages(cached.unwrap()), 1);
+        cache.invalidate();
+        let fresh = cache.user_listing("/users".to_owned(), fetch(3)).await;
+        assert_eq!(pages(fresh.unwrap()), 3);
+    }
+
+    #[tokio::test]
+    async fn does_not_cache_reads_overtaken_by_writes() {
+        let cache = QueryCache::default();
+        let stale = cache.user_listing("/users".to_owned(), async {
+            cache.invalidate();
+            Ok(None)
+        });
+        assert!(stale.await.unwrap().is_none());
+        assert!(cache.user_listings.get("/users").is_none());
+    }
+
+    #[tokio::test]
+    async fn only_writes_of_logged_in_users_empty_the_cache() {
+        use axum::{body::Body, middleware::from_fn, routing::post, Router};
+        use tower::ServiceExt;
+
+        let cache = Arc::new(QueryCache::default());
+        let write = |user: Option<User>| {
+            let app = Router::new()
+                .route("/", post(|| async {}))
+                .layer(from_fn(invalidate_on_write))
+                .layer(Extension(CurrentUser(user)))
+                .layer(Extension(cache.clone()));
+            let request = Request::post("/").body(Body::empty()).unwrap();
+            async move { app.oneshot(request).await.unwrap() }
+        };
+        let before = cache.generation.load(Ordering::Acquire);
+        write(None).await;
+        assert_eq!(cache.generation.load(Ordering::Acquire), before);
+        let user = User {
+            username: "user".to_owned(),
+            is_admin: false,
+            avatar_hue: 0,
+            avatar: None,
+            avatar_glyph: String::new(),
+        };
+        write(Some(user)).await;
+        assert_eq!(cache.generation.load(Ordering::Acquire), before + 1);
+    }
+}
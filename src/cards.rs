@@ -0,0 +1,196 @@
+//! Share cards: the image linked from the OpenGraph tags of item pages, drawn from the SVG
+//! components with the stored cover painted over the placeholder one. Rendered cards are kept on
+//! disk under a hash of everything they show, so they are only drawn again once the item changes.
+
+use crate::{
+    database::{self, DatabaseError, Item},
+    images,
+    storage::Storage,
+    svg,
+};
+use axum::body::Bytes;
+use image::{codecs::png::PngEncoder, imageops, imageops::FilterType, RgbaImage};
+use resvg::{
+    tiny_skia::{Pixmap, Transform},
+    usvg::{
+        fontdb::{Database, Family, Query},
+        Options, Tree,
+    },
+};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+};
+use tokio::{fs, task};
+use tracing::warn;
+
+/// Size of a card in pixels, as recommended for OpenGraph images.
+pub const WIDTH: u32 = 1200;
+pub const HEIGHT: u32 = 630;
+/// Width of the cover on the left of a card, keeping its 3:4 aspect ratio.
+const COVER_WIDTH: u32 = HEIGHT * 3 / 4;
+/// Directory cards are cached in, one subdirectory per item.
+const CACHE: &str = "cache/cards";
+/// Cache policy of cards, which change under the same URL along with the item.
+pub const CACHE_CONTROL: &str = "public, max-age=3600";
+
+/// Fonts installed on the system, loaded once for all cards.
+fn fonts() -> Arc<Database> {
+    static FONTS: OnceLock<Arc<Database>> = OnceLock::new();
+    FONTS
+        .get_or_init(|| {
+            let mut fonts = Database::new();
+            fonts.load_system_fonts();
+            // The default sans-serif family may not be installed, any sans-serif one will do.
+            let query = Query {
+                families: &[Family::SansSerif],
+                ..Query::default()
+            };
+            if fonts.query(&query).is_none() {
+                let family = fonts
+                    .faces()
+                    .flat_map(|face| &face.families)
+                    .map(|(family, _)| family)
+                    .find(|family| family.contains("Sans") && !family.contains("Mono"))
+                    .cloned();
+                if let Some(family) = family {
+                    fonts.set_sans_serif_family(family);
+                }
+            }
+            Arc::new(fonts)
+        })
+        .clone()
+}
+
+/// Path of the cached card of `item`, named after what it shows.
+fn path(item: &Item) -> PathBuf {
+    let mut hasher = Sha256::new();
+    for part in [
+        item.title.as_str(),
+        item.cover.as_deref().unwrap_or_default(),
+        &format!("{:.2}", item.score),
+        &item.review_count.to_string(),
+    ] {
+        hasher.update(part);
+        hasher.update([0]);
+    }
+    let mut name = format!("{:x}", hasher.finalize());
+    name.truncate(32);
+    Path::new(CACHE)
+        .join(&item.locator)
+        .join(format!("{name}.png"))
+}
+
+/// Draws `svg` scaled to `width` by `height` pixels. Its background has to be opaque, as
+/// premultiplied pixels are only the same as straight ones then.
+pub fn rasterize(svg: &str, width: u32, height: u32) -> Result<RgbaImage, DatabaseError> {
+    let options = Options {
+        fontdb: fonts(),
+        ..Options::default()
+    };
+    let tree =
+        Tree::from_str(svg, &options).map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    let mut pixmap = Pixmap::new(width, height).unwrap();
+    let size = tree.size();
+    let scale = Transform::from_scale(width as f32 / size.width(), height as f32 / size.height());
+    resvg::render(&tree, scale, &mut pixmap.as_mut());
+    Ok(RgbaImage::from_raw(width, height, pixmap.take()).unwrap())
+}
+
+/// Draws the card described by `svg` as a PNG, with `cover` painted over the placeholder.
+fn render(svg: &str, cover: Option<&[u8]>) -> Result<Bytes, DatabaseError> {
+    let mut card = rasterize(svg, WIDTH, HEIGHT)?;
+    match cover.map(image::load_from_memory) {
+        Some(Ok(cover)) => {
+            let cover = cover.resize_to_fill(COVER_WIDTH, HEIGHT, FilterType::Triangle);
+            imageops::overlay(&mut card, &cover.to_rgba8(), 0, 0);
+        }
+        Some(Err(e)) => warn!(error = %e, "decoding cover of share card failed"),
+        None => {}
+    }
+    let mut encoded = Vec::new();
+    image::DynamicImage::ImageRgba8(card)
+        .to_rgb8()
+        .write_with_encoder(PngEncoder::new(&mut encoded))
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    Ok(encoded.into())
+}
+
+/// Share card of `item`, read from the cache or drawn and cached when the item changed since.
+pub async fn card(
+    pool: &PgPool,
+    storage: &dyn Storage,
+    item: &Item,
+) -> Result<Bytes, DatabaseError> {
+    let path = path(item);
+    if let Ok(card) = fs::read(&path).await {
+        return Ok(card.into());
+    }
+    let cover = match &item.cover {
+        Some(name) => storage
+            .get(&images::cover_key(name))
+            .await
+            .map_err(|e| DatabaseError::InternalError(Box::new(e)))?,
+        None => None,
+    };
+    let hue = database::get_item_cover(pool, item.cover.as_deref().unwrap_or(&item.locator))
+        .await?
+        .map_or(0, |cover| cover.hue);
+    let svg = svg::share_card(
+        &item.title,
+        item.score,
+        item.review_count,
+        hue,
+        (WIDTH, HEIGHT),
+        COVER_WIDTH,
+    )
+    .into_string();
+    let card = task::spawn_blocking(move || render(&svg, cover.as_deref()))
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))??;
+    if let Err(e) = store(&path, &card).await {
+        warn!(locator = item.locator, error = %e, "caching share card failed");
+    }
+    Ok(card)
+}
+
+/// Writes a card to the cache in place of the outdated cards of the same item.
+async fn store(path: &Path, card: &[u8]) -> std::io::Result<()> {
+    let directory = path.parent().unwrap();
+    match fs::remove_dir_all(directory).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    fs::create_dir_all(directory).await?;
+    fs::write(path, card).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_cards_over_covers() {
+        let svg =
+            svg::share_card("Ergo Proxy", 8.5, 12, 200, (WIDTH, HEIGHT), COVER_WIDTH).into_string();
+        let mut cover = Vec::new();
+        RgbaImage::from_pixel(30, 40, image::Rgba([255, 0, 0, 255]))
+            .write_with_encoder(PngEncoder::new(&mut cover))
+            .unwrap();
+        let card = image::load_from_memory(&render(&svg, Some(&cover)).unwrap())
+            .unwrap()
+            .to_rgb8();
+        assert_eq!(card.dimensions(), (WIDTH, HEIGHT));
+        assert_eq!(card.get_pixel(COVER_WIDTH / 2, HEIGHT / 2).0, [255, 0, 0]);
+        assert_eq!(card.get_pixel(WIDTH - 1, 0).0, [0x18, 0x18, 0x1b]);
+        let placeholder = image::load_from_memory(&render(&svg, None).unwrap())
+            .unwrap()
+            .to_rgb8();
+        assert_ne!(
+            placeholder.get_pixel(COVER_WIDTH / 2, HEIGHT / 2).0,
+            [255, 0, 0]
+        );
+    }
+}
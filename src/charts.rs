@@ -0,0 +1,95 @@
+//! Monthly top charts, archived as snapshots of the item ranking. The snapshot of the current
+//! month is retaken through the month, so it settles on the ranking at its end.
+
+use crate::{database::DatabaseError, jobs::Scheduler};
+use sqlx::{query, query_as, query_scalar, types::chrono::NaiveDate, PgPool};
+use std::time::Duration;
+
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Items shown in a monthly chart.
+const CHART_SIZE: i64 = 50;
+
+pub struct ChartEntry {
+    pub rank: i64,
+    /// Rank in the previous month's chart, if the item was in it.
+    pub previous_rank: Option<i64>,
+    pub locator: String,
+    pub title: String,
+    pub score: f32,
+    pub review_count: i64,
+}
+
+/// Stores the current ranking of reviewed items as the chart of this month.
+pub async fn snapshot(pool: &PgPool) -> Result<(), DatabaseError> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    query!("DELETE FROM chart_snapshots WHERE month = date_trunc('month', CURRENT_DATE)")
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    query!(
+        "INSERT INTO chart_snapshots(month, item_id, rank, score, review_count)
+        SELECT date_trunc('month', CURRENT_DATE), id, rank, score, review_count FROM items_score WHERE review_count > 0"
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    transaction
+        .commit()
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Periodically snapshots the ranking in the background.
+pub fn schedule(scheduler: &mut Scheduler) {
+    scheduler.add(
+        "charts",
+        "Snapshot the ranking of the month",
+        SNAPSHOT_INTERVAL,
+        |pool| async move { snapshot(&pool).await },
+    );
+}
+
+/// First days of the months with a chart, latest first.
+pub async fn get_months(pool: &PgPool) -> Result<Vec<NaiveDate>, DatabaseError> {
+    query_scalar!("SELECT DISTINCT month FROM chart_snapshots ORDER BY month DESC")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Top items of the month starting on `month`.
+pub async fn get_chart(pool: &PgPool, month: NaiveDate) -> Result<Vec<ChartEntry>, DatabaseError> {
+    query_as!(
+        ChartEntry,
+        r#"SELECT s.rank, p.rank AS "previous_rank?", i.locator, i.title, s.score, s.review_count
+        FROM chart_snapshots s JOIN items i ON i.id = s.item_id
+        LEFT JOIN chart_snapshots p ON p.item_id = s.item_id AND p.month = (s.month - INTERVAL '1 month')::DATE
+        WHERE s.month = $1 ORDER BY s.rank, i.title LIMIT $2"#,
+        month,
+        CHART_SIZE
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Parses a month written as in chart urls, such as `2025-03`, into its first day.
+pub fn parse_month(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(&format!("{value}-01"), "%Y-%m-%d").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_months() {
+        assert_eq!(parse_month("2025-03"), NaiveDate::from_ymd_opt(2025, 3, 1));
+        assert_eq!(parse_month("2025-13"), None);
+        assert_eq!(parse_month("2025-03-15"), None);
+    }
+}
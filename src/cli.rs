@@ -0,0 +1,112 @@
+//! Command line of the binary, which serves the site unless given one of the administration
+//! commands below, so that an instance can be set up without access to the database.
+
+use crate::{
+    charts,
+    database::{self, DatabaseError},
+    forms, password, recommendations, seed,
+    storage::Storage,
+};
+use clap::{Parser, Subcommand};
+use passwords::PasswordGenerator;
+use sqlx::PgPool;
+
+/// Length of the password generated for an admin created without one.
+const GENERATED_PASSWORD_LENGTH: usize = 20;
+
+/// Site for rating items and reading the reviews of others.
+#[derive(Parser)]
+#[command(version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Serve the site, the default without a command
+    Serve,
+    /// Create an admin account, or make an existing user an admin
+    CreateAdmin {
+        name: String,
+        /// Password of a new account, generated and printed when not given
+        #[arg(long)]
+        password: Option<String>,
+    },
+    /// Apply the pending database migrations
+    Migrate,
+    /// Add fake users, items with covers, and reviews for development and demos
+    Seed {
+        #[arg(long, default_value_t = seed::DEFAULT_ITEMS)]
+        items: u32,
+        #[arg(long, default_value_t = seed::DEFAULT_USERS)]
+        users: u32,
+    },
+    /// Recompute the item similarities and the chart of this month from the current ratings
+    RecomputeScores,
+}
+
+/// Message of a failed command, with the cause of internal errors that the site itself would
+/// rather not show.
+pub fn describe(e: &DatabaseError) -> String {
+    match e {
+        DatabaseError::InternalError(e) => e.to_string(),
+        e => e.to_string(),
+    }
+}
+
+/// Runs an administration command, returning what to tell the operator, or nothing when the
+/// site is to be served. Migrations are applied before any command runs.
+pub async fn run(
+    pool: &PgPool,
+    storage: &dyn Storage,
+    command: Command,
+) -> Result<Option<String>, DatabaseError> {
+    let message = match command {
+        Command::Serve => return Ok(None),
+        Command::Migrate => "Applied the pending migrations".to_owned(),
+        Command::CreateAdmin { name, password } => create_admin(pool, &name, password).await?,
+        Command::Seed { items, users } => {
+            seed::seed(pool, storage, items, users).await?.to_string()
+        }
+        Command::RecomputeScores => {
+            recommendations::refresh(pool).await?;
+            charts::snapshot(pool).await?;
+            "Recomputed the item similarities and the chart of this month".to_owned()
+        }
+    };
+    Ok(Some(message))
+}
+
+async fn create_admin(
+    pool: &PgPool,
+    name: &str,
+    password: Option<String>,
+) -> Result<String, DatabaseError> {
+    if !forms::is_identifier(name) {
+        return Err(DatabaseError::IllegalUsername);
+    }
+    if database::make_admin(pool, name).await? {
+        return Ok(format!("Made {name} an admin"));
+    }
+    let (password, generated) = match password {
+        Some(password) => (password, false),
+        None => (
+            PasswordGenerator::new()
+                .length(GENERATED_PASSWORD_LENGTH)
+                .uppercase_letters(true)
+                .strict(true)
+                .generate_one()
+                .map_err(|e| DatabaseError::InternalError(e.into()))?,
+            true,
+        ),
+    };
+    password::policy().check(&password).await?;
+    database::register_user(pool, name, &password).await?;
+    database::make_admin(pool, name).await?;
+    Ok(if generated {
+        format!("Created admin {name} with password {password}")
+    } else {
+        format!("Created admin {name}")
+    })
+}
@@ -0,0 +1,654 @@
+//! Settings read once at startup from `zai.toml`, or the TOML file named by `CONFIG_FILE`, when
+//! there is one. Environment variables named after the settings in upper case, like `PORT` or
+//! `MAX_IMAGE_SIZE`, take precedence over the file, and every setting has a default, so the app
+//! runs without either. The settings are checked before the server starts, failing with the one
+//! that is wrong.
+
+use crate::routes;
+use serde::Deserialize;
+use std::{
+    env,
+    error::Error,
+    fmt::{self, Display},
+    fs, io,
+    net::{IpAddr, Ipv4Addr},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::OnceLock,
+};
+
+/// File settings are read from unless `CONFIG_FILE` names another one.
+const DEFAULT_FILE: &str = "zai.toml";
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Address the server listens on.
+    pub bind_address: IpAddr,
+    pub port: u16,
+    /// Directory served under `/static`.
+    pub static_dir: PathBuf,
+    /// Directory of the local image storage, served under `/static/images`.
+    pub image_dir: PathBuf,
+    /// Entries on a page of the item and user listings unless asked otherwise, also the
+    /// smallest page size that can be asked for.
+    pub per_page: i64,
+    /// Largest page size the item and user listings can be asked for.
+    pub max_per_page: i64,
+    /// Hours a session is kept without requests before its user is logged out.
+    pub session_lifetime_hours: i64,
+    /// Largest accepted image in bytes.
+    pub max_image_size: usize,
+    /// Largest accepted request in bytes, enough for a cover and a full gallery upload.
+    pub max_request_size: usize,
+    /// Path the app is served under behind a reverse proxy, like `/zai`, empty when served from
+    /// the root. Routes answer under it and generated links start with it.
+    pub base_path: String,
+    /// Addresses of reverse proxies whose `X-Forwarded-For`, `X-Forwarded-Proto` and
+    /// `X-Forwarded-Host` headers are believed. Set in the environment as a comma separated list.
+    pub trusted_proxies: Vec<IpAddr>,
+    /// PEM files of the certificate chain and of its private key, set together to serve HTTPS
+    /// instead of plain HTTP.
+    pub tls_cert: Option<PathBuf>,
+    pub tls_key: Option<PathBuf>,
+    /// Port of a plain HTTP listener redirecting to HTTPS, usually 80, when serving HTTPS.
+    pub http_redirect_port: Option<u16>,
+    /// Path of a Unix socket to listen on instead of `bind_address` and `port`, for a reverse
+    /// proxy on the same host.
+    pub unix_socket: Option<PathBuf>,
+    /// Permissions of the Unix socket. Connecting needs write permission, so the default lets the
+    /// owner and the group in, and the proxy should run in the group of the app.
+    pub unix_socket_mode: Mode,
+    /// `Content-Security-Policy` of every response, where `{nonce}` stands for the nonce the
+    /// page's scripts and styles are marked with. Empty to send none.
+    pub content_security_policy: String,
+    /// `Referrer-Policy` of every response, empty to send none.
+    pub referrer_policy: String,
+    /// Seconds browsers are told to only use HTTPS for the host, sent with responses served over
+    /// HTTPS, 0 to send no `Strict-Transport-Security`.
+    pub hsts_max_age: u64,
+    /// Milliseconds a database statement may take before it is logged as slow along with the
+    /// route it was run for, 0 to log none.
+    pub slow_query_ms: u64,
+    /// Requests a minute each client address may make to the `rate_limited_routes`, 0 for no
+    /// limit. Admins are not limited.
+    pub rate_limit_per_minute: u32,
+    /// Requests a client may make in a burst before being held to `rate_limit_per_minute`.
+    pub rate_limit_burst: u32,
+    /// Routes as registered, like `/items/:item/edit`, sharing the limit of each client. Set in the
+    /// environment as a comma separated list.
+    pub rate_limited_routes: Vec<String>,
+    /// Fewest characters a new password may have.
+    pub password_min_length: usize,
+    /// Lowest strength score from 0 to 100 a new password may have.
+    pub password_min_score: f64,
+    /// Have I Been Pwned SHA-1 hash list ordered by hash, one `HASH:COUNT` per line, that new
+    /// passwords must not be found in. Not checked when unset. The list is searched on disk rather
+    /// than read into memory, as full lists take tens of gigabytes.
+    pub password_breached_list: Option<PathBuf>,
+    /// Days sent mail and delivered webhooks and activities are kept for.
+    pub retention_days: u32,
+    /// Days after registration that accounts which never confirmed an email address are deleted
+    /// in. Such accounts are kept when 0, as giving an address is optional.
+    pub unverified_account_days: u32,
+}
+
+/// Policy allowing only the site's own resources, and scripts and style sheets carrying the nonce
+/// of the page. Style attributes are allowed as pages set backgrounds and colors in them.
+pub const DEFAULT_CONTENT_SECURITY_POLICY: &str = "default-src 'self'; \
+    script-src 'self' 'nonce-{nonce}'; \
+    style-src 'self' 'nonce-{nonce}'; \
+    style-src-elem 'self' 'nonce-{nonce}'; \
+    style-src-attr 'unsafe-inline'; \
+    font-src 'self'; \
+    object-src 'none'; \
+    base-uri 'self'; \
+    form-action 'self'; \
+    frame-ancestors 'none'";
+
+/// Values of `Referrer-Policy` browsers understand.
+const REFERRER_POLICIES: [&str; 8] = [
+    "no-referrer",
+    "no-referrer-when-downgrade",
+    "origin",
+    "origin-when-cross-origin",
+    "same-origin",
+    "strict-origin",
+    "strict-origin-when-cross-origin",
+    "unsafe-url",
+];
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            bind_address: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            port: 3000,
+            static_dir: PathBuf::from("static"),
+            image_dir: PathBuf::from("static/images"),
+            per_page: 12,
+            max_per_page: 60,
+            session_lifetime_hours: 6,
+            max_image_size: 5 * 1024 * 1024,
+            max_request_size: 24 * 1024 * 1024,
+            base_path: String::new(),
+            trusted_proxies: Vec::new(),
+            tls_cert: None,
+            tls_key: None,
+            http_redirect_port: None,
+            unix_socket: None,
+            unix_socket_mode: Mode(0o660),
+            content_security_policy: DEFAULT_CONTENT_SECURITY_POLICY.to_owned(),
+            referrer_policy: "strict-origin-when-cross-origin".to_owned(),
+            hsts_max_age: 365 * 24 * 60 * 60,
+            slow_query_ms: 100,
+            rate_limit_per_minute: 60,
+            rate_limit_burst: 30,
+            rate_limited_routes: [
+                routes::SEARCH,
+                routes::SEARCH_RESULTS,
+                routes::LOGIN,
+                routes::REGISTER,
+                routes::PASSWORD_RESET,
+                routes::ITEM_ADD,
+                routes::ITEM_EDIT,
+                routes::ITEMS_METADATA,
+                routes::USER_EDIT,
+                routes::USER_IMPORT,
+            ]
+            .map(str::to_owned)
+            .to_vec(),
+            password_min_length: 8,
+            password_min_score: 80.0,
+            password_breached_list: None,
+            retention_days: 30,
+            unverified_account_days: 0,
+        }
+    }
+}
+
+/// Permissions of a file, written in octal like `660` both in the file and in the environment.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(try_from = "String")]
+pub struct Mode(pub u32);
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        u32::from_str_radix(s.trim_start_matches("0o"), 8)
+            .ok()
+            .filter(|mode| *mode <= 0o777)
+            .map(Mode)
+            .ok_or_else(|| format!("{s} is not a mode in octal like 660"))
+    }
+}
+
+impl TryFrom<String> for Mode {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Variable(&'static str, String),
+    Invalid(&'static str, String),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read(path, e) => write!(f, "couldn't read {}: {e}", path.display()),
+            ConfigError::Parse(path, e) => write!(f, "couldn't parse {}: {e}", path.display()),
+            ConfigError::Variable(name, reason) => {
+                write!(f, "environment variable {name} is not valid: {reason}")
+            }
+            ConfigError::Invalid(name, reason) => write!(f, "setting {name} {reason}"),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+/// Replaces `value` with the environment variable `name` when it is set.
+fn override_with<T: FromStr>(name: &'static str, value: &mut T) -> Result<(), ConfigError>
+where
+    T::Err: Display,
+{
+    if let Ok(variable) = env::var(name) {
+        *value = variable
+            .parse()
+            .map_err(|e: T::Err| ConfigError::Variable(name, e.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Sets `value` to the environment variable `name` when it is set.
+fn override_option_with<T: FromStr>(
+    name: &'static str,
+    value: &mut Option<T>,
+) -> Result<(), ConfigError>
+where
+    T::Err: Display,
+{
+    if let Ok(variable) = env::var(name) {
+        *value = Some(
+            variable
+                .parse()
+                .map_err(|e: T::Err| ConfigError::Variable(name, e.to_string()))?,
+        );
+    }
+    Ok(())
+}
+
+/// Replaces `values` with the comma separated list in the environment variable `name` when it is
+/// set.
+fn override_list_with<T: FromStr>(
+    name: &'static str,
+    values: &mut Vec<T>,
+) -> Result<(), ConfigError>
+where
+    T::Err: Display,
+{
+    if let Ok(variable) = env::var(name) {
+        *values = variable
+            .split(',')
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|e: T::Err| ConfigError::Variable(name, format!("{value}: {e}")))
+            })
+            .collect::<Result<_, _>>()?;
+    }
+    Ok(())
+}
+
+impl Config {
+    /// Reads the settings file and the environment, then checks the result.
+    pub fn load() -> Result<Config, ConfigError> {
+        let mut config = match env::var("CONFIG_FILE") {
+            Ok(path) => Config::read(Path::new(&path))?,
+            Err(_) if Path::new(DEFAULT_FILE).exists() => Config::read(Path::new(DEFAULT_FILE))?,
+            Err(_) => Config::default(),
+        };
+        override_with("BIND_ADDRESS", &mut config.bind_address)?;
+        override_with("PORT", &mut config.port)?;
+        override_with("STATIC_DIR", &mut config.static_dir)?;
+        override_with("IMAGE_DIR", &mut config.image_dir)?;
+        override_with("PER_PAGE", &mut config.per_page)?;
+        override_with("MAX_PER_PAGE", &mut config.max_per_page)?;
+        override_with("SESSION_LIFETIME_HOURS", &mut config.session_lifetime_hours)?;
+        override_with("MAX_IMAGE_SIZE", &mut config.max_image_size)?;
+        override_with("MAX_REQUEST_SIZE", &mut config.max_request_size)?;
+        override_with("BASE_PATH", &mut config.base_path)?;
+        override_list_with("TRUSTED_PROXIES", &mut config.trusted_proxies)?;
+        override_option_with("TLS_CERT", &mut config.tls_cert)?;
+        override_option_with("TLS_KEY", &mut config.tls_key)?;
+        override_option_with("HTTP_REDIRECT_PORT", &mut config.http_redirect_port)?;
+        override_option_with("UNIX_SOCKET", &mut config.unix_socket)?;
+        override_with("UNIX_SOCKET_MODE", &mut config.unix_socket_mode)?;
+        override_with(
+            "CONTENT_SECURITY_POLICY",
+            &mut config.content_security_policy,
+        )?;
+        override_with("REFERRER_POLICY", &mut config.referrer_policy)?;
+        override_with("HSTS_MAX_AGE", &mut config.hsts_max_age)?;
+        override_with("SLOW_QUERY_MS", &mut config.slow_query_ms)?;
+        override_with("RATE_LIMIT_PER_MINUTE", &mut config.rate_limit_per_minute)?;
+        override_with("RATE_LIMIT_BURST", &mut config.rate_limit_burst)?;
+        override_list_with("RATE_LIMITED_ROUTES", &mut config.rate_limited_routes)?;
+        override_with("PASSWORD_MIN_LENGTH", &mut config.password_min_length)?;
+        override_with("PASSWORD_MIN_SCORE", &mut config.password_min_score)?;
+        override_option_with("PASSWORD_BREACHED_LIST", &mut config.password_breached_list)?;
+        override_with("RETENTION_DAYS", &mut config.retention_days)?;
+        override_with(
+            "UNVERIFIED_ACCOUNT_DAYS",
+            &mut config.unverified_account_days,
+        )?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn read(path: &Path) -> Result<Config, ConfigError> {
+        let text = fs::read_to_string(path).map_err(|e| ConfigError::Read(path.into(), e))?;
+        toml::from_str(&text).map_err(|e| ConfigError::Parse(path.into(), e))
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if !self.static_dir.is_dir() {
+            return Err(ConfigError::Invalid(
+                "static_dir",
+                format!(
+                    "must be an existing directory, {} is not",
+                    self.static_dir.display()
+                ),
+            ));
+        }
+        if self.per_page < 1 {
+            return Err(ConfigError::Invalid(
+                "per_page",
+                "must be at least 1".to_owned(),
+            ));
+        }
+        if self.max_per_page < self.per_page {
+            return Err(ConfigError::Invalid(
+                "max_per_page",
+                format!("must be at least per_page, which is {}", self.per_page),
+            ));
+        }
+        if self.session_lifetime_hours < 1 {
+            return Err(ConfigError::Invalid(
+                "session_lifetime_hours",
+                "must be at least 1".to_owned(),
+            ));
+        }
+        if self.max_image_size == 0 {
+            return Err(ConfigError::Invalid(
+                "max_image_size",
+                "must be at least 1".to_owned(),
+            ));
+        }
+        if self.max_request_size < self.max_image_size {
+            return Err(ConfigError::Invalid(
+                "max_request_size",
+                format!(
+                    "must be at least max_image_size, which is {}",
+                    self.max_image_size
+                ),
+            ));
+        }
+        if !self.base_path.is_empty()
+            && (!self.base_path.starts_with('/')
+                || self.base_path.ends_with('/')
+                || self.base_path.contains(['?', '#', ' ']))
+        {
+            return Err(ConfigError::Invalid(
+                "base_path",
+                format!(
+                    "must be empty or a path like /zai, without a trailing slash, {} is not",
+                    self.base_path
+                ),
+            ));
+        }
+        match (&self.tls_cert, &self.tls_key) {
+            (Some(_), None) => {
+                return Err(ConfigError::Invalid(
+                    "tls_key",
+                    "must be set along with tls_cert".to_owned(),
+                ))
+            }
+            (None, Some(_)) => {
+                return Err(ConfigError::Invalid(
+                    "tls_cert",
+                    "must be set along with tls_key".to_owned(),
+                ))
+            }
+            _ => {}
+        }
+        for (name, path) in [("tls_cert", &self.tls_cert), ("tls_key", &self.tls_key)] {
+            if let Some(path) = path.as_ref().filter(|path| !path.is_file()) {
+                return Err(ConfigError::Invalid(
+                    name,
+                    format!("must be an existing file, {} is not", path.display()),
+                ));
+            }
+        }
+        match self.http_redirect_port {
+            Some(_) if self.tls_cert.is_none() => {
+                return Err(ConfigError::Invalid(
+                    "http_redirect_port",
+                    "needs tls_cert and tls_key to redirect to HTTPS".to_owned(),
+                ))
+            }
+            Some(port) if port == self.port => {
+                return Err(ConfigError::Invalid(
+                    "http_redirect_port",
+                    format!("must differ from port, which is {}", self.port),
+                ))
+            }
+            _ => {}
+        }
+        if let Some(socket) = &self.unix_socket {
+            if cfg!(not(unix)) {
+                return Err(ConfigError::Invalid(
+                    "unix_socket",
+                    "is only supported on Unix".to_owned(),
+                ));
+            }
+            if self.tls_cert.is_some() {
+                return Err(ConfigError::Invalid(
+                    "unix_socket",
+                    "can't be used with tls_cert and tls_key, the proxy in front serves HTTPS"
+                        .to_owned(),
+                ));
+            }
+            let directory = match socket.parent() {
+                Some(directory) if directory.as_os_str().is_empty() => Path::new("."),
+                Some(directory) => directory,
+                None => socket,
+            };
+            if !directory.is_dir() {
+                return Err(ConfigError::Invalid(
+                    "unix_socket",
+                    format!(
+                        "must be in an existing directory, {} is not",
+                        directory.display()
+                    ),
+                ));
+            }
+        }
+        if !self
+            .content_security_policy
+            .chars()
+            .all(|c| c == ' ' || c.is_ascii_graphic())
+        {
+            return Err(ConfigError::Invalid(
+                "content_security_policy",
+                "must be a single line of ASCII".to_owned(),
+            ));
+        }
+        if !self.referrer_policy.is_empty()
+            && !REFERRER_POLICIES.contains(&self.referrer_policy.as_str())
+        {
+            return Err(ConfigError::Invalid(
+                "referrer_policy",
+                format!(
+                    "must be empty or one of {}, {} is not",
+                    REFERRER_POLICIES.join(", "),
+                    self.referrer_policy
+                ),
+            ));
+        }
+        if self.rate_limit_per_minute > 0 && self.rate_limit_burst == 0 {
+            return Err(ConfigError::Invalid(
+                "rate_limit_burst",
+                "must be at least 1 when rate_limit_per_minute is set".to_owned(),
+            ));
+        }
+        if let Some(route) = self
+            .rate_limited_routes
+            .iter()
+            .find(|route| !route.starts_with('/'))
+        {
+            return Err(ConfigError::Invalid(
+                "rate_limited_routes",
+                format!("must be routes starting with /, {route} is not"),
+            ));
+        }
+        if self.password_min_length < 1 {
+            return Err(ConfigError::Invalid(
+                "password_min_length",
+                "must be at least 1".to_owned(),
+            ));
+        }
+        if !(0.0..=100.0).contains(&self.password_min_score) {
+            return Err(ConfigError::Invalid(
+                "password_min_score",
+                format!("must be from 0 to 100, {} is not", self.password_min_score),
+            ));
+        }
+        if let Some(path) = self
+            .password_breached_list
+            .as_ref()
+            .filter(|path| !path.is_file())
+        {
+            return Err(ConfigError::Invalid(
+                "password_breached_list",
+                format!("must be an existing file, {} is not", path.display()),
+            ));
+        }
+        if self.retention_days < 1 {
+            return Err(ConfigError::Invalid(
+                "retention_days",
+                "must be at least 1".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Makes `config` the settings of the app, returned by [`get`] from then on.
+pub fn install(config: Config) -> &'static Config {
+    CONFIG.get_or_init(|| config)
+}
+
+/// Settings of the app, the defaults until others are [installed](install), as in tests.
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv6Addr;
+
+    #[test]
+    fn reads_settings_files() {
+        let config: Config = toml::from_str("port = 8080\nper_page = 10").unwrap();
+        assert_eq!(config.port, 8080);
+        assert_eq!(config.per_page, 10);
+        assert_eq!(config.max_per_page, Config::default().max_per_page);
+        assert!(toml::from_str::<Config>("prot = 8080").is_err());
+        let config: Config = toml::from_str("unix_socket_mode = \"0o600\"").unwrap();
+        assert_eq!(config.unix_socket_mode, Mode(0o600));
+        assert_eq!("660".parse(), Ok(Mode(0o660)));
+        assert!("680".parse::<Mode>().is_err());
+        assert!("1777".parse::<Mode>().is_err());
+        let config: Config = toml::from_str("trusted_proxies = [\"127.0.0.1\", \"::1\"]").unwrap();
+        assert_eq!(
+            config.trusted_proxies,
+            [
+                IpAddr::V4(Ipv4Addr::LOCALHOST),
+                IpAddr::V6(Ipv6Addr::LOCALHOST)
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_inconsistent_settings() {
+        assert!(Config::default().validate().is_ok());
+        let config = Config {
+            per_page: 24,
+            max_per_page: 12,
+            ..Config::default()
+        };
+        assert_eq!(
+            config.validate().unwrap_err().to_string(),
+            "setting max_per_page must be at least per_page, which is 24"
+        );
+        let config = Config {
+            max_request_size: 1,
+            ..Config::default()
+        };
+        assert!(config.validate().is_err());
+        for base_path in ["zai", "/zai/", "/"] {
+            let config = Config {
+                base_path: base_path.to_owned(),
+                ..Config::default()
+            };
+            assert!(config.validate().is_err(), "{base_path}");
+        }
+        let config = Config {
+            base_path: "/apps/zai".to_owned(),
+            ..Config::default()
+        };
+        assert!(config.validate().is_ok());
+        let config = Config {
+            tls_cert: Some(PathBuf::from("Cargo.toml")),
+            ..Config::default()
+        };
+        assert_eq!(
+            config.validate().unwrap_err().to_string(),
+            "setting tls_key must be set along with tls_cert"
+        );
+        let config = Config {
+            http_redirect_port: Some(80),
+            ..Config::default()
+        };
+        assert!(config.validate().is_err());
+        let config = Config {
+            tls_cert: Some(PathBuf::from("Cargo.toml")),
+            tls_key: Some(PathBuf::from("Cargo.toml")),
+            http_redirect_port: Some(80),
+            ..Config::default()
+        };
+        assert!(config.validate().is_ok());
+        let config = Config {
+            unix_socket: Some(PathBuf::from("zai.sock")),
+            ..Config::default()
+        };
+        assert!(config.validate().is_ok());
+        let config = Config {
+            unix_socket: Some(PathBuf::from("missing/zai.sock")),
+            ..Config::default()
+        };
+        assert_eq!(
+            config.validate().unwrap_err().to_string(),
+            "setting unix_socket must be in an existing directory, missing is not"
+        );
+        let config = Config {
+            referrer_policy: "never".to_owned(),
+            ..Config::default()
+        };
+        assert!(config.validate().is_err());
+        let config = Config {
+            content_security_policy: "default-src 'self';\nscript-src 'none'".to_owned(),
+            ..Config::default()
+        };
+        assert!(config.validate().is_err());
+        let config = Config {
+            rate_limited_routes: vec!["search".to_owned()],
+            ..Config::default()
+        };
+        assert_eq!(
+            config.validate().unwrap_err().to_string(),
+            "setting rate_limited_routes must be routes starting with /, search is not"
+        );
+        let config = Config {
+            password_min_score: 120.0,
+            ..Config::default()
+        };
+        assert_eq!(
+            config.validate().unwrap_err().to_string(),
+            "setting password_min_score must be from 0 to 100, 120 is not"
+        );
+        let config = Config {
+            password_breached_list: Some(PathBuf::from("missing.txt")),
+            ..Config::default()
+        };
+        assert!(config.validate().is_err());
+        let config = Config {
+            retention_days: 0,
+            ..Config::default()
+        };
+        assert!(config.validate().is_err());
+    }
+}
@@ -1,25 +1,69 @@
+use crate::{config, forms::FieldErrors, import::ImportRow, reactions, routes, search};
 use argon2::{
     password_hash::{rand_core::OsRng, SaltString},
     Argon2, PasswordHash, PasswordHasher, PasswordVerifier,
 };
-use passwords::{analyzer, scorer};
-use regex::Regex;
+use futures_util::{stream::BoxStream, StreamExt};
 use serde::{Deserialize, Serialize};
-use sqlx::{query, query_as, query_scalar, types::chrono::NaiveDateTime, Decode, PgPool};
-use std::{error::Error, fmt::Display, ops::Deref};
+use serde_json::{json, Value as JsonValue};
+use sqlx::{
+    error::BoxDynError,
+    postgres::{types::PgRecordDecoder, PgValueRef},
+    query, query_as, query_scalar,
+    types::chrono::{NaiveDate, NaiveDateTime},
+    Acquire, Decode, PgConnection, PgExecutor, PgPool, Postgres,
+};
+use std::{env, error::Error, fmt::Display, ops::{Deref, RangeInclusive}};
 
 #[derive(Debug)]
 pub enum DatabaseError {
-    InternalError(Box<dyn Error>),
+    InternalError(Box<dyn Error + Send + Sync>),
     IncorrectCredentials,
     EmptyFields,
     PasswordsDiffer,
     WeakPassword,
+    PasswordTooShort(usize),
+    BreachedPassword,
     DuplicateUser,
     DuplicateItem,
     IllegalUsername,
     NotValidImage,
-    IllegalLocator
+    ImageTooLarge,
+    IllegalLocator,
+    ReservedLocator,
+    MalformedForm,
+    InvalidFields(FieldErrors),
+    InvalidCsv,
+    RateLimited,
+    Busy,
+    IllegalTag,
+    IllegalCategory,
+    DuplicateCategory,
+    TooManyImages,
+    Unavailable,
+    PasswordResetRequired,
+    InvalidMerge,
+    InvalidDate,
+    UnknownMetadataSource,
+    MetadataUnavailable,
+    IllegalCollection,
+    DuplicateCollection,
+    NonexistentItem,
+    PossibleDuplicate,
+    Unreleased,
+    UnreleasedWithoutDate,
+    Locked,
+    IllegalList,
+    DuplicateList,
+    IllegalEmail,
+    DuplicateEmail,
+    TooLong(&'static str, usize),
+    IllegalWebsite,
+    IllegalAvatarStyle,
+    IllegalWebhookUrl,
+    NoWebhookEvents,
+    IllegalCoverUrl,
+    CoverUnavailable,
 }
 
 impl Display for DatabaseError {
@@ -31,15 +75,67 @@ impl Display for DatabaseError {
             DatabaseError::PasswordsDiffer => write!(f, "Passwords do not match!"),
             DatabaseError::DuplicateUser => write!(f, "User with this username already exists!"),
             DatabaseError::WeakPassword => write!(f, "Password is not strong enough!"),
+            DatabaseError::PasswordTooShort(length) => write!(f, "Password must be at least {length} characters long!"),
+            DatabaseError::BreachedPassword => write!(f, "Password appeared in a data breach, choose another one!"),
             DatabaseError::IllegalUsername => write!(
                 f,
                 "Only alphanumerical characters and underscores are allowed in usernames!"
             ),
             DatabaseError::DuplicateItem => write!(f, "Item with this locator already exists!"),
             DatabaseError::NotValidImage => write!(f, "Uploaded file is not a valid image"),
+            DatabaseError::ImageTooLarge => write!(f, "Upload images of at most {} MB and 5000 pixels wide and tall!", config::get().max_image_size as f64 / (1024 * 1024) as f64),
             DatabaseError::IllegalLocator => write!(f,
                 "Only alphanumerical characters and underscores are allowed in item locator!"
             ),
+            DatabaseError::ReservedLocator => write!(f, "This locator is taken by another page, choose another one!"),
+            DatabaseError::MalformedForm => write!(f, "Submitted form is malformed!"),
+            DatabaseError::InvalidFields(errors) => write!(f, "{errors}"),
+            DatabaseError::InvalidCsv => write!(f, "Uploaded file is not a valid ratings CSV!"),
+            DatabaseError::RateLimited => write!(f, "You are rating too often, try again later!"),
+            DatabaseError::Busy => write!(f, "Server is busy processing images, try again shortly!"),
+            DatabaseError::IllegalTag => write!(f, "Use at most 10 tags of letters, numbers and hyphens!"),
+            DatabaseError::IllegalCategory => write!(f, "Only letters, numbers, spaces and hyphens are allowed in category names!"),
+            DatabaseError::DuplicateCategory => write!(f, "Category with this name already exists!"),
+            DatabaseError::TooManyImages => write!(f, "Upload at most 8 gallery images at once!"),
+            DatabaseError::Unavailable => write!(f, "Service is temporarily unavailable, try again shortly!"),
+            DatabaseError::PasswordResetRequired => write!(f, "Your password was reset by an administrator, set a new one!"),
+            DatabaseError::InvalidMerge => write!(f, "Choose another existing item to merge into!"),
+            DatabaseError::InvalidDate => write!(f, "Enter a valid date!"),
+            DatabaseError::UnknownMetadataSource => write!(f, "Enter an OpenLibrary or TMDB link or ID!"),
+            DatabaseError::MetadataUnavailable => write!(f, "Couldn't fetch details of this item, try again later!"),
+            DatabaseError::IllegalCollection => write!(f, "Only letters, numbers, spaces and hyphens are allowed in collection names!"),
+            DatabaseError::DuplicateCollection => write!(f, "Collection with this name already exists!"),
+            DatabaseError::NonexistentItem => write!(f, "There is no item with this locator!"),
+            DatabaseError::PossibleDuplicate => write!(f, "Similar items already exist, make sure this is not one of them!"),
+            DatabaseError::Unreleased => write!(f, "This item can be rated once it is released!"),
+            DatabaseError::UnreleasedWithoutDate => write!(f, "Unreleased items need a release date in the future!"),
+            DatabaseError::Locked => write!(f, "Reviews of this item are locked!"),
+            DatabaseError::IllegalList => write!(f, "Only letters, numbers, spaces and hyphens are allowed in list names!"),
+            DatabaseError::DuplicateList => write!(f, "You already have a list with this name!"),
+            DatabaseError::IllegalEmail => write!(f, "Enter a valid email address!"),
+            DatabaseError::DuplicateEmail => write!(f, "This email address is used by another account!"),
+            DatabaseError::TooLong(field, length) => write!(f, "{field} must be at most {length} characters long!"),
+            DatabaseError::IllegalWebsite => write!(f, "Website must be an http or https link!"),
+            DatabaseError::IllegalAvatarStyle => write!(f, "Avatar color or glyph is not valid!"),
+            DatabaseError::IllegalWebhookUrl => write!(f, "Webhook URL must be an http or https link!"),
+            DatabaseError::NoWebhookEvents => write!(f, "Choose at least one event to send!"),
+            DatabaseError::IllegalCoverUrl => write!(f, "Cover URL must be an http or https link to a public address!"),
+            DatabaseError::CoverUnavailable => write!(f, "Couldn't download the cover, check the link or upload the image instead!"),
+        }
+    }
+}
+
+impl DatabaseError {
+    /// Whether the error comes from losing the connection to the database rather than from the
+    /// query itself, so that retrying may succeed.
+    pub fn is_transient(&self) -> bool {
+        let DatabaseError::InternalError(e) = self else {
+            return false;
+        };
+        match e.downcast_ref::<sqlx::Error>() {
+            Some(sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed) => true,
+            Some(sqlx::Error::Database(e)) => e.code().is_some_and(|code| code.starts_with("08") || code == "57P01" || code == "57P03"),
+            _ => false,
         }
     }
 }
@@ -58,11 +154,8 @@ pub async fn login_user(
     username: &str,
     password: &str,
 ) -> Result<User, DatabaseError> {
-    if username.trim().is_empty() || password.trim().is_empty() {
-        return Err(DatabaseError::EmptyFields);
-    }
     let result = query!(
-        "SELECT password_hash, is_admin, avatar_hue, has_avatar FROM users WHERE username=$1 LIMIT 1",
+        "SELECT password_hash, is_admin, avatar_hue, avatar, avatar_glyph, password_reset FROM users WHERE username=$1 LIMIT 1",
         username
     )
     .fetch_one(pool)
@@ -85,34 +178,25 @@ pub async fn login_user(
                 DatabaseError::InternalError(Box::new(e))
             }
         })?;
+    if result.password_reset {
+        return Err(DatabaseError::PasswordResetRequired);
+    }
     Ok(User {
         username: username.to_owned(),
         is_admin: result.is_admin,
         avatar_hue: result.avatar_hue,
-        has_avatar: result.has_avatar
+        avatar: result.avatar,
+        avatar_glyph: result.avatar_glyph
     })
 }
 
 pub async fn register_user(
     pool: &PgPool,
     username: &str,
-    password1: &str,
-    password2: &str,
+    password: &str,
 ) -> Result<User, DatabaseError> {
-    if username.trim().is_empty() || password1.trim().is_empty() || password2.trim().is_empty() {
-        return Err(DatabaseError::EmptyFields);
-    }
-    if !Regex::new(r"^\w+$").unwrap().is_match(username) {
-        return Err(DatabaseError::IllegalUsername);
-    }
-    if password1 != password2 {
-        return Err(DatabaseError::PasswordsDiffer);
-    }
-    if scorer::score(&analyzer::analyze(password1)) < 80.0 {
-        return Err(DatabaseError::WeakPassword);
-    }
     let password_hash = Argon2::default()
-        .hash_password(password1.as_bytes(), &SaltString::generate(&mut OsRng))
+        .hash_password(password.as_bytes(), &SaltString::generate(&mut OsRng))
         .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
         .to_string();
     query!(
@@ -133,18 +217,141 @@ pub async fn register_user(
             DatabaseError::InternalError(Box::new(e))
         }
     })?;
-    login_user(pool, username, password1).await
+    login_user(pool, username, password).await
+}
+
+/// Similarity above which fuzzy title and username search matches, read from the
+/// `SEARCH_SIMILARITY` environment variable. Postgres' default of 0.3 applies when it is unset
+/// or not between 0 and 1.
+pub fn similarity_threshold() -> Option<f32> {
+    env::var("SEARCH_SIMILARITY").ok().and_then(|threshold| threshold.parse().ok()).filter(|threshold| (0.0..=1.0).contains(threshold))
+}
+
+/// Prepares a new pool connection, applying the configured similarity threshold to the `%`
+/// operator.
+pub async fn configure_connection(connection: &mut PgConnection) -> Result<(), sqlx::Error> {
+    if let Some(threshold) = similarity_threshold() {
+        query_scalar!("SELECT set_config('pg_trgm.similarity_threshold', $1, false)", threshold.to_string()).fetch_one(connection).await?;
+    }
+    Ok(())
 }
 
+#[derive(Clone)]
 pub struct Page<T> {
     pub target: String,
     pub items: Vec<T>,
     pub current_page: i32,
     pub number_of_pages: i32,
-    pub query: Option<String>,
+    /// Entries on a full page.
+    pub per_page: i64,
+    /// Query parameters kept when moving between pages.
+    pub params: Vec<(&'static str, String)>,
+    /// Set when the listing is paged by cursor, being too long to page through by number.
+    pub keyset: Option<Keyset>,
+}
+
+/// Position of a page in a listing paged by cursor.
+#[derive(Clone)]
+pub struct Keyset {
+    /// Cursor the page was fetched after, absent on the first page.
+    pub after: Option<String>,
+    /// Cursor of the following page, absent on the last page.
+    pub next: Option<String>,
+}
+
+/// Entries on a page of the item and user listings unless asked otherwise.
+pub fn default_per_page() -> i64 {
+    config::get().per_page
+}
+
+/// Page sizes the item and user listings can be asked for.
+pub fn per_page_bounds() -> RangeInclusive<i64> {
+    config::get().per_page..=config::get().max_per_page
+}
+
+/// Pages a listing may have before it is paged by cursor instead of by number, as deep offsets
+/// make the database walk every skipped row. Later pages are not served by number, searches
+/// ranked by relevance only list their first pages.
+pub const MAX_NUMBERED_PAGES: i32 = 50;
+
+/// Page size from a query parameter, kept within bounds.
+pub fn per_page(value: Option<&str>) -> i64 {
+    let bounds = per_page_bounds();
+    value.and_then(|value| value.parse::<i64>().ok()).map_or(default_per_page(), |count| count.clamp(*bounds.start(), *bounds.end()))
+}
+
+impl<T> Page<T> {
+    /// Whether the listing lets users pick its page size.
+    pub fn is_resizable(&self) -> bool {
+        per_page_bounds().contains(&self.per_page)
+    }
+
+    fn link(&self, params: impl Iterator<Item = (&'static str, String)>) -> String {
+        let query = form_urlencoded::Serializer::new(String::new()).extend_pairs(params).finish();
+        if query.is_empty() {
+            self.target.clone()
+        } else {
+            format!("{}?{query}", self.target)
+        }
+    }
+
+    /// Url of the first page of the same listing with another page size.
+    pub fn resized_url(&self, per_page: i64) -> String {
+        let size = (per_page != default_per_page()).then(|| ("per_page", per_page.to_string()));
+        self.link(self.params.iter().filter(|(name, _)| *name != "per_page").cloned().chain(size))
+    }
+
+    /// Url of another page of the same listing, with its filters kept.
+    pub fn url(&self, page_number: i32) -> String {
+        let page = (page_number > 0).then(|| ("page", page_number.to_string()));
+        self.link(self.params.iter().cloned().chain(page))
+    }
+
+    /// Url of the page following a cursor of a listing paged by cursor.
+    pub fn after_url(&self, cursor: &str) -> String {
+        self.link(self.params.iter().cloned().chain([("after", cursor.to_owned())]))
+    }
+
+    /// Url of the following page, if any.
+    pub fn next_url(&self) -> Option<String> {
+        match &self.keyset {
+            Some(keyset) => keyset.next.as_deref().map(|next| self.after_url(next)),
+            None => (self.current_page < self.number_of_pages - 1).then(|| self.url(self.current_page + 1)),
+        }
+    }
+
+    pub fn links(&self) -> PageLinks {
+        match &self.keyset {
+            Some(keyset) => PageLinks {
+                canonical: keyset.after.as_deref().map_or_else(|| self.url(0), |after| self.after_url(after)),
+                prev: None,
+                next: self.next_url(),
+            },
+            None => PageLinks {
+                canonical: self.url(self.current_page),
+                prev: (self.current_page > 0).then(|| self.url(self.current_page - 1)),
+                next: self.next_url(),
+            },
+        }
+    }
+}
+
+/// Canonical url of a listing page and of its neighbours, emitted for crawlers.
+pub struct PageLinks {
+    pub canonical: String,
+    pub prev: Option<String>,
+    pub next: Option<String>,
 }
 
-#[derive(Decode)]
+/// Pagination parameters of the filters that are set.
+fn page_params(filters: &[(&'static str, Option<&str>)]) -> Vec<(&'static str, String)> {
+    filters
+        .iter()
+        .filter_map(|(name, value)| value.map(|value| (*name, value.to_owned())))
+        .collect()
+}
+
+#[derive(Clone)]
 pub struct Item {
     pub locator: String,
     pub title: String,
@@ -152,13 +359,97 @@ pub struct Item {
     pub score: f32,
     pub review_count: i64,
     pub rank: i64,
-    pub popularity: i64
+    pub popularity: i64,
+    pub category_slug: Option<String>,
+    pub category_name: Option<String>,
+    /// Rank among items of the same category.
+    pub category_rank: i64,
+    pub release_date: Option<NaiveDate>,
+    /// Set until the release date passes, reviews open once it is cleared.
+    pub unreleased: bool,
+    /// Set by admins to stop new and changed ratings.
+    pub locked: bool,
+    pub favorite_count: i64,
+    /// Name of the stored cover, a generated one is shown without it.
+    pub cover: Option<String>,
+}
+
+// Written out because the derive cannot decode optional record fields.
+impl<'r> Decode<'r, Postgres> for Item {
+    fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+        let mut decoder = PgRecordDecoder::new(value)?;
+        Ok(Item {
+            locator: decoder.try_decode()?,
+            title: decoder.try_decode()?,
+            description: decoder.try_decode()?,
+            score: decoder.try_decode()?,
+            review_count: decoder.try_decode()?,
+            rank: decoder.try_decode()?,
+            popularity: decoder.try_decode()?,
+            category_slug: decoder.try_decode()?,
+            category_name: decoder.try_decode()?,
+            category_rank: decoder.try_decode()?,
+            release_date: decoder.try_decode()?,
+            unreleased: decoder.try_decode()?,
+            locked: decoder.try_decode()?,
+            favorite_count: decoder.try_decode()?,
+            cover: decoder.try_decode()?,
+        })
+    }
+}
+
+/// What a generated placeholder cover is drawn from.
+pub struct Cover {
+    pub title: String,
+    pub hue: i16,
+}
+
+/// Item whose cover is missing from storage under `name`, items without a stored cover are
+/// looked up by locator.
+pub async fn get_item_cover(pool: &PgPool, name: &str) -> Result<Option<Cover>, DatabaseError> {
+    query_as!(Cover, r#"SELECT title, get_hue(title) AS "hue!" FROM items WHERE cover = $1 OR cover IS NULL AND locator = $1 LIMIT 1"#, name).fetch_optional(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// An item as listed in the admin table.
+pub struct ItemRow {
+    pub locator: String,
+    pub title: String,
+    pub score: f32,
+    pub review_count: i64,
+    pub created: NaiveDateTime,
+    pub status: String,
+}
+
+pub async fn get_item_rows(pool: &PgPool) -> Result<Vec<ItemRow>, DatabaseError> {
+    query_as!(ItemRow, r#"SELECT i.locator, i.title, s.score AS "score!", s.review_count AS "review_count!", i.created, CASE WHEN s.review_count = 0 THEN 'unrated' ELSE 'rated' END AS "status!" FROM items i JOIN items_score s ON s.id = i.id ORDER BY i.title"#).fetch_all(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// An item as exported in the full catalog.
+#[derive(Serialize)]
+pub struct CatalogEntry {
+    pub locator: String,
+    pub title: String,
+    pub description: String,
+    pub category: Option<String>,
+    pub score: f32,
+    pub review_count: i64,
+}
+
+/// Every item with its score, read row by row so that exports don't hold the catalog in memory.
+pub fn stream_catalog(pool: &PgPool) -> BoxStream<'_, Result<CatalogEntry, DatabaseError>> {
+    query_as!(CatalogEntry, r#"SELECT locator AS "locator!", title AS "title!", description AS "description!", category_name AS category, score AS "score!", review_count AS "review_count!" FROM items_score ORDER BY id"#).fetch(pool).map(|row| row.map_err(|e| DatabaseError::InternalError(Box::new(e)))).boxed()
+}
+
+/// Version of the catalog export, changing with every statement that changes an exported item,
+/// review or category.
+pub async fn get_catalog_version(pool: &PgPool) -> Result<i64, DatabaseError> {
+    query_scalar!("SELECT last_value FROM catalog_version").fetch_one(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
 }
 
 pub async fn get_item(pool: &PgPool, locator: &str) -> Result<Option<Item>, DatabaseError> {
     match query_as!(
         Item,
-        r#"SELECT locator AS "locator!", title AS "title!", description AS "description!", score AS "score!", review_count AS "review_count!", rank AS "rank!", popularity AS "popularity!" FROM items_score WHERE locator = $1 LIMIT 1"#,
+        r#"SELECT locator AS "locator!", title AS "title!", description AS "description!", score AS "score!", review_count AS "review_count!", rank AS "rank!", popularity AS "popularity!", category_slug, category_name, category_rank AS "category_rank!", release_date, unreleased AS "unreleased!", locked AS "locked!", favorite_count AS "favorite_count!", cover FROM items_score WHERE locator = $1 LIMIT 1"#,
         locator
     )
     .fetch_one(pool)
@@ -172,249 +463,1216 @@ pub async fn get_item(pool: &PgPool, locator: &str) -> Result<Option<Item>, Data
     }
 }
 
-pub async fn get_items(
-    pool: &PgPool,
-    page_number: Option<i32>,
-    query: Option<&str>,
-) -> Result<Option<Page<Item>>, DatabaseError> {
-    let page_number = page_number.unwrap_or(0);
-    let number_of_pages = if let Some(query) = query {
-        (query_scalar!("SELECT COUNT(*) FROM items WHERE title % $1", query)
-            .fetch_one(pool)
-            .await
-            .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
-            .unwrap_or_default() as usize)
-            .div_ceil(12) as i32
-    } else {
-        (query_scalar!("SELECT COUNT(*) FROM items")
-            .fetch_one(pool)
-            .await
-            .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
-            .unwrap_or_default() as usize)
-            .div_ceil(12) as i32
-    };
-    if (0..number_of_pages).contains(&page_number) {
-        let page = if let Some(query) = query {
-            query_as!(
-            Item,
-            r#"SELECT locator AS "locator!", title AS "title!", description AS "description!", score AS "score!", review_count AS "review_count!", rank AS "rank!", popularity AS "popularity!" FROM items_score WHERE title % $1 ORDER BY SIMILARITY(title,$1) DESC, score DESC LIMIT 12 OFFSET 12 * $2"#,
-            query,
-            page_number
-            )
-            .fetch_all(pool)
-            .await
-            .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
-        } else {
-            query_as!(
-                Item,
-                r#"SELECT locator AS "locator!", title AS "title!", description AS "description!", score AS "score!", review_count AS "review_count!", rank AS "rank!", popularity AS "popularity!" FROM items_score ORDER BY score DESC LIMIT 12 OFFSET 12 * $1"#,
-                page_number
-            )
-            .fetch_all(pool)
-            .await
-            .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
-        };
-        Ok(Some(Page {
-            target: "/items".to_owned(),
-            items: page,
-            current_page: page_number,
-            number_of_pages,
-            query: query.map(str::to_owned),
-        }))
-    } else {
-        Ok(None)
+/// How a search query is matched against items.
+#[derive(Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// Titles similar to the query, forgiving typos.
+    #[default]
+    Title,
+    /// Words of the query in the title, former locators or description, ranked by relevance.
+    #[serde(rename = "text")]
+    FullText,
+}
+
+impl SearchMode {
+    /// Value of the `mode` query parameter, left out for the default mode.
+    pub fn param(self) -> Option<&'static str> {
+        match self {
+            SearchMode::Title => None,
+            SearchMode::FullText => Some("text"),
+        }
     }
 }
 
-#[derive(Serialize, Deserialize, Decode)]
-pub struct User {
-    pub username: String,
-    pub is_admin: bool,
-    pub avatar_hue: i16,
-    pub has_avatar: bool
+/// Conditions narrowing down the item listing.
+#[derive(Default)]
+pub struct ItemFilter<'a> {
+    pub tag: Option<&'a str>,
+    pub category: Option<&'a str>,
+    /// Lowest average score shown.
+    pub min_score: Option<f32>,
+    /// Fewest reviews shown.
+    pub min_reviews: Option<i64>,
+    /// Initial of the titles shown, `#` standing for titles not starting with a Latin letter.
+    pub letter: Option<char>,
 }
 
-pub async fn get_user(pool: &PgPool, username: &str) -> Result<Option<User>, DatabaseError> {
-    match query_as!(
-        User,
-        "SELECT username, is_admin, avatar_hue, has_avatar FROM users WHERE username = $1 LIMIT 1",
-        username
-    )
-    .fetch_one(pool)
-    .await
-    {
-        Ok(u) => Ok(Some(u)),
-        Err(e) => match e {
-            sqlx::Error::RowNotFound => Ok(None),
-            _ => Err(DatabaseError::InternalError(Box::new(e))),
-        },
+/// Letter of the alphabetical index from a query parameter.
+pub fn index_letter(value: &str) -> Option<char> {
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(letter), None) if letter.is_ascii_alphabetic() || letter == '#' => Some(letter.to_ascii_uppercase()),
+        _ => None,
     }
 }
 
-pub async fn get_users(
+/// An item on a numbered listing page, along with its id to page by cursor from and the number of
+/// items in the whole listing.
+struct ListedItem {
+    id: i32,
+    locator: String,
+    title: String,
+    description: String,
+    score: f32,
+    review_count: i64,
+    rank: i64,
+    popularity: i64,
+    category_slug: Option<String>,
+    category_name: Option<String>,
+    category_rank: i64,
+    release_date: Option<NaiveDate>,
+    unreleased: bool,
+    locked: bool,
+    favorite_count: i64,
+    cover: Option<String>,
+    total: i64,
+}
+
+impl From<ListedItem> for Item {
+    fn from(row: ListedItem) -> Self {
+        Item { locator: row.locator, title: row.title, description: row.description, score: row.score, review_count: row.review_count, rank: row.rank, popularity: row.popularity, category_slug: row.category_slug, category_name: row.category_name, category_rank: row.category_rank, release_date: row.release_date, unreleased: row.unreleased, locked: row.locked, favorite_count: row.favorite_count, cover: row.cover }
+    }
+}
+
+/// Cursor pointing past an item in the listing by score, ties broken by id.
+fn item_cursor(score: f32, id: i32) -> String {
+    format!("{score}_{id}")
+}
+
+fn parse_item_cursor(cursor: &str) -> Option<(f32, i32)> {
+    let (score, id) = cursor.split_once('_')?;
+    Some((score.parse().ok()?, id.parse().ok()?))
+}
+
+pub async fn get_items(
     pool: &PgPool,
     page_number: Option<i32>,
-    query: Option<&str>,
-) -> Result<Option<Page<User>>, DatabaseError> {
+    per_page: i64,
+    search: Option<&str>,
+    filter: &ItemFilter<'_>,
+    mode: SearchMode,
+    after: Option<&str>,
+) -> Result<Option<Page<Item>>, DatabaseError> {
     let page_number = page_number.unwrap_or(0);
-    let number_of_pages = if let Some(query) = query {
-        (query_scalar!(
-            "SELECT COALESCE(COUNT(*), 0) FROM users WHERE username % $1",
-            query
+    // Filters typed into the search box take precedence over the filter panel.
+    let parsed = search.map(search::Query::parse).unwrap_or_default();
+    let text = parsed.text(mode);
+    let query = text.as_deref();
+    let tag = parsed.tag.as_deref().or(filter.tag);
+    let category = parsed.category.as_deref().or(filter.category);
+    let min_score = parsed.min_score.or(filter.min_score);
+    let min_reviews = parsed.min_reviews.or(filter.min_reviews);
+    let phrases = parsed.phrases.as_slice();
+    let letter = filter.letter.map(String::from);
+    let params = page_params(&[("search", search), ("mode", mode.param()), ("tag", filter.tag), ("category", filter.category), ("min_score", filter.min_score.map(|score| score.to_string()).as_deref()), ("min_reviews", filter.min_reviews.map(|count| count.to_string()).as_deref()), ("letter", letter.as_deref()), ("per_page", (per_page != default_per_page()).then(|| per_page.to_string()).as_deref())]);
+    // Only the listing by score is paged by cursor, searches are ranked by relevance.
+    let by_score = query.is_none() && letter.is_none();
+    let after = after.filter(|_| by_score);
+    let cursor = after.and_then(parse_item_cursor);
+    if cursor.is_some() {
+        let (score, id) = cursor.unzip();
+        let mut rows = query!(r#"SELECT s.id AS "id!", s.locator AS "locator!", s.title AS "title!", s.description AS "description!", s.score AS "score!", s.review_count AS "review_count!", s.rank AS "rank!", s.popularity AS "popularity!", s.category_slug, s.category_name, s.category_rank AS "category_rank!", s.release_date, s.unreleased AS "unreleased!", s.locked AS "locked!", s.favorite_count AS "favorite_count!", s.cover FROM items_score s WHERE ($1::REAL IS NULL OR (s.score, s.id) < ($1, $2::INT)) AND ($3::TEXT IS NULL OR EXISTS (SELECT 1 FROM item_tags it JOIN tags t ON t.id = it.tag_id WHERE it.item_id = s.id AND t.name = $3)) AND ($4::TEXT IS NULL OR s.category_slug = $4) AND ($5::REAL IS NULL OR s.score >= $5) AND ($6::BIGINT IS NULL OR s.review_count >= $6) AND ($7::REAL IS NULL OR s.score <= $7) ORDER BY s.score DESC, s.id DESC LIMIT $8"#, score, id, tag, category, min_score, min_reviews, parsed.max_score, per_page + 1)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+        let has_next = rows.len() as i64 > per_page;
+        rows.truncate(per_page as usize);
+        let next = rows.last().filter(|_| has_next).map(|row| item_cursor(row.score, row.id));
+        if rows.is_empty() {
+            return Ok(None);
+        }
+        let items = rows.into_iter().map(|row| Item { locator: row.locator, title: row.title, description: row.description, score: row.score, review_count: row.review_count, rank: row.rank, popularity: row.popularity, category_slug: row.category_slug, category_name: row.category_name, category_rank: row.category_rank, release_date: row.release_date, unreleased: row.unreleased, locked: row.locked, favorite_count: row.favorite_count, cover: row.cover }).collect();
+        return Ok(Some(Page {
+            target: routes::url::path(routes::ITEMS),
+            items,
+            current_page: 0,
+            number_of_pages: 0,
+            per_page,
+            params,
+            keyset: Some(Keyset { after: after.map(str::to_owned), next }),
+        }));
+    }
+    if !(0..MAX_NUMBERED_PAGES).contains(&page_number) {
+        return Ok(None);
+    }
+    let rows = if let Some(query) = query.filter(|_| mode == SearchMode::FullText) {
+        query_as!(
+        ListedItem,
+        r#"SELECT s.id AS "id!", s.locator AS "locator!", s.title AS "title!", s.description AS "description!", s.score AS "score!", s.review_count AS "review_count!", s.rank AS "rank!", s.popularity AS "popularity!", s.category_slug, s.category_name, s.category_rank AS "category_rank!", s.release_date, s.unreleased AS "unreleased!", s.locked AS "locked!", s.favorite_count AS "favorite_count!", s.cover, COUNT(*) OVER () AS "total!" FROM items_score s JOIN items i ON i.id = s.id WHERE i.search @@ websearch_to_tsquery('english', $1) AND ($3::TEXT IS NULL OR EXISTS (SELECT 1 FROM item_tags it JOIN tags t ON t.id = it.tag_id WHERE it.item_id = s.id AND t.name = $3)) AND ($4::TEXT IS NULL OR s.category_slug = $4) AND ($5::REAL IS NULL OR s.score >= $5) AND ($6::BIGINT IS NULL OR s.review_count >= $6) AND ($7::REAL IS NULL OR s.score <= $7) AND ($8::TEXT IS NULL OR CASE WHEN $8 = '#' THEN upper(left(s.title, 1)) !~ '^[A-Z]' ELSE upper(left(s.title, 1)) = $8 END) ORDER BY ts_rank(i.search, websearch_to_tsquery('english', $1)) DESC, s.score DESC, s.id DESC LIMIT $9 OFFSET $9::BIGINT * $2::INT"#,
+        query,
+        page_number,
+        tag,
+        category,
+        min_score,
+        min_reviews,
+        parsed.max_score,
+        letter,
+        per_page
         )
-        .fetch_one(pool)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
+    } else if let Some(query) = query {
+        query_as!(
+        ListedItem,
+        r#"SELECT s.id AS "id!", s.locator AS "locator!", s.title AS "title!", s.description AS "description!", s.score AS "score!", s.review_count AS "review_count!", s.rank AS "rank!", s.popularity AS "popularity!", s.category_slug, s.category_name, s.category_rank AS "category_rank!", s.release_date, s.unreleased AS "unreleased!", s.locked AS "locked!", s.favorite_count AS "favorite_count!", s.cover, COUNT(*) OVER () AS "total!" FROM items_score s WHERE s.title % $1 AND NOT EXISTS (SELECT 1 FROM unnest($8::TEXT[]) p WHERE strpos(lower(s.title), lower(p)) = 0) AND ($3::TEXT IS NULL OR EXISTS (SELECT 1 FROM item_tags it JOIN tags t ON t.id = it.tag_id WHERE it.item_id = s.id AND t.name = $3)) AND ($4::TEXT IS NULL OR s.category_slug = $4) AND ($5::REAL IS NULL OR s.score >= $5) AND ($6::BIGINT IS NULL OR s.review_count >= $6) AND ($7::REAL IS NULL OR s.score <= $7) AND ($9::TEXT IS NULL OR CASE WHEN $9 = '#' THEN upper(left(s.title, 1)) !~ '^[A-Z]' ELSE upper(left(s.title, 1)) = $9 END) ORDER BY SIMILARITY(s.title,$1) DESC, s.score DESC, s.id DESC LIMIT $10 OFFSET $10::BIGINT * $2::INT"#,
+        query,
+        page_number,
+        tag,
+        category,
+        min_score,
+        min_reviews,
+        parsed.max_score,
+        phrases,
+        letter,
+        per_page
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
+    } else if let Some(letter) = &letter {
+        query_as!(
+            ListedItem,
+            r#"SELECT s.id AS "id!", s.locator AS "locator!", s.title AS "title!", s.description AS "description!", s.score AS "score!", s.review_count AS "review_count!", s.rank AS "rank!", s.popularity AS "popularity!", s.category_slug, s.category_name, s.category_rank AS "category_rank!", s.release_date, s.unreleased AS "unreleased!", s.locked AS "locked!", s.favorite_count AS "favorite_count!", s.cover, COUNT(*) OVER () AS "total!" FROM items_score s WHERE ($2::TEXT IS NULL OR EXISTS (SELECT 1 FROM item_tags it JOIN tags t ON t.id = it.tag_id WHERE it.item_id = s.id AND t.name = $2)) AND ($3::TEXT IS NULL OR s.category_slug = $3) AND ($4::REAL IS NULL OR s.score >= $4) AND ($5::BIGINT IS NULL OR s.review_count >= $5) AND ($6::REAL IS NULL OR s.score <= $6) AND CASE WHEN $7 = '#' THEN upper(left(s.title, 1)) !~ '^[A-Z]' ELSE upper(left(s.title, 1)) = $7 END ORDER BY lower(s.title), s.score DESC, s.id DESC LIMIT $8 OFFSET $8::BIGINT * $1::INT"#,
+            page_number,
+            tag,
+            category,
+            min_score,
+            min_reviews,
+            parsed.max_score,
+            letter,
+            per_page
+        )
+        .fetch_all(pool)
         .await
         .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
-        .unwrap_or_default() as usize)
-            .div_ceil(12) as i32
     } else {
-        (query_scalar!("SELECT COUNT(*) FROM users")
-            .fetch_one(pool)
-            .await
-            .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
-            .unwrap_or_default() as usize)
-            .div_ceil(12) as i32
+        // Ordered like the cursor listing, which the first page turns into when there are many.
+        query_as!(
+            ListedItem,
+            r#"SELECT s.id AS "id!", s.locator AS "locator!", s.title AS "title!", s.description AS "description!", s.score AS "score!", s.review_count AS "review_count!", s.rank AS "rank!", s.popularity AS "popularity!", s.category_slug, s.category_name, s.category_rank AS "category_rank!", s.release_date, s.unreleased AS "unreleased!", s.locked AS "locked!", s.favorite_count AS "favorite_count!", s.cover, COUNT(*) OVER () AS "total!" FROM items_score s WHERE ($2::TEXT IS NULL OR EXISTS (SELECT 1 FROM item_tags it JOIN tags t ON t.id = it.tag_id WHERE it.item_id = s.id AND t.name = $2)) AND ($3::TEXT IS NULL OR s.category_slug = $3) AND ($4::REAL IS NULL OR s.score >= $4) AND ($5::BIGINT IS NULL OR s.review_count >= $5) AND ($6::REAL IS NULL OR s.score <= $6) ORDER BY s.score DESC, s.id DESC LIMIT $7 OFFSET $7::BIGINT * $1::INT"#,
+            page_number,
+            tag,
+            category,
+            min_score,
+            min_reviews,
+            parsed.max_score,
+            per_page
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
     };
-    if (0..number_of_pages).contains(&page_number) {
-        let page = if let Some(query) = query {
-            query_as!(
-            User,
-            "SELECT username, is_admin, avatar_hue, has_avatar FROM users WHERE username % $1 ORDER BY SIMILARITY(username,$1) DESC LIMIT 12 OFFSET 12 * $2",
-            query,
-            page_number
-            )
-            .fetch_all(pool)
-            .await
-            .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
-        } else {
-            query_as!(
-                User,
-                "SELECT username, is_admin, avatar_hue, has_avatar FROM users LIMIT 12 OFFSET 12 * $1",
-                page_number
-            )
-            .fetch_all(pool)
-            .await
-            .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
-        };
-        Ok(Some(Page {
-            target: "/users".to_owned(),
-            items: page,
-            current_page: page_number,
-            number_of_pages,
-            query: query.map(str::to_owned),
-        }))
-    } else {
-        Ok(None)
+    // Pages past the end come back empty, without a count to tell how many there are.
+    let Some(total) = rows.first().map(|row| row.total) else {
+        return Ok(None);
+    };
+    let number_of_pages = (total as usize).div_ceil(per_page as usize) as i32;
+    let keyset = (by_score && page_number == 0 && number_of_pages > MAX_NUMBERED_PAGES).then(|| Keyset {
+        after: None,
+        next: rows.last().map(|row| item_cursor(row.score, row.id)),
+    });
+    Ok(Some(Page {
+        target: routes::url::path(routes::ITEMS),
+        items: rows.into_iter().map(Item::from).collect(),
+        current_page: page_number,
+        number_of_pages: number_of_pages.min(MAX_NUMBERED_PAGES),
+        per_page,
+        params,
+        keyset,
+    }))
+}
+
+/// Items reviewed most often in the last week.
+pub async fn get_trending_items(pool: &PgPool, page_number: Option<i32>, per_page: i64) -> Result<Option<Page<Item>>, DatabaseError> {
+    let page_number = page_number.unwrap_or(0);
+    let number_of_pages = (query_scalar!("SELECT COUNT(DISTINCT item_id) FROM reviews WHERE date > now() - INTERVAL '7 days'").fetch_one(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?.unwrap_or_default() as usize).div_ceil(per_page as usize) as i32;
+    if !(0..number_of_pages).contains(&page_number) {
+        return Ok(None);
     }
+    let items = query_as!(Item, r#"SELECT s.locator AS "locator!", s.title AS "title!", s.description AS "description!", s.score AS "score!", s.review_count AS "review_count!", s.rank AS "rank!", s.popularity AS "popularity!", s.category_slug, s.category_name, s.category_rank AS "category_rank!", s.release_date, s.unreleased AS "unreleased!", s.locked AS "locked!", s.favorite_count AS "favorite_count!", s.cover FROM items_score s JOIN (SELECT item_id, COUNT(*) AS recent FROM reviews WHERE date > now() - INTERVAL '7 days' GROUP BY item_id) r ON r.item_id = s.id ORDER BY r.recent DESC, s.score DESC, s.id LIMIT $2 OFFSET $2::BIGINT * $1::INT"#, page_number, per_page).fetch_all(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    Ok(Some(Page {
+        target: routes::url::path(routes::ITEMS_TRENDING),
+        items,
+        current_page: page_number,
+        number_of_pages,
+        per_page,
+        params: page_params(&[("per_page", (per_page != default_per_page()).then(|| per_page.to_string()).as_deref())]),
+        keyset: None,
+    }))
 }
 
-pub async fn rate_item(
-    pool: &PgPool,
-    username: &str,
-    item_locator: &str,
-    rating: i16,
-) -> Result<(), DatabaseError> {
-    let rating = rating.max(1).min(10);
-    if let Err(e)=query!("INSERT INTO reviews(item_id, user_id, rating) VALUES((SELECT id FROM items WHERE locator=$1 LIMIT 1), (SELECT id FROM users WHERE username=$2 LIMIT 1), $3)",item_locator,username,rating).execute(pool).await {
-        match e {
-            sqlx::Error::Database(e) => if e.is_unique_violation(){ 
-                query!("UPDATE reviews SET rating=$3, date=now() WHERE item_id=(SELECT id FROM items WHERE locator=$1 LIMIT 1) AND user_id=(SELECT id FROM users WHERE username=$2 LIMIT 1)",item_locator,username,rating).execute(pool).await.map(|_|()) .map_err(|e| DatabaseError::InternalError(Box::new(e)))
-            } else {
-                Err(DatabaseError::InternalError(Box::new(e)))
-            },
-            _ => Err(DatabaseError::InternalError(Box::new(e)))
-        }
-    } else {
-        Ok(())
+/// Items by when they were added, newest first.
+pub async fn get_new_items(pool: &PgPool, page_number: Option<i32>, per_page: i64) -> Result<Option<Page<Item>>, DatabaseError> {
+    let page_number = page_number.unwrap_or(0);
+    let number_of_pages = (query_scalar!("SELECT COUNT(*) FROM items").fetch_one(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?.unwrap_or_default() as usize).div_ceil(per_page as usize) as i32;
+    if !(0..number_of_pages).contains(&page_number) {
+        return Ok(None);
     }
+    let items = query_as!(Item, r#"SELECT locator AS "locator!", title AS "title!", description AS "description!", score AS "score!", review_count AS "review_count!", rank AS "rank!", popularity AS "popularity!", category_slug, category_name, category_rank AS "category_rank!", release_date, unreleased AS "unreleased!", locked AS "locked!", favorite_count AS "favorite_count!", cover FROM items_score ORDER BY created DESC, id DESC LIMIT $2 OFFSET $2::BIGINT * $1::INT"#, page_number, per_page).fetch_all(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    Ok(Some(Page {
+        target: routes::url::path(routes::ITEMS_NEW),
+        items,
+        current_page: page_number,
+        number_of_pages,
+        per_page,
+        params: page_params(&[("per_page", (per_page != default_per_page()).then(|| per_page.to_string()).as_deref())]),
+        keyset: None,
+    }))
 }
 
-pub async fn remove_review(pool: &PgPool, locator:&str, username: &str) ->Result<(), DatabaseError>{
-    query!("DELETE FROM reviews WHERE item_id=(SELECT id FROM items WHERE locator=$1 LIMIT 1) AND user_id=(SELECT id FROM users WHERE username=$2)",locator, username).execute(pool).await.map(|_|()).map_err(|e|DatabaseError::InternalError(Box::new(e)))
+pub struct ItemImage {
+    pub id: i32,
+    /// Name the image is stored under.
+    pub file: String,
+    pub is_cover: bool,
 }
 
-pub async fn get_item_rating(pool: &PgPool, locator:&str, username: &str) -> Result<Option<i16>, DatabaseError> {
-    match query_scalar!("SELECT rating FROM reviews WHERE item_id=(SELECT id FROM items WHERE locator=$1 LIMIT 1) AND user_id=(SELECT id FROM users WHERE username=$2) LIMIT 1",locator,username).fetch_one(pool).await {
-        Ok(r) => Ok(Some(r)),
-        Err(e) => match e {
-            sqlx::Error::RowNotFound => Ok(None),
-            _ => Err(DatabaseError::InternalError(Box::new(e))),
-        },
-    }
+/// Gallery of an item, starting with its cover.
+pub async fn get_item_images(pool: &PgPool, locator: &str) -> Result<Vec<ItemImage>, DatabaseError> {
+    query_as!(ItemImage, "SELECT id, file, is_cover FROM item_images WHERE item_id = (SELECT id FROM items WHERE locator = $1 LIMIT 1) ORDER BY is_cover DESC, id", locator).fetch_all(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
 }
 
-pub struct RatingItem
-{
-    pub user: User,
-    pub rating: i16,
-    pub date: NaiveDateTime
+/// Adds an image stored under `file` to the gallery of an item, making it the cover when
+/// `is_cover` is set.
+pub async fn add_item_image(connection: impl Acquire<'_, Database = Postgres>, locator: &str, file: &str, is_cover: bool) -> Result<(), DatabaseError> {
+    let mut transaction = connection.begin().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    if is_cover {
+        query!("UPDATE item_images SET is_cover = FALSE WHERE is_cover AND item_id = (SELECT id FROM items WHERE locator = $1 LIMIT 1)", locator).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+        query!("UPDATE items SET cover = $2 WHERE locator = $1", locator, file).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    }
+    query!("INSERT INTO item_images(item_id, file, is_cover) VALUES((SELECT id FROM items WHERE locator = $1 LIMIT 1), $2, $3)", locator, file, is_cover).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    transaction.commit().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
 }
 
-pub async fn get_item_ratings(pool: &PgPool, page_number: Option<i32>, locator: &str)
- -> Result<Option<Page<RatingItem>>, DatabaseError> {
-    let page_number = page_number.unwrap_or(0);
-    let number_of_pages = 
-        (query_scalar!("SELECT COUNT(*) FROM reviews WHERE item_id = (SELECT id FROM items WHERE locator = $1 LIMIT 1)", locator)
-            .fetch_one(pool)
-            .await
-            .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
-            .unwrap_or_default() as usize)
-            .div_ceil(3) as i32;
-    if (0..number_of_pages).contains(&page_number) {
-        let page = 
-    query_as!(RatingItem, r#"SELECT (u.username, u.is_admin, u.avatar_hue, u.has_avatar) AS "user!: User", rating, date FROM reviews r JOIN users u ON r.user_id = u.id WHERE r.item_id = (SELECT id FROM items WHERE locator = $1 LIMIT 1) ORDER BY date DESC LIMIT 3 OFFSET 3 * $2"#,locator,page_number).fetch_all(pool).await.map_err(|e|DatabaseError::InternalError(Box::new(e)))?;
-        Ok(Some(Page {
-            target: "/items/".to_owned() + &locator,
-            items: page,
-            current_page: page_number,
-            number_of_pages,
-            query: None,
-        }))
-    } else {
-        Ok(None)
+/// Flags a gallery image as the cover of its item, returning whether the image belongs to it.
+pub async fn set_item_cover(pool: &PgPool, locator: &str, id: i32, moderator: &str) -> Result<bool, DatabaseError> {
+    let mut transaction = pool.begin().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    let before = item_snapshot(&mut *transaction, locator).await?;
+    query!("UPDATE item_images SET is_cover = FALSE WHERE is_cover AND item_id = (SELECT id FROM items WHERE locator = $1 LIMIT 1)", locator).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    let updated = query!("UPDATE item_images SET is_cover = TRUE WHERE id = $2 AND item_id = (SELECT id FROM items WHERE locator = $1 LIMIT 1)", locator, id).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?.rows_affected() > 0;
+    if updated {
+        query!("UPDATE items SET cover = (SELECT file FROM item_images WHERE id = $2) WHERE locator = $1", locator, id).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+        let after = item_snapshot(&mut *transaction, locator).await?;
+        record_action(&mut *transaction, Some(moderator), "set_item_cover", locator, before.as_ref(), after.as_ref()).await?;
+        transaction.commit().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
     }
+    Ok(updated)
 }
 
-pub struct RatingUser
-{
-    pub item: Item,
-    pub rating: i16,
-    pub date: NaiveDateTime
+/// Goes back to a generated cover for an item, keeping its cover image in the gallery.
+pub async fn clear_item_cover(connection: impl Acquire<'_, Database = Postgres>, locator: &str) -> Result<(), DatabaseError> {
+    let mut transaction = connection.begin().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    query!("UPDATE item_images SET is_cover = FALSE WHERE is_cover AND item_id = (SELECT id FROM items WHERE locator = $1 LIMIT 1)", locator).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    query!("UPDATE items SET cover = NULL WHERE locator = $1", locator).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    transaction.commit().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
 }
 
-pub async fn get_user_ratings(pool: &PgPool, page_number: Option<i32>, username: &str)
- -> Result<Option<Page<RatingUser>>, DatabaseError> {
-    let page_number = page_number.unwrap_or(0);
-    let number_of_pages = 
-        (query_scalar!("SELECT COUNT(*) FROM reviews WHERE user_id = (SELECT id FROM users WHERE username = $1 LIMIT 1)", username)
-            .fetch_one(pool)
-            .await
-            .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
-            .unwrap_or_default() as usize)
-            .div_ceil(3) as i32;
-    if (0..number_of_pages).contains(&page_number) {
-        let page = 
-    query_as!(RatingUser, r#"SELECT (i.locator, i.title, i.description, i.score, i.review_count, i.rank, i.popularity) AS "item!: Item", rating, date FROM reviews r JOIN items_score i ON r.item_id = i.id WHERE r.user_id = (SELECT id FROM users WHERE username = $1 LIMIT 1) ORDER BY date DESC LIMIT 3 OFFSET 3 * $2"#,username,page_number).fetch_all(pool).await.map_err(|e|DatabaseError::InternalError(Box::new(e)))?;
-        Ok(Some(Page {
-            target: "/users/".to_owned() + &username,
-            items: page,
-            current_page: page_number,
-            number_of_pages,
-            query: None,
-        }))
-    } else {
-        Ok(None)
-    }
+/// Points an item at a cover stored under another name.
+pub async fn set_item_cover_file(pool: &PgPool, locator: &str, cover: &str) -> Result<(), DatabaseError> {
+    query!("UPDATE items SET cover = $2 WHERE locator = $1", locator, cover).execute(pool).await.map(|_| ()).map_err(|e| DatabaseError::InternalError(Box::new(e)))
 }
 
-pub async fn add_item(pool: &PgPool, locator:&str, title:&str, description: &str) -> Result<(),DatabaseError>{
-    if locator.trim().is_empty() || title.trim().is_empty() || description.trim().is_empty() {
-        return Err(DatabaseError::EmptyFields);
-    }
-    if !Regex::new(r"^\w+$").unwrap().is_match(locator) {
-        return Err(DatabaseError::IllegalLocator);
+/// Whether an item, user or gallery image still refers to the image stored as `name` in
+/// `directory`, so that images shared by several of them are only removed with the last one.
+pub async fn is_image_used(pool: &PgPool, directory: &str, name: &str) -> Result<bool, DatabaseError> {
+    query_scalar!(r#"SELECT CASE $1 WHEN 'items' THEN EXISTS(SELECT 1 FROM items WHERE cover = $2) WHEN 'avatars' THEN EXISTS(SELECT 1 FROM users WHERE avatar = $2) ELSE EXISTS(SELECT 1 FROM item_images WHERE file = $2) END AS "used!""#, directory, name).fetch_one(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Removes a gallery image other than the cover, returning whether it existed.
+pub async fn remove_item_image(pool: &PgPool, locator: &str, id: i32, moderator: &str) -> Result<bool, DatabaseError> {
+    let mut transaction = pool.begin().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    let before = item_snapshot(&mut *transaction, locator).await?;
+    let removed = query!("DELETE FROM item_images WHERE id = $2 AND NOT is_cover AND item_id = (SELECT id FROM items WHERE locator = $1 LIMIT 1)", locator, id).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?.rows_affected() > 0;
+    if removed {
+        let after = item_snapshot(&mut *transaction, locator).await?;
+        record_action(&mut *transaction, Some(moderator), "remove_item_image", locator, before.as_ref(), after.as_ref()).await?;
+        transaction.commit().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    }
+    Ok(removed)
+}
+
+#[derive(Clone)]
+pub struct Category {
+    pub slug: String,
+    pub name: String,
+    pub item_count: i64,
+}
+
+pub async fn get_categories(pool: &PgPool) -> Result<Vec<Category>, DatabaseError> {
+    query_as!(Category, r#"SELECT c.slug, c.name, (SELECT COUNT(*) FROM items WHERE category_id = c.id) AS "item_count!" FROM categories c ORDER BY c.name"#).fetch_all(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+pub async fn add_category(pool: &PgPool, slug: &str, name: &str, moderator: &str) -> Result<(), DatabaseError> {
+    let mut transaction = pool.begin().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    match query!("INSERT INTO categories(slug, name) VALUES($1, $2)", slug, name.trim()).execute(&mut *transaction).await {
+        Ok(_) => {}
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => return Err(DatabaseError::DuplicateCategory),
+        Err(e) => return Err(DatabaseError::InternalError(Box::new(e))),
     }
-    query!("INSERT INTO items(locator, title, description) VALUES($1, $2, $3)", locator, title, description).execute(pool).await.map(|_|()).map_err(|e|match e{
+    record_action(&mut *transaction, Some(moderator), "add_category", slug, None, Some(&json!({ "name": name.trim() }))).await?;
+    transaction.commit().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Removes a category, leaving its items uncategorized.
+pub async fn remove_category(pool: &PgPool, slug: &str, moderator: &str) -> Result<(), DatabaseError> {
+    let mut transaction = pool.begin().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    let Some(name) = query_scalar!("DELETE FROM categories WHERE slug = $1 RETURNING name", slug).fetch_optional(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))? else {
+        return Ok(());
+    };
+    record_action(&mut *transaction, Some(moderator), "remove_category", slug, Some(&json!({ "name": name })), None).await?;
+    transaction.commit().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Moves an item to the category with the given slug, or out of any category for `None`.
+pub async fn set_item_category(executor: impl PgExecutor<'_>, locator: &str, category: Option<&str>) -> Result<(), DatabaseError> {
+    query!("UPDATE items SET category_id = (SELECT id FROM categories WHERE slug = $2) WHERE locator = $1", locator, category).execute(executor).await.map(|_| ()).map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+pub struct Collection {
+    pub slug: String,
+    pub name: String,
+    pub item_count: i64,
+}
+
+pub async fn get_collections(pool: &PgPool) -> Result<Vec<Collection>, DatabaseError> {
+    query_as!(Collection, r#"SELECT c.slug, c.name, (SELECT COUNT(*) FROM collection_items WHERE collection_id = c.id) AS "item_count!" FROM collections c ORDER BY c.name"#).fetch_all(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+pub async fn get_collection(pool: &PgPool, slug: &str) -> Result<Option<Collection>, DatabaseError> {
+    query_as!(Collection, r#"SELECT c.slug, c.name, (SELECT COUNT(*) FROM collection_items WHERE collection_id = c.id) AS "item_count!" FROM collections c WHERE c.slug = $1"#, slug).fetch_optional(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+pub async fn add_collection(pool: &PgPool, slug: &str, name: &str) -> Result<(), DatabaseError> {
+    match query!("INSERT INTO collections(slug, name) VALUES($1, $2)", slug, name.trim()).execute(pool).await {
+        Ok(_) => Ok(()),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => Err(DatabaseError::DuplicateCollection),
+        Err(e) => Err(DatabaseError::InternalError(Box::new(e))),
+    }
+}
+
+/// Removes a collection, leaving its items as they are.
+pub async fn remove_collection(pool: &PgPool, slug: &str) -> Result<(), DatabaseError> {
+    query!("DELETE FROM collections WHERE slug = $1", slug).execute(pool).await.map(|_| ()).map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Items of a collection in their order within it.
+pub async fn get_collection_items(pool: &PgPool, slug: &str) -> Result<Vec<Item>, DatabaseError> {
+    query_as!(Item, r#"SELECT s.locator AS "locator!", s.title AS "title!", s.description AS "description!", s.score AS "score!", s.review_count AS "review_count!", s.rank AS "rank!", s.popularity AS "popularity!", s.category_slug, s.category_name, s.category_rank AS "category_rank!", s.release_date, s.unreleased AS "unreleased!", s.locked AS "locked!", s.favorite_count AS "favorite_count!", s.cover FROM collection_items ci JOIN items_score s ON s.id = ci.item_id WHERE ci.collection_id = (SELECT id FROM collections WHERE slug = $1) ORDER BY ci.position"#, slug).fetch_all(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Appends an item to the end of a collection, keeping its place if it is already there.
+pub async fn add_collection_item(pool: &PgPool, slug: &str, locator: &str) -> Result<(), DatabaseError> {
+    let added = query!("INSERT INTO collection_items(collection_id, item_id, position) SELECT c.id, i.id, COALESCE((SELECT MAX(position) FROM collection_items WHERE collection_id = c.id), 0) + 1 FROM collections c, items i WHERE c.slug = $1 AND i.locator = $2 ON CONFLICT DO NOTHING", slug, locator).execute(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?.rows_affected();
+    if added == 0 && get_item(pool, locator).await?.is_none() {
+        return Err(DatabaseError::NonexistentItem);
+    }
+    Ok(())
+}
+
+pub async fn remove_collection_item(pool: &PgPool, slug: &str, locator: &str) -> Result<(), DatabaseError> {
+    query!("DELETE FROM collection_items WHERE collection_id = (SELECT id FROM collections WHERE slug = $1) AND item_id = (SELECT id FROM items WHERE locator = $2)", slug, locator).execute(pool).await.map(|_| ()).map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Swaps an item with the one before it in a collection, or the one after it when `earlier` is
+/// false. Items already first or last stay in place.
+pub async fn move_collection_item(pool: &PgPool, slug: &str, locator: &str, earlier: bool) -> Result<(), DatabaseError> {
+    let mut transaction = pool.begin().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    let Some(moved) = query!("SELECT ci.collection_id, ci.item_id, ci.position FROM collection_items ci WHERE ci.collection_id = (SELECT id FROM collections WHERE slug = $1) AND ci.item_id = (SELECT id FROM items WHERE locator = $2) FOR UPDATE", slug, locator).fetch_optional(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))? else {
+        return Ok(());
+    };
+    let neighbor = query!("SELECT item_id, position FROM collection_items WHERE collection_id = $1 AND CASE WHEN $3 THEN position < $2 ELSE position > $2 END ORDER BY CASE WHEN $3 THEN -position ELSE position END LIMIT 1 FOR UPDATE", moved.collection_id, moved.position, earlier).fetch_optional(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    if let Some(neighbor) = neighbor {
+        query!("UPDATE collection_items SET position = CASE item_id WHEN $2 THEN $5::INTEGER ELSE $3::INTEGER END WHERE collection_id = $1 AND item_id IN ($2, $4)", moved.collection_id, moved.item_id, moved.position, neighbor.item_id, neighbor.position).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    }
+    transaction.commit().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Items pinned by admins, in the order they are shown.
+pub async fn get_featured_items(pool: &PgPool) -> Result<Vec<Item>, DatabaseError> {
+    query_as!(Item, r#"SELECT s.locator AS "locator!", s.title AS "title!", s.description AS "description!", s.score AS "score!", s.review_count AS "review_count!", s.rank AS "rank!", s.popularity AS "popularity!", s.category_slug, s.category_name, s.category_rank AS "category_rank!", s.release_date, s.unreleased AS "unreleased!", s.locked AS "locked!", s.favorite_count AS "favorite_count!", s.cover FROM featured_items f JOIN items_score s ON s.id = f.item_id ORDER BY f.position"#).fetch_all(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+pub async fn is_featured(pool: &PgPool, locator: &str) -> Result<bool, DatabaseError> {
+    query_scalar!(r#"SELECT EXISTS(SELECT 1 FROM featured_items WHERE item_id = (SELECT id FROM items WHERE locator = $1)) AS "featured!""#, locator).fetch_one(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Pins an item at the end of the featured items, or unpins it.
+pub async fn set_featured(pool: &PgPool, locator: &str, featured: bool, moderator: &str) -> Result<(), DatabaseError> {
+    let mut transaction = pool.begin().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    let changed = if featured {
+        query!("INSERT INTO featured_items(item_id, position) SELECT id, COALESCE((SELECT MAX(position) FROM featured_items), 0) + 1 FROM items WHERE locator = $1 ON CONFLICT DO NOTHING", locator).execute(&mut *transaction).await
+    } else {
+        query!("DELETE FROM featured_items WHERE item_id = (SELECT id FROM items WHERE locator = $1)", locator).execute(&mut *transaction).await
+    }
+    .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
+    .rows_affected() > 0;
+    if changed {
+        record_action(&mut *transaction, Some(moderator), if featured { "feature_item" } else { "unfeature_item" }, locator, None, None).await?;
+    }
+    transaction.commit().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Orders the featured items as listed, leaving out locators that are not featured.
+pub async fn reorder_featured_items(pool: &PgPool, locators: &[String]) -> Result<(), DatabaseError> {
+    query!("UPDATE featured_items f SET position = o.position::INTEGER FROM items i, UNNEST($1::TEXT[]) WITH ORDINALITY AS o(locator, position) WHERE i.locator = o.locator AND f.item_id = i.id", locators).execute(pool).await.map(|_| ()).map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// A collection an item is part of, with the item's place in it.
+pub struct ItemCollection {
+    pub slug: String,
+    pub name: String,
+    /// Place of the item, counting from one.
+    pub part: i64,
+    pub item_count: i64,
+}
+
+pub async fn get_item_collections(pool: &PgPool, locator: &str) -> Result<Vec<ItemCollection>, DatabaseError> {
+    query_as!(ItemCollection, r#"SELECT c.slug, c.name, (SELECT COUNT(*) FROM collection_items o WHERE o.collection_id = c.id AND o.position <= ci.position) AS "part!", (SELECT COUNT(*) FROM collection_items WHERE collection_id = c.id) AS "item_count!" FROM collection_items ci JOIN collections c ON c.id = ci.collection_id WHERE ci.item_id = (SELECT id FROM items WHERE locator = $1) ORDER BY c.name"#, locator).fetch_all(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+pub struct UserList {
+    pub slug: String,
+    pub name: String,
+    pub item_count: i64,
+}
+
+pub async fn get_user_lists(pool: &PgPool, username: &str) -> Result<Vec<UserList>, DatabaseError> {
+    query_as!(UserList, r#"SELECT l.slug, l.name, (SELECT COUNT(*) FROM user_list_items WHERE list_id = l.id) AS "item_count!" FROM user_lists l WHERE l.user_id = (SELECT id FROM users WHERE username = $1) ORDER BY l.name"#, username).fetch_all(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+pub async fn get_user_list(pool: &PgPool, username: &str, slug: &str) -> Result<Option<UserList>, DatabaseError> {
+    query_as!(UserList, r#"SELECT l.slug, l.name, (SELECT COUNT(*) FROM user_list_items WHERE list_id = l.id) AS "item_count!" FROM user_lists l WHERE l.user_id = (SELECT id FROM users WHERE username = $1) AND l.slug = $2"#, username, slug).fetch_optional(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+pub async fn add_user_list(pool: &PgPool, username: &str, slug: &str, name: &str) -> Result<(), DatabaseError> {
+    match query!("INSERT INTO user_lists(user_id, slug, name) VALUES((SELECT id FROM users WHERE username = $1), $2, $3)", username, slug, name.trim()).execute(pool).await {
+        Ok(_) => Ok(()),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => Err(DatabaseError::DuplicateList),
+        Err(e) => Err(DatabaseError::InternalError(Box::new(e))),
+    }
+}
+
+/// Renames a list, moving it to the slug of its new name.
+pub async fn rename_user_list(pool: &PgPool, username: &str, slug: &str, new_slug: &str, name: &str) -> Result<(), DatabaseError> {
+    match query!("UPDATE user_lists SET slug = $3, name = $4 WHERE user_id = (SELECT id FROM users WHERE username = $1) AND slug = $2", username, slug, new_slug, name.trim()).execute(pool).await {
+        Ok(_) => Ok(()),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => Err(DatabaseError::DuplicateList),
+        Err(e) => Err(DatabaseError::InternalError(Box::new(e))),
+    }
+}
+
+pub async fn remove_user_list(pool: &PgPool, username: &str, slug: &str) -> Result<(), DatabaseError> {
+    query!("DELETE FROM user_lists WHERE user_id = (SELECT id FROM users WHERE username = $1) AND slug = $2", username, slug).execute(pool).await.map(|_| ()).map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Items of a user's list in their order within it.
+pub async fn get_user_list_items(pool: &PgPool, username: &str, slug: &str) -> Result<Vec<Item>, DatabaseError> {
+    query_as!(Item, r#"SELECT s.locator AS "locator!", s.title AS "title!", s.description AS "description!", s.score AS "score!", s.review_count AS "review_count!", s.rank AS "rank!", s.popularity AS "popularity!", s.category_slug, s.category_name, s.category_rank AS "category_rank!", s.release_date, s.unreleased AS "unreleased!", s.locked AS "locked!", s.favorite_count AS "favorite_count!", s.cover FROM user_list_items li JOIN items_score s ON s.id = li.item_id WHERE li.list_id = (SELECT l.id FROM user_lists l JOIN users u ON u.id = l.user_id WHERE u.username = $1 AND l.slug = $2) ORDER BY li.position"#, username, slug).fetch_all(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Appends an item to the end of a user's list, keeping its place if it is already there.
+pub async fn add_user_list_item(pool: &PgPool, username: &str, slug: &str, locator: &str) -> Result<(), DatabaseError> {
+    query!("INSERT INTO user_list_items(list_id, item_id, position) SELECT l.id, i.id, COALESCE((SELECT MAX(position) FROM user_list_items WHERE list_id = l.id), 0) + 1 FROM user_lists l, items i WHERE l.user_id = (SELECT id FROM users WHERE username = $1) AND l.slug = $2 AND i.locator = $3 ON CONFLICT DO NOTHING", username, slug, locator).execute(pool).await.map(|_| ()).map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+pub async fn remove_user_list_item(pool: &PgPool, username: &str, slug: &str, locator: &str) -> Result<(), DatabaseError> {
+    query!("DELETE FROM user_list_items WHERE list_id = (SELECT l.id FROM user_lists l JOIN users u ON u.id = l.user_id WHERE u.username = $1 AND l.slug = $2) AND item_id = (SELECT id FROM items WHERE locator = $3)", username, slug, locator).execute(pool).await.map(|_| ()).map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Swaps an item with the one before it in a user's list, or the one after it when `earlier`
+/// is false, like [`move_collection_item`].
+pub async fn move_user_list_item(pool: &PgPool, username: &str, slug: &str, locator: &str, earlier: bool) -> Result<(), DatabaseError> {
+    let mut transaction = pool.begin().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    let Some(moved) = query!("SELECT li.list_id, li.item_id, li.position FROM user_list_items li WHERE li.list_id = (SELECT l.id FROM user_lists l JOIN users u ON u.id = l.user_id WHERE u.username = $1 AND l.slug = $2) AND li.item_id = (SELECT id FROM items WHERE locator = $3) FOR UPDATE", username, slug, locator).fetch_optional(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))? else {
+        return Ok(());
+    };
+    let neighbor = query!("SELECT item_id, position FROM user_list_items WHERE list_id = $1 AND CASE WHEN $3 THEN position < $2 ELSE position > $2 END ORDER BY CASE WHEN $3 THEN -position ELSE position END LIMIT 1 FOR UPDATE", moved.list_id, moved.position, earlier).fetch_optional(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    if let Some(neighbor) = neighbor {
+        query!("UPDATE user_list_items SET position = CASE item_id WHEN $2 THEN $5::INTEGER ELSE $3::INTEGER END WHERE list_id = $1 AND item_id IN ($2, $4)", moved.list_id, moved.item_id, moved.position, neighbor.item_id, neighbor.position).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    }
+    transaction.commit().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// One of the user's lists, offered in the add to list menu of an item.
+pub struct ItemList {
+    pub slug: String,
+    pub name: String,
+    /// Whether the item is already on the list.
+    pub listed: bool,
+}
+
+pub async fn get_item_lists(pool: &PgPool, locator: &str, username: &str) -> Result<Vec<ItemList>, DatabaseError> {
+    query_as!(ItemList, r#"SELECT l.slug, l.name, EXISTS(SELECT 1 FROM user_list_items WHERE list_id = l.id AND item_id = (SELECT id FROM items WHERE locator = $1)) AS "listed!" FROM user_lists l WHERE l.user_id = (SELECT id FROM users WHERE username = $2) ORDER BY l.name"#, locator, username).fetch_all(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Locks or unlocks the ratings of an item on behalf of an admin, recording it in the audit log.
+pub async fn set_item_locked(pool: &PgPool, locator: &str, locked: bool, moderator: &str) -> Result<(), DatabaseError> {
+    let mut transaction = pool.begin().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    query!("UPDATE items SET locked = $2 WHERE locator = $1", locator, locked).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    record_action(&mut *transaction, Some(moderator), if locked { "lock_item" } else { "unlock_item" }, locator, None, None).await?;
+    transaction.commit().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+pub async fn set_item_release_date(executor: impl PgExecutor<'_>, locator: &str, release_date: Option<NaiveDate>, unreleased: bool) -> Result<(), DatabaseError> {
+    query!("UPDATE items SET release_date = $2, unreleased = $3 WHERE locator = $1", locator, release_date, unreleased).execute(executor).await.map(|_| ()).map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+pub async fn get_item_tags(pool: &PgPool, locator: &str) -> Result<Vec<String>, DatabaseError> {
+    query_scalar!("SELECT t.name FROM tags t JOIN item_tags it ON it.tag_id = t.id WHERE it.item_id = (SELECT id FROM items WHERE locator = $1 LIMIT 1) ORDER BY t.name", locator).fetch_all(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Replaces the tags of an item, creating new tags and dropping ones no longer used by any item.
+pub async fn set_item_tags(connection: impl Acquire<'_, Database = Postgres>, locator: &str, tags: &[String]) -> Result<(), DatabaseError> {
+    let mut transaction = connection.begin().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    query!("INSERT INTO tags(name) SELECT UNNEST($1::VARCHAR[]) ON CONFLICT (name) DO NOTHING", tags).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    query!("DELETE FROM item_tags WHERE item_id = (SELECT id FROM items WHERE locator = $1 LIMIT 1)", locator).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    query!("INSERT INTO item_tags(item_id, tag_id) SELECT (SELECT id FROM items WHERE locator = $1 LIMIT 1), id FROM tags WHERE name = ANY($2)", locator, tags).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    query!("DELETE FROM tags WHERE NOT EXISTS (SELECT 1 FROM item_tags WHERE tag_id = tags.id)").execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    transaction.commit().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+#[derive(Clone)]
+pub struct TagCount {
+    pub name: String,
+    pub count: i64,
+}
+
+pub async fn get_tags(pool: &PgPool) -> Result<Vec<TagCount>, DatabaseError> {
+    query_as!(TagCount, r#"SELECT t.name, COUNT(*) AS "count!" FROM tags t JOIN item_tags it ON it.tag_id = t.id GROUP BY t.name ORDER BY COUNT(*) DESC, t.name"#).fetch_all(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct User {
+    pub username: String,
+    pub is_admin: bool,
+    pub avatar_hue: i16,
+    /// Name of the stored avatar, a colored placeholder is shown without it.
+    pub avatar: Option<String>,
+    /// Name of the glyph shown on the colored placeholder when there is no avatar.
+    pub avatar_glyph: String
+}
+
+// Written out because the derive cannot decode optional record fields.
+impl<'r> Decode<'r, Postgres> for User {
+    fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+        let mut decoder = PgRecordDecoder::new(value)?;
+        Ok(User {
+            username: decoder.try_decode()?,
+            is_admin: decoder.try_decode()?,
+            avatar_hue: decoder.try_decode()?,
+            avatar: decoder.try_decode()?,
+            avatar_glyph: decoder.try_decode()?,
+        })
+    }
+}
+
+pub async fn get_user(pool: &PgPool, username: &str) -> Result<Option<User>, DatabaseError> {
+    match query_as!(
+        User,
+        "SELECT username, is_admin, avatar_hue, avatar, avatar_glyph FROM users WHERE username = $1 LIMIT 1",
+        username
+    )
+    .fetch_one(pool)
+    .await
+    {
+        Ok(u) => Ok(Some(u)),
+        Err(e) => match e {
+            sqlx::Error::RowNotFound => Ok(None),
+            _ => Err(DatabaseError::InternalError(Box::new(e))),
+        },
+    }
+}
+
+/// The user with the id kept in their session, which stays the same when they are renamed.
+pub async fn get_user_by_id(pool: &PgPool, id: i32) -> Result<Option<User>, DatabaseError> {
+    query_as!(User, "SELECT username, is_admin, avatar_hue, avatar, avatar_glyph FROM users WHERE id = $1", id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+pub async fn get_user_id(pool: &PgPool, username: &str) -> Result<Option<i32>, DatabaseError> {
+    query_scalar!("SELECT id FROM users WHERE username = $1", username)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+pub async fn get_users(
+    pool: &PgPool,
+    page_number: Option<i32>,
+    per_page: i64,
+    query: Option<&str>,
+    after: Option<&str>,
+) -> Result<Option<Page<User>>, DatabaseError> {
+    let page_number = page_number.unwrap_or(0);
+    let params = page_params(&[("search", query), ("per_page", (per_page != default_per_page()).then(|| per_page.to_string()).as_deref())]);
+    // Searches are ranked by similarity, only the full listing is paged by cursor.
+    let after = after.filter(|_| query.is_none());
+    if let Some(cursor) = after {
+        let mut rows = query!("SELECT username, is_admin, avatar_hue, avatar, avatar_glyph FROM users WHERE NOT unlisted AND username > $1 ORDER BY username LIMIT $2", cursor, per_page + 1)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+        let has_next = rows.len() as i64 > per_page;
+        rows.truncate(per_page as usize);
+        let next = rows.last().filter(|_| has_next).map(|row| row.username.clone());
+        if rows.is_empty() {
+            return Ok(None);
+        }
+        let items = rows.into_iter().map(|row| User { username: row.username, is_admin: row.is_admin, avatar_hue: row.avatar_hue, avatar: row.avatar, avatar_glyph: row.avatar_glyph }).collect();
+        return Ok(Some(Page {
+            target: routes::url::path(routes::USERS),
+            items,
+            current_page: 0,
+            number_of_pages: 0,
+            per_page,
+            params,
+            keyset: Some(Keyset { after: after.map(str::to_owned), next }),
+        }));
+    }
+    if !(0..MAX_NUMBERED_PAGES).contains(&page_number) {
+        return Ok(None);
+    }
+    let rows = if let Some(query) = query {
+        query!(
+        r#"SELECT username, is_admin, avatar_hue, avatar, avatar_glyph, COUNT(*) OVER () AS "total!" FROM users WHERE username % $1 AND NOT unlisted ORDER BY SIMILARITY(username,$1) DESC, username LIMIT $3 OFFSET $3::BIGINT * $2::INT"#,
+        query,
+        page_number,
+        per_page
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
+        .into_iter()
+        .map(|row| (User { username: row.username, is_admin: row.is_admin, avatar_hue: row.avatar_hue, avatar: row.avatar, avatar_glyph: row.avatar_glyph }, row.total))
+        .collect::<Vec<_>>()
+    } else {
+        query!(
+            r#"SELECT username, is_admin, avatar_hue, avatar, avatar_glyph, COUNT(*) OVER () AS "total!" FROM users WHERE NOT unlisted ORDER BY username LIMIT $2 OFFSET $2::BIGINT * $1::INT"#,
+            page_number,
+            per_page
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
+        .into_iter()
+        .map(|row| (User { username: row.username, is_admin: row.is_admin, avatar_hue: row.avatar_hue, avatar: row.avatar, avatar_glyph: row.avatar_glyph }, row.total))
+        .collect()
+    };
+    // Pages past the end come back empty, without a count to tell how many there are.
+    let Some(&(_, total)) = rows.first() else {
+        return Ok(None);
+    };
+    let number_of_pages = (total as usize).div_ceil(per_page as usize) as i32;
+    let keyset = (query.is_none() && page_number == 0 && number_of_pages > MAX_NUMBERED_PAGES).then(|| Keyset {
+        after: None,
+        next: rows.last().map(|(user, _)| user.username.clone()),
+    });
+    Ok(Some(Page {
+        target: routes::url::path(routes::USERS),
+        items: rows.into_iter().map(|(user, _)| user).collect(),
+        current_page: page_number,
+        number_of_pages: number_of_pages.min(MAX_NUMBERED_PAGES),
+        per_page,
+        params,
+        keyset,
+    }))
+}
+
+/// Other items a user may rate or rerate within an hour.
+pub const RATINGS_PER_HOUR: i64 = 30;
+/// Seconds before a user may change their rating of the same item again.
+pub const RATING_COOLDOWN_SECONDS: f64 = 30.0;
+
+pub async fn rate_item(
+    pool: &PgPool,
+    username: &str,
+    item_locator: &str,
+    rating: i16,
+    body: Option<&str>,
+    spoiler: Option<bool>,
+    private: Option<bool>,
+) -> Result<(), DatabaseError> {
+    let rating = rating.clamp(1, 10);
+    let mut transaction = pool.begin().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    // Locks the user until the rating is written, so that their concurrent ratings are counted one after another.
+    let user_id = query_scalar!("SELECT id FROM users WHERE username=$1 FOR UPDATE", username).fetch_one(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    let limits = query!(r#"SELECT (SELECT COUNT(*) FROM reviews WHERE user_id=$2 AND item_id<>(SELECT id FROM items WHERE locator=$1 LIMIT 1) AND date > now() - INTERVAL '1 hour') AS "recent!", EXISTS(SELECT 1 FROM reviews WHERE item_id=(SELECT id FROM items WHERE locator=$1 LIMIT 1) AND user_id=$2 AND rating<>$3 AND date > now() - make_interval(secs => $4)) AS "too_soon!", EXISTS(SELECT 1 FROM items WHERE locator=$1 AND unreleased) AS "unreleased!", EXISTS(SELECT 1 FROM items WHERE locator=$1 AND locked) AS "locked!""#,item_locator,user_id,rating,RATING_COOLDOWN_SECONDS).fetch_one(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    if limits.unreleased {
+        return Err(DatabaseError::Unreleased);
+    }
+    if limits.locked {
+        return Err(DatabaseError::Locked);
+    }
+    if limits.recent >= RATINGS_PER_HOUR || limits.too_soon {
+        return Err(DatabaseError::RateLimited);
+    }
+    query!("INSERT INTO reviews(item_id, user_id, rating, body, spoiler, private) VALUES((SELECT id FROM items WHERE locator=$1 LIMIT 1), $2, $3, NULLIF(TRIM($4), ''), COALESCE($5, FALSE), COALESCE($6, FALSE)) ON CONFLICT (item_id, user_id) DO UPDATE SET rating=EXCLUDED.rating, date=now(), body=CASE WHEN $4::TEXT IS NULL THEN reviews.body ELSE EXCLUDED.body END, spoiler=COALESCE($5, reviews.spoiler), private=COALESCE($6, reviews.private)",item_locator,user_id,rating,body,spoiler,private).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    transaction.commit().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Whether a user's rating of an item can be seen by everyone, false when there is none.
+pub async fn is_rating_public(pool: &PgPool, locator: &str, username: &str) -> Result<bool, DatabaseError> {
+    query_scalar!(r#"SELECT EXISTS(SELECT 1 FROM reviews r JOIN users u ON r.user_id = u.id WHERE r.item_id = (SELECT id FROM items WHERE locator = $1 LIMIT 1) AND u.username = $2 AND NOT r.private AND NOT u.private_ratings) AS "public!""#, locator, username).fetch_one(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+pub async fn remove_review(pool: &PgPool, locator:&str, username: &str) ->Result<(), DatabaseError>{
+    query!("DELETE FROM reviews WHERE item_id=(SELECT id FROM items WHERE locator=$1 LIMIT 1) AND user_id=(SELECT id FROM users WHERE username=$2)",locator, username).execute(pool).await.map(|_|()).map_err(|e|DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Removes another user's review on behalf of an admin, recording it in the audit log. Returns
+/// whether there was a review to remove.
+pub async fn moderate_review(pool: &PgPool, locator: &str, username: &str, moderator: &str) -> Result<bool, DatabaseError> {
+    let mut transaction = pool.begin().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    let removed = query!("DELETE FROM reviews WHERE item_id=(SELECT id FROM items WHERE locator=$1 LIMIT 1) AND user_id=(SELECT id FROM users WHERE username=$2)", locator, username).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?.rows_affected() > 0;
+    if removed {
+        record_action(&mut *transaction, Some(moderator), "remove_review", &format!("{locator}/{username}"), None, None).await?;
+    }
+    transaction.commit().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    Ok(removed)
+}
+
+pub async fn get_item_rating(pool: &PgPool, locator:&str, username: &str) -> Result<Option<i16>, DatabaseError> {
+    match query_scalar!("SELECT rating FROM reviews WHERE item_id=(SELECT id FROM items WHERE locator=$1 LIMIT 1) AND user_id=(SELECT id FROM users WHERE username=$2) LIMIT 1",locator,username).fetch_one(pool).await {
+        Ok(r) => Ok(Some(r)),
+        Err(e) => match e {
+            sqlx::Error::RowNotFound => Ok(None),
+            _ => Err(DatabaseError::InternalError(Box::new(e))),
+        },
+    }
+}
+
+pub struct Review {
+    pub rating: i16,
+    pub body: Option<String>,
+    pub spoiler: bool,
+    pub private: bool
+}
+
+pub async fn get_item_review(pool: &PgPool, locator:&str, username: &str) -> Result<Option<Review>, DatabaseError> {
+    match query_as!(Review, "SELECT rating, body, spoiler, private FROM reviews WHERE item_id=(SELECT id FROM items WHERE locator=$1 LIMIT 1) AND user_id=(SELECT id FROM users WHERE username=$2) LIMIT 1",locator,username).fetch_one(pool).await {
+        Ok(r) => Ok(Some(r)),
+        Err(e) => match e {
+            sqlx::Error::RowNotFound => Ok(None),
+            _ => Err(DatabaseError::InternalError(Box::new(e))),
+        },
+    }
+}
+
+pub async fn is_subscribed(pool: &PgPool, locator: &str, username: &str) -> Result<bool, DatabaseError> {
+    query_scalar!(r#"SELECT EXISTS(SELECT 1 FROM item_subscriptions WHERE item_id=(SELECT id FROM items WHERE locator=$1 LIMIT 1) AND user_id=(SELECT id FROM users WHERE username=$2 LIMIT 1)) AS "subscribed!""#, locator, username).fetch_one(pool).await.map_err(|e|DatabaseError::InternalError(Box::new(e)))
+}
+
+pub async fn set_subscription(pool: &PgPool, locator: &str, username: &str, subscribed: bool) -> Result<(), DatabaseError> {
+    if subscribed {
+        query!("INSERT INTO item_subscriptions(item_id, user_id) VALUES((SELECT id FROM items WHERE locator=$1 LIMIT 1), (SELECT id FROM users WHERE username=$2 LIMIT 1)) ON CONFLICT DO NOTHING", locator, username).execute(pool).await
+    } else {
+        query!("DELETE FROM item_subscriptions WHERE item_id=(SELECT id FROM items WHERE locator=$1 LIMIT 1) AND user_id=(SELECT id FROM users WHERE username=$2 LIMIT 1)", locator, username).execute(pool).await
+    }.map(|_|()).map_err(|e|DatabaseError::InternalError(Box::new(e)))
+}
+
+pub async fn is_favorite(pool: &PgPool, locator: &str, username: &str) -> Result<bool, DatabaseError> {
+    query_scalar!(r#"SELECT EXISTS(SELECT 1 FROM favorites WHERE item_id=(SELECT id FROM items WHERE locator=$1 LIMIT 1) AND user_id=(SELECT id FROM users WHERE username=$2 LIMIT 1)) AS "favorite!""#, locator, username).fetch_one(pool).await.map_err(|e|DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Adds or removes the item from the user's favorites, returning how many users now favor it.
+pub async fn set_favorite(pool: &PgPool, locator: &str, username: &str, favorite: bool) -> Result<i64, DatabaseError> {
+    if favorite {
+        query!("INSERT INTO favorites(item_id, user_id) VALUES((SELECT id FROM items WHERE locator=$1 LIMIT 1), (SELECT id FROM users WHERE username=$2 LIMIT 1)) ON CONFLICT DO NOTHING", locator, username).execute(pool).await
+    } else {
+        query!("DELETE FROM favorites WHERE item_id=(SELECT id FROM items WHERE locator=$1 LIMIT 1) AND user_id=(SELECT id FROM users WHERE username=$2 LIMIT 1)", locator, username).execute(pool).await
+    }.map_err(|e|DatabaseError::InternalError(Box::new(e)))?;
+    query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM favorites WHERE item_id=(SELECT id FROM items WHERE locator=$1 LIMIT 1)"#, locator).fetch_one(pool).await.map_err(|e|DatabaseError::InternalError(Box::new(e)))
+}
+
+pub struct Favorite {
+    pub locator: String,
+    pub title: String,
+    pub cover: Option<String>,
+}
+
+/// Items the user favored, most recently favored first.
+pub async fn get_user_favorites(pool: &PgPool, username: &str) -> Result<Vec<Favorite>, DatabaseError> {
+    query_as!(Favorite, "SELECT i.locator, i.title, i.cover FROM favorites f JOIN items i ON i.id = f.item_id WHERE f.user_id = (SELECT id FROM users WHERE username = $1 LIMIT 1) ORDER BY f.date DESC", username).fetch_all(pool).await.map_err(|e|DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Notifies subscribers of an item about a text review, once per review author.
+pub async fn notify_subscribers(pool: &PgPool, locator: &str, author: &str) -> Result<(), DatabaseError> {
+    query!("INSERT INTO notifications(user_id, item_id, author_id) SELECT s.user_id, r.item_id, r.user_id FROM reviews r JOIN users u ON r.user_id = u.id JOIN item_subscriptions s ON s.item_id = r.item_id WHERE r.item_id = (SELECT id FROM items WHERE locator = $1 LIMIT 1) AND u.username = $2 AND r.body IS NOT NULL AND NOT r.private AND NOT u.private_ratings AND s.user_id <> r.user_id AND NOT EXISTS (SELECT 1 FROM notifications n WHERE n.user_id = s.user_id AND n.item_id = r.item_id AND n.author_id = r.user_id)", locator, author).execute(pool).await.map(|_|()).map_err(|e|DatabaseError::InternalError(Box::new(e)))
+}
+
+pub struct Notification
+{
+    pub locator: String,
+    pub title: String,
+    pub author: User,
+    pub date: NaiveDateTime,
+    pub read: bool
+}
+
+/// Latest notifications of a user, marking them as read.
+pub async fn take_notifications(pool: &PgPool, username: &str) -> Result<Vec<Notification>, DatabaseError> {
+    let notifications = query_as!(Notification, r#"SELECT i.locator, i.title, (u.username, u.is_admin, u.avatar_hue, u.avatar, u.avatar_glyph) AS "author!: User", n.date, n.read FROM notifications n JOIN items i ON n.item_id = i.id JOIN users u ON n.author_id = u.id WHERE n.user_id = (SELECT id FROM users WHERE username = $1 LIMIT 1) ORDER BY n.date DESC LIMIT 50"#, username).fetch_all(pool).await.map_err(|e|DatabaseError::InternalError(Box::new(e)))?;
+    query!("UPDATE notifications SET read = TRUE WHERE user_id = (SELECT id FROM users WHERE username = $1 LIMIT 1) AND NOT read", username).execute(pool).await.map_err(|e|DatabaseError::InternalError(Box::new(e)))?;
+    Ok(notifications)
+}
+
+pub struct RatingItem
+{
+    pub user: User,
+    pub rating: i16,
+    pub date: NaiveDateTime,
+    pub body: Option<String>,
+    pub spoiler: bool,
+    pub private: bool,
+    pub reply_count: i64,
+    /// Counts of each reaction, in the order of [`reactions::REACTIONS`].
+    pub reaction_counts: Vec<i64>,
+    pub own_reactions: Vec<String>,
+    pub badges: Vec<String>,
+}
+
+/// Ratings of an item, leaving out private ones unless they belong to the viewer, and ones of
+/// users requiring a login when there is no viewer.
+pub async fn get_item_ratings(pool: &PgPool, page_number: Option<i32>, locator: &str, viewer: Option<&str>)
+ -> Result<Option<Page<RatingItem>>, DatabaseError> {
+    let page_number = page_number.unwrap_or(0);
+    if page_number < 0 {
+        return Ok(None);
+    }
+    let rows = query!(r#"SELECT (u.username, u.is_admin, u.avatar_hue, u.avatar, u.avatar_glyph) AS "user!: User", rating, date, body, spoiler, r.private OR u.private_ratings AS "private!", (SELECT COUNT(*) FROM review_replies WHERE review_id = r.id) AS "reply_count!", count_reactions(ARRAY(SELECT reaction FROM review_reactions WHERE review_id = r.id), $4) AS "reaction_counts!", ARRAY(SELECT rr.reaction FROM review_reactions rr JOIN users ru ON ru.id = rr.user_id WHERE rr.review_id = r.id AND ru.username = $3) AS "own_reactions!", ARRAY(SELECT badge FROM user_badges WHERE user_id = u.id ORDER BY date, badge) AS "badges!", COUNT(*) OVER () AS "total!" FROM reviews r JOIN users u ON r.user_id = u.id WHERE r.item_id = (SELECT id FROM items WHERE locator = $1 LIMIT 1) AND ((NOT r.private AND NOT u.private_ratings) OR u.username = $3) AND (NOT u.login_required OR $3 IS NOT NULL) ORDER BY date DESC, r.id DESC LIMIT 3 OFFSET 3 * $2"#,locator,page_number,viewer,&reactions::names() as &[&str]).fetch_all(pool).await.map_err(|e|DatabaseError::InternalError(Box::new(e)))?;
+    let Some(total) = rows.first().map(|row| row.total) else {
+        return Ok(None);
+    };
+    Ok(Some(Page {
+        target: routes::url::item(locator),
+        items: rows.into_iter().map(|row| RatingItem { user: row.user, rating: row.rating, date: row.date, body: row.body, spoiler: row.spoiler, private: row.private, reply_count: row.reply_count, reaction_counts: row.reaction_counts, own_reactions: row.own_reactions, badges: row.badges }).collect(),
+        current_page: page_number,
+        number_of_pages: (total as usize).div_ceil(3) as i32,
+        per_page: 3,
+        params: Vec::new(),
+        keyset: None,
+    }))
+}
+
+pub struct ReviewEntry
+{
+    pub locator: String,
+    pub title: String,
+    pub user: User,
+    pub rating: i16,
+    pub date: NaiveDateTime,
+    pub body: String,
+    pub spoiler: bool
+}
+
+/// Newest text reviews across all items, leaving out private ones unless the viewer wrote them,
+/// and those of users who only show their ratings to logged in users from anonymous viewers.
+pub async fn get_reviews(pool: &PgPool, page_number: Option<i32>, search: Option<&str>, min_score: Option<i16>, tag: Option<&str>, viewer: Option<&str>)
+ -> Result<Option<Page<ReviewEntry>>, DatabaseError> {
+    let page_number = page_number.unwrap_or(0);
+    let number_of_pages = 
+        (query_scalar!("SELECT COUNT(*) FROM reviews r JOIN users u ON r.user_id = u.id WHERE r.body IS NOT NULL AND ((NOT r.private AND NOT u.private_ratings) OR u.username = $1) AND (NOT u.login_required OR $1 IS NOT NULL) AND ($2::TEXT IS NULL OR to_tsvector('english', r.body) @@ websearch_to_tsquery('english', $2)) AND ($3::SMALLINT IS NULL OR r.rating >= $3) AND ($4::TEXT IS NULL OR EXISTS (SELECT 1 FROM item_tags it JOIN tags t ON t.id = it.tag_id WHERE it.item_id = r.item_id AND t.name = $4))", viewer, search, min_score, tag)
+            .fetch_one(pool)
+            .await
+            .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
+            .unwrap_or_default() as usize)
+            .div_ceil(10) as i32;
+    if (0..number_of_pages).contains(&page_number) {
+        let page = 
+    query_as!(ReviewEntry, r#"SELECT i.locator, i.title, (u.username, u.is_admin, u.avatar_hue, u.avatar, u.avatar_glyph) AS "user!: User", r.rating, r.date, r.body AS "body!", r.spoiler FROM reviews r JOIN users u ON r.user_id = u.id JOIN items i ON r.item_id = i.id WHERE r.body IS NOT NULL AND ((NOT r.private AND NOT u.private_ratings) OR u.username = $2) AND (NOT u.login_required OR $2 IS NOT NULL) AND ($3::TEXT IS NULL OR to_tsvector('english', r.body) @@ websearch_to_tsquery('english', $3)) AND ($4::SMALLINT IS NULL OR r.rating >= $4) AND ($5::TEXT IS NULL OR EXISTS (SELECT 1 FROM item_tags it JOIN tags t ON t.id = it.tag_id WHERE it.item_id = r.item_id AND t.name = $5)) ORDER BY r.date DESC, r.id DESC LIMIT 10 OFFSET 10 * $1"#, page_number, viewer, search, min_score, tag).fetch_all(pool).await.map_err(|e|DatabaseError::InternalError(Box::new(e)))?;
+        let min_score = min_score.map(|score| score.to_string());
+        Ok(Some(Page {
+            target: routes::url::path(routes::REVIEWS),
+            items: page,
+            current_page: page_number,
+            number_of_pages,
+            per_page: 10,
+            params: page_params(&[("search", search), ("score", min_score.as_deref()), ("tag", tag)]),
+            keyset: None,
+        }))
+    } else {
+        Ok(None)
+    }
+}
+
+pub struct Reply
+{
+    pub id: i32,
+    pub user: User,
+    pub body: String,
+    pub date: NaiveDateTime,
+    /// Counts of each reaction, in the order of [`reactions::REACTIONS`].
+    pub reaction_counts: Vec<i64>,
+    pub own_reactions: Vec<String>
+}
+
+pub async fn get_review_replies(pool: &PgPool, locator: &str, review_username: &str, viewer: Option<&str>) -> Result<Vec<Reply>, DatabaseError> {
+    query_as!(Reply, r#"SELECT rr.id, (u.username, u.is_admin, u.avatar_hue, u.avatar, u.avatar_glyph) AS "user!: User", rr.body, rr.date, count_reactions(ARRAY(SELECT reaction FROM reply_reactions WHERE reply_id = rr.id), $4) AS "reaction_counts!", ARRAY(SELECT re.reaction FROM reply_reactions re JOIN users ru ON ru.id = re.user_id WHERE re.reply_id = rr.id AND ru.username = $3) AS "own_reactions!" FROM review_replies rr JOIN users u ON rr.user_id = u.id WHERE rr.review_id = (SELECT id FROM reviews WHERE item_id = (SELECT id FROM items WHERE locator = $1 LIMIT 1) AND user_id = (SELECT id FROM users WHERE username = $2 LIMIT 1)) ORDER BY rr.date"#, locator, review_username, viewer, &reactions::names() as &[&str]).fetch_all(pool).await.map_err(|e|DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Reactions left on a review or reply.
+pub struct Reactions {
+    /// Counts of each reaction, in the order of [`reactions::REACTIONS`].
+    pub counts: Vec<i64>,
+    pub own: Vec<String>,
+}
+
+/// Adds the reaction to a review visible to the user, or takes it back if already left.
+/// Returns the updated reactions, or nothing when there is no such review.
+pub async fn toggle_review_reaction(pool: &PgPool, locator: &str, review_username: &str, username: &str, reaction: &str) -> Result<Option<Reactions>, DatabaseError> {
+    let mut transaction = pool.begin().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    let Some(review) = query_scalar!("SELECT r.id FROM reviews r JOIN users u ON r.user_id = u.id WHERE r.item_id = (SELECT id FROM items WHERE locator = $1 LIMIT 1) AND u.username = $2 AND ((NOT r.private AND NOT u.private_ratings) OR u.username = $3)", locator, review_username, username).fetch_optional(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))? else {
+        return Ok(None);
+    };
+    let removed = query!("DELETE FROM review_reactions WHERE review_id = $1 AND user_id = (SELECT id FROM users WHERE username = $2) AND reaction = $3", review, username, reaction).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?.rows_affected();
+    if removed == 0 {
+        query!("INSERT INTO review_reactions(review_id, user_id, reaction) VALUES($1, (SELECT id FROM users WHERE username = $2), $3)", review, username, reaction).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    }
+    let reactions = query_as!(Reactions, r#"SELECT count_reactions(ARRAY(SELECT reaction FROM review_reactions WHERE review_id = $1), $3) AS "counts!", ARRAY(SELECT rr.reaction FROM review_reactions rr JOIN users u ON u.id = rr.user_id WHERE rr.review_id = $1 AND u.username = $2) AS "own!""#, review, username, &reactions::names() as &[&str]).fetch_one(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    transaction.commit().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    Ok(Some(reactions))
+}
+
+/// Adds the reaction to a reply under the given review, or takes it back if already left.
+/// Returns the updated reactions, or nothing when there is no such reply.
+pub async fn toggle_reply_reaction(pool: &PgPool, locator: &str, review_username: &str, reply: i32, username: &str, reaction: &str) -> Result<Option<Reactions>, DatabaseError> {
+    let mut transaction = pool.begin().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    let exists = query_scalar!(r#"SELECT EXISTS(SELECT 1 FROM review_replies rr JOIN reviews r ON r.id = rr.review_id JOIN users u ON r.user_id = u.id WHERE rr.id = $1 AND r.item_id = (SELECT id FROM items WHERE locator = $2 LIMIT 1) AND u.username = $3 AND ((NOT r.private AND NOT u.private_ratings) OR u.username = $4)) AS "exists!""#, reply, locator, review_username, username).fetch_one(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    if !exists {
+        return Ok(None);
+    }
+    let removed = query!("DELETE FROM reply_reactions WHERE reply_id = $1 AND user_id = (SELECT id FROM users WHERE username = $2) AND reaction = $3", reply, username, reaction).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?.rows_affected();
+    if removed == 0 {
+        query!("INSERT INTO reply_reactions(reply_id, user_id, reaction) VALUES($1, (SELECT id FROM users WHERE username = $2), $3)", reply, username, reaction).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    }
+    let reactions = query_as!(Reactions, r#"SELECT count_reactions(ARRAY(SELECT reaction FROM reply_reactions WHERE reply_id = $1), $3) AS "counts!", ARRAY(SELECT re.reaction FROM reply_reactions re JOIN users u ON u.id = re.user_id WHERE re.reply_id = $1 AND u.username = $2) AS "own!""#, reply, username, &reactions::names() as &[&str]).fetch_one(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    transaction.commit().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    Ok(Some(reactions))
+}
+
+pub async fn add_review_reply(pool: &PgPool, locator: &str, review_username: &str, username: &str, body: &str) -> Result<(), DatabaseError> {
+    if body.trim().is_empty() {
+        return Err(DatabaseError::EmptyFields);
+    }
+    query!("INSERT INTO review_replies(review_id, user_id, body) VALUES((SELECT id FROM reviews WHERE item_id = (SELECT id FROM items WHERE locator = $1 LIMIT 1) AND user_id = (SELECT id FROM users WHERE username = $2 LIMIT 1)), (SELECT id FROM users WHERE username = $3 LIMIT 1), $4)", locator, review_username, username, body.trim()).execute(pool).await.map(|_|()).map_err(|e|DatabaseError::InternalError(Box::new(e)))
+}
+
+pub struct Comment {
+    pub id: i32,
+    pub parent_id: Option<i32>,
+    pub user: User,
+    pub body: String,
+    pub date: NaiveDateTime,
+}
+
+/// Discussion of an item, paged by thread. Threads come newest first, each followed by its
+/// replies in the order they were written.
+pub async fn get_item_comments(pool: &PgPool, page_number: Option<i32>, locator: &str) -> Result<Option<Page<Comment>>, DatabaseError> {
+    let page_number = page_number.unwrap_or(0);
+    let number_of_pages = (query_scalar!("SELECT COUNT(*) FROM comments WHERE item_id = (SELECT id FROM items WHERE locator = $1 LIMIT 1) AND parent_id IS NULL", locator).fetch_one(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?.unwrap_or_default() as usize).div_ceil(10) as i32;
+    if (0..number_of_pages).contains(&page_number) {
+        let items = query_as!(Comment, r#"WITH RECURSIVE thread(id, path) AS (SELECT id, ARRAY[-id] FROM (SELECT id FROM comments WHERE item_id = (SELECT id FROM items WHERE locator = $1 LIMIT 1) AND parent_id IS NULL ORDER BY id DESC LIMIT 10 OFFSET 10 * $2) t UNION ALL SELECT c.id, t.path || c.id FROM comments c JOIN thread t ON c.parent_id = t.id) SELECT c.id, c.parent_id, (u.username, u.is_admin, u.avatar_hue, u.avatar, u.avatar_glyph) AS "user!: User", c.body, c.date FROM thread t JOIN comments c ON c.id = t.id JOIN users u ON c.user_id = u.id ORDER BY t.path"#, locator, page_number).fetch_all(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+        Ok(Some(Page { target: routes::url::item_discussion(locator), items, current_page: page_number, number_of_pages, per_page: 10, params: Vec::new(), keyset: None }))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Adds a comment to the discussion of an item, or a reply to one of its comments. Returns
+/// whether the item and the replied to comment exist.
+pub async fn add_comment(pool: &PgPool, locator: &str, username: &str, body: &str, parent: Option<i32>) -> Result<bool, DatabaseError> {
+    if body.trim().is_empty() {
+        return Err(DatabaseError::EmptyFields);
+    }
+    query!("INSERT INTO comments(item_id, user_id, parent_id, body) SELECT i.id, (SELECT id FROM users WHERE username = $2 LIMIT 1), $4, $3 FROM items i WHERE i.locator = $1 AND ($4::INTEGER IS NULL OR EXISTS(SELECT 1 FROM comments WHERE id = $4 AND item_id = i.id))", locator, username, body.trim(), parent).execute(pool).await.map(|result| result.rows_affected() > 0).map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Removes a comment along with its replies, returning whether the user was allowed to.
+/// Removes a comment by its author or an admin, recording it in the audit log when an admin
+/// removed someone else's.
+pub async fn remove_comment(pool: &PgPool, locator: &str, id: i32, username: &str) -> Result<bool, DatabaseError> {
+    let mut transaction = pool.begin().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    let Some(removed) = query!(r#"DELETE FROM comments c WHERE id = $1 AND item_id = (SELECT id FROM items WHERE locator = $2 LIMIT 1) AND (user_id = (SELECT id FROM users WHERE username = $3) OR (SELECT is_admin FROM users WHERE username = $3)) RETURNING (SELECT username FROM users WHERE id = c.user_id) AS "author!", body"#, id, locator, username).fetch_optional(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))? else {
+        return Ok(false);
+    };
+    if removed.author != username {
+        record_action(&mut *transaction, Some(username), "remove_comment", &format!("{locator}/{}", removed.author), Some(&json!({ "body": removed.body })), None).await?;
+    }
+    transaction.commit().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    Ok(true)
+}
+
+/// Removes a reply to a review by its author or an admin, recording it in the audit log when an
+/// admin removed someone else's.
+pub async fn remove_review_reply(pool: &PgPool, id: i32, username: &str) -> Result<(), DatabaseError> {
+    let mut transaction = pool.begin().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    let Some(removed) = query!(r#"DELETE FROM review_replies rr WHERE id = $1 AND (user_id = (SELECT id FROM users WHERE username = $2) OR (SELECT is_admin FROM users WHERE username = $2)) RETURNING (SELECT i.locator FROM reviews r JOIN items i ON i.id = r.item_id WHERE r.id = rr.review_id) AS "locator!", (SELECT username FROM users WHERE id = rr.user_id) AS "author!", body"#, id, username).fetch_optional(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))? else {
+        return Ok(());
+    };
+    if removed.author != username {
+        record_action(&mut *transaction, Some(username), "remove_reply", &format!("{}/{}", removed.locator, removed.author), Some(&json!({ "body": removed.body })), None).await?;
+    }
+    transaction.commit().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+pub struct RatingUser
+{
+    pub item: Item,
+    pub rating: i16,
+    pub date: NaiveDateTime,
+    pub private: bool
+}
+
+/// Ratings of a user, leaving out private ones unless the viewer is that user.
+pub async fn get_user_ratings(pool: &PgPool, page_number: Option<i32>, username: &str, viewer: Option<&str>)
+ -> Result<Option<Page<RatingUser>>, DatabaseError> {
+    let page_number = page_number.unwrap_or(0);
+    let number_of_pages = 
+        (query_scalar!("SELECT COUNT(*) FROM reviews r JOIN users u ON r.user_id = u.id WHERE u.username = $1 AND ((NOT r.private AND NOT u.private_ratings) OR u.username = $2)", username, viewer)
+            .fetch_one(pool)
+            .await
+            .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
+            .unwrap_or_default() as usize)
+            .div_ceil(3) as i32;
+    if (0..number_of_pages).contains(&page_number) {
+        let page = 
+    query_as!(RatingUser, r#"SELECT (i.locator, i.title, i.description, i.score, i.review_count, i.rank, i.popularity, i.category_slug, i.category_name, i.category_rank, i.release_date, i.unreleased, i.locked, i.favorite_count, i.cover) AS "item!: Item", rating, date, r.private OR u.private_ratings AS "private!" FROM reviews r JOIN items_score i ON r.item_id = i.id JOIN users u ON r.user_id = u.id WHERE u.username = $1 AND ((NOT r.private AND NOT u.private_ratings) OR u.username = $3) ORDER BY date DESC, r.id DESC LIMIT 3 OFFSET 3 * $2"#,username,page_number,viewer).fetch_all(pool).await.map_err(|e|DatabaseError::InternalError(Box::new(e)))?;
+        Ok(Some(Page {
+            target: routes::url::user(username),
+            items: page,
+            current_page: page_number,
+            number_of_pages,
+            per_page: 3,
+            params: Vec::new(),
+            keyset: None,
+        }))
+    } else {
+        Ok(None)
+    }
+}
+
+pub async fn is_following(pool: &PgPool, follower: &str, followed: &str) -> Result<bool, DatabaseError> {
+    query_scalar!(r#"SELECT EXISTS(SELECT 1 FROM follows WHERE follower_id=(SELECT id FROM users WHERE username=$1 LIMIT 1) AND followed_id=(SELECT id FROM users WHERE username=$2 LIMIT 1)) AS "following!""#, follower, followed).fetch_one(pool).await.map_err(|e|DatabaseError::InternalError(Box::new(e)))
+}
+
+pub async fn set_following(pool: &PgPool, follower: &str, followed: &str, following: bool) -> Result<(), DatabaseError> {
+    if following {
+        query!("INSERT INTO follows(follower_id, followed_id) SELECT f.id, u.id FROM users f, users u WHERE f.username=$1 AND u.username=$2 ON CONFLICT DO NOTHING", follower, followed).execute(pool).await
+    } else {
+        query!("DELETE FROM follows WHERE follower_id=(SELECT id FROM users WHERE username=$1 LIMIT 1) AND followed_id=(SELECT id FROM users WHERE username=$2 LIMIT 1)", follower, followed).execute(pool).await
+    }.map(|_|()).map_err(|e|DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Rating or list addition of a followed user.
+pub struct FeedEntry {
+    pub username: String,
+    pub locator: String,
+    pub title: String,
+    /// Set on ratings.
+    pub rating: Option<i16>,
+    /// Set on items added to a list.
+    pub list_slug: Option<String>,
+    pub list_name: Option<String>,
+    pub date: NaiveDateTime,
+}
+
+/// Public ratings and list additions of the users the user follows, newest first.
+pub async fn get_feed(pool: &PgPool, page_number: Option<i32>, username: &str) -> Result<Option<Page<FeedEntry>>, DatabaseError> {
+    let page_number = page_number.unwrap_or(0);
+    let number_of_pages = (query_scalar!("SELECT (SELECT COUNT(*) FROM reviews r JOIN users u ON r.user_id = u.id JOIN follows f ON f.followed_id = u.id WHERE f.follower_id = (SELECT id FROM users WHERE username = $1) AND NOT r.private AND NOT u.private_ratings AND NOT u.hidden_ratings) + (SELECT COUNT(*) FROM user_list_items li JOIN user_lists l ON li.list_id = l.id JOIN follows f ON f.followed_id = l.user_id WHERE f.follower_id = (SELECT id FROM users WHERE username = $1))", username)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
+        .unwrap_or_default() as usize)
+        .div_ceil(10) as i32;
+    if (0..number_of_pages).contains(&page_number) {
+        let page = query_as!(FeedEntry, r#"SELECT u.username AS "username!", i.locator AS "locator!", i.title AS "title!", r.rating AS "rating?", NULL::VARCHAR AS list_slug, NULL::VARCHAR AS list_name, r.date AS "date!" FROM reviews r JOIN users u ON r.user_id = u.id JOIN items i ON r.item_id = i.id JOIN follows f ON f.followed_id = u.id WHERE f.follower_id = (SELECT id FROM users WHERE username = $1) AND NOT r.private AND NOT u.private_ratings AND NOT u.hidden_ratings UNION ALL SELECT u.username, i.locator, i.title, NULL, l.slug, l.name, li.date FROM user_list_items li JOIN user_lists l ON li.list_id = l.id JOIN users u ON l.user_id = u.id JOIN items i ON li.item_id = i.id JOIN follows f ON f.followed_id = u.id WHERE f.follower_id = (SELECT id FROM users WHERE username = $1) ORDER BY 7 DESC, 1, 2, 5 LIMIT 10 OFFSET 10 * $2"#, username, page_number).fetch_all(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+        Ok(Some(Page {
+            target: routes::url::path(routes::FEED),
+            items: page,
+            current_page: page_number,
+            number_of_pages,
+            per_page: 10,
+            params: Vec::new(),
+            keyset: None,
+        }))
+    } else {
+        Ok(None)
+    }
+}
+
+#[derive(Default)]
+pub struct UserStats {
+    pub review_count: i64,
+    pub mean_score: f32,
+    pub distribution: Vec<(String, i64)>,
+    pub per_month: Vec<(String, i64)>,
+}
+
+/// Rating statistics of a user, leaving out private ratings unless the viewer is that user.
+pub async fn get_user_stats(pool: &PgPool, username: &str, viewer: Option<&str>) -> Result<UserStats, DatabaseError> {
+    let summary = query!(r#"SELECT COUNT(*) AS "review_count!", COALESCE(AVG(r.rating)::REAL, 0) AS "mean_score!" FROM reviews r JOIN users u ON r.user_id = u.id WHERE u.username = $1 AND ((NOT r.private AND NOT u.private_ratings) OR u.username = $2)"#, username, viewer).fetch_one(pool).await.map_err(|e|DatabaseError::InternalError(Box::new(e)))?;
+    let distribution = query!(r#"SELECT s AS "score!", COUNT(r.id) AS "count!" FROM generate_series(1, 10) s LEFT JOIN (reviews r JOIN users u ON r.user_id = u.id AND u.username = $1 AND ((NOT r.private AND NOT u.private_ratings) OR u.username = $2)) ON r.rating = s GROUP BY s ORDER BY s"#, username, viewer).fetch_all(pool).await.map_err(|e|DatabaseError::InternalError(Box::new(e)))?;
+    let per_month = query!(r#"SELECT to_char(m, 'Mon') AS "month!", COUNT(r.id) AS "count!" FROM generate_series(date_trunc('month', now()) - INTERVAL '11 months', date_trunc('month', now()), INTERVAL '1 month') m LEFT JOIN (reviews r JOIN users u ON r.user_id = u.id AND u.username = $1 AND ((NOT r.private AND NOT u.private_ratings) OR u.username = $2)) ON date_trunc('month', r.date) = m GROUP BY m ORDER BY m"#, username, viewer).fetch_all(pool).await.map_err(|e|DatabaseError::InternalError(Box::new(e)))?;
+    Ok(UserStats {
+        review_count: summary.review_count,
+        mean_score: summary.mean_score,
+        distribution: distribution.into_iter().map(|r| (r.score.to_string(), r.count)).collect(),
+        per_month: per_month.into_iter().map(|r| (r.month, r.count)).collect(),
+    })
+}
+
+pub struct Compatibility {
+    pub shared_count: i64,
+    pub correlation: Option<f64>,
+}
+
+impl Compatibility {
+    /// Pearson correlation of both users' ratings mapped onto 0-100%.
+    pub fn percentage(&self) -> Option<f64> {
+        self.correlation.map(|c| (c + 1.0) * 50.0)
+    }
+}
+
+pub async fn get_compatibility(pool: &PgPool, username: &str, other_username: &str) -> Result<Compatibility, DatabaseError> {
+    query_as!(Compatibility, r#"SELECT COUNT(*) AS "shared_count!", corr(a.rating, b.rating) AS correlation FROM reviews a JOIN reviews b ON a.item_id = b.item_id JOIN users u ON b.user_id = u.id WHERE a.user_id = (SELECT id FROM users WHERE username = $1 LIMIT 1) AND u.username = $2 AND NOT b.private AND NOT u.private_ratings"#, username, other_username).fetch_one(pool).await.map_err(|e|DatabaseError::InternalError(Box::new(e)))
+}
+
+pub struct SharedRating {
+    pub locator: String,
+    pub title: String,
+    pub rating: i16,
+    pub other_rating: i16,
+}
+
+pub async fn get_shared_ratings(pool: &PgPool, username: &str, other_username: &str) -> Result<Vec<SharedRating>, DatabaseError> {
+    query_as!(SharedRating, "SELECT i.locator, i.title, a.rating, b.rating AS other_rating FROM reviews a JOIN reviews b ON a.item_id = b.item_id JOIN items i ON a.item_id = i.id JOIN users u ON b.user_id = u.id WHERE a.user_id = (SELECT id FROM users WHERE username = $1 LIMIT 1) AND u.username = $2 AND NOT b.private AND NOT u.private_ratings ORDER BY i.title", username, other_username).fetch_all(pool).await.map_err(|e|DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Title similarity from which an item being added is shown as a possible duplicate.
+const DUPLICATE_SIMILARITY: f32 = 0.5;
+
+pub struct SimilarItem {
+    pub locator: String,
+    pub title: String,
+}
+
+/// Items titled similarly to a new item, most similar first.
+pub async fn get_similar_items(pool: &PgPool, title: &str) -> Result<Vec<SimilarItem>, DatabaseError> {
+    query_as!(SimilarItem, "SELECT locator, title FROM items WHERE similarity(title, $1) >= $2 ORDER BY similarity(title, $1) DESC, title LIMIT 5", title, DUPLICATE_SIMILARITY).fetch_all(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Locators of items, or former locators, that are `base` or `base` with a number suffix.
+pub async fn get_similar_locators(pool: &PgPool, base: &str) -> Result<Vec<String>, DatabaseError> {
+    query_scalar!(r#"SELECT locator AS "locator!" FROM items WHERE locator = $1 OR locator ~ ('^' || $1 || '_[0-9]+$') UNION SELECT locator FROM item_aliases WHERE locator = $1 OR locator ~ ('^' || $1 || '_[0-9]+$')"#, base).fetch_all(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+pub async fn add_item(executor: impl PgExecutor<'_>, locator:&str, title:&str, description: &str) -> Result<(),DatabaseError>{
+    query!("INSERT INTO items(locator, title, description) VALUES($1, $2, $3)", locator, title, description).execute(executor).await.map(|_|()).map_err(|e|match e{
         sqlx::Error::Database(e) => if e.is_unique_violation() {
             DatabaseError::DuplicateItem
         } else {
@@ -424,18 +1682,112 @@ pub async fn add_item(pool: &PgPool, locator:&str, title:&str, description: &str
     })
 }
 
-pub async fn remove_item(pool: &PgPool, locator:&str) ->Result<(), DatabaseError>{
-    query!("DELETE FROM items WHERE locator=$1",locator).execute(pool).await.map(|_|()).map_err(|e|DatabaseError::InternalError(Box::new(e)))
+pub struct Suggestion {
+    pub id: i32,
+    pub user: User,
+    pub locator: String,
+    pub title: String,
+    pub description: String,
+    pub status: String,
+    pub reason: Option<String>,
+    pub date: NaiveDateTime,
 }
 
-pub async fn edit_item(pool: &PgPool,locator: &str, new_locator:Option<&str>, new_title:Option<&str>, new_description: Option<&str>) -> Result<(),DatabaseError>{
-    if new_locator.is_some_and(|l|l.trim().is_empty()) || new_title.is_some_and(|t| t.trim().is_empty()) || new_description.is_some_and(|d|d.trim().is_empty()) {
-        return Err(DatabaseError::EmptyFields);
+/// Queues an item suggested by a regular user for review by admins.
+pub async fn add_suggestion(pool: &PgPool, username: &str, locator: &str, title: &str, description: &str) -> Result<(), DatabaseError> {
+    let added = query!("INSERT INTO pending_items(user_id, locator, title, description) SELECT (SELECT id FROM users WHERE username = $1 LIMIT 1), $2::VARCHAR, $3, $4 WHERE NOT EXISTS (SELECT 1 FROM items WHERE locator = $2)", username, locator, title.trim(), description.trim()).execute(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?.rows_affected() > 0;
+    if added {
+        Ok(())
+    } else {
+        Err(DatabaseError::DuplicateItem)
     }
-    if new_locator.is_some_and(|l|!Regex::new(r"^\w+$").unwrap().is_match(l)) {
-        return Err(DatabaseError::IllegalLocator);
+}
+
+/// Suggestions made by a user, or all pending ones when no user is given.
+pub async fn get_suggestions(pool: &PgPool, username: Option<&str>) -> Result<Vec<Suggestion>, DatabaseError> {
+    query_as!(Suggestion, r#"SELECT p.id, (u.username, u.is_admin, u.avatar_hue, u.avatar, u.avatar_glyph) AS "user!: User", p.locator, p.title, p.description, p.status, p.reason, p.date FROM pending_items p JOIN users u ON p.user_id = u.id WHERE CASE WHEN $1::TEXT IS NULL THEN p.status = 'pending' ELSE u.username = $1 END ORDER BY p.date DESC"#, username).fetch_all(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Promotes a pending suggestion to an item, returning its locator and title when it was still
+/// pending.
+pub async fn approve_suggestion(pool: &PgPool, id: i32, moderator: &str) -> Result<Option<(String, String)>, DatabaseError> {
+    let mut transaction = pool.begin().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    let Some(suggestion) = query!("UPDATE pending_items SET status = 'approved' WHERE id = $1 AND status = 'pending' RETURNING locator, title, description", id).fetch_optional(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))? else {
+        return Ok(None);
+    };
+    query!("INSERT INTO items(locator, title, description) VALUES($1, $2, $3)", suggestion.locator, suggestion.title, suggestion.description).execute(&mut *transaction).await.map_err(|e|match e{
+        sqlx::Error::Database(e) => if e.is_unique_violation() {
+            DatabaseError::DuplicateItem
+        } else {
+            DatabaseError::InternalError(Box::new(e))
+        },
+        _ => DatabaseError::InternalError(Box::new(e)),
+    })?;
+    let after = item_snapshot(&mut *transaction, &suggestion.locator).await?;
+    record_action(&mut *transaction, Some(moderator), "approve_suggestion", &suggestion.locator, None, after.as_ref()).await?;
+    transaction.commit().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    Ok(Some((suggestion.locator, suggestion.title)))
+}
+
+/// Rejects a pending suggestion with a reason shown to its submitter, returning whether it was
+/// still pending.
+pub async fn reject_suggestion(pool: &PgPool, id: i32, reason: &str, moderator: &str) -> Result<bool, DatabaseError> {
+    let mut transaction = pool.begin().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    let Some(locator) = query_scalar!("UPDATE pending_items SET status = 'rejected', reason = $2 WHERE id = $1 AND status = 'pending' RETURNING locator", id, reason.trim()).fetch_optional(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))? else {
+        return Ok(false);
+    };
+    record_action(&mut *transaction, Some(moderator), "reject_suggestion", &locator, None, Some(&json!({ "reason": reason.trim() }))).await?;
+    transaction.commit().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    Ok(true)
+}
+
+pub async fn remove_item(pool: &PgPool, locator: &str, moderator: &str) -> Result<(), DatabaseError> {
+    let mut transaction = pool.begin().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    let Some(before) = item_snapshot(&mut *transaction, locator).await? else {
+        return Ok(());
+    };
+    query!("DELETE FROM items WHERE locator=$1",locator).execute(&mut *transaction).await.map_err(|e|DatabaseError::InternalError(Box::new(e)))?;
+    record_action(&mut *transaction, Some(moderator), "remove_item", locator, Some(&before), None).await?;
+    transaction.commit().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Merges an item into another one and removes it, leaving its locator as an alias of the
+/// survivor. Users who rated both items keep only their most recent review.
+pub async fn merge_items(pool: &PgPool, locator: &str, into: &str, moderator: &str) -> Result<(), DatabaseError> {
+    let mut transaction = pool.begin().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    let ids = query!("SELECT (SELECT id FROM items WHERE locator = $1) AS merged, (SELECT id FROM items WHERE locator = $2) AS survivor", locator, into).fetch_one(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    let (Some(merged), Some(survivor)) = (ids.merged, ids.survivor) else {
+        return Err(DatabaseError::InvalidMerge);
+    };
+    if merged == survivor {
+        return Err(DatabaseError::InvalidMerge);
     }
-    query!("UPDATE items SET locator = COALESCE($1,locator), title = COALESCE($2,title), description = COALESCE($3, description) WHERE locator=$4",new_locator,new_title,new_description,locator).execute(pool).await.map(|_|()).map_err(|e|match e{
+    let before = item_snapshot(&mut *transaction, locator).await?;
+    query!("DELETE FROM reviews r USING reviews o WHERE r.user_id = o.user_id AND r.item_id IN ($1, $2) AND o.item_id IN ($1, $2) AND r.item_id <> o.item_id AND (r.date, r.item_id = $1) < (o.date, o.item_id = $1)", survivor, merged).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    query!("UPDATE reviews SET item_id = $1 WHERE item_id = $2", survivor, merged).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    query!("INSERT INTO item_tags(item_id, tag_id) SELECT $1, tag_id FROM item_tags WHERE item_id = $2 ON CONFLICT DO NOTHING", survivor, merged).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    query!("INSERT INTO item_subscriptions(user_id, item_id) SELECT user_id, $1 FROM item_subscriptions WHERE item_id = $2 ON CONFLICT DO NOTHING", survivor, merged).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    query!("INSERT INTO favorites(user_id, item_id, date) SELECT user_id, $1, date FROM favorites WHERE item_id = $2 ON CONFLICT DO NOTHING", survivor, merged).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    query!("INSERT INTO collection_items(collection_id, item_id, position) SELECT collection_id, $1, position FROM collection_items WHERE item_id = $2 ON CONFLICT DO NOTHING", survivor, merged).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    query!("INSERT INTO user_list_items(list_id, item_id, position, date) SELECT list_id, $1, position, date FROM user_list_items WHERE item_id = $2 ON CONFLICT DO NOTHING", survivor, merged).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    query!("INSERT INTO featured_items(item_id, position) SELECT $1, position FROM featured_items WHERE item_id = $2 ON CONFLICT DO NOTHING", survivor, merged).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    query!("UPDATE comments SET item_id = $1 WHERE item_id = $2", survivor, merged).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    query!("UPDATE notifications SET item_id = $1 WHERE item_id = $2", survivor, merged).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    query!("UPDATE item_images SET item_id = $1, is_cover = is_cover AND NOT EXISTS(SELECT 1 FROM item_images WHERE item_id = $1 AND is_cover) WHERE item_id = $2", survivor, merged).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    query!("UPDATE item_aliases SET item_id = $1 WHERE item_id = $2", survivor, merged).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    query!("INSERT INTO item_aliases(locator, item_id) VALUES($1, $2) ON CONFLICT (locator) DO UPDATE SET item_id = EXCLUDED.item_id", locator, survivor).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    query!("DELETE FROM items WHERE id = $1", merged).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    record_action(&mut *transaction, Some(moderator), "merge_item", &format!("{locator}/{into}"), before.as_ref(), None).await?;
+    transaction.commit().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Current locator of an item merged away under the given locator.
+pub async fn resolve_item_alias(pool: &PgPool, locator: &str) -> Result<Option<String>, DatabaseError> {
+    query_scalar!("SELECT i.locator FROM item_aliases a JOIN items i ON i.id = a.item_id WHERE a.locator = $1", locator).fetch_optional(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+pub async fn edit_item(executor: impl PgExecutor<'_>,locator: &str, new_locator:Option<&str>, new_title:Option<&str>, new_description: Option<&str>) -> Result<(),DatabaseError>{
+    query!("UPDATE items SET locator = COALESCE($1,locator), title = COALESCE($2,title), description = COALESCE($3, description) WHERE locator=$4",new_locator,new_title,new_description,locator).execute(executor).await.map(|_|()).map_err(|e|match e{
         sqlx::Error::Database(e) => if e.is_unique_violation() {
             DatabaseError::DuplicateItem
         } else {
@@ -446,38 +1798,112 @@ pub async fn edit_item(pool: &PgPool,locator: &str, new_locator:Option<&str>, ne
     )
 }
 
-pub async fn remove_user(pool: &PgPool, username:&str) ->Result<(), DatabaseError>{
-    query!("DELETE FROM users WHERE username=$1", username).execute(pool).await.map(|_|()).map_err(|e|DatabaseError::InternalError(Box::new(e)))
+/// Removes a user, recording it in the audit log when a moderator removed them rather than the
+/// user themselves.
+pub async fn remove_user(pool: &PgPool, username: &str, moderator: Option<&str>) -> Result<(), DatabaseError> {
+    let mut transaction = pool.begin().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    let before = user_snapshot(&mut *transaction, username).await?;
+    query!("DELETE FROM users WHERE username=$1", username).execute(&mut *transaction).await.map_err(|e|DatabaseError::InternalError(Box::new(e)))?;
+    if let (Some(moderator), Some(before)) = (moderator, before) {
+        record_action(&mut *transaction, Some(moderator), "remove_user", username, Some(&before), None).await?;
+    }
+    transaction.commit().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Makes a user an admin from the command line, returning whether they exist.
+pub async fn make_admin(pool: &PgPool, username: &str) -> Result<bool, DatabaseError> {
+    let mut transaction = pool.begin().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    let Some(before) = user_snapshot(&mut *transaction, username).await? else {
+        return Ok(false);
+    };
+    query!("UPDATE users SET is_admin = TRUE WHERE username = $1", username).execute(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    let after = user_snapshot(&mut *transaction, username).await?;
+    record_action(&mut *transaction, None, "make_admin", username, Some(&before), after.as_ref()).await?;
+    transaction.commit().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    Ok(true)
 }
 
-pub async fn edit_user(pool: &PgPool, username: &str, new_username:Option<&str>,has_avatar:Option<bool>, new_password1:Option<&str>, new_password2:Option<&str>) -> Result<(),DatabaseError>{
-    if new_username.is_some_and(|u|u.trim().is_empty()) {
-        return Err(DatabaseError::EmptyFields);
+/// Records an admin action in the audit log, along with the fields that differ between the
+/// snapshots of its target from before and after it. Actions from the command line have no actor.
+pub async fn record_action(executor: impl PgExecutor<'_>, actor: Option<&str>, action: &str, target: &str, before: Option<&JsonValue>, after: Option<&JsonValue>) -> Result<(), DatabaseError> {
+    query!("INSERT INTO audit_log(actor_id, actor_name, action, target, diff) VALUES((SELECT id FROM users WHERE username = $1), $1, $2, $3, snapshot_diff($4, $5))", actor, action, target, before, after).execute(executor).await.map(|_| ()).map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Fields of an item that the audit log shows the changes of, or nothing when there is no such
+/// item.
+pub async fn item_snapshot(executor: impl PgExecutor<'_>, locator: &str) -> Result<Option<JsonValue>, DatabaseError> {
+    query_scalar!("SELECT item_snapshot(id) FROM items WHERE locator = $1", locator).fetch_optional(executor).await.map(Option::flatten).map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Fields of a user that the audit log shows the changes of, or nothing when there is no such
+/// user.
+pub async fn user_snapshot(executor: impl PgExecutor<'_>, username: &str) -> Result<Option<JsonValue>, DatabaseError> {
+    query_scalar!("SELECT user_snapshot(id) FROM users WHERE username = $1", username).fetch_optional(executor).await.map(Option::flatten).map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+pub struct AuditEntry {
+    pub actor: Option<String>,
+    pub action: String,
+    pub target: String,
+    pub diff: JsonValue,
+    pub date: NaiveDateTime,
+}
+
+/// Page of the audit log, newest first, narrowed down to an action, an actor and targets
+/// containing the given text.
+pub async fn get_audit_log(pool: &PgPool, page_number: Option<i32>, action: Option<&str>, actor: Option<&str>, target: Option<&str>) -> Result<Option<Page<AuditEntry>>, DatabaseError> {
+    let page_number = page_number.unwrap_or(0);
+    if page_number < 0 {
+        return Ok(None);
     }
-    if new_username.is_some_and(|u|!Regex::new(r"^\w+$").unwrap().is_match(u)) {
-        return Err(DatabaseError::IllegalUsername);
-    }
-    let password_hash = if let Some(password1) = new_password1 {
-        if let Some(password2) = new_password2 {
-            if !password1.trim().is_empty() || !password2.trim().is_empty()
-            {
-                if password1 != password2 {
-                    return Err(DatabaseError::PasswordsDiffer);
-                }
-                if scorer::score(&analyzer::analyze(password1)) < 80.0 {
-                    return Err(DatabaseError::WeakPassword);
-                }
-                Some(Argon2::default() .hash_password(password1.as_bytes(), &SaltString::generate(&mut OsRng)) .map_err(|e| DatabaseError::InternalError(Box::new(e)))? .to_string())
-            } else {
-                None
-            }
-        } else {
-            None
-        }
-    } else {
-        None
+    let rows = query!(r#"SELECT actor_name, action, target, diff, date, COUNT(*) OVER () AS "total!" FROM audit_log WHERE ($2::TEXT IS NULL OR action = $2) AND ($3::TEXT IS NULL OR actor_name = $3) AND ($4::TEXT IS NULL OR strpos(lower(target), lower($4)) > 0) ORDER BY date DESC, id DESC LIMIT 20 OFFSET 20 * $1"#, page_number, action, actor, target).fetch_all(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    let Some(total) = rows.first().map(|row| row.total) else {
+        return Ok(None);
     };
-    query!("UPDATE users SET username = COALESCE($1, username), has_avatar = COALESCE($2, has_avatar), password_hash = COALESCE($3, password_hash) WHERE username = $4", new_username, has_avatar, password_hash, username).execute(pool).await.map(|_|()).map_err(|e|match e{
+    Ok(Some(Page {
+        target: routes::url::path(routes::ADMIN_AUDIT),
+        items: rows.into_iter().map(|row| AuditEntry { actor: row.actor_name, action: row.action, target: row.target, diff: row.diff, date: row.date }).collect(),
+        current_page: page_number,
+        number_of_pages: (total as usize).div_ceil(20) as i32,
+        per_page: 20,
+        params: page_params(&[("action", action), ("actor", actor), ("target", target)]),
+        keyset: None,
+    }))
+}
+
+/// Who may see a user and their ratings.
+#[derive(Clone, Copy, Default)]
+pub struct Privacy {
+    /// Ratings are left out everywhere but for the user.
+    pub private_ratings: bool,
+    /// Left out of the user listing and user search.
+    pub unlisted: bool,
+    /// Rating history is left out of the user page, though ratings still show on items.
+    pub hidden_ratings: bool,
+    /// Only logged in users may see the user page and their ratings on items.
+    pub login_required: bool,
+}
+
+impl Privacy {
+    /// Whether the rating history of `owner` is hidden from `viewer`. It never is from the owner
+    /// and administrators.
+    pub fn hides_ratings_from(&self, owner: &str, viewer: Option<&User>) -> bool {
+        self.hidden_ratings && !viewer.is_some_and(|viewer| viewer.username == owner || viewer.is_admin)
+    }
+}
+
+/// Look of the placeholder shown instead of an uploaded avatar.
+pub struct AvatarStyle {
+    pub hue: i16,
+    pub glyph: String,
+}
+
+pub async fn edit_user(executor: impl PgExecutor<'_>, username: &str, new_username:Option<&str>,avatar:Option<Option<&str>>, new_password:Option<&str>, privacy:Option<&Privacy>, avatar_style:Option<&AvatarStyle>) -> Result<(),DatabaseError>{
+    let password_hash = match new_password {
+        Some(password) if !password.trim().is_empty() => Some(Argon2::default().hash_password(password.as_bytes(), &SaltString::generate(&mut OsRng)).map_err(|e| DatabaseError::InternalError(Box::new(e)))?.to_string()),
+        _ => None,
+    };
+    query!("UPDATE users SET username = COALESCE($1, username), avatar = CASE WHEN $2 THEN $11 ELSE avatar END, password_hash = COALESCE($3, password_hash), private_ratings = COALESCE($5, private_ratings), unlisted = COALESCE($6, unlisted), hidden_ratings = COALESCE($7, hidden_ratings), login_required = COALESCE($8, login_required), avatar_hue = COALESCE($9, avatar_hue), avatar_glyph = COALESCE($10, avatar_glyph) WHERE username = $4", new_username, avatar.is_some(), password_hash, username, privacy.map(|p| p.private_ratings), privacy.map(|p| p.unlisted), privacy.map(|p| p.hidden_ratings), privacy.map(|p| p.login_required), avatar_style.map(|style| style.hue), avatar_style.map(|style| style.glyph.as_str()), avatar.flatten()).execute(executor).await.map(|_|()).map_err(|e|match e{
         sqlx::Error::Database(e) => if e.is_unique_violation() {
             DatabaseError::DuplicateItem
         } else {
@@ -487,3 +1913,150 @@ pub async fn edit_user(pool: &PgPool, username: &str, new_username:Option<&str>,
     }
     )
 }
+
+/// Details users tell about themselves on their page.
+#[derive(Default)]
+pub struct Profile {
+    pub bio: Option<String>,
+    pub location: Option<String>,
+    pub website: Option<String>,
+    /// Names of the badges awarded to the user, oldest first.
+    pub badges: Vec<String>,
+}
+
+pub async fn get_user_profile(pool: &PgPool, username: &str) -> Result<Option<(User, Profile, Privacy)>, DatabaseError> {
+    query!(r#"SELECT username, is_admin, avatar_hue, avatar, avatar_glyph, bio, location, website, ARRAY(SELECT badge FROM user_badges WHERE user_id = users.id ORDER BY date, badge) AS "badges!", private_ratings, unlisted, hidden_ratings, login_required FROM users WHERE username = $1 LIMIT 1"#, username).fetch_optional(pool).await.map(|row| row.map(|row| (User { username: row.username, is_admin: row.is_admin, avatar_hue: row.avatar_hue, avatar: row.avatar, avatar_glyph: row.avatar_glyph }, Profile { bio: row.bio, location: row.location, website: row.website, badges: row.badges }, Privacy { private_ratings: row.private_ratings, unlisted: row.unlisted, hidden_ratings: row.hidden_ratings, login_required: row.login_required }))).map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Privacy settings of a user, `None` when there is no such user.
+pub async fn get_privacy(pool: &PgPool, username: &str) -> Result<Option<Privacy>, DatabaseError> {
+    query_as!(Privacy, "SELECT private_ratings, unlisted, hidden_ratings, login_required FROM users WHERE username = $1 LIMIT 1", username).fetch_optional(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+pub async fn set_user_profile(executor: impl PgExecutor<'_>, username: &str, profile: &Profile) -> Result<(), DatabaseError> {
+    query!("UPDATE users SET bio = $2, location = $3, website = $4 WHERE username = $1", username, profile.bio, profile.location, profile.website).execute(executor).await.map(|_| ()).map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Whether the user browses the item listing by scrolling instead of by page.
+pub async fn get_infinite_scroll(pool: &PgPool, username: &str) -> Result<bool, DatabaseError> {
+    query_scalar!("SELECT infinite_scroll FROM users WHERE username = $1", username).fetch_optional(pool).await.map(|enabled| enabled.unwrap_or_default()).map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+pub async fn set_infinite_scroll(executor: impl PgExecutor<'_>, username: &str, enabled: bool) -> Result<(), DatabaseError> {
+    query!("UPDATE users SET infinite_scroll = $2 WHERE username = $1", username, enabled).execute(executor).await.map(|_| ()).map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Subscribes a user to the weekly email of top reviews, or unsubscribes them. It is only sent to
+/// confirmed addresses.
+pub async fn set_weekly_email(executor: impl PgExecutor<'_>, username: &str, enabled: bool) -> Result<(), DatabaseError> {
+    query!("UPDATE users SET weekly_email = $2 WHERE username = $1", username, enabled).execute(executor).await.map(|_| ()).map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Email address of a user and whether it was confirmed.
+pub struct UserEmail {
+    pub address: String,
+    pub verified: bool,
+    /// Whether the weekly email of top reviews is sent to the address.
+    pub weekly: bool,
+}
+
+pub async fn get_user_email(pool: &PgPool, username: &str) -> Result<Option<UserEmail>, DatabaseError> {
+    query_as!(UserEmail, r#"SELECT email AS "address!", email_verified AS verified, weekly_email AS weekly FROM users WHERE username = $1 AND email IS NOT NULL LIMIT 1"#, username).fetch_optional(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Hours a link confirming an email address can be used for.
+pub const EMAIL_TOKEN_HOURS: i32 = 48;
+
+/// Changes the email address of a user, who has to confirm it again. Returns the confirmation token when the address changed, a blank address removes it.
+pub async fn set_user_email(executor: impl PgExecutor<'_>, username: &str, email: &str) -> Result<Option<String>, DatabaseError> {
+    let email = email.trim();
+    if email.is_empty() {
+        query!("UPDATE users SET email = NULL, email_verified = FALSE, email_token = NULL, email_token_created = NULL, gravatar_checked = NULL WHERE username = $1", username).execute(executor).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+        return Ok(None);
+    }
+    match query_scalar!(r#"UPDATE users SET email = $2, email_verified = FALSE, email_token = gen_random_uuid()::TEXT, email_token_created = now(), gravatar_checked = NULL WHERE username = $1 AND email IS DISTINCT FROM $2 RETURNING email_token AS "token!""#, username, email).fetch_optional(executor).await {
+        Ok(token) => Ok(token),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => Err(DatabaseError::DuplicateEmail),
+        Err(e) => Err(DatabaseError::InternalError(Box::new(e))),
+    }
+}
+
+/// Confirms the email address a token was sent to, returning the username of its owner. Tokens
+/// older than [`EMAIL_TOKEN_HOURS`] are rejected.
+pub async fn verify_email(pool: &PgPool, token: &str) -> Result<Option<String>, DatabaseError> {
+    query_scalar!("UPDATE users SET email_verified = TRUE, first_verified = COALESCE(first_verified, now()), email_token = NULL, email_token_created = NULL WHERE email_token = $1 AND email_token_created > now() - make_interval(hours => $2) RETURNING username", token, EMAIL_TOKEN_HOURS).fetch_optional(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Requires the user to set a new password at next login, returning their id. Administrators cannot
+/// be forced.
+pub async fn force_password_reset(pool: &PgPool, username: &str, moderator: &str) -> Result<Option<i32>, DatabaseError> {
+    let mut transaction = pool.begin().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    let Some(id) = query_scalar!("UPDATE users SET password_reset = TRUE WHERE username = $1 AND NOT is_admin RETURNING id", username).fetch_optional(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))? else {
+        return Ok(None);
+    };
+    record_action(&mut *transaction, Some(moderator), "force_password_reset", username, None, None).await?;
+    transaction.commit().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    Ok(Some(id))
+}
+
+/// Sets the new password of a user whose password was reset, returning whether a reset was pending.
+pub async fn reset_password(pool: &PgPool, username: &str, password: &str) -> Result<bool, DatabaseError> {
+    let password_hash = Argon2::default().hash_password(password.as_bytes(), &SaltString::generate(&mut OsRng)).map_err(|e| DatabaseError::InternalError(Box::new(e)))?.to_string();
+    query!("UPDATE users SET password_hash = $1, password_reset = FALSE WHERE username = $2 AND password_reset", password_hash, username).execute(pool).await.map(|result| result.rows_affected() > 0).map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+#[derive(Clone)]
+pub struct InstanceStats {
+    pub items: i64,
+    pub users: i64,
+    pub reviews: i64,
+    pub busiest_day: Option<NaiveDate>,
+    pub busiest_day_reviews: i64,
+}
+
+/// Totals shown on the about page, with the day most reviews were last written on.
+pub async fn get_instance_stats(pool: &PgPool) -> Result<InstanceStats, DatabaseError> {
+    query_as!(InstanceStats, r#"SELECT (SELECT COUNT(*) FROM items) AS "items!", (SELECT COUNT(*) FROM users) AS "users!", (SELECT COUNT(*) FROM reviews) AS "reviews!", d.day AS busiest_day, COALESCE(d.count, 0) AS "busiest_day_reviews!" FROM (SELECT 1) one LEFT JOIN (SELECT date::DATE AS day, COUNT(*) AS count FROM reviews GROUP BY day ORDER BY count DESC, day DESC LIMIT 1) d ON TRUE"#).fetch_one(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+pub struct Settings {
+    pub admin_contact: String,
+    pub policies: String,
+}
+
+pub async fn get_settings(pool: &PgPool) -> Result<Settings, DatabaseError> {
+    query_as!(Settings, r#"SELECT COALESCE((SELECT value FROM settings WHERE key = 'admin_contact'), '') AS "admin_contact!", COALESCE((SELECT value FROM settings WHERE key = 'policies'), '') AS "policies!""#).fetch_one(pool).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+pub struct ImportMatch {
+    pub query: String,
+    pub rating: i16,
+    pub locator: Option<String>,
+    pub title: Option<String>,
+    pub similarity: Option<f32>,
+}
+
+/// Matches imported rows to items, by exact locator first and by the most similar title otherwise.
+pub async fn match_import_rows(pool: &PgPool, rows: &[ImportRow]) -> Result<Vec<ImportMatch>, DatabaseError> {
+    let queries: Vec<&str> = rows.iter().map(|row| row.query.as_str()).collect();
+    let ratings: Vec<i16> = rows.iter().map(|row| row.rating).collect();
+    query_as!(ImportMatch, r#"SELECT t.query AS "query!", t.rating AS "rating!", m.locator AS "locator?", m.title AS "title?", m.similarity AS "similarity?" FROM UNNEST($1::TEXT[], $2::SMALLINT[]) WITH ORDINALITY AS t(query, rating, n) LEFT JOIN LATERAL (SELECT locator, title, CASE WHEN locator = t.query THEN 1 ELSE similarity(title, t.query) END AS similarity FROM items WHERE locator = t.query OR title % t.query ORDER BY 3 DESC LIMIT 1) m ON TRUE ORDER BY t.n"#, &queries as &[&str], &ratings).fetch_all(pool).await.map_err(|e|DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Rates all given items at once, overwriting existing ratings. Runs as a single statement, so
+/// either every rating is imported or none is.
+/// Rates the items at once, failing with [`DatabaseError::RateLimited`] when that would take the user
+/// past [`RATINGS_PER_HOUR`], like rating them one by one would.
+pub async fn import_ratings(pool: &PgPool, username: &str, ratings: &[(String, i16)]) -> Result<u64, DatabaseError> {
+    let (locators, ratings): (Vec<&str>, Vec<i16>) = ratings.iter().map(|(locator, rating)| (locator.as_str(), (*rating).clamp(1, 10))).unzip();
+    let mut transaction = pool.begin().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    // Locked like in rate_item, so that ratings made meanwhile are counted too.
+    let user_id = query_scalar!("SELECT id FROM users WHERE username=$1 FOR UPDATE", username).fetch_one(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    let limits = query!(r#"SELECT (SELECT COUNT(*) FROM items WHERE locator = ANY($2) AND NOT unreleased AND NOT locked) AS "imported!", (SELECT COUNT(*) FROM reviews WHERE user_id = $1 AND date > now() - INTERVAL '1 hour' AND item_id NOT IN (SELECT id FROM items WHERE locator = ANY($2))) AS "recent!""#, user_id, &locators as &[&str]).fetch_one(&mut *transaction).await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    if limits.imported + limits.recent > RATINGS_PER_HOUR {
+        return Err(DatabaseError::RateLimited);
+    }
+    let imported = query!("INSERT INTO reviews(item_id, user_id, rating) SELECT i.id, $1, r.rating FROM UNNEST($2::TEXT[], $3::SMALLINT[]) AS r(locator, rating) JOIN items i ON i.locator = r.locator AND NOT i.unreleased AND NOT i.locked ON CONFLICT (item_id, user_id) DO UPDATE SET rating = EXCLUDED.rating, date = now()", user_id, &locators as &[&str], &ratings).execute(&mut *transaction).await.map(|result| result.rows_affected()).map_err(|e|DatabaseError::InternalError(Box::new(e)))?;
+    transaction.commit().await.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    Ok(imported)
+}
@@ -0,0 +1,216 @@
+//! Emails sent to users, rendered with inline styles as mail clients ignore stylesheets. Each
+//! mail carries a plain text version for clients that do not show HTML.
+
+use crate::database::EMAIL_TOKEN_HOURS;
+use maud::{html, Markup, DOCTYPE};
+use sqlx::types::chrono::NaiveDateTime;
+
+pub struct Mail {
+    pub subject: String,
+    pub html: Markup,
+    pub text: String,
+}
+
+/// Unread notification listed in a digest.
+pub struct DigestEntry {
+    pub author: String,
+    pub title: String,
+    pub date: NaiveDateTime,
+}
+
+/// New review featured in a weekly email.
+pub struct WeeklyReview {
+    pub author: String,
+    pub title: String,
+    pub link: String,
+    pub rating: i16,
+    pub excerpt: String,
+    pub reactions: i64,
+}
+
+/// Item whose score changed the most since the last weekly email.
+pub struct ScoreMover {
+    pub title: String,
+    pub link: String,
+    pub previous_score: f32,
+    pub score: f32,
+}
+
+fn layout(title: &str, content: Markup) -> Markup {
+    html! {
+        (DOCTYPE)
+        html lang="en" {
+            head {
+                meta charset="UTF-8";
+                meta name="viewport" content="width=device-width, initial-scale=1.0";
+                title {(title)}
+            }
+            body style="margin:0;padding:24px;background:#27272a;color:#ffffff;font-family:Quicksand,Arial,sans-serif" {
+                div style="max-width:480px;margin:0 auto;padding:24px;background:#18181b;border-radius:6px" {
+                    div style="margin-bottom:16px;font-size:24px;font-weight:bold;color:#a78bfa" {"ZAI"}
+                    (content)
+                }
+            }
+        }
+    }
+}
+
+fn button(link: &str, label: &str) -> Markup {
+    html! {
+        p style="margin:24px 0" {
+            a href=(link) style="display:inline-block;padding:8px 16px;background:#a78bfa;color:#000000;border-radius:9999px;text-decoration:none" {(label)}
+        }
+    }
+}
+
+pub fn verification(username: &str, link: &str) -> Mail {
+    let subject = "Confirm your email address";
+    Mail {
+        subject: subject.to_owned(),
+        html: layout(
+            subject,
+            html! {
+                p {"Hi " b {(username)} ","}
+                p {"Confirm this is your email address to get notification digests and account notices."}
+                (button(link, "Confirm email"))
+                p style="font-size:12px;color:#a1a1aa" {"The link expires in " (EMAIL_TOKEN_HOURS) " hours. If you didn't add this address to your account, ignore this email."}
+            },
+        ),
+        text: format!(
+            "Hi {username},\n\nConfirm this is your email address to get notification digests and account notices:\n{link}\n\nThe link expires in {EMAIL_TOKEN_HOURS} hours. If you didn't add this address to your account, ignore this email.\n"
+        ),
+    }
+}
+
+pub fn password_reset(username: &str, link: &str) -> Mail {
+    let subject = "Your password was reset";
+    Mail {
+        subject: subject.to_owned(),
+        html: layout(
+            subject,
+            html! {
+                p {"Hi " b {(username)} ","}
+                p {"An administrator reset your password and logged you out everywhere. Log in with your old password to choose a new one."}
+                (button(link, "Log in"))
+            },
+        ),
+        text: format!(
+            "Hi {username},\n\nAn administrator reset your password and logged you out everywhere. Log in with your old password to choose a new one:\n{link}\n"
+        ),
+    }
+}
+
+pub fn digest(username: &str, entries: &[DigestEntry], link: &str) -> Mail {
+    let subject = match entries.len() {
+        1 => "1 new review of items you subscribed to".to_owned(),
+        count => format!("{count} new reviews of items you subscribed to"),
+    };
+    let lines: String = entries
+        .iter()
+        .map(|entry| {
+            format!(
+                "- {} reviewed {} ({})\n",
+                entry.author,
+                entry.title,
+                entry.date.format("%b %d, %Y")
+            )
+        })
+        .collect();
+    Mail {
+        html: layout(
+            &subject,
+            html! {
+                p {"Hi " b {(username)} ", here is what you missed:"}
+                @for entry in entries {
+                    p style="margin:8px 0;padding:8px;background:#27272a;border-radius:6px" {
+                        b {(entry.author)} " reviewed " b style="color:#a78bfa" {(entry.title)}
+                        br;
+                        span style="font-size:12px;color:#a1a1aa" {(entry.date.format("%b %d, %Y"))}
+                    }
+                }
+                (button(link, "See notifications"))
+            },
+        ),
+        text: format!("Hi {username}, here is what you missed:\n\n{lines}\n{link}\n"),
+        subject,
+    }
+}
+
+pub fn weekly(
+    username: &str,
+    reviews: &[WeeklyReview],
+    movers: &[ScoreMover],
+    settings_link: &str,
+) -> Mail {
+    let subject = "This week's top reviews";
+    let mut text = format!("Hi {username}, here is what stood out this week:\n");
+    if !reviews.is_empty() {
+        text.push_str("\nMost liked reviews:\n");
+        for review in reviews {
+            text.push_str(&format!(
+                "- {} rated {} {}/10: {}\n  {}\n",
+                review.author, review.title, review.rating, review.excerpt, review.link
+            ));
+        }
+    }
+    if !movers.is_empty() {
+        text.push_str("\nBiggest score changes:\n");
+        for mover in movers {
+            text.push_str(&format!(
+                "- {}: {:.2} -> {:.2}\n  {}\n",
+                mover.title, mover.previous_score, mover.score, mover.link
+            ));
+        }
+    }
+    text.push_str(&format!(
+        "\nYou get this email because you subscribed to it. Unsubscribe in your account settings:\n{settings_link}\n"
+    ));
+    Mail {
+        subject: subject.to_owned(),
+        html: layout(
+            subject,
+            html! {
+                p {"Hi " b {(username)} ", here is what stood out this week:"}
+                @if !reviews.is_empty() {
+                    p style="margin:16px 0 8px;font-weight:bold;color:#a78bfa" {"Most liked reviews"}
+                    @for review in reviews {
+                        p style="margin:8px 0;padding:8px;background:#27272a;border-radius:6px" {
+                            b {(review.author)} " rated " a href=(review.link) style="font-weight:bold;color:#a78bfa" {(review.title)} " " (review.rating) "/10"
+                            br;
+                            (review.excerpt)
+                            br;
+                            span style="font-size:12px;color:#a1a1aa" {(review.reactions) " reactions"}
+                        }
+                    }
+                }
+                @if !movers.is_empty() {
+                    p style="margin:16px 0 8px;font-weight:bold;color:#a78bfa" {"Biggest score changes"}
+                    @for mover in movers {
+                        p style="margin:8px 0;padding:8px;background:#27272a;border-radius:6px" {
+                            a href=(mover.link) style="font-weight:bold;color:#a78bfa" {(mover.title)}
+                            br;
+                            span style="font-size:12px;color:#a1a1aa" {(format!("{:.2}", mover.previous_score)) " → " (format!("{:.2}", mover.score))}
+                        }
+                    }
+                }
+                p style="font-size:12px;color:#a1a1aa" {"You get this email because you subscribed to it. Unsubscribe in " a href=(settings_link) style="color:#a1a1aa" {"your account settings"} "."}
+            },
+        ),
+        text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_escaped_mail() {
+        let mail = verification("<b>user</b>", "https://example.com/verify-email?token=a&b");
+        assert!(mail.html.0.contains("&lt;b&gt;user&lt;/b&gt;"));
+        assert!(mail.html.0.contains("token=a&amp;b"));
+        assert!(mail
+            .text
+            .contains("https://example.com/verify-email?token=a&b"));
+    }
+}
@@ -0,0 +1,219 @@
+//! Errors of request handlers, answered with a page saying what went wrong instead of a dropped
+//! connection. Pages are rendered by [`render_errors`] within the layout of the app, which also
+//! dresses up bare error statuses, while htmx requests get an error modal instead, as htmx would
+//! not swap an error response in.
+
+use crate::{database::DatabaseError, routes, sessions::CurrentUser, templates};
+use axum::{
+    extract::Request,
+    http::{
+        header,
+        uri::{InvalidUri, InvalidUriParts},
+        StatusCode,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use axum_htmx::{HxReswap, HxRetarget, SwapOption, HX_REQUEST};
+use std::{
+    fmt::{self, Display},
+    io,
+};
+use tracing::error;
+
+#[derive(Debug)]
+pub enum AppError {
+    Database(DatabaseError),
+    Io(io::Error),
+    /// The requested resource does not exist.
+    NotFound,
+    /// The request lacks something every well-formed request has, like the htmx current URL.
+    BadRequest,
+    /// The client made more requests to expensive routes than it is allowed to.
+    TooManyRequests,
+}
+
+impl AppError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::Database(e) if e.is_transient() => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::Database(DatabaseError::InternalError(_)) | AppError::Io(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+            AppError::Database(DatabaseError::Unavailable | DatabaseError::Busy) => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            AppError::Database(DatabaseError::RateLimited) => StatusCode::TOO_MANY_REQUESTS,
+            AppError::Database(DatabaseError::NonexistentItem) | AppError::NotFound => {
+                StatusCode::NOT_FOUND
+            }
+            AppError::Database(DatabaseError::Locked) => StatusCode::FORBIDDEN,
+            AppError::Database(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::BadRequest => StatusCode::BAD_REQUEST,
+            AppError::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+}
+
+impl Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Database(e) if e.is_transient() => DatabaseError::Unavailable.fmt(f),
+            AppError::Database(e) => e.fmt(f),
+            AppError::Io(_) => write!(f, "Internal server error!"),
+            AppError::NotFound => write!(f, "This page does not exist!"),
+            AppError::BadRequest => write!(f, "Malformed request!"),
+            AppError::TooManyRequests => {
+                write!(f, "You are sending too many requests, try again later!")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AppError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AppError::Database(e) => Some(e),
+            AppError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<DatabaseError> for AppError {
+    fn from(e: DatabaseError) -> Self {
+        AppError::Database(e)
+    }
+}
+
+impl From<io::Error> for AppError {
+    fn from(e: io::Error) -> Self {
+        AppError::Io(e)
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(e: sqlx::Error) -> Self {
+        AppError::Database(DatabaseError::InternalError(Box::new(e)))
+    }
+}
+
+impl From<InvalidUri> for AppError {
+    fn from(e: InvalidUri) -> Self {
+        AppError::Database(DatabaseError::InternalError(Box::new(e)))
+    }
+}
+
+impl From<InvalidUriParts> for AppError {
+    fn from(e: InvalidUriParts) -> Self {
+        AppError::Database(DatabaseError::InternalError(Box::new(e)))
+    }
+}
+
+/// Message of an error response, picked up by [`render_errors`].
+#[derive(Clone)]
+struct ErrorMessage(String);
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        if status.is_server_error() {
+            error!(error = ?self, "request failed");
+        }
+        let mut response = status.into_response();
+        response
+            .extensions_mut()
+            .insert(ErrorMessage(self.to_string()));
+        response
+    }
+}
+
+/// Message shown for an error status returned without saying more.
+fn status_message(status: StatusCode) -> String {
+    match status {
+        StatusCode::NOT_FOUND => AppError::NotFound.to_string(),
+        StatusCode::UNAUTHORIZED => "Log in to see this page!".to_owned(),
+        StatusCode::FORBIDDEN => "You are not allowed to see this page!".to_owned(),
+        status if status.is_server_error() => "Internal server error!".to_owned(),
+        _ => AppError::BadRequest.to_string(),
+    }
+}
+
+/// Renders handler errors as error pages, or as an error modal on top of the current page for htmx
+/// requests. Error statuses returned without a body are rendered as pages too when a browser asks
+/// for a page, so that dead links and forbidden pages keep the header and the search.
+pub async fn render_errors(
+    CurrentUser(user): CurrentUser,
+    request: Request,
+    next: Next,
+) -> Response {
+    let is_htmx = request.headers().contains_key(HX_REQUEST);
+    let wants_page = request
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/html"));
+    let response = next.run(request).await;
+    let status = response.status();
+    let message = match response.extensions().get::<ErrorMessage>() {
+        Some(ErrorMessage(message)) => message.clone(),
+        None if (status.is_client_error() || status.is_server_error())
+            && !response.headers().contains_key(header::CONTENT_TYPE)
+            && wants_page
+            && !is_htmx =>
+        {
+            status_message(status)
+        }
+        None => return response,
+    };
+    let (mut parts, _) = response.into_parts();
+    parts.headers.remove(header::CONTENT_LENGTH);
+    if is_htmx {
+        parts.status = StatusCode::OK;
+        (
+            parts,
+            HxRetarget("body".to_owned()),
+            HxReswap(SwapOption::BeforeEnd),
+            templates::error_modal(&message),
+        )
+            .into_response()
+    } else {
+        (
+            parts,
+            templates::index(
+                templates::error_page(status, &message),
+                routes::ITEMS,
+                user.as_ref(),
+                None,
+            ),
+        )
+            .into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_errors_to_statuses() {
+        assert_eq!(
+            AppError::from(DatabaseError::NonexistentItem).status(),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            AppError::from(DatabaseError::RateLimited).status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        assert_eq!(
+            AppError::from(DatabaseError::EmptyFields).status(),
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+        let internal = AppError::from(io::Error::other("disk full"));
+        assert_eq!(internal.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(internal.to_string(), "Internal server error!");
+        let outage = AppError::from(sqlx::Error::PoolTimedOut);
+        assert_eq!(outage.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(outage.to_string(), DatabaseError::Unavailable.to_string());
+    }
+}
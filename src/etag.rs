@@ -0,0 +1,43 @@
+//! Conditional GET for API responses. Responses carry a weak `ETag` naming the version of the data
+//! they were built from, and requests whose `If-None-Match` still names it are answered with a
+//! bodiless 304 so that polling clients do not download unchanged data again.
+
+use axum::http::{header, HeaderMap};
+
+/// Weak entity tag of a response, weak because the same data may be encoded differently.
+pub fn weak(tag: &str) -> String {
+    format!("W/\"{tag}\"")
+}
+
+/// Whether `If-None-Match` names the entity tag, compared weakly as RFC 9110 requires.
+pub fn matches(headers: &HeaderMap, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_owned();
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|tag| tag.trim() == "*" || opaque(tag) == opaque(etag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn if_none_match(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn matches_weakly_in_lists() {
+        let etag = weak("catalog-json-7");
+        assert_eq!(etag, r#"W/"catalog-json-7""#);
+        assert!(matches(&if_none_match(r#""a", W/"catalog-json-7""#), &etag));
+        assert!(matches(&if_none_match(r#""catalog-json-7""#), &etag));
+        assert!(matches(&if_none_match("*"), &etag));
+        assert!(!matches(&if_none_match(r#"W/"catalog-json-8""#), &etag));
+        assert!(!matches(&HeaderMap::new(), &etag));
+    }
+}
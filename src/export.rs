@@ -0,0 +1,179 @@
+//! Full catalog exports, encoded row by row while they are read from the database.
+//!
+//! Besides admins, clients sending `Authorization: Bearer <token>` may export the catalog when
+//! the token matches the `EXPORT_TOKEN` environment variable. Exports are tagged with the version
+//! of the catalog, so clients polling with `If-None-Match` only download it again once it changed.
+
+use crate::{
+    database::{self, CatalogEntry, DatabaseError},
+    etag,
+};
+use axum::{
+    body::Body,
+    http::{header, HeaderMap},
+};
+use csv::WriterBuilder;
+use futures_util::{stream, StreamExt};
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::{env, sync::OnceLock};
+use subtle::ConstantTimeEq;
+use tokio::sync::mpsc;
+
+/// Encoded rows kept ahead of a slow client before reading from the database pauses.
+const BUFFERED_ROWS: usize = 64;
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    #[default]
+    Csv,
+    Json,
+}
+
+impl Format {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Csv => "text/csv",
+            Format::Json => "application/json",
+        }
+    }
+
+    pub fn content_disposition(self) -> &'static str {
+        match self {
+            Format::Csv => "attachment; filename=\"catalog.csv\"",
+            Format::Json => "attachment; filename=\"catalog.json\"",
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Json => "json",
+        }
+    }
+
+    /// Entity tag of the export of a catalog version.
+    pub fn etag(self, version: i64) -> String {
+        etag::weak(&format!("catalog-{}-{version}", self.name()))
+    }
+
+    fn header(self) -> &'static str {
+        match self {
+            Format::Csv => "locator,title,description,category,score,review_count\n",
+            Format::Json => "[",
+        }
+    }
+
+    fn footer(self) -> &'static str {
+        match self {
+            Format::Csv => "",
+            Format::Json => "]",
+        }
+    }
+
+    /// Encodes the `index`-th row of the export.
+    fn row(self, index: usize, entry: &CatalogEntry) -> Result<Vec<u8>, DatabaseError> {
+        let internal = |e| DatabaseError::InternalError(Box::new(e));
+        match self {
+            Format::Csv => {
+                let mut writer = WriterBuilder::new()
+                    .has_headers(false)
+                    .from_writer(Vec::new());
+                writer.serialize(entry).map_err(internal)?;
+                writer
+                    .into_inner()
+                    .map_err(|e| DatabaseError::InternalError(Box::new(e.into_error())))
+            }
+            Format::Json => {
+                let mut row = if index > 0 { b",".to_vec() } else { Vec::new() };
+                serde_json::to_writer(&mut row, entry)
+                    .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+                Ok(row)
+            }
+        }
+    }
+}
+
+/// Whether the request carries the configured export token.
+pub fn has_token(headers: &HeaderMap) -> bool {
+    static TOKEN: OnceLock<Option<String>> = OnceLock::new();
+    let Some(token) = TOKEN.get_or_init(|| env::var("EXPORT_TOKEN").ok().filter(|t| !t.is_empty()))
+    else {
+        return false;
+    };
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|value| bool::from(value.as_bytes().ct_eq(token.as_bytes())))
+}
+
+/// Streams the whole catalog, reading further rows only as the client receives earlier ones.
+pub fn catalog(pool: PgPool, format: Format) -> Body {
+    let (sender, receiver) = mpsc::channel::<Result<Vec<u8>, DatabaseError>>(BUFFERED_ROWS);
+    tokio::spawn(async move {
+        if sender.send(Ok(format.header().into())).await.is_err() {
+            return;
+        }
+        let mut rows = database::stream_catalog(&pool).enumerate();
+        while let Some((index, row)) = rows.next().await {
+            let chunk = row.and_then(|entry| format.row(index, &entry));
+            let failed = chunk.is_err();
+            if sender.send(chunk).await.is_err() || failed {
+                return;
+            }
+        }
+        let _ = sender.send(Ok(format.footer().into())).await;
+    });
+    Body::from_stream(stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|chunk| (chunk, receiver))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(format: Format, entries: &[CatalogEntry]) -> String {
+        let mut export = format.header().as_bytes().to_vec();
+        for (index, entry) in entries.iter().enumerate() {
+            export.extend(format.row(index, entry).unwrap());
+        }
+        export.extend(format.footer().as_bytes());
+        String::from_utf8(export).unwrap()
+    }
+
+    #[test]
+    fn encodes_rows_in_both_formats() {
+        let entries = [
+            CatalogEntry {
+                locator: "ergo_proxy".to_owned(),
+                title: "Ergo Proxy".to_owned(),
+                description: "Dystopia, \"Romdo\"".to_owned(),
+                category: Some("Anime".to_owned()),
+                score: 8.5,
+                review_count: 2,
+            },
+            CatalogEntry {
+                locator: "steins_gate".to_owned(),
+                title: "Steins;Gate".to_owned(),
+                description: String::new(),
+                category: None,
+                score: 0.0,
+                review_count: 0,
+            },
+        ];
+        assert_eq!(
+            encode(Format::Csv, &entries),
+            "locator,title,description,category,score,review_count\n\
+             ergo_proxy,Ergo Proxy,\"Dystopia, \"\"Romdo\"\"\",Anime,8.5,2\n\
+             steins_gate,Steins;Gate,,,0.0,0\n"
+        );
+        let json: serde_json::Value =
+            serde_json::from_str(&encode(Format::Json, &entries)).unwrap();
+        assert_eq!(json[0]["description"], "Dystopia, \"Romdo\"");
+        assert_eq!(json[1]["category"], serde_json::Value::Null);
+        assert_eq!(encode(Format::Json, &[]), "[]");
+    }
+}
@@ -0,0 +1,1213 @@
+use crate::{
+    database::{self, DatabaseError},
+    metadata, password, routes, svg, webhooks,
+};
+use axum::{
+    body::Bytes,
+    extract::{multipart::Field, Multipart},
+};
+use deunicode::deunicode;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    types::chrono::{NaiveDate, Utc},
+    PgPool,
+};
+use std::{collections::BTreeMap, fmt::Display};
+use validator::{
+    Validate, ValidateEmail, ValidateUrl, ValidationError, ValidationErrors, ValidationErrorsKind,
+};
+
+/// Validation failures keyed by field name, shown as a single message in HTML forms and
+/// serialized as is for JSON input.
+#[derive(Debug, Default, Serialize)]
+pub struct FieldErrors(pub BTreeMap<String, Vec<String>>);
+
+impl From<ValidationErrors> for FieldErrors {
+    fn from(errors: ValidationErrors) -> Self {
+        let mut field_errors = Self::default();
+        for (field, kind) in errors.into_errors() {
+            if let ValidationErrorsKind::Field(errors) = kind {
+                field_errors
+                    .0
+                    .insert(field.to_owned(), errors.into_iter().map(message).collect());
+            }
+        }
+        field_errors
+    }
+}
+
+impl Display for FieldErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut messages: Vec<&str> = self.0.values().flatten().map(String::as_str).collect();
+        messages.sort_unstable();
+        messages.dedup();
+        write!(f, "{}", messages.join(" "))
+    }
+}
+
+/// Runs the declarative validation of a form, turning failures into
+/// [`DatabaseError::InvalidFields`].
+pub trait Validated: Validate + Sized {
+    fn validated(self) -> Result<Self, DatabaseError> {
+        self.validate()
+            .map(|_| self)
+            .map_err(|e| DatabaseError::InvalidFields(e.into()))
+    }
+}
+
+impl<T: Validate> Validated for T {}
+
+fn message(error: ValidationError) -> String {
+    match error.message {
+        Some(message) => message.into_owned(),
+        None => match error.code.as_ref() {
+            "required" => DatabaseError::EmptyFields.to_string(),
+            "must_match" => DatabaseError::PasswordsDiffer.to_string(),
+            code => format!("Invalid value ({code})!"),
+        },
+    }
+}
+
+fn invalid(code: &'static str, error: DatabaseError) -> ValidationError {
+    ValidationError::new(code).with_message(error.to_string().into())
+}
+
+fn not_blank(value: &str) -> Result<(), ValidationError> {
+    if value.trim().is_empty() {
+        Err(invalid("blank", DatabaseError::EmptyFields))
+    } else {
+        Ok(())
+    }
+}
+
+/// Whether a username or locator is made of letters, digits and underscores only.
+pub fn is_identifier(value: &str) -> bool {
+    Regex::new(r"^\w+$").unwrap().is_match(value)
+}
+
+fn valid_username(value: &str) -> Result<(), ValidationError> {
+    not_blank(value)?;
+    if is_identifier(value) {
+        Ok(())
+    } else {
+        Err(invalid("username", DatabaseError::IllegalUsername))
+    }
+}
+
+fn valid_locator(value: &str) -> Result<(), ValidationError> {
+    not_blank(value)?;
+    if !is_identifier(value) {
+        Err(invalid("locator", DatabaseError::IllegalLocator))
+    } else if routes::is_reserved_locator(value) {
+        Err(invalid("locator", DatabaseError::ReservedLocator))
+    } else {
+        Ok(())
+    }
+}
+
+/// Longest locator generated from a title.
+const MAX_GENERATED_LOCATOR_LENGTH: usize = 64;
+
+/// Turns a title into a locator by transliterating it to ASCII, lowercasing it and joining its
+/// words with underscores, so that "Steins;Gate" becomes `steins_gate`.
+pub fn locator_from_title(title: &str) -> String {
+    let mut locator = String::new();
+    for word in deunicode(title)
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+    {
+        if !locator.is_empty() {
+            if locator.len() + 1 + word.len() > MAX_GENERATED_LOCATOR_LENGTH {
+                break;
+            }
+            locator.push('_');
+        }
+        locator.push_str(word);
+    }
+    locator.truncate(MAX_GENERATED_LOCATOR_LENGTH);
+    if locator.is_empty() {
+        "item".to_owned()
+    } else {
+        locator
+    }
+}
+
+/// First of `base`, `base_2`, `base_3` and so on that is neither taken nor reserved for a route.
+pub fn unused_locator(base: &str, taken: &[String]) -> String {
+    std::iter::once(base.to_owned())
+        .chain((2..).map(|n| format!("{base}_{n}")))
+        .find(|locator| !taken.contains(locator) && !routes::is_reserved_locator(locator))
+        .unwrap()
+}
+
+/// Parses a date as sent by date inputs, which send an empty value for no date.
+pub fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()
+}
+
+fn valid_date(value: &str) -> Result<(), ValidationError> {
+    if value.is_empty() || parse_date(value).is_some() {
+        Ok(())
+    } else {
+        Err(invalid("date", DatabaseError::InvalidDate))
+    }
+}
+
+/// Upcoming items must be released on a date that has not come yet.
+fn valid_release(data: &ItemFormData) -> Result<(), ValidationError> {
+    let today = Utc::now().date_naive();
+    if !data.unreleased
+        || data
+            .release_date
+            .as_deref()
+            .and_then(parse_date)
+            .is_some_and(|date| date > today)
+    {
+        Ok(())
+    } else {
+        Err(invalid("unreleased", DatabaseError::UnreleasedWithoutDate))
+    }
+}
+
+/// Tags an item may have.
+pub const MAX_TAGS: usize = 10;
+/// Longest allowed tag name.
+const MAX_TAG_LENGTH: usize = 32;
+
+const MAX_BIO_LENGTH: usize = 500;
+const MAX_LOCATION_LENGTH: usize = 100;
+const MAX_WEBSITE_LENGTH: usize = 200;
+const MAX_WEBHOOK_URL_LENGTH: usize = 500;
+
+/// Lowercases a name and joins its words with hyphens, so that "Slice of life" and
+/// "slice-of-life" are the same tag or category.
+pub fn slug(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase()
+}
+
+/// Splits a comma separated tag list into distinct normalized tags.
+pub fn parse_tags(value: &str) -> Vec<String> {
+    let mut tags: Vec<String> = value
+        .split(',')
+        .map(slug)
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    tags.sort_unstable();
+    tags.dedup();
+    tags
+}
+
+fn is_slug(value: &str) -> bool {
+    value.chars().count() <= MAX_TAG_LENGTH
+        && value.chars().all(|c| c.is_alphanumeric() || c == '-')
+}
+
+fn valid_tags(value: &str) -> Result<(), ValidationError> {
+    let tags = parse_tags(value);
+    if tags.len() <= MAX_TAGS && tags.iter().all(|tag| is_slug(tag)) {
+        Ok(())
+    } else {
+        Err(invalid("tags", DatabaseError::IllegalTag))
+    }
+}
+
+fn valid_category(value: &str) -> Result<(), ValidationError> {
+    not_blank(value)?;
+    if is_slug(&slug(value)) {
+        Ok(())
+    } else {
+        Err(invalid("category", DatabaseError::IllegalCategory))
+    }
+}
+
+fn valid_collection(value: &str) -> Result<(), ValidationError> {
+    not_blank(value)?;
+    if is_slug(&slug(value)) {
+        Ok(())
+    } else {
+        Err(invalid("collection", DatabaseError::IllegalCollection))
+    }
+}
+
+fn valid_email(value: &str) -> Result<(), ValidationError> {
+    if value.trim().is_empty() || value.trim().validate_email() {
+        Ok(())
+    } else {
+        Err(invalid("email", DatabaseError::IllegalEmail))
+    }
+}
+
+fn at_most(value: &str, field: &'static str, length: usize) -> Result<(), ValidationError> {
+    if value.trim().chars().count() <= length {
+        Ok(())
+    } else {
+        Err(invalid("length", DatabaseError::TooLong(field, length)))
+    }
+}
+
+fn valid_bio(value: &str) -> Result<(), ValidationError> {
+    at_most(value, "Bio", MAX_BIO_LENGTH)
+}
+
+fn valid_location(value: &str) -> Result<(), ValidationError> {
+    at_most(value, "Location", MAX_LOCATION_LENGTH)
+}
+
+fn valid_avatar_hue(value: i16) -> Result<(), ValidationError> {
+    if (0..360).contains(&value) {
+        Ok(())
+    } else {
+        Err(invalid("avatar", DatabaseError::IllegalAvatarStyle))
+    }
+}
+
+fn valid_avatar_glyph(value: &str) -> Result<(), ValidationError> {
+    if svg::AVATAR_GLYPHS.contains(&value) {
+        Ok(())
+    } else {
+        Err(invalid("avatar", DatabaseError::IllegalAvatarStyle))
+    }
+}
+
+fn valid_website(value: &str) -> Result<(), ValidationError> {
+    let value = value.trim();
+    at_most(value, "Website", MAX_WEBSITE_LENGTH)?;
+    let is_link =
+        (value.starts_with("https://") || value.starts_with("http://")) && value.validate_url();
+    if value.is_empty() || is_link {
+        Ok(())
+    } else {
+        Err(invalid("website", DatabaseError::IllegalWebsite))
+    }
+}
+
+fn valid_webhook_url(value: &str) -> Result<(), ValidationError> {
+    at_most(value, "Webhook URL", MAX_WEBHOOK_URL_LENGTH)?;
+    if (value.starts_with("https://") || value.starts_with("http://")) && value.validate_url() {
+        Ok(())
+    } else {
+        Err(invalid("webhook_url", DatabaseError::IllegalWebhookUrl))
+    }
+}
+
+fn valid_webhook_events(events: &[String]) -> Result<(), ValidationError> {
+    if !events.is_empty()
+        && events
+            .iter()
+            .all(|event| webhooks::Event::parse(event).is_some())
+    {
+        Ok(())
+    } else {
+        Err(invalid("webhook_events", DatabaseError::NoWebhookEvents))
+    }
+}
+
+/// Trims a profile field, dropping control characters other than line breaks in the bio and
+/// leaving blank fields unset.
+fn clean(value: &str, multiline: bool) -> Option<String> {
+    let value: String = value
+        .trim()
+        .chars()
+        .filter(|c| !c.is_control() || (multiline && *c == '\n'))
+        .collect();
+    (!value.is_empty()).then_some(value)
+}
+
+fn valid_list(value: &str) -> Result<(), ValidationError> {
+    not_blank(value)?;
+    if is_slug(&slug(value)) {
+        Ok(())
+    } else {
+        Err(invalid("list", DatabaseError::IllegalList))
+    }
+}
+
+/// Checks the new password of a validated form against the [policy](password::policy), which may
+/// read from disk and so is not part of the declarative validation. A blank password, which the
+/// user edit form leaves unchanged, is not checked. Broken rules are reported under `password1`.
+pub async fn check_new_password(value: &str) -> Result<(), DatabaseError> {
+    if value.trim().is_empty() {
+        return Ok(());
+    }
+    match password::policy().check(value).await {
+        Err(
+            e @ (DatabaseError::PasswordTooShort(_)
+            | DatabaseError::WeakPassword
+            | DatabaseError::BreachedPassword),
+        ) => {
+            let mut errors = FieldErrors::default();
+            errors.0.insert("password1".to_owned(), vec![e.to_string()]);
+            Err(DatabaseError::InvalidFields(errors))
+        }
+        result => result,
+    }
+}
+
+/// Fields submitted by the login form.
+#[derive(Deserialize, Validate)]
+pub struct LoginFormData {
+    #[validate(custom(function = "not_blank"))]
+    pub username: String,
+    #[validate(custom(function = "not_blank"))]
+    pub password: String,
+}
+
+/// Fields submitted by the registration form.
+#[derive(Deserialize, Validate)]
+pub struct RegisterFormData {
+    #[validate(custom(function = "valid_username"))]
+    pub username: String,
+    #[validate(custom(function = "not_blank"))]
+    pub password1: String,
+    #[validate(must_match(other = "password1"))]
+    pub password2: String,
+}
+
+/// Fields submitted by the form setting a new password after a forced reset.
+#[derive(Deserialize, Validate)]
+pub struct PasswordResetFormData {
+    #[validate(custom(function = "not_blank"))]
+    pub password1: String,
+    #[validate(must_match(other = "password1"))]
+    pub password2: String,
+}
+
+/// Gallery images accepted in a single upload.
+pub const MAX_GALLERY_UPLOAD: usize = 8;
+
+/// Fields submitted by the item add and edit forms.
+#[derive(Default, Validate)]
+#[validate(schema(function = "valid_release", skip_on_field_errors = false))]
+pub struct ItemFormData {
+    #[validate(required, custom(function = "not_blank"))]
+    pub title: Option<String>,
+    #[validate(required, custom(function = "valid_locator"))]
+    pub locator: Option<String>,
+    #[validate(required, custom(function = "not_blank"))]
+    pub description: Option<String>,
+    pub image: Option<Bytes>,
+    /// Whether to go back to a generated cover, ignored when a cover image is uploaded.
+    pub clear_image: bool,
+    /// Images added to the item gallery.
+    pub gallery: Vec<Bytes>,
+    /// Comma separated, left out to keep the current tags.
+    #[validate(custom(function = "valid_tags"))]
+    pub tags: Option<String>,
+    /// Category slug, empty for none and left out to keep the current category.
+    pub category: Option<String>,
+    /// Empty for none and left out to keep the current release date.
+    #[validate(custom(function = "valid_date"))]
+    pub release_date: Option<String>,
+    /// Whether the item is upcoming, only changed along with the release date.
+    pub unreleased: bool,
+    /// Cover found in an external catalog, used when no cover image is uploaded.
+    pub cover_url: Option<String>,
+    /// Set once the admin has seen the similarly titled items and still adds the item.
+    pub allow_duplicate: bool,
+}
+
+impl ItemFormData {
+    pub async fn from_multipart(mut multipart: Multipart) -> Result<Self, DatabaseError> {
+        let mut data = Self::default();
+        while let Some(field) = multipart
+            .next_field()
+            .await
+            .map_err(|_| DatabaseError::MalformedForm)?
+        {
+            match field.name() {
+                Some("image") => {
+                    if let Some(image) = image(field).await? {
+                        data.image = Some(image);
+                    }
+                }
+                Some("gallery") => {
+                    if let Some(image) = image(field).await? {
+                        if data.gallery.len() == MAX_GALLERY_UPLOAD {
+                            return Err(DatabaseError::TooManyImages);
+                        }
+                        data.gallery.push(image);
+                    }
+                }
+                Some("title") => data.title = Some(text(field).await?),
+                Some("locator") => data.locator = Some(text(field).await?),
+                Some("description") => data.description = Some(text(field).await?),
+                Some("tags") => data.tags = Some(text(field).await?),
+                Some("category") => data.category = Some(text(field).await?),
+                Some("release_date") => data.release_date = Some(text(field).await?),
+                Some("unreleased") => data.unreleased = true,
+                Some("clear_image") => data.clear_image = true,
+                Some("cover_url") => data.cover_url = Some(text(field).await?),
+                Some("allow_duplicate") => data.allow_duplicate = text(field).await? == "true",
+                _ => {}
+            }
+        }
+        Ok(data)
+    }
+
+    /// Generates the locator from the title when it was left blank, suffixing it with a number
+    /// when it is already taken.
+    pub async fn with_generated_locator(mut self, pool: &PgPool) -> Result<Self, DatabaseError> {
+        let blank = self
+            .locator
+            .as_deref()
+            .is_none_or(|locator| locator.trim().is_empty());
+        if let Some(title) = self
+            .title
+            .as_deref()
+            .filter(|title| blank && !title.trim().is_empty())
+        {
+            let base = locator_from_title(title);
+            let taken = database::get_similar_locators(pool, &base).await?;
+            self.locator = Some(unused_locator(&base, &taken));
+        }
+        Ok(self)
+    }
+
+    /// Downloads the cover at the given URL, entered in the form or found in an external catalog,
+    /// unless a cover image was uploaded.
+    pub async fn with_fetched_cover(mut self) -> Result<Self, DatabaseError> {
+        if let Some(url) = self.cover_url.as_deref().filter(|url| !url.is_empty()) {
+            if self.image.is_none() {
+                self.image = Some(metadata::download_cover(url).await?);
+            }
+        }
+        Ok(self)
+    }
+}
+
+/// Fields submitted by the category add form.
+#[derive(Deserialize, Validate)]
+pub struct CategoryFormData {
+    #[validate(custom(function = "valid_category"))]
+    pub name: String,
+}
+
+/// Fields submitted by the webhook add form, which repeats `events` for every checked event.
+#[derive(Default, Validate)]
+pub struct WebhookFormData {
+    #[validate(custom(function = "valid_webhook_url"))]
+    pub url: String,
+    #[validate(custom(function = "valid_webhook_events"))]
+    pub events: Vec<String>,
+}
+
+impl From<Vec<(String, String)>> for WebhookFormData {
+    fn from(fields: Vec<(String, String)>) -> Self {
+        let mut data = Self::default();
+        for (name, value) in fields {
+            match name.as_str() {
+                "url" => data.url = value.trim().to_owned(),
+                "events" => data.events.push(value),
+                _ => {}
+            }
+        }
+        data
+    }
+}
+
+/// Fields submitted by the collection add form.
+#[derive(Deserialize, Validate)]
+pub struct CollectionFormData {
+    #[validate(custom(function = "valid_collection"))]
+    pub name: String,
+}
+
+/// Fields submitted by the forms creating and renaming a user's list.
+#[derive(Deserialize, Validate)]
+pub struct ListFormData {
+    #[validate(custom(function = "valid_list"))]
+    pub name: String,
+}
+
+/// Item added to a collection.
+#[derive(Deserialize, Validate)]
+pub struct CollectionItemFormData {
+    #[validate(custom(function = "valid_locator"))]
+    pub locator: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Earlier,
+    Later,
+}
+
+/// Direction an item is moved in within a collection or a user's list.
+#[derive(Deserialize)]
+pub struct MoveFormData {
+    pub direction: Direction,
+}
+
+/// Fields submitted by the item suggestion form.
+#[derive(Deserialize, Validate)]
+pub struct SuggestionFormData {
+    #[validate(custom(function = "not_blank"))]
+    pub title: String,
+    #[validate(custom(function = "valid_locator"))]
+    pub locator: String,
+    #[validate(custom(function = "not_blank"))]
+    pub description: String,
+}
+
+/// Link or ID of an item in an external catalog, sent from the item add form.
+#[derive(Deserialize)]
+pub struct MetadataFormData {
+    pub source: String,
+}
+
+/// Description sent for previewing from the item add and edit forms.
+#[derive(Deserialize)]
+pub struct PreviewFormData {
+    #[serde(default)]
+    pub description: String,
+}
+
+/// Fields submitted when merging an item into another one.
+#[derive(Deserialize, Validate)]
+pub struct MergeFormData {
+    #[validate(custom(function = "valid_locator"))]
+    pub into: String,
+}
+
+/// Fields submitted when rejecting an item suggestion.
+#[derive(Deserialize, Validate)]
+pub struct RejectionFormData {
+    #[validate(custom(function = "not_blank"))]
+    pub reason: String,
+}
+
+/// Fields submitted by the user edit form.
+#[derive(Default, Validate)]
+pub struct UserFormData {
+    #[validate(required, custom(function = "valid_username"))]
+    pub username: Option<String>,
+    /// Left blank to keep the current password, checked by [`check_new_password`].
+    pub password1: String,
+    #[validate(must_match(other = "password1"))]
+    pub password2: String,
+    /// Left blank to remove the email address, absent to keep it.
+    #[validate(custom(function = "valid_email"))]
+    pub email: Option<String>,
+    /// Profile fields, absent to keep the current profile.
+    #[validate(custom(function = "valid_bio"))]
+    pub bio: Option<String>,
+    #[validate(custom(function = "valid_location"))]
+    pub location: Option<String>,
+    #[validate(custom(function = "valid_website"))]
+    pub website: Option<String>,
+    pub avatar: Option<Bytes>,
+    pub clear_avatar: bool,
+    /// Placeholder avatar look, absent to keep the current one.
+    #[validate(custom(function = "valid_avatar_hue"))]
+    pub avatar_hue: Option<i16>,
+    #[validate(custom(function = "valid_avatar_glyph"))]
+    pub avatar_glyph: Option<String>,
+    pub privacy: database::Privacy,
+    pub infinite_scroll: bool,
+    pub weekly_email: bool,
+}
+
+impl UserFormData {
+    pub async fn from_multipart(mut multipart: Multipart) -> Result<Self, DatabaseError> {
+        let mut data = Self::default();
+        while let Some(field) = multipart
+            .next_field()
+            .await
+            .map_err(|_| DatabaseError::MalformedForm)?
+        {
+            match field.name() {
+                Some("avatar") => {
+                    if let Some(avatar) = image(field).await? {
+                        data.avatar = Some(avatar);
+                    }
+                }
+                Some("username") => data.username = Some(text(field).await?),
+                Some("password1") => data.password1 = text(field).await?,
+                Some("password2") => data.password2 = text(field).await?,
+                Some("email") => data.email = Some(text(field).await?),
+                Some("bio") => data.bio = Some(text(field).await?),
+                Some("location") => data.location = Some(text(field).await?),
+                Some("website") => data.website = Some(text(field).await?),
+                Some("clear_avatar") => data.clear_avatar = true,
+                Some("avatar_hue") => {
+                    data.avatar_hue = Some(
+                        text(field)
+                            .await?
+                            .parse()
+                            .map_err(|_| DatabaseError::MalformedForm)?,
+                    )
+                }
+                Some("avatar_glyph") => data.avatar_glyph = Some(text(field).await?),
+                Some("private_ratings") => data.privacy.private_ratings = true,
+                Some("unlisted") => data.privacy.unlisted = true,
+                Some("hidden_ratings") => data.privacy.hidden_ratings = true,
+                Some("login_required") => data.privacy.login_required = true,
+                Some("infinite_scroll") => data.infinite_scroll = true,
+                Some("weekly_email") => data.weekly_email = true,
+                _ => {}
+            }
+        }
+        Ok(data)
+    }
+
+    /// Cleaned profile, when the form carried the profile fields.
+    pub fn profile(&self) -> Option<database::Profile> {
+        if self.bio.is_none() && self.location.is_none() && self.website.is_none() {
+            return None;
+        }
+        let field = |value: &Option<String>, multiline| {
+            value.as_deref().and_then(|value| clean(value, multiline))
+        };
+        Some(database::Profile {
+            bio: field(&self.bio, true),
+            location: field(&self.location, false),
+            website: field(&self.website, false),
+            ..Default::default()
+        })
+    }
+
+    /// Placeholder avatar look, when the form carried both of its fields.
+    pub fn avatar_style(&self) -> Option<database::AvatarStyle> {
+        Some(database::AvatarStyle {
+            hue: self.avatar_hue?,
+            glyph: self.avatar_glyph.clone()?,
+        })
+    }
+}
+
+/// Fields submitted by the ratings import form.
+#[derive(Default)]
+pub struct ImportFormData {
+    pub file: Option<Bytes>,
+}
+
+impl ImportFormData {
+    pub async fn from_multipart(mut multipart: Multipart) -> Result<Self, DatabaseError> {
+        let mut data = Self::default();
+        while let Some(field) = multipart
+            .next_field()
+            .await
+            .map_err(|_| DatabaseError::MalformedForm)?
+        {
+            if field.name() == Some("file") {
+                let bytes = field
+                    .bytes()
+                    .await
+                    .map_err(|_| DatabaseError::MalformedForm)?;
+                if !bytes.is_empty() {
+                    data.file = Some(bytes);
+                }
+            }
+        }
+        Ok(data)
+    }
+}
+
+async fn text(field: Field<'_>) -> Result<String, DatabaseError> {
+    field.text().await.map_err(|_| DatabaseError::MalformedForm)
+}
+
+/// Reads an uploaded image, returning `None` when the file input was left empty.
+async fn image(field: Field<'_>) -> Result<Option<Bytes>, DatabaseError> {
+    let no_file = field.file_name().is_some_and(str::is_empty);
+    let is_image = field
+        .content_type()
+        .is_some_and(|content_type| content_type.starts_with("image/"));
+    let bytes = field
+        .bytes()
+        .await
+        .map_err(|_| DatabaseError::MalformedForm)?;
+    if no_file && bytes.is_empty() {
+        Ok(None)
+    } else if !is_image || bytes.is_empty() {
+        Err(DatabaseError::NotValidImage)
+    } else {
+        Ok(Some(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        extract::{FromRequest, Request},
+        http::header::CONTENT_TYPE,
+    };
+    use proptest::prelude::*;
+
+    const BOUNDARY: &str = "zai-boundary";
+
+    struct Part<'a> {
+        name: &'a str,
+        file: Option<(&'a str, &'a str)>,
+        content: &'a [u8],
+    }
+
+    fn text_part<'a>(name: &'a str, content: &'a str) -> Part<'a> {
+        Part {
+            name,
+            file: None,
+            content: content.as_bytes(),
+        }
+    }
+
+    fn body(parts: &[Part]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for part in parts {
+            body.extend(format!("--{BOUNDARY}\r\n").bytes());
+            match part.file {
+                Some((file_name, content_type)) => body.extend(
+                    format!(
+                        "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+                        part.name, file_name, content_type
+                    )
+                    .bytes(),
+                ),
+                None => body.extend(
+                    format!(
+                        "Content-Disposition: form-data; name=\"{}\"\r\n\r\n",
+                        part.name
+                    )
+                    .bytes(),
+                ),
+            }
+            body.extend(part.content);
+            body.extend(b"\r\n");
+        }
+        body.extend(format!("--{BOUNDARY}--\r\n").bytes());
+        body
+    }
+
+    async fn multipart(body: Vec<u8>) -> Multipart {
+        let request = Request::builder()
+            .header(
+                CONTENT_TYPE,
+                format!("multipart/form-data; boundary={BOUNDARY}"),
+            )
+            .body(Body::from(body))
+            .unwrap();
+        Multipart::from_request(request, &()).await.unwrap()
+    }
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[tokio::test]
+    async fn item_form_reads_all_fields() {
+        let data = ItemFormData::from_multipart(
+            multipart(body(&[
+                text_part("title", "Title"),
+                text_part("locator", "locator"),
+                text_part("description", "Description"),
+                Part {
+                    name: "image",
+                    file: Some(("cover.png", "image/png")),
+                    content: b"png",
+                },
+            ]))
+            .await,
+        )
+        .await
+        .unwrap();
+        assert_eq!(data.title.as_deref(), Some("Title"));
+        assert_eq!(data.locator.as_deref(), Some("locator"));
+        assert_eq!(data.description.as_deref(), Some("Description"));
+        assert_eq!(data.image.as_deref(), Some(&b"png"[..]));
+    }
+
+    #[tokio::test]
+    async fn missing_fields_are_none() {
+        let data =
+            ItemFormData::from_multipart(multipart(body(&[text_part("title", "Title")])).await)
+                .await
+                .unwrap();
+        assert_eq!(data.title.as_deref(), Some("Title"));
+        assert!(data.locator.is_none());
+        assert!(data.description.is_none());
+        assert!(data.image.is_none());
+    }
+
+    #[tokio::test]
+    async fn duplicate_fields_keep_last_value() {
+        let data = UserFormData::from_multipart(
+            multipart(body(&[
+                text_part("username", "first"),
+                text_part("username", "second"),
+            ]))
+            .await,
+        )
+        .await
+        .unwrap();
+        assert_eq!(data.username.as_deref(), Some("second"));
+    }
+
+    #[tokio::test]
+    async fn empty_file_input_is_ignored() {
+        let data = UserFormData::from_multipart(
+            multipart(body(&[
+                text_part("username", "user"),
+                Part {
+                    name: "avatar",
+                    file: Some(("", "application/octet-stream")),
+                    content: b"",
+                },
+                text_part("clear_avatar", "on"),
+            ]))
+            .await,
+        )
+        .await
+        .unwrap();
+        assert!(data.avatar.is_none());
+        assert!(data.clear_avatar);
+    }
+
+    #[tokio::test]
+    async fn empty_duplicate_file_keeps_earlier_image() {
+        let data = ItemFormData::from_multipart(
+            multipart(body(&[
+                Part {
+                    name: "image",
+                    file: Some(("cover.png", "image/png")),
+                    content: b"png",
+                },
+                Part {
+                    name: "image",
+                    file: Some(("", "application/octet-stream")),
+                    content: b"",
+                },
+            ]))
+            .await,
+        )
+        .await
+        .unwrap();
+        assert_eq!(data.image.as_deref(), Some(&b"png"[..]));
+    }
+
+    #[tokio::test]
+    async fn gallery_collects_every_file() {
+        let gallery = |name| Part {
+            name: "gallery",
+            file: Some((name, "image/png")),
+            content: b"png",
+        };
+        let data = ItemFormData::from_multipart(
+            multipart(body(&[
+                gallery("1.png"),
+                gallery("2.png"),
+                Part {
+                    name: "gallery",
+                    file: Some(("", "application/octet-stream")),
+                    content: b"",
+                },
+            ]))
+            .await,
+        )
+        .await
+        .unwrap();
+        assert_eq!(data.gallery.len(), 2);
+        let parts: Vec<Part> = (0..=MAX_GALLERY_UPLOAD).map(|_| gallery("1.png")).collect();
+        assert!(matches!(
+            ItemFormData::from_multipart(multipart(body(&parts)).await).await,
+            Err(DatabaseError::TooManyImages)
+        ));
+    }
+
+    #[tokio::test]
+    async fn non_image_file_is_rejected() {
+        let result = ItemFormData::from_multipart(
+            multipart(body(&[Part {
+                name: "image",
+                file: Some(("script.sh", "text/x-shellscript")),
+                content: b"echo",
+            }]))
+            .await,
+        )
+        .await;
+        assert!(matches!(result, Err(DatabaseError::NotValidImage)));
+    }
+
+    #[tokio::test]
+    async fn truncated_body_is_malformed() {
+        let mut truncated = body(&[text_part("title", "Title")]);
+        truncated.truncate(truncated.len() - 10);
+        let result = ItemFormData::from_multipart(multipart(truncated).await).await;
+        assert!(matches!(result, Err(DatabaseError::MalformedForm)));
+    }
+
+    fn field_errors(result: Result<impl Sized, DatabaseError>) -> BTreeMap<String, Vec<String>> {
+        match result {
+            Err(DatabaseError::InvalidFields(errors)) => errors.0,
+            _ => panic!("expected field errors"),
+        }
+    }
+
+    #[test]
+    fn new_item_does_not_require_image() {
+        let data = ItemFormData {
+            title: Some("Title".to_owned()),
+            locator: Some("locator".to_owned()),
+            description: Some("Description".to_owned()),
+            ..Default::default()
+        };
+        assert!(data.validated().is_ok_and(|data| data.image.is_none()));
+    }
+
+    #[test]
+    fn unreleased_items_need_future_date() {
+        let item = |release_date: &str| ItemFormData {
+            title: Some("Title".to_owned()),
+            locator: Some("locator".to_owned()),
+            description: Some("Description".to_owned()),
+            release_date: Some(release_date.to_owned()),
+            unreleased: true,
+            ..Default::default()
+        };
+        let errors = field_errors(item("2001-01-01").validated());
+        assert_eq!(
+            errors.values().flatten().collect::<Vec<_>>(),
+            [&DatabaseError::UnreleasedWithoutDate.to_string()]
+        );
+        assert!(item("").validated().is_err());
+        assert!(item("9999-12-31").validated().is_ok());
+    }
+
+    #[test]
+    fn errors_are_reported_per_field() {
+        let data = ItemFormData {
+            title: Some(" ".to_owned()),
+            locator: Some("not a locator".to_owned()),
+            release_date: Some("soon".to_owned()),
+            ..Default::default()
+        };
+        let errors = field_errors(data.validated());
+        assert_eq!(errors["title"], [DatabaseError::EmptyFields.to_string()]);
+        assert_eq!(
+            errors["locator"],
+            [DatabaseError::IllegalLocator.to_string()]
+        );
+        assert_eq!(
+            errors["description"],
+            [DatabaseError::EmptyFields.to_string()]
+        );
+        assert_eq!(
+            errors["release_date"],
+            [DatabaseError::InvalidDate.to_string()]
+        );
+    }
+
+    #[test]
+    fn locators_of_static_routes_are_rejected() {
+        let item = |locator: &str| ItemFormData {
+            title: Some("Title".to_owned()),
+            locator: Some(locator.to_owned()),
+            description: Some("Description".to_owned()),
+            ..Default::default()
+        };
+        for locator in ["new", "trending", "export", "metadata", "add"] {
+            let errors = field_errors(item(locator).validated());
+            assert_eq!(
+                errors["locator"],
+                [DatabaseError::ReservedLocator.to_string()]
+            );
+        }
+        assert!(item("newest").validated().is_ok());
+    }
+
+    #[test]
+    fn tags_are_normalized_and_checked() {
+        let data = ItemFormData {
+            title: Some("Title".to_owned()),
+            locator: Some("locator".to_owned()),
+            description: Some("Description".to_owned()),
+            tags: Some(" Slice of  Life, sci-fi,,slice-of-life ".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(
+            parse_tags(data.tags.as_deref().unwrap()),
+            ["sci-fi", "slice-of-life"]
+        );
+        assert!(data.validated().is_ok());
+        let data = ItemFormData {
+            tags: Some("horror, c++".to_owned()),
+            ..Default::default()
+        };
+        let errors = field_errors(data.validated());
+        assert_eq!(errors["tags"], [DatabaseError::IllegalTag.to_string()]);
+    }
+
+    #[test]
+    fn locators_are_generated_from_titles() {
+        assert_eq!(locator_from_title("Steins;Gate"), "steins_gate");
+        assert_eq!(
+            locator_from_title("  Pokémon: Crème brûlée "),
+            "pokemon_creme_brulee"
+        );
+        assert_eq!(locator_from_title("進撃の巨人"), "jin_ji_noju_ren");
+        assert_eq!(locator_from_title("?!"), "item");
+        assert!(locator_from_title(&"word ".repeat(40)).len() <= MAX_GENERATED_LOCATOR_LENGTH);
+        assert!(is_identifier(&locator_from_title(
+            "Re:Zero − Starting Life"
+        )));
+        let taken = ["steins_gate".to_owned(), "steins_gate_2".to_owned()];
+        assert_eq!(unused_locator("steins_gate", &taken), "steins_gate_3");
+        assert_eq!(unused_locator("ergo_proxy", &taken), "ergo_proxy");
+        assert_eq!(unused_locator(&locator_from_title("New"), &[]), "new_2");
+        assert_eq!(
+            unused_locator(&locator_from_title("Trending"), &[]),
+            "trending_2"
+        );
+    }
+
+    #[test]
+    fn webhook_fields_are_checked() {
+        let data = WebhookFormData::from(vec![
+            ("url".to_owned(), " https://example.com/hook ".to_owned()),
+            ("events".to_owned(), "item.created".to_owned()),
+            ("events".to_owned(), "review.added".to_owned()),
+        ]);
+        let data = data.validated().unwrap();
+        assert_eq!(data.url, "https://example.com/hook");
+        assert_eq!(data.events, ["item.created", "review.added"]);
+
+        let data = WebhookFormData::from(vec![
+            ("url".to_owned(), "ftp://example.com".to_owned()),
+            ("events".to_owned(), "item.renamed".to_owned()),
+        ]);
+        let errors = field_errors(data.validated());
+        assert_eq!(
+            errors["url"],
+            [DatabaseError::IllegalWebhookUrl.to_string()]
+        );
+        assert_eq!(
+            errors["events"],
+            [DatabaseError::NoWebhookEvents.to_string()]
+        );
+    }
+
+    #[test]
+    fn blank_password_keeps_current_one() {
+        let data = UserFormData {
+            username: Some("user".to_owned()),
+            ..Default::default()
+        };
+        assert!(data.validated().is_ok());
+    }
+
+    #[test]
+    fn profile_fields_are_checked_and_cleaned() {
+        let data = UserFormData {
+            username: Some("user".to_owned()),
+            bio: Some("a".repeat(501)),
+            website: Some("javascript:alert(1)".to_owned()),
+            ..Default::default()
+        };
+        let errors = field_errors(data.validated());
+        assert_eq!(
+            errors["bio"],
+            [DatabaseError::TooLong("Bio", 500).to_string()]
+        );
+        assert_eq!(
+            errors["website"],
+            [DatabaseError::IllegalWebsite.to_string()]
+        );
+
+        let data = UserFormData {
+            username: Some("user".to_owned()),
+            bio: Some(" Line\r\nnext\u{7}  ".to_owned()),
+            location: Some("  ".to_owned()),
+            website: Some("https://example.com ".to_owned()),
+            ..Default::default()
+        };
+        let profile = data.validated().unwrap().profile().unwrap();
+        assert_eq!(profile.bio.as_deref(), Some("Line\nnext"));
+        assert_eq!(profile.location, None);
+        assert_eq!(profile.website.as_deref(), Some("https://example.com"));
+        assert!(UserFormData::default().profile().is_none());
+    }
+
+    #[test]
+    fn avatar_style_is_checked() {
+        let data = UserFormData {
+            username: Some("user".to_owned()),
+            avatar_hue: Some(360),
+            avatar_glyph: Some("skull".to_owned()),
+            ..Default::default()
+        };
+        let errors = field_errors(data.validated());
+        assert_eq!(
+            errors["avatar_hue"],
+            [DatabaseError::IllegalAvatarStyle.to_string()]
+        );
+        assert_eq!(
+            errors["avatar_glyph"],
+            [DatabaseError::IllegalAvatarStyle.to_string()]
+        );
+
+        let data = UserFormData {
+            username: Some("user".to_owned()),
+            avatar_hue: Some(200),
+            avatar_glyph: Some("moon".to_owned()),
+            ..Default::default()
+        };
+        let style = data.validated().unwrap().avatar_style().unwrap();
+        assert_eq!((style.hue, style.glyph.as_str()), (200, "moon"));
+        assert!(UserFormData::default().avatar_style().is_none());
+    }
+
+    #[tokio::test]
+    async fn registration_checks_passwords() {
+        let data = RegisterFormData {
+            username: "user".to_owned(),
+            password1: "password".to_owned(),
+            password2: "different".to_owned(),
+        };
+        let errors = field_errors(data.validated());
+        assert_eq!(
+            errors["password2"],
+            [DatabaseError::PasswordsDiffer.to_string()]
+        );
+        let errors = field_errors(check_new_password("password").await);
+        assert_eq!(
+            errors["password1"],
+            [DatabaseError::WeakPassword.to_string()]
+        );
+        assert!(check_new_password(" ").await.is_ok());
+    }
+
+    proptest! {
+        #[test]
+        fn arbitrary_bodies_do_not_panic(raw in proptest::collection::vec(any::<u8>(), 0..512)) {
+            block_on(async {
+                let _ = ItemFormData::from_multipart(multipart(raw.clone()).await).await;
+                let _ = UserFormData::from_multipart(multipart(raw).await).await;
+            });
+        }
+
+        #[test]
+        fn text_fields_round_trip(
+            fields in proptest::collection::vec(
+                (prop::sample::select(vec!["title", "locator", "description", "other"]), "[a-zA-Z0-9 ]{0,32}"),
+                0..8,
+            )
+        ) {
+            let parts: Vec<Part> = fields.iter().map(|(name, value)| text_part(name, value)).collect();
+            let data = block_on(async { ItemFormData::from_multipart(multipart(body(&parts)).await).await }).unwrap();
+            let last = |name: &str| fields.iter().rev().find(|(n, _)| *n == name).map(|(_, v)| v.as_str());
+            prop_assert_eq!(data.title.as_deref(), last("title"));
+            prop_assert_eq!(data.locator.as_deref(), last("locator"));
+            prop_assert_eq!(data.description.as_deref(), last("description"));
+            prop_assert!(data.image.is_none());
+        }
+    }
+}
@@ -0,0 +1,110 @@
+//! Avatars of users who have not uploaded one, fetched from Gravatar for their verified email
+//! address and cached like uploaded avatars. Enabled by setting the `GRAVATAR` environment
+//! variable to `true`; users without a Gravatar keep the colored placeholder.
+
+use crate::{database::DatabaseError, images, jobs::Scheduler, metadata, storage::Storage};
+use axum::body::Bytes;
+use reqwest::{header, StatusCode};
+use sha2::{Digest, Sha256};
+use sqlx::{query, query_as, PgPool};
+use std::{env, sync::Arc, time::Duration};
+use tracing::warn;
+
+const FETCH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Users looked up per run.
+const BATCH_SIZE: i64 = 50;
+
+/// Size in pixels requested from Gravatar, matching the largest avatar shown.
+const SIZE: u32 = 256;
+
+/// Largest avatar accepted from Gravatar.
+const MAX_AVATAR_SIZE: usize = 1024 * 1024;
+
+struct PendingUser {
+    username: String,
+    email: String,
+}
+
+/// Address of the Gravatar of an email, answering 404 instead of a default image when there is
+/// none.
+fn url(email: &str) -> String {
+    let hash = Sha256::digest(email.trim().to_lowercase());
+    format!("https://gravatar.com/avatar/{hash:x}?s={SIZE}&d=404")
+}
+
+async fn fetch(email: &str) -> Result<Option<Bytes>, reqwest::Error> {
+    let response = metadata::client().get(url(email)).send().await?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    let response = response.error_for_status()?;
+    let is_image = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("image/"));
+    let avatar = response.bytes().await?;
+    Ok(Some(avatar).filter(|avatar| is_image && avatar.len() <= MAX_AVATAR_SIZE))
+}
+
+/// Looks up Gravatars of users without an avatar, checking each email again after a week.
+/// Returns how many avatars were cached.
+pub async fn refresh(pool: &PgPool, storage: &dyn Storage) -> Result<usize, DatabaseError> {
+    let users = query_as!(PendingUser, r#"SELECT username, email AS "email!" FROM users WHERE avatar IS NULL AND email_verified AND email IS NOT NULL AND (gravatar_checked IS NULL OR gravatar_checked < now() - INTERVAL '7 days') ORDER BY gravatar_checked NULLS FIRST LIMIT $1"#, BATCH_SIZE)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    let mut cached = 0;
+    for user in users {
+        let avatar = match fetch(&user.email).await {
+            Ok(avatar) => avatar,
+            Err(e) => {
+                warn!(username = user.username, error = %e, "fetching gravatar failed");
+                continue;
+            }
+        };
+        let name = avatar.as_deref().map(images::name);
+        // An avatar uploaded in the meantime wins over the Gravatar.
+        let claimed = query!("UPDATE users SET avatar = $2, gravatar_checked = now() WHERE username = $1 AND avatar IS NULL", user.username, name)
+            .execute(pool)
+            .await
+            .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
+            .rows_affected()
+            > 0;
+        if let (true, Some(name), Some(avatar)) = (claimed, name, avatar) {
+            images::store(storage, vec![(images::avatar_key(&name), avatar)]).await?;
+            cached += 1;
+        }
+    }
+    Ok(cached)
+}
+
+/// Periodically caches Gravatars in the background, when enabled.
+pub fn schedule(scheduler: &mut Scheduler, storage: Arc<dyn Storage>) {
+    if !env::var("GRAVATAR").is_ok_and(|value| value == "true") {
+        return;
+    }
+    scheduler.add(
+        "gravatars",
+        "Cache the Gravatars of users",
+        FETCH_INTERVAL,
+        move |pool| {
+            let storage = storage.clone();
+            async move { refresh(&pool, &*storage).await }
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_normalized_email() {
+        assert_eq!(
+            url(" MyEmailAddress@example.com "),
+            "https://gravatar.com/avatar/84059b07d4be67b806386c0aad8070a23f18836bbaae342275dc0a83414c32ee?s=256&d=404"
+        );
+    }
+}
@@ -0,0 +1,851 @@
+use crate::{
+    config,
+    database::{self, DatabaseError},
+    jobs::Scheduler,
+    storage::Storage,
+};
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use axum::body::Bytes;
+use image::{
+    codecs::{jpeg::JpegEncoder, png::PngEncoder},
+    imageops::FilterType,
+    DynamicImage, ImageDecoder, ImageError, ImageReader, ImageResult, Limits, Rgb, RgbImage,
+};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, BufRead, Cursor, ErrorKind, Seek},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    sync::{OwnedSemaphorePermit, Semaphore},
+    task,
+};
+use tracing::{error, warn};
+
+/// Uploads allowed to wait for or undergo processing at once.
+pub const QUEUE_CAPACITY: usize = 32;
+/// Uploads a single user may have queued at once.
+const PER_USER: usize = 2;
+/// Largest accepted width and height of an image in pixels.
+const MAX_DIMENSION: u32 = 5000;
+/// Directory of item covers.
+pub const COVERS: &str = "items";
+/// Directory of user avatars.
+pub const AVATARS: &str = "avatars";
+/// Directory of item gallery images.
+pub const GALLERY: &str = "gallery";
+/// Directories of stored images.
+const DIRECTORIES: [&str; 3] = [COVERS, AVATARS, GALLERY];
+/// Directory uploads wait in until the edit referring to them is committed.
+const STAGING: &str = "staging";
+/// Age after which staged uploads are taken to be left behind by an interrupted edit.
+const STAGING_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+/// Hex digits of the content hash images are stored under.
+const NAME_LENGTH: usize = 32;
+/// Cache policy of stored images, which never change under their name.
+pub const CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+/// Quality uploads without transparency are stored at.
+const UPLOAD_QUALITY: u8 = 90;
+/// Side in pixels avatars are scaled down to, enough for the largest variant they are shown at.
+const AVATAR_SIZE: u32 = 512;
+/// Quality of the JPEG variants.
+const JPEG_QUALITY: u8 = 85;
+/// Quality of the WebP variants.
+const WEBP_QUALITY: f32 = 80.0;
+/// Color transparent images are flattened onto for their JPEG variants.
+const BACKGROUND: [u8; 3] = [0, 0, 0];
+
+/// Resized copy of a stored image, written next to it as `<path>.<variant>.<format>` in every
+/// [`Format`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Variant {
+    /// Avatars next to reviews and comments.
+    Thumbnail,
+    /// Item cards, gallery tiles and profile avatars.
+    Card,
+    /// Covers on item pages.
+    Page,
+}
+
+impl Variant {
+    pub const ALL: [Variant; 3] = [Variant::Thumbnail, Variant::Card, Variant::Page];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Variant::Thumbnail => "thumbnail",
+            Variant::Card => "card",
+            Variant::Page => "page",
+        }
+    }
+
+    /// Longest side in pixels, smaller images are never scaled up.
+    pub fn size(self) -> u32 {
+        match self {
+            Variant::Thumbnail => 128,
+            Variant::Card => 384,
+            Variant::Page => 768,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Jpeg,
+    WebP,
+}
+
+impl Format {
+    pub const ALL: [Format; 2] = [Format::Jpeg, Format::WebP];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Jpeg => "jpg",
+            Format::WebP => "webp",
+        }
+    }
+
+    pub fn mime(self) -> &'static str {
+        match self {
+            Format::Jpeg => "image/jpeg",
+            Format::WebP => "image/webp",
+        }
+    }
+}
+
+/// Decodes an image and turns it upright according to its EXIF orientation.
+fn decode<R: BufRead + Seek>(reader: ImageReader<R>) -> ImageResult<DynamicImage> {
+    let mut decoder = reader.into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
+/// Decodes an upload within the size and dimension limits.
+fn decode_upload(image: &[u8]) -> Result<DynamicImage, DatabaseError> {
+    if image.len() > config::get().max_image_size {
+        return Err(DatabaseError::ImageTooLarge);
+    }
+    let mut reader = ImageReader::new(Cursor::new(image))
+        .with_guessed_format()
+        .map_err(|_| DatabaseError::NotValidImage)?;
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    reader.limits(limits);
+    decode(reader).map_err(|e| match e {
+        ImageError::Limits(_) => DatabaseError::ImageTooLarge,
+        _ => DatabaseError::NotValidImage,
+    })
+}
+
+/// Encodes an upload as PNG when it has transparency and as JPEG otherwise.
+fn encode_upload(image: &DynamicImage) -> Result<Bytes, DatabaseError> {
+    let mut encoded = Vec::new();
+    if image.color().has_alpha() {
+        image
+            .to_rgba8()
+            .write_with_encoder(PngEncoder::new(&mut encoded))
+    } else {
+        image
+            .to_rgb8()
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut encoded, UPLOAD_QUALITY))
+    }
+    .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    Ok(encoded.into())
+}
+
+/// Decodes an upload and encodes it again, as PNG when it has transparency and as JPEG
+/// otherwise, so that only well-formed images of a known format are ever stored. Only the
+/// upright pixels survive, EXIF data such as GPS coordinates and embedded ICC profiles are
+/// never written back.
+pub fn sanitize(image: &[u8]) -> Result<Bytes, DatabaseError> {
+    encode_upload(&decode_upload(image)?)
+}
+
+/// [Sanitizes](sanitize) an avatar, keeping the largest square in its center scaled down to
+/// [`AVATAR_SIZE`], so that round frames never cut off or stretch it.
+pub fn sanitize_avatar(image: &[u8]) -> Result<Bytes, DatabaseError> {
+    let image = decode_upload(image)?;
+    let side = image.width().min(image.height());
+    let image = image.crop_imm(
+        (image.width() - side) / 2,
+        (image.height() - side) / 2,
+        side,
+        side,
+    );
+    if side > AVATAR_SIZE {
+        encode_upload(&image.resize_exact(AVATAR_SIZE, AVATAR_SIZE, FilterType::Lanczos3))
+    } else {
+        encode_upload(&image)
+    }
+}
+
+/// Name an image is stored under, a hash of its contents so that a changed image always gets a
+/// new URL and served ones can be cached for good.
+pub fn name(image: &[u8]) -> String {
+    let mut name = format!("{:x}", Sha256::digest(image));
+    name.truncate(NAME_LENGTH);
+    name
+}
+
+/// Key of the image stored as `name` in `directory`.
+pub fn key(directory: &str, name: &str) -> String {
+    format!("{directory}/{name}")
+}
+
+/// Key of an item cover, a copy of its cover gallery image kept where item cards look for it.
+pub fn cover_key(name: &str) -> String {
+    key(COVERS, name)
+}
+
+pub fn avatar_key(name: &str) -> String {
+    key(AVATARS, name)
+}
+
+pub fn gallery_key(name: &str) -> String {
+    key(GALLERY, name)
+}
+
+/// Media type of a stored image, told by its contents.
+pub fn content_type(image: &[u8]) -> &'static str {
+    image::guess_format(image).map_or("application/octet-stream", |format| format.to_mime_type())
+}
+
+/// Path or URL of a variant of the image stored at `original`.
+pub fn variant(original: &str, variant: Variant, format: Format) -> String {
+    format!("{original}.{}.{}", variant.name(), format.extension())
+}
+
+/// Path or URL of the image a variant was made of, if `path` names a variant.
+pub fn original(path: &str) -> Option<&str> {
+    let (rest, extension) = path.rsplit_once('.')?;
+    let (original, name) = rest.rsplit_once('.')?;
+    (Format::ALL
+        .iter()
+        .any(|format| format.extension() == extension)
+        && Variant::ALL.iter().any(|variant| variant.name() == name)
+        && !original.ends_with('/'))
+    .then_some(original)
+}
+
+/// Every variant of the image stored at `original`.
+fn variants(original: &str) -> impl Iterator<Item = String> + '_ {
+    Variant::ALL.into_iter().flat_map(move |size| {
+        Format::ALL
+            .into_iter()
+            .map(move |format| variant(original, size, format))
+    })
+}
+
+fn flatten(image: &DynamicImage) -> RgbImage {
+    let image = image.to_rgba8();
+    RgbImage::from_fn(image.width(), image.height(), |x, y| {
+        let [r, g, b, a] = image.get_pixel(x, y).0;
+        let blend = |channel: u8, background: u8| {
+            ((u16::from(channel) * u16::from(a) + u16::from(background) * u16::from(255 - a)) / 255)
+                as u8
+        };
+        Rgb([
+            blend(r, BACKGROUND[0]),
+            blend(g, BACKGROUND[1]),
+            blend(b, BACKGROUND[2]),
+        ])
+    })
+}
+
+/// Variants of an image stored under a key, or why they could not be made.
+type Variants = ImageResult<Vec<(String, Bytes)>>;
+
+/// Decodes the image stored under `key` and encodes all of its variants.
+fn encode_variants(key: &str, image: &[u8]) -> Variants {
+    let image = decode(ImageReader::new(Cursor::new(image)).with_guessed_format()?)?;
+    let mut variants = Vec::new();
+    for size in Variant::ALL {
+        let resized = if image.width().max(image.height()) > size.size() {
+            image.resize(size.size(), size.size(), FilterType::Lanczos3)
+        } else {
+            image.clone()
+        };
+        let mut jpeg = Vec::new();
+        flatten(&resized)
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut jpeg, JPEG_QUALITY))?;
+        variants.push((variant(key, size, Format::Jpeg), jpeg.into()));
+        let resized = resized.to_rgba8();
+        let webp = webp::Encoder::from_rgba(&resized, resized.width(), resized.height())
+            .encode(WEBP_QUALITY);
+        variants.push((
+            variant(key, size, Format::WebP),
+            Bytes::copy_from_slice(&webp),
+        ));
+    }
+    Ok(variants)
+}
+
+/// Encodes the variants of images on the blocking pool.
+async fn resize(
+    files: Vec<(String, Bytes)>,
+) -> Result<Vec<(String, Bytes, Variants)>, DatabaseError> {
+    task::spawn_blocking(move || {
+        files
+            .into_iter()
+            .map(|(key, image)| {
+                let variants = encode_variants(&key, &image);
+                (key, image, variants)
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Stores the variants of an image. Images that cannot be decoded are kept as they are, without
+/// variants, so that they are served at their original size.
+async fn put_variants(storage: &dyn Storage, key: &str, variants: Variants) -> io::Result<()> {
+    match variants {
+        Ok(variants) => {
+            for (key, variant) in variants {
+                storage.put(&key, variant).await?;
+            }
+            Ok(())
+        }
+        Err(e) => {
+            error!(key, error = %e, "resizing image failed");
+            remove_variants(storage, key).await
+        }
+    }
+}
+
+async fn put(storage: &dyn Storage, files: Vec<(String, Bytes, Variants)>) -> io::Result<()> {
+    for (key, image, variants) in files {
+        storage.put(&key, image).await?;
+        put_variants(storage, &key, variants).await?;
+    }
+    Ok(())
+}
+
+/// Stores each image under its key along with its variants.
+pub async fn store(
+    storage: &dyn Storage,
+    files: Vec<(String, Bytes)>,
+) -> Result<(), DatabaseError> {
+    put(storage, resize(files).await?)
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+async fn remove_variants(storage: &dyn Storage, original: &str) -> io::Result<()> {
+    for key in variants(original) {
+        storage.delete(&key).await?;
+    }
+    Ok(())
+}
+
+/// Removes a stored image along with its variants.
+pub async fn remove(storage: &dyn Storage, key: &str) -> io::Result<()> {
+    storage.delete(key).await?;
+    remove_variants(storage, key).await
+}
+
+/// Copies a stored image along with its variants.
+pub async fn copy(storage: &dyn Storage, from: &str, to: &str) -> io::Result<()> {
+    storage.copy(from, to).await?;
+    for (from, to) in variants(from).zip(variants(to)) {
+        match storage.copy(&from, &to).await {
+            Err(e) if e.kind() == ErrorKind::NotFound => storage.delete(&to).await?,
+            result => result?,
+        }
+    }
+    Ok(())
+}
+
+/// Moves a stored image along with its variants. The image itself is moved last, so that an
+/// interrupted move can be picked up again from it. Variants missing under `from` are taken to be
+/// moved already, since images with the same name have the same contents.
+pub async fn rename(storage: &dyn Storage, from: &str, to: &str) -> io::Result<()> {
+    for (from, to) in variants(from).zip(variants(to)) {
+        match storage.rename(&from, &to).await {
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            result => result?,
+        }
+    }
+    storage.rename(from, to).await
+}
+
+/// Uploads of a single edit, staged outside the image directories until the transaction
+/// referring to them commits, so that a failed edit neither leaves images behind nor points at
+/// missing ones.
+pub struct Staging<'a> {
+    storage: &'a dyn Storage,
+    /// Start of the staged keys, telling when they were staged.
+    prefix: String,
+    /// Keys the staged images are moved to on commit.
+    keys: Vec<String>,
+}
+
+impl<'a> Staging<'a> {
+    pub fn new(storage: &'a dyn Storage) -> Self {
+        let staged_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        Staging {
+            storage,
+            prefix: format!("{STAGING}/{staged_at}-{:016x}", OsRng.next_u64()),
+            keys: Vec::new(),
+        }
+    }
+
+    /// Key an image to be stored under `key` is staged under.
+    fn staged(&self, key: &str) -> String {
+        format!("{}.{}", self.prefix, key.replacen('/', ".", 1))
+    }
+
+    /// Waits for a free worker and stages each uploaded image for its key along with its
+    /// variants.
+    pub async fn stage(
+        &mut self,
+        ticket: Ticket,
+        files: Vec<(String, Bytes)>,
+    ) -> Result<(), DatabaseError> {
+        let files = files
+            .into_iter()
+            .map(|(key, image)| {
+                let staged = self.staged(&key);
+                self.keys.push(key);
+                (staged, image)
+            })
+            .collect();
+        ticket.store_all(self.storage, files).await
+    }
+
+    /// Moves the staged images in place once the transaction referring to them has committed.
+    /// Images that fail to move are left for [`recover_staged`] to move later.
+    pub async fn commit(self) {
+        for key in &self.keys {
+            if let Err(e) = rename(self.storage, &self.staged(key), key).await {
+                error!(key, error = %e, "moving staged image failed");
+            }
+        }
+    }
+
+    /// Removes the staged images of an edit that failed.
+    pub async fn abort(self) {
+        for key in &self.keys {
+            if let Err(e) = remove(self.storage, &self.staged(key)).await {
+                warn!(key, error = %e, "removing staged image failed");
+            }
+        }
+    }
+}
+
+/// Finishes the edits interrupted between staging their uploads and moving them in place,
+/// moving staged images that are referred to and removing the others. Returns the number of
+/// staged images handled.
+pub async fn recover_staged(pool: &PgPool, storage: &dyn Storage) -> Result<usize, DatabaseError> {
+    let keys: HashSet<String> = storage
+        .list(STAGING)
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
+        .into_iter()
+        .collect();
+    let stale_before = SystemTime::now()
+        .checked_sub(STAGING_TIMEOUT)
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .unwrap_or_default()
+        .as_secs();
+    let mut recovered = 0;
+    for staged in &keys {
+        let Some((staged_at, directory, name)) = staged
+            .strip_prefix(&format!("{STAGING}/"))
+            .and_then(|staged| staged.split_once('-'))
+            .and_then(|(staged_at, rest)| {
+                let (_, rest) = rest.split_once('.')?;
+                let (directory, name) = rest.split_once('.')?;
+                Some((staged_at.parse::<u64>().ok()?, directory, name))
+            })
+        else {
+            continue;
+        };
+        if staged_at >= stale_before || !DIRECTORIES.contains(&directory) {
+            continue;
+        }
+        let result = match original(staged) {
+            // Variants are handled along with their image, unless it is gone.
+            Some(image) if keys.contains(image) => continue,
+            Some(_) => storage.delete(staged).await,
+            None if database::is_image_used(pool, directory, name).await? => {
+                rename(storage, staged, &key(directory, name)).await
+            }
+            None => remove(storage, staged).await,
+        };
+        result.map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+        recovered += 1;
+    }
+    Ok(recovered)
+}
+
+/// Periodically recovers uploads left staged by interrupted edits.
+pub fn schedule(scheduler: &mut Scheduler, storage: Arc<dyn Storage>) {
+    scheduler.add(
+        "staging",
+        "Move or remove uploads left staged by interrupted edits",
+        STAGING_TIMEOUT,
+        move |pool| {
+            let storage = storage.clone();
+            async move { recover_staged(&pool, &*storage).await }
+        },
+    );
+}
+
+/// Writes the variants missing for images stored before they were generated, or stored while
+/// their generation failed.
+pub async fn write_missing_variants(storage: Arc<dyn Storage>) {
+    for directory in DIRECTORIES {
+        let keys: HashSet<String> = match storage.list(directory).await {
+            Ok(keys) => keys.into_iter().collect(),
+            Err(e) => {
+                error!(directory, error = %e, "listing images failed");
+                continue;
+            }
+        };
+        for key in &keys {
+            if original(key).is_some() || variants(key).all(|variant| keys.contains(&variant)) {
+                continue;
+            }
+            let result = async {
+                let Some(image) = storage.get(key).await? else {
+                    return Ok(());
+                };
+                let variants = task::spawn_blocking({
+                    let key = key.clone();
+                    move || encode_variants(&key, &image)
+                })
+                .await?;
+                put_variants(&*storage, key, variants).await
+            }
+            .await;
+            if let Err(e) = result {
+                error!(key, error = %e, "resizing image failed");
+            }
+        }
+    }
+}
+
+/// Bounded queue for CPU-heavy image work, run on the blocking pool so that a burst of uploads
+/// cannot starve request handling.
+pub struct ImageQueue {
+    workers: Arc<Semaphore>,
+    worker_count: usize,
+    queued: Arc<Semaphore>,
+    users: Mutex<HashMap<String, usize>>,
+}
+
+impl Default for ImageQueue {
+    fn default() -> Self {
+        let worker_count = thread::available_parallelism().map_or(1, |count| count.get());
+        ImageQueue {
+            workers: Arc::new(Semaphore::new(worker_count)),
+            worker_count,
+            queued: Arc::new(Semaphore::new(QUEUE_CAPACITY)),
+            users: Mutex::default(),
+        }
+    }
+}
+
+impl ImageQueue {
+    /// Reserves a place in the queue for `username`, failing with [`DatabaseError::Busy`] when
+    /// the queue is full or the user already has too many uploads in it.
+    pub fn enqueue(self: &Arc<Self>, username: &str) -> Result<Ticket, DatabaseError> {
+        let mut users = self.users.lock().unwrap();
+        let pending = users.entry(username.to_owned()).or_default();
+        if *pending >= PER_USER {
+            return Err(DatabaseError::Busy);
+        }
+        let slot = self
+            .queued
+            .clone()
+            .try_acquire_owned()
+            .map_err(|_| DatabaseError::Busy)?;
+        *pending += 1;
+        Ok(Ticket {
+            queue: self.clone(),
+            username: username.to_owned(),
+            _slot: slot,
+        })
+    }
+
+    /// Uploads waiting for a worker or being processed.
+    pub fn depth(&self) -> usize {
+        QUEUE_CAPACITY - self.queued.available_permits()
+    }
+
+    /// Uploads being processed.
+    pub fn in_flight(&self) -> usize {
+        self.worker_count - self.workers.available_permits()
+    }
+}
+
+/// A reserved place in the [`ImageQueue`], released once the upload is processed or dropped.
+pub struct Ticket {
+    queue: Arc<ImageQueue>,
+    username: String,
+    _slot: OwnedSemaphorePermit,
+}
+
+impl Ticket {
+    async fn worker(&self) -> Result<OwnedSemaphorePermit, DatabaseError> {
+        self.queue
+            .workers
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|e| DatabaseError::InternalError(Box::new(e)))
+    }
+
+    /// Waits for a free worker and runs `work` on the blocking pool.
+    async fn process<T: Send + 'static>(
+        &self,
+        work: impl FnOnce() -> Result<T, DatabaseError> + Send + 'static,
+    ) -> Result<T, DatabaseError> {
+        let _worker = self.worker().await?;
+        task::spawn_blocking(work)
+            .await
+            .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
+    }
+
+    /// Waits for a free worker and [sanitizes](sanitize) the uploaded images, failing on the
+    /// first one that is not valid.
+    pub async fn sanitize(&self, images: Vec<Bytes>) -> Result<Vec<Bytes>, DatabaseError> {
+        self.process(move || images.iter().map(|image| sanitize(image)).collect())
+            .await
+    }
+
+    /// Waits for a free worker and [sanitizes](sanitize_avatar) an uploaded avatar.
+    pub async fn sanitize_avatar(&self, avatar: Bytes) -> Result<Bytes, DatabaseError> {
+        self.process(move || sanitize_avatar(&avatar)).await
+    }
+
+    /// Waits for a free worker and stores each uploaded image under its key along with its
+    /// variants.
+    pub async fn store_all(
+        self,
+        storage: &dyn Storage,
+        files: Vec<(String, Bytes)>,
+    ) -> Result<(), DatabaseError> {
+        let files = {
+            let _worker = self.worker().await?;
+            resize(files).await?
+        };
+        put(storage, files)
+            .await
+            .map_err(|e| DatabaseError::InternalError(Box::new(e)))
+    }
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        let mut users = self.queue.users.lock().unwrap();
+        if let Some(pending) = users.get_mut(&self.username) {
+            *pending -= 1;
+            if *pending == 0 {
+                users.remove(&self.username);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Local;
+
+    #[test]
+    fn limits_uploads_per_user() {
+        let queue = Arc::new(ImageQueue::default());
+        let first = queue.enqueue("test1").unwrap();
+        let _second = queue.enqueue("test1").unwrap();
+        assert!(matches!(queue.enqueue("test1"), Err(DatabaseError::Busy)));
+        assert!(queue.enqueue("test2").is_ok());
+        assert_eq!(queue.depth(), 2);
+        drop(first);
+        assert!(queue.enqueue("test1").is_ok());
+    }
+
+    #[test]
+    fn rejects_uploads_when_full() {
+        let queue = Arc::new(ImageQueue::default());
+        let _tickets: Vec<Ticket> = (0..QUEUE_CAPACITY)
+            .map(|user| queue.enqueue(&user.to_string()).unwrap())
+            .collect();
+        assert_eq!(queue.depth(), QUEUE_CAPACITY);
+        assert!(matches!(queue.enqueue("admin"), Err(DatabaseError::Busy)));
+    }
+
+    fn encode(image: DynamicImage) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut encoded), image::ImageFormat::Png)
+            .unwrap();
+        encoded
+    }
+
+    #[test]
+    fn sanitizes_uploads() {
+        let opaque = sanitize(&encode(DynamicImage::new_rgb8(10, 10))).unwrap();
+        assert_eq!(
+            image::guess_format(&opaque).unwrap(),
+            image::ImageFormat::Jpeg
+        );
+        let transparent = sanitize(&encode(DynamicImage::new_rgba8(10, 10))).unwrap();
+        assert_eq!(
+            image::guess_format(&transparent).unwrap(),
+            image::ImageFormat::Png
+        );
+        assert!(matches!(
+            sanitize(b"<svg/>"),
+            Err(DatabaseError::NotValidImage)
+        ));
+        let mut truncated = encode(DynamicImage::new_rgb8(10, 10));
+        truncated.truncate(truncated.len() / 2);
+        assert!(matches!(
+            sanitize(&truncated),
+            Err(DatabaseError::NotValidImage)
+        ));
+        assert!(matches!(
+            sanitize(&encode(DynamicImage::new_luma8(MAX_DIMENSION + 1, 1))),
+            Err(DatabaseError::ImageTooLarge)
+        ));
+        assert!(matches!(
+            sanitize(&vec![0; config::get().max_image_size + 1]),
+            Err(DatabaseError::ImageTooLarge)
+        ));
+    }
+
+    #[test]
+    fn strips_metadata_from_uploads() {
+        // EXIF saying the photo is rotated by 90 degrees and where it was taken.
+        let mut exif = b"Exif\0\0MM\0\x2a\0\0\0\x08\0\x02".to_vec();
+        exif.extend([0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, 6, 0, 0]);
+        exif.extend([0x88, 0x25, 0, 4, 0, 0, 0, 1, 0, 0, 0, 38]);
+        exif.extend([0, 0, 0, 0, 0, 1]);
+        exif.extend([0, 1, 0, 2, 0, 0, 0, 2, b'N', 0, 0, 0, 0, 0, 0, 0]);
+        let mut photo = Vec::new();
+        DynamicImage::new_rgb8(20, 10)
+            .write_to(&mut Cursor::new(&mut photo), image::ImageFormat::Jpeg)
+            .unwrap();
+        let mut segment = vec![0xff, 0xe1];
+        segment.extend(u16::try_from(exif.len() + 2).unwrap().to_be_bytes());
+        segment.extend(exif);
+        photo.splice(2..2, segment);
+        let sanitized = sanitize(&photo).unwrap();
+        assert!(!sanitized.windows(4).any(|window| window == b"Exif"));
+        let image = image::load_from_memory(&sanitized).unwrap();
+        assert_eq!((image.width(), image.height()), (10, 20));
+    }
+
+    #[test]
+    fn crops_avatars_to_squares() {
+        let avatar = |width, height| {
+            let avatar = sanitize_avatar(&encode(DynamicImage::new_rgb8(width, height))).unwrap();
+            let avatar = image::load_from_memory(&avatar).unwrap();
+            (avatar.width(), avatar.height())
+        };
+        assert_eq!(avatar(300, 100), (100, 100));
+        assert_eq!(avatar(100, 300), (100, 100));
+        assert_eq!(avatar(1200, 900), (AVATAR_SIZE, AVATAR_SIZE));
+        let banner = RgbImage::from_fn(300, 100, |x, _| {
+            Rgb(if (100..200).contains(&x) {
+                [255; 3]
+            } else {
+                [0; 3]
+            })
+        });
+        let cropped = sanitize_avatar(&encode(banner.into())).unwrap();
+        let cropped = image::load_from_memory(&cropped).unwrap().to_luma8();
+        assert!(cropped.pixels().all(|pixel| pixel.0[0] > 200));
+        assert!(matches!(
+            sanitize_avatar(b"<svg/>"),
+            Err(DatabaseError::NotValidImage)
+        ));
+    }
+
+    #[test]
+    fn names_images_by_contents() {
+        assert_eq!(name(b"image"), name(b"image"));
+        assert_ne!(name(b"image"), name(b"other image"));
+        assert_eq!(name(b"image").len(), NAME_LENGTH);
+        assert!(name(b"image").chars().all(|c| c.is_ascii_hexdigit()));
+    }
+
+    #[test]
+    fn finds_originals_of_variants() {
+        let path = variant("/static/images/items/test", Variant::Card, Format::WebP);
+        assert_eq!(path, "/static/images/items/test.card.webp");
+        assert_eq!(original(&path), Some("/static/images/items/test"));
+        assert_eq!(original("/static/images/items/test"), None);
+        assert_eq!(original("/static/images/items/test.card.png"), None);
+        assert_eq!(original("/static/images/items/.card.jpg"), None);
+    }
+
+    #[tokio::test]
+    async fn stores_resized_variants() {
+        let root = std::env::temp_dir().join(format!("zai-images-{}", std::process::id()));
+        let storage = Local::new(&root);
+        let image = encode(DynamicImage::new_rgba8(1000, 500));
+        store(&storage, vec![("items/test".to_owned(), image.into())])
+            .await
+            .unwrap();
+        for size in Variant::ALL {
+            for format in Format::ALL {
+                let resized = image::open(root.join(variant("items/test", size, format))).unwrap();
+                assert_eq!(resized.width(), size.size());
+                assert_eq!(resized.height(), size.size() / 2);
+            }
+        }
+        copy(&storage, "items/test", "items/copy").await.unwrap();
+        assert_eq!(storage.list("items").await.unwrap().len(), 14);
+        store(
+            &storage,
+            vec![("items/copy".to_owned(), Bytes::from("not an image"))],
+        )
+        .await
+        .unwrap();
+        remove(&storage, "items/test").await.unwrap();
+        assert_eq!(storage.list("items").await.unwrap(), ["items/copy"]);
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn stages_uploads_until_committed() {
+        let root = std::env::temp_dir().join(format!("zai-staging-{}", std::process::id()));
+        let storage = Local::new(&root);
+        let queue = Arc::new(ImageQueue::default());
+        let image = Bytes::from(encode(DynamicImage::new_rgba8(100, 100)));
+        let mut staging = Staging::new(&storage);
+        let files = vec![("items/kept".to_owned(), image.clone())];
+        staging
+            .stage(queue.enqueue("user").unwrap(), files)
+            .await
+            .unwrap();
+        assert!(storage.list("items").await.unwrap().is_empty());
+        assert_eq!(storage.list(STAGING).await.unwrap().len(), 7);
+        staging.commit().await;
+        assert!(storage.list(STAGING).await.unwrap().is_empty());
+        assert_eq!(storage.list("items").await.unwrap().len(), 7);
+        let mut staging = Staging::new(&storage);
+        let files = vec![("items/dropped".to_owned(), image)];
+        staging
+            .stage(queue.enqueue("user").unwrap(), files)
+            .await
+            .unwrap();
+        staging.abort().await;
+        assert!(storage.list(STAGING).await.unwrap().is_empty());
+        assert_eq!(storage.list("items").await.unwrap().len(), 7);
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,111 @@
+use crate::database::DatabaseError;
+use csv::ReaderBuilder;
+
+const TITLE_COLUMNS: [&str; 4] = ["locator", "title", "name", "series_title"];
+const RATING_COLUMNS: [&str; 3] = ["rating", "score", "my_score"];
+/// Title similarity from which a fuzzy match is preselected for import.
+pub const CONFIDENT_MATCH: f32 = 0.5;
+/// Column identifying Letterboxd exports, which rate on a 0.5 to 5 scale.
+const LETTERBOXD_COLUMN: &str = "letterboxd uri";
+
+/// A rating read from an imported CSV file, before it is matched to an item.
+#[derive(Debug, PartialEq)]
+pub struct ImportRow {
+    pub query: String,
+    pub rating: i16,
+}
+
+/// Reads ratings from a CSV export with a header row, taking the item from the first locator or
+/// title column and the rating from the first score column. Rows without a rating are skipped.
+pub fn parse_csv(data: &[u8]) -> Result<Vec<ImportRow>, DatabaseError> {
+    let mut reader = ReaderBuilder::new().flexible(true).from_reader(data);
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|_| DatabaseError::InvalidCsv)?
+        .iter()
+        .map(|header| header.trim().to_lowercase())
+        .collect();
+    let column = |names: &[&str]| {
+        names
+            .iter()
+            .find_map(|name| headers.iter().position(|header| header == name))
+    };
+    let (Some(query_column), Some(rating_column)) =
+        (column(&TITLE_COLUMNS), column(&RATING_COLUMNS))
+    else {
+        return Err(DatabaseError::InvalidCsv);
+    };
+    let scale = if headers.iter().any(|header| header == LETTERBOXD_COLUMN) {
+        2.0
+    } else {
+        1.0
+    };
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|_| DatabaseError::InvalidCsv)?;
+        let query = record.get(query_column).unwrap_or_default().trim();
+        let rating = record
+            .get(rating_column)
+            .and_then(|rating| rating.trim().parse::<f32>().ok())
+            .filter(|rating| *rating > 0.0);
+        if let (false, Some(rating)) = (query.is_empty(), rating) {
+            rows.push(ImportRow {
+                query: query.to_owned(),
+                rating: (rating * scale).round().clamp(1.0, 10.0) as i16,
+            });
+        }
+    }
+    if rows.is_empty() {
+        Err(DatabaseError::InvalidCsv)
+    } else {
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(query: &str, rating: i16) -> ImportRow {
+        ImportRow {
+            query: query.to_owned(),
+            rating,
+        }
+    }
+
+    #[test]
+    fn reads_title_and_score_columns() {
+        let rows = parse_csv(b"Series_Title,My_Score\nErgo Proxy,9\nFLCL,7\n").unwrap();
+        assert_eq!(rows, [row("Ergo Proxy", 9), row("FLCL", 7)]);
+    }
+
+    #[test]
+    fn prefers_locator_over_title() {
+        let rows = parse_csv(b"title,locator,rating\nErgo Proxy,ergo_proxy,8\n").unwrap();
+        assert_eq!(rows, [row("ergo_proxy", 8)]);
+    }
+
+    #[test]
+    fn scales_letterboxd_ratings() {
+        let rows = parse_csv(
+            b"Date,Name,Year,Letterboxd URI,Rating\n2024-01-01,Spirited Away,2001,https://boxd.it/x,4.5\n2024-01-02,Psycho-Pass,2012,https://boxd.it/y,0.5\n",
+        )
+        .unwrap();
+        assert_eq!(rows, [row("Spirited Away", 9), row("Psycho-Pass", 1)]);
+    }
+
+    #[test]
+    fn skips_unrated_rows() {
+        let rows = parse_csv(b"title,score\nBNA,0\nBeastars,\n,5\nFLCL,11\n").unwrap();
+        assert_eq!(rows, [row("FLCL", 10)]);
+    }
+
+    #[test]
+    fn rejects_files_without_known_columns() {
+        assert!(matches!(
+            parse_csv(b"foo,bar\n1,2\n"),
+            Err(DatabaseError::InvalidCsv)
+        ));
+        assert!(matches!(parse_csv(b""), Err(DatabaseError::InvalidCsv)));
+    }
+}
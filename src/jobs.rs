@@ -0,0 +1,373 @@
+//! Background jobs, run periodically by a [`Scheduler`]. The schedule is kept in the `jobs` table
+//! rather than in memory, so that a restart does not run every job at once, and a run is claimed
+//! by moving the next run forward, so that several instances of the app never run a job twice.
+//! The table also keeps the outcome of the last run for the admin page.
+
+use crate::{
+    config::Config,
+    database::{self, DatabaseError},
+    images,
+    storage::Storage,
+};
+use futures_util::future::BoxFuture;
+use sqlx::{query, query_as, query_scalar, types::chrono::NaiveDateTime, PgPool};
+use std::{
+    fmt::{self, Display, Formatter},
+    future::Future,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::time::sleep;
+use tracing::error;
+
+/// Longest wait between checks whether a job is due, so that a job run from the admin page starts
+/// soon after.
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Runs a job, returning a summary of what it did, if it reports one, or the error it failed with.
+type Run = Arc<dyn Fn(PgPool) -> BoxFuture<'static, Result<Option<String>, String>> + Send + Sync>;
+
+struct Job {
+    name: &'static str,
+    description: &'static str,
+    interval: Duration,
+    run: Run,
+}
+
+/// Jobs of the app, registered by the modules doing the work and spawned together.
+pub struct Scheduler {
+    pool: PgPool,
+    jobs: Vec<Job>,
+}
+
+impl Scheduler {
+    pub fn new(pool: PgPool) -> Self {
+        Scheduler {
+            pool,
+            jobs: Vec::new(),
+        }
+    }
+
+    /// Runs `run` every `interval`, failures being logged and shown on the admin page.
+    pub fn add<F, Fut, T, E>(
+        &mut self,
+        name: &'static str,
+        description: &'static str,
+        interval: Duration,
+        run: F,
+    ) where
+        F: Fn(PgPool) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<T, E>> + Send + 'static,
+        E: Display,
+    {
+        let run = Arc::new(run);
+        self.jobs.push(Job {
+            name,
+            description,
+            interval,
+            run: Arc::new(move |pool| {
+                let run = run.clone();
+                Box::pin(async move { run(pool).await.map(|_| None).map_err(|e| e.to_string()) })
+            }),
+        });
+    }
+
+    /// Like [`add`](Self::add), also showing what the last successful run returned on the admin
+    /// page.
+    pub fn add_reporting<F, Fut, T, E>(
+        &mut self,
+        name: &'static str,
+        description: &'static str,
+        interval: Duration,
+        run: F,
+    ) where
+        F: Fn(PgPool) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<T, E>> + Send + 'static,
+        T: Display,
+        E: Display,
+    {
+        let run = Arc::new(run);
+        self.jobs.push(Job {
+            name,
+            description,
+            interval,
+            run: Arc::new(move |pool| {
+                let run = run.clone();
+                Box::pin(async move {
+                    run(pool)
+                        .await
+                        .map(|summary| Some(summary.to_string()))
+                        .map_err(|e| e.to_string())
+                })
+            }),
+        });
+    }
+
+    /// Spawns a task for every job.
+    pub fn spawn(self) {
+        for job in self.jobs {
+            tokio::spawn(run(self.pool.clone(), job));
+        }
+    }
+}
+
+async fn run(pool: PgPool, job: Job) {
+    if let Err(e) = register(&pool, &job).await {
+        error!(job = job.name, error = %e, "registering job failed");
+    }
+    loop {
+        match claim(&pool, &job).await {
+            Ok(true) => {
+                let result = (job.run)(pool.clone()).await;
+                if let Err(e) = &result {
+                    error!(job = job.name, error = %e, "job failed");
+                }
+                if let Err(e) = finish(&pool, job.name, result).await {
+                    error!(job = job.name, error = %e, "recording job run failed");
+                }
+            }
+            Ok(false) => {}
+            Err(e) => error!(job = job.name, error = %e, "claiming job failed"),
+        }
+        let wait = until_due(&pool, job.name).await.unwrap_or(POLL_INTERVAL);
+        sleep(wait.min(POLL_INTERVAL)).await;
+    }
+}
+
+/// Adds the job to the table, or updates it, bringing the next run forward if the interval
+/// shrank.
+async fn register(pool: &PgPool, job: &Job) -> Result<(), DatabaseError> {
+    let interval = i32::try_from(job.interval.as_secs()).unwrap_or(i32::MAX);
+    query!("INSERT INTO jobs(name, description, interval_seconds) VALUES ($1, $2, $3) ON CONFLICT (name) DO UPDATE SET description = EXCLUDED.description, interval_seconds = EXCLUDED.interval_seconds, next_run_at = LEAST(jobs.next_run_at, now() + make_interval(secs => EXCLUDED.interval_seconds))", job.name, job.description, interval)
+        .execute(pool)
+        .await
+        .map(drop)
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Claims the run of a due job by scheduling the next one, returning whether it was due.
+async fn claim(pool: &PgPool, job: &Job) -> Result<bool, DatabaseError> {
+    query_scalar!("UPDATE jobs SET started_at = now(), next_run_at = now() + make_interval(secs => interval_seconds) WHERE name = $1 AND next_run_at <= now() RETURNING name", job.name)
+        .fetch_optional(pool)
+        .await
+        .map(|name| name.is_some())
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+async fn finish(
+    pool: &PgPool,
+    name: &str,
+    result: Result<Option<String>, String>,
+) -> Result<(), DatabaseError> {
+    let (summary, error) = match result {
+        Ok(summary) => (summary, None),
+        Err(e) => (None, Some(e)),
+    };
+    query!("UPDATE jobs SET finished_at = now(), last_error = $2, last_result = $3, runs = runs + 1, failures = failures + (CASE WHEN $2::TEXT IS NULL THEN 0 ELSE 1 END) WHERE name = $1", name, error, summary)
+        .execute(pool)
+        .await
+        .map(drop)
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+async fn until_due(pool: &PgPool, name: &str) -> Result<Duration, DatabaseError> {
+    query_scalar!(r#"SELECT GREATEST(EXTRACT(EPOCH FROM next_run_at - now()), 0)::FLOAT8 AS "wait!" FROM jobs WHERE name = $1"#, name)
+        .fetch_one(pool)
+        .await
+        .map(Duration::from_secs_f64)
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// A job as shown on the admin page.
+pub struct JobStatus {
+    pub name: String,
+    pub description: String,
+    pub interval_seconds: i32,
+    pub next_run_at: NaiveDateTime,
+    pub started_at: Option<NaiveDateTime>,
+    pub finished_at: Option<NaiveDateTime>,
+    pub last_error: Option<String>,
+    /// What the last successful run did, for jobs reporting it.
+    pub last_result: Option<String>,
+    pub runs: i32,
+    pub failures: i32,
+}
+
+impl JobStatus {
+    /// Whether the job is running now, or was when the app stopped.
+    pub fn is_running(&self) -> bool {
+        match (self.started_at, self.finished_at) {
+            (Some(started_at), Some(finished_at)) => started_at > finished_at,
+            (started_at, _) => started_at.is_some(),
+        }
+    }
+
+    /// How long the last finished run took.
+    pub fn last_duration(&self) -> Option<chrono::Duration> {
+        match (self.started_at, self.finished_at) {
+            (Some(started_at), Some(finished_at)) if !self.is_running() => {
+                Some(finished_at - started_at)
+            }
+            _ => None,
+        }
+    }
+}
+
+pub async fn get_jobs(pool: &PgPool) -> Result<Vec<JobStatus>, DatabaseError> {
+    query_as!(JobStatus, "SELECT name, description, interval_seconds, next_run_at, started_at, finished_at, last_error, last_result, runs, failures FROM jobs ORDER BY name")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Makes a job due now, returning whether it exists.
+pub async fn run_now(pool: &PgPool, name: &str) -> Result<bool, DatabaseError> {
+    query_scalar!(
+        "UPDATE jobs SET next_run_at = now() WHERE name = $1 RETURNING name",
+        name
+    )
+    .fetch_optional(pool)
+    .await
+    .map(|name| name.is_some())
+    .map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// How many records a [`clean_up`] deleted.
+#[derive(Debug, Default, PartialEq)]
+pub struct Cleanup {
+    pub mail: u64,
+    pub webhook_deliveries: u64,
+    pub activitypub_deliveries: u64,
+    pub email_tokens: u64,
+    pub accounts: u64,
+}
+
+impl Display for Cleanup {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Deleted {} sent mails, {} webhook deliveries, {} activity deliveries, {} expired email tokens and {} unverified accounts",
+            self.mail, self.webhook_deliveries, self.activitypub_deliveries, self.email_tokens, self.accounts
+        )
+    }
+}
+
+/// Deletes sent mail and delivered webhooks and activities past their retention, expired email
+/// confirmation tokens, and, if enabled, accounts that never confirmed an email address in time.
+/// Administrators are never deleted.
+pub async fn clean_up(
+    pool: &PgPool,
+    storage: &dyn Storage,
+    config: &Config,
+) -> Result<Cleanup, DatabaseError> {
+    let retention_days = i32::try_from(config.retention_days).unwrap_or(i32::MAX);
+    let account_days = i32::try_from(config.unverified_account_days).unwrap_or(i32::MAX);
+    let mut transaction = pool
+        .begin()
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    let mut deleted = Cleanup::default();
+    for (count, result) in [
+        (
+            &mut deleted.mail,
+            query!("DELETE FROM mail_jobs WHERE sent_at < now() - make_interval(days => $1)", retention_days)
+                .execute(&mut *transaction)
+                .await,
+        ),
+        (
+            &mut deleted.webhook_deliveries,
+            query!("DELETE FROM webhook_deliveries WHERE delivered_at < now() - make_interval(days => $1)", retention_days)
+                .execute(&mut *transaction)
+                .await,
+        ),
+        (
+            &mut deleted.activitypub_deliveries,
+            query!("DELETE FROM activitypub_deliveries WHERE delivered_at < now() - make_interval(days => $1)", retention_days)
+                .execute(&mut *transaction)
+                .await,
+        ),
+        (
+            &mut deleted.email_tokens,
+            query!("UPDATE users SET email_token = NULL, email_token_created = NULL WHERE email_token_created <= now() - make_interval(hours => $1)", database::EMAIL_TOKEN_HOURS)
+                .execute(&mut *transaction)
+                .await,
+        ),
+    ] {
+        *count = result
+            .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
+            .rows_affected();
+    }
+    let avatars = if account_days > 0 {
+        query_scalar!("DELETE FROM users WHERE first_verified IS NULL AND NOT is_admin AND registered < now() - make_interval(days => $1) RETURNING avatar", account_days)
+            .fetch_all(&mut *transaction)
+            .await
+            .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
+    } else {
+        Vec::new()
+    };
+    deleted.accounts = avatars.len() as u64;
+    transaction
+        .commit()
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    for avatar in avatars.iter().flatten() {
+        if !database::is_image_used(pool, images::AVATARS, avatar).await? {
+            images::remove(storage, &images::key(images::AVATARS, avatar))
+                .await
+                .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+        }
+    }
+    Ok(deleted)
+}
+
+/// Cleans up delivered messages, expired tokens and unverified accounts daily in the background.
+pub fn schedule(scheduler: &mut Scheduler, storage: Arc<dyn Storage>, config: &'static Config) {
+    scheduler.add_reporting(
+        "cleanup",
+        "Delete sent mail, delivered webhooks and activities, expired email tokens and unverified accounts",
+        CLEANUP_INTERVAL,
+        move |pool| {
+            let storage = storage.clone();
+            async move { clean_up(&pool, &*storage, config).await }
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn tells_running_jobs_apart() {
+        let at = |minute| {
+            NaiveDate::from_ymd_opt(2024, 6, 26)
+                .unwrap()
+                .and_hms_opt(12, minute, 0)
+                .unwrap()
+        };
+        let mut job = JobStatus {
+            name: "cleanup".to_owned(),
+            description: String::new(),
+            interval_seconds: 60,
+            next_run_at: at(0),
+            started_at: None,
+            finished_at: None,
+            last_error: None,
+            last_result: None,
+            runs: 0,
+            failures: 0,
+        };
+        assert!(!job.is_running());
+        job.started_at = Some(at(1));
+        assert!(job.is_running());
+        assert_eq!(job.last_duration(), None);
+        job.finished_at = Some(at(3));
+        assert!(!job.is_running());
+        assert_eq!(job.last_duration(), Some(chrono::Duration::minutes(2)));
+        job.started_at = Some(at(4));
+        assert!(job.is_running());
+        assert_eq!(job.last_duration(), None);
+    }
+}
@@ -1,31 +1,98 @@
 use axum::{
-    extract::{Multipart, Path, Query, Request, State},
-    http::{StatusCode, Uri},
-    middleware::{from_fn, Next},
-    response::{IntoResponse, Redirect},
-    routing::{get, post},
-    Form, Router,
+    body::Bytes,
+    extract::{DefaultBodyLimit, Multipart, Path, Query, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode, Uri},
+    middleware::{from_fn, from_fn_with_state, Next},
+    response::{IntoResponse, Redirect, Response},
+    routing::{delete, get, post},
+    Extension, Form, Router,
 };
-use axum_htmx::{HxBoosted, HxCurrentUrl, HxLocation, HxPushUrl, HxReplaceUrl, HxRequest};
-use axum_session::{Session, SessionLayer, SessionNullPool, SessionStore};
+use axum_htmx::{
+    HxBoosted, HxCurrentUrl, HxLocation, HxPushUrl, HxReplaceUrl, HxRequest, HxReswap, HxRetarget,
+    HxTrigger, SwapOption,
+};
+use axum_session::{Session, SessionConfig, SessionLayer, SessionNullPool, SessionStore};
+use chrono::Duration;
+use clap::Parser;
 use dotenvy::dotenv;
+use error::AppError;
+use forms::Validated;
+use log::LevelFilter;
 use serde::Deserialize;
-use sqlx::{migrate::MigrateDatabase, PgPool, Postgres};
-use std::{collections::HashMap, env};
-use tokio::{
-    fs::{remove_file, rename, try_exists, File},
-    io::AsyncWriteExt,
-    net::TcpListener,
+use sessions::{AdminUser, AuthUser, CurrentUser};
+use sqlx::{
+    migrate::MigrateDatabase,
+    postgres::{PgConnectOptions, PgPoolOptions},
+    ConnectOptions, PgConnection, PgPool, Postgres,
+};
+use std::{collections::HashMap, env, process, sync::Arc};
+use tower_http::{
+    compression::CompressionLayer, services::ServeDir, set_header::SetResponseHeader,
 };
-use tower_http::services::ServeDir;
 
+mod activitypub;
+mod admin;
+mod assets;
+mod audit;
+mod badges;
+mod cache;
+mod cards;
+mod charts;
+mod cli;
+mod config;
 mod database;
+mod emails;
+mod error;
+mod etag;
+mod export;
+mod forms;
+mod gravatar;
+mod images;
+mod import;
+mod jobs;
+mod mailer;
+mod markdown;
+mod metadata;
+mod metrics;
+mod password;
+mod proxy;
+mod rate_limit;
+mod reactions;
+mod recommendations;
+mod releases;
+mod resilience;
+mod routes;
+mod search;
+mod security;
+mod seed;
+mod server;
+mod sessions;
+mod stats;
+mod storage;
 mod svg;
 mod templates;
+mod version;
+mod webhooks;
 
 #[tokio::main]
 async fn main() {
     dotenv().unwrap();
+    let command = cli::Cli::parse().command.unwrap_or(cli::Command::Serve);
+    metrics::install_tracing();
+    let config = match config::Config::load() {
+        Ok(config) => config::install(config),
+        Err(e) => {
+            eprintln!("Invalid configuration: {e}");
+            process::exit(1);
+        }
+    };
+    match password::PasswordPolicy::from_config(config) {
+        Ok(policy) => password::install(policy),
+        Err(e) => {
+            eprintln!("Invalid configuration: {e}");
+            process::exit(1);
+        }
+    }
     let database_url = env::var("DATABASE_URL").unwrap();
     if !Postgres::database_exists(&database_url)
         .await
@@ -33,55 +100,350 @@ async fn main() {
     {
         Postgres::create_database(&database_url).await.unwrap();
     }
-    let pool = PgPool::connect_lazy(&database_url).unwrap();
+    let connect_options = database_url
+        .parse::<PgConnectOptions>()
+        .unwrap()
+        .log_slow_statements(
+            match config.slow_query_ms {
+                0 => LevelFilter::Off,
+                _ => LevelFilter::Warn,
+            },
+            std::time::Duration::from_millis(config.slow_query_ms),
+        );
+    let pool = PgPoolOptions::new()
+        .acquire_timeout(resilience::ACQUIRE_TIMEOUT)
+        .after_connect(|connection, _| Box::pin(database::configure_connection(connection)))
+        .connect_lazy_with(connect_options);
     sqlx::migrate!().run(&pool).await.unwrap();
-    let static_service = ServeDir::new("static");
-    let session_store = SessionStore::<SessionNullPool>::new(None, Default::default())
+    let storage = storage::from_env(config);
+    match cli::run(&pool, &*storage, command).await {
+        Ok(None) => {}
+        Ok(Some(message)) => {
+            println!("{message}");
+            pool.close().await;
+            return;
+        }
+        Err(e) => {
+            eprintln!("{}", cli::describe(&e));
+            process::exit(1);
+        }
+    }
+    let mut scheduler = jobs::Scheduler::new(pool.clone());
+    recommendations::schedule(&mut scheduler);
+    releases::schedule(&mut scheduler);
+    charts::schedule(&mut scheduler);
+    mailer::schedule(&mut scheduler);
+    mailer::schedule_digests(&mut scheduler);
+    mailer::schedule_weekly(&mut scheduler);
+    badges::schedule(&mut scheduler);
+    gravatar::schedule(&mut scheduler, storage.clone());
+    images::schedule(&mut scheduler, storage.clone());
+    webhooks::schedule(&mut scheduler);
+    activitypub::schedule(&mut scheduler);
+    jobs::schedule(&mut scheduler, storage.clone(), config);
+    scheduler.spawn();
+    tokio::spawn(images::write_missing_variants(storage.clone()));
+    if let Err(e) = assets::precompress(config.static_dir.clone()).await {
+        eprintln!("Failed to precompress static assets: {e}");
+    }
+    if let Err(e) = assets::fingerprint(config.static_dir.clone(), config.image_dir.clone()).await {
+        eprintln!("Failed to fingerprint static assets: {e}");
+    }
+    let missing = assets::missing_vendored(&config.static_dir);
+    if !missing.is_empty() {
+        eprintln!(
+            "Vendored assets are missing from {}: {}. Run `make vendor` to fetch them.",
+            config.static_dir.display(),
+            missing.join(", ")
+        );
+        process::exit(1);
+    }
+    // Sessions are only kept in memory, so they have to stay loaded for as long as they last.
+    let lifetime = Duration::hours(config.session_lifetime_hours);
+    let session_config = SessionConfig::default()
+        .with_lifetime(lifetime)
+        .with_memory_lifetime(lifetime)
+        .with_secure(config.tls_cert.is_some());
+    let session_store = SessionStore::<SessionNullPool>::new(None, session_config)
         .await
         .unwrap();
-    let app = Router::new()
-        .route("/", get(index_handler))
-        .route("/login", get(login_form_handler).post(login_handler))
+    if let Err(e) = server::serve(app(pool.clone(), session_store, storage, config), config).await {
+        eprintln!("Failed to serve: {e}");
+        process::exit(1);
+    }
+    pool.close().await;
+}
+
+fn app(
+    pool: PgPool,
+    session_store: SessionStore<SessionNullPool>,
+    storage: Arc<dyn storage::Storage>,
+    config: &config::Config,
+) -> Router {
+    let image_service = SetResponseHeader::if_not_present(
+        ServeDir::new(&config.image_dir)
+            .fallback(get(image_fallback_handler).with_state(pool.clone())),
+        header::CACHE_CONTROL,
+        |response: &Response<_>| {
+            response
+                .status()
+                .is_success()
+                .then_some(HeaderValue::from_static(images::CACHE_CONTROL))
+        },
+    );
+    let static_service = SetResponseHeader::if_not_present(
+        ServeDir::new(&config.static_dir)
+            .precompressed_br()
+            .precompressed_gzip(),
+        header::CACHE_CONTROL,
+        |response: &Response<_>| {
+            response
+                .status()
+                .is_success()
+                .then_some(HeaderValue::from_static(assets::CACHE_CONTROL))
+        },
+    );
+    let latencies = Arc::new(metrics::Latencies::default());
+    // Seeding fills the database with fake data, so only development builds offer it.
+    let router = if cfg!(debug_assertions) {
+        Router::new().route(routes::ADMIN_SEED, post(seed_handler))
+    } else {
+        Router::new()
+    };
+    let router = router
+        .route(routes::INDEX, get(index_handler))
+        .route(routes::SCRIPTS, get(scripts_handler))
+        .route(routes::LOGIN, get(login_form_handler).post(login_handler))
         .route(
-            "/register",
+            routes::REGISTER,
             get(register_form_handler).post(register_handler),
         )
-        .route("/logout", post(logout_handler))
-        .route("/search", get(search_handler))
-        .route("/items", get(item_view_handler))
+        .route(routes::PASSWORD_RESET, post(password_reset_handler))
+        .route(routes::VERIFY_EMAIL, get(verify_email_handler))
+        .route(routes::LOGOUT, post(logout_handler))
+        .route(routes::MARKDOWN_PREVIEW, post(markdown_preview_handler))
+        .route(routes::SEARCH, get(search_handler))
+        .route(routes::SEARCH_RESULTS, get(search_results_handler))
+        .route(routes::ITEMS, get(item_view_handler))
+        .route(routes::ITEMS_TRENDING, get(trending_view_handler))
+        .route(routes::ITEMS_NEW, get(new_items_view_handler))
+        .route(routes::CHARTS, get(charts_view_handler))
+        .route(routes::CHART, get(chart_view_handler))
         .route(
-            "/items/add",
+            routes::ITEM_ADD,
             get(item_add_form_handler).post(item_add_handler),
         )
-        .route("/items/:item", get(item_handler))
+        .route(routes::ITEMS_METADATA, post(item_metadata_handler))
+        .route(routes::ITEM, get(item_handler))
         .route(
-            "/items/:item/edit",
+            routes::ITEM_EDIT,
             get(item_edit_form_handler).post(item_edit_handler),
         )
         .route(
-            "/items/:item/remove",
+            routes::ITEM_REMOVE,
             get(item_remove_form_handler).post(item_remove_handler),
         )
         .route(
-            "/items/:item/rate",
+            routes::ITEM_MERGE,
+            get(item_merge_form_handler).post(item_merge_handler),
+        )
+        .route(
+            routes::ITEM_RATE,
             post(review_add_handler).delete(review_remove_handler),
         )
-        .route("/users", get(user_view_handler))
-        .route("/users/:user", get(user_handler))
+        .route(routes::ITEM_RATING, delete(review_moderate_handler))
+        .route(routes::ITEM_REVIEW, get(review_form_handler))
+        .route(
+            routes::ITEM_SUBSCRIPTION,
+            post(subscription_add_handler).delete(subscription_remove_handler),
+        )
+        .route(
+            routes::ITEM_FAVORITE,
+            post(favorite_add_handler).delete(favorite_remove_handler),
+        )
+        .route(routes::ITEM_LISTS, get(item_lists_handler))
+        .route(
+            routes::ITEM_LIST,
+            post(item_list_add_handler).delete(item_list_remove_handler),
+        )
+        .route(routes::NOTIFICATIONS, get(notification_view_handler))
+        .route(routes::FEED, get(feed_handler))
+        .route(routes::ABOUT, get(about_handler))
+        .route(routes::VERSION, get(version_handler))
+        .route(
+            routes::SUGGESTIONS,
+            get(suggestions_handler).post(suggestion_add_handler),
+        )
+        .route(routes::ADMIN_SUGGESTIONS, get(admin_suggestions_handler))
+        .route(
+            routes::ADMIN_SUGGESTION_APPROVE,
+            post(suggestion_approve_handler),
+        )
+        .route(
+            routes::ADMIN_SUGGESTION_REJECT,
+            post(suggestion_reject_handler),
+        )
+        .route(
+            routes::ADMIN_WEBHOOKS,
+            get(admin_webhooks_handler).post(webhook_add_handler),
+        )
+        .route(routes::ADMIN_WEBHOOK, delete(webhook_remove_handler))
+        .route(
+            routes::ADMIN_WEBHOOK_DELIVERY,
+            post(webhook_redeliver_handler),
+        )
+        .route(routes::ADMIN_JOBS, get(admin_jobs_handler))
+        .route(routes::ADMIN_JOB_RUN, post(job_run_handler))
+        .route(routes::ADMIN_AUDIT, get(admin_audit_handler))
+        .route(routes::ITEM_COVER, get(cover_view_handler))
+        .route(routes::ITEM_CARD, get(item_card_handler))
+        .route(
+            routes::ITEM_DISCUSSION,
+            get(item_discussion_handler).post(comment_add_handler),
+        )
+        .route(routes::ITEM_COMMENT, delete(comment_remove_handler))
+        .route(
+            routes::ITEM_FEATURE,
+            post(item_feature_handler).delete(item_unfeature_handler),
+        )
+        .route(
+            routes::ITEM_LOCK,
+            post(item_lock_handler).delete(item_unlock_handler),
+        )
+        .route(
+            routes::ADMIN_FEATURED,
+            get(admin_featured_handler).post(featured_reorder_handler),
+        )
+        .route(routes::ITEM_IMAGE, delete(item_image_remove_handler))
+        .route(routes::ITEM_IMAGE_COVER, post(item_cover_handler))
+        .route(
+            routes::REVIEW_REPLIES,
+            get(review_replies_handler).post(review_reply_add_handler),
+        )
+        .route(
+            routes::REVIEW_REPLY,
+            delete(review_reply_remove_handler),
+        )
+        .route(
+            routes::REVIEW_REACTIONS,
+            get(review_reaction_picker_handler).post(review_reaction_handler),
+        )
+        .route(
+            routes::REPLY_REACTIONS,
+            get(reply_reaction_picker_handler).post(reply_reaction_handler),
+        )
+        .route(routes::ADMIN_ITEMS, get(admin_items_handler))
+        .route(routes::ADMIN_ITEMS_CSV, get(admin_items_csv_handler))
+        .route(routes::ITEMS_EXPORT, get(catalog_export_handler))
+        .route(
+            routes::ADMIN_CATEGORIES,
+            get(admin_categories_handler).post(category_add_handler),
+        )
+        .route(routes::ADMIN_CATEGORY, delete(category_remove_handler))
+        .route(
+            routes::ADMIN_COLLECTIONS,
+            get(admin_collections_handler).post(collection_add_handler),
+        )
+        .route(routes::ADMIN_COLLECTION, delete(collection_remove_handler))
+        .route(routes::COLLECTION, get(collection_handler))
+        .route(routes::COLLECTION_ITEMS, post(collection_item_add_handler))
+        .route(
+            routes::COLLECTION_ITEM,
+            post(collection_item_move_handler).delete(collection_item_remove_handler),
+        )
+        .route(routes::METRICS, get(metrics_handler))
+        .route(routes::TAGS, get(tag_view_handler))
+        .route(routes::REVIEWS, get(review_view_handler))
+        .route(routes::USERS, get(user_view_handler))
+        .route(routes::USER, get(user_handler))
         .route(
-            "/users/:user/edit",
+            routes::USER_EDIT,
             get(user_edit_form_handler).post(user_edit_handler),
         )
         .route(
-            "/users/:user/remove",
+            routes::USER_REMOVE,
             get(user_remove_form_handler).post(user_remove_handler),
         )
-        .nest_service("/static", static_service)
+        .route(
+            routes::USER_PASSWORD_RESET,
+            post(user_password_reset_handler),
+        )
+        .route(routes::USER_COMPATIBILITY, get(user_compatibility_handler))
+        .route(routes::USER_FAVORITES, get(user_favorites_handler))
+        .route(
+            routes::USER_FOLLOW,
+            post(follow_add_handler).delete(follow_remove_handler),
+        )
+        .route(
+            routes::USER_LISTS,
+            get(user_lists_handler).post(user_list_add_handler),
+        )
+        .route(
+            routes::USER_LIST,
+            get(user_list_handler)
+                .put(user_list_rename_handler)
+                .delete(user_list_remove_handler),
+        )
+        .route(
+            routes::USER_LIST_ITEM,
+            post(user_list_item_move_handler).delete(user_list_item_remove_handler),
+        )
+        .route(
+            routes::USER_IMPORT,
+            get(user_import_form_handler).post(user_import_handler),
+        )
+        .route(
+            routes::USER_IMPORT_PREVIEW,
+            post(user_import_preview_handler),
+        )
+        .route(routes::WEBFINGER, get(webfinger_handler))
+        .route(routes::ACTIVITYPUB_ACTOR, get(activitypub_actor_handler))
+        .route(routes::ACTIVITYPUB_INBOX, post(activitypub_inbox_handler))
+        .route(routes::ACTIVITYPUB_OUTBOX, get(activitypub_outbox_handler))
+        .route(
+            routes::ACTIVITYPUB_FOLLOWERS,
+            get(activitypub_followers_handler),
+        )
+        .nest_service(routes::IMAGES, image_service)
+        .nest(
+            routes::STATIC,
+            Router::new()
+                .fallback_service(static_service)
+                .layer(from_fn(assets::revalidate)),
+        )
+        .fallback(not_found_handler)
+        .layer(Extension(Arc::new(images::ImageQueue::default())))
+        .layer(Extension(latencies.clone()))
+        .layer(DefaultBodyLimit::max(config.max_request_size))
+        .layer(Extension(storage))
+        .layer(Extension(Arc::new(stats::StatsCache::default())))
+        .layer(Extension(Arc::new(resilience::PageCache::default())))
+        .layer(from_fn(cache::invalidate_on_write))
+        .layer(from_fn_with_state(latencies, metrics::track_latency))
+        .layer(from_fn_with_state(
+            Arc::new(rate_limit::RateLimiter::default()),
+            rate_limit::limit,
+        ))
+        .layer(from_fn(error::render_errors))
+        .layer(from_fn_with_state(pool.clone(), sessions::load_user))
+        .layer(Extension(Arc::new(cache::QueryCache::default())))
+        .layer(Extension(Arc::new(sessions::Revocations::default())))
         .layer(SessionLayer::new(session_store))
+        .layer(from_fn_with_state(pool.clone(), resilience::catch_outage))
         .layer(from_fn(strip_empty_query))
+        .layer(CompressionLayer::new())
         .with_state(pool);
-    let listener = TcpListener::bind("0.0.0.0:3000").await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    let router = if config.base_path.is_empty() {
+        router
+    } else {
+        // Nesting serves the index of the nested router at the base path alone.
+        Router::new()
+            .nest(&config.base_path, router)
+            .route(&format!("{}/", config.base_path), get(index_handler))
+    };
+    router
+        .layer(from_fn(security::add_headers))
+        .layer(from_fn(proxy::forwarded))
 }
 
 async fn strip_empty_query(
@@ -89,10 +451,10 @@ async fn strip_empty_query(
     Query(mut query): Query<HashMap<String, String>>,
     mut request: Request,
     next: Next,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
     let initial_param_count = query.len();
     query.retain(|_, v| !v.is_empty() && v != "0");
-    if initial_param_count != query.len() {
+    Ok(if initial_param_count != query.len() {
         let new_query_string = query
             .into_iter()
             .map(|(k, v)| format!("{}={}", k, v))
@@ -104,880 +466,4247 @@ async fn strip_empty_query(
         };
         let new_uri = {
             let mut parts = request.uri().clone().into_parts();
-            parts.path_and_query = Some(new_pq_string.try_into().unwrap());
-            Uri::from_parts(parts).unwrap()
+            parts.path_and_query = Some(new_pq_string.as_str().try_into()?);
+            Uri::from_parts(parts)?
         };
         if boosted {
-            *request.uri_mut() = new_uri.clone();
+            *request.uri_mut() = new_uri;
         }
         let response = next.run(request).await;
+        // Routes see paths without the base path, the browser is sent back with it.
+        let new_url = routes::url::path(&new_pq_string);
         if boosted {
-            (HxReplaceUrl(new_uri), response).into_response()
+            (HxReplaceUrl(new_url.try_into()?), response).into_response()
         } else {
-            Redirect::to(&new_uri.to_string()).into_response()
+            Redirect::to(&new_url).into_response()
         }
     } else {
         next.run(request).await
-    }
+    })
 }
 
-async fn index_handler(HxBoosted(boosted): HxBoosted) -> impl IntoResponse {
-    if boosted {
-        (HxLocation::from_uri("/items".try_into().unwrap()), ()).into_response()
+async fn index_handler(HxBoosted(boosted): HxBoosted) -> Result<Response, AppError> {
+    let items = routes::url::path(routes::ITEMS);
+    Ok(if boosted {
+        (HxLocation::from_uri(items.try_into()?), ()).into_response()
     } else {
-        Redirect::to("/items").into_response()
-    }
+        Redirect::to(&items).into_response()
+    })
+}
+
+async fn not_found_handler() -> AppError {
+    AppError::NotFound
+}
+
+async fn scripts_handler() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/javascript")],
+        templates::scripts::SCRIPT,
+    )
 }
 
 #[derive(Deserialize)]
 struct Score {
     score: i16,
+    body: Option<String>,
+    spoiler: Option<String>,
+    private: Option<String>,
 }
 
-async fn review_add_handler(
+async fn review_form_handler(
     State(pool): State<PgPool>,
-    session: Session<SessionNullPool>,
+    AuthUser(user): AuthUser,
     Path(locator): Path<String>,
     HxRequest(is_htmx): HxRequest,
-    HxCurrentUrl(current_url): HxCurrentUrl,
-    score: Form<Score>,
-) -> impl IntoResponse {
-    if let Some(user) = session.get::<database::User>("user") {
-        database::rate_item(&pool, &user.username, &locator, score.score)
-            .await
-            .unwrap();
-        if is_htmx {
-            (
-                HxLocation {
-                    uri: current_url.unwrap(),
-                },
-                (),
-            )
-                .into_response()
-        } else {
-            StatusCode::OK.into_response()
-        }
-    } else {
-        StatusCode::UNAUTHORIZED.into_response()
+) -> Result<Response, AppError> {
+    if !is_htmx {
+        return Ok(StatusCode::NOT_FOUND.into_response());
     }
+    let review = database::get_item_review(&pool, &locator, &user.username).await?;
+    Ok(templates::review_form(&locator, review.as_ref()).into_response())
 }
 
-async fn review_remove_handler(
+async fn review_add_handler(
     State(pool): State<PgPool>,
-    session: Session<SessionNullPool>,
+    AuthUser(user): AuthUser,
     Path(locator): Path<String>,
     HxRequest(is_htmx): HxRequest,
     HxCurrentUrl(current_url): HxCurrentUrl,
-) -> impl IntoResponse {
-    let Some(user) = session.get::<database::User>("user") else {
-        return StatusCode::UNAUTHORIZED.into_response();
-    };
-    if database::remove_review(&pool, &locator, &user.username)
-        .await
-        .is_ok()
+    score: Form<Score>,
+) -> Result<Response, AppError> {
+    let result = database::rate_item(
+        &pool,
+        &user.username,
+        &locator,
+        score.score,
+        score.body.as_deref(),
+        score.body.as_ref().map(|_| score.spoiler.is_some()),
+        score.body.as_ref().map(|_| score.private.is_some()),
+    )
+    .await;
+    if let Err(
+        e @ (database::DatabaseError::RateLimited
+        | database::DatabaseError::Unreleased
+        | database::DatabaseError::Locked),
+    ) = result
     {
-        if is_htmx {
+        return Ok(if is_htmx {
             (
-                HxLocation {
-                    uri: current_url.unwrap(),
-                },
-                (),
+                HxRetarget("body".to_owned()),
+                HxReswap(SwapOption::BeforeEnd),
+                templates::error_modal(&e.to_string()),
             )
                 .into_response()
+        } else if let database::DatabaseError::RateLimited = e {
+            StatusCode::TOO_MANY_REQUESTS.into_response()
         } else {
-            StatusCode::OK.into_response()
-        }
-    } else {
-        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            StatusCode::FORBIDDEN.into_response()
+        });
+    }
+    result?;
+    let public = database::is_rating_public(&pool, &locator, &user.username).await?;
+    webhooks::dispatch(
+        &pool,
+        webhooks::Event::ReviewAdded,
+        webhooks::review(
+            &locator,
+            public.then_some(user.username.as_str()),
+            Some(score.score).filter(|_| public),
+        ),
+    )
+    .await?;
+    if score.body.is_some() {
+        database::notify_subscribers(&pool, &locator, &user.username).await?;
     }
+    Ok(if is_htmx {
+        (
+            HxLocation {
+                uri: current_url.ok_or(AppError::BadRequest)?,
+            },
+            (),
+        )
+            .into_response()
+    } else {
+        StatusCode::OK.into_response()
+    })
 }
 
-#[derive(Deserialize)]
-struct Params {
-    search: Option<String>,
-    page: Option<i32>,
+async fn subscription_add_handler(
+    State(pool): State<PgPool>,
+    AuthUser(user): AuthUser,
+    Path(locator): Path<String>,
+) -> Result<Response, AppError> {
+    database::set_subscription(&pool, &locator, &user.username, true).await?;
+    Ok(templates::subscription_button(&locator, true).into_response())
 }
 
-async fn item_handler(
+async fn subscription_remove_handler(
     State(pool): State<PgPool>,
-    session: Session<SessionNullPool>,
+    AuthUser(user): AuthUser,
     Path(locator): Path<String>,
-    query: Query<Params>,
-    HxBoosted(boosted): HxBoosted,
-) -> impl IntoResponse {
-    if let Some(item) = database::get_item(&pool, &locator).await.unwrap() {
-        if let Some(user) = session.get::<database::User>("user") {
-            let item_page = templates::item_page(
-                &item,
-                database::get_item_ratings(&pool, query.page, &locator)
-                    .await
-                    .unwrap(),
-                Some(&user),
-                database::get_item_rating(&pool, &locator, &user.username)
-                    .await
-                    .unwrap(),
-            );
-            if boosted {
-                item_page.into_response()
-            } else {
-                templates::index(item_page, "/items", Some(&user)).into_response()
-            }
-        } else {
-            let item_page = templates::item_page(
-                &item,
-                database::get_item_ratings(&pool, query.page, &locator)
-                    .await
-                    .unwrap(),
-                None,
-                None,
-            );
-            if boosted {
-                item_page.into_response()
-            } else {
-                templates::index(item_page, "/items", None).into_response()
-            }
-        }
-    } else {
-        StatusCode::NOT_FOUND.into_response()
-    }
+) -> Result<Response, AppError> {
+    database::set_subscription(&pool, &locator, &user.username, false).await?;
+    Ok(templates::subscription_button(&locator, false).into_response())
 }
 
-async fn item_remove_form_handler(
+async fn favorite_add_handler(
+    State(pool): State<PgPool>,
+    AuthUser(user): AuthUser,
     Path(locator): Path<String>,
-    HxRequest(is_htmx): HxRequest,
-) -> impl IntoResponse {
-    if is_htmx {
-        templates::remove_form(
-            &("/items/".to_owned() + &locator + "/remove"),
-            "Remove item",
-            &locator,
-        )
-        .into_response()
-    } else {
-        StatusCode::NOT_FOUND.into_response()
-    }
+) -> Result<Response, AppError> {
+    let count = database::set_favorite(&pool, &locator, &user.username, true).await?;
+    Ok(templates::favorite_button(&locator, true, count).into_response())
 }
 
-async fn item_remove_handler(
+async fn favorite_remove_handler(
     State(pool): State<PgPool>,
-    session: Session<SessionNullPool>,
+    AuthUser(user): AuthUser,
     Path(locator): Path<String>,
-    HxRequest(is_htmx): HxRequest,
-) -> impl IntoResponse {
-    if let Some(user) = session.get::<database::User>("user") {
-        if !user.is_admin {
-            return StatusCode::FORBIDDEN.into_response();
-        }
+) -> Result<Response, AppError> {
+    let count = database::set_favorite(&pool, &locator, &user.username, false).await?;
+    Ok(templates::favorite_button(&locator, false, count).into_response())
+}
+
+async fn item_lock_handler(
+    State(pool): State<PgPool>,
+    AdminUser(user): AdminUser,
+    Path(locator): Path<String>,
+) -> Result<Response, AppError> {
+    database::set_item_locked(&pool, &locator, true, &user.username).await?;
+    Ok(templates::lock_button(&locator, true).into_response())
+}
+
+async fn item_unlock_handler(
+    State(pool): State<PgPool>,
+    AdminUser(user): AdminUser,
+    Path(locator): Path<String>,
+) -> Result<Response, AppError> {
+    database::set_item_locked(&pool, &locator, false, &user.username).await?;
+    Ok(templates::lock_button(&locator, false).into_response())
+}
+
+async fn item_feature_handler(
+    State(pool): State<PgPool>,
+    AdminUser(user): AdminUser,
+    Path(locator): Path<String>,
+) -> Result<Response, AppError> {
+    database::set_featured(&pool, &locator, true, &user.username).await?;
+    Ok(templates::featured_button(&locator, true).into_response())
+}
+
+async fn item_unfeature_handler(
+    State(pool): State<PgPool>,
+    AdminUser(user): AdminUser,
+    Path(locator): Path<String>,
+) -> Result<Response, AppError> {
+    database::set_featured(&pool, &locator, false, &user.username).await?;
+    Ok(templates::featured_button(&locator, false).into_response())
+}
+
+async fn admin_featured_handler(
+    State(pool): State<PgPool>,
+    AdminUser(user): AdminUser,
+    HxBoosted(boosted): HxBoosted,
+) -> Result<Response, AppError> {
+    let content = templates::admin_featured(&database::get_featured_items(&pool).await?);
+    Ok(if boosted {
+        content.into_response()
     } else {
-        return StatusCode::FORBIDDEN.into_response();
-    }
-    if database::remove_item(&pool, &locator).await.is_ok() {
-        remove_file("static/images/items/".to_owned() + &locator)
-            .await
-            .unwrap();
-        if is_htmx {
-            (
-                HxLocation {
-                    uri: "/items".try_into().unwrap(),
-                },
-                (),
-            )
-                .into_response()
-        } else {
-            StatusCode::OK.into_response()
-        }
+        templates::index(content, routes::ITEMS, Some(&user), None).into_response()
+    })
+}
+
+/// Saves the order the featured items were dragged into, sent as repeated `locator` fields.
+async fn featured_reorder_handler(
+    State(pool): State<PgPool>,
+    _: AdminUser,
+    Form(fields): Form<Vec<(String, String)>>,
+) -> Result<Response, AppError> {
+    let locators: Vec<String> = fields
+        .into_iter()
+        .filter(|(name, _)| name == "locator")
+        .map(|(_, locator)| locator)
+        .collect();
+    database::reorder_featured_items(&pool, &locators).await?;
+    Ok(templates::admin_featured(&database::get_featured_items(&pool).await?).into_response())
+}
+
+async fn notification_view_handler(
+    State(pool): State<PgPool>,
+    AuthUser(user): AuthUser,
+    HxBoosted(boosted): HxBoosted,
+) -> Result<Response, AppError> {
+    let content =
+        templates::notification_view(&database::take_notifications(&pool, &user.username).await?);
+    Ok(if boosted {
+        content.into_response()
     } else {
-        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-    }
+        templates::index(content, routes::ITEMS, Some(&user), None).into_response()
+    })
 }
 
-async fn item_view_handler(
+async fn feed_handler(
     State(pool): State<PgPool>,
-    session: Session<SessionNullPool>,
+    AuthUser(user): AuthUser,
     query: Query<Params>,
     HxBoosted(boosted): HxBoosted,
-) -> impl IntoResponse {
-    let content = templates::item_view(
-        database::get_items(&pool, query.page, query.search.as_deref())
-            .await
-            .unwrap(),
-        session.get("user").as_ref(),
-    );
-    if boosted {
-        content
+) -> Result<Response, AppError> {
+    let page = database::get_feed(&pool, query.page, &user.username).await?;
+    let links = page.as_ref().map(database::Page::links);
+    let content = templates::feed(page);
+    Ok(if boosted {
+        content.into_response()
     } else {
-        templates::index(content, "/items", session.get("user").as_ref())
-    }
+        templates::index(content, routes::USERS, Some(&user), links.as_ref()).into_response()
+    })
 }
 
-async fn user_remove_form_handler(
-    Path(username): Path<String>,
+async fn review_remove_handler(
+    State(pool): State<PgPool>,
+    AuthUser(user): AuthUser,
+    Path(locator): Path<String>,
     HxRequest(is_htmx): HxRequest,
-) -> impl IntoResponse {
-    if is_htmx {
-        templates::remove_form(
-            &("/users/".to_owned() + &username + "/remove"),
-            "Remove user",
-            &username,
+    HxCurrentUrl(current_url): HxCurrentUrl,
+) -> Result<Response, AppError> {
+    let public = database::is_rating_public(&pool, &locator, &user.username).await?;
+    database::remove_review(&pool, &locator, &user.username).await?;
+    webhooks::dispatch(
+        &pool,
+        webhooks::Event::ReviewRemoved,
+        webhooks::review(&locator, public.then_some(user.username.as_str()), None),
+    )
+    .await?;
+    Ok(if is_htmx {
+        (
+            HxLocation {
+                uri: current_url.ok_or(AppError::BadRequest)?,
+            },
+            (),
         )
-        .into_response()
+            .into_response()
     } else {
-        StatusCode::NOT_FOUND.into_response()
-    }
+        StatusCode::OK.into_response()
+    })
 }
 
-async fn user_remove_handler(
+async fn review_moderate_handler(
     State(pool): State<PgPool>,
-    session: Session<SessionNullPool>,
-    Path(username): Path<String>,
+    AdminUser(user): AdminUser,
+    Path((locator, username)): Path<(String, String)>,
     HxRequest(is_htmx): HxRequest,
-) -> impl IntoResponse {
-    let Some(user) = session.get::<database::User>("user") else {
-        return StatusCode::FORBIDDEN.into_response();
-    };
-    if !user.is_admin && user.username != username {
-        return StatusCode::FORBIDDEN.into_response();
-    }
-    let Ok(Some(page_user)) = database::get_user(&pool, &username).await else {
-        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-    };
-    if page_user.is_admin {
-        return StatusCode::FORBIDDEN.into_response();
+    HxCurrentUrl(current_url): HxCurrentUrl,
+) -> Result<Response, AppError> {
+    let public = database::is_rating_public(&pool, &locator, &username).await?;
+    if !database::moderate_review(&pool, &locator, &username, &user.username).await? {
+        return Ok(StatusCode::NOT_FOUND.into_response());
     }
-    if database::remove_user(&pool, &username).await.is_ok() {
-        if user.username == page_user.username {
-            session.destroy();
-        }
-        if try_exists("static/images/avatars/".to_owned() + &username)
-            .await
-            .unwrap_or(false)
-        {
-            remove_file("static/images/avatars/".to_owned() + &username)
-                .await
-                .unwrap();
-        }
-        if is_htmx {
-            (
-                HxLocation {
-                    uri: "/users".try_into().unwrap(),
-                },
-                (),
+    webhooks::dispatch(
+        &pool,
+        webhooks::Event::ReviewRemoved,
+        webhooks::review(&locator, public.then_some(username.as_str()), None),
+    )
+    .await?;
+    Ok(if is_htmx {
+        (
+            HxLocation {
+                uri: current_url.ok_or(AppError::BadRequest)?,
+            },
+            (),
+        )
+            .into_response()
+    } else {
+        StatusCode::OK.into_response()
+    })
+}
+
+async fn review_replies_handler(
+    State(pool): State<PgPool>,
+    CurrentUser(user): CurrentUser,
+    Path((locator, username)): Path<(String, String)>,
+    HxRequest(is_htmx): HxRequest,
+) -> Result<Response, AppError> {
+    Ok(if is_htmx {
+        templates::review_replies(
+            &locator,
+            &username,
+            &database::get_review_replies(
+                &pool,
+                &locator,
+                &username,
+                user.as_ref().map(|user| user.username.as_str()),
             )
-                .into_response()
-        } else {
-            StatusCode::OK.into_response()
-        }
+            .await?,
+            user.as_ref(),
+            None,
+        )
+        .into_response()
+    } else {
+        StatusCode::NOT_FOUND.into_response()
+    })
+}
+
+#[derive(Deserialize)]
+struct ReplyForm {
+    body: String,
+}
+
+async fn review_reply_add_handler(
+    State(pool): State<PgPool>,
+    AuthUser(user): AuthUser,
+    Path((locator, username)): Path<(String, String)>,
+    HxRequest(is_htmx): HxRequest,
+    form: Form<ReplyForm>,
+) -> Result<Response, AppError> {
+    let message =
+        database::add_review_reply(&pool, &locator, &username, &user.username, &form.body)
+            .await
+            .err()
+            .map(|e| e.to_string());
+    Ok(if is_htmx {
+        templates::review_replies(
+            &locator,
+            &username,
+            &database::get_review_replies(&pool, &locator, &username, Some(&user.username)).await?,
+            Some(&user),
+            message.as_deref(),
+        )
+        .into_response()
+    } else if message.is_none() {
+        StatusCode::OK.into_response()
+    } else {
+        StatusCode::UNPROCESSABLE_ENTITY.into_response()
+    })
+}
+
+async fn review_reply_remove_handler(
+    State(pool): State<PgPool>,
+    AuthUser(user): AuthUser,
+    Path((locator, username, reply)): Path<(String, String, i32)>,
+    HxRequest(is_htmx): HxRequest,
+) -> Result<Response, AppError> {
+    database::remove_review_reply(&pool, reply, &user.username).await?;
+    Ok(if is_htmx {
+        templates::review_replies(
+            &locator,
+            &username,
+            &database::get_review_replies(&pool, &locator, &username, Some(&user.username)).await?,
+            Some(&user),
+            None,
+        )
+        .into_response()
     } else {
-        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        StatusCode::OK.into_response()
+    })
+}
+
+#[derive(Deserialize)]
+struct ReactionForm {
+    reaction: String,
+}
+
+async fn review_reaction_picker_handler(
+    _: AuthUser,
+    Path((locator, username)): Path<(String, String)>,
+    HxRequest(is_htmx): HxRequest,
+) -> impl IntoResponse {
+    if !is_htmx {
+        return StatusCode::NOT_FOUND.into_response();
     }
+    templates::reaction_picker(&routes::url::review_reactions(&locator, &username)).into_response()
 }
 
-async fn user_handler(
+async fn review_reaction_handler(
     State(pool): State<PgPool>,
-    session: Session<SessionNullPool>,
+    AuthUser(user): AuthUser,
+    Path((locator, username)): Path<(String, String)>,
+    form: Form<ReactionForm>,
+) -> impl IntoResponse {
+    if !reactions::is_reaction(&form.reaction) {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+    match database::toggle_review_reaction(
+        &pool,
+        &locator,
+        &username,
+        &user.username,
+        &form.reaction,
+    )
+    .await
+    {
+        Ok(Some(reactions)) => templates::reaction_bar(
+            &routes::url::review_reactions(&locator, &username),
+            &reactions.counts,
+            &reactions.own,
+            Some(&user),
+        )
+        .into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+async fn reply_reaction_picker_handler(
+    _: AuthUser,
+    Path((locator, username, reply)): Path<(String, String, i32)>,
+    HxRequest(is_htmx): HxRequest,
+) -> impl IntoResponse {
+    if !is_htmx {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    templates::reaction_picker(&routes::url::reply_reactions(&locator, &username, reply))
+        .into_response()
+}
+
+async fn reply_reaction_handler(
+    State(pool): State<PgPool>,
+    AuthUser(user): AuthUser,
+    Path((locator, username, reply)): Path<(String, String, i32)>,
+    form: Form<ReactionForm>,
+) -> impl IntoResponse {
+    if !reactions::is_reaction(&form.reaction) {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+    match database::toggle_reply_reaction(
+        &pool,
+        &locator,
+        &username,
+        reply,
+        &user.username,
+        &form.reaction,
+    )
+    .await
+    {
+        Ok(Some(reactions)) => templates::reaction_bar(
+            &routes::url::reply_reactions(&locator, &username, reply),
+            &reactions.counts,
+            &reactions.own,
+            Some(&user),
+        )
+        .into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct Params {
+    search: Option<String>,
+    #[serde(default)]
+    mode: database::SearchMode,
+    page: Option<i32>,
+    tag: Option<String>,
+    category: Option<String>,
+    min_score: Option<String>,
+    min_reviews: Option<String>,
+    letter: Option<String>,
+    per_page: Option<String>,
+    after: Option<String>,
+}
+
+async fn item_handler(
+    State(pool): State<PgPool>,
+    CurrentUser(user): CurrentUser,
+    Extension(cache): Extension<Arc<cache::QueryCache>>,
+    Extension(origin): Extension<proxy::Origin>,
+    Path(locator): Path<String>,
     query: Query<Params>,
-    Path(username): Path<String>,
     HxBoosted(boosted): HxBoosted,
-) -> impl IntoResponse {
-    if let Some(page_user) = database::get_user(&pool, &username).await.unwrap() {
-        let user = session.get::<database::User>("user");
-        let user_page = templates::user_page(
-            &page_user,
-            database::get_user_ratings(&pool, query.page, &username)
-                .await
-                .unwrap(),
-            user.as_ref(),
+) -> Result<Response, AppError> {
+    let Some(item) = cache.item(&pool, &locator).await? else {
+        return Ok(match database::resolve_item_alias(&pool, &locator).await? {
+            Some(survivor) => (
+                StatusCode::MOVED_PERMANENTLY,
+                [(header::LOCATION, routes::url::item(&survivor))],
+            )
+                .into_response(),
+            None => StatusCode::NOT_FOUND.into_response(),
+        });
+    };
+    let tags = database::get_item_tags(&pool, &locator).await?;
+    let collections = database::get_item_collections(&pool, &locator).await?;
+    let gallery = database::get_item_images(&pool, &locator).await?;
+    Ok(if let Some(user) = user {
+        let ratings =
+            database::get_item_ratings(&pool, query.page, &locator, Some(&user.username)).await?;
+        let links = ratings.as_ref().map(database::Page::links);
+        let item_page = templates::item_page(
+            &item,
+            ratings,
+            Some(&user),
+            database::get_item_rating(&pool, &locator, &user.username).await?,
+            &tags,
+            &collections,
+            database::is_subscribed(&pool, &locator, &user.username).await?,
+            database::is_favorite(&pool, &locator, &user.username).await?,
+            user.is_admin && database::is_featured(&pool, &locator).await?,
+            &gallery,
         );
         if boosted {
-            user_page.into_response()
+            item_page.into_response()
         } else {
-            templates::index(user_page, "/users", user.as_ref()).into_response()
+            templates::index_with_meta(
+                item_page,
+                routes::ITEMS,
+                Some(&user),
+                links.as_ref(),
+                templates::item_meta(&item, &origin),
+            )
+            .into_response()
         }
     } else {
-        StatusCode::NOT_FOUND.into_response()
-    }
+        let ratings = database::get_item_ratings(&pool, query.page, &locator, None).await?;
+        let links = ratings.as_ref().map(database::Page::links);
+        let item_page = templates::item_page(
+            &item,
+            ratings,
+            None,
+            None,
+            &tags,
+            &collections,
+            false,
+            false,
+            false,
+            &gallery,
+        );
+        if boosted {
+            item_page.into_response()
+        } else {
+            templates::index_with_meta(
+                item_page,
+                routes::ITEMS,
+                None,
+                links.as_ref(),
+                templates::item_meta(&item, &origin),
+            )
+            .into_response()
+        }
+    })
 }
 
-async fn user_view_handler(
+async fn item_discussion_handler(
     State(pool): State<PgPool>,
-    session: Session<SessionNullPool>,
+    CurrentUser(user): CurrentUser,
+    Path(locator): Path<String>,
     query: Query<Params>,
     HxBoosted(boosted): HxBoosted,
-) -> impl IntoResponse {
-    let content = templates::user_view(
-        database::get_users(&pool, query.page, query.search.as_deref())
-            .await
-            .unwrap(),
-    );
-    if boosted {
-        content
+) -> Result<Response, AppError> {
+    let Some(item) = database::get_item(&pool, &locator).await? else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    let comments = database::get_item_comments(&pool, query.page, &locator).await?;
+    let links = comments.as_ref().map(database::Page::links);
+    let content = templates::item_discussion(&item, comments, user.as_ref(), None);
+    Ok(if boosted {
+        content.into_response()
     } else {
-        templates::index(content, "/users", session.get("user").as_ref())
-    }
+        templates::index(content, routes::ITEMS, user.as_ref(), links.as_ref()).into_response()
+    })
 }
 
 #[derive(Deserialize)]
-#[serde(tag = "target", rename_all = "lowercase")]
-enum SearchTarget {
-    Items,
-    Users,
+struct CommentForm {
+    body: String,
+    /// Comment being replied to.
+    parent: Option<i32>,
+    /// Page of the discussion shown after commenting.
+    page: Option<i32>,
 }
 
-async fn search_handler(
+async fn comment_add_handler(
     State(pool): State<PgPool>,
-    session: Session<SessionNullPool>,
-    Query(target): Query<SearchTarget>,
+    AuthUser(user): AuthUser,
+    Path(locator): Path<String>,
     HxRequest(is_htmx): HxRequest,
-) -> impl IntoResponse {
-    if is_htmx {
-        match target {
-            SearchTarget::Items => {
-                let content = templates::item_view(
-                    database::get_items(&pool, None, None).await.unwrap(),
-                    session.get("user").as_ref(),
-                );
-                (
-                    HxPushUrl("/items".try_into().unwrap()),
-                    templates::search("/items", Some(content)),
-                )
-            }
-            SearchTarget::Users => {
-                let content =
-                    templates::user_view(database::get_users(&pool, None, None).await.unwrap());
-                (
-                    HxPushUrl("/users".try_into().unwrap()),
-                    templates::search("/users", Some(content)),
-                )
-            }
-        }
+    Form(form): Form<CommentForm>,
+) -> Result<Response, AppError> {
+    let message =
+        match database::add_comment(&pool, &locator, &user.username, &form.body, form.parent).await
+        {
+            Ok(true) => None,
+            Ok(false) => return Ok(StatusCode::NOT_FOUND.into_response()),
+            Err(err) => Some(err.to_string()),
+        };
+    Ok(if is_htmx {
+        let item = database::get_item(&pool, &locator)
+            .await?
+            .ok_or(AppError::NotFound)?;
+        templates::item_discussion(
+            &item,
+            database::get_item_comments(&pool, form.page, &locator).await?,
+            Some(&user),
+            message.as_deref(),
+        )
         .into_response()
+    } else if message.is_none() {
+        StatusCode::OK.into_response()
     } else {
-        StatusCode::NOT_FOUND.into_response()
+        StatusCode::UNPROCESSABLE_ENTITY.into_response()
+    })
+}
+
+async fn comment_remove_handler(
+    State(pool): State<PgPool>,
+    AuthUser(user): AuthUser,
+    Path((locator, comment)): Path<(String, i32)>,
+) -> Result<Response, AppError> {
+    Ok(
+        if database::remove_comment(&pool, &locator, comment, &user.username).await? {
+            StatusCode::OK.into_response()
+        } else {
+            StatusCode::NOT_FOUND.into_response()
+        },
+    )
+}
+
+async fn cover_view_handler(
+    State(pool): State<PgPool>,
+    Path(locator): Path<String>,
+    HxRequest(is_htmx): HxRequest,
+) -> Result<Response, AppError> {
+    if !is_htmx {
+        return Ok(StatusCode::NOT_FOUND.into_response());
     }
+    Ok(match database::get_item(&pool, &locator).await? {
+        Some(item) => templates::cover_lightbox(&item).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    })
 }
 
-async fn user_edit_form_handler(
-    Path(username): Path<String>,
+async fn item_card_handler(
+    State(pool): State<PgPool>,
+    Extension(storage): Extension<Arc<dyn storage::Storage>>,
+    Path(locator): Path<String>,
+) -> Result<Response, AppError> {
+    let Some(item) = database::get_item(&pool, &locator).await? else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    let card = cards::card(&pool, storage.as_ref(), &item).await?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "image/png"),
+            (header::CACHE_CONTROL, cards::CACHE_CONTROL),
+        ],
+        card,
+    )
+        .into_response())
+}
+
+async fn item_remove_form_handler(
+    Path(locator): Path<String>,
     HxRequest(is_htmx): HxRequest,
 ) -> impl IntoResponse {
     if is_htmx {
-        templates::user_edit_form(None, &username).into_response()
+        templates::remove_form(
+            &routes::url::item_remove(&locator),
+            "Remove item",
+            &locator,
+        )
+        .into_response()
     } else {
         StatusCode::NOT_FOUND.into_response()
     }
 }
 
-async fn user_edit_handler(
-    session: Session<SessionNullPool>,
-    Path(username): Path<String>,
+async fn item_remove_handler(
     State(pool): State<PgPool>,
+    Extension(storage): Extension<Arc<dyn storage::Storage>>,
+    AdminUser(user): AdminUser,
+    Path(locator): Path<String>,
     HxRequest(is_htmx): HxRequest,
-    mut multipart: Multipart,
-) -> impl IntoResponse {
-    let Some(user) = session.get::<database::User>("user") else {
-        return StatusCode::FORBIDDEN.into_response();
-    };
-    if !user.is_admin && user.username != username {
-        return StatusCode::FORBIDDEN.into_response();
-    }
-    let mut new_username = None;
-    let mut new_avatar = None;
-    let mut new_password1 = None;
-    let mut new_password2 = None;
-    let mut clear_avatar = false;
-    while let Some(field) = multipart.next_field().await.unwrap() {
-        if let Some(field_name) = field.name() {
-            if field_name == "avatar" {
-                if let Some(content_type) = field.content_type() {
-                    if !content_type.starts_with("image/") {
-                        return if is_htmx {
-                            templates::user_edit_form(
-                                Some(&database::DatabaseError::NotValidImage.to_string()),
-                                &username,
-                            )
-                            .into_response()
-                        } else {
-                            StatusCode::UNPROCESSABLE_ENTITY.into_response()
-                        };
-                    }
-                    if let Ok(bytes) = field.bytes().await {
-                        new_avatar = Some(bytes);
-                    }
-                }
-            } else if field_name == "username" {
-                if let Ok(text) = field.text().await {
-                    new_username = Some(text);
-                }
-            } else if field_name == "password1" {
-                if let Ok(text) = field.text().await {
-                    new_password1 = Some(text);
-                }
-            } else if field_name == "password2" {
-                if let Ok(text) = field.text().await {
-                    new_password2 = Some(text);
-                }
-            } else if field_name == "clear_avatar" {
-                clear_avatar = true;
-            }
-        }
-    }
-    if new_username.is_none() {
-        return if is_htmx {
-            templates::user_edit_form(
-                Some(&database::DatabaseError::EmptyFields.to_string()),
-                &username,
-            )
-            .into_response()
-        } else {
-            StatusCode::UNPROCESSABLE_ENTITY.into_response()
-        };
-    }
-    if let Err(err) = database::edit_user(
+) -> Result<Response, AppError> {
+    let gallery = database::get_item_images(&pool, &locator).await?;
+    let cover = database::get_item(&pool, &locator)
+        .await?
+        .and_then(|item| item.cover);
+    database::remove_item(&pool, &locator, &user.username).await?;
+    webhooks::dispatch(
         &pool,
-        &username,
-        new_username.as_deref(),
-        if new_avatar.is_none() && clear_avatar {
-            Some(false)
-        } else {
-            new_avatar.as_ref().map(|_| true)
-        },
-        new_password1.as_deref(),
-        new_password2.as_deref(),
+        webhooks::Event::ItemDeleted,
+        webhooks::item(&locator, None),
     )
-    .await
-    {
-        return if is_htmx {
-            templates::user_edit_form(Some(&err.to_string()), &username).into_response()
-        } else {
-            StatusCode::UNAUTHORIZED.into_response()
-        };
-    };
-    if clear_avatar {
-        if try_exists("static/images/avatars/".to_owned() + &username)
-            .await
-            .unwrap_or(false)
-        {
-            remove_file("static/images/avatars/".to_owned() + &username)
-                .await
-                .unwrap()
-        }
-    }
-    if let Some(new_username) = &new_username {
-        if try_exists("static/images/avatars/".to_owned() + &username)
+    .await?;
+    for image in gallery {
+        release_image(&pool, &*storage, images::GALLERY, &image.file)
             .await
-            .unwrap_or(false)
-        {
-            rename(
-                "static/images/avatars/".to_owned() + &username,
-                "static/images/avatars/".to_owned() + &new_username,
-            )
-            .await
-            .unwrap();
-        }
-    }
-    if let Some(new_avatar) = new_avatar {
-        let mut file = File::create(
-            "static/images/avatars/".to_owned() + new_username.as_ref().unwrap_or(&username),
-        )
-        .await
-        .unwrap();
-        file.write_all(&new_avatar).await.unwrap();
+            .unwrap_or_default();
     }
-    if user.username == username {
-        session.set(
-            "user",
-            database::get_user(&pool, &new_username.as_ref().unwrap_or(&username))
-                .await
-                .unwrap(),
-        )
+    if let Some(cover) = cover {
+        release_image(&pool, &*storage, images::COVERS, &cover).await?;
     }
-    if is_htmx {
+    Ok(if is_htmx {
         (
             HxLocation {
-                uri: ("/users/".to_owned() + &new_username.unwrap_or(username))
-                    .try_into()
-                    .unwrap(),
+                uri: routes::url::path(routes::ITEMS).try_into()?,
             },
             (),
         )
             .into_response()
     } else {
         StatusCode::OK.into_response()
-    }
+    })
 }
 
-async fn item_edit_form_handler(
-    State(pool): State<PgPool>,
-    Path(locator): Path<String>,
+async fn markdown_preview_handler(
     HxRequest(is_htmx): HxRequest,
+    Form(form): Form<forms::PreviewFormData>,
 ) -> impl IntoResponse {
     if is_htmx {
-        if let Ok(Some(item)) = database::get_item(&pool, &locator).await {
-            templates::item_form(
-                &("/items/".to_owned() + &locator + "/edit"),
-                "Edit item",
-                None,
-                Some(&item.title),
-                Some(&item.locator),
-                Some(&item.description),
-            )
-            .into_response()
-        } else {
-            StatusCode::NOT_FOUND.into_response()
-        }
+        templates::markdown_preview(&form.description).into_response()
     } else {
         StatusCode::NOT_FOUND.into_response()
     }
 }
 
-async fn item_edit_handler(
-    session: Session<SessionNullPool>,
+async fn item_merge_form_handler(
     Path(locator): Path<String>,
-    State(pool): State<PgPool>,
     HxRequest(is_htmx): HxRequest,
-    mut multipart: Multipart,
 ) -> impl IntoResponse {
-    if let Some(user) = session.get::<database::User>("user") {
-        if !user.is_admin {
-            return StatusCode::FORBIDDEN.into_response();
-        }
+    if is_htmx {
+        templates::merge_form(&locator, None).into_response()
     } else {
-        return StatusCode::FORBIDDEN.into_response();
+        StatusCode::NOT_FOUND.into_response()
     }
-    let mut new_title = None;
-    let mut new_locator = None;
-    let mut new_description = None;
-    let mut new_image = None;
-    while let Some(field) = multipart.next_field().await.unwrap() {
-        if let Some(field_name) = field.name() {
-            if field_name == "image" {
-                if let Some(content_type) = field.content_type() {
-                    if !content_type.starts_with("image/") {
-                        return if is_htmx {
-                            templates::item_form(
-                                &("/items/".to_owned() + &locator + "/edit"),
-                                "Edit item",
-                                Some(&database::DatabaseError::NotValidImage.to_string()),
-                                None,
-                                None,
-                                None,
-                            )
-                            .into_response()
-                        } else {
-                            StatusCode::UNPROCESSABLE_ENTITY.into_response()
-                        };
-                    }
-                    if let Ok(bytes) = field.bytes().await {
-                        new_image = Some(bytes);
-                    }
-                }
-            } else if field_name == "title" {
-                if let Ok(text) = field.text().await {
-                    new_title = Some(text);
-                }
-            } else if field_name == "description" {
-                if let Ok(text) = field.text().await {
-                    new_description = Some(text);
-                }
-            } else if field_name == "locator" {
-                if let Ok(text) = field.text().await {
-                    new_locator = Some(text);
-                }
-            }
+}
+
+async fn item_merge_handler(
+    State(pool): State<PgPool>,
+    Extension(storage): Extension<Arc<dyn storage::Storage>>,
+    AdminUser(user): AdminUser,
+    Path(locator): Path<String>,
+    HxRequest(is_htmx): HxRequest,
+    Form(form): Form<forms::MergeFormData>,
+) -> Result<Response, AppError> {
+    let cover = database::get_item(&pool, &locator)
+        .await?
+        .and_then(|item| item.cover);
+    let result = match form.validated() {
+        Ok(form) => database::merge_items(&pool, &locator, &form.into, &user.username)
+            .await
+            .map(|_| form.into),
+        Err(e) => Err(e),
+    };
+    let into = match result {
+        Ok(into) => into,
+        Err(e) => {
+            return Ok(if is_htmx {
+                templates::merge_form(&locator, Some(&e.to_string())).into_response()
+            } else {
+                StatusCode::UNPROCESSABLE_ENTITY.into_response()
+            });
         }
-    }
-    if new_locator.is_none() || new_title.is_none() || new_description.is_none() {
-        return if is_htmx {
-            templates::item_form(
-                &("/items/".to_owned() + &locator + "/edit"),
-                "Edit item",
-                Some(&database::DatabaseError::EmptyFields.to_string()),
-                None,
-                None,
-                None,
-            )
-            .into_response()
-        } else {
-            StatusCode::UNPROCESSABLE_ENTITY.into_response()
-        };
-    }
-    if let Err(err) = database::edit_item(
+    };
+    webhooks::dispatch(
         &pool,
-        &locator,
-        new_locator.as_deref(),
-        new_title.as_deref(),
-        new_description.as_deref(),
+        webhooks::Event::ItemDeleted,
+        webhooks::item_merge(&locator, &into),
     )
-    .await
-    {
-        return if is_htmx {
-            templates::item_form(
-                &("/items/".to_owned() + &locator + "/edit"),
-                "Edit item",
-                Some(&err.to_string()),
-                None,
-                None,
-                None,
-            )
-            .into_response()
-        } else {
-            StatusCode::UNAUTHORIZED.into_response()
+    .await?;
+    if let Some(cover) = cover {
+        let current = database::get_item(&pool, &into)
+            .await?
+            .and_then(|item| item.cover);
+        let has_cover = match &current {
+            Some(current) => storage
+                .exists(&images::cover_key(current))
+                .await
+                .unwrap_or(false),
+            None => false,
         };
-    };
-    if let Some(new_locator) = &new_locator {
-        rename(
-            "static/images/items/".to_owned() + &locator,
-            "static/images/items/".to_owned() + &new_locator,
-        )
-        .await
-        .unwrap();
-    }
-    if let Some(new_image) = new_image {
-        let mut file = File::create(
-            "static/images/items/".to_owned() + new_locator.as_ref().unwrap_or(&locator),
-        )
-        .await
-        .unwrap();
-        file.write_all(&new_image).await.unwrap();
+        if !has_cover {
+            database::set_item_cover_file(&pool, &into, &cover).await?;
+            if let Some(current) = current {
+                release_image(&pool, &*storage, images::COVERS, &current).await?;
+            }
+        }
+        release_image(&pool, &*storage, images::COVERS, &cover).await?;
     }
-    if is_htmx {
+    Ok(if is_htmx {
         (
             HxLocation {
-                uri: ("/items/".to_owned() + &new_locator.unwrap_or(locator))
-                    .try_into()
-                    .unwrap(),
+                uri: routes::url::item(&into).try_into()?,
             },
             (),
         )
             .into_response()
     } else {
         StatusCode::OK.into_response()
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn item_view_handler(
+    State(pool): State<PgPool>,
+    session: Session<SessionNullPool>,
+    CurrentUser(user): CurrentUser,
+    Extension(pages): Extension<Arc<resilience::PageCache>>,
+    Extension(cache): Extension<Arc<cache::QueryCache>>,
+    uri: Uri,
+    query: Query<Params>,
+    HxBoosted(boosted): HxBoosted,
+    HxTrigger(trigger): HxTrigger,
+) -> Result<maud::Markup, AppError> {
+    let filter = database::ItemFilter {
+        tag: query.tag.as_deref().filter(|tag| !tag.is_empty()),
+        category: query
+            .category
+            .as_deref()
+            .filter(|category| !category.is_empty()),
+        min_score: query
+            .min_score
+            .as_deref()
+            .and_then(|score| score.parse().ok()),
+        min_reviews: query
+            .min_reviews
+            .as_deref()
+            .and_then(|count| count.parse().ok()),
+        letter: query.letter.as_deref().and_then(database::index_letter),
+    };
+    let per_page = database::per_page(query.per_page.as_deref());
+    let is_landing = query.search.is_none()
+        && filter.tag.is_none()
+        && filter.category.is_none()
+        && filter.min_score.is_none()
+        && filter.min_reviews.is_none()
+        && filter.letter.is_none()
+        && query.after.is_none()
+        && query.page.unwrap_or(0) == 0;
+    if trigger.as_deref() == Some(templates::NEXT_PAGE_ID) {
+        let page = database::get_items(
+            &pool,
+            query.page,
+            per_page,
+            query.search.as_deref(),
+            &filter,
+            query.mode,
+            query.after.as_deref(),
+        )
+        .await?;
+        return Ok(page.map(templates::item_scroll_page).unwrap_or_default());
     }
+    let infinite_scroll = infinite_scroll(&pool, &session, user.as_ref()).await?;
+    let recommended = if is_landing {
+        recommended_items(&pool, user.as_ref()).await
+    } else {
+        Vec::new()
+    };
+    let fetch = || async {
+        Ok((
+            database::get_items(
+                &pool,
+                query.page,
+                per_page,
+                query.search.as_deref(),
+                &filter,
+                query.mode,
+                query.after.as_deref(),
+            )
+            .await?,
+            database::get_categories(&pool).await?,
+            database::get_tags(&pool).await?,
+            if is_landing {
+                database::get_featured_items(&pool).await?
+            } else {
+                Vec::new()
+            },
+        ))
+    };
+    let result = resilience::retry(|| async {
+        if is_landing {
+            cache.item_listing(uri.to_string(), fetch()).await
+        } else {
+            fetch().await
+        }
+    })
+    .await;
+    let links = result
+        .as_ref()
+        .ok()
+        .and_then(|(page, _, _, _)| page.as_ref())
+        .map(database::Page::links);
+    let key = resilience::PageCache::key(&uri, user.as_ref().map(|user| user.username.as_str()));
+    let content = pages.render(&key, result, |(page, categories, tags, featured)| {
+        templates::item_view(
+            page,
+            &featured,
+            &recommended,
+            user.as_ref(),
+            query.search.as_deref(),
+            query.mode,
+            &filter,
+            &categories,
+            &tags,
+            infinite_scroll,
+        )
+    })?;
+    Ok(if boosted {
+        content
+    } else {
+        templates::index(content, routes::ITEMS, user.as_ref(), links.as_ref())
+    })
 }
 
-async fn item_add_form_handler(HxRequest(is_htmx): HxRequest) -> impl IntoResponse {
-    if is_htmx {
-        templates::item_form("/items/add", "Add item", None, None, None, None).into_response()
+async fn trending_view_handler(
+    State(pool): State<PgPool>,
+    CurrentUser(user): CurrentUser,
+    Extension(pages): Extension<Arc<resilience::PageCache>>,
+    uri: Uri,
+    query: Query<Params>,
+    HxBoosted(boosted): HxBoosted,
+) -> Result<maud::Markup, AppError> {
+    let per_page = database::per_page(query.per_page.as_deref());
+    let result =
+        resilience::retry(|| database::get_trending_items(&pool, query.page, per_page)).await;
+    let links = result
+        .as_ref()
+        .ok()
+        .and_then(Option::as_ref)
+        .map(database::Page::links);
+    let content = pages.render(&resilience::PageCache::key(&uri, None), result, |page| {
+        templates::chart(
+            "Trending",
+            "Items reviewed most this week",
+            "No reviews this week yet!",
+            page,
+        )
+    })?;
+    Ok(if boosted {
+        content
     } else {
-        StatusCode::NOT_FOUND.into_response()
-    }
+        templates::index(content, routes::ITEMS, user.as_ref(), links.as_ref())
+    })
 }
 
-async fn item_add_handler(
-    session: Session<SessionNullPool>,
+async fn charts_view_handler(
     State(pool): State<PgPool>,
-    HxRequest(is_htmx): HxRequest,
-    HxCurrentUrl(current_url): HxCurrentUrl,
-    mut multipart: Multipart,
-) -> impl IntoResponse {
-    if let Some(user) = session.get::<database::User>("user") {
-        if !user.is_admin {
-            return StatusCode::FORBIDDEN.into_response();
-        }
+    CurrentUser(user): CurrentUser,
+    HxBoosted(boosted): HxBoosted,
+) -> Result<maud::Markup, AppError> {
+    let content = templates::chart_archive(&charts::get_months(&pool).await?);
+    Ok(if boosted {
+        content
     } else {
-        return StatusCode::FORBIDDEN.into_response();
-    }
-    let mut title = None;
-    let mut locator = None;
-    let mut description = None;
-    let mut image = None;
-    while let Some(field) = multipart.next_field().await.unwrap() {
-        if let Some(field_name) = field.name() {
-            if field_name == "image" {
-                if let Some(content_type) = field.content_type() {
-                    if !content_type.starts_with("image/") {
-                        return if is_htmx {
-                            templates::item_form(
-                                "/items/add",
-                                "Add item",
-                                Some(&database::DatabaseError::NotValidImage.to_string()),
-                                None,
-                                None,
-                                None,
-                            )
-                            .into_response()
-                        } else {
-                            StatusCode::UNPROCESSABLE_ENTITY.into_response()
-                        };
-                    }
-                    if let Ok(bytes) = field.bytes().await {
-                        image = Some(bytes);
-                    }
-                }
-            } else if field_name == "title" {
-                if let Ok(text) = field.text().await {
-                    title = Some(text);
-                }
-            } else if field_name == "description" {
-                if let Ok(text) = field.text().await {
-                    description = Some(text);
-                }
-            } else if field_name == "locator" {
-                if let Ok(text) = field.text().await {
-                    locator = Some(text);
-                }
-            }
-        }
-    }
-    if locator.is_none() || image.is_none() || title.is_none() || description.is_none() {
-        return if is_htmx {
-            templates::item_form(
-                "/items/add",
-                "Add item",
-                Some(&database::DatabaseError::EmptyFields.to_string()),
-                None,
-                None,
-                None,
-            )
-            .into_response()
-        } else {
-            StatusCode::UNPROCESSABLE_ENTITY.into_response()
-        };
-    }
-    let locator = locator.unwrap();
-    let image = image.unwrap();
-    let title = title.unwrap();
-    let description = description.unwrap();
-    if let Err(err) = database::add_item(&pool, &locator, &title, &description).await {
-        return if is_htmx {
-            templates::item_form(
-                "/items/add",
-                "Add item",
-                Some(&err.to_string()),
-                None,
-                None,
-                None,
-            )
-            .into_response()
-        } else {
-            StatusCode::UNAUTHORIZED.into_response()
-        };
+        templates::index(content, routes::ITEMS, user.as_ref(), None)
+    })
+}
+
+async fn chart_view_handler(
+    State(pool): State<PgPool>,
+    CurrentUser(user): CurrentUser,
+    Path(month): Path<String>,
+    HxBoosted(boosted): HxBoosted,
+) -> Result<Response, AppError> {
+    let months = charts::get_months(&pool).await?;
+    let Some(position) = charts::parse_month(&month)
+        .and_then(|month| months.iter().position(|archived| *archived == month))
+    else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
     };
-    let mut file = File::create("static/images/items/".to_owned() + &locator)
-        .await
-        .unwrap();
-    file.write_all(&image).await.unwrap();
-    if is_htmx {
-        (
-            HxLocation {
-                uri: current_url.unwrap(),
-            },
-            (),
-        )
-            .into_response()
+    let content = templates::monthly_chart(
+        months[position],
+        &charts::get_chart(&pool, months[position]).await?,
+        months.get(position + 1).copied(),
+        position.checked_sub(1).map(|newer| months[newer]),
+    );
+    Ok(if boosted {
+        content.into_response()
     } else {
-        StatusCode::OK.into_response()
-    }
+        templates::index(content, routes::ITEMS, user.as_ref(), None).into_response()
+    })
 }
 
-async fn login_form_handler(HxRequest(is_htmx): HxRequest) -> impl IntoResponse {
-    if is_htmx {
-        templates::login_form(None).into_response()
+async fn new_items_view_handler(
+    State(pool): State<PgPool>,
+    CurrentUser(user): CurrentUser,
+    Extension(pages): Extension<Arc<resilience::PageCache>>,
+    uri: Uri,
+    query: Query<Params>,
+    HxBoosted(boosted): HxBoosted,
+) -> Result<maud::Markup, AppError> {
+    let per_page = database::per_page(query.per_page.as_deref());
+    let result = resilience::retry(|| database::get_new_items(&pool, query.page, per_page)).await;
+    let links = result
+        .as_ref()
+        .ok()
+        .and_then(Option::as_ref)
+        .map(database::Page::links);
+    let content = pages.render(&resilience::PageCache::key(&uri, None), result, |page| {
+        templates::chart(
+            "Recently added",
+            "Newest items in the catalog",
+            "No matching entries found!",
+            page,
+        )
+    })?;
+    Ok(if boosted {
+        content
     } else {
-        StatusCode::NOT_FOUND.into_response()
-    }
+        templates::index(content, routes::ITEMS, user.as_ref(), links.as_ref())
+    })
 }
 
-async fn register_form_handler(HxRequest(is_htmx): HxRequest) -> impl IntoResponse {
-    if is_htmx {
-        templates::register_form(None).into_response()
+async fn tag_view_handler(
+    State(pool): State<PgPool>,
+    CurrentUser(user): CurrentUser,
+    Extension(pages): Extension<Arc<resilience::PageCache>>,
+    uri: Uri,
+    HxBoosted(boosted): HxBoosted,
+) -> Result<maud::Markup, AppError> {
+    let result = resilience::retry(|| database::get_tags(&pool)).await;
+    let content = pages.render(&resilience::PageCache::key(&uri, None), result, |tags| {
+        templates::tag_view(&tags)
+    })?;
+    Ok(if boosted {
+        content
     } else {
-        StatusCode::NOT_FOUND.into_response()
-    }
+        templates::index(content, routes::ITEMS, user.as_ref(), None)
+    })
 }
 
 #[derive(Deserialize)]
-struct Login {
-    username: String,
-    password: String,
+struct ReviewParams {
+    search: Option<String>,
+    score: Option<String>,
+    tag: Option<String>,
+    page: Option<i32>,
 }
 
-async fn login_handler(
+async fn review_view_handler(
     State(pool): State<PgPool>,
-    session: Session<SessionNullPool>,
-    HxRequest(is_htmx): HxRequest,
-    HxCurrentUrl(current_url): HxCurrentUrl,
-    form: Form<Login>,
-) -> impl IntoResponse {
-    match database::login_user(&pool, &form.username, &form.password).await {
-        Ok(user) => {
-            session.set("user", &user);
-            if is_htmx {
-                (
-                    HxLocation {
-                        uri: current_url.unwrap(),
-                    },
-                    templates::logged_in(&user),
-                )
-                    .into_response()
-            } else {
-                StatusCode::OK.into_response()
-            }
-        }
-        Err(e) => {
-            if is_htmx {
-                templates::login_form(Some(&e.to_string())).into_response()
-            } else {
-                StatusCode::UNAUTHORIZED.into_response()
-            }
+    CurrentUser(user): CurrentUser,
+    Extension(pages): Extension<Arc<resilience::PageCache>>,
+    uri: Uri,
+    Query(query): Query<ReviewParams>,
+    HxBoosted(boosted): HxBoosted,
+) -> Result<maud::Markup, AppError> {
+    let search = query.search.filter(|search| !search.trim().is_empty());
+    let score = query.score.and_then(|score| score.parse::<i16>().ok());
+    let tag = query.tag.filter(|tag| !tag.is_empty());
+    let username = user.as_ref().map(|user| user.username.as_str());
+    let result = resilience::retry(|| async {
+        Ok((
+            database::get_reviews(
+                &pool,
+                query.page,
+                search.as_deref(),
+                score,
+                tag.as_deref(),
+                username,
+            )
+            .await?,
+            database::get_tags(&pool).await?,
+        ))
+    })
+    .await;
+    let links = result
+        .as_ref()
+        .ok()
+        .and_then(|(page, _)| page.as_ref())
+        .map(database::Page::links);
+    let content = pages.render(
+        &resilience::PageCache::key(&uri, username),
+        result,
+        |(page, tags)| {
+            templates::review_view(page, search.as_deref(), score, tag.as_deref(), &tags)
+        },
+    )?;
+    Ok(if boosted {
+        content
+    } else {
+        templates::index(content, routes::ITEMS, user.as_ref(), links.as_ref())
+    })
+}
+
+/// Whether the user browses items by scrolling, remembered in the session after the first look.
+async fn infinite_scroll(
+    pool: &PgPool,
+    session: &Session<SessionNullPool>,
+    user: Option<&database::User>,
+) -> Result<bool, database::DatabaseError> {
+    match (session.get::<bool>("infinite_scroll"), user) {
+        (Some(enabled), _) => Ok(enabled),
+        (None, Some(user)) => {
+            let enabled = database::get_infinite_scroll(pool, &user.username).await?;
+            session.set("infinite_scroll", enabled);
+            Ok(enabled)
         }
+        (None, None) => Ok(false),
     }
 }
 
-#[derive(Deserialize)]
-struct Register {
-    username: String,
-    password1: String,
-    password2: String,
+async fn recommended_items(pool: &PgPool, user: Option<&database::User>) -> Vec<database::Item> {
+    match user {
+        Some(user) => recommendations::recommended_items(pool, &user.username, 4)
+            .await
+            .unwrap_or_default(),
+        None => Vec::new(),
+    }
 }
 
-async fn register_handler(
-    State(pool): State<PgPool>,
-    session: Session<SessionNullPool>,
+async fn user_remove_form_handler(
+    Path(username): Path<String>,
     HxRequest(is_htmx): HxRequest,
-    HxCurrentUrl(current_url): HxCurrentUrl,
-    form: Form<Register>,
 ) -> impl IntoResponse {
-    match database::register_user(&pool, &form.username, &form.password1, &form.password2).await {
-        Ok(user) => {
-            session.set("user", &user);
-            if is_htmx {
-                (
-                    HxLocation {
-                        uri: current_url.unwrap(),
-                    },
-                    templates::logged_in(&user),
-                )
-                    .into_response()
-            } else {
-                StatusCode::OK.into_response()
-            }
-        }
-        Err(e) => {
-            if is_htmx {
-                templates::register_form(Some(&e.to_string())).into_response()
-            } else {
-                StatusCode::UNAUTHORIZED.into_response()
-            }
-        }
+    if is_htmx {
+        templates::remove_form(
+            &routes::url::user_remove(&username),
+            "Remove user",
+            &username,
+        )
+        .into_response()
+    } else {
+        StatusCode::NOT_FOUND.into_response()
     }
 }
 
-async fn logout_handler(
+async fn user_remove_handler(
+    State(pool): State<PgPool>,
+    Extension(storage): Extension<Arc<dyn storage::Storage>>,
     session: Session<SessionNullPool>,
-    HxCurrentUrl(current_url): HxCurrentUrl,
+    AuthUser(user): AuthUser,
+    Path(username): Path<String>,
     HxRequest(is_htmx): HxRequest,
-) -> impl IntoResponse {
-    session.destroy();
-    if is_htmx {
+) -> Result<Response, AppError> {
+    if !user.is_admin && user.username != username {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    }
+    let page_user = database::get_user(&pool, &username)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    if page_user.is_admin {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    }
+    let moderator = (user.username != username).then_some(user.username.as_str());
+    database::remove_user(&pool, &username, moderator).await?;
+    if user.username == page_user.username {
+        session.destroy();
+    }
+    if let Some(avatar) = &page_user.avatar {
+        release_image(&pool, &*storage, images::AVATARS, avatar).await?;
+    }
+    Ok(if is_htmx {
         (
             HxLocation {
-                uri: current_url.unwrap(),
+                uri: routes::url::path(routes::USERS).try_into()?,
             },
-            templates::login_button(),
+            (),
         )
             .into_response()
     } else {
         StatusCode::OK.into_response()
+    })
+}
+
+async fn user_password_reset_handler(
+    State(pool): State<PgPool>,
+    AdminUser(user): AdminUser,
+    Extension(revocations): Extension<Arc<sessions::Revocations>>,
+    Path(username): Path<String>,
+) -> Result<Response, AppError> {
+    let Some(id) = database::force_password_reset(&pool, &username, &user.username).await? else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    revocations.revoke(id);
+    if let Some(email) = database::get_user_email(&pool, &username).await? {
+        if email.verified {
+            mailer::enqueue(
+                &pool,
+                &email.address,
+                &emails::password_reset(
+                    &username,
+                    &mailer::link(&routes::url::path(routes::INDEX)),
+                ),
+            )
+            .await?;
+        }
+    }
+    Ok((
+        HxLocation {
+            uri: routes::url::user(&username).try_into()?,
+        },
+        (),
+    )
+        .into_response())
+}
+
+/// Asks a visitor to log in when the user whose page they opened lets only logged in users see
+/// their pages. Every page of a user checks this, through [`guard_user_pages`] when it does not
+/// load the user's privacy settings itself.
+fn login_required(
+    username: &str,
+    privacy: &database::Privacy,
+    viewer: Option<&database::User>,
+    boosted: bool,
+) -> Option<Response> {
+    if !privacy.login_required || viewer.is_some() {
+        return None;
+    }
+    let content = templates::user_login_required(username);
+    Some(if boosted {
+        (StatusCode::UNAUTHORIZED, content).into_response()
+    } else {
+        (
+            StatusCode::UNAUTHORIZED,
+            templates::index(content, routes::USERS, None, None),
+        )
+            .into_response()
+    })
+}
+
+/// The response to give instead of a page of the user, when there is no such user or the visitor
+/// has to [log in](login_required) to see it.
+async fn guard_user_pages(
+    pool: &PgPool,
+    username: &str,
+    viewer: Option<&database::User>,
+    boosted: bool,
+) -> Result<Option<Response>, AppError> {
+    Ok(match database::get_privacy(pool, username).await? {
+        Some(privacy) => login_required(username, &privacy, viewer, boosted),
+        None => Some(StatusCode::NOT_FOUND.into_response()),
+    })
+}
+
+async fn user_handler(
+    State(pool): State<PgPool>,
+    CurrentUser(user): CurrentUser,
+    query: Query<Params>,
+    Path(username): Path<String>,
+    HxBoosted(boosted): HxBoosted,
+) -> Result<Response, AppError> {
+    let Some((page_user, profile, privacy)) = database::get_user_profile(&pool, &username).await?
+    else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    if let Some(response) = login_required(&username, &privacy, user.as_ref(), boosted) {
+        return Ok(response);
+    }
+    let hidden_ratings = privacy.hides_ratings_from(&username, user.as_ref());
+    let (compatibility, following) = match &user {
+        Some(user) if user.username != username => (
+            if hidden_ratings {
+                None
+            } else {
+                Some(database::get_compatibility(&pool, &user.username, &username).await?)
+            },
+            Some(database::is_following(&pool, &user.username, &username).await?),
+        ),
+        _ => (None, None),
+    };
+    let (ratings, stats) = if hidden_ratings {
+        (None, database::UserStats::default())
+    } else {
+        (
+            database::get_user_ratings(
+                &pool,
+                query.page,
+                &username,
+                user.as_ref().map(|user| user.username.as_str()),
+            )
+            .await?,
+            database::get_user_stats(
+                &pool,
+                &username,
+                user.as_ref().map(|user| user.username.as_str()),
+            )
+            .await?,
+        )
+    };
+    let links = ratings.as_ref().map(database::Page::links);
+    let user_page = templates::user_page(
+        &page_user,
+        &profile,
+        &privacy,
+        &stats,
+        compatibility.as_ref(),
+        ratings,
+        user.as_ref(),
+        following,
+    );
+    Ok(if boosted {
+        user_page.into_response()
+    } else {
+        templates::index(user_page, routes::USERS, user.as_ref(), links.as_ref()).into_response()
+    })
+}
+
+async fn user_compatibility_handler(
+    State(pool): State<PgPool>,
+    AuthUser(user): AuthUser,
+    Path(username): Path<String>,
+    HxRequest(is_htmx): HxRequest,
+) -> Result<Response, AppError> {
+    if !is_htmx {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    }
+    match database::get_user_profile(&pool, &username).await? {
+        Some((_, _, privacy)) if !privacy.hides_ratings_from(&username, Some(&user)) => {}
+        _ => return Ok(StatusCode::NOT_FOUND.into_response()),
+    }
+    Ok(templates::shared_ratings(
+        &username,
+        &database::get_shared_ratings(&pool, &user.username, &username).await?,
+    )
+    .into_response())
+}
+
+async fn item_lists_handler(
+    State(pool): State<PgPool>,
+    AuthUser(user): AuthUser,
+    Path(locator): Path<String>,
+    HxRequest(is_htmx): HxRequest,
+) -> Result<Response, AppError> {
+    if !is_htmx {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    }
+    let lists = database::get_item_lists(&pool, &locator, &user.username).await?;
+    Ok(templates::item_list_menu(&locator, &user.username, &lists).into_response())
+}
+
+async fn item_list_add_handler(
+    State(pool): State<PgPool>,
+    AuthUser(user): AuthUser,
+    Path((locator, list)): Path<(String, String)>,
+) -> Result<Response, AppError> {
+    database::add_user_list_item(&pool, &user.username, &list, &locator).await?;
+    let lists = database::get_item_lists(&pool, &locator, &user.username).await?;
+    Ok(templates::item_list_menu(&locator, &user.username, &lists).into_response())
+}
+
+async fn item_list_remove_handler(
+    State(pool): State<PgPool>,
+    AuthUser(user): AuthUser,
+    Path((locator, list)): Path<(String, String)>,
+) -> Result<Response, AppError> {
+    database::remove_user_list_item(&pool, &user.username, &list, &locator).await?;
+    let lists = database::get_item_lists(&pool, &locator, &user.username).await?;
+    Ok(templates::item_list_menu(&locator, &user.username, &lists).into_response())
+}
+
+async fn follow_add_handler(
+    State(pool): State<PgPool>,
+    AuthUser(user): AuthUser,
+    Path(username): Path<String>,
+) -> Result<Response, AppError> {
+    if user.username == username {
+        return Ok(StatusCode::BAD_REQUEST.into_response());
+    }
+    database::set_following(&pool, &user.username, &username, true).await?;
+    Ok(templates::follow_button(&username, true).into_response())
+}
+
+async fn follow_remove_handler(
+    State(pool): State<PgPool>,
+    AuthUser(user): AuthUser,
+    Path(username): Path<String>,
+) -> Result<Response, AppError> {
+    database::set_following(&pool, &user.username, &username, false).await?;
+    Ok(templates::follow_button(&username, false).into_response())
+}
+
+async fn user_favorites_handler(
+    State(pool): State<PgPool>,
+    CurrentUser(user): CurrentUser,
+    Path(username): Path<String>,
+    HxRequest(is_htmx): HxRequest,
+) -> Result<Response, AppError> {
+    if !is_htmx {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    }
+    if let Some(response) = guard_user_pages(&pool, &username, user.as_ref(), true).await? {
+        return Ok(response);
+    }
+    Ok(
+        templates::user_favorites(&database::get_user_favorites(&pool, &username).await?)
+            .into_response(),
+    )
+}
+
+async fn user_lists_handler(
+    State(pool): State<PgPool>,
+    CurrentUser(user): CurrentUser,
+    Path(username): Path<String>,
+    HxBoosted(boosted): HxBoosted,
+) -> Result<Response, AppError> {
+    if let Some(response) = guard_user_pages(&pool, &username, user.as_ref(), boosted).await? {
+        return Ok(response);
+    }
+    let content = templates::user_lists(
+        &username,
+        &database::get_user_lists(&pool, &username).await?,
+        user.as_ref(),
+        None,
+    );
+    Ok(if boosted {
+        content.into_response()
+    } else {
+        templates::index(content, routes::USERS, user.as_ref(), None).into_response()
+    })
+}
+
+async fn user_list_add_handler(
+    State(pool): State<PgPool>,
+    AuthUser(user): AuthUser,
+    Path(username): Path<String>,
+    Form(form): Form<forms::ListFormData>,
+) -> Result<Response, AppError> {
+    if user.username != username {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    }
+    let result = match form.validated() {
+        Ok(form) => {
+            database::add_user_list(&pool, &username, &forms::slug(&form.name), &form.name).await
+        }
+        Err(e) => Err(e),
+    };
+    Ok(templates::user_lists(
+        &username,
+        &database::get_user_lists(&pool, &username).await?,
+        Some(&user),
+        result.err().map(|err| err.to_string()).as_deref(),
+    )
+    .into_response())
+}
+
+/// Renders the page of a user's list, or nothing when the user has no such list.
+async fn user_list_page(
+    pool: &PgPool,
+    username: &str,
+    slug: &str,
+    user: Option<&database::User>,
+    message: Option<&str>,
+) -> Result<Option<maud::Markup>, database::DatabaseError> {
+    let Some(list) = database::get_user_list(pool, username, slug).await? else {
+        return Ok(None);
+    };
+    let items = database::get_user_list_items(pool, username, slug).await?;
+    Ok(Some(templates::user_list_page(
+        username, &list, &items, user, message,
+    )))
+}
+
+async fn user_list_handler(
+    State(pool): State<PgPool>,
+    CurrentUser(user): CurrentUser,
+    Path((username, list)): Path<(String, String)>,
+    HxBoosted(boosted): HxBoosted,
+) -> Result<Response, AppError> {
+    if let Some(response) = guard_user_pages(&pool, &username, user.as_ref(), boosted).await? {
+        return Ok(response);
+    }
+    let Some(content) = user_list_page(&pool, &username, &list, user.as_ref(), None).await? else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    Ok(if boosted {
+        content.into_response()
+    } else {
+        templates::index(content, routes::USERS, user.as_ref(), None).into_response()
+    })
+}
+
+async fn user_list_rename_handler(
+    State(pool): State<PgPool>,
+    AuthUser(user): AuthUser,
+    Path((username, list)): Path<(String, String)>,
+    Form(form): Form<forms::ListFormData>,
+) -> Result<Response, AppError> {
+    if user.username != username {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    }
+    let result = match form.validated() {
+        Ok(form) => {
+            let slug = forms::slug(&form.name);
+            database::rename_user_list(&pool, &username, &list, &slug, &form.name)
+                .await
+                .map(|_| slug)
+        }
+        Err(e) => Err(e),
+    };
+    Ok(match result {
+        Ok(slug) => (
+            HxLocation {
+                uri: routes::url::user_list(&username, &slug).try_into()?,
+            },
+            (),
+        )
+            .into_response(),
+        Err(e) => {
+            match user_list_page(&pool, &username, &list, Some(&user), Some(&e.to_string())).await?
+            {
+                Some(content) => content.into_response(),
+                None => StatusCode::NOT_FOUND.into_response(),
+            }
+        }
+    })
+}
+
+async fn user_list_remove_handler(
+    State(pool): State<PgPool>,
+    AuthUser(user): AuthUser,
+    Path((username, list)): Path<(String, String)>,
+) -> Result<Response, AppError> {
+    if user.username != username {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    }
+    database::remove_user_list(&pool, &username, &list).await?;
+    Ok((
+        HxLocation {
+            uri: routes::url::user_lists(&username).try_into()?,
+        },
+        (),
+    )
+        .into_response())
+}
+
+async fn user_list_item_move_handler(
+    State(pool): State<PgPool>,
+    AuthUser(user): AuthUser,
+    Path((username, list, locator)): Path<(String, String, String)>,
+    Form(form): Form<forms::MoveFormData>,
+) -> Result<Response, AppError> {
+    if user.username != username {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    }
+    let earlier = matches!(form.direction, forms::Direction::Earlier);
+    database::move_user_list_item(&pool, &username, &list, &locator, earlier).await?;
+    Ok(
+        match user_list_page(&pool, &username, &list, Some(&user), None).await? {
+            Some(content) => content.into_response(),
+            None => StatusCode::NOT_FOUND.into_response(),
+        },
+    )
+}
+
+async fn user_list_item_remove_handler(
+    State(pool): State<PgPool>,
+    AuthUser(user): AuthUser,
+    Path((username, list, locator)): Path<(String, String, String)>,
+) -> Result<Response, AppError> {
+    if user.username != username {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    }
+    database::remove_user_list_item(&pool, &username, &list, &locator).await?;
+    Ok(
+        match user_list_page(&pool, &username, &list, Some(&user), None).await? {
+            Some(content) => content.into_response(),
+            None => StatusCode::NOT_FOUND.into_response(),
+        },
+    )
+}
+
+async fn user_import_form_handler(
+    AuthUser(user): AuthUser,
+    Path(username): Path<String>,
+    HxRequest(is_htmx): HxRequest,
+) -> impl IntoResponse {
+    if !is_htmx {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    if user.username != username {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    templates::import_form(&username, None).into_response()
+}
+
+async fn user_import_preview_handler(
+    State(pool): State<PgPool>,
+    AuthUser(user): AuthUser,
+    Path(username): Path<String>,
+    HxRequest(is_htmx): HxRequest,
+    multipart: Multipart,
+) -> impl IntoResponse {
+    if !is_htmx {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    if user.username != username {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    let rows = match forms::ImportFormData::from_multipart(multipart)
+        .await
+        .and_then(|form| form.file.ok_or(database::DatabaseError::EmptyFields))
+        .and_then(|file| import::parse_csv(&file))
+    {
+        Ok(rows) => rows,
+        Err(err) => {
+            return templates::import_form(&username, Some(&err.to_string())).into_response()
+        }
+    };
+    match database::match_import_rows(&pool, &rows).await {
+        Ok(matches) => templates::import_preview(&username, &matches).into_response(),
+        Err(err) => templates::import_form(&username, Some(&err.to_string())).into_response(),
+    }
+}
+
+async fn user_import_handler(
+    State(pool): State<PgPool>,
+    AuthUser(user): AuthUser,
+    Path(username): Path<String>,
+    HxRequest(is_htmx): HxRequest,
+    HxCurrentUrl(current_url): HxCurrentUrl,
+    Form(ratings): Form<Vec<(String, String)>>,
+) -> Result<Response, AppError> {
+    if user.username != username {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    }
+    let ratings: HashMap<String, i16> = ratings
+        .into_iter()
+        .filter_map(|(locator, rating)| Some((locator, rating.parse().ok()?)))
+        .collect();
+    let ratings: Vec<(String, i16)> = ratings.into_iter().collect();
+    if let Err(err) = database::import_ratings(&pool, &username, &ratings).await {
+        return Ok(if is_htmx {
+            templates::import_form(&username, Some(&err.to_string())).into_response()
+        } else if let database::DatabaseError::RateLimited = err {
+            StatusCode::TOO_MANY_REQUESTS.into_response()
+        } else {
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        });
+    }
+    Ok(if is_htmx {
+        (
+            HxLocation {
+                uri: current_url.ok_or(AppError::BadRequest)?,
+            },
+            (),
+        )
+            .into_response()
+    } else {
+        StatusCode::OK.into_response()
+    })
+}
+
+async fn user_view_handler(
+    State(pool): State<PgPool>,
+    CurrentUser(user): CurrentUser,
+    Extension(pages): Extension<Arc<resilience::PageCache>>,
+    Extension(cache): Extension<Arc<cache::QueryCache>>,
+    uri: Uri,
+    query: Query<Params>,
+    HxBoosted(boosted): HxBoosted,
+) -> Result<maud::Markup, AppError> {
+    let per_page = database::per_page(query.per_page.as_deref());
+    let is_first_page =
+        query.search.is_none() && query.after.is_none() && query.page.unwrap_or(0) == 0;
+    let fetch = || {
+        database::get_users(
+            &pool,
+            query.page,
+            per_page,
+            query.search.as_deref(),
+            query.after.as_deref(),
+        )
+    };
+    let result = resilience::retry(|| async {
+        if is_first_page {
+            cache.user_listing(uri.to_string(), fetch()).await
+        } else {
+            fetch().await
+        }
+    })
+    .await;
+    let links = result
+        .as_ref()
+        .ok()
+        .and_then(Option::as_ref)
+        .map(database::Page::links);
+    let content = pages.render(
+        &resilience::PageCache::key(&uri, None),
+        result,
+        templates::user_view,
+    )?;
+    Ok(if boosted {
+        content
+    } else {
+        templates::index(content, routes::USERS, user.as_ref(), links.as_ref())
+    })
+}
+
+/// Serves images that are not in the local image directory from storage, redirects requests for
+/// resized variants that were never written to the image they were made of, and serves a
+/// generated cover in place of missing item images, so that cards never show a broken background.
+/// Only the stored images are cached for good, as a generated cover changes with the title.
+async fn image_fallback_handler(
+    State(pool): State<PgPool>,
+    Extension(storage): Extension<Arc<dyn storage::Storage>>,
+    uri: Uri,
+) -> impl IntoResponse {
+    let Some(key) = uri.path().strip_prefix('/') else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    match storage.get(key).await {
+        Ok(Some(image)) => {
+            return (
+                [(header::CONTENT_TYPE, images::content_type(&image))],
+                image,
+            )
+                .into_response()
+        }
+        Ok(None) => {}
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+    let original = images::original(key);
+    if let Some(original) = original {
+        if storage.exists(original).await.unwrap_or(false) {
+            return Redirect::temporary(&routes::url::image(original)).into_response();
+        }
+    }
+    let Some(name) = original
+        .unwrap_or(key)
+        .strip_prefix(images::COVERS)
+        .and_then(|name| name.strip_prefix('/'))
+    else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    match database::get_item_cover(&pool, name).await {
+        Ok(Some(cover)) => (
+            [
+                (header::CONTENT_TYPE, "image/svg+xml"),
+                (header::CACHE_CONTROL, "no-cache"),
+            ],
+            svg::cover(&cover.title, cover.hue),
+        )
+            .into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+async fn admin_categories_handler(
+    State(pool): State<PgPool>,
+    AdminUser(user): AdminUser,
+    HxBoosted(boosted): HxBoosted,
+) -> Result<Response, AppError> {
+    let content = templates::admin_categories(&database::get_categories(&pool).await?, None);
+    Ok(if boosted {
+        content.into_response()
+    } else {
+        templates::index(content, routes::ITEMS, Some(&user), None).into_response()
+    })
+}
+
+async fn category_add_handler(
+    State(pool): State<PgPool>,
+    AdminUser(user): AdminUser,
+    Form(form): Form<forms::CategoryFormData>,
+) -> Result<Response, AppError> {
+    let result = match form.validated() {
+        Ok(form) => {
+            let slug = forms::slug(&form.name);
+            database::add_category(&pool, &slug, &form.name, &user.username).await
+        }
+        Err(e) => Err(e),
+    };
+    Ok(templates::admin_categories(
+        &database::get_categories(&pool).await?,
+        result.err().map(|err| err.to_string()).as_deref(),
+    )
+    .into_response())
+}
+
+async fn category_remove_handler(
+    State(pool): State<PgPool>,
+    AdminUser(user): AdminUser,
+    Path(category): Path<String>,
+) -> Result<Response, AppError> {
+    database::remove_category(&pool, &category, &user.username).await?;
+    Ok(templates::admin_categories(&database::get_categories(&pool).await?, None).into_response())
+}
+
+async fn admin_collections_handler(
+    State(pool): State<PgPool>,
+    AdminUser(user): AdminUser,
+    HxBoosted(boosted): HxBoosted,
+) -> Result<Response, AppError> {
+    let content = templates::admin_collections(&database::get_collections(&pool).await?, None);
+    Ok(if boosted {
+        content.into_response()
+    } else {
+        templates::index(content, routes::ITEMS, Some(&user), None).into_response()
+    })
+}
+
+async fn collection_add_handler(
+    State(pool): State<PgPool>,
+    _: AdminUser,
+    Form(form): Form<forms::CollectionFormData>,
+) -> Result<Response, AppError> {
+    let result = match form.validated() {
+        Ok(form) => database::add_collection(&pool, &forms::slug(&form.name), &form.name).await,
+        Err(e) => Err(e),
+    };
+    Ok(templates::admin_collections(
+        &database::get_collections(&pool).await?,
+        result.err().map(|err| err.to_string()).as_deref(),
+    )
+    .into_response())
+}
+
+async fn collection_remove_handler(
+    State(pool): State<PgPool>,
+    _: AdminUser,
+    Path(collection): Path<String>,
+) -> Result<Response, AppError> {
+    database::remove_collection(&pool, &collection).await?;
+    Ok(
+        templates::admin_collections(&database::get_collections(&pool).await?, None)
+            .into_response(),
+    )
+}
+
+/// Renders the page of a collection, or nothing when there is no such collection.
+async fn collection_page(
+    pool: &PgPool,
+    slug: &str,
+    user: Option<&database::User>,
+    message: Option<&str>,
+) -> Result<Option<maud::Markup>, database::DatabaseError> {
+    let Some(collection) = database::get_collection(pool, slug).await? else {
+        return Ok(None);
+    };
+    let items = database::get_collection_items(pool, slug).await?;
+    Ok(Some(templates::collection_page(
+        &collection,
+        &items,
+        user,
+        message,
+    )))
+}
+
+async fn collection_handler(
+    State(pool): State<PgPool>,
+    CurrentUser(user): CurrentUser,
+    Path(collection): Path<String>,
+    HxBoosted(boosted): HxBoosted,
+) -> Result<Response, AppError> {
+    let Some(content) = collection_page(&pool, &collection, user.as_ref(), None).await? else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    Ok(if boosted {
+        content.into_response()
+    } else {
+        templates::index(content, routes::ITEMS, user.as_ref(), None).into_response()
+    })
+}
+
+async fn collection_item_add_handler(
+    State(pool): State<PgPool>,
+    AdminUser(user): AdminUser,
+    Path(collection): Path<String>,
+    Form(form): Form<forms::CollectionItemFormData>,
+) -> Result<Response, AppError> {
+    let result = match form.validated() {
+        Ok(form) => database::add_collection_item(&pool, &collection, &form.locator).await,
+        Err(e) => Err(e),
+    };
+    let message = result.err().map(|err| err.to_string());
+    Ok(
+        match collection_page(&pool, &collection, Some(&user), message.as_deref()).await? {
+            Some(content) => content.into_response(),
+            None => StatusCode::NOT_FOUND.into_response(),
+        },
+    )
+}
+
+async fn collection_item_move_handler(
+    State(pool): State<PgPool>,
+    AdminUser(user): AdminUser,
+    Path((collection, locator)): Path<(String, String)>,
+    Form(form): Form<forms::MoveFormData>,
+) -> Result<Response, AppError> {
+    let earlier = matches!(form.direction, forms::Direction::Earlier);
+    database::move_collection_item(&pool, &collection, &locator, earlier).await?;
+    Ok(
+        match collection_page(&pool, &collection, Some(&user), None).await? {
+            Some(content) => content.into_response(),
+            None => StatusCode::NOT_FOUND.into_response(),
+        },
+    )
+}
+
+async fn collection_item_remove_handler(
+    State(pool): State<PgPool>,
+    AdminUser(user): AdminUser,
+    Path((collection, locator)): Path<(String, String)>,
+) -> Result<Response, AppError> {
+    database::remove_collection_item(&pool, &collection, &locator).await?;
+    Ok(
+        match collection_page(&pool, &collection, Some(&user), None).await? {
+            Some(content) => content.into_response(),
+            None => StatusCode::NOT_FOUND.into_response(),
+        },
+    )
+}
+
+async fn suggestions_handler(
+    State(pool): State<PgPool>,
+    AuthUser(user): AuthUser,
+    HxBoosted(boosted): HxBoosted,
+) -> Result<Response, AppError> {
+    let content = templates::suggestions(
+        &database::get_suggestions(&pool, Some(&user.username)).await?,
+        None,
+    );
+    Ok(if boosted {
+        content.into_response()
+    } else {
+        templates::index(content, routes::ITEMS, Some(&user), None).into_response()
+    })
+}
+
+async fn suggestion_add_handler(
+    State(pool): State<PgPool>,
+    AuthUser(user): AuthUser,
+    Form(form): Form<forms::SuggestionFormData>,
+) -> Result<Response, AppError> {
+    let result = match form.validated() {
+        Ok(form) => {
+            database::add_suggestion(
+                &pool,
+                &user.username,
+                &form.locator,
+                &form.title,
+                &form.description,
+            )
+            .await
+        }
+        Err(e) => Err(e),
+    };
+    Ok(templates::suggestions(
+        &database::get_suggestions(&pool, Some(&user.username)).await?,
+        result.err().map(|err| err.to_string()).as_deref(),
+    )
+    .into_response())
+}
+
+async fn admin_suggestions_handler(
+    State(pool): State<PgPool>,
+    AdminUser(user): AdminUser,
+    HxBoosted(boosted): HxBoosted,
+) -> Result<Response, AppError> {
+    let content =
+        templates::admin_suggestions(&database::get_suggestions(&pool, None).await?, None);
+    Ok(if boosted {
+        content.into_response()
+    } else {
+        templates::index(content, routes::ITEMS, Some(&user), None).into_response()
+    })
+}
+
+async fn suggestion_approve_handler(
+    State(pool): State<PgPool>,
+    AdminUser(user): AdminUser,
+    Path(id): Path<i32>,
+) -> Result<Response, AppError> {
+    let message = match database::approve_suggestion(&pool, id, &user.username).await {
+        Ok(Some((locator, title))) => {
+            webhooks::dispatch(
+                &pool,
+                webhooks::Event::ItemCreated,
+                webhooks::item(&locator, Some(&title)),
+            )
+            .await?;
+            activitypub::publish_item(&pool, &locator).await?;
+            None
+        }
+        Ok(None) => return Ok(StatusCode::NOT_FOUND.into_response()),
+        Err(err) => Some(err.to_string()),
+    };
+    Ok(templates::admin_suggestions(
+        &database::get_suggestions(&pool, None).await?,
+        message.as_deref(),
+    )
+    .into_response())
+}
+
+async fn suggestion_reject_handler(
+    State(pool): State<PgPool>,
+    AdminUser(user): AdminUser,
+    Path(id): Path<i32>,
+    Form(form): Form<forms::RejectionFormData>,
+) -> Result<Response, AppError> {
+    let message = match form.validated() {
+        Ok(form) => {
+            match database::reject_suggestion(&pool, id, &form.reason, &user.username).await? {
+                true => None,
+                false => return Ok(StatusCode::NOT_FOUND.into_response()),
+            }
+        }
+        Err(err) => Some(err.to_string()),
+    };
+    Ok(templates::admin_suggestions(
+        &database::get_suggestions(&pool, None).await?,
+        message.as_deref(),
+    )
+    .into_response())
+}
+
+async fn admin_webhooks_handler(
+    State(pool): State<PgPool>,
+    AdminUser(user): AdminUser,
+    HxBoosted(boosted): HxBoosted,
+) -> Result<Response, AppError> {
+    let content = templates::admin_webhooks(
+        &webhooks::get_webhooks(&pool).await?,
+        &webhooks::get_deliveries(&pool).await?,
+        None,
+    );
+    Ok(if boosted {
+        content.into_response()
+    } else {
+        templates::index(content, routes::ITEMS, Some(&user), None).into_response()
+    })
+}
+
+async fn webhook_add_handler(
+    State(pool): State<PgPool>,
+    _: AdminUser,
+    Form(fields): Form<Vec<(String, String)>>,
+) -> Result<Response, AppError> {
+    let result = match forms::WebhookFormData::from(fields).validated() {
+        Ok(form) => webhooks::add_webhook(&pool, &form.url, &form.events).await,
+        Err(e) => Err(e),
+    };
+    Ok(templates::admin_webhooks(
+        &webhooks::get_webhooks(&pool).await?,
+        &webhooks::get_deliveries(&pool).await?,
+        result.err().map(|err| err.to_string()).as_deref(),
+    )
+    .into_response())
+}
+
+async fn webhook_remove_handler(
+    State(pool): State<PgPool>,
+    _: AdminUser,
+    Path(id): Path<i32>,
+) -> Result<Response, AppError> {
+    webhooks::remove_webhook(&pool, id).await?;
+    Ok(templates::admin_webhooks(
+        &webhooks::get_webhooks(&pool).await?,
+        &webhooks::get_deliveries(&pool).await?,
+        None,
+    )
+    .into_response())
+}
+
+async fn webhook_redeliver_handler(
+    State(pool): State<PgPool>,
+    _: AdminUser,
+    Path(id): Path<i32>,
+) -> Result<Response, AppError> {
+    if !webhooks::redeliver(&pool, id).await? {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    }
+    Ok(templates::admin_webhooks(
+        &webhooks::get_webhooks(&pool).await?,
+        &webhooks::get_deliveries(&pool).await?,
+        None,
+    )
+    .into_response())
+}
+
+async fn admin_jobs_handler(
+    State(pool): State<PgPool>,
+    AdminUser(user): AdminUser,
+    HxBoosted(boosted): HxBoosted,
+) -> Result<Response, AppError> {
+    let content = templates::admin_jobs(&jobs::get_jobs(&pool).await?, None);
+    Ok(if boosted {
+        content.into_response()
+    } else {
+        templates::index(content, routes::ITEMS, Some(&user), None).into_response()
+    })
+}
+
+async fn job_run_handler(
+    State(pool): State<PgPool>,
+    _: AdminUser,
+    Path(name): Path<String>,
+) -> Result<Response, AppError> {
+    if !jobs::run_now(&pool, &name).await? {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    }
+    Ok(templates::admin_jobs(
+        &jobs::get_jobs(&pool).await?,
+        Some(&format!("The {name} job will run within a minute.")),
+    )
+    .into_response())
+}
+
+#[derive(Deserialize)]
+struct AuditParams {
+    action: Option<String>,
+    actor: Option<String>,
+    target: Option<String>,
+    page: Option<i32>,
+}
+
+async fn admin_audit_handler(
+    State(pool): State<PgPool>,
+    AdminUser(user): AdminUser,
+    Query(query): Query<AuditParams>,
+    HxBoosted(boosted): HxBoosted,
+) -> Result<Response, AppError> {
+    let action = query.action.filter(|action| !action.is_empty());
+    let actor = query.actor.filter(|actor| !actor.trim().is_empty());
+    let target = query.target.filter(|target| !target.trim().is_empty());
+    let page = database::get_audit_log(
+        &pool,
+        query.page,
+        action.as_deref(),
+        actor.as_deref().map(str::trim),
+        target.as_deref().map(str::trim),
+    )
+    .await?;
+    let content =
+        templates::admin_audit(page, action.as_deref(), actor.as_deref(), target.as_deref());
+    Ok(if boosted {
+        content.into_response()
+    } else {
+        templates::index(content, routes::ITEMS, Some(&user), None).into_response()
+    })
+}
+
+#[derive(Deserialize)]
+struct SeedParams {
+    items: Option<u32>,
+    users: Option<u32>,
+}
+
+async fn seed_handler(
+    State(pool): State<PgPool>,
+    Extension(storage): Extension<Arc<dyn storage::Storage>>,
+    _: AdminUser,
+    Query(params): Query<SeedParams>,
+) -> Result<Response, AppError> {
+    let seeded = seed::seed(
+        &pool,
+        &*storage,
+        params.items.unwrap_or(seed::DEFAULT_ITEMS),
+        params.users.unwrap_or(seed::DEFAULT_USERS),
+    )
+    .await?;
+    Ok(seeded.to_string().into_response())
+}
+
+async fn metrics_handler(
+    Extension(images): Extension<Arc<images::ImageQueue>>,
+    Extension(latencies): Extension<Arc<metrics::Latencies>>,
+) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics::render(&images, &latencies),
+    )
+}
+
+async fn about_handler(
+    State(pool): State<PgPool>,
+    CurrentUser(user): CurrentUser,
+    Extension(stats): Extension<Arc<stats::StatsCache>>,
+    HxBoosted(boosted): HxBoosted,
+) -> Result<maud::Markup, AppError> {
+    let content = templates::about(
+        &stats.get(&pool).await?,
+        &database::get_settings(&pool).await?,
+    );
+    Ok(if boosted {
+        content
+    } else {
+        templates::index(content, routes::ITEMS, user.as_ref(), None)
+    })
+}
+
+async fn version_handler() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        version::render(),
+    )
+}
+
+async fn admin_items_handler(
+    State(pool): State<PgPool>,
+    AdminUser(user): AdminUser,
+    Query(params): Query<Vec<(String, String)>>,
+    HxBoosted(boosted): HxBoosted,
+    HxRequest(is_htmx): HxRequest,
+) -> Result<Response, AppError> {
+    let table = admin::ItemTable::from_params(&params);
+    let mut rows = database::get_item_rows(&pool).await?;
+    table.sort(&mut rows);
+    let content = templates::admin_items(&table, &rows);
+    Ok(if boosted || is_htmx {
+        content.into_response()
+    } else {
+        templates::index(content, routes::ITEMS, Some(&user), None).into_response()
+    })
+}
+
+async fn admin_items_csv_handler(
+    State(pool): State<PgPool>,
+    _: AdminUser,
+    Query(params): Query<Vec<(String, String)>>,
+) -> Result<Response, AppError> {
+    let table = admin::ItemTable::from_params(&params);
+    let mut rows = database::get_item_rows(&pool).await?;
+    table.sort(&mut rows);
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"items.csv\"",
+            ),
+        ],
+        table.to_csv(&rows)?,
+    )
+        .into_response())
+}
+
+#[derive(Deserialize)]
+struct ExportParams {
+    #[serde(default)]
+    format: export::Format,
+}
+
+async fn catalog_export_handler(
+    State(pool): State<PgPool>,
+    CurrentUser(user): CurrentUser,
+    headers: HeaderMap,
+    Query(params): Query<ExportParams>,
+) -> Result<Response, AppError> {
+    let is_admin = user.is_some_and(|user| user.is_admin);
+    if !is_admin && !export::has_token(&headers) {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    }
+    let etag = params
+        .format
+        .etag(database::get_catalog_version(&pool).await?);
+    if etag::matches(&headers, &etag) {
+        return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response());
+    }
+    Ok((
+        [
+            (header::CONTENT_TYPE, params.format.content_type()),
+            (
+                header::CONTENT_DISPOSITION,
+                params.format.content_disposition(),
+            ),
+            (header::ETAG, &etag),
+            (header::CACHE_CONTROL, "private, no-cache"),
+        ],
+        export::catalog(pool, params.format),
+    )
+        .into_response())
+}
+
+#[derive(Deserialize)]
+struct WebfingerParams {
+    resource: String,
+}
+
+async fn webfinger_handler(Query(params): Query<WebfingerParams>) -> impl IntoResponse {
+    if !activitypub::enabled() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    match activitypub::webfinger(&params.resource) {
+        Some(found) => (
+            [(header::CONTENT_TYPE, activitypub::WEBFINGER_CONTENT_TYPE)],
+            found.to_string(),
+        )
+            .into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn activitypub_actor_handler(State(pool): State<PgPool>) -> Result<Response, AppError> {
+    if !activitypub::enabled() {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    }
+    Ok((
+        [(header::CONTENT_TYPE, activitypub::CONTENT_TYPE)],
+        activitypub::actor(&pool).await?.to_string(),
+    )
+        .into_response())
+}
+
+async fn activitypub_outbox_handler(State(pool): State<PgPool>) -> Result<Response, AppError> {
+    if !activitypub::enabled() {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    }
+    Ok((
+        [(header::CONTENT_TYPE, activitypub::CONTENT_TYPE)],
+        activitypub::outbox(&pool).await?.to_string(),
+    )
+        .into_response())
+}
+
+async fn activitypub_followers_handler(State(pool): State<PgPool>) -> Result<Response, AppError> {
+    if !activitypub::enabled() {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    }
+    Ok((
+        [(header::CONTENT_TYPE, activitypub::CONTENT_TYPE)],
+        activitypub::followers(&pool).await?.to_string(),
+    )
+        .into_response())
+}
+
+async fn activitypub_inbox_handler(
+    State(pool): State<PgPool>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, AppError> {
+    if !activitypub::enabled() {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    }
+    let Ok(activity) = serde_json::from_slice(&body) else {
+        return Ok(StatusCode::BAD_REQUEST.into_response());
+    };
+    if !activitypub::verify(&pool, &headers, &body, &activity).await? {
+        return Ok(StatusCode::UNAUTHORIZED.into_response());
+    }
+    activitypub::receive(&pool, &activity).await?;
+    Ok(StatusCode::ACCEPTED.into_response())
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "target", rename_all = "lowercase")]
+enum SearchTarget {
+    All,
+    Items,
+    Users,
+}
+
+async fn search_handler(
+    State(pool): State<PgPool>,
+    session: Session<SessionNullPool>,
+    CurrentUser(user): CurrentUser,
+    Query(target): Query<SearchTarget>,
+    HxRequest(is_htmx): HxRequest,
+) -> Result<Response, AppError> {
+    Ok(if is_htmx {
+        match target {
+            SearchTarget::All => (
+                HxPushUrl(routes::url::path(routes::SEARCH_RESULTS).try_into()?),
+                templates::search(
+                    routes::SEARCH_RESULTS,
+                    Some(templates::search_results(None, None, None)),
+                ),
+            ),
+            SearchTarget::Items => {
+                let content = templates::item_view(
+                    database::get_items(
+                        &pool,
+                        None,
+                        database::default_per_page(),
+                        None,
+                        &database::ItemFilter::default(),
+                        database::SearchMode::Title,
+                        None,
+                    )
+                    .await?,
+                    &database::get_featured_items(&pool).await?,
+                    &recommended_items(&pool, user.as_ref()).await,
+                    user.as_ref(),
+                    None,
+                    database::SearchMode::Title,
+                    &database::ItemFilter::default(),
+                    &database::get_categories(&pool).await?,
+                    &database::get_tags(&pool).await?,
+                    infinite_scroll(&pool, &session, user.as_ref()).await?,
+                );
+                (
+                    HxPushUrl(routes::url::path(routes::ITEMS).try_into()?),
+                    templates::search(routes::ITEMS, Some(content)),
+                )
+            }
+            SearchTarget::Users => {
+                let content = templates::user_view(
+                    database::get_users(&pool, None, database::default_per_page(), None, None)
+                        .await?,
+                );
+                (
+                    HxPushUrl(routes::url::path(routes::USERS).try_into()?),
+                    templates::search(routes::USERS, Some(content)),
+                )
+            }
+        }
+        .into_response()
+    } else {
+        StatusCode::NOT_FOUND.into_response()
+    })
+}
+
+/// Items and users matching a search, each section linking to its full listing.
+async fn search_results_handler(
+    State(pool): State<PgPool>,
+    CurrentUser(user): CurrentUser,
+    Extension(pages): Extension<Arc<resilience::PageCache>>,
+    uri: Uri,
+    query: Query<Params>,
+    HxBoosted(boosted): HxBoosted,
+) -> Result<maud::Markup, AppError> {
+    let search = query
+        .search
+        .as_deref()
+        .filter(|search| !search.trim().is_empty());
+    let result = resilience::retry(|| async {
+        Ok(match search {
+            Some(search) => (
+                database::get_items(
+                    &pool,
+                    None,
+                    database::default_per_page(),
+                    Some(search),
+                    &database::ItemFilter::default(),
+                    query.mode,
+                    None,
+                )
+                .await?,
+                match search::Query::parse(search).text(database::SearchMode::Title) {
+                    Some(text) => {
+                        database::get_users(
+                            &pool,
+                            None,
+                            database::default_per_page(),
+                            Some(&text),
+                            None,
+                        )
+                        .await?
+                    }
+                    None => None,
+                },
+            ),
+            None => (None, None),
+        })
+    })
+    .await;
+    let content = pages.render(
+        &resilience::PageCache::key(&uri, None),
+        result,
+        |(items, users)| templates::search_results(search, items, users),
+    )?;
+    Ok(if boosted {
+        content
+    } else {
+        templates::index(content, routes::SEARCH_RESULTS, user.as_ref(), None)
+    })
+}
+
+async fn user_edit_form_handler(
+    State(pool): State<PgPool>,
+    Path(username): Path<String>,
+    HxRequest(is_htmx): HxRequest,
+) -> Result<Response, AppError> {
+    Ok(if is_htmx {
+        let Some((page_user, profile, privacy)) =
+            database::get_user_profile(&pool, &username).await?
+        else {
+            return Ok(StatusCode::NOT_FOUND.into_response());
+        };
+        templates::user_edit_form(
+            None,
+            &username,
+            database::get_user_email(&pool, &username).await?.as_ref(),
+            Some(&profile),
+            Some(&database::AvatarStyle {
+                hue: page_user.avatar_hue,
+                glyph: page_user.avatar_glyph,
+            }),
+            &privacy,
+            database::get_infinite_scroll(&pool, &username).await?,
+        )
+        .into_response()
+    } else {
+        StatusCode::NOT_FOUND.into_response()
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn user_edit_handler(
+    session: Session<SessionNullPool>,
+    AuthUser(user): AuthUser,
+    Path(username): Path<String>,
+    State(pool): State<PgPool>,
+    Extension(storage): Extension<Arc<dyn storage::Storage>>,
+    Extension(images): Extension<Arc<images::ImageQueue>>,
+    HxRequest(is_htmx): HxRequest,
+    multipart: Multipart,
+) -> Result<Response, AppError> {
+    if !user.is_admin && user.username != username {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    }
+    let form = match async {
+        let form = forms::UserFormData::from_multipart(multipart)
+            .await?
+            .validated()?;
+        forms::check_new_password(&form.password1)
+            .await
+            .map(|_| form)
+    }
+    .await
+    {
+        Ok(form) => form,
+        Err(err) => {
+            return Ok(if is_htmx {
+                templates::user_edit_form(
+                    Some(&err.to_string()),
+                    &username,
+                    None,
+                    None,
+                    None,
+                    &database::Privacy::default(),
+                    false,
+                )
+                .into_response()
+            } else {
+                StatusCode::UNPROCESSABLE_ENTITY.into_response()
+            });
+        }
+    };
+    let profile = form.profile();
+    let avatar_style = form.avatar_style();
+    let forms::UserFormData {
+        username: new_username,
+        password1: new_password1,
+        email,
+        avatar: new_avatar,
+        clear_avatar,
+        privacy,
+        infinite_scroll,
+        weekly_email,
+        ..
+    } = form;
+    let (ticket, new_avatar) = match async {
+        let Some(avatar) = new_avatar else {
+            return Ok::<_, database::DatabaseError>((None, None));
+        };
+        let ticket = images.enqueue(&user.username)?;
+        let avatar = ticket.sanitize_avatar(avatar).await?;
+        Ok((Some(ticket), Some(avatar)))
+    }
+    .await
+    {
+        Ok(upload) => upload,
+        Err(err) => {
+            return Ok(if is_htmx {
+                templates::user_edit_form(
+                    Some(&err.to_string()),
+                    &username,
+                    None,
+                    None,
+                    avatar_style.as_ref(),
+                    &privacy,
+                    infinite_scroll,
+                )
+                .into_response()
+            } else {
+                upload_error_status(&err).into_response()
+            });
+        }
+    };
+    let previous_avatar = database::get_user(&pool, &username)
+        .await?
+        .and_then(|user| user.avatar);
+    let avatar = new_avatar.as_deref().map(images::name);
+    let mut staging = images::Staging::new(&*storage);
+    let rejected = async {
+        if let (Some(ticket), Some(new_avatar), Some(avatar)) = (ticket, new_avatar, &avatar) {
+            staging
+                .stage(ticket, vec![(images::avatar_key(avatar), new_avatar)])
+                .await?;
+        }
+        let mut transaction = pool.begin().await?;
+        // Changes users make to their own accounts are theirs to make, only admins' are audited.
+        let audited = user.username != username;
+        let before = match audited {
+            true => database::user_snapshot(&mut *transaction, &username).await?,
+            false => None,
+        };
+        if let Some(profile) = &profile {
+            database::set_user_profile(&mut *transaction, &username, profile).await?;
+        }
+        database::set_infinite_scroll(&mut *transaction, &username, infinite_scroll).await?;
+        database::set_weekly_email(&mut *transaction, &username, weekly_email).await?;
+        if let Some(email) = email {
+            match database::set_user_email(&mut *transaction, &username, &email).await {
+                Ok(Some(token)) => {
+                    mailer::enqueue(
+                        &mut *transaction,
+                        email.trim(),
+                        &emails::verification(
+                            new_username.as_deref().unwrap_or(&username),
+                            &mailer::link(&routes::url::verify_email(&token)),
+                        ),
+                    )
+                    .await?
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    return Ok(Some(if is_htmx {
+                        templates::user_edit_form(
+                            Some(&err.to_string()),
+                            &username,
+                            None,
+                            None,
+                            avatar_style.as_ref(),
+                            &privacy,
+                            infinite_scroll,
+                        )
+                        .into_response()
+                    } else {
+                        StatusCode::CONFLICT.into_response()
+                    }));
+                }
+            }
+        }
+        if let Err(err) = database::edit_user(
+            &mut *transaction,
+            &username,
+            new_username.as_deref(),
+            if avatar.is_none() && clear_avatar {
+                Some(None)
+            } else {
+                avatar.as_deref().map(Some)
+            },
+            Some(&new_password1),
+            Some(&privacy),
+            avatar_style.as_ref(),
+        )
+        .await
+        {
+            return Ok(Some(if is_htmx {
+                templates::user_edit_form(
+                    Some(&err.to_string()),
+                    &username,
+                    None,
+                    None,
+                    avatar_style.as_ref(),
+                    &privacy,
+                    infinite_scroll,
+                )
+                .into_response()
+            } else {
+                StatusCode::UNAUTHORIZED.into_response()
+            }));
+        };
+        if audited {
+            let edited = new_username.as_deref().unwrap_or(&username);
+            let after = database::user_snapshot(&mut *transaction, edited).await?;
+            database::record_action(
+                &mut *transaction,
+                Some(&user.username),
+                "edit_user",
+                edited,
+                before.as_ref(),
+                after.as_ref(),
+            )
+            .await?;
+        }
+        transaction.commit().await?;
+        Ok::<_, AppError>(None)
+    }
+    .await;
+    match rejected {
+        Ok(None) => staging.commit().await,
+        Ok(Some(response)) => {
+            staging.abort().await;
+            return Ok(response);
+        }
+        Err(err) => {
+            staging.abort().await;
+            return Err(err);
+        }
+    }
+    if user.username == username {
+        session.set("infinite_scroll", infinite_scroll);
+    }
+    if let (true, Some(previous_avatar)) = (avatar.is_some() || clear_avatar, previous_avatar) {
+        release_image(&pool, &*storage, images::AVATARS, &previous_avatar).await?;
+    }
+    Ok(if is_htmx {
+        (
+            HxLocation {
+                uri: routes::url::user(&new_username.unwrap_or(username)).try_into()?,
+            },
+            (),
+        )
+            .into_response()
+    } else {
+        StatusCode::OK.into_response()
+    })
+}
+
+async fn item_edit_form_handler(
+    State(pool): State<PgPool>,
+    Path(locator): Path<String>,
+    HxRequest(is_htmx): HxRequest,
+) -> impl IntoResponse {
+    if is_htmx {
+        if let Ok(Some(item)) = database::get_item(&pool, &locator).await {
+            let tags = database::get_item_tags(&pool, &locator)
+                .await
+                .unwrap_or_default()
+                .join(", ");
+            templates::item_form(
+                &routes::url::item_edit(&locator),
+                "Edit item",
+                None,
+                Some(&item),
+                Some(&tags),
+                &database::get_categories(&pool).await.unwrap_or_default(),
+                &[],
+            )
+            .into_response()
+        } else {
+            StatusCode::NOT_FOUND.into_response()
+        }
+    } else {
+        StatusCode::NOT_FOUND.into_response()
+    }
+}
+
+async fn item_edit_handler(
+    AdminUser(user): AdminUser,
+    Path(locator): Path<String>,
+    State(pool): State<PgPool>,
+    Extension(storage): Extension<Arc<dyn storage::Storage>>,
+    Extension(images): Extension<Arc<images::ImageQueue>>,
+    HxRequest(is_htmx): HxRequest,
+    multipart: Multipart,
+) -> Result<Response, AppError> {
+    let forms::ItemFormData {
+        title: new_title,
+        locator: new_locator,
+        description: new_description,
+        image: new_image,
+        clear_image,
+        gallery,
+        tags,
+        category,
+        release_date,
+        unreleased,
+        cover_url: _,
+        allow_duplicate: _,
+    } = match async {
+        forms::ItemFormData::from_multipart(multipart)
+            .await?
+            .with_fetched_cover()
+            .await?
+            .validated()
+    }
+    .await
+    {
+        Ok(form) => form,
+        Err(err) => {
+            return Ok(if is_htmx {
+                templates::item_form(
+                    &routes::url::item_edit(&locator),
+                    "Edit item",
+                    Some(&err.to_string()),
+                    None,
+                    None,
+                    &[],
+                    &[],
+                )
+                .into_response()
+            } else {
+                StatusCode::UNPROCESSABLE_ENTITY.into_response()
+            });
+        }
+    };
+    let (ticket, new_image, gallery) = match async {
+        if new_image.is_none() && gallery.is_empty() {
+            return Ok::<_, database::DatabaseError>((None, None, gallery));
+        }
+        let ticket = images.enqueue(&user.username)?;
+        let (new_image, gallery) = sanitize_item_images(&ticket, new_image, gallery).await?;
+        Ok((Some(ticket), new_image, gallery))
+    }
+    .await
+    {
+        Ok(upload) => upload,
+        Err(err) => {
+            return Ok(if is_htmx {
+                templates::item_form(
+                    &routes::url::item_edit(&locator),
+                    "Edit item",
+                    Some(&err.to_string()),
+                    None,
+                    None,
+                    &[],
+                    &[],
+                )
+                .into_response()
+            } else {
+                upload_error_status(&err).into_response()
+            });
+        }
+    };
+    let replaces_cover = new_image.is_some() || clear_image;
+    let previous_cover = database::get_item(&pool, &locator)
+        .await?
+        .and_then(|item| item.cover);
+    let mut staging = images::Staging::new(&*storage);
+    let rejected = async {
+        let (cover, gallery) = match ticket {
+            Some(ticket) => stage_item_images(&mut staging, ticket, new_image, gallery).await?,
+            None => (None, Vec::new()),
+        };
+        let mut transaction = pool.begin().await?;
+        let before = database::item_snapshot(&mut *transaction, &locator).await?;
+        if let Err(err) = database::edit_item(
+            &mut *transaction,
+            &locator,
+            new_locator.as_deref(),
+            new_title.as_deref(),
+            new_description.as_deref(),
+        )
+        .await
+        {
+            return Ok(Some(if is_htmx {
+                templates::item_form(
+                    &routes::url::item_edit(&locator),
+                    "Edit item",
+                    Some(&err.to_string()),
+                    None,
+                    None,
+                    &[],
+                    &[],
+                )
+                .into_response()
+            } else {
+                StatusCode::UNAUTHORIZED.into_response()
+            }));
+        };
+        let locator = new_locator.as_ref().unwrap_or(&locator);
+        if clear_image && cover.is_none() {
+            database::clear_item_cover(&mut *transaction, locator).await?;
+        }
+        add_item_images(&mut transaction, locator, cover.as_deref(), &gallery).await?;
+        if let Some(tags) = tags {
+            database::set_item_tags(&mut *transaction, locator, &forms::parse_tags(&tags)).await?;
+        }
+        if let Some(category) = category {
+            database::set_item_category(
+                &mut *transaction,
+                locator,
+                Some(category.as_str()).filter(|category| !category.is_empty()),
+            )
+            .await?;
+        }
+        if let Some(release_date) = release_date {
+            database::set_item_release_date(
+                &mut *transaction,
+                locator,
+                forms::parse_date(&release_date),
+                unreleased,
+            )
+            .await?;
+        }
+        let after = database::item_snapshot(&mut *transaction, locator).await?;
+        database::record_action(
+            &mut *transaction,
+            Some(&user.username),
+            "edit_item",
+            locator,
+            before.as_ref(),
+            after.as_ref(),
+        )
+        .await?;
+        transaction.commit().await?;
+        Ok::<_, AppError>(None)
+    }
+    .await;
+    match rejected {
+        Ok(None) => staging.commit().await,
+        Ok(Some(response)) => {
+            staging.abort().await;
+            return Ok(response);
+        }
+        Err(err) => {
+            staging.abort().await;
+            return Err(err);
+        }
+    }
+    if let (true, Some(previous)) = (replaces_cover, previous_cover) {
+        release_image(&pool, &*storage, images::COVERS, &previous).await?;
+    }
+    webhooks::dispatch(
+        &pool,
+        webhooks::Event::ItemEdited,
+        webhooks::item_edit(
+            new_locator.as_ref().unwrap_or(&locator),
+            &locator,
+            new_title.as_deref(),
+        ),
+    )
+    .await?;
+    Ok(if is_htmx {
+        (
+            HxLocation {
+                uri: routes::url::item(&new_locator.unwrap_or(locator)).try_into()?,
+            },
+            (),
+        )
+            .into_response()
+    } else {
+        StatusCode::OK.into_response()
+    })
+}
+
+async fn item_add_form_handler(
+    State(pool): State<PgPool>,
+    HxRequest(is_htmx): HxRequest,
+) -> impl IntoResponse {
+    if is_htmx {
+        templates::item_form(
+            &routes::url::path(routes::ITEM_ADD),
+            "Add item",
+            None,
+            None,
+            None,
+            &database::get_categories(&pool).await.unwrap_or_default(),
+            &[],
+        )
+        .into_response()
+    } else {
+        StatusCode::NOT_FOUND.into_response()
+    }
+}
+
+async fn item_metadata_handler(
+    _: AdminUser,
+    HxRequest(is_htmx): HxRequest,
+    Form(form): Form<forms::MetadataFormData>,
+) -> impl IntoResponse {
+    if !is_htmx {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let metadata = match metadata::Source::parse(&form.source) {
+        Some(source) => metadata::fetch(&source).await,
+        None => Err(database::DatabaseError::UnknownMetadataSource),
+    };
+    templates::item_metadata(metadata.as_ref().map_err(ToString::to_string)).into_response()
+}
+
+/// Status of a request whose uploads could not be queued or were not valid images.
+fn upload_error_status(err: &database::DatabaseError) -> StatusCode {
+    match err {
+        database::DatabaseError::Busy => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::UNPROCESSABLE_ENTITY,
+    }
+}
+
+/// Sanitizes the cover and gallery images uploaded for an item.
+async fn sanitize_item_images(
+    ticket: &images::Ticket,
+    cover: Option<Bytes>,
+    gallery: Vec<Bytes>,
+) -> Result<(Option<Bytes>, Vec<Bytes>), database::DatabaseError> {
+    let has_cover = cover.is_some();
+    let mut images = ticket
+        .sanitize(cover.into_iter().chain(gallery).collect())
+        .await?;
+    let cover = has_cover.then(|| images.remove(0));
+    Ok((cover, images))
+}
+
+/// Stages uploaded images for the gallery of an item, along with a copy of a new cover where
+/// item cards look for it, returning the names of the cover and of the other images.
+async fn stage_item_images(
+    staging: &mut images::Staging<'_>,
+    ticket: images::Ticket,
+    cover: Option<Bytes>,
+    gallery: Vec<Bytes>,
+) -> Result<(Option<String>, Vec<String>), database::DatabaseError> {
+    let cover = cover.map(|cover| (images::name(&cover), cover));
+    let gallery: Vec<_> = gallery
+        .into_iter()
+        .map(|image| (images::name(&image), image))
+        .collect();
+    let mut files = Vec::new();
+    if let Some((name, cover)) = &cover {
+        files.push((images::gallery_key(name), cover.clone()));
+        files.push((images::cover_key(name), cover.clone()));
+    }
+    for (name, image) in &gallery {
+        files.push((images::gallery_key(name), image.clone()));
+    }
+    staging.stage(ticket, files).await?;
+    Ok((
+        cover.map(|(name, _)| name),
+        gallery.into_iter().map(|(name, _)| name).collect(),
+    ))
+}
+
+/// Adds staged images to the gallery of an item, making `cover` its cover.
+async fn add_item_images(
+    connection: &mut PgConnection,
+    locator: &str,
+    cover: Option<&str>,
+    gallery: &[String],
+) -> Result<(), database::DatabaseError> {
+    if let Some(cover) = cover {
+        database::add_item_image(&mut *connection, locator, cover, true).await?;
+    }
+    for name in gallery {
+        database::add_item_image(&mut *connection, locator, name, false).await?;
+    }
+    Ok(())
+}
+
+/// Removes an image stored as `name` in `directory` once nothing refers to it anymore.
+async fn release_image(
+    pool: &PgPool,
+    storage: &dyn storage::Storage,
+    directory: &str,
+    name: &str,
+) -> Result<(), database::DatabaseError> {
+    if database::is_image_used(pool, directory, name).await? {
+        return Ok(());
+    }
+    images::remove(storage, &images::key(directory, name))
+        .await
+        .map_err(|e| database::DatabaseError::InternalError(Box::new(e)))
+}
+
+async fn item_image_remove_handler(
+    State(pool): State<PgPool>,
+    Extension(storage): Extension<Arc<dyn storage::Storage>>,
+    AdminUser(user): AdminUser,
+    Path((locator, id)): Path<(String, i32)>,
+    HxCurrentUrl(current_url): HxCurrentUrl,
+) -> Result<Response, AppError> {
+    let Some(image) = database::get_item_images(&pool, &locator)
+        .await?
+        .into_iter()
+        .find(|image| image.id == id)
+    else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    if !database::remove_item_image(&pool, &locator, id, &user.username).await? {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    }
+    release_image(&pool, &*storage, images::GALLERY, &image.file)
+        .await
+        .unwrap_or_default();
+    Ok(match current_url {
+        Some(uri) => (HxLocation { uri }, ()).into_response(),
+        None => StatusCode::OK.into_response(),
+    })
+}
+
+async fn item_cover_handler(
+    State(pool): State<PgPool>,
+    Extension(storage): Extension<Arc<dyn storage::Storage>>,
+    AdminUser(user): AdminUser,
+    Path((locator, id)): Path<(String, i32)>,
+    HxCurrentUrl(current_url): HxCurrentUrl,
+) -> Result<Response, AppError> {
+    let Some(image) = database::get_item_images(&pool, &locator)
+        .await?
+        .into_iter()
+        .find(|image| image.id == id)
+    else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    let previous = database::get_item(&pool, &locator)
+        .await?
+        .and_then(|item| item.cover);
+    images::copy(
+        &*storage,
+        &images::gallery_key(&image.file),
+        &images::cover_key(&image.file),
+    )
+    .await?;
+    if !database::set_item_cover(&pool, &locator, id, &user.username).await? {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    }
+    if let Some(previous) = previous {
+        release_image(&pool, &*storage, images::COVERS, &previous).await?;
+    }
+    Ok(match current_url {
+        Some(uri) => (HxLocation { uri }, ()).into_response(),
+        None => StatusCode::OK.into_response(),
+    })
+}
+
+async fn item_add_handler(
+    AdminUser(user): AdminUser,
+    State(pool): State<PgPool>,
+    Extension(storage): Extension<Arc<dyn storage::Storage>>,
+    Extension(images): Extension<Arc<images::ImageQueue>>,
+    HxRequest(is_htmx): HxRequest,
+    HxCurrentUrl(current_url): HxCurrentUrl,
+    multipart: Multipart,
+) -> Result<Response, AppError> {
+    let forms::ItemFormData {
+        title,
+        locator,
+        description,
+        image,
+        clear_image: _,
+        gallery,
+        tags,
+        category,
+        release_date,
+        unreleased,
+        cover_url: _,
+        allow_duplicate,
+    } = match async {
+        forms::ItemFormData::from_multipart(multipart)
+            .await?
+            .with_generated_locator(&pool)
+            .await?
+            .with_fetched_cover()
+            .await?
+            .validated()
+    }
+    .await
+    {
+        Ok(form) => form,
+        Err(err) => {
+            return Ok(if is_htmx {
+                templates::item_form(
+                    &routes::url::path(routes::ITEM_ADD),
+                    "Add item",
+                    Some(&err.to_string()),
+                    None,
+                    None,
+                    &[],
+                    &[],
+                )
+                .into_response()
+            } else {
+                StatusCode::UNPROCESSABLE_ENTITY.into_response()
+            });
+        }
+    };
+    let locator = locator.ok_or(database::DatabaseError::EmptyFields)?;
+    let title = title.ok_or(database::DatabaseError::EmptyFields)?;
+    let description = description.ok_or(database::DatabaseError::EmptyFields)?;
+    if !allow_duplicate {
+        let duplicates = database::get_similar_items(&pool, &title).await?;
+        if !duplicates.is_empty() {
+            return Ok(if is_htmx {
+                templates::item_form(
+                    &routes::url::path(routes::ITEM_ADD),
+                    "Add anyway",
+                    Some(&database::DatabaseError::PossibleDuplicate.to_string()),
+                    None,
+                    None,
+                    &[],
+                    &duplicates,
+                )
+                .into_response()
+            } else {
+                StatusCode::CONFLICT.into_response()
+            });
+        }
+    }
+    let (ticket, image, gallery) = match async {
+        if image.is_none() && gallery.is_empty() {
+            return Ok::<_, database::DatabaseError>((None, None, gallery));
+        }
+        let ticket = images.enqueue(&user.username)?;
+        let (image, gallery) = sanitize_item_images(&ticket, image, gallery).await?;
+        Ok((Some(ticket), image, gallery))
+    }
+    .await
+    {
+        Ok(upload) => upload,
+        Err(err) => {
+            return Ok(if is_htmx {
+                templates::item_form(
+                    &routes::url::path(routes::ITEM_ADD),
+                    "Add item",
+                    Some(&err.to_string()),
+                    None,
+                    None,
+                    &[],
+                    &[],
+                )
+                .into_response()
+            } else {
+                upload_error_status(&err).into_response()
+            });
+        }
+    };
+    let mut staging = images::Staging::new(&*storage);
+    let rejected = async {
+        let (cover, gallery) = match ticket {
+            Some(ticket) => stage_item_images(&mut staging, ticket, image, gallery).await?,
+            None => (None, Vec::new()),
+        };
+        let mut transaction = pool.begin().await?;
+        if let Err(err) =
+            database::add_item(&mut *transaction, &locator, &title, &description).await
+        {
+            return Ok(Some(if is_htmx {
+                templates::item_form(
+                    &routes::url::path(routes::ITEM_ADD),
+                    "Add item",
+                    Some(&err.to_string()),
+                    None,
+                    None,
+                    &[],
+                    &[],
+                )
+                .into_response()
+            } else {
+                StatusCode::UNAUTHORIZED.into_response()
+            }));
+        };
+        add_item_images(&mut transaction, &locator, cover.as_deref(), &gallery).await?;
+        if let Some(tags) = tags {
+            database::set_item_tags(&mut *transaction, &locator, &forms::parse_tags(&tags)).await?;
+        }
+        if let Some(category) = category.filter(|category| !category.is_empty()) {
+            database::set_item_category(&mut *transaction, &locator, Some(&category)).await?;
+        }
+        if let Some(release_date) = release_date.as_deref().and_then(forms::parse_date) {
+            database::set_item_release_date(
+                &mut *transaction,
+                &locator,
+                Some(release_date),
+                unreleased,
+            )
+            .await?;
+        }
+        let after = database::item_snapshot(&mut *transaction, &locator).await?;
+        database::record_action(
+            &mut *transaction,
+            Some(&user.username),
+            "add_item",
+            &locator,
+            None,
+            after.as_ref(),
+        )
+        .await?;
+        transaction.commit().await?;
+        Ok::<_, AppError>(None)
+    }
+    .await;
+    match rejected {
+        Ok(None) => staging.commit().await,
+        Ok(Some(response)) => {
+            staging.abort().await;
+            return Ok(response);
+        }
+        Err(err) => {
+            staging.abort().await;
+            return Err(err);
+        }
+    }
+    webhooks::dispatch(
+        &pool,
+        webhooks::Event::ItemCreated,
+        webhooks::item(&locator, Some(&title)),
+    )
+    .await?;
+    activitypub::publish_item(&pool, &locator).await?;
+    Ok(if is_htmx {
+        (
+            HxLocation {
+                uri: current_url.ok_or(AppError::BadRequest)?,
+            },
+            (),
+        )
+            .into_response()
+    } else {
+        StatusCode::OK.into_response()
+    })
+}
+
+async fn login_form_handler(HxRequest(is_htmx): HxRequest) -> impl IntoResponse {
+    if is_htmx {
+        templates::login_form(None).into_response()
+    } else {
+        StatusCode::NOT_FOUND.into_response()
+    }
+}
+
+async fn register_form_handler(HxRequest(is_htmx): HxRequest) -> impl IntoResponse {
+    if is_htmx {
+        templates::register_form(None).into_response()
+    } else {
+        StatusCode::NOT_FOUND.into_response()
+    }
+}
+
+async fn login_handler(
+    State(pool): State<PgPool>,
+    session: Session<SessionNullPool>,
+    Extension(revocations): Extension<Arc<sessions::Revocations>>,
+    HxRequest(is_htmx): HxRequest,
+    HxCurrentUrl(current_url): HxCurrentUrl,
+    Form(form): Form<forms::LoginFormData>,
+) -> Result<Response, AppError> {
+    let result = match form.validated() {
+        Ok(form) => database::login_user(&pool, &form.username, &form.password)
+            .await
+            .inspect_err(|e| {
+                if let database::DatabaseError::PasswordResetRequired = e {
+                    session.set("password_reset", &form.username);
+                }
+            }),
+        Err(e) => Err(e),
+    };
+    Ok(match result {
+        Ok(user) => {
+            sessions::log_in(&pool, &session, &revocations, &user).await?;
+            if is_htmx {
+                (
+                    HxLocation {
+                        uri: current_url.ok_or(AppError::BadRequest)?,
+                    },
+                    templates::logged_in(&user),
+                )
+                    .into_response()
+            } else {
+                StatusCode::OK.into_response()
+            }
+        }
+        Err(database::DatabaseError::PasswordResetRequired) if is_htmx => {
+            templates::password_reset_form(None).into_response()
+        }
+        Err(e) => {
+            if is_htmx {
+                templates::login_form(Some(&e.to_string())).into_response()
+            } else {
+                StatusCode::UNAUTHORIZED.into_response()
+            }
+        }
+    })
+}
+
+#[derive(Deserialize)]
+struct VerifyEmailParams {
+    token: String,
+}
+
+async fn verify_email_handler(
+    State(pool): State<PgPool>,
+    CurrentUser(user): CurrentUser,
+    Query(params): Query<VerifyEmailParams>,
+) -> Result<maud::Markup, AppError> {
+    let verified = database::verify_email(&pool, &params.token)
+        .await?
+        .is_some();
+    Ok(templates::index(
+        templates::email_verified(verified),
+        routes::ITEMS,
+        user.as_ref(),
+        None,
+    ))
+}
+
+async fn password_reset_handler(
+    State(pool): State<PgPool>,
+    session: Session<SessionNullPool>,
+    Extension(revocations): Extension<Arc<sessions::Revocations>>,
+    HxRequest(is_htmx): HxRequest,
+    HxCurrentUrl(current_url): HxCurrentUrl,
+    Form(form): Form<forms::PasswordResetFormData>,
+) -> Result<Response, AppError> {
+    let Some(username) = session.get::<String>("password_reset") else {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    };
+    let result = match async {
+        let form = form.validated()?;
+        forms::check_new_password(&form.password1)
+            .await
+            .map(|_| form)
+    }
+    .await
+    {
+        Ok(form) => match database::reset_password(&pool, &username, &form.password1).await {
+            Ok(true) => database::login_user(&pool, &username, &form.password1).await,
+            Ok(false) => {
+                session.remove("password_reset");
+                return Ok(StatusCode::FORBIDDEN.into_response());
+            }
+            Err(e) => Err(e),
+        },
+        Err(e) => Err(e),
+    };
+    Ok(match result {
+        Ok(user) => {
+            session.remove("password_reset");
+            sessions::log_in(&pool, &session, &revocations, &user).await?;
+            if is_htmx {
+                (
+                    HxLocation {
+                        uri: current_url.ok_or(AppError::BadRequest)?,
+                    },
+                    templates::logged_in(&user),
+                )
+                    .into_response()
+            } else {
+                StatusCode::OK.into_response()
+            }
+        }
+        Err(e) => {
+            if is_htmx {
+                templates::password_reset_form(Some(&e.to_string())).into_response()
+            } else {
+                StatusCode::UNPROCESSABLE_ENTITY.into_response()
+            }
+        }
+    })
+}
+
+async fn register_handler(
+    State(pool): State<PgPool>,
+    session: Session<SessionNullPool>,
+    Extension(revocations): Extension<Arc<sessions::Revocations>>,
+    Extension(cache): Extension<Arc<cache::QueryCache>>,
+    HxRequest(is_htmx): HxRequest,
+    HxCurrentUrl(current_url): HxCurrentUrl,
+    Form(form): Form<forms::RegisterFormData>,
+) -> Result<Response, AppError> {
+    let result = match async {
+        let form = form.validated()?;
+        forms::check_new_password(&form.password1)
+            .await
+            .map(|_| form)
+    }
+    .await
+    {
+        Ok(form) => database::register_user(&pool, &form.username, &form.password1).await,
+        Err(e) => Err(e),
+    };
+    Ok(match result {
+        Ok(user) => {
+            // Visitors' writes leave the cache be, while the new user belongs on the user listing.
+            cache.invalidate();
+            sessions::log_in(&pool, &session, &revocations, &user).await?;
+            if is_htmx {
+                (
+                    HxLocation {
+                        uri: current_url.ok_or(AppError::BadRequest)?,
+                    },
+                    templates::logged_in(&user),
+                )
+                    .into_response()
+            } else {
+                StatusCode::OK.into_response()
+            }
+        }
+        Err(e) => {
+            if is_htmx {
+                templates::register_form(Some(&e.to_string())).into_response()
+            } else {
+                StatusCode::UNAUTHORIZED.into_response()
+            }
+        }
+    })
+}
+
+async fn logout_handler(
+    session: Session<SessionNullPool>,
+    HxCurrentUrl(current_url): HxCurrentUrl,
+    HxRequest(is_htmx): HxRequest,
+) -> Result<Response, AppError> {
+    session.destroy();
+    Ok(if is_htmx {
+        (
+            HxLocation {
+                uri: current_url.ok_or(AppError::BadRequest)?,
+            },
+            templates::login_button(),
+        )
+            .into_response()
+    } else {
+        StatusCode::OK.into_response()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, extract::ConnectInfo};
+    use std::net::SocketAddr;
+    use tower::ServiceExt;
+
+    /// Queries a listing page may run regardless of how many entries it shows.
+    const QUERY_BUDGET: usize = 6;
+
+    async fn test_app(pool: PgPool) -> Router {
+        let session_store = SessionStore::<SessionNullPool>::new(None, Default::default())
+            .await
+            .unwrap();
+        let storage = Arc::new(storage::Local::new(&config::get().image_dir));
+        app(pool, session_store, storage, config::get())
+    }
+
+    async fn query_count(pool: PgPool, uri: &str) -> usize {
+        metrics::install_tracing();
+        let response = test_app(pool)
+            .await
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{uri}");
+        response.headers()[metrics::QUERY_COUNT_HEADER]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn listing_pages_stay_within_query_budget(pool: PgPool) {
+        for uri in [
+            routes::ITEMS.to_owned(),
+            format!("{}?page=1", routes::ITEMS),
+            format!("{}?search=proxy", routes::ITEMS),
+            format!("{}?search=proxy&mode=text", routes::ITEMS),
+            format!("{}?letter=E", routes::ITEMS),
+            format!("{}?per_page=60", routes::ITEMS),
+            format!("{}?after=10_0", routes::ITEMS),
+            routes::USERS.to_owned(),
+            format!("{}?after=admin", routes::USERS),
+            routes::url::user("admin"),
+            routes::url::item("ergo_proxy"),
+        ] {
+            let queries = query_count(pool.clone(), &uri).await;
+            assert!(
+                queries <= QUERY_BUDGET,
+                "{uri} ran {queries} queries, over the budget of {QUERY_BUDGET}"
+            );
+        }
+    }
+
+    #[sqlx::test]
+    async fn pages_users_by_username(pool: PgPool) {
+        // Registered last, so it would come last if users were listed by id.
+        database::register_user(&pool, "aardvark", "password")
+            .await
+            .unwrap();
+        let mut numbered = Vec::new();
+        let mut page_number = 0;
+        while let Some(page) = database::get_users(&pool, Some(page_number), 2, None, None)
+            .await
+            .unwrap()
+        {
+            numbered.extend(page.items.into_iter().map(|user| user.username));
+            page_number += 1;
+        }
+        let mut by_cursor = Vec::new();
+        let mut after = None;
+        loop {
+            let cursor = after.as_deref().unwrap_or("");
+            let Some(page) = database::get_users(&pool, None, 2, None, Some(cursor))
+                .await
+                .unwrap()
+            else {
+                break;
+            };
+            by_cursor.extend(page.items.into_iter().map(|user| user.username));
+            after = page.keyset.and_then(|keyset| keyset.next);
+            if after.is_none() {
+                break;
+            }
+        }
+        let mut sorted = numbered.clone();
+        sorted.sort();
+        assert_eq!(numbered.first().map(String::as_str), Some("aardvark"));
+        assert_eq!(numbered, sorted);
+        assert_eq!(by_cursor, sorted);
+    }
+
+    #[sqlx::test]
+    async fn does_not_page_long_listings_by_number_past_the_limit(pool: PgPool) {
+        sqlx::query("INSERT INTO users(username, password_hash) SELECT 'paged' || n, '' FROM generate_series(1, $1) n")
+            .bind(database::MAX_NUMBERED_PAGES + 10)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let first = database::get_users(&pool, None, 1, None, None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(first.keyset.is_some_and(|keyset| keyset.next.is_some()));
+        assert_eq!(first.number_of_pages, database::MAX_NUMBERED_PAGES);
+        let last = Some(database::MAX_NUMBERED_PAGES - 1);
+        assert!(database::get_users(&pool, last, 1, None, None)
+            .await
+            .unwrap()
+            .is_some());
+        let past = Some(database::MAX_NUMBERED_PAGES);
+        assert!(database::get_users(&pool, past, 1, None, None)
+            .await
+            .unwrap()
+            .is_none());
+        let filter = database::ItemFilter::default();
+        let mode = database::SearchMode::default();
+        assert!(
+            database::get_items(&pool, past, 1, None, &filter, mode, None)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[sqlx::test]
+    async fn limits_concurrent_and_imported_ratings(pool: PgPool) {
+        sqlx::query("INSERT INTO items(locator, title, description) SELECT 'limited_' || n, 'Limited ' || n, '' FROM generate_series(1, $1) n")
+            .bind(database::RATINGS_PER_HOUR + 5)
+            .execute(&pool)
+            .await
+            .unwrap();
+        database::register_user(&pool, "eager", "password")
+            .await
+            .unwrap();
+        let ratings = (1..=database::RATINGS_PER_HOUR + 5).map(|n| {
+            let pool = pool.clone();
+            tokio::spawn(async move {
+                let locator = format!("limited_{n}");
+                database::rate_item(&pool, "eager", &locator, 7, None, None, None).await
+            })
+        });
+        let mut accepted = 0;
+        for rating in ratings.collect::<Vec<_>>() {
+            match rating.await.unwrap() {
+                Ok(()) => accepted += 1,
+                Err(database::DatabaseError::RateLimited) => {}
+                Err(e) => panic!("{e}"),
+            }
+        }
+        assert_eq!(accepted, database::RATINGS_PER_HOUR);
+
+        database::register_user(&pool, "importer", "password")
+            .await
+            .unwrap();
+        let ratings: Vec<_> = (1..=database::RATINGS_PER_HOUR + 1)
+            .map(|n| (format!("limited_{n}"), 7))
+            .collect();
+        assert!(matches!(
+            database::import_ratings(&pool, "importer", &ratings).await,
+            Err(database::DatabaseError::RateLimited)
+        ));
+        let allowed = &ratings[..database::RATINGS_PER_HOUR as usize];
+        assert_eq!(
+            database::import_ratings(&pool, "importer", allowed)
+                .await
+                .unwrap(),
+            database::RATINGS_PER_HOUR as u64
+        );
+    }
+
+    #[sqlx::test]
+    async fn rejects_and_clears_expired_email_tokens(pool: PgPool) {
+        let fresh = database::set_user_email(&pool, "test1", "test1@example.com")
+            .await
+            .unwrap()
+            .unwrap();
+        let stale = database::set_user_email(&pool, "test2", "test2@example.com")
+            .await
+            .unwrap()
+            .unwrap();
+        sqlx::query("UPDATE users SET email_token_created = now() - make_interval(hours => $1) WHERE username = 'test2'")
+            .bind(database::EMAIL_TOKEN_HOURS + 1)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(database::verify_email(&pool, &stale).await.unwrap(), None);
+        let storage = storage::Local::new(&config::get().image_dir);
+        let cleanup = jobs::clean_up(&pool, &storage, &config::Config::default())
+            .await
+            .unwrap();
+        assert!(cleanup.email_tokens >= 1);
+        let token: Option<String> =
+            sqlx::query_scalar("SELECT email_token FROM users WHERE username = 'test2'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(token, None);
+        assert_eq!(
+            database::verify_email(&pool, &fresh).await.unwrap().as_deref(),
+            Some("test1")
+        );
+    }
+
+    #[sqlx::test]
+    async fn queues_weekly_email_to_subscribers(pool: PgPool) {
+        database::add_item(&pool, "weekly", "Weekly", "Reviewed this week.")
+            .await
+            .unwrap();
+        for username in ["subscriber", "unconfirmed", "critic", "spoiler"] {
+            database::register_user(&pool, username, "password")
+                .await
+                .unwrap();
+        }
+        let token = database::set_user_email(&pool, "subscriber", "subscriber@example.com")
+            .await
+            .unwrap()
+            .unwrap();
+        database::verify_email(&pool, &token).await.unwrap();
+        database::set_user_email(&pool, "unconfirmed", "unconfirmed@example.com")
+            .await
+            .unwrap();
+        for username in ["subscriber", "unconfirmed"] {
+            database::set_weekly_email(&pool, username, true)
+                .await
+                .unwrap();
+        }
+        database::rate_item(
+            &pool,
+            "critic",
+            "weekly",
+            9,
+            Some("A great week for it."),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        database::rate_item(
+            &pool,
+            "spoiler",
+            "weekly",
+            3,
+            Some("It ends badly."),
+            Some(true),
+            None,
+        )
+        .await
+        .unwrap();
+        for author in ["critic", "spoiler"] {
+            database::toggle_review_reaction(&pool, "weekly", author, "subscriber", "like")
+                .await
+                .unwrap();
+        }
+        let weekly_mail = |pool: PgPool| async move {
+            sqlx::query_as::<_, (String, String)>(
+                "SELECT recipient, text FROM mail_jobs ORDER BY id DESC LIMIT 1",
+            )
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+        };
+
+        assert_eq!(mailer::send_weekly(&pool).await.unwrap(), 1);
+        let (recipient, text) = weekly_mail(pool.clone()).await;
+        assert_eq!(recipient, "subscriber@example.com");
+        assert!(text.contains("A great week for it."));
+        assert!(!text.contains("It ends badly."));
+        assert!(!text.contains("Biggest score changes"));
+
+        database::rate_item(&pool, "subscriber", "weekly", 10, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(mailer::send_weekly(&pool).await.unwrap(), 1);
+        let (_, text) = weekly_mail(pool.clone()).await;
+        assert!(text.contains("- Weekly: 6.00 -> 7.33"));
+    }
+
+    #[sqlx::test]
+    async fn deletes_accounts_never_verified_in_time(pool: PgPool) {
+        for username in ["stale", "verified", "fresh", "stale_admin"] {
+            database::register_user(&pool, username, "password")
+                .await
+                .unwrap();
+        }
+        let token = database::set_user_email(&pool, "verified", "verified@example.com")
+            .await
+            .unwrap()
+            .unwrap();
+        database::verify_email(&pool, &token).await.unwrap();
+        database::set_user_email(&pool, "verified", "changed@example.com")
+            .await
+            .unwrap();
+        database::make_admin(&pool, "stale_admin").await.unwrap();
+        sqlx::query("UPDATE users SET registered = now() - INTERVAL '8 days' WHERE username IN ('stale', 'verified', 'stale_admin')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let storage = storage::Local::new(&config::get().image_dir);
+        let kept = jobs::clean_up(&pool, &storage, &config::Config::default())
+            .await
+            .unwrap();
+        assert_eq!(kept.accounts, 0);
+        let config = config::Config {
+            unverified_account_days: 7,
+            ..config::Config::default()
+        };
+        let cleanup = jobs::clean_up(&pool, &storage, &config).await.unwrap();
+        assert_eq!(cleanup.accounts, 1);
+        assert!(cleanup.to_string().contains("1 unverified accounts"));
+        assert!(database::get_user(&pool, "stale").await.unwrap().is_none());
+        for username in ["verified", "fresh", "stale_admin"] {
+            assert!(database::get_user(&pool, username).await.unwrap().is_some());
+        }
+    }
+
+    #[sqlx::test]
+    async fn records_admin_actions_with_diffs(pool: PgPool) {
+        database::add_item(&pool, "audited", "Audited", "Before.")
+            .await
+            .unwrap();
+        let mut transaction = pool.begin().await.unwrap();
+        let before = database::item_snapshot(&mut *transaction, "audited")
+            .await
+            .unwrap();
+        database::edit_item(
+            &mut *transaction,
+            "audited",
+            None,
+            Some("Audited (2024)"),
+            None,
+        )
+        .await
+        .unwrap();
+        let after = database::item_snapshot(&mut *transaction, "audited")
+            .await
+            .unwrap();
+        database::record_action(
+            &mut *transaction,
+            Some("admin"),
+            "edit_item",
+            "audited",
+            before.as_ref(),
+            after.as_ref(),
+        )
+        .await
+        .unwrap();
+        transaction.commit().await.unwrap();
+        database::remove_item(&pool, "audited", "admin")
+            .await
+            .unwrap();
+        database::register_user(&pool, "promoted", "password")
+            .await
+            .unwrap();
+        assert!(database::make_admin(&pool, "promoted").await.unwrap());
+
+        let entries = database::get_audit_log(&pool, None, None, None, Some("AUDITED"))
+            .await
+            .unwrap()
+            .unwrap()
+            .items;
+        let actions: Vec<_> = entries.iter().map(|entry| entry.action.as_str()).collect();
+        assert_eq!(actions, ["remove_item", "edit_item"]);
+        let edit = audit::changes(&entries[1].diff);
+        assert_eq!(edit.len(), 1);
+        assert_eq!(edit[0].field, "title");
+        assert_eq!(edit[0].old.as_deref(), Some("Audited"));
+        assert_eq!(edit[0].new.as_deref(), Some("Audited (2024)"));
+        let removal = audit::changes(&entries[0].diff);
+        assert!(removal.iter().all(|change| change.new.is_none()));
+        assert!(removal.iter().any(|change| change.field == "description"));
+
+        let promotions = database::get_audit_log(&pool, None, Some("make_admin"), None, None)
+            .await
+            .unwrap()
+            .unwrap()
+            .items;
+        assert_eq!(promotions.len(), 1);
+        assert_eq!(promotions[0].actor, None);
+        assert_eq!(
+            promotions[0].diff,
+            serde_json::json!({ "is_admin": [false, true] })
+        );
+        assert!(
+            database::get_audit_log(&pool, None, None, Some("promoted"), None)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[sqlx::test]
+    async fn seeds_users_items_and_reviews(pool: PgPool) {
+        use storage::Storage;
+        let root = std::env::temp_dir().join(format!("zai-seed-{}", std::process::id()));
+        let storage = storage::Local::new(&root);
+        let last_item: i32 = sqlx::query_scalar("SELECT COALESCE(MAX(id), 0) FROM items")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let seeded = seed::seed(&pool, &storage, 3, 2).await.unwrap();
+        assert_eq!((seeded.users, seeded.items), (2, 3));
+        let covers: Vec<String> =
+            sqlx::query_scalar("SELECT cover FROM items WHERE id > $1 AND cover IS NOT NULL")
+                .bind(last_item)
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(covers.len(), 3);
+        for cover in covers {
+            assert!(storage
+                .get(&images::cover_key(&cover))
+                .await
+                .unwrap()
+                .is_some());
+        }
+        let (reviews, out_of_range): (i64, i64) = sqlx::query_as("SELECT COUNT(*), COUNT(*) FILTER (WHERE rating NOT BETWEEN 1 AND 10) FROM reviews WHERE user_id IN (SELECT id FROM users ORDER BY id DESC LIMIT 2)")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(reviews as u64, seeded.reviews);
+        assert!(reviews >= 2);
+        assert_eq!(out_of_range, 0);
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn missing_pages_render_within_the_layout() {
+        // Rendering the page takes no queries, so the database is never connected to.
+        let pool = PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/zai")
+            .unwrap();
+        let response = test_app(pool)
+            .await
+            .oneshot(
+                Request::builder()
+                    .uri("/no/such/page")
+                    .header(header::ACCEPT, "text/html")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let page = String::from_utf8(body.to_vec()).unwrap();
+        assert!(page.contains("This page does not exist!"));
+        assert!(page.contains(routes::SEARCH));
+    }
+
+    #[tokio::test]
+    async fn serves_routes_under_the_base_path() {
+        let pool = PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/zai")
+            .unwrap();
+        let session_store = SessionStore::<SessionNullPool>::new(None, Default::default())
+            .await
+            .unwrap();
+        let config = config::Config {
+            base_path: "/zai".to_owned(),
+            ..Default::default()
+        };
+        let storage = Arc::new(storage::Local::new(&config.image_dir));
+        let app = app(pool, session_store, storage, &config);
+        for (uri, status) in [
+            (format!("/zai{}", routes::VERSION), StatusCode::OK),
+            (routes::VERSION.to_owned(), StatusCode::NOT_FOUND),
+        ] {
+            let response = app
+                .clone()
+                .oneshot(Request::builder().uri(&uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), status, "{uri}");
+        }
+    }
+
+    #[sqlx::test]
+    async fn marks_page_scripts_with_the_nonce_of_the_policy(pool: PgPool) {
+        let response = test_app(pool)
+            .await
+            .oneshot(
+                Request::builder()
+                    .uri(routes::ITEMS)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let headers = response.headers().clone();
+        assert_eq!(headers[header::X_CONTENT_TYPE_OPTIONS], "nosniff");
+        assert_eq!(
+            headers[header::REFERRER_POLICY],
+            "strict-origin-when-cross-origin"
+        );
+        // Served over plain HTTP, where browsers ignore it.
+        assert!(!headers.contains_key(header::STRICT_TRANSPORT_SECURITY));
+        let policy = headers[header::CONTENT_SECURITY_POLICY].to_str().unwrap();
+        let nonce = policy
+            .split(['\'', ' '])
+            .find_map(|source| source.strip_prefix("nonce-"))
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        let scripts: Vec<&str> = body
+            .split("<script ")
+            .skip(1)
+            .map(|script| script.split('>').next().unwrap())
+            .collect();
+        assert!(!scripts.is_empty());
+        for script in scripts {
+            assert!(script.contains(&format!("nonce=\"{nonce}\"")), "{script}");
+        }
+    }
+
+    #[sqlx::test]
+    async fn reports_route_latency_histograms(pool: PgPool) {
+        metrics::install_tracing();
+        let app = test_app(pool).await;
+        for uri in [routes::ITEMS, routes::ITEMS, routes::METRICS] {
+            let response = app
+                .clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{uri}");
+        }
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(routes::METRICS)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        let items = format!("{{route=\"{}\"}}", routes::ITEMS);
+        assert!(body.contains(&format!("zai_request_duration_seconds_count{items} 2")));
+        assert!(body.contains(&format!(
+            "zai_request_query_duration_seconds_count{items} 2"
+        )));
+        let sum = body
+            .lines()
+            .find_map(|line| {
+                line.strip_prefix(&format!("zai_request_query_duration_seconds_sum{items} "))
+            })
+            .unwrap();
+        assert!(sum.parse::<f64>().unwrap() > 0.0);
+    }
+
+    #[sqlx::test]
+    async fn limits_requests_to_expensive_routes(pool: PgPool) {
+        let app = test_app(pool).await;
+        let client = ConnectInfo(SocketAddr::from(([203, 0, 113, 7], 40000)));
+        let request = |uri: &str| {
+            Request::builder()
+                .uri(uri)
+                .extension(client)
+                .body(Body::empty())
+                .unwrap()
+        };
+        // Tokens trickle back while the requests are made, so a few more may get through.
+        let mut rejected = None;
+        for _ in 0..config::get().rate_limit_burst * 2 {
+            let response = app
+                .clone()
+                .oneshot(request(routes::SEARCH_RESULTS))
+                .await
+                .unwrap();
+            if response.status() == StatusCode::TOO_MANY_REQUESTS {
+                rejected = Some(response);
+                break;
+            }
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+        let rejected = rejected.unwrap();
+        assert!(rejected.headers().contains_key(header::RETRY_AFTER));
+        let response = app.oneshot(request(routes::ITEMS)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[sqlx::test]
+    async fn leaves_private_ratings_out_of_user_stats(pool: PgPool) {
+        database::register_user(&pool, "rater", "password")
+            .await
+            .unwrap();
+        database::rate_item(&pool, "rater", "ergo_proxy", 8, None, None, None)
+            .await
+            .unwrap();
+        database::rate_item(&pool, "rater", "steins_gate", 3, None, None, Some(true))
+            .await
+            .unwrap();
+        let count_of = |stats: &database::UserStats, score: &str| {
+            stats
+                .distribution
+                .iter()
+                .find(|(bucket, _)| bucket == score)
+                .map(|(_, count)| *count)
+        };
+        for viewer in [None, Some("test1")] {
+            let stats = database::get_user_stats(&pool, "rater", viewer)
+                .await
+                .unwrap();
+            assert_eq!(stats.review_count, 1);
+            assert_eq!(stats.mean_score, 8.0);
+            assert_eq!(count_of(&stats, "3"), Some(0));
+            assert_eq!(
+                stats.per_month.iter().map(|(_, count)| count).sum::<i64>(),
+                1
+            );
+        }
+        let own = database::get_user_stats(&pool, "rater", Some("rater"))
+            .await
+            .unwrap();
+        assert_eq!(own.review_count, 2);
+        assert_eq!(count_of(&own, "3"), Some(1));
+    }
+
+    #[sqlx::test]
+    async fn hides_reviews_of_login_required_users_from_anonymous_viewers(pool: PgPool) {
+        database::register_user(&pool, "guarded", "password")
+            .await
+            .unwrap();
+        database::rate_item(&pool, "guarded", "ergo_proxy", 8, Some("Seen"), None, None)
+            .await
+            .unwrap();
+        let privacy = database::Privacy {
+            login_required: true,
+            ..Default::default()
+        };
+        database::edit_user(&pool, "guarded", None, None, None, Some(&privacy), None)
+            .await
+            .unwrap();
+        let authors = |page: Option<database::Page<database::ReviewEntry>>| {
+            page.map(|page| page.items)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|review| review.user.username)
+                .collect::<Vec<_>>()
+        };
+        let anonymous = database::get_reviews(&pool, None, None, None, None, None)
+            .await
+            .unwrap();
+        assert!(!authors(anonymous).contains(&"guarded".to_owned()));
+        let logged_in = database::get_reviews(&pool, None, None, None, None, Some("test1"))
+            .await
+            .unwrap();
+        assert!(authors(logged_in).contains(&"guarded".to_owned()));
+    }
+
+    #[sqlx::test]
+    async fn asks_anonymous_viewers_to_log_in_on_every_page_of_login_required_users(pool: PgPool) {
+        database::register_user(&pool, "guarded", "password")
+            .await
+            .unwrap();
+        database::add_user_list(&pool, "guarded", "watched", "Watched")
+            .await
+            .unwrap();
+        let privacy = database::Privacy {
+            login_required: true,
+            ..Default::default()
+        };
+        database::edit_user(&pool, "guarded", None, None, None, Some(&privacy), None)
+            .await
+            .unwrap();
+        let app = test_app(pool).await;
+        for uri in [
+            routes::url::user("guarded"),
+            routes::url::user_favorites("guarded"),
+            routes::url::user_lists("guarded"),
+            routes::url::user_list("guarded", "watched"),
+        ] {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .uri(&uri)
+                        .header("HX-Request", "true")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{uri}");
+        }
+    }
+
+    #[sqlx::test]
+    async fn leaves_hidden_ratings_out_of_the_feed(pool: PgPool) {
+        database::register_user(&pool, "follower", "password")
+            .await
+            .unwrap();
+        database::register_user(&pool, "hidden", "password")
+            .await
+            .unwrap();
+        database::rate_item(&pool, "hidden", "ergo_proxy", 8, None, None, None)
+            .await
+            .unwrap();
+        database::set_following(&pool, "follower", "hidden", true)
+            .await
+            .unwrap();
+        let feed = |pool: PgPool| async move {
+            database::get_feed(&pool, None, "follower")
+                .await
+                .unwrap()
+                .map(|page| page.items.len())
+                .unwrap_or_default()
+        };
+        assert_eq!(feed(pool.clone()).await, 1);
+        let privacy = database::Privacy {
+            hidden_ratings: true,
+            ..Default::default()
+        };
+        database::edit_user(&pool, "hidden", None, None, None, Some(&privacy), None)
+            .await
+            .unwrap();
+        assert_eq!(feed(pool).await, 0);
     }
 }
@@ -0,0 +1,74 @@
+//! Safe Markdown subset used in item descriptions and review text: paragraphs, emphasis,
+//! strikethrough, code, lists, quotes and links. Raw HTML is shown as typed, headings are
+//! rendered as paragraphs and anything left unsafe is removed by the sanitizer.
+
+use ammonia::Builder;
+use maud::{Markup, PreEscaped};
+use pulldown_cmark::{html, Event, Options, Parser, Tag, TagEnd};
+use std::{collections::HashSet, sync::OnceLock};
+
+/// Tailwind classes styling rendered Markdown, as the preflight styles strip lists and links.
+pub const CLASSES: &str = "[&>*+*]:mt-2 [&_a]:text-violet-400 [&_a]:underline [&_ul]:list-disc [&_ul]:ps-6 [&_ol]:list-decimal [&_ol]:ps-6 [&_blockquote]:border-s-4 [&_blockquote]:border-violet-400 [&_blockquote]:ps-2 [&_code]:rounded [&_code]:bg-zinc-700 [&_code]:px-1 [&_pre]:overflow-x-auto";
+
+const TAGS: [&str; 13] = [
+    "p",
+    "br",
+    "hr",
+    "em",
+    "strong",
+    "del",
+    "code",
+    "pre",
+    "blockquote",
+    "ul",
+    "ol",
+    "li",
+    "a",
+];
+
+fn sanitizer() -> &'static Builder<'static> {
+    static SANITIZER: OnceLock<Builder> = OnceLock::new();
+    SANITIZER.get_or_init(|| {
+        let mut builder = Builder::default();
+        builder
+            .tags(HashSet::from(TAGS))
+            .url_schemes(HashSet::from(["http", "https", "mailto"]))
+            .link_rel(Some("nofollow noopener noreferrer"));
+        builder
+    })
+}
+
+pub fn render(source: &str) -> Markup {
+    let events = Parser::new_ext(source, Options::ENABLE_STRIKETHROUGH).map(|event| match event {
+        // Keeps line breaks as typed, as plain text descriptions were shown.
+        Event::SoftBreak => Event::HardBreak,
+        Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+        Event::Start(Tag::Heading { .. }) => Event::Start(Tag::Paragraph),
+        Event::End(TagEnd::Heading(_)) => Event::End(TagEnd::Paragraph),
+        event => event,
+    });
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, events);
+    PreEscaped(sanitizer().clean(&unsafe_html).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_subset_safely() {
+        assert_eq!(
+            render("**bold** and ~~gone~~\nnext line").0,
+            "<p><strong>bold</strong> and <del>gone</del><br>\nnext line</p>\n"
+        );
+        let rendered = render(
+            "<script>alert(1)</script>\n\n[link](javascript:alert(1)) # not a heading\n\n# Heading",
+        )
+        .0;
+        assert!(!rendered.contains("<script>"));
+        assert!(rendered.contains("&lt;script&gt;"));
+        assert!(!rendered.contains("javascript:"));
+        assert!(rendered.ends_with("<p>Heading</p>\n"));
+    }
+}
@@ -0,0 +1,423 @@
+//! Details of new items fetched from external catalogs: OpenLibrary works and editions, and TMDB
+//! movies and shows when the `TMDB_API_KEY` environment variable is set.
+
+use crate::{config, database::DatabaseError};
+use axum::body::Bytes;
+use regex::Regex;
+use reqwest::{header, redirect::Policy, Client, Url};
+use serde::{de::DeserializeOwned, Deserialize};
+use sqlx::types::chrono::NaiveDate;
+use std::{
+    env,
+    net::{IpAddr, SocketAddr},
+    sync::OnceLock,
+    time::Duration,
+};
+use tokio::net::lookup_host;
+use tracing::warn;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+const USER_AGENT: &str = concat!("zai/", env!("CARGO_PKG_VERSION"));
+/// Redirects followed when downloading a cover.
+const MAX_REDIRECTS: usize = 5;
+
+#[derive(Debug, PartialEq)]
+pub enum Source {
+    /// Path of a work or an edition, such as `works/OL45804W`.
+    OpenLibrary(String),
+    /// Kind (`movie` or `tv`) and ID of an entry.
+    Tmdb(String, u64),
+}
+
+impl Source {
+    /// Recognizes catalog links, bare OpenLibrary IDs and IDs written as `tmdb:movie/603`.
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        static OPEN_LIBRARY: OnceLock<Regex> = OnceLock::new();
+        static TMDB: OnceLock<Regex> = OnceLock::new();
+        let open_library = OPEN_LIBRARY.get_or_init(|| {
+            Regex::new(
+                r"^(?:https?://(?:www\.)?openlibrary\.org/(?:works|books)/)?(OL\d+([WM]))(?:[/?#].*)?$",
+            )
+            .unwrap()
+        });
+        let tmdb = TMDB.get_or_init(|| {
+            Regex::new(
+                r"^(?:https?://(?:www\.)?themoviedb\.org/|tmdb:)(movie|tv)/(\d+)(?:[-/?#].*)?$",
+            )
+            .unwrap()
+        });
+        if let Some(captures) = open_library.captures(input) {
+            let kind = if &captures[2] == "W" {
+                "works"
+            } else {
+                "books"
+            };
+            Some(Source::OpenLibrary(format!("{kind}/{}", &captures[1])))
+        } else {
+            let captures = tmdb.captures(input)?;
+            Some(Source::Tmdb(
+                captures[1].to_owned(),
+                captures[2].parse().ok()?,
+            ))
+        }
+    }
+}
+
+/// Fields pre-filled in the add item form.
+#[derive(Debug, PartialEq)]
+pub struct Metadata {
+    pub title: String,
+    pub description: String,
+    pub release_date: Option<NaiveDate>,
+    pub cover_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OpenLibraryText {
+    Plain(String),
+    Typed { value: String },
+}
+
+#[derive(Deserialize)]
+struct OpenLibraryEntry {
+    title: String,
+    description: Option<OpenLibraryText>,
+    /// Set on works.
+    first_publish_date: Option<String>,
+    /// Set on editions.
+    publish_date: Option<String>,
+    #[serde(default)]
+    covers: Vec<i64>,
+}
+
+impl From<OpenLibraryEntry> for Metadata {
+    fn from(entry: OpenLibraryEntry) -> Self {
+        Metadata {
+            title: entry.title,
+            description: match entry.description {
+                Some(OpenLibraryText::Plain(text) | OpenLibraryText::Typed { value: text }) => text,
+                None => String::new(),
+            },
+            release_date: entry
+                .first_publish_date
+                .or(entry.publish_date)
+                .and_then(|date| parse_date(&date)),
+            // Removed covers are left in the list as negative IDs.
+            cover_url: entry
+                .covers
+                .into_iter()
+                .find(|id| *id > 0)
+                .map(|id| format!("https://covers.openlibrary.org/b/id/{id}-L.jpg")),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TmdbEntry {
+    /// Set on movies.
+    title: Option<String>,
+    /// Set on shows.
+    name: Option<String>,
+    #[serde(default)]
+    overview: String,
+    release_date: Option<String>,
+    first_air_date: Option<String>,
+    poster_path: Option<String>,
+}
+
+impl From<TmdbEntry> for Metadata {
+    fn from(entry: TmdbEntry) -> Self {
+        Metadata {
+            title: entry.title.or(entry.name).unwrap_or_default(),
+            description: entry.overview,
+            release_date: entry
+                .release_date
+                .or(entry.first_air_date)
+                .and_then(|date| parse_date(&date)),
+            cover_url: entry
+                .poster_path
+                .map(|path| format!("https://image.tmdb.org/t/p/w780{path}")),
+        }
+    }
+}
+
+/// Parses the date formats catalogs use, taking the first day of the year when only the year
+/// is known.
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    let value = value.trim();
+    ["%Y-%m-%d", "%B %d, %Y", "%b %d, %Y", "%d %B %Y"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+        .or_else(|| {
+            value
+                .parse()
+                .ok()
+                .and_then(|year| NaiveDate::from_ymd_opt(year, 1, 1))
+        })
+}
+
+pub fn client() -> &'static Client {
+    static CLIENT: OnceLock<Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        Client::builder()
+            .timeout(TIMEOUT)
+            .user_agent(USER_AGENT)
+            .build()
+            .unwrap()
+    })
+}
+
+async fn get_json<T: DeserializeOwned>(url: Url) -> Result<T, DatabaseError> {
+    let response = async {
+        client()
+            .get(url.clone())
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<T>()
+            .await
+    };
+    response.await.map_err(|e| {
+        warn!(url = url.path(), error = %e, "fetching metadata failed");
+        DatabaseError::MetadataUnavailable
+    })
+}
+
+pub async fn fetch(source: &Source) -> Result<Metadata, DatabaseError> {
+    match source {
+        Source::OpenLibrary(path) => {
+            let url = Url::parse(&format!("https://openlibrary.org/{path}.json")).unwrap();
+            get_json::<OpenLibraryEntry>(url).await.map(Metadata::from)
+        }
+        Source::Tmdb(kind, id) => {
+            let key = env::var("TMDB_API_KEY")
+                .ok()
+                .filter(|key| !key.is_empty())
+                .ok_or(DatabaseError::UnknownMetadataSource)?;
+            let url = Url::parse_with_params(
+                &format!("https://api.themoviedb.org/3/{kind}/{id}"),
+                [("api_key", key)],
+            )
+            .unwrap();
+            get_json::<TmdbEntry>(url).await.map(Metadata::from)
+        }
+    }
+}
+
+/// Whether an address is reachable on the public internet, so that URLs provided by others cannot
+/// be used to make requests to the server itself or to its private network.
+fn is_public(address: IpAddr) -> bool {
+    match address {
+        IpAddr::V4(address) => {
+            let [first, second, ..] = address.octets();
+            !(address.is_unspecified()
+                || address.is_loopback()
+                || address.is_private()
+                || address.is_link_local()
+                || address.is_broadcast()
+                || address.is_documentation()
+                || address.is_multicast()
+                || first == 0
+                || first >= 240
+                // Shared address space of carrier-grade NAT.
+                || (first == 100 && second & 0xc0 == 64)
+                // Benchmarking networks.
+                || (first == 198 && second & 0xfe == 18))
+        }
+        IpAddr::V6(address) => match address.to_ipv4_mapped() {
+            Some(address) => is_public(address.into()),
+            None => {
+                let first = address.segments()[0];
+                !(address.is_unspecified()
+                    || address.is_loopback()
+                    || address.is_multicast()
+                    // Unique local addresses.
+                    || first & 0xfe00 == 0xfc00
+                    // Link-local addresses.
+                    || first & 0xffc0 == 0xfe80)
+            }
+        },
+    }
+}
+
+/// Resolves the host of a URL, failing unless every address it resolves to is public.
+async fn public_address(url: &Url) -> Result<SocketAddr, DatabaseError> {
+    let port = url
+        .port_or_known_default()
+        .filter(|_| matches!(url.scheme(), "http" | "https"))
+        .ok_or(DatabaseError::IllegalCoverUrl)?;
+    let host = url.host_str().ok_or(DatabaseError::IllegalCoverUrl)?;
+    // IPv6 hosts are kept in brackets.
+    let addresses: Vec<SocketAddr> = match host.trim_matches(['[', ']']).parse::<IpAddr>() {
+        Ok(address) => vec![SocketAddr::new(address, port)],
+        Err(_) => lookup_host((host, port))
+            .await
+            .map_err(|_| DatabaseError::CoverUnavailable)?
+            .collect(),
+    };
+    match addresses.first() {
+        Some(address) if addresses.iter().all(|address| is_public(address.ip())) => Ok(*address),
+        Some(_) => Err(DatabaseError::IllegalCoverUrl),
+        None => Err(DatabaseError::CoverUnavailable),
+    }
+}
+
+/// Client for a request to a URL provided by someone else. The host must resolve to a public
+/// address, and the client is pinned to that address so that the host cannot resolve to another
+/// one in between. Redirects are not followed, as the host they lead to has to be checked too.
+pub async fn pinned_client(url: &Url) -> Result<Client, DatabaseError> {
+    let address = public_address(url).await?;
+    let mut client = Client::builder()
+        .timeout(TIMEOUT)
+        .user_agent(USER_AGENT)
+        .redirect(Policy::none());
+    if let Some(domain) = url.domain() {
+        client = client.resolve(domain, address);
+    }
+    client
+        .build()
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Downloads the image at a cover URL, entered by an admin or found by [`fetch`]. Every host on
+/// the way, redirects included, is fetched through a [`pinned_client`].
+pub async fn download_cover(url: &str) -> Result<Bytes, DatabaseError> {
+    let mut url = Url::parse(url.trim()).map_err(|_| DatabaseError::IllegalCoverUrl)?;
+    for _ in 0..=MAX_REDIRECTS {
+        let mut response = pinned_client(&url)
+            .await?
+            .get(url.clone())
+            .send()
+            .await
+            .map_err(|_| DatabaseError::CoverUnavailable)?;
+        if response.status().is_redirection() {
+            url = response
+                .headers()
+                .get(header::LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .and_then(|location| url.join(location).ok())
+                .ok_or(DatabaseError::CoverUnavailable)?;
+            continue;
+        }
+        if !response.status().is_success() {
+            return Err(DatabaseError::CoverUnavailable);
+        }
+        let is_image = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("image/"));
+        if !is_image {
+            return Err(DatabaseError::NotValidImage);
+        }
+        if response.content_length().unwrap_or(0) > config::get().max_image_size as u64 {
+            return Err(DatabaseError::ImageTooLarge);
+        }
+        let mut cover = Vec::new();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|_| DatabaseError::CoverUnavailable)?
+        {
+            if cover.len() + chunk.len() > config::get().max_image_size {
+                return Err(DatabaseError::ImageTooLarge);
+            }
+            cover.extend_from_slice(&chunk);
+        }
+        return Ok(cover.into());
+    }
+    Err(DatabaseError::CoverUnavailable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_public_addresses_are_fetched() {
+        for address in ["93.184.215.14", "2606:2800:21f:cb07:6820:80da:af6b:8b2c"] {
+            assert!(is_public(address.parse().unwrap()), "{address}");
+        }
+        for address in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public(address.parse().unwrap()), "{address}");
+        }
+    }
+
+    #[tokio::test]
+    async fn refuses_private_cover_urls() {
+        for url in [
+            "http://127.0.0.1/cover.png",
+            "http://[::1]:3000/cover.png",
+            "http://localhost/cover.png",
+            "ftp://example.com/cover.png",
+            "not a url",
+        ] {
+            assert!(
+                matches!(
+                    download_cover(url).await,
+                    Err(DatabaseError::IllegalCoverUrl)
+                ),
+                "{url}"
+            );
+        }
+    }
+
+    #[test]
+    fn parses_sources_and_entries() {
+        assert_eq!(
+            Source::parse("https://openlibrary.org/works/OL45804W/Fantastic_Mr_Fox"),
+            Some(Source::OpenLibrary("works/OL45804W".to_owned()))
+        );
+        assert_eq!(
+            Source::parse(" OL7353617M "),
+            Some(Source::OpenLibrary("books/OL7353617M".to_owned()))
+        );
+        assert_eq!(
+            Source::parse("https://www.themoviedb.org/tv/1396-breaking-bad"),
+            Some(Source::Tmdb("tv".to_owned(), 1396))
+        );
+        assert_eq!(
+            Source::parse("tmdb:movie/603"),
+            Some(Source::Tmdb("movie".to_owned(), 603))
+        );
+        assert_eq!(Source::parse("https://example.com/works/OL45804W"), None);
+
+        let work: OpenLibraryEntry = serde_json::from_str(
+            r#"{"title": "Fantastic Mr Fox", "description": {"type": "/type/text", "value": "Foxes."}, "first_publish_date": "October 1, 1970", "covers": [-1, 6498519]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            Metadata::from(work),
+            Metadata {
+                title: "Fantastic Mr Fox".to_owned(),
+                description: "Foxes.".to_owned(),
+                release_date: NaiveDate::from_ymd_opt(1970, 10, 1),
+                cover_url: Some("https://covers.openlibrary.org/b/id/6498519-L.jpg".to_owned()),
+            }
+        );
+        let show: TmdbEntry = serde_json::from_str(
+            r#"{"name": "Breaking Bad", "overview": "A teacher.", "first_air_date": "2008-01-20", "poster_path": "/ggFHVNu6YYI5L9pCfOacjizRGt.jpg"}"#,
+        )
+        .unwrap();
+        let show = Metadata::from(show);
+        assert_eq!(show.title, "Breaking Bad");
+        assert_eq!(show.release_date, NaiveDate::from_ymd_opt(2008, 1, 20));
+        assert_eq!(parse_date("1954"), NaiveDate::from_ymd_opt(1954, 1, 1));
+    }
+}
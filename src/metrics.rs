@@ -0,0 +1,294 @@
+use crate::{images::ImageQueue, sessions::CurrentUser, templates};
+use axum::{
+    body::{to_bytes, Body},
+    extract::{MatchedPath, Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use axum_htmx::HxRequest;
+use std::{
+    cell::Cell,
+    collections::{HashMap, VecDeque},
+    fmt::{Debug, Write},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::{
+    debug,
+    field::{Field, Visit},
+    warn, warn_span, Event, Instrument, Level, Subscriber,
+};
+use tracing_subscriber::{
+    filter::{EnvFilter, Targets},
+    fmt,
+    layer::{Context, SubscriberExt},
+    util::SubscriberInitExt,
+    Layer,
+};
+
+/// Pages rendering slower than this are reported as slow.
+pub const BUDGET: Duration = Duration::from_millis(250);
+/// Number of most recent samples kept per route.
+const SAMPLES: usize = 1000;
+/// Upper bounds in seconds of the latency histogram buckets, the defaults of Prometheus clients.
+const BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Statements run for the request being handled and the time they took together.
+#[derive(Default)]
+struct Queries {
+    count: Cell<usize>,
+    time: Cell<Duration>,
+}
+
+tokio::task_local! {
+    static QUERIES: Queries;
+}
+
+/// Execution time sqlx logs statements with.
+struct Elapsed(Option<f64>);
+
+impl Visit for Elapsed {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        if field.name() == "elapsed_secs" {
+            self.0 = Some(value);
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn Debug) {}
+}
+
+/// Counts statements logged by sqlx towards the request being handled and adds up their
+/// execution time.
+struct QueryCounter;
+
+impl<S: Subscriber> Layer<S> for QueryCounter {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut elapsed = Elapsed(None);
+        event.record(&mut elapsed);
+        let _ = QUERIES.try_with(|queries| {
+            queries.count.set(queries.count.get() + 1);
+            if let Some(secs) = elapsed.0 {
+                queries
+                    .time
+                    .set(queries.time.get() + Duration::from_secs_f64(secs));
+            }
+        });
+    }
+}
+
+/// Logs application traces and slow statements filtered by `RUST_LOG` (warnings and above by
+/// default) and counts the queries of each request. Does nothing if a subscriber is already
+/// installed, as happens when several tests set up the app.
+pub fn install_tracing() {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new("zai=warn,sqlx::query=warn"));
+    let _ = tracing_subscriber::registry()
+        .with(fmt::layer().with_filter(filter))
+        .with(QueryCounter.with_filter(Targets::new().with_target("sqlx::query", Level::TRACE)))
+        .try_init();
+}
+
+/// Response header carrying the number of queries run for the request, making N+1 queries easy
+/// to spot from the browser.
+pub const QUERY_COUNT_HEADER: &str = "x-query-count";
+
+/// Renders queue gauges and route latency histograms in the Prometheus text format.
+pub fn render(images: &ImageQueue, latencies: &Latencies) -> String {
+    format!(
+        "# TYPE zai_image_queue_depth gauge\nzai_image_queue_depth {}\n# TYPE zai_image_queue_in_flight gauge\nzai_image_queue_in_flight {}\n{}",
+        images.depth(),
+        images.in_flight(),
+        latencies.render()
+    )
+}
+
+pub struct Percentiles {
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    pub samples: usize,
+}
+
+/// Cumulative counts of observations under each of the [`BUCKETS`].
+#[derive(Default)]
+struct Histogram {
+    buckets: [u64; BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        for (bucket, bound) in self.buckets.iter_mut().zip(BUCKETS) {
+            if secs <= bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += secs;
+    }
+
+    fn render(&self, output: &mut String, name: &str, route: &str) {
+        let route = route.replace('\\', "\\\\").replace('"', "\\\"");
+        for (count, bound) in self.buckets.iter().zip(BUCKETS) {
+            let _ = writeln!(
+                output,
+                "{name}_bucket{{route=\"{route}\",le=\"{bound}\"}} {count}"
+            );
+        }
+        let count = self.count;
+        let _ = writeln!(
+            output,
+            "{name}_bucket{{route=\"{route}\",le=\"+Inf\"}} {count}"
+        );
+        let _ = writeln!(output, "{name}_sum{{route=\"{route}\"}} {}", self.sum);
+        let _ = writeln!(output, "{name}_count{{route=\"{route}\"}} {count}");
+    }
+}
+
+/// Timings of a route: its most recent response times, and histograms of all of them and of the
+/// time its statements took.
+#[derive(Default)]
+struct RouteTimings {
+    samples: VecDeque<Duration>,
+    responses: Histogram,
+    queries: Histogram,
+}
+
+/// Response times of each route.
+#[derive(Default)]
+pub struct Latencies(Mutex<HashMap<String, RouteTimings>>);
+
+impl Latencies {
+    fn record(&self, route: &str, elapsed: Duration, query_time: Duration) -> Percentiles {
+        let mut routes = self.0.lock().unwrap();
+        let timings = routes.entry(route.to_owned()).or_default();
+        timings.responses.observe(elapsed);
+        timings.queries.observe(query_time);
+        let samples = &mut timings.samples;
+        if samples.len() == SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back(elapsed);
+        let mut sorted: Vec<Duration> = samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let percentile = |p: usize| sorted[(sorted.len() * p / 100).min(sorted.len() - 1)];
+        Percentiles {
+            p50: percentile(50),
+            p95: percentile(95),
+            p99: percentile(99),
+            samples: sorted.len(),
+        }
+    }
+
+    /// Histograms of the response times and query times of every route, sorted by route.
+    fn render(&self) -> String {
+        let routes = self.0.lock().unwrap();
+        let mut sorted: Vec<_> = routes.iter().collect();
+        sorted.sort_unstable_by_key(|(route, _)| *route);
+        let mut output = String::new();
+        let name = "zai_request_duration_seconds";
+        let _ = writeln!(output, "# TYPE {name} histogram");
+        for (route, timings) in &sorted {
+            timings.responses.render(&mut output, name, route);
+        }
+        let name = "zai_request_query_duration_seconds";
+        let _ = writeln!(output, "# TYPE {name} histogram");
+        for (route, timings) in &sorted {
+            timings.queries.render(&mut output, name, route);
+        }
+        output
+    }
+}
+
+pub struct PageStats {
+    pub route: String,
+    pub elapsed: Duration,
+    pub queries: usize,
+    /// Time the queries took, out of `elapsed`.
+    pub query_time: Duration,
+    pub percentiles: Percentiles,
+}
+
+/// Records the response time, query count and query time of every matched route, warns about
+/// pages over [`BUDGET`] and, in debug builds, shows admins a footer with them. Slow statements
+/// are logged in a span naming the route.
+pub async fn track_latency(
+    State(latencies): State<Arc<Latencies>>,
+    CurrentUser(user): CurrentUser,
+    HxRequest(is_htmx): HxRequest,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(route) = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_owned())
+    else {
+        return next.run(request).await;
+    };
+    let start = Instant::now();
+    let (response, queries, query_time) = QUERIES
+        .scope(
+            Queries::default(),
+            async {
+                let response = next.run(request).await;
+                let (queries, query_time) =
+                    QUERIES.with(|queries| (queries.count.get(), queries.time.get()));
+                (response, queries, query_time)
+            }
+            .instrument(warn_span!("request", route)),
+        )
+        .await;
+    let elapsed = start.elapsed();
+    let elapsed_ms = elapsed.as_millis() as u64;
+    let query_ms = query_time.as_millis() as u64;
+    if elapsed > BUDGET {
+        warn!(route, elapsed_ms, queries, query_ms, "slow page");
+    } else {
+        debug!(route, elapsed_ms, queries, query_ms, "page rendered");
+    }
+    let stats = PageStats {
+        percentiles: latencies.record(&route, elapsed, query_time),
+        route,
+        elapsed,
+        queries,
+        query_time,
+    };
+    let is_admin = user.is_some_and(|user| user.is_admin);
+    let is_html = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("text/html"));
+    let mut response = if cfg!(debug_assertions) && is_admin && is_html {
+        with_footer(response, &stats, is_htmx).await
+    } else {
+        response
+    };
+    response
+        .headers_mut()
+        .insert(QUERY_COUNT_HEADER, HeaderValue::from(queries));
+    response
+}
+
+/// Full pages get the footer at the end of the body, htmx responses swap it out of band.
+async fn with_footer(response: Response, stats: &PageStats, is_htmx: bool) -> Response {
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = to_bytes(body, usize::MAX).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let html = String::from_utf8_lossy(&bytes);
+    let footer = templates::debug_footer(stats, is_htmx).into_string();
+    let html = if is_htmx {
+        html.into_owned() + &footer
+    } else {
+        html.replacen("</body>", &(footer + "</body>"), 1)
+    };
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(html))
+}
@@ -0,0 +1,215 @@
+//! Rules new passwords have to follow, built at startup from the `password_*` settings of the
+//! [config](crate::config).
+
+use crate::{
+    config::{Config, ConfigError},
+    database::DatabaseError,
+};
+use passwords::{analyzer, scorer};
+use sha1_smol::Sha1;
+use std::{
+    cmp::Ordering,
+    fs::File,
+    io::{self, BufRead, BufReader, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+};
+use tokio::task;
+
+/// A single requirement of the [`PasswordPolicy`].
+pub trait Rule: Send + Sync {
+    fn check(&self, password: &str) -> Result<(), DatabaseError>;
+}
+
+pub struct MinLength(pub usize);
+
+impl Rule for MinLength {
+    fn check(&self, password: &str) -> Result<(), DatabaseError> {
+        if password.chars().count() < self.0 {
+            Err(DatabaseError::PasswordTooShort(self.0))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Rejects passwords scoring below the threshold, penalizing common passwords, repeated
+/// characters and few character classes.
+pub struct MinScore(pub f64);
+
+impl Rule for MinScore {
+    fn check(&self, password: &str) -> Result<(), DatabaseError> {
+        if scorer::score(&analyzer::analyze(password)) < self.0 {
+            Err(DatabaseError::WeakPassword)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Rejects passwords found in known data breaches, looking their hash up in a list ordered by hash
+/// with a binary search, which reads only a few dozen lines of it.
+pub struct NotBreached(PathBuf);
+
+impl NotBreached {
+    /// Opens the list at `path`, failing when it cannot be read.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        File::open(path)?;
+        Ok(NotBreached(path.to_owned()))
+    }
+
+    fn contains(&self, hash: &str) -> io::Result<bool> {
+        let mut file = BufReader::new(File::open(&self.0)?);
+        let (mut low, mut high) = (0, file.get_ref().metadata()?.len());
+        let mut line = Vec::new();
+        // Lines starting within `low..high` are the ones that may hold the hash.
+        while low < high {
+            let middle = low + (high - low) / 2;
+            // Skips the rest of the line around the middle, landing on the first line starting at
+            // or after it.
+            let mut start = middle;
+            if middle > 0 {
+                file.seek(SeekFrom::Start(middle - 1))?;
+                line.clear();
+                start = middle - 1 + file.read_until(b'\n', &mut line)? as u64;
+            } else {
+                file.seek(SeekFrom::Start(0))?;
+            }
+            if start >= high {
+                high = middle;
+                continue;
+            }
+            line.clear();
+            let length = file.read_until(b'\n', &mut line)? as u64;
+            let entry = line.split(|&byte| byte == b':').next().unwrap_or_default();
+            let entry = String::from_utf8_lossy(entry).trim().to_ascii_uppercase();
+            match entry.as_str().cmp(hash) {
+                Ordering::Equal => return Ok(true),
+                Ordering::Less => low = start + length,
+                Ordering::Greater => high = middle,
+            }
+        }
+        Ok(false)
+    }
+}
+
+impl Rule for NotBreached {
+    fn check(&self, password: &str) -> Result<(), DatabaseError> {
+        let hash = Sha1::from(password)
+            .digest()
+            .to_string()
+            .to_ascii_uppercase();
+        if self
+            .contains(&hash)
+            .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
+        {
+            Err(DatabaseError::BreachedPassword)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+pub struct PasswordPolicy {
+    rules: Arc<Vec<Box<dyn Rule>>>,
+}
+
+impl PasswordPolicy {
+    pub fn new(rules: Vec<Box<dyn Rule>>) -> Self {
+        PasswordPolicy {
+            rules: Arc::new(rules),
+        }
+    }
+
+    /// Builds the policy the settings ask for, failing when the breached password list cannot be
+    /// opened.
+    pub fn from_config(config: &Config) -> Result<Self, ConfigError> {
+        let mut rules: Vec<Box<dyn Rule>> = vec![
+            Box::new(MinLength(config.password_min_length)),
+            Box::new(MinScore(config.password_min_score)),
+        ];
+        if let Some(path) = &config.password_breached_list {
+            let list = NotBreached::open(path).map_err(|e| ConfigError::Read(path.clone(), e))?;
+            rules.push(Box::new(list));
+        }
+        Ok(PasswordPolicy::new(rules))
+    }
+
+    /// Checks the password against every rule, failing with the first one it breaks. The rules run
+    /// on the blocking thread pool, as looking the password up in the breached password list reads
+    /// from disk.
+    pub async fn check(&self, password: &str) -> Result<(), DatabaseError> {
+        let rules = self.rules.clone();
+        let password = password.to_owned();
+        task::spawn_blocking(move || rules.iter().try_for_each(|rule| rule.check(&password)))
+            .await
+            .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
+    }
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        PasswordPolicy::from_config(&Config::default()).unwrap()
+    }
+}
+
+static POLICY: OnceLock<PasswordPolicy> = OnceLock::new();
+
+/// Makes `policy` the policy of the app, returned by [`policy`] from then on.
+pub fn install(policy: PasswordPolicy) {
+    POLICY.get_or_init(|| policy);
+}
+
+/// The policy configured for this instance, the default until another is [installed](install), as
+/// in tests.
+pub fn policy() -> &'static PasswordPolicy {
+    POLICY.get_or_init(PasswordPolicy::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn reports_first_broken_rule() {
+        let policy = PasswordPolicy::new(vec![Box::new(MinLength(12)), Box::new(MinScore(80.0))]);
+        assert!(matches!(
+            policy.check("Tr0ub4dor&3").await,
+            Err(DatabaseError::PasswordTooShort(12))
+        ));
+        assert!(matches!(
+            policy.check("aaaaaaaaaaaa").await,
+            Err(DatabaseError::WeakPassword)
+        ));
+        assert!(policy
+            .check("correct horse battery staple 42!")
+            .await
+            .is_ok());
+    }
+
+    #[test]
+    fn finds_breached_passwords_in_ordered_lists() {
+        let path = std::env::temp_dir().join(format!("zai-breached-{}", std::process::id()));
+        let mut hashes: Vec<_> = (0..500)
+            .map(|n| Sha1::from(format!("password{n}")).digest().to_string())
+            .collect();
+        hashes.sort_unstable();
+        let list: String = hashes
+            .iter()
+            .enumerate()
+            .map(|(n, hash)| format!("{}:{}\r\n", hash.to_ascii_uppercase(), n * 37))
+            .collect();
+        std::fs::write(&path, list).unwrap();
+        let rule = NotBreached::open(&path).unwrap();
+        for n in [0, 1, 250, 499] {
+            assert!(matches!(
+                rule.check(&format!("password{n}")),
+                Err(DatabaseError::BreachedPassword)
+            ));
+        }
+        assert!(rule.check("password500").is_ok());
+        assert!(rule.check("correct horse battery staple 42!").is_ok());
+        std::fs::remove_file(&path).unwrap();
+        assert!(NotBreached::open(&path).is_err());
+    }
+}
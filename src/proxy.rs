@@ -0,0 +1,186 @@
+//! Requests passed on by a reverse proxy like nginx. The proxy connects from its own address and
+//! tells who the client is and what it asked for in the `X-Forwarded-For`, `X-Forwarded-Proto` and
+//! `X-Forwarded-Host` headers. Anyone can send those, so they are only believed from the
+//! `trusted_proxies` of the config, or over the Unix socket, which only the proxy can connect to,
+//! and the connection itself is taken at its word otherwise.
+
+use crate::{config, mailer};
+use axum::{
+    extract::{ConnectInfo, Request},
+    http::{header, HeaderMap},
+    middleware::Next,
+    response::Response,
+};
+use std::net::{IpAddr, SocketAddr};
+
+const FORWARDED_FOR: &str = "x-forwarded-for";
+const FORWARDED_PROTO: &str = "x-forwarded-proto";
+const FORWARDED_HOST: &str = "x-forwarded-host";
+
+/// Scheme and host the client sent the request to, like `https://example.com`, when known.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Origin(Option<String>);
+
+impl Origin {
+    /// Absolute link to a path of the site, which already starts with the base path. Starts with
+    /// `PUBLIC_URL` when it is set, so that shared links agree with those in mail.
+    pub fn link(&self, path: &str) -> String {
+        match (mailer::public_url(), &self.0) {
+            (None, Some(origin)) => format!("{origin}{path}"),
+            _ => mailer::link(path),
+        }
+    }
+
+    /// Whether the client sent the request over HTTPS, to the proxy in front when there is one.
+    pub fn is_https(&self) -> bool {
+        self.0
+            .as_deref()
+            .is_some_and(|origin| origin.starts_with("https://"))
+    }
+}
+
+/// Marks requests that came over the Unix socket rather than from an address.
+#[derive(Clone, Copy, Debug)]
+pub struct UnixSocket;
+
+/// First value of a forwarded header, proxies add theirs after the one the client sent them.
+fn first<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get(name)?
+        .to_str()
+        .ok()?
+        .split(',')
+        .map(str::trim)
+        .find(|value| !value.is_empty())
+}
+
+/// Address of the client behind a chain of trusted proxies. Every proxy appends the address it
+/// was connected from to `X-Forwarded-For`, so the client is the last one that is not a trusted
+/// proxy itself, as anything before it was made up by the client.
+fn client_address(headers: &HeaderMap, trusted: &[IpAddr]) -> Option<IpAddr> {
+    let chain: Vec<&str> = headers
+        .get_all(FORWARDED_FOR)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect();
+    let mut client = None;
+    for address in chain.into_iter().rev() {
+        let address = address
+            .parse::<IpAddr>()
+            .or_else(|_| address.parse::<SocketAddr>().map(|address| address.ip()))
+            .ok()?;
+        client = Some(address);
+        if !trusted.contains(&address) {
+            break;
+        }
+    }
+    client
+}
+
+/// Client address and origin of a request from `peer`, read off the forwarded headers when
+/// `peer` is a trusted proxy or the request came over the Unix socket.
+fn resolve(
+    headers: &HeaderMap,
+    authority: Option<&str>,
+    peer: Option<IpAddr>,
+    unix_socket: bool,
+    trusted: &[IpAddr],
+) -> (Option<IpAddr>, Origin) {
+    let from_proxy = unix_socket || peer.is_some_and(|peer| trusted.contains(&peer));
+    let forwarded = |name| from_proxy.then(|| first(headers, name)).flatten();
+    let scheme = match forwarded(FORWARDED_PROTO) {
+        Some(scheme) if scheme.eq_ignore_ascii_case("https") => "https",
+        _ => "http",
+    };
+    let host = forwarded(FORWARDED_HOST)
+        .or_else(|| headers.get(header::HOST)?.to_str().ok())
+        .or(authority);
+    let client = if from_proxy {
+        client_address(headers, trusted).or(peer)
+    } else {
+        peer
+    };
+    (
+        client,
+        Origin(host.map(|host| format!("{scheme}://{host}"))),
+    )
+}
+
+/// Puts the address of the client in place of that of the proxy in [`ConnectInfo`], for anything
+/// telling clients apart by address, and adds the [`Origin`] of the request.
+pub async fn forwarded(mut request: Request, next: Next) -> Response {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(address)| *address);
+    let (client, origin) = resolve(
+        request.headers(),
+        request
+            .uri()
+            .authority()
+            .map(|authority| authority.as_str()),
+        peer.map(|peer| peer.ip()),
+        request.extensions().get::<UnixSocket>().is_some(),
+        &config::get().trusted_proxies,
+    );
+    if let Some(client) = client.filter(|client| peer.map(|peer| peer.ip()) != Some(*client)) {
+        // The port of the client is not forwarded.
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::new(client, 0)));
+    }
+    request.extensions_mut().insert(origin);
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn believes_forwarded_headers_from_trusted_proxies() {
+        let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+        let client: IpAddr = "203.0.113.7".parse().unwrap();
+        let forwarded = headers(&[
+            ("host", "10.0.0.2:3000"),
+            (FORWARDED_FOR, "198.51.100.1, 203.0.113.7"),
+            (FORWARDED_FOR, "10.0.0.1"),
+            (FORWARDED_PROTO, "https"),
+            (FORWARDED_HOST, "example.com"),
+        ]);
+        let (address, origin) = resolve(&forwarded, None, Some(proxy), false, &[proxy]);
+        assert_eq!(address, Some(client));
+        assert_eq!(origin, Origin(Some("https://example.com".to_owned())));
+        let (address, origin) = resolve(&forwarded, None, Some(client), false, &[proxy]);
+        assert_eq!(address, Some(client));
+        assert_eq!(origin, Origin(Some("http://10.0.0.2:3000".to_owned())));
+        let garbled = headers(&[(FORWARDED_FOR, "203.0.113.7, unknown")]);
+        assert_eq!(
+            resolve(&garbled, None, Some(proxy), false, &[proxy]).0,
+            Some(proxy)
+        );
+        let (address, origin) = resolve(
+            &HeaderMap::new(),
+            Some("example.com"),
+            None,
+            false,
+            &[proxy],
+        );
+        assert_eq!(address, None);
+        assert_eq!(origin, Origin(Some("http://example.com".to_owned())));
+        let (address, origin) = resolve(&forwarded, None, None, true, &[]);
+        assert_eq!(address, Some("10.0.0.1".parse().unwrap()));
+        assert_eq!(origin, Origin(Some("https://example.com".to_owned())));
+    }
+}
@@ -0,0 +1,189 @@
+//! Limits how often each client may hit the expensive routes of `rate_limited_routes`, like search,
+//! registration and uploads. Every client address has a token bucket holding up to
+//! `rate_limit_burst` requests, refilled at `rate_limit_per_minute`, shared by all of those routes.
+//! Clients are told by address as [`proxy`](crate::proxy) resolves it, and requests without one,
+//! as over a Unix socket without forwarded headers, are not limited.
+
+use crate::{config, error::AppError, sessions::CurrentUser};
+use axum::{
+    extract::{ConnectInfo, MatchedPath, Request, State},
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv6Addr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Clients tracked at most. Beyond it, those whose buckets have refilled are forgotten first, and
+/// the least recently seen one otherwise.
+const MAX_CLIENTS: usize = 10_000;
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token buckets of the clients that recently made limited requests.
+#[derive(Default)]
+pub struct RateLimiter(Mutex<HashMap<IpAddr, Bucket>>);
+
+impl RateLimiter {
+    /// Takes a token from the bucket of `client`, or tells how long until there is one.
+    fn take(
+        &self,
+        client: IpAddr,
+        now: Instant,
+        per_minute: u32,
+        burst: u32,
+    ) -> Result<(), Duration> {
+        let rate = f64::from(per_minute) / 60.0;
+        let burst = f64::from(burst);
+        let refill = |bucket: &Bucket| {
+            let elapsed = now.saturating_duration_since(bucket.updated);
+            (bucket.tokens + elapsed.as_secs_f64() * rate).min(burst)
+        };
+        let mut buckets = self.0.lock().unwrap();
+        if buckets.len() >= MAX_CLIENTS && !buckets.contains_key(&client) {
+            buckets.retain(|_, bucket| refill(bucket) < burst);
+            if buckets.len() >= MAX_CLIENTS {
+                let oldest = buckets
+                    .iter()
+                    .min_by_key(|(_, bucket)| bucket.updated)
+                    .map(|(&address, _)| address);
+                if let Some(oldest) = oldest {
+                    buckets.remove(&oldest);
+                }
+            }
+        }
+        let bucket = buckets.entry(client).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+        bucket.tokens = refill(bucket);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / rate))
+        }
+    }
+}
+
+/// Address a client is limited by. IPv6 clients are usually given a whole /64, so its addresses
+/// share a bucket.
+fn client_key(address: IpAddr) -> IpAddr {
+    match address {
+        IpAddr::V6(address) => match address.to_ipv4_mapped() {
+            Some(address) => IpAddr::V4(address),
+            None => IpAddr::V6(Ipv6Addr::from(u128::from(address) & !u128::from(u64::MAX))),
+        },
+        address => address,
+    }
+}
+
+/// Answers requests to the limited routes with 429 Too Many Requests once the client has used up
+/// its bucket, saying in `Retry-After` when to try again.
+pub async fn limit(
+    State(limiter): State<Arc<RateLimiter>>,
+    CurrentUser(user): CurrentUser,
+    request: Request,
+    next: Next,
+) -> Response {
+    let config = config::get();
+    let is_limited = request
+        .extensions()
+        .get::<MatchedPath>()
+        .is_some_and(|path| {
+            let route = path.as_str();
+            let route = route.strip_prefix(&config.base_path).unwrap_or(route);
+            config
+                .rate_limited_routes
+                .iter()
+                .any(|limited| limited == route)
+        });
+    let is_exempt = config.rate_limit_per_minute == 0 || user.is_some_and(|user| user.is_admin);
+    let client = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .filter(|_| is_limited && !is_exempt)
+        .map(|ConnectInfo(address)| client_key(address.ip()));
+    let Some(client) = client else {
+        return next.run(request).await;
+    };
+    match limiter.take(
+        client,
+        Instant::now(),
+        config.rate_limit_per_minute,
+        config.rate_limit_burst,
+    ) {
+        Ok(()) => next.run(request).await,
+        Err(wait) => {
+            let mut response = AppError::TooManyRequests.into_response();
+            response.headers_mut().insert(
+                header::RETRY_AFTER,
+                HeaderValue::from(wait.as_secs_f64().ceil() as u64),
+            );
+            response
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refills_buckets_over_time() {
+        let limiter = RateLimiter::default();
+        let client = "203.0.113.7".parse().unwrap();
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert_eq!(limiter.take(client, start, 60, 3), Ok(()));
+        }
+        assert_eq!(
+            limiter.take(client, start, 60, 3),
+            Err(Duration::from_secs(1))
+        );
+        let other = "203.0.113.8".parse().unwrap();
+        assert_eq!(limiter.take(other, start, 60, 3), Ok(()));
+        let later = start + Duration::from_millis(1500);
+        assert_eq!(limiter.take(client, later, 60, 3), Ok(()));
+        assert!(limiter.take(client, later, 60, 3).is_err());
+        let much_later = start + Duration::from_secs(3600);
+        for _ in 0..3 {
+            assert_eq!(limiter.take(client, much_later, 60, 3), Ok(()));
+        }
+    }
+
+    #[test]
+    fn forgets_the_least_recently_seen_client_when_full() {
+        let limiter = RateLimiter::default();
+        let start = Instant::now();
+        for i in 0..MAX_CLIENTS as u32 {
+            let client = IpAddr::from((0x0a00_0000 + i).to_be_bytes());
+            let now = start + Duration::from_millis(u64::from(i));
+            assert_eq!(limiter.take(client, now, 1, 2), Ok(()));
+        }
+        let first = IpAddr::from(0x0a00_0000u32.to_be_bytes());
+        let newcomer = "203.0.113.7".parse().unwrap();
+        let now = start + Duration::from_secs(20);
+        assert_eq!(limiter.take(newcomer, now, 1, 2), Ok(()));
+        let buckets = limiter.0.lock().unwrap();
+        assert_eq!(buckets.len(), MAX_CLIENTS);
+        assert!(!buckets.contains_key(&first));
+        assert!(buckets.contains_key(&newcomer));
+    }
+
+    #[test]
+    fn shares_buckets_within_ipv6_networks() {
+        let key = |address: &str| client_key(address.parse().unwrap());
+        assert_eq!(key("2001:db8::1"), key("2001:db8::ffff:1"));
+        assert_ne!(key("2001:db8::1"), key("2001:db8:0:1::1"));
+        assert_eq!(key("::ffff:203.0.113.7"), key("203.0.113.7"));
+    }
+}
@@ -0,0 +1,20 @@
+//! Emoji reactions left on reviews and replies, a lighter way of responding than replying.
+
+/// Reactions that may be left, by name as stored and emoji as shown.
+pub const REACTIONS: [(&str, &str); 6] = [
+    ("like", "👍"),
+    ("love", "❤️"),
+    ("funny", "😂"),
+    ("wow", "😮"),
+    ("sad", "😢"),
+    ("fire", "🔥"),
+];
+
+/// Names of all reactions, in the order their counts are returned by the database.
+pub fn names() -> Vec<&'static str> {
+    REACTIONS.iter().map(|(name, _)| *name).collect()
+}
+
+pub fn is_reaction(name: &str) -> bool {
+    REACTIONS.iter().any(|(reaction, _)| *reaction == name)
+}
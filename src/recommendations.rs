@@ -0,0 +1,71 @@
+use crate::{
+    database::{DatabaseError, Item},
+    jobs::Scheduler,
+};
+use sqlx::{query, query_as, PgPool};
+use std::time::Duration;
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Recomputes the item-item similarity matrix using adjusted cosine similarity, i.e. cosine
+/// similarity of ratings centered on each user's mean rating.
+pub async fn refresh(pool: &PgPool) -> Result<(), DatabaseError> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    query!("DELETE FROM item_similarities")
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    query!(
+        "WITH centered AS (SELECT item_id, user_id, rating - AVG(rating) OVER (PARTITION BY user_id) AS r FROM reviews)
+        INSERT INTO item_similarities(item_id, similar_item_id, similarity)
+        SELECT a.item_id, b.item_id, (SUM(a.r * b.r) / NULLIF(sqrt(SUM(a.r * a.r)) * sqrt(SUM(b.r * b.r)), 0))::REAL AS similarity
+        FROM centered a JOIN centered b ON a.user_id = b.user_id AND a.item_id <> b.item_id
+        GROUP BY a.item_id, b.item_id
+        HAVING SUM(a.r * b.r) / NULLIF(sqrt(SUM(a.r * a.r)) * sqrt(SUM(b.r * b.r)), 0) IS NOT NULL"
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    transaction
+        .commit()
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Periodically refreshes the similarity matrix in the background.
+pub fn schedule(scheduler: &mut Scheduler) {
+    scheduler.add(
+        "recommendations",
+        "Recompute the item similarities recommendations are made from",
+        REFRESH_INTERVAL,
+        |pool| async move { refresh(&pool).await },
+    );
+}
+
+/// Items not yet rated by the user, ranked by the user's centered ratings of similar items.
+pub async fn recommended_items(
+    pool: &PgPool,
+    username: &str,
+    limit: i64,
+) -> Result<Vec<Item>, DatabaseError> {
+    query_as!(
+        Item,
+        r#"WITH centered AS (SELECT item_id, rating - AVG(rating) OVER () AS r FROM reviews WHERE user_id = (SELECT id FROM users WHERE username = $1 LIMIT 1))
+        SELECT i.locator AS "locator!", i.title AS "title!", i.description AS "description!", i.score AS "score!", i.review_count AS "review_count!", i.rank AS "rank!", i.popularity AS "popularity!", i.category_slug, i.category_name, i.category_rank AS "category_rank!", i.release_date, i.unreleased AS "unreleased!", i.locked AS "locked!", i.favorite_count AS "favorite_count!", i.cover
+        FROM items_score i JOIN (
+            SELECT s.similar_item_id AS item_id, SUM(s.similarity * c.r) / SUM(s.similarity) AS prediction
+            FROM item_similarities s JOIN centered c ON s.item_id = c.item_id
+            WHERE s.similarity > 0 AND s.similar_item_id NOT IN (SELECT item_id FROM reviews WHERE user_id = (SELECT id FROM users WHERE username = $1 LIMIT 1))
+            GROUP BY s.similar_item_id
+        ) p ON p.item_id = i.id
+        ORDER BY p.prediction DESC, i.score DESC LIMIT $2"#,
+        username,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
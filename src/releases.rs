@@ -0,0 +1,26 @@
+//! Upcoming items, which open for reviews once their release date comes.
+
+use crate::{database::DatabaseError, jobs::Scheduler};
+use sqlx::{query, PgPool};
+use std::time::Duration;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Marks upcoming items whose release date has come as released, returning how many were.
+pub async fn release_due(pool: &PgPool) -> Result<u64, DatabaseError> {
+    query!("UPDATE items SET unreleased = FALSE WHERE unreleased AND release_date <= CURRENT_DATE")
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected())
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Periodically releases due items in the background.
+pub fn schedule(scheduler: &mut Scheduler) {
+    scheduler.add(
+        "releases",
+        "Open upcoming items for reviews on their release date",
+        CHECK_INTERVAL,
+        |pool| async move { release_due(&pool).await },
+    );
+}
@@ -0,0 +1,170 @@
+use crate::{database::DatabaseError, routes, security, templates};
+use axum::{
+    extract::{Request, State},
+    http::{StatusCode, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use axum_htmx::{HxReswap, HxRetarget, SwapOption, HX_REQUEST};
+use maud::{Markup, PreEscaped};
+use sqlx::PgPool;
+use std::{collections::HashMap, future::Future, sync::Mutex, time::Duration};
+use tokio::time::{sleep, timeout};
+
+/// How long a query waits for a connection, kept short so that an outage is noticed quickly.
+pub const ACQUIRE_TIMEOUT: Duration = Duration::from_secs(2);
+/// Times a query failing on a lost connection is attempted before giving up.
+const ATTEMPTS: u32 = 3;
+/// Wait before the first retry, doubled for each following one.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+/// Listing pages kept for serving while the database is down.
+const CACHED_PAGES: usize = 256;
+
+/// Runs a database operation, retrying with exponential backoff while it fails on a lost
+/// connection.
+pub async fn retry<T, F, Fut>(mut operation: F) -> Result<T, DatabaseError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, DatabaseError>>,
+{
+    let mut backoff = INITIAL_BACKOFF;
+    for _ in 1..ATTEMPTS {
+        match operation().await {
+            Err(e) if e.is_transient() => {
+                sleep(backoff).await;
+                backoff *= 2;
+            }
+            result => return result,
+        }
+    }
+    operation().await
+}
+
+/// Last rendered content of listing pages, keyed by path and query, served read-only while the
+/// database is unreachable.
+#[derive(Default)]
+pub struct PageCache {
+    pages: Mutex<HashMap<String, String>>,
+}
+
+impl PageCache {
+    /// Cache key of a page as seen by a user, as listings show user specific parts.
+    pub fn key(uri: &Uri, username: Option<&str>) -> String {
+        format!("{}@{uri}", username.unwrap_or_default())
+    }
+
+    pub fn store(&self, key: &str, content: &Markup) {
+        let mut pages = self.pages.lock().unwrap();
+        if pages.len() >= CACHED_PAGES && !pages.contains_key(key) {
+            pages.clear();
+        }
+        pages.insert(key.to_owned(), content.0.clone());
+    }
+
+    pub fn get(&self, key: &str) -> Option<Markup> {
+        self.pages.lock().unwrap().get(key).cloned().map(PreEscaped)
+    }
+
+    /// Renders a listing page from a fresh result, falling back to the cached copy behind an
+    /// unavailability banner when the database could not be reached.
+    pub fn render<T>(
+        &self,
+        key: &str,
+        result: Result<T, DatabaseError>,
+        render: impl FnOnce(T) -> Markup,
+    ) -> Result<Markup, DatabaseError> {
+        match result {
+            Ok(value) => {
+                let content = render(value);
+                self.store(key, &content);
+                Ok(content)
+            }
+            Err(e) if e.is_transient() => Ok(templates::unavailable(self.get(key))),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Turns handler panics into an unavailability notice when they were caused by the database
+/// being down, instead of dropping the connection.
+pub async fn catch_outage(State(pool): State<PgPool>, request: Request, next: Next) -> Response {
+    let is_htmx = request.headers().contains_key(HX_REQUEST);
+    match tokio::spawn(security::with_nonce(next.run(request))).await {
+        Ok(response) => response,
+        Err(e) if e.is_panic() && !is_reachable(&pool).await => {
+            if is_htmx {
+                (
+                    HxRetarget("body".to_owned()),
+                    HxReswap(SwapOption::BeforeEnd),
+                    templates::error_modal(&DatabaseError::Unavailable.to_string()),
+                )
+                    .into_response()
+            } else {
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    templates::index(templates::unavailable(None), routes::ITEMS, None, None),
+                )
+                    .into_response()
+            }
+        }
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+async fn is_reachable(pool: &PgPool) -> bool {
+    matches!(
+        timeout(
+            Duration::from_secs(1),
+            sqlx::query("SELECT 1").execute(pool)
+        )
+        .await,
+        Ok(Ok(_))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maud::html;
+    use std::cell::Cell;
+
+    fn lost_connection() -> DatabaseError {
+        DatabaseError::InternalError(Box::new(sqlx::Error::PoolTimedOut))
+    }
+
+    #[tokio::test]
+    async fn retries_only_lost_connections() {
+        let attempts = Cell::new(0);
+        let result: Result<(), _> = retry(|| async {
+            attempts.set(attempts.get() + 1);
+            Err(lost_connection())
+        })
+        .await;
+        assert!(result.unwrap_err().is_transient());
+        assert_eq!(attempts.get(), ATTEMPTS);
+        attempts.set(0);
+        let result: Result<(), _> = retry(|| async {
+            attempts.set(attempts.get() + 1);
+            Err(DatabaseError::EmptyFields)
+        })
+        .await;
+        assert!(matches!(result, Err(DatabaseError::EmptyFields)));
+        assert_eq!(attempts.get(), 1);
+    }
+
+    #[test]
+    fn serves_cached_page_while_unavailable() {
+        let pages = PageCache::default();
+        let render = |count: i32| html! { (count) };
+        assert_eq!(pages.render("/items", Ok(1), render).unwrap().0, "1");
+        let degraded = pages
+            .render("/items", Err(lost_connection()), render)
+            .unwrap()
+            .0;
+        assert!(degraded.contains(&DatabaseError::Unavailable.to_string()));
+        assert!(degraded.ends_with('1'));
+        assert!(pages
+            .render("/items", Err(DatabaseError::EmptyFields), render)
+            .is_err());
+    }
+}
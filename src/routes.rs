@@ -0,0 +1,409 @@
+pub const INDEX: &str = "/";
+pub const SCRIPTS: &str = "/scripts.js";
+pub const LOGIN: &str = "/login";
+pub const REGISTER: &str = "/register";
+pub const PASSWORD_RESET: &str = "/password-reset";
+pub const VERIFY_EMAIL: &str = "/verify-email";
+pub const MARKDOWN_PREVIEW: &str = "/markdown/preview";
+pub const LOGOUT: &str = "/logout";
+pub const SEARCH: &str = "/search";
+pub const SEARCH_RESULTS: &str = "/search/results";
+pub const ITEMS: &str = "/items";
+pub const ITEMS_TRENDING: &str = "/items/trending";
+pub const ITEMS_NEW: &str = "/items/new";
+pub const CHARTS: &str = "/charts";
+pub const CHART: &str = "/charts/:month";
+pub const ITEM_ADD: &str = "/items/add";
+pub const ITEMS_METADATA: &str = "/items/metadata";
+pub const ITEM: &str = "/items/:item";
+pub const ITEM_EDIT: &str = "/items/:item/edit";
+pub const ITEM_REMOVE: &str = "/items/:item/remove";
+pub const ITEM_MERGE: &str = "/items/:item/merge";
+pub const ITEM_RATE: &str = "/items/:item/rate";
+pub const ITEM_RATING: &str = "/items/:item/rate/:user";
+pub const ITEM_REVIEW: &str = "/items/:item/review";
+pub const ITEM_SUBSCRIPTION: &str = "/items/:item/subscription";
+pub const ITEM_FAVORITE: &str = "/items/:item/favorite";
+pub const ITEM_LISTS: &str = "/items/:item/lists";
+pub const ITEM_LIST: &str = "/items/:item/lists/:list";
+pub const ITEM_COVER: &str = "/items/:item/cover";
+pub const ITEM_CARD: &str = "/items/:item/card.png";
+pub const ITEM_FEATURE: &str = "/items/:item/feature";
+pub const ITEM_LOCK: &str = "/items/:item/lock";
+pub const ITEM_DISCUSSION: &str = "/items/:item/discussion";
+pub const ITEM_COMMENT: &str = "/items/:item/discussion/:comment";
+pub const ITEM_IMAGE: &str = "/items/:item/images/:image";
+pub const ITEM_IMAGE_COVER: &str = "/items/:item/images/:image/cover";
+pub const REVIEW_REPLIES: &str = "/items/:item/reviews/:user/replies";
+pub const REVIEW_REPLY: &str = "/items/:item/reviews/:user/replies/:reply";
+pub const REVIEW_REACTIONS: &str = "/items/:item/reviews/:user/reactions";
+pub const REPLY_REACTIONS: &str = "/items/:item/reviews/:user/replies/:reply/reactions";
+pub const TAGS: &str = "/tags";
+pub const REVIEWS: &str = "/reviews";
+pub const NOTIFICATIONS: &str = "/notifications";
+pub const FEED: &str = "/feed";
+pub const USERS: &str = "/users";
+pub const USER: &str = "/users/:user";
+pub const USER_EDIT: &str = "/users/:user/edit";
+pub const USER_REMOVE: &str = "/users/:user/remove";
+pub const USER_PASSWORD_RESET: &str = "/users/:user/password-reset";
+pub const USER_COMPATIBILITY: &str = "/users/:user/compatibility";
+pub const USER_FAVORITES: &str = "/users/:user/favorites";
+pub const USER_FOLLOW: &str = "/users/:user/follow";
+pub const USER_LISTS: &str = "/users/:user/lists";
+pub const USER_LIST: &str = "/users/:user/lists/:list";
+pub const USER_LIST_ITEM: &str = "/users/:user/lists/:list/items/:item";
+pub const USER_IMPORT: &str = "/users/:user/import";
+pub const USER_IMPORT_PREVIEW: &str = "/users/:user/import/preview";
+pub const ADMIN_ITEMS: &str = "/admin/items";
+pub const ADMIN_ITEMS_CSV: &str = "/admin/items.csv";
+pub const ITEMS_EXPORT: &str = "/items/export";
+pub const ADMIN_CATEGORIES: &str = "/admin/categories";
+pub const ADMIN_CATEGORY: &str = "/admin/categories/:category";
+pub const ADMIN_FEATURED: &str = "/admin/featured";
+pub const ADMIN_COLLECTIONS: &str = "/admin/collections";
+pub const ADMIN_COLLECTION: &str = "/admin/collections/:collection";
+pub const COLLECTION: &str = "/collections/:collection";
+pub const COLLECTION_ITEMS: &str = "/collections/:collection/items";
+pub const COLLECTION_ITEM: &str = "/collections/:collection/items/:item";
+pub const METRICS: &str = "/metrics";
+pub const ABOUT: &str = "/about";
+pub const VERSION: &str = "/version";
+pub const SUGGESTIONS: &str = "/suggestions";
+pub const ADMIN_SUGGESTIONS: &str = "/admin/suggestions";
+pub const ADMIN_SUGGESTION_APPROVE: &str = "/admin/suggestions/:suggestion/approve";
+pub const ADMIN_SUGGESTION_REJECT: &str = "/admin/suggestions/:suggestion/reject";
+pub const ADMIN_WEBHOOKS: &str = "/admin/webhooks";
+pub const ADMIN_WEBHOOK: &str = "/admin/webhooks/:webhook";
+pub const ADMIN_WEBHOOK_DELIVERY: &str = "/admin/webhooks/deliveries/:delivery";
+pub const ADMIN_JOBS: &str = "/admin/jobs";
+pub const ADMIN_JOB_RUN: &str = "/admin/jobs/:job/run";
+pub const ADMIN_AUDIT: &str = "/admin/audit";
+pub const ADMIN_SEED: &str = "/admin/seed";
+pub const WEBFINGER: &str = "/.well-known/webfinger";
+pub const ACTIVITYPUB_ACTOR: &str = "/activitypub/actor";
+pub const ACTIVITYPUB_INBOX: &str = "/activitypub/inbox";
+pub const ACTIVITYPUB_OUTBOX: &str = "/activitypub/outbox";
+pub const ACTIVITYPUB_FOLLOWERS: &str = "/activitypub/followers";
+pub const STATIC: &str = "/static";
+pub const IMAGES: &str = "/static/images";
+
+/// Static routes directly under [`ITEMS`], which shadow [`ITEM`] for items with their last segment
+/// as the locator.
+const ITEMS_STATIC: [&str; 5] = [
+    ITEMS_TRENDING,
+    ITEMS_NEW,
+    ITEM_ADD,
+    ITEMS_METADATA,
+    ITEMS_EXPORT,
+];
+
+/// Whether an item with this locator could never be reached, as a static route takes its place.
+pub fn is_reserved_locator(locator: &str) -> bool {
+    ITEMS_STATIC.iter().any(|route| {
+        route
+            .strip_prefix(ITEMS)
+            .and_then(|segment| segment.strip_prefix('/'))
+            == Some(locator)
+    })
+}
+
+/// Builders filling the parameters of the route patterns above, so that links stay in sync with
+/// the router, and prefixing them with the configured base path.
+pub mod url {
+    use super::*;
+    use crate::{assets, config, images};
+    use sqlx::types::chrono::NaiveDate;
+
+    /// Address of a route without parameters, under the base path the app is served from. The
+    /// index is the base path itself, without a trailing slash.
+    pub fn path(route: &str) -> String {
+        match config::get().base_path.as_str() {
+            "" => route.to_owned(),
+            base if route == INDEX => base.to_owned(),
+            base => format!("{base}{route}"),
+        }
+    }
+
+    pub fn item(locator: &str) -> String {
+        path(&ITEM.replace(":item", locator))
+    }
+
+    pub fn verify_email(token: &str) -> String {
+        path(&format!("{VERIFY_EMAIL}?token={token}"))
+    }
+
+    pub fn items_tagged(tag: &str) -> String {
+        path(&format!("{ITEMS}?tag={tag}"))
+    }
+
+    pub fn items_in_category(category: &str) -> String {
+        path(&format!("{ITEMS}?category={category}"))
+    }
+
+    pub fn item_edit(locator: &str) -> String {
+        path(&ITEM_EDIT.replace(":item", locator))
+    }
+
+    pub fn item_remove(locator: &str) -> String {
+        path(&ITEM_REMOVE.replace(":item", locator))
+    }
+
+    pub fn item_merge(locator: &str) -> String {
+        path(&ITEM_MERGE.replace(":item", locator))
+    }
+
+    pub fn item_rate(locator: &str) -> String {
+        path(&ITEM_RATE.replace(":item", locator))
+    }
+
+    pub fn item_subscription(locator: &str) -> String {
+        path(&ITEM_SUBSCRIPTION.replace(":item", locator))
+    }
+
+    pub fn item_favorite(locator: &str) -> String {
+        path(&ITEM_FAVORITE.replace(":item", locator))
+    }
+
+    pub fn item_lists(locator: &str) -> String {
+        path(&ITEM_LISTS.replace(":item", locator))
+    }
+
+    pub fn item_list(locator: &str, list: &str) -> String {
+        path(&ITEM_LIST.replace(":item", locator).replace(":list", list))
+    }
+
+    pub fn item_cover(locator: &str) -> String {
+        path(&ITEM_COVER.replace(":item", locator))
+    }
+
+    pub fn item_card(locator: &str) -> String {
+        path(&ITEM_CARD.replace(":item", locator))
+    }
+
+    pub fn item_gallery_image(locator: &str, id: i32) -> String {
+        path(
+            &ITEM_IMAGE
+                .replace(":item", locator)
+                .replace(":image", &id.to_string()),
+        )
+    }
+
+    pub fn item_gallery_cover(locator: &str, id: i32) -> String {
+        path(
+            &ITEM_IMAGE_COVER
+                .replace(":item", locator)
+                .replace(":image", &id.to_string()),
+        )
+    }
+
+    pub fn item_rating(locator: &str, username: &str) -> String {
+        path(
+            &ITEM_RATING
+                .replace(":item", locator)
+                .replace(":user", username),
+        )
+    }
+
+    pub fn item_review(locator: &str) -> String {
+        path(&ITEM_REVIEW.replace(":item", locator))
+    }
+
+    pub fn item_discussion(locator: &str) -> String {
+        path(&ITEM_DISCUSSION.replace(":item", locator))
+    }
+
+    pub fn item_comment(locator: &str, comment: i32) -> String {
+        path(
+            &ITEM_COMMENT
+                .replace(":item", locator)
+                .replace(":comment", &comment.to_string()),
+        )
+    }
+
+    pub fn review_replies(locator: &str, username: &str) -> String {
+        path(
+            &REVIEW_REPLIES
+                .replace(":item", locator)
+                .replace(":user", username),
+        )
+    }
+
+    pub fn review_reply(locator: &str, username: &str, reply: i32) -> String {
+        path(
+            &REVIEW_REPLY
+                .replace(":item", locator)
+                .replace(":user", username)
+                .replace(":reply", &reply.to_string()),
+        )
+    }
+
+    pub fn review_reactions(locator: &str, username: &str) -> String {
+        path(
+            &REVIEW_REACTIONS
+                .replace(":item", locator)
+                .replace(":user", username),
+        )
+    }
+
+    pub fn reply_reactions(locator: &str, username: &str, reply: i32) -> String {
+        path(
+            &REPLY_REACTIONS
+                .replace(":item", locator)
+                .replace(":user", username)
+                .replace(":reply", &reply.to_string()),
+        )
+    }
+
+    pub fn user(username: &str) -> String {
+        path(&USER.replace(":user", username))
+    }
+
+    pub fn user_edit(username: &str) -> String {
+        path(&USER_EDIT.replace(":user", username))
+    }
+
+    pub fn user_remove(username: &str) -> String {
+        path(&USER_REMOVE.replace(":user", username))
+    }
+
+    pub fn user_password_reset(username: &str) -> String {
+        path(&USER_PASSWORD_RESET.replace(":user", username))
+    }
+
+    pub fn user_compatibility(username: &str) -> String {
+        path(&USER_COMPATIBILITY.replace(":user", username))
+    }
+
+    pub fn user_favorites(username: &str) -> String {
+        path(&USER_FAVORITES.replace(":user", username))
+    }
+
+    pub fn user_follow(username: &str) -> String {
+        path(&USER_FOLLOW.replace(":user", username))
+    }
+
+    pub fn user_lists(username: &str) -> String {
+        path(&USER_LISTS.replace(":user", username))
+    }
+
+    pub fn user_list(username: &str, list: &str) -> String {
+        path(&USER_LIST.replace(":user", username).replace(":list", list))
+    }
+
+    pub fn user_list_item(username: &str, list: &str, locator: &str) -> String {
+        path(
+            &USER_LIST_ITEM
+                .replace(":user", username)
+                .replace(":list", list)
+                .replace(":item", locator),
+        )
+    }
+
+    pub fn user_import(username: &str) -> String {
+        path(&USER_IMPORT.replace(":user", username))
+    }
+
+    pub fn user_import_preview(username: &str) -> String {
+        path(&USER_IMPORT_PREVIEW.replace(":user", username))
+    }
+
+    pub fn admin_items(query: &str) -> String {
+        path(&format!("{ADMIN_ITEMS}?{query}"))
+    }
+
+    pub fn items_export(format: &str) -> String {
+        path(&format!("{ITEMS_EXPORT}?format={format}"))
+    }
+
+    pub fn admin_items_csv(query: &str) -> String {
+        path(&format!("{ADMIN_ITEMS_CSV}?{query}"))
+    }
+
+    pub fn admin_category(category: &str) -> String {
+        path(&ADMIN_CATEGORY.replace(":category", category))
+    }
+
+    pub fn item_feature(locator: &str) -> String {
+        path(&ITEM_FEATURE.replace(":item", locator))
+    }
+
+    pub fn chart(month: NaiveDate) -> String {
+        path(&CHART.replace(":month", &month.format("%Y-%m").to_string()))
+    }
+
+    pub fn item_lock(locator: &str) -> String {
+        path(&ITEM_LOCK.replace(":item", locator))
+    }
+
+    pub fn admin_collection(collection: &str) -> String {
+        path(&ADMIN_COLLECTION.replace(":collection", collection))
+    }
+
+    pub fn collection(collection: &str) -> String {
+        path(&COLLECTION.replace(":collection", collection))
+    }
+
+    pub fn collection_items(collection: &str) -> String {
+        path(&COLLECTION_ITEMS.replace(":collection", collection))
+    }
+
+    pub fn collection_item(collection: &str, locator: &str) -> String {
+        path(
+            &COLLECTION_ITEM
+                .replace(":collection", collection)
+                .replace(":item", locator),
+        )
+    }
+
+    pub fn search(target: &str) -> String {
+        path(&format!("{SEARCH}?target={target}"))
+    }
+
+    /// Address of a static asset, carrying its fingerprint once it has been computed.
+    pub fn static_file(file: &str) -> String {
+        match assets::fingerprint_of(file) {
+            Some(fingerprint) => path(&format!("{STATIC}/{file}?v={fingerprint}")),
+            None => path(&format!("{STATIC}/{file}")),
+        }
+    }
+
+    /// Address of an image kept in storage.
+    pub fn image(key: &str) -> String {
+        path(&format!("{IMAGES}/{key}"))
+    }
+
+    /// Address of the cover of an item, a generated one is served for items without a stored
+    /// cover.
+    pub fn item_image(locator: &str, cover: Option<&str>) -> String {
+        image(&images::cover_key(cover.unwrap_or(locator)))
+    }
+
+    pub fn admin_suggestion_approve(id: i32) -> String {
+        path(&ADMIN_SUGGESTION_APPROVE.replace(":suggestion", &id.to_string()))
+    }
+
+    pub fn admin_suggestion_reject(id: i32) -> String {
+        path(&ADMIN_SUGGESTION_REJECT.replace(":suggestion", &id.to_string()))
+    }
+
+    pub fn admin_webhook(id: i32) -> String {
+        path(&ADMIN_WEBHOOK.replace(":webhook", &id.to_string()))
+    }
+
+    pub fn admin_webhook_delivery(id: i32) -> String {
+        path(&ADMIN_WEBHOOK_DELIVERY.replace(":delivery", &id.to_string()))
+    }
+
+    pub fn admin_job_run(job: &str) -> String {
+        path(&ADMIN_JOB_RUN.replace(":job", job))
+    }
+
+    pub fn gallery_image(file: &str) -> String {
+        image(&images::gallery_key(file))
+    }
+
+    pub fn avatar(avatar: &str) -> String {
+        image(&images::avatar_key(avatar))
+    }
+}
@@ -0,0 +1,142 @@
+//! Query language of the item search box. Besides plain words it understands `"quoted phrases"`
+//! that must appear as written and filters such as `tag:rpg`, `category:anime`, `score:>8` or
+//! `reviews:>=10`, which narrow the listing like the filter panel does.
+
+use crate::database::SearchMode;
+
+/// Search box input split into text and filters.
+#[derive(Debug, Default, PartialEq)]
+pub struct Query {
+    pub words: Vec<String>,
+    pub phrases: Vec<String>,
+    pub tag: Option<String>,
+    pub category: Option<String>,
+    /// Lowest average score matched, inclusive.
+    pub min_score: Option<f32>,
+    /// Highest average score matched, inclusive.
+    pub max_score: Option<f32>,
+    pub min_reviews: Option<i64>,
+}
+
+/// Comparison in front of a filter value, `>=` when missing.
+fn split_operator(value: &str) -> (&str, &str) {
+    [">=", "<=", ">", "<", "="]
+        .into_iter()
+        .find_map(|operator| Some((operator, value.strip_prefix(operator)?)))
+        .unwrap_or((">=", value))
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Self {
+        let mut query = Query::default();
+        let mut rest = input.trim_start();
+        while !rest.is_empty() {
+            if let Some(quoted) = rest.strip_prefix('"') {
+                let (phrase, after) = quoted.split_once('"').unwrap_or((quoted, ""));
+                let phrase = phrase.split_whitespace().collect::<Vec<_>>().join(" ");
+                if !phrase.is_empty() {
+                    query.phrases.push(phrase);
+                }
+                rest = after;
+            } else {
+                let end = rest
+                    .find(|c: char| c.is_whitespace() || c == '"')
+                    .unwrap_or(rest.len());
+                let (term, after) = rest.split_at(end);
+                if !query.apply_filter(term) {
+                    query.words.push(term.to_owned());
+                }
+                rest = after;
+            }
+            rest = rest.trim_start();
+        }
+        query
+    }
+
+    /// Takes a `key:value` term as a filter, returning whether it was one.
+    fn apply_filter(&mut self, term: &str) -> bool {
+        let Some((key, value)) = term.split_once(':') else {
+            return false;
+        };
+        match key.to_lowercase().as_str() {
+            "tag" if !value.is_empty() => self.tag = Some(value.to_lowercase()),
+            "category" if !value.is_empty() => self.category = Some(value.to_lowercase()),
+            "score" => {
+                let (operator, value) = split_operator(value);
+                let Some(score) = value
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|score| (0.0..=10.0).contains(score))
+                else {
+                    return false;
+                };
+                match operator {
+                    ">" => self.min_score = Some(score.next_up()),
+                    ">=" => self.min_score = Some(score),
+                    "<" => self.max_score = Some(score.next_down()),
+                    "<=" => self.max_score = Some(score),
+                    _ => (self.min_score, self.max_score) = (Some(score), Some(score)),
+                }
+            }
+            "reviews" => {
+                let (operator, value) = split_operator(value);
+                match (operator, value.parse::<i64>()) {
+                    (">", Ok(count)) if count >= 0 => self.min_reviews = Some(count + 1),
+                    (">=", Ok(count)) if count >= 0 => self.min_reviews = Some(count),
+                    _ => return false,
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Text matched against items, phrases quoted for full-text search.
+    pub fn text(&self, mode: SearchMode) -> Option<String> {
+        let phrases = self.phrases.iter().map(|phrase| match mode {
+            SearchMode::Title => phrase.clone(),
+            SearchMode::FullText => format!("\"{phrase}\""),
+        });
+        let text = self
+            .words
+            .iter()
+            .cloned()
+            .chain(phrases)
+            .collect::<Vec<_>>()
+            .join(" ");
+        Some(text).filter(|text| !text.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_filters_and_phrases() {
+        let query = Query::parse(r#"  tag:RPG "ergo   proxy" score:>8 reviews:>=3 dark "#);
+        assert_eq!(query.words, ["dark"]);
+        assert_eq!(query.phrases, ["ergo proxy"]);
+        assert_eq!(query.tag.as_deref(), Some("rpg"));
+        assert_eq!(query.min_score, Some(8.0f32.next_up()));
+        assert_eq!(query.max_score, None);
+        assert_eq!(query.min_reviews, Some(3));
+        assert_eq!(
+            query.text(SearchMode::Title).as_deref(),
+            Some("dark ergo proxy")
+        );
+        assert_eq!(
+            query.text(SearchMode::FullText).as_deref(),
+            Some(r#"dark "ergo proxy""#)
+        );
+    }
+
+    #[test]
+    fn keeps_malformed_filters_as_words() {
+        let query = Query::parse(r#"score:high reviews:<2 time:12 "unclosed phrase"#);
+        assert_eq!(query.words, ["score:high", "reviews:<2", "time:12"]);
+        assert_eq!(query.phrases, ["unclosed phrase"]);
+        assert_eq!(Query::parse("score:<=5").max_score, Some(5.0));
+        assert_eq!(Query::parse("category:anime").text(SearchMode::Title), None);
+    }
+}
@@ -0,0 +1,66 @@
+//! Headers hardening every response against content injection, MIME sniffing, leaking addresses
+//! to other sites and downgrades to plain HTTP, each configurable per deployment. Every request
+//! gets a fresh nonce that pages mark their scripts and style sheets with and that the
+//! `Content-Security-Policy` allows, so that injected markup cannot run scripts.
+
+use crate::{config, proxy::Origin};
+use axum::{
+    extract::Request,
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use std::future::Future;
+
+tokio::task_local! {
+    static NONCE: String;
+}
+
+/// Nonce of the request being handled, none outside of one, as in tests of single templates.
+pub fn nonce() -> Option<String> {
+    NONCE.try_with(Clone::clone).ok()
+}
+
+/// Runs `future` with the nonce of the request being handled, as the nonce does not follow
+/// futures spawned onto other tasks.
+pub fn with_nonce<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    NONCE.scope(nonce().unwrap_or_default(), future)
+}
+
+/// Handles the request with a fresh nonce and adds the security headers to the response, leaving
+/// those a handler set itself.
+pub async fn add_headers(request: Request, next: Next) -> Response {
+    let config = config::get();
+    let https = config.tls_cert.is_some()
+        || request
+            .extensions()
+            .get::<Origin>()
+            .is_some_and(Origin::is_https);
+    let nonce = BASE64.encode(rand::random::<[u8; 16]>());
+    let mut response = NONCE.scope(nonce.clone(), next.run(request)).await;
+    let headers = response.headers_mut();
+    let policy = config.content_security_policy.replace("{nonce}", &nonce);
+    if let Ok(policy) = HeaderValue::from_str(&policy) {
+        if !policy.is_empty() {
+            headers
+                .entry(header::CONTENT_SECURITY_POLICY)
+                .or_insert(policy);
+        }
+    }
+    headers
+        .entry(header::X_CONTENT_TYPE_OPTIONS)
+        .or_insert(HeaderValue::from_static("nosniff"));
+    if let Ok(policy) = HeaderValue::from_str(&config.referrer_policy) {
+        if !policy.is_empty() {
+            headers.entry(header::REFERRER_POLICY).or_insert(policy);
+        }
+    }
+    if https && config.hsts_max_age > 0 {
+        let max_age = format!("max-age={}", config.hsts_max_age);
+        headers
+            .entry(header::STRICT_TRANSPORT_SECURITY)
+            .or_insert(HeaderValue::from_str(&max_age).unwrap());
+    }
+    response
+}
@@ -0,0 +1,467 @@
+//! Fake but plausible data for local development and demos: users with profiles, items with
+//! placeholder covers and reviews whose ratings agree on which items are good, so that listings,
+//! charts and recommendations have something to show on a fresh instance.
+
+use crate::{
+    cards,
+    database::{self, DatabaseError},
+    forms, images,
+    storage::Storage,
+    svg,
+};
+use argon2::{
+    password_hash::{rand_core::OsRng, SaltString},
+    Argon2, PasswordHasher,
+};
+use image::{codecs::png::PngEncoder, DynamicImage};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use sqlx::{query, query_as, query_scalar, PgPool};
+use std::fmt;
+use tokio::task;
+
+/// Password of the seeded users, the same as that of the test accounts of the initial migration.
+pub const PASSWORD: &str = "password";
+pub const DEFAULT_ITEMS: u32 = 20;
+pub const DEFAULT_USERS: u32 = 10;
+/// Size of the seeded covers in pixels, that of the placeholder they are drawn from.
+const COVER_WIDTH: u32 = 300;
+const COVER_HEIGHT: u32 = 400;
+/// Rounds of drawing new names for the users and items whose names turned out to be taken.
+const ATTEMPTS: usize = 5;
+/// Chance of a seeded user having filled in their profile.
+const PROFILE_CHANCE: f64 = 0.5;
+/// Share of the released items that a seeded user rates at most.
+const MAX_RATED_SHARE: f64 = 0.6;
+/// Chance of a rating coming with a review.
+const REVIEW_CHANCE: f64 = 0.3;
+/// Days back that seeded items are dated at most.
+const MAX_AGE_DAYS: i32 = 730;
+/// Years that seeded items were released before being added at most.
+const MAX_RELEASE_YEARS: i32 = 30;
+
+const FIRST_NAMES: &[&str] = &[
+    "Alice", "Bartek", "Chiara", "Dmitri", "Ewa", "Felix", "Greta", "Hiro", "Ines", "Jakub",
+    "Kasia", "Liam", "Maja", "Noah", "Olga", "Pablo", "Quinn", "Rosa", "Sven", "Tomek", "Uma",
+    "Viktor", "Wanda", "Yuki", "Zofia",
+];
+const LAST_NAMES: &[&str] = &[
+    "Nowak",
+    "Kowalski",
+    "Smith",
+    "Tanaka",
+    "Rossi",
+    "Novak",
+    "Larsen",
+    "Garcia",
+    "Dubois",
+    "Schmidt",
+    "Wisniewska",
+    "Okafor",
+    "Silva",
+    "Murphy",
+    "Lindqvist",
+];
+const CITIES: &[&str] = &[
+    "Warsaw", "Kraków", "Gdańsk", "Berlin", "Prague", "Lisbon", "Osaka", "Toronto", "Dublin",
+    "Oslo", "Lyon", "Seoul",
+];
+const BIOS: &[&str] = &[
+    "Watching everything at least twice.",
+    "Mostly here for the soundtracks.",
+    "Reviewing my backlog one rainy evening at a time.",
+    "Harsh but fair. Mostly harsh.",
+    "If it has robots in it, I have seen it.",
+    "Slowly working through the classics.",
+    "I rate with my heart, not my head.",
+    "Collector of hidden gems and guilty pleasures.",
+];
+
+const ADJECTIVES: &[&str] = &[
+    "Crimson",
+    "Silent",
+    "Broken",
+    "Eternal",
+    "Hollow",
+    "Neon",
+    "Forgotten",
+    "Last",
+    "Wandering",
+    "Shattered",
+    "Golden",
+    "Midnight",
+    "Distant",
+    "Iron",
+    "Paper",
+    "Burning",
+];
+const NOUNS: &[&str] = &[
+    "Horizon",
+    "Requiem",
+    "Garden",
+    "Signal",
+    "Empire",
+    "Tide",
+    "Lantern",
+    "Orbit",
+    "Crown",
+    "Harbor",
+    "Echo",
+    "Frontier",
+    "Labyrinth",
+    "Sparrow",
+    "Engine",
+    "Prophecy",
+];
+const PLACES: &[&str] = &[
+    "Ash",
+    "the North",
+    "Tomorrow",
+    "the Deep",
+    "Glass",
+    "the Lost City",
+    "Seven Moons",
+    "the Old Road",
+];
+const SEQUELS: &[&str] = &["II", "III", "Reborn", "Zero", "Origins"];
+
+const SETTINGS: &[&str] = &[
+    "In a flooded Tokyo,",
+    "Deep beneath the ice of Europa,",
+    "In a village where it never stops raining,",
+    "At a boarding school for young magicians,",
+    "Somewhere along an endless railway,",
+    "In the last days of a dying empire,",
+    "In a quiet seaside town,",
+    "Aboard a generation ship drifting off course,",
+];
+const PROTAGONISTS: &[&str] = &[
+    "a reluctant detective",
+    "an exiled princess",
+    "a retired mercenary",
+    "two estranged siblings",
+    "a high school student",
+    "a washed-up pilot",
+    "an android with no memories",
+    "a small-town baker",
+];
+const INCITEMENTS: &[&str] = &[
+    "stumbles upon",
+    "is hired to track down",
+    "inherits",
+    "must protect",
+    "wakes up next to",
+    "is haunted by",
+];
+const OBJECTS: &[&str] = &[
+    "a map to a city that should not exist",
+    "the last working starship on Earth",
+    "a cursed violin",
+    "a stranger who knows their future",
+    "a letter from their future self",
+    "a sword that chooses its wielder",
+];
+const CLOSERS: &[&str] = &[
+    "What follows is a story about friendship and the price of ambition.",
+    "Nothing is as it seems, and time is running out.",
+    "Their choices will change the fate of everyone around them.",
+    "Along the way, they learn that some secrets are better left buried.",
+    "It is a quiet, funny and often heartbreaking journey.",
+];
+
+const PRAISE: &[&str] = &[
+    "An absolute masterpiece.",
+    "The soundtrack alone is worth it.",
+    "I could not stop thinking about the ending for days.",
+    "Every character gets a moment to shine.",
+    "Gorgeous from the first scene to the last.",
+    "One of the best things I have seen this year.",
+];
+const MIXED: &[&str] = &[
+    "Solid, if a little slow in the middle.",
+    "Great premise, uneven execution.",
+    "The first half is much stronger than the second.",
+    "Worth a watch, but do not expect too much.",
+    "Some brilliant moments buried in a lot of filler.",
+];
+const CRITICISM: &[&str] = &[
+    "I really wanted to like this one.",
+    "The plot falls apart completely at the end.",
+    "Beautiful to look at, but there is nothing underneath.",
+    "Dragged on far longer than it needed to.",
+    "The characters never feel like real people.",
+];
+
+/// What [`seed`] added, fewer than asked for when it kept drawing names that were taken.
+pub struct Seeded {
+    pub users: usize,
+    pub items: usize,
+    pub reviews: u64,
+}
+
+impl fmt::Display for Seeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Added {} users with the password {PASSWORD}, {} items and {} reviews",
+            self.users, self.items, self.reviews
+        )
+    }
+}
+
+struct SeededItem {
+    locator: String,
+    title: String,
+    hue: i16,
+}
+
+struct Released {
+    id: i32,
+    age: i32,
+}
+
+/// Adds `users` users, `items` items with covers, and ratings of the seeded users for all released
+/// items, some of them with reviews.
+pub async fn seed(
+    pool: &PgPool,
+    storage: &dyn Storage,
+    items: u32,
+    users: u32,
+) -> Result<Seeded, DatabaseError> {
+    let mut rng = StdRng::from_entropy();
+    let users = add_users(pool, &mut rng, users as usize).await?;
+    let items = add_items(pool, &mut rng, items as usize).await?;
+    for item in &items {
+        add_cover(pool, storage, item).await?;
+    }
+    let reviews = add_reviews(pool, &mut rng, &users).await?;
+    Ok(Seeded {
+        users: users.len(),
+        items: items.len(),
+        reviews,
+    })
+}
+
+fn pick(rng: &mut StdRng, words: &[&'static str]) -> &'static str {
+    words.choose(rng).unwrap()
+}
+
+fn username(rng: &mut StdRng) -> String {
+    let first = pick(rng, FIRST_NAMES);
+    let last = pick(rng, LAST_NAMES);
+    match rng.gen_range(0..3) {
+        0 => format!("{first}_{last}"),
+        1 => format!("{first}{}", rng.gen_range(1..1000)),
+        _ => format!("{first}{}", &last[..1]),
+    }
+    .to_lowercase()
+}
+
+fn title(rng: &mut StdRng) -> String {
+    let adjective = pick(rng, ADJECTIVES);
+    let noun = pick(rng, NOUNS);
+    match rng.gen_range(0..4) {
+        0 => format!("{adjective} {noun}"),
+        1 => format!("The {noun} of {}", pick(rng, PLACES)),
+        2 => format!("{}: {adjective} {noun}", pick(rng, NOUNS)),
+        _ => format!("{adjective} {noun} {}", pick(rng, SEQUELS)),
+    }
+}
+
+fn description(rng: &mut StdRng) -> String {
+    format!(
+        "{} {} {} {}. {}",
+        pick(rng, SETTINGS),
+        pick(rng, PROTAGONISTS),
+        pick(rng, INCITEMENTS),
+        pick(rng, OBJECTS),
+        pick(rng, CLOSERS)
+    )
+}
+
+/// Body of a review agreeing with its `rating`.
+fn review(rng: &mut StdRng, rating: i16) -> String {
+    let sentences = match rating {
+        8.. => PRAISE,
+        5..=7 => MIXED,
+        _ => CRITICISM,
+    };
+    let count = rng.gen_range(1..=2);
+    sentences
+        .choose_multiple(rng, count)
+        .copied()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+async fn add_users(
+    pool: &PgPool,
+    rng: &mut StdRng,
+    count: usize,
+) -> Result<Vec<i32>, DatabaseError> {
+    let password_hash = Argon2::default()
+        .hash_password(PASSWORD.as_bytes(), &SaltString::generate(&mut OsRng))
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
+        .to_string();
+    let mut ids = Vec::with_capacity(count);
+    for _ in 0..ATTEMPTS {
+        let missing = count - ids.len();
+        if missing == 0 {
+            break;
+        }
+        let mut usernames = Vec::with_capacity(missing);
+        let mut bios = Vec::with_capacity(missing);
+        let mut locations = Vec::with_capacity(missing);
+        for _ in 0..missing {
+            usernames.push(username(rng));
+            let profile = rng.gen_bool(PROFILE_CHANCE);
+            bios.push(profile.then(|| pick(rng, BIOS).to_owned()));
+            locations.push(profile.then(|| pick(rng, CITIES).to_owned()));
+        }
+        ids.extend(
+            query_scalar!(
+                "INSERT INTO users(username, password_hash, bio, location) SELECT u.username, $2, u.bio, u.location FROM UNNEST($1::VARCHAR[], $3::VARCHAR[], $4::VARCHAR[]) AS u(username, bio, location) ON CONFLICT (username) DO NOTHING RETURNING id",
+                &usernames,
+                password_hash,
+                &bios as &[Option<String>],
+                &locations as &[Option<String>]
+            )
+            .fetch_all(pool)
+            .await
+            .map_err(|e| DatabaseError::InternalError(Box::new(e)))?,
+        );
+    }
+    Ok(ids)
+}
+
+async fn add_items(
+    pool: &PgPool,
+    rng: &mut StdRng,
+    count: usize,
+) -> Result<Vec<SeededItem>, DatabaseError> {
+    let categories = query_scalar!("SELECT id FROM categories")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    let mut items = Vec::with_capacity(count);
+    for _ in 0..ATTEMPTS {
+        let missing = count - items.len();
+        if missing == 0 {
+            break;
+        }
+        let mut titles = Vec::with_capacity(missing);
+        let mut locators = Vec::with_capacity(missing);
+        let mut descriptions = Vec::with_capacity(missing);
+        let mut ages = Vec::with_capacity(missing);
+        let mut releases = Vec::with_capacity(missing);
+        let mut category_ids = Vec::with_capacity(missing);
+        for _ in 0..missing {
+            let title = title(rng);
+            locators.push(forms::unused_locator(
+                &forms::locator_from_title(&title),
+                &[],
+            ));
+            titles.push(title);
+            descriptions.push(description(rng));
+            ages.push(rng.gen_range(0..=MAX_AGE_DAYS));
+            releases.push(rng.gen_range(0..=MAX_RELEASE_YEARS * 365));
+            category_ids.push(categories.choose(rng).copied());
+        }
+        items.extend(
+            query_as!(
+                SeededItem,
+                r#"INSERT INTO items(locator, title, description, created, release_date, category_id) SELECT i.locator, i.title, i.description, now() - make_interval(days => i.age), (now() - make_interval(days => i.age + i.release))::DATE, i.category_id FROM UNNEST($1::VARCHAR[], $2::VARCHAR[], $3::TEXT[], $4::INT[], $5::INT[], $6::INT[]) AS i(locator, title, description, age, release, category_id) ON CONFLICT (locator) DO NOTHING RETURNING locator, title, get_hue(title) AS "hue!""#,
+                &locators,
+                &titles,
+                &descriptions,
+                &ages,
+                &releases,
+                &category_ids as &[Option<i32>]
+            )
+            .fetch_all(pool)
+            .await
+            .map_err(|e| DatabaseError::InternalError(Box::new(e)))?,
+        );
+    }
+    Ok(items)
+}
+
+/// Stores the placeholder cover of `item` as its actual cover, so that it shows in the places
+/// that only show stored images.
+async fn add_cover(
+    pool: &PgPool,
+    storage: &dyn Storage,
+    item: &SeededItem,
+) -> Result<(), DatabaseError> {
+    let svg = svg::cover(&item.title, item.hue).into_string();
+    let cover = task::spawn_blocking(move || {
+        let cover = cards::rasterize(&svg, COVER_WIDTH, COVER_HEIGHT)?;
+        let mut encoded = Vec::new();
+        DynamicImage::ImageRgba8(cover)
+            .to_rgb8()
+            .write_with_encoder(PngEncoder::new(&mut encoded))
+            .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+        Ok::<_, DatabaseError>(encoded)
+    })
+    .await
+    .map_err(|e| DatabaseError::InternalError(Box::new(e)))??;
+    let name = images::name(&cover);
+    images::store(
+        storage,
+        vec![
+            (images::gallery_key(&name), cover.clone().into()),
+            (images::cover_key(&name), cover.into()),
+        ],
+    )
+    .await?;
+    database::add_item_image(pool, &item.locator, &name, true).await
+}
+
+/// Rates a random share of the released items by each of `users`. Every item gets a quality and
+/// every user a bias, which the ratings stray from only a little, so that the seeded users mostly
+/// agree and their ratings make for meaningful charts and recommendations.
+async fn add_reviews(pool: &PgPool, rng: &mut StdRng, users: &[i32]) -> Result<u64, DatabaseError> {
+    let released = query_as!(
+        Released,
+        r#"SELECT id, EXTRACT(DAY FROM now() - created)::INT AS "age!" FROM items WHERE NOT unreleased"#
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    if released.is_empty() {
+        return Ok(0);
+    }
+    let quality: Vec<f64> = released.iter().map(|_| rng.gen_range(3.0..9.0)).collect();
+    let max_rated = ((released.len() as f64 * MAX_RATED_SHARE) as usize).max(1);
+    let indices: Vec<usize> = (0..released.len()).collect();
+    let mut item_ids = Vec::new();
+    let mut user_ids = Vec::new();
+    let mut ratings = Vec::new();
+    let mut bodies = Vec::new();
+    let mut ages = Vec::new();
+    for &user in users {
+        let bias = rng.gen_range(-1.5..1.5);
+        let count = rng.gen_range(1..=max_rated);
+        for &i in indices.choose_multiple(rng, count) {
+            let rating = (quality[i] + bias + rng.gen_range(-1.5..1.5))
+                .round()
+                .clamp(1.0, 10.0) as i16;
+            item_ids.push(released[i].id);
+            user_ids.push(user);
+            ratings.push(rating);
+            bodies.push(rng.gen_bool(REVIEW_CHANCE).then(|| review(rng, rating)));
+            ages.push(rng.gen_range(0..=released[i].age.clamp(0, MAX_AGE_DAYS)));
+        }
+    }
+    Ok(query!(
+        "INSERT INTO reviews(item_id, user_id, rating, body, date) SELECT r.item_id, r.user_id, r.rating, r.body, now() - make_interval(days => r.age, secs => random() * 86400) FROM UNNEST($1::INT[], $2::INT[], $3::SMALLINT[], $4::TEXT[], $5::INT[]) AS r(item_id, user_id, rating, body, age) ON CONFLICT (item_id, user_id) DO NOTHING",
+        &item_ids,
+        &user_ids,
+        &ratings,
+        &bodies as &[Option<String>],
+        &ages
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| DatabaseError::InternalError(Box::new(e)))?
+    .rows_affected())
+}
@@ -0,0 +1,205 @@
+//! Listeners the app is served on. Plain HTTP by default, or HTTPS terminated in process with
+//! rustls when `tls_cert` and `tls_key` are set, so that small deployments need no reverse proxy in
+//! front. Serving HTTPS, a plain HTTP listener on `http_redirect_port` sends browsers over to it.
+//! A proxy on the same host can instead connect over a Unix socket at `unix_socket`.
+
+use crate::config::{self, Config};
+use axum::{
+    http::{header, uri::Authority, HeaderMap, StatusCode, Uri},
+    response::{IntoResponse, Redirect, Response},
+    Router,
+};
+use axum_server::{tls_rustls::RustlsConfig, Handle};
+use std::{io, net::SocketAddr};
+use tokio::{net::TcpListener, signal};
+
+/// Serves `app` until SIGINT or SIGTERM, then waits for the requests in flight to finish.
+pub async fn serve(app: Router, config: &Config) -> io::Result<()> {
+    #[cfg(unix)]
+    if let Some(path) = &config.unix_socket {
+        return unix::serve(app, path, config.unix_socket_mode).await;
+    }
+    let address = SocketAddr::new(config.bind_address, config.port);
+    let app = app.into_make_service_with_connect_info::<SocketAddr>();
+    let (Some(cert), Some(key)) = (&config.tls_cert, &config.tls_key) else {
+        let listener = TcpListener::bind(address).await?;
+        return axum::serve(listener, app)
+            .with_graceful_shutdown(shutdown_signal())
+            .await;
+    };
+    let tls = RustlsConfig::from_pem_file(cert, key).await?;
+    let handle = Handle::new();
+    tokio::spawn({
+        let handle = handle.clone();
+        async move {
+            shutdown_signal().await;
+            handle.graceful_shutdown(None);
+        }
+    });
+    let https = axum_server::bind_rustls(address, tls)
+        .handle(handle.clone())
+        .serve(app);
+    match config.http_redirect_port {
+        Some(port) => {
+            let redirect = Router::new().fallback(redirect_to_https);
+            let http = axum_server::bind(SocketAddr::new(config.bind_address, port))
+                .handle(handle)
+                .serve(redirect.into_make_service());
+            tokio::try_join!(https, http).map(|_| ())
+        }
+        None => https.await,
+    }
+}
+
+/// Resolves on SIGINT or SIGTERM, after which the server stops accepting connections and waits
+/// for the requests in flight to finish.
+async fn shutdown_signal() {
+    let interrupt = async {
+        signal::ctrl_c().await.unwrap();
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .unwrap()
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = interrupt => {},
+        _ = terminate => {},
+    }
+    eprintln!("Shutting down, waiting for requests in flight");
+}
+
+#[cfg(unix)]
+mod unix {
+    use super::shutdown_signal;
+    use crate::{config::Mode, proxy::UnixSocket};
+    use axum::{Extension, Router};
+    use hyper_util::{
+        rt::{TokioExecutor, TokioIo},
+        server::{conn::auto, graceful::GracefulShutdown},
+        service::TowerToHyperService,
+    };
+    use std::{
+        fs::{self, DirBuilder, Permissions},
+        io,
+        os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt},
+        path::{Path, PathBuf},
+        pin::pin,
+        process,
+    };
+    use tokio::net::UnixListener;
+
+    /// Serves `app` on a Unix socket at `path` with permissions `mode`, until SIGINT or SIGTERM.
+    pub async fn serve(app: Router, path: &Path, mode: Mode) -> io::Result<()> {
+        let listener = bind(path, mode)?;
+        let app = app.layer(Extension(UnixSocket));
+        let builder = auto::Builder::new(TokioExecutor::new());
+        let graceful = GracefulShutdown::new();
+        let mut shutdown = pin!(shutdown_signal());
+        loop {
+            let stream = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        eprintln!("Failed to accept a connection: {e}");
+                        continue;
+                    }
+                },
+                _ = &mut shutdown => break,
+            };
+            let connection = builder
+                .serve_connection_with_upgrades(
+                    TokioIo::new(stream),
+                    TowerToHyperService::new(app.clone()),
+                )
+                .into_owned();
+            // Errors are those of clients going away mid request.
+            tokio::spawn(graceful.watch(connection));
+        }
+        drop(listener);
+        let _ = fs::remove_file(path);
+        graceful.shutdown().await;
+        Ok(())
+    }
+
+    /// Listens on a socket at `path`, replacing the one left by an earlier run. The socket is
+    /// created in a directory only the app can enter and moved out once its permissions are set,
+    /// so that nobody can connect before that.
+    fn bind(path: &Path, mode: Mode) -> io::Result<UnixListener> {
+        match fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.file_type().is_socket() => fs::remove_file(path)?,
+            Ok(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} exists and is not a socket", path.display()),
+                ))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        let mut private = path.as_os_str().to_owned();
+        private.push(format!(".{}", process::id()));
+        let private = PathBuf::from(private);
+        let _ = fs::remove_dir_all(&private);
+        DirBuilder::new().mode(0o700).create(&private)?;
+        let staged = private.join("socket");
+        let bound = UnixListener::bind(&staged).and_then(|listener| {
+            fs::set_permissions(&staged, Permissions::from_mode(mode.0))?;
+            fs::rename(&staged, path)?;
+            Ok(listener)
+        });
+        let _ = fs::remove_dir_all(&private);
+        bound
+    }
+}
+
+/// Sends a request made over plain HTTP to the same address over HTTPS.
+async fn redirect_to_https(headers: HeaderMap, uri: Uri) -> Response {
+    let host = headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok());
+    let path = uri
+        .path_and_query()
+        .map_or("/", |path_and_query| path_and_query.as_str());
+    match host.and_then(|host| https_url(host, config::get().port, path)) {
+        Some(url) => Redirect::permanent(&url).into_response(),
+        None => StatusCode::BAD_REQUEST.into_response(),
+    }
+}
+
+/// Address of `path` over HTTPS on `port` of the host named in a `Host` header, whatever port the
+/// header names.
+fn https_url(host: &str, port: u16, path: &str) -> Option<String> {
+    let host = host.parse::<Authority>().ok()?;
+    let host = host.host();
+    Some(match port {
+        443 => format!("https://{host}{path}"),
+        port => format!("https://{host}:{port}{path}"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redirects_to_the_https_port() {
+        assert_eq!(
+            https_url("example.com", 443, "/zai/items?page=2").as_deref(),
+            Some("https://example.com/zai/items?page=2")
+        );
+        assert_eq!(
+            https_url("example.com:8080", 8443, "/").as_deref(),
+            Some("https://example.com:8443/")
+        );
+        assert_eq!(
+            https_url("[::1]:80", 443, "/").as_deref(),
+            Some("https://[::1]/")
+        );
+        assert_eq!(https_url("exa mple.com", 443, "/"), None);
+    }
+}
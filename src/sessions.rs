@@ -0,0 +1,189 @@
+use crate::{
+    cache::QueryCache,
+    database::{self, DatabaseError, User},
+};
+use async_trait::async_trait;
+use axum::{
+    extract::{FromRequestParts, Request, State},
+    http::{request::Parts, StatusCode},
+    middleware::Next,
+    response::Response,
+    Extension,
+};
+use axum_session::{Session, SessionNullPool};
+use sqlx::PgPool;
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::{Arc, Mutex},
+};
+use tracing::warn;
+
+/// Per user counters, bumped to log the user out of every session. They are keyed by user id like
+/// the sessions, so that renaming an account does not bring revoked sessions back. Sessions are
+/// only kept in memory, so the counters are too.
+#[derive(Default)]
+pub struct Revocations {
+    generations: Mutex<HashMap<i32, u64>>,
+}
+
+impl Revocations {
+    pub fn generation(&self, id: i32) -> u64 {
+        self.generations
+            .lock()
+            .unwrap()
+            .get(&id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Invalidates every session the user is currently logged in with.
+    pub fn revoke(&self, id: i32) {
+        *self.generations.lock().unwrap().entry(id).or_default() += 1;
+    }
+}
+
+/// Logs the user in, remembering which sessions of theirs are still valid. Only the id of the user
+/// is kept in the session, the user is loaded afresh on every request by [`load_user`].
+pub async fn log_in(
+    pool: &PgPool,
+    session: &Session<SessionNullPool>,
+    revocations: &Revocations,
+    user: &User,
+) -> Result<(), DatabaseError> {
+    let id = database::get_user_id(pool, &user.username)
+        .await?
+        .ok_or(DatabaseError::IncorrectCredentials)?;
+    session.set("user_id", id);
+    session.set("generation", revocations.generation(id));
+    session.remove("infinite_scroll");
+    Ok(())
+}
+
+/// Loads the user of the session, so that renames, demotions and deletions apply to their next
+/// request, and logs out sessions that were revoked since they were logged in or whose user was
+/// deleted. While the database is unreachable, the request is served as if logged out.
+pub async fn load_user(
+    State(pool): State<PgPool>,
+    Extension(cache): Extension<Arc<QueryCache>>,
+    Extension(revocations): Extension<Arc<Revocations>>,
+    session: Session<SessionNullPool>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let user = match session.get::<i32>("user_id") {
+        Some(id) => match cache.user(&pool, id).await {
+            Ok(Some(user))
+                if session.get::<u64>("generation").unwrap_or_default()
+                    >= revocations.generation(id) =>
+            {
+                Some(user)
+            }
+            Ok(_) => {
+                session.clear();
+                None
+            }
+            Err(e) => {
+                warn!(error = ?e, "failed to load the user of a session");
+                None
+            }
+        },
+        None => None,
+    };
+    request.extensions_mut().insert(CurrentUser(user));
+    next.run(request).await
+}
+
+/// The user the request was made by, if logged in.
+#[derive(Clone)]
+pub struct CurrentUser(pub Option<User>);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for CurrentUser {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .extensions
+            .get::<CurrentUser>()
+            .cloned()
+            .unwrap_or(CurrentUser(None)))
+    }
+}
+
+/// The logged in user. Handlers taking it answer visitors with a 401 without running.
+pub struct AuthUser(pub User);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AuthUser {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Ok(CurrentUser(user)) = CurrentUser::from_request_parts(parts, state).await;
+        user.map(AuthUser).ok_or(StatusCode::UNAUTHORIZED)
+    }
+}
+
+/// The logged in admin. Handlers taking it answer everyone else with a 403 without running.
+pub struct AdminUser(pub User);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AdminUser {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Ok(CurrentUser(user)) = CurrentUser::from_request_parts(parts, state).await;
+        user.filter(|user| user.is_admin)
+            .map(AdminUser)
+            .ok_or(StatusCode::FORBIDDEN)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn revoking_bumps_only_that_user() {
+        let revocations = Revocations::default();
+        revocations.revoke(1);
+        revocations.revoke(1);
+        assert_eq!(revocations.generation(1), 2);
+        assert_eq!(revocations.generation(2), 0);
+    }
+
+    #[tokio::test]
+    async fn lets_only_admins_through_as_admins() {
+        let user = |is_admin| User {
+            username: "test1".to_owned(),
+            is_admin,
+            avatar_hue: 0,
+            avatar: None,
+            avatar_glyph: String::new(),
+        };
+        let parts = |user: Option<User>| {
+            let (mut parts, _) = Request::new(()).into_parts();
+            parts.extensions.insert(CurrentUser(user));
+            parts
+        };
+        assert!(AuthUser::from_request_parts(&mut parts(None), &())
+            .await
+            .is_err());
+        assert!(
+            AuthUser::from_request_parts(&mut parts(Some(user(false))), &())
+                .await
+                .is_ok()
+        );
+        assert_eq!(
+            AdminUser::from_request_parts(&mut parts(Some(user(false))), &())
+                .await
+                .err(),
+            Some(StatusCode::FORBIDDEN)
+        );
+        assert!(
+            AdminUser::from_request_parts(&mut parts(Some(user(true))), &())
+                .await
+                .is_ok()
+        );
+    }
+}
@@ -0,0 +1,28 @@
+use crate::database::{self, DatabaseError, InstanceStats};
+use sqlx::PgPool;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// How long instance statistics are served before being recounted.
+const TTL: Duration = Duration::from_secs(600);
+
+/// Instance statistics, recounted at most once per [`TTL`] since the aggregate queries scan
+/// every review.
+#[derive(Default)]
+pub struct StatsCache {
+    cached: Mutex<Option<(Instant, InstanceStats)>>,
+}
+
+impl StatsCache {
+    pub async fn get(&self, pool: &PgPool) -> Result<InstanceStats, DatabaseError> {
+        let mut cached = self.cached.lock().await;
+        match &*cached {
+            Some((counted, stats)) if counted.elapsed() < TTL => Ok(stats.clone()),
+            _ => {
+                let stats = database::get_instance_stats(pool).await?;
+                *cached = Some((Instant::now(), stats.clone()));
+                Ok(stats)
+            }
+        }
+    }
+}
@@ -4,7 +4,7 @@ pub fn logo() -> Markup {
     html! {
         svg xmlns="http://www.w3.org/2000/svg" fill="white" viewBox="0 0 9.2257929 2.3518026" class="size-full" {
             g transform="translate(-51.777543,-94.905347)" {
-                path d="m 51.777545,97.216843 0.47594,-2.27273 h 0.466638 l -0.395324,1.892908 h 1.156518 l -0.07906,0.379822 z m 2.074292,-0.891419 q 0,-0.201538 0.06046,-0.42478 0.07906,-0.299207 0.240295,-0.519348 0.162781,-0.220142 0.409277,-0.347266 0.246497,-0.128674 0.561206,-0.128674 0.42168,0 0.680579,0.261999 0.260449,0.262 0.260449,0.694532 0,0.359668 -0.168982,0.696081 -0.168982,0.336414 -0.458886,0.517798 -0.289905,0.181384 -0.655774,0.181384 -0.31781,0 -0.533301,-0.144177 -0.215491,-0.144177 -0.305408,-0.356567 -0.08992,-0.213941 -0.08992,-0.430982 z m 0.461987,-0.0093 q 0,0.234095 0.142627,0.392225 0.142627,0.158129 0.375171,0.158129 0.189136,0 0.362769,-0.124023 0.175183,-0.125574 0.288354,-0.378271 0.114722,-0.254249 0.114722,-0.494544 0,-0.268201 -0.144177,-0.420129 -0.144177,-0.153479 -0.36742,-0.153479 -0.342614,0 -0.558105,0.31936 -0.213941,0.31936 -0.213941,0.700732 z m 3.125391,-0.313159 h 1.016992 L 58.251569,96.9781 q -0.179834,0.116272 -0.441834,0.196887 -0.260449,0.08061 -0.53175,0.08061 -0.42168,0 -0.646472,-0.190686 -0.306958,-0.260449 -0.306958,-0.754993 0,-0.331762 0.131774,-0.63562 0.15813,-0.365869 0.449585,-0.567407 0.291455,-0.201538 0.68523,-0.201538 0.393774,0 0.630969,0.182934 0.238745,0.182935 0.31626,0.533301 l -0.435632,0.04961 q -0.05736,-0.192237 -0.186036,-0.289905 -0.127124,-0.09767 -0.32091,-0.09767 -0.226343,0 -0.415479,0.117823 -0.189135,0.117822 -0.296106,0.364318 -0.10697,0.246497 -0.10697,0.537952 0,0.286804 0.130225,0.42478 0.130224,0.136426 0.381372,0.136426 0.150378,0 0.308508,-0.04186 0.15813,-0.04341 0.272852,-0.102319 l 0.06976,-0.337964 h -0.57981 z m 1.351855,0.322461 q 0,-0.201538 0.06046,-0.42478 0.07906,-0.299207 0.240295,-0.519348 0.162781,-0.220142 0.409278,-0.347266 0.246496,-0.128674 0.561206,-0.128674 0.421679,0 0.680578,0.261999 0.260449,0.262 0.260449,0.694532 0,0.359668 -0.168982,0.696081 -0.168981,0.336414 -0.458886,0.517798 -0.289905,0.181384 -0.655774,0.181384 -0.31781,0 -0.533301,-0.144177 -0.215491,-0.144177 -0.305408,-0.356567 -0.08992,-0.213941 -0.08992,-0.430982 z m 0.461988,-0.0093 q 0,0.234095 0.142627,0.392225 0.142627,0.158129 0.375171,0.158129 0.189135,0 0.362768,-0.124023 0.175183,-0.125574 0.288355,-0.378271 0.114721,-0.254249 0.114721,-0.494544 0,-0.268201 -0.144177,-0.420129 -0.144177,-0.153479 -0.367419,-0.153479 -0.342615,0 -0.558106,0.31936 -0.21394,0.31936 -0.21394,0.700732 z";
+                path d="m 51.777545,97.216843 0.47594,-2.27273 h 0.466638 l -0.395324,1.892908 h 1.156518 l -0.07906,0.379822 z m 2.074292,-0.891419 q 0,-0.201538 0.06046,-0.42478 0.07906,-0.299207 0.240295,-0.519348 0.162781,-0.220142 0.409277,-0.347266 0.246497,-0.128674 0.561206,-0.128674 0.42168,0 0.680579,0.261999 0.260449,0.262 0.260449,0.694532 0,0.359668 -0.168982,0.696081 -0.168982,0.336414 -0.458886,0.517798 -0.289905,0.181384 -0.655774,0.181384 -0.31781,0 -0.533301,-0.144177 -0.215491,-0.144177 -0.305408,-0.356567 -0.08992,-0.213941 -0.08992,-0.430982 z m 0.461987,-0.0093 q 0,0.234095 0.142627,0.392225 0.142627,0.158129 0.375171,0.158129 0.189136,0 0.362769,-0.124023 0.175183,-0.125574 0.288354,-0.378271 0.114722,-0.254249 0.114722,-0.494544 0,-0.268201 -0.144177,-0.420129 -0.144177,-0.153479 -0.36742,-0.153479 -0.342614,0 -0.558105,0.31936 -0.213941,0.31936 -0.213941,0.700732 z m 3.125391,-0.313159 h 1.016992 L 58.251569,96.9781 q -0.179834,0.116272 -0.441834,0.196887 -0.260449,0.08061 -0.53175,0.08061 -0.42168,0 -0.646472,-0.190686 -0.306958,-0.260449 -0.306958,-0.754993 0,-0.331762 0.131774,-0.63562 0.15813,-0.365869 0.449585,-0.567407 0.291455,-0.201538 0.68523,-0.201538 0.393774,0 0.630969,0.182934 0.238745,0.182935 0.31626,0.533301 l -0.435632,0.04961 q -0.05736,-0.192237 -0.186036,-0.289905 -0.127124,-0.09767 -0.32091,-0.09767 -0.226343,0 -0.415479,0.117823 -0.189135,0.117822 -0.296106,0.364318 -0.10697,0.246497 -0.10697,0.537952 0,0.286804 0.130225,0.42478 0.130224,0.136426 0.381372,0.136426 0.150378,0 0.308508,-0.04186 0.15813,-0.04341 0.272852,-0.102319 l 0.06976,-0.337964 h -0.57981 z m 1.351855,0.322461 q 0,-0.201538 0.06046,-0.42478 0.07906,-0.299207 0.240295,-0.519348 0.162781,-0.220142 0.409278,-0.347266 0.246496,-0.128674 0.561206,-0.128674 0.421679,0 0.680578,0.261999 0.260449,0.262 0.260449,0.694532 0,0.359668 -0.168982,0.696081 -0.168981,0.336414 -0.458886,0.517798 -0.289905,0.181384 -0.655774,0.181384 -0.31781,0 -0.533301,-0.144177 -0.215491,-0.144177 -0.305408,-0.356567 -0.08992,-0.213941 -0.08992,-0.430982 z m 0.461988,-0.0093 q 0,0.234095 0.142627,0.392225 0.142627,0.158129 0.375171,0.158129 0.189135,0 0.362768,-0.124023 0.175183,-0.125574 0.288355,-0.378271 0.114721,-0.254249 0.114721,-0.494544 0,-0.268201 -0.144177,-0.420129 -0.144177,-0.153479 -0.367419,-0.153479 -0.342615,0 -0.558106,0.31936 -0.21394,0.31936 -0.21394,0.700732 z" {}
             }
         }
     }
@@ -13,7 +13,7 @@ pub fn logo() -> Markup {
 pub fn left_arrow() -> Markup {
     html! {
         svg xmlns="http://www.w3.org/2000/svg" fill="none" viewBox="0 0 24 24" stroke-width="1.5" stroke="currentColor" class="size-full" {
-            path stroke-linecap="round" stroke-linejoin="round" d="M15.75 19.5 8.25 12l7.5-7.5";
+            path stroke-linecap="round" stroke-linejoin="round" d="M15.75 19.5 8.25 12l7.5-7.5" {}
         }
     }
 }
@@ -21,7 +21,7 @@ pub fn left_arrow() -> Markup {
 pub fn right_arrow() -> Markup {
     html! {
         svg xmlns="http://www.w3.org/2000/svg" fill="none" viewBox="0 0 24 24" stroke-width="1.5" stroke="currentColor" class="size-full" {
-            path stroke-linecap="round" stroke-linejoin="round" d="m8.25 4.5 7.5 7.5-7.5 7.5";
+            path stroke-linecap="round" stroke-linejoin="round" d="m8.25 4.5 7.5 7.5-7.5 7.5" {}
         }
     }
 }
@@ -29,7 +29,7 @@ pub fn right_arrow() -> Markup {
 pub fn user() -> Markup {
     html! {
         svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24" fill="currentColor" class="aspect-square size-full" {
-          path fill-rule="evenodd" d="M7.5 6a4.5 4.5 0 1 1 9 0 4.5 4.5 0 0 1-9 0ZM3.751 20.105a8.25 8.25 0 0 1 16.498 0 .75.75 0 0 1-.437.695A18.683 18.683 0 0 1 12 22.5c-2.786 0-5.433-.608-7.812-1.7a.75.75 0 0 1-.437-.695Z" clip-rule="evenodd";
+          path fill-rule="evenodd" d="M7.5 6a4.5 4.5 0 1 1 9 0 4.5 4.5 0 0 1-9 0ZM3.751 20.105a8.25 8.25 0 0 1 16.498 0 .75.75 0 0 1-.437.695A18.683 18.683 0 0 1 12 22.5c-2.786 0-5.433-.608-7.812-1.7a.75.75 0 0 1-.437-.695Z" clip-rule="evenodd" {}
         }
     }
 }
@@ -37,7 +37,7 @@ pub fn user() -> Markup {
 pub fn star_left() -> Markup {
     html! {
         svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 12 24" fill="currentColor" class="aspect-[1/2] size-full" {
-            path fill-rule="evenodd" d="M10.788 3.21c.448-1.077 1.976-1.077 2.424 0l2.082 5.006 5.404.434c1.164.093 1.636 1.545.749 2.305l-4.117 3.527 1.257 5.273c.271 1.136-.964 2.033-1.96 1.425L12 18.354 7.373 21.18c-.996.608-2.231-.29-1.96-1.425l1.257-5.273-4.117-3.527c-.887-.76-.415-2.212.749-2.305l5.404-.434 2.082-5.005Z" clip-rule="evenodd";
+            path fill-rule="evenodd" d="M10.788 3.21c.448-1.077 1.976-1.077 2.424 0l2.082 5.006 5.404.434c1.164.093 1.636 1.545.749 2.305l-4.117 3.527 1.257 5.273c.271 1.136-.964 2.033-1.96 1.425L12 18.354 7.373 21.18c-.996.608-2.231-.29-1.96-1.425l1.257-5.273-4.117-3.527c-.887-.76-.415-2.212.749-2.305l5.404-.434 2.082-5.005Z" clip-rule="evenodd" {}
         }
     }
 }
@@ -45,7 +45,191 @@ pub fn star_left() -> Markup {
 pub fn star_right() -> Markup {
     html! {
         svg xmlns="http://www.w3.org/2000/svg" viewBox="12 0 12 24" fill="currentColor" class="aspect-[1/2] size-full" {
-            path fill-rule="evenodd" d="M10.788 3.21c.448-1.077 1.976-1.077 2.424 0l2.082 5.006 5.404.434c1.164.093 1.636 1.545.749 2.305l-4.117 3.527 1.257 5.273c.271 1.136-.964 2.033-1.96 1.425L12 18.354 7.373 21.18c-.996.608-2.231-.29-1.96-1.425l1.257-5.273-4.117-3.527c-.887-.76-.415-2.212.749-2.305l5.404-.434 2.082-5.005Z" clip-rule="evenodd";
+            path fill-rule="evenodd" d="M10.788 3.21c.448-1.077 1.976-1.077 2.424 0l2.082 5.006 5.404.434c1.164.093 1.636 1.545.749 2.305l-4.117 3.527 1.257 5.273c.271 1.136-.964 2.033-1.96 1.425L12 18.354 7.373 21.18c-.996.608-2.231-.29-1.96-1.425l1.257-5.273-4.117-3.527c-.887-.76-.415-2.212.749-2.305l5.404-.434 2.082-5.005Z" clip-rule="evenodd" {}
         }
     }
 }
+
+pub fn heart() -> Markup {
+    html! {
+        svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24" fill="currentColor" class="aspect-square size-full" {
+            path d="m11.645 20.91-.007-.003-.022-.012a15.247 15.247 0 0 1-.383-.218 25.18 25.18 0 0 1-4.244-3.17C4.688 15.36 2.25 12.174 2.25 8.25 2.25 5.322 4.714 3 7.688 3A5.5 5.5 0 0 1 12 5.052 5.5 5.5 0 0 1 16.313 3c2.973 0 5.437 2.322 5.437 5.25 0 3.925-2.438 7.111-4.739 9.256a25.175 25.175 0 0 1-4.244 3.17 15.247 15.247 0 0 1-.383.219l-.022.012-.007.004-.003.001a.752.752 0 0 1-.704 0l-.003-.001Z" {}
+        }
+    }
+}
+
+pub fn star() -> Markup {
+    html! {
+        svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24" fill="currentColor" class="aspect-square size-full" {
+            path fill-rule="evenodd" d="M10.788 3.21c.448-1.077 1.976-1.077 2.424 0l2.082 5.006 5.404.434c1.164.093 1.636 1.545.749 2.305l-4.117 3.527 1.257 5.273c.271 1.136-.964 2.033-1.96 1.425L12 18.354 7.373 21.18c-.996.608-2.231-.29-1.96-1.425l1.257-5.273-4.117-3.527c-.887-.76-.415-2.212.749-2.305l5.404-.434 2.082-5.005Z" clip-rule="evenodd" {}
+        }
+    }
+}
+
+pub fn bolt() -> Markup {
+    html! {
+        svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24" fill="currentColor" class="aspect-square size-full" {
+            path fill-rule="evenodd" d="M14.615 1.595a.75.75 0 0 1 .359.852L12.982 9.75h7.268a.75.75 0 0 1 .548 1.262l-10.5 11.25a.75.75 0 0 1-1.272-.71l1.992-7.302H3.75a.75.75 0 0 1-.548-1.262l10.5-11.25a.75.75 0 0 1 .913-.143Z" clip-rule="evenodd" {}
+        }
+    }
+}
+
+pub fn moon() -> Markup {
+    html! {
+        svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24" fill="currentColor" class="aspect-square size-full" {
+            path fill-rule="evenodd" d="M9.528 1.718a.75.75 0 0 1 .162.819A8.97 8.97 0 0 0 9 6a9 9 0 0 0 9 9 8.97 8.97 0 0 0 3.463-.69.75.75 0 0 1 .981.98 10.503 10.503 0 0 1-9.694 6.46c-5.799 0-10.5-4.7-10.5-10.5 0-4.368 2.667-8.112 6.46-9.694a.75.75 0 0 1 .818.162Z" clip-rule="evenodd" {}
+        }
+    }
+}
+
+pub fn sun() -> Markup {
+    html! {
+        svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24" fill="currentColor" class="aspect-square size-full" {
+            path d="M12 2.25a.75.75 0 0 1 .75.75v2.25a.75.75 0 0 1-1.5 0V3a.75.75 0 0 1 .75-.75ZM7.5 12a4.5 4.5 0 1 1 9 0 4.5 4.5 0 0 1-9 0ZM18.894 6.166a.75.75 0 0 0-1.06-1.06l-1.591 1.59a.75.75 0 1 0 1.06 1.061l1.591-1.59ZM21.75 12a.75.75 0 0 1-.75.75h-2.25a.75.75 0 0 1 0-1.5H21a.75.75 0 0 1 .75.75ZM17.834 18.894a.75.75 0 0 0 1.06-1.06l-1.59-1.591a.75.75 0 1 0-1.061 1.06l1.59 1.591ZM12 18a.75.75 0 0 1 .75.75V21a.75.75 0 0 1-1.5 0v-2.25A.75.75 0 0 1 12 18ZM7.758 17.303a.75.75 0 0 0-1.061-1.06l-1.591 1.59a.75.75 0 0 0 1.06 1.061l1.591-1.59ZM6 12a.75.75 0 0 1-.75.75H3a.75.75 0 0 1 0-1.5h2.25A.75.75 0 0 1 6 12ZM6.697 7.757a.75.75 0 0 0 1.06-1.06l-1.59-1.591a.75.75 0 0 0-1.061 1.06l1.59 1.591Z" {}
+        }
+    }
+}
+
+/// Glyphs users can pick for the placeholder shown when they have no avatar, by stored name.
+pub const AVATAR_GLYPHS: [&str; 6] = ["user", "star", "heart", "bolt", "moon", "sun"];
+
+/// Placeholder avatar glyph by name, the user silhouette for unknown names.
+pub fn avatar_glyph(name: &str) -> Markup {
+    match name {
+        "star" => star(),
+        "heart" => heart(),
+        "bolt" => bolt(),
+        "moon" => moon(),
+        "sun" => sun(),
+        _ => user(),
+    }
+}
+
+pub fn bar_chart(bars: &[(String, i64)]) -> Markup {
+    let max = bars
+        .iter()
+        .map(|(_, v)| *v)
+        .max()
+        .unwrap_or_default()
+        .max(1);
+    html! {
+        svg xmlns="http://www.w3.org/2000/svg" viewBox={"0 0 " (bars.len() * 24) " 120"} fill="currentColor" class="w-full" {
+            @for (i, (label, value)) in bars.iter().enumerate() {
+                @let height = 90 * value / max;
+                rect x=(i * 24 + 4) y=(100 - height) width="16" height=(height) rx="2" {}
+                @if *value > 0 {
+                    text x=(i * 24 + 12) y=(96 - height) text-anchor="middle" font-size="8" fill="white" {
+                        (value)
+                    }
+                }
+                text x=(i * 24 + 12) y="112" text-anchor="middle" font-size="8" fill="white" {
+                    (label)
+                }
+            }
+        }
+    }
+}
+
+/// Placeholder cover for items without an image: the title over a gradient derived from `hue`.
+pub fn cover(title: &str, hue: i16) -> Markup {
+    let lines = wrap(title, 14, 6);
+    let top = 200 - 18 * lines.len() as i32;
+    html! {
+        svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 300 400" {
+            defs {
+                linearGradient id="cover" x1="0" y1="0" x2="1" y2="1" {
+                    stop offset="0" stop-color={"hsl(" (hue) ",70%,45%)"} {}
+                    stop offset="1" stop-color={"hsl(" ((hue + 60) % 360) ",70%,20%)"} {}
+                }
+            }
+            rect width="300" height="400" fill="url(#cover)" {}
+            text x="150" y=(top) text-anchor="middle" font-family="Quicksand, sans-serif" font-size="28" font-weight="bold" fill="white" {
+                @for line in &lines {
+                    tspan x="150" dy="36" { (line) }
+                }
+            }
+        }
+    }
+}
+
+/// Share card of an item, `width` by `height` pixels: the placeholder cover on the left, to be
+/// painted over by the stored one, with the title and the score in stars next to it.
+pub fn share_card(
+    title: &str,
+    score: f32,
+    review_count: i64,
+    hue: i16,
+    (width, height): (u32, u32),
+    cover_width: u32,
+) -> Markup {
+    let left = cover_width + 64;
+    let lines = wrap(title, 18, 3);
+    let halves = score.round() as i64;
+    html! {
+        svg xmlns="http://www.w3.org/2000/svg" width=(width) height=(height) viewBox={"0 0 " (width) " " (height)} {
+            rect width=(width) height=(height) fill="#18181b" {}
+            svg width=(cover_width) height=(height) {
+                (cover(title, hue))
+            }
+            svg x=(left) y="56" width="160" height="41" {
+                (logo())
+            }
+            text x=(left) y="170" font-family="Quicksand, sans-serif" font-size="48" font-weight="bold" fill="white" {
+                @for line in &lines {
+                    tspan x=(left) dy="60" { (line) }
+                }
+            }
+            @for half in 0..10 {
+                svg x=(left + half as u32 * 32) y="440" width="32" height="64" color=(if half < halves { "#facc15" } else { "#3f3f46" }) {
+                    @if half % 2 == 0 {
+                        (star_left())
+                    } @else {
+                        (star_right())
+                    }
+                }
+            }
+            text x=(left) y="570" font-family="Quicksand, sans-serif" font-size="36" fill="#a1a1aa" {
+                @if review_count == 0 {
+                    "Not rated yet"
+                } @else {
+                    (format!("{score:.2}")) " · " (review_count) @if review_count == 1 { " review" } @else { " reviews" }
+                }
+            }
+        }
+    }
+}
+
+/// Greedily breaks `text` into at most `max_lines` lines of about `width` characters, ending with
+/// an ellipsis when it does not fit.
+fn wrap(text: &str, width: usize, max_lines: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + 1 + word.chars().count() <= width => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_owned()),
+        }
+    }
+    if lines.len() > max_lines {
+        lines.truncate(max_lines);
+        lines[max_lines - 1].push('…');
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_long_titles() {
+        assert_eq!(wrap("Ergo Proxy", 14, 6), ["Ergo Proxy"]);
+        assert_eq!(
+            wrap("WataMote: No Matter How I Look At It", 14, 3),
+            ["WataMote: No", "Matter How I", "Look At It"]
+        );
+        assert_eq!(wrap("a b c d", 1, 2), ["a", "b…"]);
+    }
+}
@@ -1,6 +1,16 @@
-use crate::{database, svg};
-use maud::{html, Markup, DOCTYPE};
-use std::{collections::HashMap, ops::Range};
+use crate::{
+    admin, assets, audit, badges, cards, charts, database, forms,
+    images::{self, Format, Variant},
+    import, jobs, markdown, metadata, metrics, proxy, reactions,
+    routes::{self, url},
+    security, svg, version, webhooks,
+};
+use axum::http::StatusCode;
+use maud::{html, Markup, PreEscaped, DOCTYPE};
+use sqlx::types::chrono::{NaiveDate, Utc};
+use std::ops::Range;
+
+pub mod scripts;
 
 fn get_pagination(
     number_of_pages: usize,
@@ -11,9 +21,7 @@ fn get_pagination(
         0..number_of_pages
     } else {
         if current_page < number_of_pages - displayed_pages / 2 {
-            let low = current_page
-                .checked_sub(displayed_pages / 2)
-                .unwrap_or_default();
+            let low = current_page.saturating_sub(displayed_pages / 2);
             low..low + displayed_pages
         } else {
             number_of_pages - displayed_pages..number_of_pages
@@ -21,20 +29,65 @@ fn get_pagination(
     }
 }
 
-fn get_query(params: &HashMap<&str, String>) -> Option<String> {
-    params
+/// Style showing a resized variant of a stored image as the background, in WebP where the
+/// browser supports it.
+fn background_image(url: &str, variant: Variant) -> String {
+    let sources: Vec<String> = [Format::WebP, Format::Jpeg]
         .into_iter()
-        .filter(|(_, v)| !v.is_empty() && *v != "0")
-        .map(|(k, v)| format!("{}={}", k, v))
-        .reduce(|acc, s| format!("{}&{}", acc, s))
-        .map(|s| format!("?{}", s))
+        .map(|format| {
+            format!(
+                "url('{}') type('{}')",
+                images::variant(url, variant, format),
+                format.mime()
+            )
+        })
+        .collect();
+    format!(
+        "background-image: url('{}'); background-image: image-set({})",
+        images::variant(url, variant, Format::Jpeg),
+        sources.join(", ")
+    )
+}
+
+/// Page sizes offered below resizable listings, multiples of the default one.
+fn page_sizes() -> impl Iterator<Item = i64> {
+    let bounds = database::per_page_bounds();
+    bounds.clone().step_by(*bounds.start() as usize)
 }
 
 fn pagination<T>(page: database::Page<T>) -> Markup {
-    let mut params = HashMap::new();
-    params.insert("search", page.query.unwrap_or_default());
     html! {
-        @if page.number_of_pages>1
+        @if let Some(keyset) = &page.keyset {
+            div class="flex flex-row gap-4 justify-center mt-4 text-black" {
+                @let button_style = " grid justify-center content-center size-8 rounded-full";
+                @if keyset.after.is_none() {
+                    div class={"bg-zinc-700" (button_style)} {
+                        div class="size-6"{
+                            (svg::left_arrow())
+                        }
+                    }
+                } @else {
+                    a hx-target="#content" hx-boost="true" href=(page.url(0)) title="First page" class={"bg-violet-400 hover:bg-black hover:text-white" (button_style)} {
+                        div class="size-6"{
+                            (svg::left_arrow())
+                        }
+                    }
+                }
+                @if let Some(next) = page.next_url() {
+                    a hx-target="#content" hx-boost="true" href=(next) title="Next page" class={"bg-violet-400 hover:bg-black hover:text-white" (button_style)} {
+                        div class="size-6"{
+                            (svg::right_arrow())
+                        }
+                    }
+                } @else {
+                    div class={"bg-zinc-700" (button_style)} {
+                        div class="size-6"{
+                            (svg::right_arrow())
+                        }
+                    }
+                }
+            }
+        } @else if page.number_of_pages>1
         {
             div class="flex flex-row gap-4 justify-center mt-4 text-black" {
                 @let button_style = " grid justify-center content-center size-8 rounded-full";
@@ -46,18 +99,18 @@ fn pagination<T>(page: database::Page<T>) -> Markup {
                     }
                 }
                 @else {
-                    a hx-target="#content" hx-boost="true" href={(page.target) ({params.insert("page",(page.current_page-1).to_string());get_query(&params).unwrap_or_default()})} class={"bg-violet-400 hover:bg-black hover:text-white" (button_style)} {
+                    a hx-target="#content" hx-boost="true" href=(page.url(page.current_page-1)) class={"bg-violet-400 hover:bg-black hover:text-white" (button_style)} {
                         div class="size-6"{
                             (svg::left_arrow())
                         }
                     }
                 }
                 @for p in get_pagination(page.number_of_pages as usize,page.current_page as usize,5) {
-                    a hx-target="#content" hx-boost="true" href={(page.target) ({params.insert("page",p.to_string());get_query(&params).unwrap_or_default()})} hx-push-url="true" class={"hover:bg-black hover:text-white " @if p==page.current_page as usize {"bg-violet-400"} @else {"bg-white"} (button_style)} {
+                    a hx-target="#content" hx-boost="true" href=(page.url(p as i32)) hx-push-url="true" class={"hover:bg-black hover:text-white " @if p==page.current_page as usize {"bg-violet-400"} @else {"bg-white"} (button_style)} {
                         (p+1)
                     }
                 }
-                @for _ in 0..5usize.checked_sub(page.number_of_pages as usize).unwrap_or_default() {
+                @for _ in 0..5usize.saturating_sub(page.number_of_pages as usize) {
                     div class={"bg-zinc-700" (button_style)} {}
                 }
                 @if page.current_page==page.number_of_pages-1 {
@@ -68,7 +121,7 @@ fn pagination<T>(page: database::Page<T>) -> Markup {
                     }
                 }
                 @else {
-                    a hx-target="#content" hx-boost="true" href={(page.target) ({params.insert("page",(page.current_page+1).to_string());get_query(&params).unwrap_or_default()})}  class={"bg-violet-400 hover:bg-black hover:text-white" (button_style)} {
+                    a hx-target="#content" hx-boost="true" href=(page.url(page.current_page+1))  class={"bg-violet-400 hover:bg-black hover:text-white" (button_style)} {
                         div class="size-6"{
                             (svg::right_arrow())
                         }
@@ -76,80 +129,197 @@ fn pagination<T>(page: database::Page<T>) -> Markup {
                 }
             }
         }
+        @if page.is_resizable() && (page.keyset.is_some() || page.number_of_pages > 1 || page.per_page != database::default_per_page()) {
+            div class="flex flex-row gap-2 justify-center items-center mt-4 text-sm text-white" {
+                "Per page"
+                @for size in page_sizes() {
+                    a hx-target="#content" hx-boost="true" href=(page.resized_url(size)) class={"px-2 rounded-full " @if size == page.per_page {"bg-violet-400 text-black"} @else {"bg-zinc-700 hover:bg-violet-400 hover:text-black"}} {(size)}
+                }
+            }
+        }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn item_page(
     item: &database::Item,
     page: Option<database::Page<database::RatingItem>>,
     user: Option<&database::User>,
     rating: Option<i16>,
+    tags: &[String],
+    collections: &[database::ItemCollection],
+    subscribed: bool,
+    favorite: bool,
+    featured: bool,
+    gallery: &[database::ItemImage],
 ) -> Markup {
     let rating = rating.unwrap_or_default();
     html! {
         @if let Some(user) = user {
             @if user.is_admin {
                 div class="mb-4 flex flex-row gap-x-4" {
-                    button hx-get={"/items/" (item.locator) "/edit"} hx-swap="afterend" class="rounded-full p-2 bg-violet-400 hover:bg-black hover:text-white" {
+                    button hx-get=(url::item_edit(&item.locator)) hx-swap="afterend" class="rounded-full p-2 bg-violet-400 hover:bg-black hover:text-white" {
                         "Edit item"
                     }
-                    button hx-get={"/items/" (item.locator) "/remove"} hx-swap="afterend"  class="rounded-full p-2 bg-violet-400 hover:bg-black hover:text-white" {
+                    button hx-get=(url::item_remove(&item.locator)) hx-swap="afterend"  class="rounded-full p-2 bg-violet-400 hover:bg-black hover:text-white" {
                         "Remove item"
                     }
+                    button hx-get=(url::item_merge(&item.locator)) hx-swap="afterend" class="rounded-full p-2 bg-violet-400 hover:bg-black hover:text-white" {
+                        "Merge item"
+                    }
+                    (featured_button(&item.locator, featured))
+                    (lock_button(&item.locator, item.locked))
                 }
             }
         }
         div class="flex flex-row [@media(max-width:39rem)]:flex-col gap-4" {
             div {
-                div style={"background-image: url('/static/images/items/" (item.locator) "')"} class="flex-none w-64 aspect-[3/4] rounded-md bg-cover bg-center" {}
+                @if gallery.is_empty() {
+                    div hx-get=(url::item_cover(&item.locator)) hx-target="body" hx-swap="beforeend" title="Enlarge cover" style=(background_image(&url::item_image(&item.locator, item.cover.as_deref()), Variant::Page)) class="flex-none w-64 aspect-[3/4] rounded-md bg-cover bg-center cursor-pointer" {}
+                } @else {
+                    div data-lightbox-open=(gallery.iter().position(|image| image.is_cover).unwrap_or_default()) title="Open gallery" style=(background_image(&url::item_image(&item.locator, item.cover.as_deref()), Variant::Page)) class="flex-none w-64 aspect-[3/4] rounded-md bg-cover bg-center cursor-pointer" {}
+                    (item_gallery(&item.locator, gallery, user.is_some_and(|user| user.is_admin)))
+                }
             }
             div class="text-white" {
-                b class="text-2xl" {
-                    (item.title)
+                div class="flex flex-row items-center gap-4" {
+                    b class="text-2xl" {
+                        (item.title)
+                    }
+                    @if user.is_some() {
+                        (favorite_button(&item.locator, favorite, item.favorite_count))
+                        details hx-get=(url::item_lists(&item.locator)) hx-trigger="toggle once" hx-target="find div" class="relative" {
+                            summary class="list-none cursor-pointer select-none" {
+                                span class="px-2 text-xs bg-zinc-700" {"Add to list"}
+                            }
+                            div class="absolute z-10 mt-1 w-48 flex flex-col gap-1 p-2 text-sm bg-zinc-900 rounded-md" {}
+                        }
+                    } @else {
+                        span title="Favorites" class="flex flex-row items-center gap-1 text-zinc-400" {
+                            span class="size-5" {(svg::heart())}
+                            (item.favorite_count)
+                        }
+                    }
+                }
+                @if !tags.is_empty() {
+                    div class="flex flex-row flex-wrap gap-2 my-2" {
+                        @for tag in tags {
+                            a href=(url::items_tagged(tag)) hx-boost="true" hx-target="#content" class="px-2 text-xs bg-zinc-700 hover:bg-violet-400" {(tag)}
+                        }
+                    }
+                }
+                @for collection in collections {
+                    div class="text-sm" {
+                        "Part " (collection.part) " of " (collection.item_count) " in "
+                        a href=(url::collection(&collection.slug)) hx-boost="true" hx-target="#content" class="text-violet-400 hover:text-white" {(collection.name)}
+                    }
                 }
                 br;
                 "Score: " b class="text-violet-400" {(format!("{:.2}",item.score)) "/10.00 (#" (item.rank) ")"}
+                @if let (Some(slug), Some(name)) = (&item.category_slug, &item.category_name) {
+                    " "
+                    a href=(url::items_in_category(slug)) hx-boost="true" hx-target="#content" class="px-2 text-xs bg-zinc-700 hover:bg-violet-400" {
+                        "#" (item.category_rank) " in " (name)
+                    }
+                }
                 " Reviews: " b class="text-violet-400" {(item.review_count) " (#" (item.popularity) ")"}
+                @if let Some(release_date) = item.release_date {
+                    @if item.unreleased {
+                        " Releases: "
+                    } @else {
+                        " Released: "
+                    }
+                    b class="text-violet-400" {(release_date.format("%b %d, %Y"))}
+                }
                 br;
                 br;
-                b {
-                    "Your rating"
-                    @if user.is_some() && rating!=0 {
-                        " "
-                        button hx-delete={"/items/" (item.locator) "/rate"} {
-                            span class="px-2 text-xs bg-zinc-700" {
-                                "Remove review"
-                            }
+                @if let (true, Some(release_date)) = (item.unreleased, item.release_date) {
+                    b {"Upcoming"}
+                    div class="flex flex-row items-baseline gap-2" {
+                        "Reviews open in"
+                        span data-countdown=(release_date.format("%Y-%m-%dT00:00:00Z")) class="text-2xl text-violet-400" {
+                            @let days = (release_date - Utc::now().date_naive()).num_days();
+                            (days) @if days == 1 {" day"} @else {" days"}
                         }
                     }
-                }
-                @if user.is_some() {
-                    div class="relative z-0 flex flex-row size-fit group" {
-                        @if rating==0 {
-                            div class="absolute left-1/2 top-1/2 translate-x-[-50%] translate-y-[-50%] text-white select-none group-hover:hidden" {
-                                "Item not rated yet"
-                            }
+                    @if user.is_some() {
+                        (subscription_button(&item.locator, subscribed))
+                    }
+                } @else if item.locked {
+                    b {
+                        "Your rating"
+                        @if user.is_some() {
+                            " "
+                            (subscription_button(&item.locator, subscribed))
                         }
+                    }
+                    div title="Reviews of this item are locked" class="flex flex-row size-fit" {
                         @for s in 0..5 {
-                            button hx-post={"/items/" (item.locator) "/rate"} hx-target="#content" name="score" value={(2*s+1)} class={"peer peer-hover:text-zinc-700 w-8" @if (2*s+1)<=rating {" text-yellow-400"} @else {" text-zinc-700 group-hover:text-yellow-400"}} {
+                            div class={"w-8" @if (2*s+1)<=rating {" text-yellow-400"} @else {" text-zinc-700"}} {
                                 (svg::star_left())
                             }
-                            button hx-post={"/items/" (item.locator) "/rate"} hx-target="#content" name="score" value={(2*s+2)} class={"peer peer-hover:text-zinc-700 w-8" @if (2*s+2)<=rating {" text-yellow-400"} @else {" text-zinc-700 group-hover:text-yellow-400"}} {
+                            div class={"w-8" @if (2*s+2)<=rating {" text-yellow-400"} @else {" text-zinc-700"}} {
                                 (svg::star_right())
                             }
                         }
                     }
+                    div class="text-sm text-zinc-400" {"Reviews of this item are locked"}
                 } @else {
-                    div class="relative z-0 flex flex-row text-zinc-700 size-fit" {
-                        div class="absolute left-1/2 top-1/2 translate-x-[-50%] translate-y-[-50%] text-white select-none" {
-                            "Login to rate item"
+                    b {
+                        "Your rating"
+                        @if user.is_some() {
+                            " "
+                            button hx-get=(url::item_review(&item.locator)) hx-target="#content" hx-swap="beforeend" {
+                                span class="px-2 text-xs bg-zinc-700" {
+                                    @if rating!=0 {
+                                        "Edit review"
+                                    } @else {
+                                        "Write review"
+                                    }
+                                }
+                            }
                         }
-                        @for _ in 0..5 {
-                            div class="w-8"{
-                                (svg::star_left())
+                        @if user.is_some() && rating!=0 {
+                            " "
+                            button hx-delete=(url::item_rate(&item.locator)) {
+                                span class="px-2 text-xs bg-zinc-700" {
+                                    "Remove review"
+                                }
                             }
-                            div class="w-8"{
-                                (svg::star_right())
+                        }
+                        @if user.is_some() {
+                            " "
+                            (subscription_button(&item.locator, subscribed))
+                        }
+                    }
+                    @if user.is_some() {
+                        div class="relative z-0 flex flex-row size-fit group" {
+                            @if rating==0 {
+                                div class="absolute left-1/2 top-1/2 translate-x-[-50%] translate-y-[-50%] text-white select-none group-hover:hidden" {
+                                    "Item not rated yet"
+                                }
+                            }
+                            @for s in 0..5 {
+                                button hx-post=(url::item_rate(&item.locator)) hx-target="#content" name="score" value={(2*s+1)} class={"peer peer-hover:text-zinc-700 w-8" @if (2*s+1)<=rating {" text-yellow-400"} @else {" text-zinc-700 group-hover:text-yellow-400"}} {
+                                    (svg::star_left())
+                                }
+                                button hx-post=(url::item_rate(&item.locator)) hx-target="#content" name="score" value={(2*s+2)} class={"peer peer-hover:text-zinc-700 w-8" @if (2*s+2)<=rating {" text-yellow-400"} @else {" text-zinc-700 group-hover:text-yellow-400"}} {
+                                    (svg::star_right())
+                                }
+                            }
+                        }
+                    } @else {
+                        div class="relative z-0 flex flex-row text-zinc-700 size-fit" {
+                            div class="absolute left-1/2 top-1/2 translate-x-[-50%] translate-y-[-50%] text-white select-none" {
+                                "Login to rate item"
+                            }
+                            @for _ in 0..5 {
+                                div class="w-8"{
+                                    (svg::star_left())
+                                }
+                                div class="w-8"{
+                                    (svg::star_right())
+                                }
                             }
                         }
                     }
@@ -157,56 +327,91 @@ pub fn item_page(
                 br;
                 b {"Description"}
                 br;
-                div class="whitespace-pre-line"{
-                    (item.description)
+                div class=(markdown::CLASSES) {
+                    (markdown::render(&item.description))
                 }
             }
         }
         div class="mt-4 text-white" {
             div class="mx-auto flex flex-col text-white w-full gap-4 max-w-[39rem]" {
-                b {"User ratings"}
+                (item_tabs(&item.locator, false))
                 @if let Some(page) = page
                 {
                     @for rating in &page.items {
-                        a href={"/users/" (rating.user.username) } hx-boost="true" hx-target="#content" {
-                            div class="p-4 h-20 w-full flex flex-row items-center bg-zinc-900 rounded-md" {
-                                div class="basis-1/3 flex flex-col items-center" {
-                                    @if rating.user.has_avatar {
-                                            div style={"background-image:url('/static/images/avatars/" (rating.user.username) "')"} class="bg-cover bg-center size-8 rounded-full overflow-hidden" {}
+                        div class="w-full flex flex-col bg-zinc-900 rounded-md" {
+                            a href=(url::user(&rating.user.username)) hx-boost="true" hx-target="#content" {
+                                div class="p-4 h-20 w-full flex flex-row items-center" {
+                                    div class="basis-1/3 flex flex-col items-center" {
+                                        @if let Some(avatar) = &rating.user.avatar {
+                                                div style=(background_image(&url::avatar(avatar), Variant::Thumbnail)) class="bg-cover bg-center size-8 rounded-full overflow-hidden" {}
 
-                                    } @else {
-                                        div style={"background-color:hsl(" (rating.user.avatar_hue) ",100%,50%)"} class="grid justify-center content-center size-8 text-white rounded-full" {
-                                            div class="size-6" {
-                                                (svg::user())
+                                        } @else {
+                                            div style={"background-color:hsl(" (rating.user.avatar_hue) ",100%,50%)"} class="grid justify-center content-center size-8 text-white rounded-full" {
+                                                div class="size-6" {
+                                                    (svg::avatar_glyph(&rating.user.avatar_glyph))
+                                                }
+                                            }
+                                        }
+                                        b {
+                                            (rating.user.username)
+                                        }
+                                        (badge_icons(&rating.badges))
+                                        @if rating.user.is_admin {
+                                            span class="bg-violet-400 text-white px-2 text-xs" {
+                                                    "admin"
                                             }
                                         }
                                     }
-                                    b {
-                                        (rating.user.username)
-                                    }
-                                    @if rating.user.is_admin {
-                                        span class="bg-violet-400 text-white px-2 text-xs" {
-                                                "admin"
+                                    div class="basis-1/3 flex flex-row size-fit justify-center" {
+                                        @for s in 0..5 {
+                                            div class={"w-6" @if (2*s+1)<=rating.rating {" text-yellow-400"} @else {" text-zinc-700"}} {
+                                                (svg::star_left())
+                                            }
+                                            div class={"w-6" @if (2*s+2)<=rating.rating {" text-yellow-400"} @else {" text-zinc-700"}} {
+                                                (svg::star_right())
+                                            }
                                         }
                                     }
-                                }
-                                div class="basis-1/3 flex flex-row size-fit justify-center" {
-                                    @for s in 0..5 {
-                                        div class={"w-6" @if (2*s+1)<=rating.rating {" text-yellow-400"} @else {" text-zinc-700"}} {
-                                            (svg::star_left())
+                                    div class="basis-1/3 text-center" {
+                                        (rating.date.format("%b %d, %Y"))
+                                        @if rating.private {
+                                            div {
+                                                span class="px-2 text-xs bg-zinc-700" {"private"}
+                                            }
                                         }
-                                        div class={"w-6" @if (2*s+2)<=rating.rating {" text-yellow-400"} @else {" text-zinc-700"}} {
-                                            (svg::star_right())
+                                        @if user.is_some_and(|user| user.is_admin && user.username != rating.user.username) {
+                                            div {
+                                                button hx-delete=(url::item_rating(&item.locator, &rating.user.username)) hx-confirm={"Remove the review by " (rating.user.username) "?"} {
+                                                    span class="px-2 text-xs bg-zinc-700" {"Remove"}
+                                                }
+                                            }
                                         }
                                     }
                                 }
-                                div class="basis-1/3 text-center" {
-                                    (rating.date.format("%b %d, %Y"))
+                            }
+                            @if let Some(body) = &rating.body {
+                                @if rating.spoiler {
+                                    div data-spoiler title="Spoiler, click to reveal" class={"px-4 pb-4 blur-sm cursor-pointer " (markdown::CLASSES)} {
+                                        (markdown::render(body))
+                                    }
+                                } @else {
+                                    div class={"px-4 pb-4 " (markdown::CLASSES)} {
+                                        (markdown::render(body))
+                                    }
+                                }
+                            }
+                            div class="px-4 pb-4" {
+                                (reaction_bar(&url::review_reactions(&item.locator, &rating.user.username), &rating.reaction_counts, &rating.own_reactions, user))
+                            }
+                            details hx-get=(url::review_replies(&item.locator, &rating.user.username)) hx-trigger="toggle once" hx-target="find div" class="px-4 pb-4" {
+                                summary class="text-xs text-violet-400 cursor-pointer select-none" {
+                                    "Replies (" (rating.reply_count) ")"
                                 }
+                                div id={"replies-" (item.locator) "-" (rating.user.username)} class="mt-2 flex flex-col gap-2" {}
                             }
                         }
                     }
-                    @for _ in 0..3usize.checked_sub(page.items.len()).unwrap_or_default() {
+                    @for _ in 0..3usize.saturating_sub(page.items.len()) {
                         div class="grid justify-center content-center bg-zinc-700 rounded-md h-20 w-full max-w-[39rem] p-4" {}
                     }
                 (pagination(page))
@@ -221,197 +426,1803 @@ pub fn item_page(
     }
 }
 
-pub fn item_view(
-    page_opt: Option<database::Page<database::Item>>,
+/// Switches between the reviews and the discussion of an item.
+fn item_tabs(locator: &str, discussion: bool) -> Markup {
+    let tab = |active: bool| {
+        if active {
+            "pb-1 border-b-2 border-violet-400 font-bold"
+        } else {
+            "pb-1 border-b-2 border-transparent hover:text-violet-400"
+        }
+    };
+    html! {
+        div class="flex flex-row gap-4" {
+            a href=(url::item(locator)) hx-boost="true" hx-target="#content" class=(tab(!discussion)) {"User ratings"}
+            a href=(url::item_discussion(locator)) hx-boost="true" hx-target="#content" class=(tab(discussion)) {"Discussion"}
+        }
+    }
+}
+
+pub fn item_discussion(
+    item: &database::Item,
+    page: Option<database::Page<database::Comment>>,
     user: Option<&database::User>,
+    message: Option<&str>,
 ) -> Markup {
     html! {
-        @if let Some(user) = user {
-            @if user.is_admin {
-                div class="mb-4 flex flex-row flex-wrap gap-x-4 justify-center" {
-                    div class="w-56"{
-                        button hx-get="/items/add" hx-swap="afterend" class="rounded-full p-2 bg-violet-400 hover:bg-black hover:text-white" {
-                            "Add item"
-                        }
-                    }
-                    div class="w-56 h-0"{}
-                    div class="w-56 h-0"{}
-                    div class="w-56 h-0"{}
-                }
+        div class="mx-auto flex flex-col text-white w-full gap-4 max-w-[39rem]" {
+            a href=(url::item(&item.locator)) hx-boost="true" hx-target="#content" class="text-2xl font-bold hover:text-violet-400" {
+                (item.title)
             }
-        }
-        @if let Some(page) = page_opt {
-            div class="flex flex-row flex-wrap gap-4 justify-center" {
-                @for item in &page.items {
-                    a href={"/items/" (item.locator)} hx-boost="true" hx-target="#content" {
-                        div class="group relative z-0 w-56 aspect-[3/4] rounded-md overflow-hidden outline outline-offset-2 outline-2 outline-transparent hover:outline-violet-400" {
-                            div style={"background-image: url('/static/images/items/" (item.locator) "')"} class="size-full bg-cover bg-center group-hover:brightness-75 transition-[filter]" {}
-                            div class="absolute w-full h-24 top-0 bg-gradient-to-b from-black to-transparent" {
-                                div class="m-2 text-white text-xs flex flex-col items-center size-fit" {
-                                    div class="text-yellow-400 flex flex-row w-8" {
-                                        (svg::star_left())
-                                        (svg::star_right())
-                                    }
-                                    div {
-                                        (format!("{:.2}",item.score))
-                                    }
-                                }
-                            }
-                            div class="absolute w-full h-24 bottom-0 text-white text-center bg-gradient-to-t from-black to-transparent flex flex-col justify-end p-4" {
-                                (item.title)
-                            }
-                        }
-                    }
+            (item_tabs(&item.locator, true))
+            @if let Some(message)=message
+            {
+                div class="grid justify-center content-center px-2 min-h-8 text-center bg-orange-200 text-orange-400 rounded-[1rem]" {
+                    (message)
                 }
-                @for _ in 0..12usize.checked_sub(page.items.len()).unwrap_or_default() {
-                    div class="w-56 aspect-[3/4] bg-zinc-700 rounded-md" {}
+            }
+            @if user.is_some() {
+                form hx-post=(url::item_discussion(&item.locator)) hx-target="#content" class="flex flex-col items-end gap-2" {
+                    textarea style="scrollbar-width: none" class="p-2 w-full min-h-20 rounded-[1rem] text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" name="body" placeholder="Talk about this item, Markdown is supported" {}
+                    button class="px-4 h-8 bg-violet-400 text-black rounded-full hover:bg-black hover:text-white" type="submit" {"Comment"}
                 }
+            } @else {
+                div class="text-sm text-zinc-400" {"Login to join the discussion"}
             }
-            (pagination(page))
-        } @else {
-            div class="mx-auto text-white grid justify-center content-center bg-zinc-700 rounded-md h-20 w-full max-w-[39rem] p-4" {
-                "No matching entries found!"
+            @if let Some(page) = page {
+                (comment_thread(&item.locator, &page.items, None, user, page.current_page))
+                (pagination(page))
+            } @else {
+                div class="grid justify-center content-center bg-zinc-700 rounded-md h-20 w-full p-4" {
+                    "No comments on this item yet!"
+                }
             }
         }
     }
 }
 
-pub fn user_view(page_opt: Option<database::Page<database::User>>) -> Markup {
-    if let Some(page) = page_opt {
-        html! {
-            div class="flex flex-row flex-wrap gap-4 justify-center" {
-                @for item in &page.items {
-                    a href={"/users/" (item.username)} hx-boost="true" hx-target="#content" {
-                        div class="group w-56 aspect-[3/4] grid justify-center content-center" {
-                            div class="flex flex-col justify-between content-center text-white" {
-                                @if item.has_avatar
-                                {
-                                    div style={"background-image:url('/static/images/avatars/" (item.username) "')"} class="bg-cover bg-center size-56 rounded-full group-hover:brightness-75 transition-[filter] overflow-hidden outline outline-offset-2 outline-2 outline-transparent group-hover:outline-violet-400" {}
-                                } @else {
-                                    div style={"background-color:hsl(" (item.avatar_hue) ",100%,50%)"} class="relative z-0 size-56 grid justify-center content-center rounded-full group-hover:brightness-75 transition-[filter] overflow-hidden outline outline-offset-2 outline-2 outline-transparent group-hover:outline-violet-400" {
-                                        div class="size-[10.5rem]"{
-                                            (svg::user())
-                                        }
-                                    }
-                                }
-                                div class="flex flex-row justify-center items-center pt-4"
-                                {
-                                    (item.username)
-                                    @if item.is_admin {
-                                        span class="bg-violet-400 text-white px-2 text-xs" {
-                                            b {
-                                                "admin"
-                                            }
-                                        }
-                                    }
-                                }
+/// Comments replying to `parent`, or the threads of the page when it is `None`, each followed by
+/// its own replies.
+fn comment_thread(
+    locator: &str,
+    comments: &[database::Comment],
+    parent: Option<i32>,
+    user: Option<&database::User>,
+    page_number: i32,
+) -> Markup {
+    html! {
+        @for comment in comments.iter().filter(|comment| comment.parent_id == parent) {
+            div data-comment class={"flex flex-col gap-2" @if parent.is_some() {" ms-2 ps-4 border-s-2 border-zinc-700"} @else {" bg-zinc-900 rounded-md p-4"}} {
+                div class="flex flex-row items-center gap-2 text-xs" {
+                    @if let Some(avatar) = &comment.user.avatar {
+                        div style=(background_image(&url::avatar(avatar), Variant::Thumbnail)) class="flex-none bg-cover bg-center size-8 rounded-full overflow-hidden" {}
+                    } @else {
+                        div style={"background-color:hsl(" (comment.user.avatar_hue) ",100%,50%)"} class="flex-none grid justify-center content-center size-8 text-white rounded-full" {
+                            div class="size-6" {
+                                (svg::avatar_glyph(&comment.user.avatar_glyph))
                             }
                         }
                     }
+                    a href=(url::user(&comment.user.username)) hx-boost="true" hx-target="#content" {
+                        b {
+                            (comment.user.username)
+                        }
+                    }
+                    @if comment.user.is_admin {
+                        span class="bg-violet-400 text-white px-2 text-xs" {"admin"}
+                    }
+                    (comment.date.format("%b %d, %Y"))
+                    @if user.is_some_and(|user| user.username == comment.user.username || user.is_admin) {
+                        button hx-delete=(url::item_comment(locator, comment.id)) hx-target="closest [data-comment]" hx-swap="delete" hx-confirm="Remove this comment along with its replies?" {
+                            span class="px-2 text-xs bg-zinc-700" {"Remove"}
+                        }
+                    }
                 }
-                @for _ in 0..12usize.checked_sub(page.items.len()).unwrap_or_default() {
-                    div class="w-56 aspect-[3/4] grid justify-center content-center" {
-                        div class="flex flex-col justify-between content-center text-white" {
-                            div class="size-56 bg-zinc-700 rounded-full" {}
-                            div class="min-h-10" {}
+                div class=(markdown::CLASSES) {
+                    (markdown::render(&comment.body))
+                }
+                @if user.is_some() {
+                    details {
+                        summary class="text-xs text-violet-400 cursor-pointer select-none" {"Reply"}
+                        form hx-post=(url::item_discussion(locator)) hx-target="#content" class="mt-2 flex flex-col items-end gap-2" {
+                            input type="hidden" name="parent" value=(comment.id);
+                            input type="hidden" name="page" value=(page_number);
+                            textarea style="scrollbar-width: none" class="p-2 w-full min-h-20 rounded-[1rem] text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" name="body" placeholder="Write a reply" {}
+                            button class="px-4 h-8 bg-violet-400 text-black rounded-full hover:bg-black hover:text-white" type="submit" {"Reply"}
                         }
                     }
-
                 }
-            }
-            (pagination(page))
-        }
-    } else {
-        html! {
-            div class="mx-auto text-white grid justify-center content-center bg-zinc-700 rounded-md h-20 w-full max-w-[39rem] p-4" {
-                "No matching entries found!"
+                (comment_thread(locator, comments, Some(comment.id), user, page_number))
             }
         }
     }
 }
 
-pub fn user_page(
-    page_user: &database::User,
-    page: Option<database::Page<database::RatingUser>>,
-    user: Option<&database::User>,
-) -> Markup {
+fn item_gallery(locator: &str, gallery: &[database::ItemImage], is_admin: bool) -> Markup {
     html! {
-        @if let Some(user) = user {
-            @if user.username == page_user.username || user.is_admin {
-                div class="mb-4 flex flex-row gap-x-4" {
-                    button hx-get={"/users/" (page_user.username) "/edit"} hx-swap="afterend" class="rounded-full p-2 bg-violet-400 hover:bg-black hover:text-white" {
-                        "Edit user"
-                    }
-                    @if !page_user.is_admin {
-                        button hx-get={"/users/" (page_user.username) "/remove"} hx-swap="afterend"  class="rounded-full p-2 bg-violet-400 hover:bg-black hover:text-white" {
-                            "Remove user"
+        div class="mt-2 grid grid-cols-4 gap-2 w-64" {
+            @for (index, image) in gallery.iter().enumerate() {
+                div class="flex flex-col gap-1" {
+                    div data-lightbox-open=(index) data-lightbox-src=(url::gallery_image(&image.file)) style=(background_image(&url::gallery_image(&image.file), Variant::Card)) class={"aspect-square rounded-md bg-cover bg-center cursor-pointer" @if image.is_cover {" outline outline-2 outline-violet-400"}} {}
+                    @if is_admin && !image.is_cover {
+                        button hx-post=(url::item_gallery_cover(locator, image.id)) title="Use as cover" {
+                            span class="block px-2 text-xs bg-zinc-700 text-white" {"Cover"}
+                        }
+                        button hx-delete=(url::item_gallery_image(locator, image.id)) hx-confirm="Remove this image?" {
+                            span class="block px-2 text-xs bg-zinc-700 text-white" {"Remove"}
                         }
                     }
                 }
             }
         }
-        div class="flex flex-col gap-4 content-center items-center" {
-            div {
-                @if page_user.has_avatar {
-                    div style={"background-image:url('/static/images/avatars/" (page_user.username) "')"} class="bg-cover bg-center size-64 rounded-full overflow-hidden" {}
-                } @else {
-                    div style={"background-color:hsl(" (page_user.avatar_hue) ",100%,50%)"} class="text-white size-64 grid justify-center content-center rounded-full overflow-hidden" {
-                        div class="size-[12rem]"{
-                            (svg::user())
-                        }
-                    }
+        div data-lightbox class="hidden fixed left-0 top-0 w-full h-full justify-center items-center gap-4 z-50" {
+            div data-lightbox-close class="absolute w-full h-full bg-black/50" {}
+            button data-lightbox-step="-1" title="Previous image" class="relative grid justify-center content-center size-8 rounded-full bg-violet-400 hover:bg-black hover:text-white" {
+                div class="size-6" {
+                    (svg::left_arrow())
                 }
             }
-            div class="text-white" {
-                div class="flex flex-row items-center" {
-                    b class="text-2xl" {
-                        (page_user.username)
-                    }
-                    @if page_user.is_admin {
-                        b class="bg-violet-400 px-4 text-lg" {
-                            "admin"
-                        }
-                    }
+            img data-lightbox-image alt="Gallery image" class="relative max-h-[80vh] max-w-[80vw] rounded-md";
+            button data-lightbox-step="1" title="Next image" class="relative grid justify-center content-center size-8 rounded-full bg-violet-400 hover:bg-black hover:text-white" {
+                div class="size-6" {
+                    (svg::right_arrow())
                 }
             }
-            div class="mx-auto flex flex-col text-white w-full gap-4 max-w-[39rem]" {
-                b {"User ratings"}
-                @if let Some(page) = page
-                {
-                    @for rating in &page.items {
-                        a href={"/items/" (rating.item.locator) } hx-boost="true" hx-target="#content" {
-                            div class="w-full p-4 h-20 flex flex-row items-center bg-zinc-900 rounded-md" {
-                                div class="basis-1/3 flex flex-row items-center" {
-                                    b class="text-xs" {
-                                        (rating.item.title)
-                                    }
-                                }
-                                div class="basis-1/3 flex flex-row size-fit justify-center" {
-                                    @for s in 0..5 {
-                                        div class={"w-6" @if (2*s+1)<=rating.rating {" text-yellow-400"} @else {" text-zinc-700"}} {
-                                            (svg::star_left())
-                                        }
-                                        div class={"w-6" @if (2*s+2)<=rating.rating {" text-yellow-400"} @else {" text-zinc-700"}} {
-                                            (svg::star_right())
-                                        }
-                                    }
-                                }
-                                div class="basis-1/3 text-center" {
-                                    (rating.date.format("%b %d, %Y"))
-                                }
-                            }
+        }
+    }
+}
+
+pub fn cover_lightbox(item: &database::Item) -> Markup {
+    html! {
+        div class="fixed left-0 top-0 w-full h-full flex justify-center items-center z-50" {
+            div data-dismiss class="absolute w-full h-full bg-black/50" {}
+            img src=(url::item_image(&item.locator, item.cover.as_deref())) alt=(item.title) class="relative max-h-[80vh] max-w-[80vw] rounded-md";
+        }
+    }
+}
+
+pub fn lock_button(locator: &str, locked: bool) -> Markup {
+    html! {
+        @if locked {
+            button hx-delete=(url::item_lock(locator)) hx-swap="outerHTML" title="Allow new and changed ratings again" class="rounded-full p-2 bg-black text-white hover:bg-violet-400 hover:text-black" {
+                "Locked"
+            }
+        } @else {
+            button hx-post=(url::item_lock(locator)) hx-swap="outerHTML" title="Stop new and changed ratings" class="rounded-full p-2 bg-violet-400 hover:bg-black hover:text-white" {
+                "Lock reviews"
+            }
+        }
+    }
+}
+
+pub fn featured_button(locator: &str, featured: bool) -> Markup {
+    html! {
+        @if featured {
+            button hx-delete=(url::item_feature(locator)) hx-swap="outerHTML" title="Take the item off the featured carousel" class="rounded-full p-2 bg-black text-white hover:bg-violet-400 hover:text-black" {
+                "Featured"
+            }
+        } @else {
+            button hx-post=(url::item_feature(locator)) hx-swap="outerHTML" title="Show the item in the featured carousel" class="rounded-full p-2 bg-violet-400 hover:bg-black hover:text-white" {
+                "Feature"
+            }
+        }
+    }
+}
+
+pub fn subscription_button(locator: &str, subscribed: bool) -> Markup {
+    html! {
+        @if subscribed {
+            button hx-delete=(url::item_subscription(locator)) hx-swap="outerHTML" title="Stop getting notified about new reviews" {
+                span class="px-2 text-xs bg-violet-400 text-black" {"Subscribed"}
+            }
+        } @else {
+            button hx-post=(url::item_subscription(locator)) hx-swap="outerHTML" title="Get notified about new reviews" {
+                span class="px-2 text-xs bg-zinc-700" {"Subscribe"}
+            }
+        }
+    }
+}
+
+pub fn favorite_button(locator: &str, favorite: bool, count: i64) -> Markup {
+    html! {
+        @if favorite {
+            button hx-delete=(url::item_favorite(locator)) hx-swap="outerHTML" title="Remove from favorites" class="flex flex-row items-center gap-1 text-red-500 hover:text-zinc-400" {
+                span class="size-5" {(svg::heart())}
+                (count)
+            }
+        } @else {
+            button hx-post=(url::item_favorite(locator)) hx-swap="outerHTML" title="Add to favorites" class="flex flex-row items-center gap-1 text-zinc-400 hover:text-red-500" {
+                span class="size-5" {(svg::heart())}
+                (count)
+            }
+        }
+    }
+}
+
+/// Contents of the add to list menu of an item, with the user's lists.
+pub fn item_list_menu(locator: &str, username: &str, lists: &[database::ItemList]) -> Markup {
+    html! {
+        @for list in lists {
+            @if list.listed {
+                button hx-delete=(url::item_list(locator, &list.slug)) hx-target="closest div" title="Remove from the list" class="flex flex-row justify-between gap-2 text-start text-violet-400 hover:text-white" {
+                    span {(list.name)}
+                    span {"✓"}
+                }
+            } @else {
+                button hx-post=(url::item_list(locator, &list.slug)) hx-target="closest div" title="Add to the list" class="text-start hover:text-violet-400" {
+                    (list.name)
+                }
+            }
+        }
+        @if lists.is_empty() {
+            span class="text-zinc-400" {"No lists yet"}
+        }
+        a href=(url::user_lists(username)) hx-boost="true" hx-target="#content" class="text-xs text-zinc-400 hover:text-white" {"Manage lists"}
+    }
+}
+
+pub fn review_replies(
+    locator: &str,
+    review_username: &str,
+    replies: &[database::Reply],
+    user: Option<&database::User>,
+    message: Option<&str>,
+) -> Markup {
+    html! {
+        @for reply in replies {
+            div class="flex flex-row gap-2" {
+                @if let Some(avatar) = &reply.user.avatar {
+                    div style=(background_image(&url::avatar(avatar), Variant::Thumbnail)) class="flex-none bg-cover bg-center size-8 rounded-full overflow-hidden" {}
+                } @else {
+                    div style={"background-color:hsl(" (reply.user.avatar_hue) ",100%,50%)"} class="flex-none grid justify-center content-center size-8 text-white rounded-full" {
+                        div class="size-6" {
+                            (svg::avatar_glyph(&reply.user.avatar_glyph))
+                        }
+                    }
+                }
+                div {
+                    div class="text-xs" {
+                        a href=(url::user(&reply.user.username)) hx-boost="true" hx-target="#content" {
+                            b {
+                                (reply.user.username)
+                            }
+                        }
+                        " " (reply.date.format("%b %d, %Y"))
+                        @if let Some(user) = user {
+                            @if user.username == reply.user.username || user.is_admin {
+                                " "
+                                button hx-delete=(url::review_reply(locator, review_username, reply.id)) hx-target={"#replies-" (locator) "-" (review_username)} {
+                                    span class="px-2 text-xs bg-zinc-700" {
+                                        "Remove reply"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    div class="whitespace-pre-line" {
+                        (reply.body)
+                    }
+                    (reaction_bar(&url::reply_reactions(locator, review_username, reply.id), &reply.reaction_counts, &reply.own_reactions, user))
+                }
+            }
+        }
+        @if replies.is_empty() {
+            div class="text-xs" {
+                "No replies yet!"
+            }
+        }
+        @if let Some(message)=message
+        {
+            div class="grid justify-center content-center px-2 min-h-8 text-center bg-orange-200 text-orange-400 rounded-[1rem]" {
+                (message)
+            }
+        }
+        @if user.is_some() {
+            form hx-post=(url::review_replies(locator, review_username)) hx-target={"#replies-" (locator) "-" (review_username)} class="flex flex-row gap-2" {
+                input class="p-2 w-full h-8 rounded-full text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" type="text" name="body" placeholder="Write a reply";
+                button class="px-4 h-8 bg-violet-400 text-black rounded-full hover:bg-black hover:text-white" type="submit" {"Reply"}
+            }
+        }
+    }
+}
+
+/// Reactions left so far, which logged in users toggle by clicking them or add from the picker.
+pub fn reaction_bar(
+    endpoint: &str,
+    counts: &[i64],
+    own: &[String],
+    user: Option<&database::User>,
+) -> Markup {
+    html! {
+        div data-reactions class="relative mt-1 flex flex-row flex-wrap items-center gap-1 text-xs" {
+            @for ((name, emoji), count) in reactions::REACTIONS.iter().zip(counts) {
+                @if *count > 0 {
+                    @let class = if own.iter().any(|reaction| reaction == name) {"px-2 rounded-full bg-violet-400 text-black"} else {"px-2 rounded-full bg-zinc-700"};
+                    @if user.is_some() {
+                        button hx-post=(endpoint) hx-vals={"{\"reaction\":\"" (name) "\"}"} hx-target="closest [data-reactions]" hx-swap="outerHTML" title=(name) class=(class) {
+                            (emoji) " " (count)
+                        }
+                    } @else {
+                        span title=(name) class=(class) {
+                            (emoji) " " (count)
+                        }
+                    }
+                }
+            }
+            @if user.is_some() {
+                button hx-get=(endpoint) hx-target="closest [data-reactions]" hx-swap="beforeend" title="Add a reaction" class="px-2 rounded-full bg-zinc-700 hover:bg-violet-400 hover:text-black" {
+                    "+"
+                }
+            }
+        }
+    }
+}
+
+pub fn reaction_picker(endpoint: &str) -> Markup {
+    html! {
+        div {
+            div data-dismiss class="fixed left-0 top-0 w-full h-full z-40" {}
+            div class="absolute left-0 top-6 z-50 flex flex-row gap-1 p-1 rounded-md bg-zinc-800 shadow-lg" {
+                @for (name, emoji) in reactions::REACTIONS {
+                    button hx-post=(endpoint) hx-vals={"{\"reaction\":\"" (name) "\"}"} hx-target="closest [data-reactions]" hx-swap="outerHTML" title=(name) class="size-8 rounded-md text-base hover:bg-zinc-700" {
+                        (emoji)
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn item_card(item: &database::Item) -> Markup {
+    html! {
+        a href=(url::item(&item.locator)) hx-boost="true" hx-target="#content" {
+            div class="group relative z-0 w-56 aspect-[3/4] rounded-md overflow-hidden outline outline-offset-2 outline-2 outline-transparent hover:outline-violet-400" {
+                div style=(background_image(&url::item_image(&item.locator, item.cover.as_deref()), Variant::Card)) class="size-full bg-cover bg-center group-hover:brightness-75 transition-[filter]" {}
+                div class="absolute w-full h-24 top-0 bg-gradient-to-b from-black to-transparent" {
+                    div class="m-2 text-white text-xs flex flex-col items-center size-fit" {
+                        div class="text-yellow-400 flex flex-row w-8" {
+                            (svg::star_left())
+                            (svg::star_right())
+                        }
+                        div {
+                            (format!("{:.2}",item.score))
+                        }
+                    }
+                    @if let Some(category) = &item.category_name {
+                        span class="absolute top-2 right-2 px-2 text-xs bg-zinc-700 text-white" {(category)}
+                    }
+                }
+                div class="absolute w-full h-24 bottom-0 text-white text-center bg-gradient-to-t from-black to-transparent flex flex-col justify-end p-4" {
+                    (item.title)
+                }
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn item_view(
+    page_opt: Option<database::Page<database::Item>>,
+    featured: &[database::Item],
+    recommended: &[database::Item],
+    user: Option<&database::User>,
+    search: Option<&str>,
+    mode: database::SearchMode,
+    filter: &database::ItemFilter,
+    categories: &[database::Category],
+    tags: &[database::TagCount],
+    infinite_scroll: bool,
+) -> Markup {
+    let (tag, category) = (filter.tag, filter.category);
+    html! {
+        @if let Some(user) = user {
+            @if user.is_admin {
+                div class="mb-4 flex flex-row flex-wrap gap-x-4 justify-center" {
+                    div class="w-56"{
+                        button hx-get=(url::path(routes::ITEM_ADD)) hx-swap="afterend" class="rounded-full p-2 bg-violet-400 hover:bg-black hover:text-white" {
+                            "Add item"
+                        }
+                    }
+                    div class="w-56"{
+                        a href=(url::path(routes::ADMIN_ITEMS)) hx-boost="true" hx-target="#content" class="block w-fit rounded-full p-2 bg-violet-400 hover:bg-black hover:text-white" {
+                            "Manage items"
+                        }
+                    }
+                    div class="w-56"{
+                        a href=(url::path(routes::ADMIN_CATEGORIES)) hx-boost="true" hx-target="#content" class="block w-fit rounded-full p-2 bg-violet-400 hover:bg-black hover:text-white" {
+                            "Categories"
+                        }
+                    }
+                    div class="w-56"{
+                        a href=(url::path(routes::ADMIN_FEATURED)) hx-boost="true" hx-target="#content" class="block w-fit rounded-full p-2 bg-violet-400 hover:bg-black hover:text-white" {
+                            "Featured"
+                        }
+                    }
+                    div class="w-56"{
+                        a href=(url::path(routes::ADMIN_COLLECTIONS)) hx-boost="true" hx-target="#content" class="block w-fit rounded-full p-2 bg-violet-400 hover:bg-black hover:text-white" {
+                            "Collections"
+                        }
+                    }
+                    div class="w-56"{
+                        a href=(url::path(routes::ADMIN_SUGGESTIONS)) hx-boost="true" hx-target="#content" class="block w-fit rounded-full p-2 bg-violet-400 hover:bg-black hover:text-white" {
+                            "Suggestions"
+                        }
+                    }
+                    div class="w-56"{
+                        a href=(url::path(routes::ADMIN_WEBHOOKS)) hx-boost="true" hx-target="#content" class="block w-fit rounded-full p-2 bg-violet-400 hover:bg-black hover:text-white" {
+                            "Webhooks"
+                        }
+                    }
+                    div class="w-56"{
+                        a href=(url::path(routes::ADMIN_JOBS)) hx-boost="true" hx-target="#content" class="block w-fit rounded-full p-2 bg-violet-400 hover:bg-black hover:text-white" {
+                            "Jobs"
+                        }
+                    }
+                    div class="w-56"{
+                        a href=(url::path(routes::ADMIN_AUDIT)) hx-boost="true" hx-target="#content" class="block w-fit rounded-full p-2 bg-violet-400 hover:bg-black hover:text-white" {
+                            "Audit log"
+                        }
+                    }
+                    div class="w-56 h-0"{}
+                }
+            } @else {
+                div class="mb-4 flex flex-row justify-center" {
+                    a href=(url::path(routes::SUGGESTIONS)) hx-boost="true" hx-target="#content" class="block w-fit rounded-full p-2 bg-violet-400 hover:bg-black hover:text-white" {
+                        "Suggest item"
+                    }
+                }
+            }
+        }
+        @if !categories.is_empty() {
+            div class="mb-4 flex flex-row flex-wrap justify-center gap-2 text-sm text-white" {
+                @let all_class = if category.is_none() {"px-2 bg-violet-400 text-black"} else {"px-2 bg-zinc-700 hover:bg-violet-400"};
+                a href=(url::path(routes::ITEMS)) hx-boost="true" hx-target="#content" class=(all_class) {"All"}
+                @for c in categories {
+                    a href=(url::items_in_category(&c.slug)) hx-boost="true" hx-target="#content" class={@if category == Some(c.slug.as_str()) {"px-2 bg-violet-400 text-black"} @else {"px-2 bg-zinc-700 hover:bg-violet-400"}} {(c.name)}
+                }
+            }
+        }
+        div class="mb-4 flex flex-row justify-center gap-2 text-sm text-white" {
+            @if let Some(tag) = tag {
+                "Tagged"
+                a href=(url::path(routes::ITEMS)) hx-boost="true" hx-target="#content" title="Clear tag" class="px-2 bg-violet-400 text-black hover:bg-black hover:text-white" {(tag) " ×"}
+            } @else {
+                a href=(url::path(routes::TAGS)) hx-boost="true" hx-target="#content" class="text-violet-400 hover:text-white" {"Browse tags"}
+                a href=(url::path(routes::REVIEWS)) hx-boost="true" hx-target="#content" class="text-violet-400 hover:text-white" {"Latest reviews"}
+            }
+        }
+        details open[filter.min_score.is_some() || filter.min_reviews.is_some()] class="mb-4 mx-auto w-full max-w-[39rem] text-sm text-white" {
+            summary class="w-fit mx-auto cursor-pointer select-none text-violet-400 hover:text-white" {"Filters"}
+            form action=(url::path(routes::ITEMS)) method="get" hx-boost="true" hx-target="#content" class="mt-2 flex flex-row flex-wrap gap-4 justify-center" {
+                @if let Some(search) = search {
+                    input type="hidden" name="search" value=(search);
+                }
+                @if let Some(mode) = mode.param() {
+                    input type="hidden" name="mode" value=(mode);
+                }
+                select name="category" aria-label="Category" class="p-2 h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" {
+                    option value="" {"Any category"}
+                    @for c in categories {
+                        option value=(c.slug) selected[category == Some(c.slug.as_str())] {(c.name)}
+                    }
+                }
+                select name="tag" aria-label="Tag" class="p-2 h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" {
+                    option value="" {"Any tag"}
+                    @for t in tags {
+                        option value=(t.name) selected[tag == Some(t.name.as_str())] {(t.name)}
+                    }
+                }
+                select name="min_score" aria-label="Lowest score" class="p-2 h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" {
+                    option value="" {"Any score"}
+                    @for s in (1..=9).rev() {
+                        option value=(s) selected[filter.min_score == Some(s as f32)] {(s) "+"}
+                    }
+                }
+                input type="number" name="min_reviews" min="0" placeholder="Fewest reviews" aria-label="Fewest reviews" value=[filter.min_reviews] class="p-2 w-48 h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400";
+                @if let Some(letter) = filter.letter {
+                    input type="hidden" name="letter" value=(letter);
+                }
+                button type="submit" class="px-4 h-8 bg-violet-400 text-black rounded-full hover:bg-black hover:text-white" {"Apply"}
+            }
+        }
+        form action=(url::path(routes::ITEMS)) method="get" hx-boost="true" hx-target="#content" aria-label="Titles starting with" class="mb-4 flex flex-row flex-wrap justify-center gap-1 text-sm" {
+            @for (name, value) in [("search", search), ("mode", mode.param()), ("tag", tag), ("category", category)] {
+                @if let Some(value) = value {
+                    input type="hidden" name=(name) value=(value);
+                }
+            }
+            @if let Some(min_score) = filter.min_score {
+                input type="hidden" name="min_score" value=(min_score);
+            }
+            @if let Some(min_reviews) = filter.min_reviews {
+                input type="hidden" name="min_reviews" value=(min_reviews);
+            }
+            @for letter in std::iter::once('#').chain('A'..='Z') {
+                @if filter.letter == Some(letter) {
+                    button type="submit" name="letter" value="" title="Clear letter" class="size-6 rounded-full bg-violet-400 text-black hover:bg-black hover:text-white" {(letter)}
+                } @else {
+                    button type="submit" name="letter" value=(letter) class="size-6 rounded-full bg-zinc-700 text-white hover:bg-violet-400 hover:text-black" {(letter)}
+                }
+            }
+        }
+        @if !featured.is_empty() {
+            div class="mb-4 flex flex-col items-center gap-2" {
+                div class="text-white text-lg" { "Featured" }
+                div class="flex flex-row gap-4 w-full overflow-x-auto snap-x snap-mandatory pb-2" {
+                    @for item in featured {
+                        div class="flex-none snap-start" {
+                            (item_card(item))
+                        }
+                    }
+                }
+            }
+        }
+        @if !recommended.is_empty() {
+            div class="mb-4 flex flex-col items-center gap-2" {
+                div class="text-white text-lg" { "Recommended for you" }
+                div class="flex flex-row flex-wrap gap-4 justify-center" {
+                    @for item in recommended {
+                        (item_card(item))
+                    }
+                }
+            }
+        }
+        @if infinite_scroll {
+            @if let Some(page) = page_opt {
+                div class="flex flex-row flex-wrap gap-4 justify-center" {
+                    (item_scroll_page(page))
+                }
+            } @else {
+                (item_grid(None, "No matching entries found!"))
+            }
+        } @else {
+            (item_grid(page_opt, "No matching entries found!"))
+        }
+    }
+}
+
+/// Id of the element loading the next page of a scrolled listing, telling its requests apart.
+pub const NEXT_PAGE_ID: &str = "next-page";
+
+/// Item cards of a page followed by a placeholder that loads the next page in its place once
+/// scrolled into view, pushing its URL so the position can be shared.
+pub fn item_scroll_page(page: database::Page<database::Item>) -> Markup {
+    html! {
+        @for item in &page.items {
+            (item_card(item))
+        }
+        @if let Some(next) = page.next_url() {
+            div id=(NEXT_PAGE_ID) hx-get=(next) hx-trigger="revealed" hx-swap="outerHTML" hx-push-url="true" class="w-full h-8" {}
+        }
+    }
+}
+
+/// A page of item cards, padded to full rows.
+fn item_grid(page_opt: Option<database::Page<database::Item>>, empty_message: &str) -> Markup {
+    html! {
+        @if let Some(page) = page_opt {
+            div class="flex flex-row flex-wrap gap-4 justify-center" {
+                @for item in &page.items {
+                    (item_card(item))
+                }
+                @for _ in 0..(page.per_page as usize).saturating_sub(page.items.len()) {
+                    div class="w-56 aspect-[3/4] bg-zinc-700 rounded-md" {}
+                }
+            }
+            (pagination(page))
+        } @else {
+            div class="mx-auto text-white grid justify-center content-center bg-zinc-700 rounded-md h-20 w-full max-w-[39rem] p-4" {
+                (empty_message)
+            }
+        }
+    }
+}
+
+pub fn chart(
+    title: &str,
+    subtitle: &str,
+    empty_message: &str,
+    page_opt: Option<database::Page<database::Item>>,
+) -> Markup {
+    html! {
+        div class="mb-4 text-center text-white" {
+            b class="text-2xl" {(title)}
+            div class="text-sm text-zinc-400" {(subtitle)}
+        }
+        (item_grid(page_opt, empty_message))
+    }
+}
+
+pub fn chart_archive(months: &[NaiveDate]) -> Markup {
+    html! {
+        div class="mb-4 text-center text-white" {
+            b class="text-2xl" {"Chart archive"}
+            div class="text-sm text-zinc-400" {"Top items of every month"}
+        }
+        div class="mx-auto flex flex-col gap-2 w-full max-w-[39rem]" {
+            @for month in months {
+                a href=(url::chart(*month)) hx-boost="true" hx-target="#content" class="p-2 text-center text-white bg-zinc-700 rounded-md hover:bg-violet-400 hover:text-black" {
+                    "Top items of " (month.format("%B %Y"))
+                }
+            }
+            @if months.is_empty() {
+                div class="grid justify-center content-center bg-zinc-700 rounded-md h-20 w-full p-4 text-white" {
+                    "No charts archived yet!"
+                }
+            }
+        }
+    }
+}
+
+/// Chart of a month, with links to the months before and after it.
+pub fn monthly_chart(
+    month: NaiveDate,
+    entries: &[charts::ChartEntry],
+    previous: Option<NaiveDate>,
+    next: Option<NaiveDate>,
+) -> Markup {
+    html! {
+        div class="mb-4 text-center text-white" {
+            b class="text-2xl" {"Top items of " (month.format("%B %Y"))}
+            div class="text-sm text-zinc-400" {
+                @if next.is_some() {
+                    "Ranking at the end of the month"
+                } @else {
+                    "Ranking so far this month"
+                }
+            }
+        }
+        div class="mx-auto flex flex-col gap-2 w-full max-w-[39rem] text-white" {
+            div class="flex flex-row justify-between text-sm" {
+                @if let Some(previous) = previous {
+                    a href=(url::chart(previous)) hx-boost="true" hx-target="#content" class="text-violet-400 hover:text-white" {"← " (previous.format("%B %Y"))}
+                } @else {
+                    span {}
+                }
+                @if let Some(next) = next {
+                    a href=(url::chart(next)) hx-boost="true" hx-target="#content" class="text-violet-400 hover:text-white" {(next.format("%B %Y")) " →"}
+                }
+            }
+            @for entry in entries {
+                div class="flex flex-row items-center gap-4 p-2 bg-zinc-700 rounded-md" {
+                    b class="w-8 text-center text-violet-400" {"#" (entry.rank)}
+                    span class="w-8 text-center text-xs text-zinc-400" {
+                        @match entry.previous_rank {
+                            None => "new",
+                            Some(previous) if previous > entry.rank => {"▲" (previous - entry.rank)},
+                            Some(previous) if previous < entry.rank => {"▼" (entry.rank - previous)},
+                            Some(_) => "=",
+                        }
+                    }
+                    a href=(url::item(&entry.locator)) hx-boost="true" hx-target="#content" class="flex-1 hover:text-violet-400" {(entry.title)}
+                    span class="text-sm" {(format!("{:.2}", entry.score)) " (" (entry.review_count) ")"}
+                }
+            }
+        }
+    }
+}
+
+pub fn admin_items(table: &admin::ItemTable, rows: &[database::ItemRow]) -> Markup {
+    html! {
+        div class="mx-auto flex flex-col gap-4 text-white w-full max-w-[64rem]" {
+            div class="flex flex-row flex-wrap gap-4 items-center justify-between" {
+                form hx-get=(url::path(routes::ADMIN_ITEMS)) hx-trigger="change" hx-target="#content" hx-push-url="true" class="flex flex-row flex-wrap gap-4" {
+                    input type="hidden" name="sort" value=(table.sort.name());
+                    @if table.descending {
+                        input type="hidden" name="desc" value="true";
+                    }
+                    @for column in admin::ItemColumn::ALL {
+                        label class="flex flex-row gap-2 items-center text-sm text-violet-400" {
+                            input class="size-4 accent-violet-400" type="checkbox" name="column" value=(column.name()) checked[table.columns.contains(&column)];
+                            (column.label())
+                        }
+                    }
+                }
+                a href=(url::admin_items_csv(&table.query_string())) download class="rounded-full p-2 bg-violet-400 hover:bg-black hover:text-white" {
+                    "Export CSV"
+                }
+                a href=(url::items_export("json")) download class="rounded-full p-2 bg-violet-400 hover:bg-black hover:text-white" {
+                    "Export catalog"
+                }
+            }
+            table class="w-full text-left" {
+                thead class="text-sm text-violet-400" {
+                    tr {
+                        @for column in &table.columns {
+                            th class="p-2" {
+                                a href=(url::admin_items(&table.sorted_by(*column).query_string())) hx-boost="true" hx-target="#content" class="hover:text-white" {
+                                    (column.label())
+                                    @if table.sort == *column {
+                                        @if table.descending {" ▼"} @else {" ▲"}
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                tbody {
+                    @for row in rows {
+                        tr class="odd:bg-zinc-800" {
+                            @for column in &table.columns {
+                                td class="p-2" {
+                                    @if *column == admin::ItemColumn::Title {
+                                        a href=(url::item(&row.locator)) hx-boost="true" hx-target="#content" class="hover:text-violet-400" {(row.title)}
+                                    } @else {
+                                        (column.value(row))
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn admin_categories(categories: &[database::Category], message: Option<&str>) -> Markup {
+    html! {
+        div class="mx-auto flex flex-col gap-4 text-white w-full max-w-[39rem]" {
+            form hx-post=(url::path(routes::ADMIN_CATEGORIES)) hx-target="#content" class="flex flex-row gap-4" {
+                input class="p-2 flex-1 h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" type="text" name="name" placeholder="New category";
+                button class="rounded-full px-4 h-8 bg-violet-400 text-black hover:bg-black hover:text-white" type="submit" {"Add category"}
+            }
+            @if let Some(message) = message {
+                div class="grid justify-center content-center px-2 min-h-8 text-center bg-orange-200 text-orange-400 rounded-[1rem]" {
+                    (message)
+                }
+            }
+            @if categories.is_empty() {
+                div class="grid justify-center content-center bg-zinc-700 rounded-md h-20 w-full p-4" {
+                    "No categories yet!"
+                }
+            }
+            @for category in categories {
+                div class="flex flex-row items-center gap-4 bg-zinc-700 rounded-md p-2" {
+                    a href=(url::items_in_category(&category.slug)) hx-boost="true" hx-target="#content" class="flex-1 hover:text-violet-400" {
+                        (category.name)
+                    }
+                    span class="text-xs text-zinc-400" {(category.item_count) " items"}
+                    button hx-delete=(url::admin_category(&category.slug)) hx-target="#content" hx-confirm={"Remove the " (category.name) " category? Its items will be left uncategorized."} {
+                        span class="px-2 text-xs bg-zinc-800" {"Remove"}
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn admin_featured(items: &[database::Item]) -> Markup {
+    html! {
+        div class="mx-auto flex flex-col gap-4 text-white w-full max-w-[39rem]" {
+            div class="text-center text-sm text-zinc-400" {
+                "Drag items to reorder the carousel shown on top of the items page. Items are featured from their own pages."
+            }
+            @if items.is_empty() {
+                div class="grid justify-center content-center bg-zinc-700 rounded-md h-20 w-full p-4" {
+                    "No featured items yet!"
+                }
+            }
+            form data-sortable hx-post=(url::path(routes::ADMIN_FEATURED)) hx-trigger="reorder" hx-target="#content" class="flex flex-col gap-2" {
+                @for item in items {
+                    div draggable="true" class="flex flex-row items-center gap-4 bg-zinc-700 rounded-md p-2 cursor-move" {
+                        input type="hidden" name="locator" value=(item.locator);
+                        span class="text-zinc-400" {"⠿"}
+                        a href=(url::item(&item.locator)) hx-boost="true" hx-target="#content" class="flex-1 hover:text-violet-400" {
+                            (item.title)
+                        }
+                        button type="button" hx-delete=(url::item_feature(&item.locator)) hx-target="closest [draggable]" hx-swap="delete" {
+                            span class="px-2 text-xs bg-zinc-800" {"Remove"}
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn admin_collections(collections: &[database::Collection], message: Option<&str>) -> Markup {
+    html! {
+        div class="mx-auto flex flex-col gap-4 text-white w-full max-w-[39rem]" {
+            form hx-post=(url::path(routes::ADMIN_COLLECTIONS)) hx-target="#content" class="flex flex-row gap-4" {
+                input class="p-2 flex-1 h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" type="text" name="name" placeholder="New collection";
+                button class="rounded-full px-4 h-8 bg-violet-400 text-black hover:bg-black hover:text-white" type="submit" {"Add collection"}
+            }
+            @if let Some(message) = message {
+                div class="grid justify-center content-center px-2 min-h-8 text-center bg-orange-200 text-orange-400 rounded-[1rem]" {
+                    (message)
+                }
+            }
+            @if collections.is_empty() {
+                div class="grid justify-center content-center bg-zinc-700 rounded-md h-20 w-full p-4" {
+                    "No collections yet!"
+                }
+            }
+            @for collection in collections {
+                div class="flex flex-row items-center gap-4 bg-zinc-700 rounded-md p-2" {
+                    a href=(url::collection(&collection.slug)) hx-boost="true" hx-target="#content" class="flex-1 hover:text-violet-400" {
+                        (collection.name)
+                    }
+                    span class="text-xs text-zinc-400" {(collection.item_count) " items"}
+                    button hx-delete=(url::admin_collection(&collection.slug)) hx-target="#content" hx-confirm={"Remove the " (collection.name) " collection? Its items will be kept."} {
+                        span class="px-2 text-xs bg-zinc-800" {"Remove"}
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn collection_page(
+    collection: &database::Collection,
+    items: &[database::Item],
+    user: Option<&database::User>,
+    message: Option<&str>,
+) -> Markup {
+    let is_admin = user.is_some_and(|user| user.is_admin);
+    html! {
+        div class="mx-auto flex flex-col items-center gap-4 text-white" {
+            div class="text-center" {
+                b class="text-2xl" {(collection.name)}
+                div class="text-sm text-zinc-400" {(collection.item_count) " items"}
+            }
+            @if is_admin {
+                form hx-post=(url::collection_items(&collection.slug)) hx-target="#content" class="flex flex-row gap-4 w-full max-w-[39rem]" {
+                    input class="p-2 flex-1 h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" type="text" name="locator" placeholder="Item locator";
+                    button class="rounded-full px-4 h-8 bg-violet-400 text-black hover:bg-black hover:text-white" type="submit" {"Add item"}
+                }
+            }
+            @if let Some(message) = message {
+                div class="grid justify-center content-center px-2 min-h-8 text-center bg-orange-200 text-orange-400 rounded-[1rem]" {
+                    (message)
+                }
+            }
+            @if items.is_empty() {
+                div class="grid justify-center content-center bg-zinc-700 rounded-md h-20 w-full max-w-[39rem] p-4" {
+                    "No items in this collection yet!"
+                }
+            }
+            div class="flex flex-row flex-wrap gap-4 justify-center" {
+                @for (index, item) in items.iter().enumerate() {
+                    div class="flex flex-col items-center gap-2" {
+                        b class="text-violet-400" {"#" (index + 1)}
+                        (item_card(item))
+                        @if is_admin {
+                            div class="flex flex-row gap-2" {
+                                button hx-post=(url::collection_item(&collection.slug, &item.locator)) hx-vals=r#"{"direction":"earlier"}"# hx-target="#content" title="Move earlier" disabled[index == 0] {
+                                    span class="px-2 text-xs bg-zinc-700" {"←"}
+                                }
+                                button hx-post=(url::collection_item(&collection.slug, &item.locator)) hx-vals=r#"{"direction":"later"}"# hx-target="#content" title="Move later" disabled[index + 1 == items.len()] {
+                                    span class="px-2 text-xs bg-zinc-700" {"→"}
+                                }
+                                button hx-delete=(url::collection_item(&collection.slug, &item.locator)) hx-target="#content" hx-confirm={"Remove " (item.title) " from the collection?"} {
+                                    span class="px-2 text-xs bg-zinc-700" {"Remove"}
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn user_lists(
+    username: &str,
+    lists: &[database::UserList],
+    user: Option<&database::User>,
+    message: Option<&str>,
+) -> Markup {
+    let is_owner = user.is_some_and(|user| user.username == username);
+    html! {
+        div class="mx-auto flex flex-col gap-4 text-white w-full max-w-[39rem]" {
+            div class="text-center" {
+                b class="text-2xl" {
+                    "Lists of "
+                    a href=(url::user(username)) hx-boost="true" hx-target="#content" class="text-violet-400 hover:text-white" {(username)}
+                }
+            }
+            @if is_owner {
+                form hx-post=(url::user_lists(username)) hx-target="#content" class="flex flex-row gap-4" {
+                    input class="p-2 flex-1 h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" type="text" name="name" placeholder="New list";
+                    button class="rounded-full px-4 h-8 bg-violet-400 text-black hover:bg-black hover:text-white" type="submit" {"Create list"}
+                }
+            }
+            @if let Some(message) = message {
+                div class="grid justify-center content-center px-2 min-h-8 text-center bg-orange-200 text-orange-400 rounded-[1rem]" {
+                    (message)
+                }
+            }
+            @if lists.is_empty() {
+                div class="grid justify-center content-center bg-zinc-700 rounded-md h-20 w-full p-4" {
+                    "No lists yet!"
+                }
+            }
+            @for list in lists {
+                div class="flex flex-row items-center gap-4 bg-zinc-700 rounded-md p-2" {
+                    a href=(url::user_list(username, &list.slug)) hx-boost="true" hx-target="#content" class="flex-1 hover:text-violet-400" {
+                        (list.name)
+                    }
+                    span class="text-xs text-zinc-400" {(list.item_count) " items"}
+                    @if is_owner {
+                        button hx-delete=(url::user_list(username, &list.slug)) hx-confirm={"Remove the " (list.name) " list?"} {
+                            span class="px-2 text-xs bg-zinc-800" {"Remove"}
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn user_list_page(
+    username: &str,
+    list: &database::UserList,
+    items: &[database::Item],
+    user: Option<&database::User>,
+    message: Option<&str>,
+) -> Markup {
+    let is_owner = user.is_some_and(|user| user.username == username);
+    html! {
+        div class="mx-auto flex flex-col items-center gap-4 text-white" {
+            div class="text-center" {
+                b class="text-2xl" {(list.name)}
+                div class="text-sm text-zinc-400" {
+                    (list.item_count) " items listed by "
+                    a href=(url::user_lists(username)) hx-boost="true" hx-target="#content" class="text-violet-400 hover:text-white" {(username)}
+                }
+            }
+            @if is_owner {
+                form hx-put=(url::user_list(username, &list.slug)) hx-target="#content" class="flex flex-row gap-4 w-full max-w-[39rem]" {
+                    input class="p-2 flex-1 h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" type="text" name="name" value=(list.name);
+                    button class="rounded-full px-4 h-8 bg-violet-400 text-black hover:bg-black hover:text-white" type="submit" {"Rename list"}
+                    button class="rounded-full px-4 h-8 bg-violet-400 text-black hover:bg-black hover:text-white" type="button" hx-delete=(url::user_list(username, &list.slug)) hx-confirm={"Remove the " (list.name) " list?"} {"Remove list"}
+                }
+            }
+            @if let Some(message) = message {
+                div class="grid justify-center content-center px-2 min-h-8 text-center bg-orange-200 text-orange-400 rounded-[1rem]" {
+                    (message)
+                }
+            }
+            @if items.is_empty() {
+                div class="grid justify-center content-center bg-zinc-700 rounded-md h-20 w-full max-w-[39rem] p-4" {
+                    "No items on this list yet!"
+                }
+            }
+            div class="flex flex-row flex-wrap gap-4 justify-center" {
+                @for (index, item) in items.iter().enumerate() {
+                    div class="flex flex-col items-center gap-2" {
+                        b class="text-violet-400" {"#" (index + 1)}
+                        (item_card(item))
+                        @if is_owner {
+                            div class="flex flex-row gap-2" {
+                                button hx-post=(url::user_list_item(username, &list.slug, &item.locator)) hx-vals=r#"{"direction":"earlier"}"# hx-target="#content" title="Move earlier" disabled[index == 0] {
+                                    span class="px-2 text-xs bg-zinc-700" {"←"}
+                                }
+                                button hx-post=(url::user_list_item(username, &list.slug, &item.locator)) hx-vals=r#"{"direction":"later"}"# hx-target="#content" title="Move later" disabled[index + 1 == items.len()] {
+                                    span class="px-2 text-xs bg-zinc-700" {"→"}
+                                }
+                                button hx-delete=(url::user_list_item(username, &list.slug, &item.locator)) hx-target="#content" hx-confirm={"Remove " (item.title) " from the list?"} {
+                                    span class="px-2 text-xs bg-zinc-700" {"Remove"}
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn suggestions(suggestions: &[database::Suggestion], message: Option<&str>) -> Markup {
+    html! {
+        div class="mx-auto flex flex-col gap-4 text-white w-full max-w-[39rem]" {
+            form hx-post=(url::path(routes::SUGGESTIONS)) hx-target="#content" class="flex flex-col gap-4" {
+                div class="flex flex-row gap-4" {
+                    input class="p-2 flex-1 h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" type="text" name="title" placeholder="Title";
+                    input class="p-2 flex-1 h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" type="text" name="locator" placeholder="Locator";
+                }
+                textarea style="scrollbar-width: none" class="p-2 w-full min-h-32 rounded-[1rem] text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" name="description" placeholder="Description" {}
+                button class="rounded-full px-4 h-8 bg-violet-400 text-black hover:bg-black hover:text-white" type="submit" {"Suggest item"}
+            }
+            @if let Some(message) = message {
+                div class="grid justify-center content-center px-2 min-h-8 text-center bg-orange-200 text-orange-400 rounded-[1rem]" {
+                    (message)
+                }
+            }
+            @if suggestions.is_empty() {
+                div class="grid justify-center content-center bg-zinc-700 rounded-md h-20 w-full p-4" {
+                    "No suggestions yet!"
+                }
+            }
+            @for suggestion in suggestions {
+                div class="flex flex-col gap-2 bg-zinc-700 rounded-md p-2" {
+                    div class="flex flex-row items-center gap-4" {
+                        @if suggestion.status == "approved" {
+                            a href=(url::item(&suggestion.locator)) hx-boost="true" hx-target="#content" class="flex-1 hover:text-violet-400" {(suggestion.title)}
+                        } @else {
+                            span class="flex-1" {(suggestion.title)}
+                        }
+                        span class="text-xs text-zinc-400" {(suggestion.date.format("%b %d, %Y"))}
+                        span class={"px-2 text-xs " @if suggestion.status == "rejected" {"bg-orange-200 text-orange-400"} @else if suggestion.status == "approved" {"bg-violet-400 text-black"} @else {"bg-zinc-800"}} {(suggestion.status)}
+                    }
+                    @if let Some(reason) = &suggestion.reason {
+                        div class="text-sm whitespace-pre-line" {(reason)}
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn admin_suggestions(suggestions: &[database::Suggestion], message: Option<&str>) -> Markup {
+    html! {
+        div class="mx-auto flex flex-col gap-4 text-white w-full max-w-[39rem]" {
+            @if let Some(message) = message {
+                div class="grid justify-center content-center px-2 min-h-8 text-center bg-orange-200 text-orange-400 rounded-[1rem]" {
+                    (message)
+                }
+            }
+            @if suggestions.is_empty() {
+                div class="grid justify-center content-center bg-zinc-700 rounded-md h-20 w-full p-4" {
+                    "No pending suggestions!"
+                }
+            }
+            @for suggestion in suggestions {
+                div class="flex flex-col gap-2 bg-zinc-700 rounded-md p-2" {
+                    div class="flex flex-row items-center gap-4" {
+                        b class="flex-1" {(suggestion.title) " " span class="text-xs text-zinc-400" {(suggestion.locator)}}
+                        a href=(url::user(&suggestion.user.username)) hx-boost="true" hx-target="#content" class="text-sm hover:text-violet-400" {(suggestion.user.username)}
+                        span class="text-xs text-zinc-400" {(suggestion.date.format("%b %d, %Y"))}
+                    }
+                    div class={"text-sm " (markdown::CLASSES)} {(markdown::render(&suggestion.description))}
+                    div class="flex flex-row gap-4" {
+                        button hx-post=(url::admin_suggestion_approve(suggestion.id)) hx-target="#content" class="rounded-full px-4 h-8 bg-violet-400 text-black hover:bg-black hover:text-white" {"Approve"}
+                        form hx-post=(url::admin_suggestion_reject(suggestion.id)) hx-target="#content" class="flex-1 flex flex-row gap-4" {
+                            input class="p-2 flex-1 h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" type="text" name="reason" placeholder="Reason";
+                            button class="rounded-full px-4 h-8 bg-zinc-800 hover:bg-black" type="submit" {"Reject"}
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn admin_webhooks(
+    hooks: &[webhooks::Webhook],
+    deliveries: &[webhooks::Delivery],
+    message: Option<&str>,
+) -> Markup {
+    html! {
+        div class="mx-auto flex flex-col gap-4 text-white w-full max-w-[39rem]" {
+            form hx-post=(url::path(routes::ADMIN_WEBHOOKS)) hx-target="#content" class="flex flex-col gap-2" {
+                div class="flex flex-row gap-4" {
+                    input class="p-2 flex-1 h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" type="url" name="url" placeholder="https://example.com/webhook";
+                    button class="rounded-full px-4 h-8 bg-violet-400 text-black hover:bg-black hover:text-white" type="submit" {"Add webhook"}
+                }
+                div class="flex flex-row flex-wrap gap-4 justify-center" {
+                    @for event in webhooks::Event::ALL {
+                        label class="flex flex-row gap-2 items-center text-sm text-violet-400" {
+                            input class="size-4 accent-violet-400" type="checkbox" name="events" value=(event.name()) checked;
+                            (event.name())
+                        }
+                    }
+                }
+            }
+            @if let Some(message) = message {
+                div class="grid justify-center content-center px-2 min-h-8 text-center bg-orange-200 text-orange-400 rounded-[1rem]" {
+                    (message)
+                }
+            }
+            @if hooks.is_empty() {
+                div class="grid justify-center content-center bg-zinc-700 rounded-md h-20 w-full p-4" {
+                    "No webhooks yet!"
+                }
+            }
+            @for hook in hooks {
+                div class="flex flex-col gap-2 bg-zinc-700 rounded-md p-2" {
+                    div class="flex flex-row items-center gap-4" {
+                        b class="flex-1 break-all" {(hook.url)}
+                        span class="text-xs text-zinc-400" {(hook.created.format("%b %d, %Y"))}
+                        button hx-delete=(url::admin_webhook(hook.id)) hx-target="#content" hx-confirm={"Remove the webhook sending to " (hook.url) "? Its pending deliveries will be dropped."} {
+                            span class="px-2 text-xs bg-zinc-800" {"Remove"}
+                        }
+                    }
+                    div class="flex flex-row flex-wrap gap-2" {
+                        @for event in &hook.events {
+                            span class="px-2 text-xs bg-zinc-800 text-violet-400" {(event)}
+                        }
+                    }
+                    div class="text-xs text-zinc-400 break-all" {
+                        "Signing secret: " code class="text-white" {(hook.secret)}
+                    }
+                }
+            }
+            @if !deliveries.is_empty() {
+                h2 class="text-center text-zinc-400" {"Recent deliveries"}
+                div class="flex flex-col gap-2" {
+                    @for delivery in deliveries {
+                        div class="flex flex-col gap-1 bg-zinc-700 rounded-md p-2 text-sm" {
+                            div class="flex flex-row items-center gap-4" {
+                                span class="text-violet-400" {(delivery.event)}
+                                span class="flex-1 text-xs text-zinc-400 break-all" {(delivery.url)}
+                                span class="text-xs text-zinc-400" {(delivery.created.format("%b %d, %Y %H:%M"))}
+                            }
+                            div class="flex flex-row items-center gap-4 text-xs" {
+                                @if let Some(delivered_at) = delivery.delivered_at {
+                                    span class="text-violet-400" {"Delivered " (delivered_at.format("%H:%M:%S"))}
+                                } @else if delivery.is_failed() {
+                                    span class="text-red-500" {"Failed"}
+                                } @else {
+                                    span class="text-zinc-400" {"Pending, next attempt " (delivery.run_at.format("%H:%M:%S"))}
+                                }
+                                @if let Some(status_code) = delivery.status_code {
+                                    span {"HTTP " (status_code)}
+                                }
+                                span class="text-zinc-400" {(delivery.attempts) "/" (webhooks::MAX_ATTEMPTS) " attempts"}
+                                @if delivery.is_failed() {
+                                    button hx-post=(url::admin_webhook_delivery(delivery.id)) hx-target="#content" {
+                                        span class="px-2 bg-zinc-800 hover:bg-violet-400" {"Retry"}
+                                    }
+                                }
+                            }
+                            @if let Some(error) = &delivery.last_error {
+                                @if delivery.delivered_at.is_none() {
+                                    div class="text-xs text-orange-400 break-all" {(error)}
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn admin_jobs(jobs: &[jobs::JobStatus], message: Option<&str>) -> Markup {
+    html! {
+        div class="mx-auto flex flex-col gap-4 text-white w-full max-w-[39rem]" {
+            @if let Some(message) = message {
+                div class="grid justify-center content-center px-2 min-h-8 text-center bg-orange-200 text-orange-400 rounded-[1rem]" {
+                    (message)
+                }
+            }
+            @if jobs.is_empty() {
+                div class="grid justify-center content-center bg-zinc-700 rounded-md h-20 w-full p-4" {
+                    "No jobs yet!"
+                }
+            }
+            @for job in jobs {
+                div class="flex flex-col gap-1 bg-zinc-700 rounded-md p-2 text-sm" {
+                    div class="flex flex-row items-center gap-4" {
+                        b class="text-violet-400" {(job.name)}
+                        span class="flex-1 text-xs text-zinc-400" {(job.description)}
+                        button hx-post=(url::admin_job_run(&job.name)) hx-target="#content" {
+                            span class="px-2 text-xs bg-zinc-800 hover:bg-violet-400" {"Run now"}
+                        }
+                    }
+                    div class="flex flex-row flex-wrap items-center gap-4 text-xs" {
+                        @if job.is_running() {
+                            span class="text-violet-400" {"Running since " (job.started_at.unwrap_or_default().format("%b %d, %H:%M:%S"))}
+                        } @else if let Some(finished_at) = job.finished_at {
+                            @if job.last_error.is_some() {
+                                span class="text-red-500" {"Failed " (finished_at.format("%b %d, %H:%M:%S"))}
+                            } @else {
+                                span class="text-violet-400" {"Succeeded " (finished_at.format("%b %d, %H:%M:%S"))}
+                            }
+                            @if let Some(duration) = job.last_duration() {
+                                span class="text-zinc-400" {"in " (duration.num_milliseconds()) " ms"}
+                            }
+                        } @else {
+                            span class="text-zinc-400" {"Never run"}
+                        }
+                        span class="text-zinc-400" {"Next run " (job.next_run_at.format("%b %d, %H:%M:%S"))}
+                        span class="text-zinc-400" {"Every " (job.interval_seconds) " s"}
+                        span class="text-zinc-400" {(job.runs) " runs, " (job.failures) " failed"}
+                    }
+                    @if let Some(error) = &job.last_error {
+                        div class="text-xs text-orange-400 break-all" {(error)}
+                    }
+                    @if let Some(result) = &job.last_result {
+                        div class="text-xs text-zinc-400" {(result)}
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn admin_audit(
+    page_opt: Option<database::Page<database::AuditEntry>>,
+    action: Option<&str>,
+    actor: Option<&str>,
+    target: Option<&str>,
+) -> Markup {
+    html! {
+        div class="mx-auto flex flex-col gap-4 items-center text-white w-full max-w-[39rem]" {
+            form hx-get=(url::path(routes::ADMIN_AUDIT)) hx-trigger="change, submit" hx-target="#content" hx-push-url="true" class="w-full flex flex-row flex-wrap gap-4 justify-center" {
+                select name="action" class="p-2 h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" {
+                    option value="" {"Any action"}
+                    @for a in &audit::ACTIONS {
+                        option value=(a.name) selected[action == Some(a.name)] {(a.label)}
+                    }
+                }
+                input type="text" placeholder="Actor" name="actor" value=[actor] class="appearance-none grow h-8 px-4 text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400 rounded-full";
+                input type="text" placeholder="Target" name="target" value=[target] class="appearance-none grow h-8 px-4 text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400 rounded-full";
+            }
+            @if let Some(page) = page_opt {
+                @for entry in &page.items {
+                    div class="w-full flex flex-col gap-1 bg-zinc-700 rounded-md p-2 text-sm" {
+                        div class="flex flex-row flex-wrap items-center gap-4" {
+                            b class="text-violet-400" {(audit::label(&entry.action))}
+                            @if let Some(link) = audit::link(&entry.action, &entry.target) {
+                                a href=(link) hx-boost="true" hx-target="#content" class="flex-1 break-all hover:text-violet-400" {(entry.target)}
+                            } @else {
+                                span class="flex-1 break-all" {(entry.target)}
+                            }
+                            span class="text-xs text-zinc-400" {
+                                @if let Some(actor) = &entry.actor {
+                                    "by " (actor)
+                                } @else {
+                                    "from the command line"
+                                }
+                                ", " (entry.date.format("%b %d, %Y %H:%M"))
+                            }
+                        }
+                        @for change in audit::changes(&entry.diff) {
+                            div class="flex flex-row gap-2 text-xs break-all" {
+                                span class="flex-none text-zinc-400" {(change.field)}
+                                span class="text-red-500" {(change.old.as_deref().unwrap_or("none"))}
+                                span class="flex-none text-zinc-400" {"→"}
+                                span class="text-violet-400" {(change.new.as_deref().unwrap_or("none"))}
+                            }
+                        }
+                    }
+                }
+                (pagination(page))
+            } @else {
+                div class="grid justify-center content-center bg-zinc-700 rounded-md h-20 w-full p-4" {
+                    "No matching actions found!"
+                }
+            }
+        }
+    }
+}
+
+pub fn tag_view(tags: &[database::TagCount]) -> Markup {
+    html! {
+        @if tags.is_empty() {
+            div class="mx-auto text-white grid justify-center content-center bg-zinc-700 rounded-md h-20 w-full max-w-[39rem] p-4" {
+                "No tags yet!"
+            }
+        } @else {
+            div class="mx-auto flex flex-row flex-wrap gap-2 justify-center w-full max-w-[39rem] text-white" {
+                @for tag in tags {
+                    a href=(url::items_tagged(&tag.name)) hx-boost="true" hx-target="#content" class="px-2 bg-zinc-700 hover:bg-violet-400" {
+                        (tag.name) " "
+                        span class="text-xs text-violet-400" {(tag.count)}
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn review_view(
+    page_opt: Option<database::Page<database::ReviewEntry>>,
+    search: Option<&str>,
+    score: Option<i16>,
+    tag: Option<&str>,
+    tags: &[database::TagCount],
+) -> Markup {
+    html! {
+        div class="mx-auto flex flex-col gap-4 items-center text-white w-full max-w-[39rem]" {
+            form hx-get=(url::path(routes::REVIEWS)) hx-trigger="change, submit" hx-target="#content" hx-push-url="true" class="w-full flex flex-row flex-wrap gap-4 justify-center" {
+                input type="text" placeholder="Search reviews" name="search" value=[search] class="appearance-none grow h-8 px-4 text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400 rounded-full";
+                select name="score" class="p-2 h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" {
+                    option value="" {"Any score"}
+                    @for s in (1..=10).rev() {
+                        option value=(s) selected[score == Some(s)] {(s) "+"}
+                    }
+                }
+                select name="tag" class="p-2 h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" {
+                    option value="" {"Any tag"}
+                    @for t in tags {
+                        option value=(t.name) selected[tag == Some(t.name.as_str())] {(t.name)}
+                    }
+                }
+            }
+            @if let Some(page) = page_opt {
+                @for review in &page.items {
+                    div class="w-full flex flex-col bg-zinc-900 rounded-md" {
+                        div class="p-4 flex flex-row gap-4 items-center" {
+                            @if let Some(avatar) = &review.user.avatar {
+                                div style=(background_image(&url::avatar(avatar), Variant::Thumbnail)) class="flex-none bg-cover bg-center size-8 rounded-full overflow-hidden" {}
+                            } @else {
+                                div style={"background-color:hsl(" (review.user.avatar_hue) ",100%,50%)"} class="flex-none grid justify-center content-center size-8 text-white rounded-full" {
+                                    div class="size-6" {
+                                        (svg::avatar_glyph(&review.user.avatar_glyph))
+                                    }
+                                }
+                            }
+                            div class="grow" {
+                                a href=(url::user(&review.user.username)) hx-boost="true" hx-target="#content" class="font-bold hover:text-violet-400" {(review.user.username)}
+                                " on "
+                                a href=(url::item(&review.locator)) hx-boost="true" hx-target="#content" class="font-bold hover:text-violet-400" {(review.title)}
+                                div class="text-xs text-zinc-400" {(review.date.format("%b %d, %Y"))}
+                            }
+                            div class="flex-none px-2 bg-violet-400 text-black" {(review.rating) "/10"}
+                        }
+                        @if review.spoiler {
+                            div data-spoiler title="Spoiler, click to reveal" class={"px-4 pb-4 blur-sm cursor-pointer " (markdown::CLASSES)} {
+                                (markdown::render(&review.body))
+                            }
+                        } @else {
+                            div class={"px-4 pb-4 " (markdown::CLASSES)} {
+                                (markdown::render(&review.body))
+                            }
+                        }
+                    }
+                }
+                (pagination(page))
+            } @else {
+                div class="grid justify-center content-center bg-zinc-700 rounded-md h-20 w-full p-4" {
+                    "No matching reviews found!"
+                }
+            }
+        }
+    }
+}
+
+pub fn notification_view(notifications: &[database::Notification]) -> Markup {
+    html! {
+        div class="mx-auto flex flex-col gap-2 text-white w-full max-w-[39rem]" {
+            @for notification in notifications {
+                a href=(url::item(&notification.locator)) hx-boost="true" hx-target="#content" class={"p-4 rounded-md hover:bg-zinc-700 " @if notification.read {"bg-zinc-900"} @else {"bg-zinc-800"}} {
+                    b {(notification.author.username)}
+                    " reviewed "
+                    b class="text-violet-400" {(notification.title)}
+                    div class="text-xs text-zinc-400" {(notification.date.format("%b %d, %Y"))}
+                }
+            }
+            @if notifications.is_empty() {
+                div class="grid justify-center content-center bg-zinc-700 rounded-md h-20 w-full p-4" {
+                    "No notifications yet!"
+                }
+            }
+        }
+    }
+}
+
+pub fn email_verified(verified: bool) -> Markup {
+    html! {
+        div class="mx-auto grid justify-center content-center bg-zinc-700 rounded-md h-20 w-full max-w-[39rem] p-4 text-white" {
+            @if verified {
+                "Your email address is confirmed!"
+            } @else {
+                "This confirmation link is invalid, expired or was already used!"
+            }
+        }
+    }
+}
+
+pub fn feed(page: Option<database::Page<database::FeedEntry>>) -> Markup {
+    html! {
+        div class="mx-auto flex flex-col gap-2 text-white w-full max-w-[39rem]" {
+            @if let Some(page) = page {
+                @for entry in &page.items {
+                    div class="p-4 rounded-md bg-zinc-900" {
+                        a href=(url::user(&entry.username)) hx-boost="true" hx-target="#content" class="hover:text-violet-400" {
+                            b {(entry.username)}
+                        }
+                        @if let (Some(slug), Some(name)) = (&entry.list_slug, &entry.list_name) {
+                            " added "
+                            a href=(url::item(&entry.locator)) hx-boost="true" hx-target="#content" class="text-violet-400 hover:text-white" {(entry.title)}
+                            " to "
+                            a href=(url::user_list(&entry.username, slug)) hx-boost="true" hx-target="#content" class="text-violet-400 hover:text-white" {(name)}
+                        } @else if let Some(rating) = entry.rating {
+                            " rated "
+                            a href=(url::item(&entry.locator)) hx-boost="true" hx-target="#content" class="text-violet-400 hover:text-white" {(entry.title)}
+                            " " b {(rating) "/10"}
+                        }
+                        div class="text-xs text-zinc-400" {(entry.date.format("%b %d, %Y"))}
+                    }
+                }
+                (pagination(page))
+            } @else {
+                div class="grid justify-center content-center bg-zinc-700 rounded-md h-20 w-full p-4 text-center" {
+                    "No activity yet! Follow users to see their ratings and lists here."
+                }
+            }
+        }
+    }
+}
+
+pub fn about(stats: &database::InstanceStats, settings: &database::Settings) -> Markup {
+    html! {
+        div class="mx-auto flex flex-col gap-4 text-white w-full max-w-[39rem]" {
+            div class="grid grid-cols-3 gap-4 text-center" {
+                @for (label, count) in [("Items", stats.items), ("Users", stats.users), ("Reviews", stats.reviews)] {
+                    div class="p-4 rounded-md bg-zinc-900" {
+                        b class="text-2xl text-violet-400" {(count)}
+                        div class="text-sm" {(label)}
+                    }
+                }
+            }
+            @if let Some(day) = stats.busiest_day {
+                div {
+                    "Most active day: " b {(day.format("%b %d, %Y"))} " with " b {(stats.busiest_day_reviews)} " reviews"
+                }
+            }
+            div {
+                "Version: " b {(version::VERSION)}
+            }
+            @if !settings.admin_contact.is_empty() {
+                div {
+                    "Contact: " b {(settings.admin_contact)}
+                }
+            }
+            div {
+                b class="text-violet-400" {"Policies"}
+                ul class="text-sm" {
+                    li {"Up to " (database::RATINGS_PER_HOUR) " items rated per hour, with " (database::RATING_COOLDOWN_SECONDS) " seconds before changing a rating again."}
+                    li {"Up to " (forms::MAX_TAGS) " tags per item and " (forms::MAX_GALLERY_UPLOAD) " gallery images per upload."}
+                }
+                @if !settings.policies.is_empty() {
+                    div class="mt-2 text-sm whitespace-pre-line" {(settings.policies)}
+                }
+            }
+        }
+    }
+}
+
+fn user_card(user: &database::User) -> Markup {
+    html! {
+        a href=(url::user(&user.username)) hx-boost="true" hx-target="#content" {
+            div class="group w-56 aspect-[3/4] grid justify-center content-center" {
+                div class="flex flex-col justify-between content-center text-white" {
+                    @if let Some(avatar) = &user.avatar
+                    {
+                        div style=(background_image(&url::avatar(avatar), Variant::Card)) class="bg-cover bg-center size-56 rounded-full group-hover:brightness-75 transition-[filter] overflow-hidden outline outline-offset-2 outline-2 outline-transparent group-hover:outline-violet-400" {}
+                    } @else {
+                        div style={"background-color:hsl(" (user.avatar_hue) ",100%,50%)"} class="relative z-0 size-56 grid justify-center content-center rounded-full group-hover:brightness-75 transition-[filter] overflow-hidden outline outline-offset-2 outline-2 outline-transparent group-hover:outline-violet-400" {
+                            div class="size-[10.5rem]"{
+                                (svg::avatar_glyph(&user.avatar_glyph))
+                            }
+                        }
+                    }
+                    div class="flex flex-row justify-center items-center pt-4"
+                    {
+                        (user.username)
+                        @if user.is_admin {
+                            span class="bg-violet-400 text-white px-2 text-xs" {
+                                b {
+                                    "admin"
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn user_view(page_opt: Option<database::Page<database::User>>) -> Markup {
+    if let Some(page) = page_opt {
+        html! {
+            div class="flex flex-row flex-wrap gap-4 justify-center" {
+                @for user in &page.items {
+                    (user_card(user))
+                }
+                @for _ in 0..(page.per_page as usize).saturating_sub(page.items.len()) {
+                    div class="w-56 aspect-[3/4] grid justify-center content-center" {
+                        div class="flex flex-col justify-between content-center text-white" {
+                            div class="size-56 bg-zinc-700 rounded-full" {}
+                            div class="min-h-10" {}
+                        }
+                    }
+
+                }
+            }
+            (pagination(page))
+        }
+    } else {
+        html! {
+            div class="mx-auto text-white grid justify-center content-center bg-zinc-700 rounded-md h-20 w-full max-w-[39rem] p-4" {
+                "No matching entries found!"
+            }
+        }
+    }
+}
+
+pub fn user_login_required(username: &str) -> Markup {
+    html! {
+        div class="mx-auto grid justify-center content-center bg-zinc-700 rounded-md h-20 w-full max-w-[39rem] p-4 text-white" {
+            "Log in to see the page of " (username) "!"
+        }
+    }
+}
+
+/// Icons of awarded badges, named in their tooltips.
+pub fn badge_icons(badges: &[String]) -> Markup {
+    html! {
+        @if !badges.is_empty() {
+            span class="inline-flex flex-row gap-1" {
+                @for badge in badges.iter().filter_map(|name| badges::get(name)) {
+                    span title=(badge.title) aria-label=(badge.title) {(badge.emoji)}
+                }
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn user_page(
+    page_user: &database::User,
+    profile: &database::Profile,
+    privacy: &database::Privacy,
+    stats: &database::UserStats,
+    compatibility: Option<&database::Compatibility>,
+    page: Option<database::Page<database::RatingUser>>,
+    user: Option<&database::User>,
+    following: Option<bool>,
+) -> Markup {
+    html! {
+        @if let Some(user) = user {
+            @if user.username == page_user.username || user.is_admin {
+                div class="mb-4 flex flex-row gap-x-4" {
+                    button hx-get=(url::user_edit(&page_user.username)) hx-swap="afterend" class="rounded-full p-2 bg-violet-400 hover:bg-black hover:text-white" {
+                        "Edit user"
+                    }
+                    @if user.username == page_user.username {
+                        button hx-get=(url::user_import(&page_user.username)) hx-swap="afterend" class="rounded-full p-2 bg-violet-400 hover:bg-black hover:text-white" {
+                            "Import ratings"
+                        }
+                    }
+                    @if !page_user.is_admin {
+                        button hx-get=(url::user_remove(&page_user.username)) hx-swap="afterend"  class="rounded-full p-2 bg-violet-400 hover:bg-black hover:text-white" {
+                            "Remove user"
+                        }
+                    }
+                    @if user.is_admin && !page_user.is_admin {
+                        button hx-post=(url::user_password_reset(&page_user.username)) hx-confirm={"Force " (page_user.username) " to set a new password? They will be logged out everywhere."} class="rounded-full p-2 bg-violet-400 hover:bg-black hover:text-white" {
+                            "Force password reset"
+                        }
+                    }
+                }
+            }
+        }
+        div class="flex flex-col gap-4 content-center items-center" {
+            div {
+                @if let Some(avatar) = &page_user.avatar {
+                    div style=(background_image(&url::avatar(avatar), Variant::Card)) class="bg-cover bg-center size-64 rounded-full overflow-hidden" {}
+                } @else {
+                    div style={"background-color:hsl(" (page_user.avatar_hue) ",100%,50%)"} class="text-white size-64 grid justify-center content-center rounded-full overflow-hidden" {
+                        div class="size-[12rem]"{
+                            (svg::avatar_glyph(&page_user.avatar_glyph))
+                        }
+                    }
+                }
+            }
+            div class="text-white" {
+                div class="flex flex-row items-center" {
+                    b class="text-2xl" {
+                        (page_user.username)
+                    }
+                    span class="ms-2 text-xl" {(badge_icons(&profile.badges))}
+                    @if page_user.is_admin {
+                        b class="bg-violet-400 px-4 text-lg" {
+                            "admin"
+                        }
+                    }
+                }
+                div class="mt-2 flex flex-row items-center justify-center gap-4" {
+                    a href=(url::user_lists(&page_user.username)) hx-boost="true" hx-target="#content" class="text-sm text-violet-400 hover:text-white" {"Lists"}
+                    @if let Some(following) = following {
+                        (follow_button(&page_user.username, following))
+                    }
+                }
+            }
+            @if profile.location.is_some() || profile.website.is_some() {
+                div class="flex flex-row items-center justify-center gap-4 text-sm text-zinc-400" {
+                    @if let Some(location) = &profile.location {
+                        span {(location)}
+                    }
+                    @if let Some(website) = &profile.website {
+                        a href=(website) rel="nofollow noopener noreferrer" target="_blank" class="text-violet-400 hover:text-white" {
+                            (website.trim_start_matches("https://").trim_start_matches("http://"))
+                        }
+                    }
+                }
+            }
+            @if let Some(bio) = &profile.bio {
+                div class={"w-full max-w-[39rem] text-white " (markdown::CLASSES)} {
+                    (markdown::render(bio))
+                }
+            }
+            @if let Some(compatibility) = compatibility {
+                div class="text-white" {
+                    "Taste compatibility: "
+                    @if let Some(percentage) = compatibility.percentage() {
+                        b class="text-violet-400" {(format!("{:.0}%", percentage))}
+                    } @else {
+                        b class="text-violet-400" {"unknown"}
+                    }
+                    " (" (compatibility.shared_count) " shared items)"
+                    @if compatibility.shared_count > 0 {
+                        " "
+                        button hx-get=(url::user_compatibility(&page_user.username)) hx-target="#content" hx-swap="beforeend" {
+                            span class="px-2 text-xs bg-zinc-700" {
+                                "Show shared items"
+                            }
+                        }
+                    }
+                }
+            }
+            @if stats.review_count > 0 {
+                div class="mx-auto flex flex-col text-white w-full gap-4 max-w-[39rem]" {
+                    div {
+                        "Mean score: " b class="text-violet-400" {(format!("{:.2}", stats.mean_score))}
+                        " Reviews: " b class="text-violet-400" {(stats.review_count)}
+                    }
+                    div class="flex flex-row [@media(max-width:39rem)]:flex-col gap-4" {
+                        div class="flex-1 flex flex-col gap-2 p-4 bg-zinc-900 text-violet-400 rounded-md" {
+                            b class="text-white" {"Score distribution"}
+                            (svg::bar_chart(&stats.distribution))
+                        }
+                        div class="flex-1 flex flex-col gap-2 p-4 bg-zinc-900 text-violet-400 rounded-md" {
+                            b class="text-white" {"Reviews per month"}
+                            (svg::bar_chart(&stats.per_month))
+                        }
+                    }
+                }
+            }
+            div hx-get=(url::user_favorites(&page_user.username)) hx-trigger="load" hx-swap="outerHTML" {}
+            div class="mx-auto flex flex-col text-white w-full gap-4 max-w-[39rem]" {
+                b {"User ratings"}
+                @if let Some(page) = page
+                {
+                    @for rating in &page.items {
+                        a href=(url::item(&rating.item.locator)) hx-boost="true" hx-target="#content" {
+                            div class="w-full p-4 h-20 flex flex-row items-center bg-zinc-900 rounded-md" {
+                                div class="basis-1/3 flex flex-row items-center" {
+                                    b class="text-xs" {
+                                        (rating.item.title)
+                                    }
+                                }
+                                div class="basis-1/3 flex flex-row size-fit justify-center" {
+                                    @for s in 0..5 {
+                                        div class={"w-6" @if (2*s+1)<=rating.rating {" text-yellow-400"} @else {" text-zinc-700"}} {
+                                            (svg::star_left())
+                                        }
+                                        div class={"w-6" @if (2*s+2)<=rating.rating {" text-yellow-400"} @else {" text-zinc-700"}} {
+                                            (svg::star_right())
+                                        }
+                                    }
+                                }
+                                div class="basis-1/3 text-center" {
+                                    (rating.date.format("%b %d, %Y"))
+                                    @if rating.private {
+                                        div {
+                                            span class="px-2 text-xs bg-zinc-700" {"private"}
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    @for _ in 0..3usize.saturating_sub(page.items.len()) {
+                        div class="grid justify-center content-center bg-zinc-700 rounded-md h-20 w-full max-w-[39rem] p-4" {}
+                    }
+                (pagination(page))
+                } @else if privacy.hides_ratings_from(&page_user.username, user) {
+                    div class="grid justify-center content-center bg-zinc-700 rounded-md h-20 w-full max-w-[39rem] p-4" {
+                        "User keeps their rating history hidden!"
+                    }
+                } @else {
+                    div class="grid justify-center content-center bg-zinc-700 rounded-md h-20 w-full max-w-[39rem] p-4" {
+                        "User has no reviews!"
+                    }
+                }
+
+            }
+        }
+    }
+}
+
+/// Notice shown while the database is unreachable, above the last cached copy of the page if
+/// there is one.
+pub fn unavailable(cached: Option<Markup>) -> Markup {
+    html! {
+        div class="mb-4 grid justify-center content-center px-2 min-h-8 text-center bg-orange-200 text-orange-400 rounded-[1rem]" {
+            (database::DatabaseError::Unavailable)
+            @if cached.is_some() {
+                " Showing a saved copy of this page."
+            }
+        }
+        @if let Some(cached) = cached {
+            (cached)
+        }
+    }
+}
+
+pub fn error_page(status: StatusCode, message: &str) -> Markup {
+    html! {
+        div class="mx-auto flex flex-col items-center gap-4 text-white w-full max-w-[39rem]" {
+            b class="text-2xl" {(status)}
+            div class="grid justify-center content-center px-2 min-h-8 w-full text-center bg-orange-200 text-orange-400 rounded-[1rem]" {(message)}
+            @if status == StatusCode::NOT_FOUND {
+                p class="text-center" {"The link may be broken or the page may have been removed. Try searching for it instead."}
+            } @else if status.is_server_error() {
+                p class="text-center" {"Something went wrong on our side. Try again in a moment."}
+            }
+            a href=(url::path(routes::ITEMS)) class="rounded-full px-4 h-8 grid content-center bg-violet-400 text-black hover:bg-black hover:text-white" {"Back to items"}
+        }
+    }
+}
+
+pub fn error_modal(message: &str) -> Markup {
+    html! {
+        div class="fixed left-0 top-0 w-full h-full flex justify-center z-50" {
+            div data-dismiss class="absolute w-full h-full bg-black/50" {}
+            div class="flex flex-col gap-4 absolute bg-zinc-800 p-4 rounded-md top-1/4 w-96" {
+                div class="grid justify-center content-center px-2 min-h-8 text-center bg-orange-200 text-orange-400 rounded-[1rem]" {(message)}
+            }
+        }
+    }
+}
+
+pub fn follow_button(username: &str, following: bool) -> Markup {
+    html! {
+        @if following {
+            button hx-delete=(url::user_follow(username)) hx-swap="outerHTML" title="Stop seeing their activity in your feed" class="rounded-full px-4 h-8 bg-zinc-700 hover:bg-black" {
+                "Following"
+            }
+        } @else {
+            button hx-post=(url::user_follow(username)) hx-swap="outerHTML" title="See their activity in your feed" class="rounded-full px-4 h-8 bg-violet-400 text-black hover:bg-black hover:text-white" {
+                "Follow"
+            }
+        }
+    }
+}
+
+pub fn user_favorites(favorites: &[database::Favorite]) -> Markup {
+    html! {
+        @if !favorites.is_empty() {
+            div class="mx-auto flex flex-col text-white w-full gap-4 max-w-[39rem]" {
+                b {"Favorites"}
+                div class="grid grid-cols-4 gap-4" {
+                    @for favorite in favorites {
+                        a href=(url::item(&favorite.locator)) hx-boost="true" hx-target="#content" title=(favorite.title) class="flex flex-col gap-1 text-xs hover:text-violet-400" {
+                            div style=(background_image(&url::item_image(&favorite.locator, favorite.cover.as_deref()), Variant::Card)) class="w-full aspect-[3/4] rounded-md bg-cover bg-center" {}
+                            span class="truncate" {(favorite.title)}
                         }
                     }
-                    @for _ in 0..3usize.checked_sub(page.items.len()).unwrap_or_default() {
-                        div class="grid justify-center content-center bg-zinc-700 rounded-md h-20 w-full max-w-[39rem] p-4" {}
-                    }
-                (pagination(page))
-                } @else {
-                    div class="grid justify-center content-center bg-zinc-700 rounded-md h-20 w-full max-w-[39rem] p-4" {
-                        "User has no reviews!"
-                    }
                 }
+            }
+        }
+    }
+}
 
+pub fn shared_ratings(username: &str, ratings: &[database::SharedRating]) -> Markup {
+    html! {
+        div class="fixed left-0 top-0 w-full h-full flex justify-center z-50" {
+            div data-dismiss class="absolute w-full h-full bg-black/50" {}
+            div class="flex flex-col gap-2 absolute bg-zinc-800 text-white p-4 rounded-md top-1/4 w-96" {
+                div class="flex flex-row text-sm text-violet-400" {
+                    div class="basis-1/2" {"Item"}
+                    div class="basis-1/4 text-center" {"You"}
+                    div class="basis-1/4 text-center" {(username)}
+                }
+                @for rating in ratings {
+                    a href=(url::item(&rating.locator)) hx-boost="true" hx-target="#content" class="flex flex-row hover:text-violet-400" {
+                        div class="basis-1/2" {(rating.title)}
+                        div class="basis-1/4 text-center" {(rating.rating)}
+                        div class="basis-1/4 text-center" {(rating.other_rating)}
+                    }
+                }
             }
         }
     }
@@ -430,22 +2241,28 @@ pub fn logged_in(user: &database::User) -> Markup {
                     }
                 }
             }
-            @if user.has_avatar {
-                    div style={"background-image:url('/static/images/avatars/" (user.username) "')"} class="ms-2 bg-cover bg-center size-8 rounded-full overflow-hidden" {}
+            @if let Some(avatar) = &user.avatar {
+                    div style=(background_image(&url::avatar(avatar), Variant::Thumbnail)) class="ms-2 bg-cover bg-center size-8 rounded-full overflow-hidden" {}
 
             } @else {
                 div style={"background-color:hsl(" (user.avatar_hue) ",100%,50%)"} class="ms-2 grid justify-center content-center size-8 text-white rounded-full" {
                     div class="size-6" {
-                        (svg::user())
+                        (svg::avatar_glyph(&user.avatar_glyph))
                     }
                 }
             }
             div class="absolute top-8 w-full hidden group-hover:block" {
                 div class="flex flex-col justify-center bg-white rounded-b-[1rem]" {
-                    a href={"/users/" (user.username)} hx-boost="true" hx-target="#content" class="text-center rounded-full h-8 grid justify-content content-center hover:bg-black hover:text-white" {
+                    a href=(url::user(&user.username)) hx-boost="true" hx-target="#content" class="text-center rounded-full h-8 grid justify-content content-center hover:bg-black hover:text-white" {
                         "Profile"
                     }
-                    button hx-post="/logout" class="rounded-full h-8 hover:bg-black hover:text-white" {
+                    a href=(url::path(routes::FEED)) hx-boost="true" hx-target="#content" class="text-center rounded-full h-8 grid justify-content content-center hover:bg-black hover:text-white" {
+                        "Feed"
+                    }
+                    a href=(url::path(routes::NOTIFICATIONS)) hx-boost="true" hx-target="#content" class="text-center rounded-full h-8 grid justify-content content-center hover:bg-black hover:text-white" {
+                        "Notifications"
+                    }
+                    button hx-post=(url::path(routes::LOGOUT)) class="rounded-full h-8 hover:bg-black hover:text-white" {
                         "Logout"
                     }
                 }
@@ -456,7 +2273,7 @@ pub fn logged_in(user: &database::User) -> Markup {
 
 pub fn login_button() -> Markup {
     html! {
-        button hx-get="/login" class="bg-white rounded-full px-4 h-8 hover:bg-black hover:text-white" {
+        button hx-get=(url::path(routes::LOGIN)) class="bg-white rounded-full px-4 h-8 hover:bg-black hover:text-white" {
             "Login"
         }
     }
@@ -465,7 +2282,7 @@ pub fn login_button() -> Markup {
 pub fn remove_form(endpoint: &str, button_prompt: &str, item: &str) -> Markup {
     html! {
         div hx-target="this" class="fixed left-0 top-0 w-full h-full flex justify-center z-50" {
-            div _="on click remove closest parent <div/>" class="absolute w-full h-full bg-black/50" {}
+            div data-dismiss class="absolute w-full h-full bg-black/50" {}
             form hx-post=(endpoint) hx-swap="outerHTML" class="flex flex-col gap-4 absolute bg-zinc-800 p-4 rounded-md top-1/4 w-96" {
                 div class="text-white text-center" {
                     "Are you absolutely sure that you want to remove " span class="text-violet-400" {(item)} "? This operation is irreversible."
@@ -476,11 +2293,102 @@ pub fn remove_form(endpoint: &str, button_prompt: &str, item: &str) -> Markup {
     }
 }
 
-pub fn user_edit_form(message: Option<&str>, username: &str) -> Markup {
+pub fn markdown_preview(source: &str) -> Markup {
+    html! {
+        div class=(markdown::CLASSES) {
+            (markdown::render(source))
+        }
+    }
+}
+
+pub fn merge_form(locator: &str, message: Option<&str>) -> Markup {
+    html! {
+        div hx-target="this" class="fixed left-0 top-0 w-full h-full flex justify-center z-50" {
+            div data-dismiss class="absolute w-full h-full bg-black/50" {}
+            form hx-post=(url::item_merge(locator)) hx-swap="outerHTML" class="flex flex-col gap-4 absolute bg-zinc-800 p-4 rounded-md top-1/4 w-96" {
+                @if let Some(message)=message
+                {
+                    div class="grid justify-center content-center px-2 min-h-8 text-center bg-orange-200 text-orange-400 rounded-[1rem]" {
+                        (message)
+                    }
+                }
+                div class="text-white text-sm" {
+                    "Reviews, tags, subscribers and images of " span class="text-violet-400" {(locator)} " will be moved to the chosen item, keeping only the latest review of users who reviewed both. Its locator will redirect to the chosen item."
+                }
+                div {
+                    label for="into" class="block mb-2 text-sm text-violet-400" {"Merge into locator"}
+                    input class="p-2 w-full h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" type="text" name="into" id="into";
+                }
+                button class="h-8 bg-violet-400 rounded-full hover:bg-black hover:text-white" type="submit" {"Merge item"}
+            }
+        }
+    }
+}
+
+pub fn import_form(username: &str, message: Option<&str>) -> Markup {
+    html! {
+        div hx-target="this" class="fixed left-0 top-0 w-full h-full flex justify-center z-50" {
+            div data-dismiss class="absolute w-full h-full bg-black/50" {}
+            form hx-post=(url::user_import_preview(username)) hx-swap="outerHTML" class="flex flex-col gap-4 absolute bg-zinc-800 p-4 rounded-md top-1/4 w-96" enctype="multipart/form-data" {
+                @if let Some(message)=message
+                {
+                    div class="grid justify-center content-center px-2 min-h-8 text-center bg-orange-200 text-orange-400 rounded-[1rem]" {
+                        (message)
+                    }
+                }
+                div class="text-white text-sm" {
+                    "Upload a CSV export with a title or locator column and a rating or score column, e.g. from MyAnimeList or Letterboxd."
+                }
+                div class="group" {
+                    label for="file" class="block mb-2 text-sm text-violet-400" {"Ratings file"}
+                    input class="w-full h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400 file:bg-violet-400 file:rounded-full file:border-none file:h-full justify-center content-center group-hover:file:text-white group-hover:file:bg-black" type="file" name="file" id="file" accept=".csv,text/csv";
+                }
+                button class="h-8 bg-violet-400 rounded-full hover:bg-black hover:text-white" type="submit" {"Preview import"}
+            }
+        }
+    }
+}
+
+pub fn import_preview(username: &str, matches: &[database::ImportMatch]) -> Markup {
+    html! {
+        div hx-target="this" class="fixed left-0 top-0 w-full h-full flex justify-center z-50" {
+            div data-dismiss class="absolute w-full h-full bg-black/50" {}
+            form hx-post=(url::user_import(username)) hx-swap="outerHTML" class="flex flex-col gap-4 absolute bg-zinc-800 text-white p-4 rounded-md top-1/4 w-96" {
+                div class="text-sm" {
+                    "Check the matched items before importing. Uncertain matches are left unchecked."
+                }
+                div class="flex flex-col gap-2 max-h-96 overflow-y-auto" {
+                    @for row in matches {
+                        div class="flex flex-row items-center gap-2 text-sm" {
+                            @if let (Some(locator), Some(title)) = (&row.locator, &row.title) {
+                                input class="size-4 accent-violet-400" type="checkbox" name=(locator) value=(row.rating) checked[row.similarity.is_some_and(|s| s >= import::CONFIDENT_MATCH)];
+                                div class="flex-1" {
+                                    (title)
+                                    @if row.query != *title && row.query != *locator {
+                                        div class="text-xs text-zinc-400" { "from \"" (row.query) "\"" }
+                                    }
+                                }
+                            } @else {
+                                div class="size-4" {}
+                                div class="flex-1 text-orange-400" { (row.query) " (no match)" }
+                            }
+                            div class="text-yellow-400" { (row.rating) "/10" }
+                        }
+                    }
+                }
+                button class="h-8 bg-violet-400 text-black rounded-full hover:bg-black hover:text-white" type="submit" {"Import ratings"}
+            }
+        }
+    }
+}
+
+pub fn user_edit_form(message: Option<&str>, username: &str, email: Option<&database::UserEmail>, profile: Option<&database::Profile>, avatar_style: Option<&database::AvatarStyle>, privacy: &database::Privacy, infinite_scroll: bool) -> Markup {
+    let hue = avatar_style.map_or(0, |style| style.hue);
+    let glyph = avatar_style.map_or("user", |style| style.glyph.as_str());
     html! {
         div hx-target="this" class="fixed left-0 top-0 w-full h-full flex justify-center z-50" {
-            div _="on click remove closest parent <div/>" class="absolute w-full h-full bg-black/50" {}
-            form hx-post={"/users/" (username) "/edit"} hx-swap="outerHTML" class="flex flex-col gap-4 absolute bg-zinc-800 p-4 rounded-md top-1/4 w-96" enctype="multipart/form-data" {
+            div data-dismiss class="absolute w-full h-full bg-black/50" {}
+            form hx-post=(url::user_edit(username)) hx-swap="outerHTML" class="flex flex-col gap-4 absolute bg-zinc-800 p-4 rounded-md top-1/4 w-96" enctype="multipart/form-data" {
                 @if let Some(message)=message
                 {
                     div class="grid justify-center content-center px-2 min-h-8 text-center bg-orange-200 text-orange-400 rounded-[1rem]" {
@@ -491,6 +2399,33 @@ pub fn user_edit_form(message: Option<&str>, username: &str) -> Markup {
                     label for="username" class="block mb-2 text-sm text-violet-400" {"Username"}
                     input class="p-2 w-full h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" type="text" name="username" id="username" value=(username) hx-preserve;
                 }
+                div {
+                    label for="email" class="block mb-2 text-sm text-violet-400" {
+                        "Email"
+                        @if email.is_some_and(|email| !email.verified) {
+                            span class="text-zinc-400" {" (unconfirmed, check your inbox)"}
+                        }
+                    }
+                    input class="p-2 w-full h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" type="email" name="email" id="email" value=[email.map(|email| &email.address)] hx-preserve;
+                    label for="weekly_email" class="flex flex-row items-center gap-2 mt-2 text-sm text-white" {
+                        input class="size-5 rounded-full accent-violet-400 checked:hover:accent-black" type="checkbox" name="weekly_email" id="weekly_email" checked[email.is_some_and(|email| email.weekly)] hx-preserve;
+                        "Email me the week's top reviews"
+                    }
+                }
+                div {
+                    label for="bio" class="block mb-2 text-sm text-violet-400" {"Bio"}
+                    textarea style="scrollbar-width: none" class="p-2 w-full min-h-20 rounded-[1rem] text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" name="bio" id="bio" maxlength="500" placeholder="Tell others about yourself, Markdown is supported" hx-preserve {
+                        @if let Some(bio) = profile.and_then(|profile| profile.bio.as_ref()) {(bio)}
+                    }
+                }
+                div {
+                    label for="location" class="block mb-2 text-sm text-violet-400" {"Location"}
+                    input class="p-2 w-full h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" type="text" name="location" id="location" maxlength="100" value=[profile.and_then(|profile| profile.location.as_ref())] hx-preserve;
+                }
+                div {
+                    label for="website" class="block mb-2 text-sm text-violet-400" {"Website"}
+                    input class="p-2 w-full h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" type="url" name="website" id="website" maxlength="200" value=[profile.and_then(|profile| profile.website.as_ref())] hx-preserve;
+                }
                 div {
                     label for="password1" class="block mb-2 text-sm text-violet-400" {"New password"}
                     input class="p-2 w-full h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" type="password" name="password1" id="password1" hx-preserve;
@@ -507,6 +2442,43 @@ pub fn user_edit_form(message: Option<&str>, username: &str) -> Markup {
                     label for="clear_avatar" class="block mb-2 text-sm text-violet-400" {"Clear avatar"}
                     input class="size-8 rounded-full accent-violet-400 checked:hover:accent-black" type="checkbox" name="clear_avatar" id="clear_avatar" hx-preserve;
                 }
+                fieldset style={"--hue:" (hue)} class="flex flex-col gap-2" {
+                    legend class="mb-2 text-sm text-violet-400" {"Placeholder avatar"}
+                    input class="w-full accent-violet-400" type="range" name="avatar_hue" id="avatar_hue" min="0" max="359" value=(hue) aria-label="Color" data-hue hx-preserve;
+                    div class="flex flex-row justify-between" {
+                        @for name in svg::AVATAR_GLYPHS {
+                            label for={"avatar_glyph_" (name)} title=(name) class="cursor-pointer" {
+                                input class="peer sr-only" type="radio" name="avatar_glyph" id={"avatar_glyph_" (name)} value=(name) checked[name == glyph] hx-preserve;
+                                div style="background-color:hsl(var(--hue),100%,50%)" class="grid justify-center content-center size-8 text-white rounded-full outline outline-offset-2 outline-2 outline-transparent peer-checked:outline-violet-400" {
+                                    div class="size-6" {
+                                        (svg::avatar_glyph(name))
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                fieldset class="flex flex-col gap-2" {
+                    legend class="mb-2 text-sm text-violet-400" {"Privacy"}
+                    @for (name, label, checked) in [
+                        ("private_ratings", "Keep all ratings private", privacy.private_ratings),
+                        ("unlisted", "Hide me from the user list and search", privacy.unlisted),
+                        ("hidden_ratings", "Hide my rating history", privacy.hidden_ratings),
+                        ("login_required", "Only show my page to logged in users", privacy.login_required),
+                    ] {
+                        label for=(name) class="flex flex-row items-center gap-2 text-sm text-white" {
+                            input class="size-5 rounded-full accent-violet-400 checked:hover:accent-black" type="checkbox" name=(name) id=(name) checked[checked] hx-preserve;
+                            (label)
+                        }
+                    }
+                }
+                fieldset class="flex flex-col gap-2" {
+                    legend class="mb-2 text-sm text-violet-400" {"Browsing"}
+                    label for="infinite_scroll" class="flex flex-row items-center gap-2 text-sm text-white" {
+                        input class="size-5 rounded-full accent-violet-400 checked:hover:accent-black" type="checkbox" name="infinite_scroll" id="infinite_scroll" checked[infinite_scroll] hx-preserve;
+                        "Load more items while scrolling instead of by page"
+                    }
+                }
                 button class="h-8 bg-violet-400 rounded-full hover:bg-black hover:text-white" type="submit" {"Edit user"}
             }
         }
@@ -517,13 +2489,15 @@ pub fn item_form(
     endpoint: &str,
     button_prompt: &str,
     message: Option<&str>,
-    title: Option<&str>,
-    locator: Option<&str>,
-    description: Option<&str>,
+    item: Option<&database::Item>,
+    tags: Option<&str>,
+    categories: &[database::Category],
+    duplicates: &[database::SimilarItem],
 ) -> Markup {
+    let adding = endpoint == url::path(routes::ITEM_ADD);
     html! {
         div hx-target="this" class="fixed left-0 top-0 w-full h-full flex justify-center z-50" {
-            div _="on click remove closest parent <div/>" class="absolute w-full h-full bg-black/50" {}
+            div data-dismiss class="absolute w-full h-full bg-black/50" {}
             form hx-post=(endpoint) hx-swap="outerHTML" class="flex flex-col gap-4 absolute bg-zinc-800 p-4 rounded-md top-1/4 w-96" enctype="multipart/form-data" {
                 @if let Some(message)=message
                 {
@@ -531,25 +2505,82 @@ pub fn item_form(
                         (message)
                     }
                 }
+                @if !duplicates.is_empty() {
+                    input type="hidden" name="allow_duplicate" value="true";
+                    ul class="flex flex-col gap-1 text-sm text-white" {
+                        @for duplicate in duplicates {
+                            li {
+                                a href=(url::item(&duplicate.locator)) target="_blank" class="hover:text-violet-400" {
+                                    (duplicate.title)
+                                }
+                            }
+                        }
+                    }
+                }
+                @if adding {
+                    div {
+                        label for="source" class="block mb-2 text-sm text-violet-400" {"Fetch details"}
+                        div class="flex flex-row gap-2" {
+                            input class="p-2 w-full h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" type="text" name="source" id="source" placeholder="OpenLibrary or TMDB link" hx-preserve;
+                            button type="button" hx-post=(url::path(routes::ITEMS_METADATA)) hx-params="source" hx-target="#metadata" hx-swap="innerHTML" class="px-4 h-8 bg-white rounded-full hover:bg-black hover:text-white" {"Fetch"}
+                        }
+                        div id="metadata" class="mt-2 empty:hidden" {}
+                    }
+                }
                 div {
                     label for="title" class="block mb-2 text-sm text-violet-400" {"Title"}
-                    input class="p-2 w-full h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" type="text" name="title" id="title" value=[title] hx-preserve;
+                    (item_title_input(item.map(|item| item.title.as_str()), false))
                 }
                 div {
                     label for="locator" class="block mb-2 text-sm text-violet-400" {"Locator"}
-                    input class="p-2 w-full h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" type="text" name="locator" id="locator" value=[locator] hx-preserve;
+                    input class="p-2 w-full h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" type="text" name="locator" id="locator" value=[item.map(|item| &item.locator)] placeholder=[adding.then_some("Generated from the title if left blank")] hx-preserve;
                 }
                 div {
                     label for="description" class="block mb-2 text-sm text-violet-400" {"Description"}
-                    textarea style="scrollbar-width: none" class="p-2 w-full min-h-32 rounded-[1rem] text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" name="description" id="description" hx-preserve {
-                        @if let Some(description) = description {
-                            (description)
+                    (item_description_input(item.map(|item| item.description.as_str()), false))
+                    button type="button" hx-post=(url::path(routes::MARKDOWN_PREVIEW)) hx-params="description" hx-target="#description-preview" hx-swap="innerHTML" class="mt-2 px-4 h-8 bg-white rounded-full hover:bg-black hover:text-white" {"Preview"}
+                    div id="description-preview" class="mt-2 text-white empty:hidden" {}
+                }
+                div {
+                    label for="release_date" class="block mb-2 text-sm text-violet-400" {"Release date"}
+                    (item_release_date_input(item.and_then(|item| item.release_date), false))
+                    label class="mt-2 flex flex-row items-center gap-2 text-sm text-white" {
+                        input class="size-4 accent-violet-400" type="checkbox" name="unreleased" checked[item.is_some_and(|item| item.unreleased)];
+                        "Unreleased, reviews open on the release date"
+                    }
+                }
+                div {
+                    label for="tags" class="block mb-2 text-sm text-violet-400" {"Tags"}
+                    input class="p-2 w-full h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" type="text" name="tags" id="tags" placeholder="horror, sci-fi" value=[tags] hx-preserve;
+                }
+                div {
+                    label for="category" class="block mb-2 text-sm text-violet-400" {"Category"}
+                    select class="p-2 w-full h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" name="category" id="category" hx-preserve {
+                        option value="" {"None"}
+                        @for category in categories {
+                            option value=(category.slug) selected[item.is_some_and(|item| item.category_slug.as_ref() == Some(&category.slug))] {(category.name)}
                         }
                     }
                 }
                 div class="group" {
                     label for="image" class="block mb-2 text-sm text-violet-400" {"Cover image"}
                     input class="w-full h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400 file:bg-violet-400 file:rounded-full file:border-none file:h-full justify-center content-center group-hover:file:text-white group-hover:file:bg-black" type="file" name="image" id="image" accept="image/*" hx-preserve;
+                    @if item.is_some() {
+                        label class="mt-2 flex flex-row items-center gap-2 text-sm text-white" {
+                            input class="size-4 accent-violet-400" type="checkbox" name="clear_image";
+                            "Clear cover, a generated one is shown instead"
+                        }
+                    } @else {
+                        p class="mt-2 text-sm text-white" {"Optional, a cover is generated from the title without one"}
+                    }
+                }
+                div {
+                    label for="cover_url" class="block mb-2 text-sm text-violet-400" {"Cover URL"}
+                    (item_cover_url_input(None, false))
+                }
+                div class="group" {
+                    label for="gallery" class="block mb-2 text-sm text-violet-400" {"Gallery images"}
+                    input class="w-full h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400 file:bg-violet-400 file:rounded-full file:border-none file:h-full justify-center content-center group-hover:file:text-white group-hover:file:bg-black" type="file" name="gallery" id="gallery" accept="image/*" multiple hx-preserve;
                 }
                 button class="h-8 bg-violet-400 rounded-full hover:bg-black hover:text-white" type="submit" {(button_prompt)}
             }
@@ -557,12 +2588,101 @@ pub fn item_form(
     }
 }
 
+// Item form fields are also swapped in out of band when details are fetched from a catalog.
+fn item_title_input(title: Option<&str>, oob: bool) -> Markup {
+    html! {
+        input class="p-2 w-full h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" type="text" name="title" id="title" value=[title] hx-swap-oob=[oob.then_some("true")] hx-preserve;
+    }
+}
+
+fn item_description_input(description: Option<&str>, oob: bool) -> Markup {
+    html! {
+        textarea style="scrollbar-width: none" class="p-2 w-full min-h-32 rounded-[1rem] text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" name="description" id="description" hx-swap-oob=[oob.then_some("true")] hx-preserve {
+            @if let Some(description) = description {
+                (description)
+            }
+        }
+    }
+}
+
+fn item_cover_url_input(cover_url: Option<&str>, oob: bool) -> Markup {
+    html! {
+        input class="p-2 w-full h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" type="url" name="cover_url" id="cover_url" value=[cover_url] placeholder="Downloaded unless a cover image is chosen" hx-swap-oob=[oob.then_some("true")] hx-preserve;
+    }
+}
+
+fn item_release_date_input(release_date: Option<NaiveDate>, oob: bool) -> Markup {
+    html! {
+        input class="p-2 w-full h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" type="date" name="release_date" id="release_date" value=[release_date.map(|date| date.format("%Y-%m-%d").to_string())] hx-swap-oob=[oob.then_some("true")] hx-preserve;
+    }
+}
+
+/// Details fetched from a catalog, filling in the add item form.
+pub fn item_metadata(metadata: Result<&metadata::Metadata, String>) -> Markup {
+    html! {
+        @match metadata {
+            Ok(metadata) => {
+                @if let Some(cover_url) = &metadata.cover_url {
+                    (item_cover_url_input(Some(cover_url), true))
+                    div class="flex flex-row items-center gap-2 text-sm text-white" {
+                        img src=(cover_url) alt="Fetched cover" class="h-16 rounded-md";
+                        "Used as the cover unless another image is chosen"
+                    }
+                }
+                (item_title_input(Some(&metadata.title), true))
+                (item_description_input(Some(&metadata.description), true))
+                (item_release_date_input(metadata.release_date, true))
+            }
+            Err(message) => {
+                div class="grid justify-center content-center px-2 min-h-8 text-center bg-orange-200 text-orange-400 rounded-[1rem]" {
+                    (message)
+                }
+            }
+        }
+    }
+}
+
+pub fn review_form(locator: &str, review: Option<&database::Review>) -> Markup {
+    html! {
+        div hx-target="this" class="fixed left-0 top-0 w-full h-full flex justify-center z-50" {
+            div data-dismiss class="absolute w-full h-full bg-black/50" {}
+            form hx-post=(url::item_rate(locator)) class="flex flex-col gap-4 absolute bg-zinc-800 p-4 rounded-md top-1/4 w-96" {
+                div {
+                    label for="score" class="block mb-2 text-sm text-violet-400" {"Score"}
+                    select class="p-2 w-full h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" name="score" id="score" {
+                        @for s in (1..=10).rev() {
+                            option value=(s) selected[review.is_some_and(|r| r.rating == s)] {(s)}
+                        }
+                    }
+                }
+                div {
+                    label for="body" class="block mb-2 text-sm text-violet-400" {"Review"}
+                    textarea style="scrollbar-width: none" class="p-2 w-full min-h-32 rounded-[1rem] text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" name="body" id="body" {
+                        @if let Some(body) = review.and_then(|r| r.body.as_ref()) {
+                            (body)
+                        }
+                    }
+                }
+                div {
+                    label for="spoiler" class="block mb-2 text-sm text-violet-400" {"Contains spoilers"}
+                    input class="size-8 rounded-full accent-violet-400 checked:hover:accent-black" type="checkbox" name="spoiler" id="spoiler" checked[review.is_some_and(|r| r.spoiler)];
+                }
+                div {
+                    label for="private" class="block mb-2 text-sm text-violet-400" {"Private"}
+                    input class="size-8 rounded-full accent-violet-400 checked:hover:accent-black" type="checkbox" name="private" id="private" checked[review.is_some_and(|r| r.private)];
+                }
+                button class="h-8 bg-violet-400 rounded-full hover:bg-black hover:text-white" type="submit" {"Save review"}
+            }
+        }
+    }
+}
+
 pub fn login_form(message: Option<&str>) -> Markup {
     html! {
         (login_button())
         div class="fixed left-0 top-0 w-full h-full flex justify-center z-50" {
-            div _="on click remove closest parent <div/>" class="absolute w-full h-full bg-black/50" {}
-            form hx-post="/login" class="flex flex-col gap-4 absolute bg-zinc-800 p-4 rounded-md top-1/4 w-96" {
+            div data-dismiss class="absolute w-full h-full bg-black/50" {}
+            form hx-post=(url::path(routes::LOGIN)) class="flex flex-col gap-4 absolute bg-zinc-800 p-4 rounded-md top-1/4 w-96" {
                 @if let Some(message)=message
                 {
                     div class="grid justify-center content-center px-2 min-h-8 text-center bg-orange-200 text-orange-400 rounded-[1rem]" {
@@ -578,7 +2698,34 @@ pub fn login_form(message: Option<&str>) -> Markup {
                     input class="p-2 w-full h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" type="password" name="password" id="password" hx-preserve;
                 }
                 button class="h-8 bg-violet-400 rounded-full hover:bg-black hover:text-white transition-colors" type="submit" {"Login"}
-                button hx-get="/register" class="h-8 bg-white rounded-full hover:bg-black hover:text-white" {"Register"}
+                button hx-get=(url::path(routes::REGISTER)) class="h-8 bg-white rounded-full hover:bg-black hover:text-white" {"Register"}
+            }
+        }
+    }
+}
+
+pub fn password_reset_form(message: Option<&str>) -> Markup {
+    html! {
+        (login_button())
+        div class="fixed left-0 top-0 w-full h-full flex justify-center z-50" {
+            div data-dismiss class="absolute w-full h-full bg-black/50" {}
+            form hx-post=(url::path(routes::PASSWORD_RESET)) class="flex flex-col gap-4 absolute bg-zinc-800 p-4 rounded-md top-1/4 w-96" {
+                div class="grid justify-center content-center px-2 min-h-8 text-center bg-orange-200 text-orange-400 rounded-[1rem]" {
+                    @if let Some(message) = message {
+                        (message)
+                    } @else {
+                        (database::DatabaseError::PasswordResetRequired)
+                    }
+                }
+                div {
+                    label for="password1" class="block mb-2 text-sm text-violet-400" {"New password"}
+                    input class="p-2 w-full h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" type="password" name="password1" id="password1";
+                }
+                div {
+                    label for="password2" class="block mb-2 text-sm text-violet-400" {"Repeat new password"}
+                    input class="p-2 w-full h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" type="password" name="password2" id="password2";
+                }
+                button class="h-8 bg-violet-400 rounded-full hover:bg-black hover:text-white transition-colors" type="submit" {"Set password"}
             }
         }
     }
@@ -588,8 +2735,8 @@ pub fn register_form(message: Option<&str>) -> Markup {
     html! {
         (login_button())
         div class="fixed left-0 top-0 w-full h-full flex justify-center z-50" {
-            div _="on click remove closest parent <div/>" class="absolute w-full h-full bg-black/50" {}
-            form hx-post="/register" class="flex flex-col gap-4 absolute bg-zinc-800 p-4 rounded-md top-1/4 w-96" {
+            div data-dismiss class="absolute w-full h-full bg-black/50" {}
+            form hx-post=(url::path(routes::REGISTER)) class="flex flex-col gap-4 absolute bg-zinc-800 p-4 rounded-md top-1/4 w-96" {
                 @if let Some(message)=message
                 {
                     div class="grid justify-center content-center px-2 min-h-8 text-center bg-orange-200 text-orange-400 rounded-[1rem]" {
@@ -609,33 +2756,66 @@ pub fn register_form(message: Option<&str>) -> Markup {
                     input class="p-2 w-full h-8 rounded-full text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-violet-400" type="password" name="password2" id="password2" hx-preserve;
                 }
                 button class="h-8 bg-violet-400 rounded-full hover:bg-black hover:text-white transition-colors" type="submit" {"Register"}
-                button hx-get="/login" class="h-8 bg-white rounded-full hover:bg-black hover:text-white transition-colors" {"Login"}
+                button hx-get=(url::path(routes::LOGIN)) class="h-8 bg-white rounded-full hover:bg-black hover:text-white transition-colors" {"Login"}
             }
         }
     }
 }
 
+/// Query syntax of the item search box, listed in its help popover.
+const SEARCH_SYNTAX: [(&str, &str); 5] = [
+    ("\"a phrase\"", "Contains the exact phrase"),
+    ("tag:rpg", "Has the tag"),
+    ("category:anime", "Belongs to the category"),
+    ("score:>8", "Score above 8, also >=, < and <="),
+    ("reviews:>=10", "At least 10 reviews"),
+];
+
 pub fn search(target: &str, content: Option<Markup>) -> Markup {
+    let targets = [
+        (routes::SEARCH_RESULTS, "all", "All"),
+        (routes::ITEMS, "items", "Items"),
+        (routes::USERS, "users", "Users"),
+    ];
     html! {
-        form action=(target) method="get" hx-boost="true" hx-target="#content" hx-trigger="input changed from:input delay:500ms" class="absolute w-full" {
+        form action=(url::path(target)) method="get" hx-boost="true" hx-target="#content" hx-trigger="input changed from:input delay:500ms, change from:input[name='mode']" class="absolute w-full" {
             input autofocus type="text" placeholder="Search" name="search" class="appearance-none w-full h-8 text-center text-black bg-white outline outline-offset-2 outline-2 outline-transparent focus:outline-white rounded-full" {}
+            @if target == routes::ITEMS || target == routes::SEARCH_RESULTS {
+                div class="absolute left-0 top-0 flex flex-row items-center gap-2 h-8 px-4 text-sm select-none" {
+                    label title="Match words in titles and descriptions instead of similar titles" class="flex flex-row items-center gap-2" {
+                        input class="size-4 accent-violet-400" type="checkbox" name="mode" value="text";
+                        "Full text"
+                    }
+                    div class="relative group" {
+                        div class="grid content-center justify-center size-4 rounded-full bg-zinc-700 text-white text-xs" {"?"}
+                        div class="absolute top-6 left-0 z-10 w-64 hidden group-hover:block p-2 rounded-md bg-white text-black text-left shadow-lg" {
+                            table class="w-full" {
+                                @for (syntax, meaning) in SEARCH_SYNTAX {
+                                    tr {
+                                        td class="px-2" { code {(syntax)} }
+                                        td {(meaning)}
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
         }
         div class="absolute right-0 z-10" {
             div class="relative group grid justify-content content-center bg-white px-4 h-8 rounded-[1rem] hover:rounded-b-none select-none" {
-                @if target=="/items" {
-                    "Items"
-                } @else if target=="/users" {
-                    "Users"
+                @for (path, _, label) in targets {
+                    @if path == target {
+                        (label)
+                    }
                 }
                 div class="absolute top-8 w-full hidden group-hover:block" {
                     div class="flex flex-col justify-center bg-white rounded-b-[1rem]" {
-                        @if target=="/items" {
-                            button hx-get="/search?target=users" class="rounded-full h-8 hover:bg-black hover:text-white" {
-                                "Users"
-                            }
-                        } @else if target=="/users" {
-                            button hx-get="/search?target=items" class="rounded-full h-8 hover:bg-black hover:text-white" {
-                                "Items"
+                        @for (path, name, label) in targets {
+                            @if path != target {
+                                button hx-get=(url::search(name)) class="rounded-full h-8 hover:bg-black hover:text-white" {
+                                    (label)
+                                }
                             }
                         }
                     }
@@ -650,7 +2830,113 @@ pub fn search(target: &str, content: Option<Markup>) -> Markup {
     }
 }
 
-pub fn index(content: Markup, search_target: &str, user: Option<&database::User>) -> Markup {
+/// Items and users matching a search, each section linking to its full listing when it has more
+/// than one page.
+pub fn search_results(
+    search: Option<&str>,
+    items: Option<database::Page<database::Item>>,
+    users: Option<database::Page<database::User>>,
+) -> Markup {
+    html! {
+        @if search.is_none() || (items.is_none() && users.is_none()) {
+            div class="mx-auto text-white grid justify-center content-center bg-zinc-700 rounded-md h-20 w-full max-w-[39rem] p-4" {
+                @if search.is_none() {
+                    "Type to search items and users!"
+                } @else {
+                    "No matching entries found!"
+                }
+            }
+        }
+        @if let Some(page) = items {
+            div class="mb-4 flex flex-col items-center gap-2" {
+                div class="flex flex-row items-center gap-4 text-white" {
+                    div class="text-lg" {"Items"}
+                    @if page.number_of_pages > 1 {
+                        a href=(page.url(0)) hx-boost="true" hx-target="#content" class="text-sm text-violet-400 hover:text-white" {"See all"}
+                    }
+                }
+                div class="flex flex-row flex-wrap gap-4 justify-center" {
+                    @for item in &page.items {
+                        (item_card(item))
+                    }
+                }
+            }
+        }
+        @if let Some(page) = users {
+            div class="mb-4 flex flex-col items-center gap-2" {
+                div class="flex flex-row items-center gap-4 text-white" {
+                    div class="text-lg" {"Users"}
+                    @if page.number_of_pages > 1 {
+                        a href=(page.url(0)) hx-boost="true" hx-target="#content" class="text-sm text-violet-400 hover:text-white" {"See all"}
+                    }
+                }
+                div class="flex flex-row flex-wrap gap-4 justify-center" {
+                    @for user in &page.items {
+                        (user_card(user))
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn debug_footer(stats: &metrics::PageStats, oob: bool) -> Markup {
+    let percentiles = &stats.percentiles;
+    html! {
+        div id="debug-footer" hx-swap-oob=[oob.then_some("true")] class={"mx-auto w-full max-w-screen-lg p-2 text-xs text-center " @if stats.elapsed > metrics::BUDGET {"bg-orange-200 text-orange-400"} @else {"bg-zinc-700 text-white"}} {
+            "Rendered in " (stats.elapsed.as_millis()) " ms, " (stats.queries) " queries taking " (stats.query_time.as_millis()) " ms. "
+            (stats.route) ": p50 " (percentiles.p50.as_millis()) " ms, p95 " (percentiles.p95.as_millis()) " ms, p99 " (percentiles.p99.as_millis()) " ms over " (percentiles.samples) " requests"
+        }
+    }
+}
+
+pub fn index(
+    content: Markup,
+    search_target: &str,
+    user: Option<&database::User>,
+    links: Option<&database::PageLinks>,
+) -> Markup {
+    index_with_meta(content, search_target, user, links, html! {})
+}
+
+/// OpenGraph tags of an item page, so that links to it unfurl into its share card.
+pub fn item_meta(item: &database::Item, origin: &proxy::Origin) -> Markup {
+    let description: String = item
+        .description
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(200)
+        .collect();
+    html! {
+        meta property="og:type" content="website";
+        meta property="og:title" content=(item.title);
+        meta property="og:description" content=(description);
+        meta property="og:url" content=(origin.link(&url::item(&item.locator)));
+        meta property="og:image" content=(origin.link(&url::item_card(&item.locator)));
+        meta property="og:image:width" content=(cards::WIDTH);
+        meta property="og:image:height" content=(cards::HEIGHT);
+        meta name="twitter:card" content="summary_large_image";
+    }
+}
+
+/// [`index`] with extra tags in the head, such as the [`item_meta`] of an item page.
+pub fn index_with_meta(
+    content: Markup,
+    search_target: &str,
+    user: Option<&database::User>,
+    links: Option<&database::PageLinks>,
+    meta: Markup,
+) -> Markup {
+    let nonce = security::nonce();
+    let font = url::static_file(assets::FONT);
+    let htmx_config = serde_json::json!({
+        "scrollIntoViewOnBoost": false,
+        "includeIndicatorStyles": false,
+        "allowEval": false,
+        "inlineScriptNonce": nonce.as_deref().unwrap_or_default(),
+    });
     html! {
         (DOCTYPE)
         html {
@@ -661,22 +2947,43 @@ pub fn index(content: Markup, search_target: &str, user: Option<&database::User>
                 meta charset="UTF-8";
                 meta name="author" content="Jakub Grodzki 240675";
                 meta name="viewport" content="width=device-width, initial-scale=1.0";
-                meta name="htmx-config" content="{\"scrollIntoViewOnBoost\":false}";
-                script src="https://unpkg.com/htmx.org@1.9.11" {}
-                script src="https://unpkg.com/hyperscript.org@0.9.12" {}
-                link rel="stylesheet" href="/static/style.css";
-                link rel="icon" href="/static/icon.png";
-                link rel="preconnect" href="https://fonts.googleapis.com";
-                link rel="preconnect" href="https://fonts.gstatic.com" crossorigin;
-                link href="https://fonts.googleapis.com/css2?family=Quicksand:wght@500&display=swap" rel="stylesheet";
-
+                meta name="htmx-config" content=(htmx_config);
+                script src=(url::static_file(assets::HTMX)) nonce=[&nonce] {}
+                script src=(url::path(routes::SCRIPTS)) nonce=[&nonce] {}
+                link rel="stylesheet" href=(url::static_file("style.css"));
+                link rel="icon" href=(url::static_file("icon.png"));
+                link rel="preload" href=(font) as="font" type="font/woff2" crossorigin;
+                style nonce=[&nonce] {
+                    (PreEscaped(format!("@font-face{{font-family:Quicksand;font-weight:500;font-display:swap;src:url({font}) format(\"woff2\")}}")))
+                }
+                @if let Some(links) = links {
+                    link rel="canonical" href=(links.canonical);
+                    @if let Some(prev) = &links.prev {
+                        link rel="prev" href=(prev);
+                    }
+                    @if let Some(next) = &links.next {
+                        link rel="next" href=(next);
+                    }
+                }
+                (meta)
             }
             body class="flex flex-col bg-zinc-900 min-h-screen min-w-[31rem] font-[Quicksand]" {
                 header class="top-0 sticky z-40 flex justify-between items-center bg-violet-400 text-black mx-auto w-full max-w-screen-lg p-4" {
-                    div class="flex h-8 justify-start basis-1/4" {
-                        a href="/" hx-boost="true" hx-target="#content" {
+                    div class="flex h-8 justify-start items-center gap-4 basis-1/4" {
+                        a href=(url::path(routes::INDEX)) hx-boost="true" hx-target="#content" {
                             (svg::logo())
                         }
+                        div class="relative z-10 group grid content-center bg-white px-4 h-8 rounded-[1rem] hover:rounded-b-none select-none" {
+                            "Charts"
+                            div class="absolute top-8 left-0 w-full hidden group-hover:block" {
+                                div class="flex flex-col justify-center bg-white rounded-b-[1rem]" {
+                                    a href=(url::path(routes::ITEMS)) hx-boost="true" hx-target="#content" class="grid justify-center content-center rounded-full h-8 hover:bg-black hover:text-white" {"Top"}
+                                    a href=(url::path(routes::ITEMS_TRENDING)) hx-boost="true" hx-target="#content" class="grid justify-center content-center rounded-full h-8 hover:bg-black hover:text-white" {"Trending"}
+                                    a href=(url::path(routes::ITEMS_NEW)) hx-boost="true" hx-target="#content" class="grid justify-center content-center rounded-full h-8 hover:bg-black hover:text-white" {"New"}
+                                    a href=(url::path(routes::CHARTS)) hx-boost="true" hx-target="#content" class="grid justify-center content-center rounded-full h-8 hover:bg-black hover:text-white" {"Archive"}
+                                }
+                            }
+                        }
                     }
                     div class="relative z-10 h-8 rounded-full w-1/2 flex flex-row mx-4" hx-target="this" {
                         (search(search_target, None))
@@ -694,6 +3001,15 @@ pub fn index(content: Markup, search_target: &str, user: Option<&database::User>
                 div id="content" class="min-h-full flex-1 bg-zinc-800 mx-auto w-full max-w-screen-lg p-4" {
                     (content)
                 }
+                footer class="mx-auto w-full max-w-screen-lg p-2 text-xs text-center bg-zinc-900 text-white" {
+                    a href=(url::path(routes::ABOUT)) hx-boost="true" hx-target="#content" class="text-violet-400 hover:text-white" {"About"}
+                    @if user.is_some_and(|user| user.is_admin) {
+                        " · "
+                        a href=(url::path(routes::VERSION)) class="hover:text-violet-400" {
+                            (version::VERSION) " (" (version::COMMIT) ", built " (version::built()) ", migration " (version::MIGRATION) ")"
+                        }
+                    }
+                }
             }
         }
     }
@@ -0,0 +1,106 @@
+//! Client side behaviour that htmx does not cover, served as a self-hosted script so that pages
+//! work under a strict Content-Security-Policy without inline handlers.
+
+/// Elements marked with `data-dismiss` remove their parent (the modal wrapper) when clicked.
+/// Elements marked with `data-spoiler` are unblurred when clicked.
+/// Elements marked with `data-lightbox-open` show the gallery image at that index in the
+/// `data-lightbox` overlay, which steps through the images of `data-lightbox-src` elements.
+/// Escape closes the open lightbox or the topmost modal, arrow keys step through the gallery.
+/// Children of `data-sortable` lists are reordered by dragging, after which the list's form is
+/// sent with a `reorder` event.
+/// Elements marked with `data-countdown` count down every second to that instant.
+/// Range inputs marked with `data-hue` set the `--hue` of their parent as they move, tinting
+/// the avatar previews in it.
+pub const SCRIPT: &str = r#"function showImage(lightbox, index) {
+    const sources = [...document.querySelectorAll("[data-lightbox-src]")].map((image) => image.dataset.lightboxSrc);
+    const count = sources.length;
+    lightbox.dataset.index = ((index % count) + count) % count;
+    lightbox.querySelector("[data-lightbox-image]").src = sources[lightbox.dataset.index];
+}
+
+document.addEventListener("click", (event) => {
+    const dismiss = event.target.closest("[data-dismiss]");
+    if (dismiss) {
+        dismiss.parentElement.remove();
+        return;
+    }
+    const spoiler = event.target.closest("[data-spoiler]");
+    if (spoiler) {
+        spoiler.classList.remove("blur-sm", "cursor-pointer");
+        spoiler.removeAttribute("data-spoiler");
+        spoiler.removeAttribute("title");
+        return;
+    }
+    const lightbox = document.querySelector("[data-lightbox]");
+    if (!lightbox) {
+        return;
+    }
+    const open = event.target.closest("[data-lightbox-open]");
+    if (open) {
+        showImage(lightbox, Number(open.dataset.lightboxOpen));
+        lightbox.classList.replace("hidden", "flex");
+    } else if (event.target.closest("[data-lightbox-close]")) {
+        lightbox.classList.replace("flex", "hidden");
+    } else {
+        const step = event.target.closest("[data-lightbox-step]");
+        if (step) {
+            showImage(lightbox, Number(lightbox.dataset.index) + Number(step.dataset.lightboxStep));
+        }
+    }
+});
+
+document.addEventListener("keydown", (event) => {
+    const lightbox = document.querySelector("[data-lightbox].flex");
+    if (event.key === "Escape") {
+        const modals = document.querySelectorAll("[data-dismiss]");
+        if (lightbox) {
+            lightbox.classList.replace("flex", "hidden");
+        } else if (modals.length > 0) {
+            modals[modals.length - 1].parentElement.remove();
+        }
+    } else if (lightbox && (event.key === "ArrowLeft" || event.key === "ArrowRight")) {
+        showImage(lightbox, Number(lightbox.dataset.index) + (event.key === "ArrowLeft" ? -1 : 1));
+    }
+});
+
+let dragged = null;
+
+document.addEventListener("dragstart", (event) => {
+    dragged = event.target.closest("[data-sortable] > *");
+});
+
+document.addEventListener("dragover", (event) => {
+    const over = event.target.closest("[data-sortable] > *");
+    if (!dragged || !over || over.parentElement !== dragged.parentElement) {
+        return;
+    }
+    event.preventDefault();
+    if (over !== dragged) {
+        const box = over.getBoundingClientRect();
+        over.parentElement.insertBefore(dragged, event.clientY > box.top + box.height / 2 ? over.nextSibling : over);
+    }
+});
+
+document.addEventListener("dragend", () => {
+    if (dragged) {
+        htmx.trigger(dragged.closest("form"), "reorder");
+        dragged = null;
+    }
+});
+
+document.addEventListener("input", (event) => {
+    if (event.target.matches("[data-hue]")) {
+        event.target.parentElement.style.setProperty("--hue", event.target.value);
+    }
+});
+
+setInterval(() => {
+    for (const countdown of document.querySelectorAll("[data-countdown]")) {
+        const seconds = Math.max(0, Math.floor((Date.parse(countdown.dataset.countdown) - Date.now()) / 1000));
+        const time = [Math.floor(seconds / 3600) % 24, Math.floor(seconds / 60) % 60, seconds % 60]
+            .map((part) => String(part).padStart(2, "0"))
+            .join(":");
+        countdown.textContent = `${Math.floor(seconds / 86400)}d ${time}`;
+    }
+}, 1000);
+"#;
@@ -0,0 +1,42 @@
+use sqlx::types::chrono::DateTime;
+
+/// Version of the application package.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Short hash of the commit the binary was built from.
+pub const COMMIT: &str = env!("ZAI_COMMIT");
+/// Version of the newest migration the binary applies.
+pub const MIGRATION: &str = env!("ZAI_MIGRATION");
+
+/// Time the binary was built at, in UTC.
+pub fn built() -> String {
+    env!("ZAI_BUILT")
+        .parse()
+        .ok()
+        .and_then(|seconds| DateTime::from_timestamp(seconds, 0))
+        .map_or_else(
+            || "unknown".to_owned(),
+            |built| built.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+        )
+}
+
+/// Renders build information as `key value` lines.
+pub fn render() -> String {
+    format!(
+        "version {VERSION}\ncommit {COMMIT}\nbuilt {}\nmigration {MIGRATION}\n",
+        built()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_information_is_compiled_in() {
+        let rendered = render();
+        assert!(rendered.starts_with(&format!("version {VERSION}\n")));
+        assert!(!COMMIT.is_empty());
+        assert_ne!(built(), "unknown");
+        assert!(MIGRATION.chars().all(|c| c.is_ascii_digit()) && !MIGRATION.is_empty());
+    }
+}
@@ -0,0 +1,313 @@
+//! Webhooks telling other services about changes to the catalog and its reviews. Every event is
+//! queued in the `webhook_deliveries` table once for each webhook subscribed to it and POSTed as
+//! JSON by a background worker, so that a slow or failing receiver never holds up a request.
+//!
+//! Requests carry the event name in `X-Zai-Event`, the Unix time they were sent at in
+//! `X-Zai-Timestamp` and an HMAC-SHA256 of `<timestamp>.<body>`, keyed with the secret of the
+//! webhook, in `X-Zai-Signature` as `sha256=<hex digest>`. As the timestamp is signed, receivers
+//! can reject old deliveries replayed to them. Any response other than a 2xx counts as a failure
+//! and is retried later with a growing delay.
+
+use crate::{database::DatabaseError, jobs::Scheduler, mailer, metadata, routes::url};
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use sqlx::{
+    query, query_as, query_scalar,
+    types::chrono::{NaiveDateTime, Utc},
+    PgPool,
+};
+use std::time::Duration;
+
+const DELIVERY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Deliveries sent per delivery run.
+const BATCH_SIZE: i64 = 20;
+
+/// Failed attempts at a delivery before it is given up on.
+pub const MAX_ATTEMPTS: i32 = 6;
+
+/// Deliveries shown in the delivery log.
+const LOG_SIZE: i64 = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+    ItemCreated,
+    ItemEdited,
+    ItemDeleted,
+    ReviewAdded,
+    ReviewRemoved,
+}
+
+impl Event {
+    pub const ALL: [Event; 5] = [
+        Event::ItemCreated,
+        Event::ItemEdited,
+        Event::ItemDeleted,
+        Event::ReviewAdded,
+        Event::ReviewRemoved,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Event::ItemCreated => "item.created",
+            Event::ItemEdited => "item.edited",
+            Event::ItemDeleted => "item.deleted",
+            Event::ReviewAdded => "review.added",
+            Event::ReviewRemoved => "review.removed",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|event| event.name() == name)
+    }
+}
+
+pub struct Webhook {
+    pub id: i32,
+    pub url: String,
+    pub secret: String,
+    pub events: Vec<String>,
+    pub created: NaiveDateTime,
+}
+
+/// Entry of the delivery log.
+pub struct Delivery {
+    pub id: i32,
+    pub url: String,
+    pub event: String,
+    pub attempts: i32,
+    pub status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub run_at: NaiveDateTime,
+    pub delivered_at: Option<NaiveDateTime>,
+    pub created: NaiveDateTime,
+}
+
+impl Delivery {
+    /// Whether the delivery was given up on after failing too many times.
+    pub fn is_failed(&self) -> bool {
+        self.delivered_at.is_none() && self.attempts >= MAX_ATTEMPTS
+    }
+}
+
+struct DeliveryJob {
+    id: i32,
+    url: String,
+    secret: String,
+    event: String,
+    payload: String,
+}
+
+/// `sha256=` followed by the hex HMAC-SHA256 of a message keyed with a webhook secret.
+fn hmac_sha256(secret: &str, message: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(message.as_bytes());
+    format!("sha256={:x}", mac.finalize().into_bytes())
+}
+
+/// Signature of a body sent at a Unix timestamp, covering both so that it cannot be replayed
+/// later under a fresh timestamp.
+pub fn signature(secret: &str, timestamp: i64, body: &str) -> String {
+    hmac_sha256(secret, &format!("{timestamp}.{body}"))
+}
+
+/// Data of item events, the title left out when it is not known or did not change.
+pub fn item(locator: &str, title: Option<&str>) -> Value {
+    json!({
+        "locator": locator,
+        "title": title,
+        "url": mailer::link(&url::item(locator)),
+    })
+}
+
+/// Data of an item edit, which may have changed its locator.
+pub fn item_edit(locator: &str, previous_locator: &str, title: Option<&str>) -> Value {
+    let mut data = item(locator, title);
+    data["previous_locator"] = previous_locator.into();
+    data
+}
+
+/// Data of an item deleted by merging it into another one.
+pub fn item_merge(locator: &str, into: &str) -> Value {
+    json!({
+        "locator": locator,
+        "title": null,
+        "merged_into": into,
+        "url": mailer::link(&url::item(into)),
+    })
+}
+
+/// Data of review events, the score left out for removed reviews. Private ratings are reported
+/// without their author and score, which only the author may see.
+pub fn review(locator: &str, username: Option<&str>, score: Option<i16>) -> Value {
+    json!({
+        "item": locator,
+        "user": username,
+        "score": score,
+        "url": mailer::link(&url::item(locator)),
+    })
+}
+
+/// Queues an event for every webhook subscribed to it.
+pub async fn dispatch(pool: &PgPool, event: Event, data: Value) -> Result<(), DatabaseError> {
+    let payload = json!({
+        "event": event.name(),
+        "created": Utc::now().to_rfc3339(),
+        "data": data,
+    })
+    .to_string();
+    query!("INSERT INTO webhook_deliveries(webhook_id, event, payload) SELECT id, $1::VARCHAR, $2 FROM webhooks WHERE $1::VARCHAR = ANY(events)", event.name(), payload)
+        .execute(pool)
+        .await
+        .map(|_| ())
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+pub async fn get_webhooks(pool: &PgPool) -> Result<Vec<Webhook>, DatabaseError> {
+    query_as!(
+        Webhook,
+        "SELECT id, url, secret, events, created FROM webhooks ORDER BY id"
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+pub async fn add_webhook(pool: &PgPool, url: &str, events: &[String]) -> Result<(), DatabaseError> {
+    query!(
+        "INSERT INTO webhooks(url, events) VALUES ($1, $2)",
+        url,
+        events
+    )
+    .execute(pool)
+    .await
+    .map(|_| ())
+    .map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+pub async fn remove_webhook(pool: &PgPool, id: i32) -> Result<(), DatabaseError> {
+    query!("DELETE FROM webhooks WHERE id = $1", id)
+        .execute(pool)
+        .await
+        .map(|_| ())
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Most recent deliveries to all webhooks, newest first.
+pub async fn get_deliveries(pool: &PgPool) -> Result<Vec<Delivery>, DatabaseError> {
+    query_as!(Delivery, "SELECT d.id, w.url, d.event, d.attempts, d.status_code, d.last_error, d.run_at, d.delivered_at, d.created FROM webhook_deliveries d JOIN webhooks w ON w.id = d.webhook_id ORDER BY d.id DESC LIMIT $1", LOG_SIZE)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Queues a failed delivery again with a fresh set of attempts, returning whether it was failed.
+pub async fn redeliver(pool: &PgPool, id: i32) -> Result<bool, DatabaseError> {
+    query_scalar!("UPDATE webhook_deliveries SET attempts = 0, run_at = now() WHERE id = $1 AND delivered_at IS NULL AND attempts >= $2 RETURNING id", id, MAX_ATTEMPTS)
+        .fetch_optional(pool)
+        .await
+        .map(|id| id.is_some())
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// POSTs a delivery, returning the response status when it was received at all.
+async fn send(job: &DeliveryJob) -> (Option<u16>, Result<(), String>) {
+    let timestamp = Utc::now().timestamp();
+    let response = metadata::client()
+        .post(&job.url)
+        .header("Content-Type", "application/json")
+        .header("X-Zai-Event", &job.event)
+        .header("X-Zai-Delivery", job.id.to_string())
+        .header("X-Zai-Timestamp", timestamp.to_string())
+        .header(
+            "X-Zai-Signature",
+            signature(&job.secret, timestamp, &job.payload),
+        )
+        .body(job.payload.clone())
+        .send()
+        .await;
+    match response {
+        Ok(response) if response.status().is_success() => {
+            (Some(response.status().as_u16()), Ok(()))
+        }
+        Ok(response) => (
+            Some(response.status().as_u16()),
+            Err(format!("Responded with {}", response.status())),
+        ),
+        Err(e) => (None, Err(e.to_string())),
+    }
+}
+
+/// Sends a batch of due deliveries, retrying failed ones later with a growing delay.
+pub async fn deliver(pool: &PgPool) -> Result<(), DatabaseError> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    let jobs = query_as!(DeliveryJob, "SELECT d.id, w.url, w.secret, d.event, d.payload FROM webhook_deliveries d JOIN webhooks w ON w.id = d.webhook_id WHERE d.delivered_at IS NULL AND d.attempts < $1 AND d.run_at <= now() ORDER BY d.run_at LIMIT $2 FOR UPDATE OF d SKIP LOCKED", MAX_ATTEMPTS, BATCH_SIZE)
+        .fetch_all(&mut *transaction)
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    for job in jobs {
+        let (status_code, result) = send(&job).await;
+        let status_code = status_code.map(i32::from);
+        match result {
+            Ok(()) => query!("UPDATE webhook_deliveries SET attempts = attempts + 1, status_code = $2, last_error = NULL, delivered_at = now() WHERE id = $1", job.id, status_code)
+                .execute(&mut *transaction)
+                .await,
+            Err(error) => query!("UPDATE webhook_deliveries SET attempts = attempts + 1, status_code = $2, last_error = $3, run_at = now() + (attempts + 1) * INTERVAL '1 minute' WHERE id = $1", job.id, status_code, error)
+                .execute(&mut *transaction)
+                .await,
+        }
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))?;
+    }
+    transaction
+        .commit()
+        .await
+        .map_err(|e| DatabaseError::InternalError(Box::new(e)))
+}
+
+/// Periodically sends queued deliveries in the background.
+pub fn schedule(scheduler: &mut Scheduler) {
+    scheduler.add(
+        "webhooks",
+        "Send queued webhook deliveries",
+        DELIVERY_INTERVAL,
+        |pool| async move { deliver(&pool).await },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signs_bodies_with_hmac_sha256() {
+        // Test case 2 of RFC 4231.
+        assert_eq!(
+            hmac_sha256("Jefe", "what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn signs_the_timestamp_along_with_the_body() {
+        assert_eq!(
+            signature("secret", 1718000000, "{}"),
+            hmac_sha256("secret", "1718000000.{}")
+        );
+        assert_ne!(
+            signature("secret", 1718000000, "{}"),
+            signature("secret", 1718000001, "{}")
+        );
+    }
+
+    #[test]
+    fn parses_event_names() {
+        for event in Event::ALL {
+            assert_eq!(Event::parse(event.name()), Some(event));
+        }
+        assert_eq!(Event::parse("item.renamed"), None);
+    }
+}